@@ -1,4 +1,6 @@
 use crate::{allow_std_io::AllowStdIo,
+            cache::{CachedResponse,
+                    ResponseCache},
             error::{Error,
                     Result},
             hab_http::ApiClient,
@@ -34,7 +36,10 @@ use habitat_core::{crypto::keys::box_key_pair::WrappedSealedBox,
 use percent_encoding::{percent_encode,
                        AsciiSet,
                        CONTROLS};
-use reqwest::{header::CONTENT_LENGTH,
+use reqwest::{header::{HeaderValue,
+                       CONTENT_LENGTH,
+                       IF_MODIFIED_SINCE,
+                       IF_NONE_MATCH},
               Body,
               IntoUrl,
               RequestBuilder,
@@ -101,6 +106,11 @@ mod json {
                                   release: Some(ident.release), }
         }
     }
+
+    #[derive(Deserialize)]
+    pub struct ChannelPackages {
+        pub data: Vec<PackageIdent>,
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -116,7 +126,8 @@ pub struct OriginChannelIdent {
     pub name: String,
 }
 
-pub struct BuilderAPIClient(ApiClient);
+/// A client for the Builder API. Metadata responses are cached by the `ResponseCache`.
+pub struct BuilderAPIClient(ApiClient, ResponseCache);
 
 impl BuilderAPIClient {
     pub fn new<U>(endpoint: U,
@@ -127,12 +138,14 @@ impl BuilderAPIClient {
         where U: IntoUrl
     {
         let mut endpoint = endpoint.into_url().map_err(Error::ReqwestError)?;
-        if !endpoint.cannot_be_a_base() && endpoint.path() == "/" {
+        // An `srv://` URL without a path has an empty one rather than `/`.
+        if !endpoint.cannot_be_a_base() && matches!(endpoint.path(), "" | "/") {
             endpoint.set_path(DEFAULT_API_PATH);
         }
         let client = BuilderAPIClient(
             ApiClient::new(endpoint, product, version, fs_root_path)
                 .map_err(Error::HabitatHttpClient)?,
+            ResponseCache::new(fs_root_path),
         );
         Ok(client)
     }
@@ -154,6 +167,50 @@ impl BuilderAPIClient {
         }
     }
 
+    /// Sends a GET request for metadata, made conditional on any response to it that is cached.
+    /// If Builder reports that the cached response is still current its body is used, otherwise
+    /// the new response is cached. Returns the status and body of a response with one of the
+    /// `ok` statuses.
+    async fn get_cached(&self,
+                        rb: RequestBuilder,
+                        token: Option<&str>,
+                        ok: &[StatusCode])
+                        -> Result<(StatusCode, String)> {
+        let mut request = self.maybe_add_authz(rb, token).build()?;
+        let url = request.url().clone();
+        let cached = self.1.get(&url, token);
+        if let Some(ref cached) = cached {
+            let headers = request.headers_mut();
+            if let Some(etag) = cached.etag.as_deref() {
+                if let Ok(value) = HeaderValue::from_str(etag) {
+                    headers.insert(IF_NONE_MATCH, value);
+                }
+            }
+            if let Some(last_modified) = cached.last_modified.as_deref() {
+                if let Ok(value) = HeaderValue::from_str(last_modified) {
+                    headers.insert(IF_MODIFIED_SINCE, value);
+                }
+            }
+        }
+
+        let resp = self.0.execute(request).await?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                debug!("Using cached response for {}", url);
+                return Ok((cached.status(), cached.body));
+            }
+        }
+
+        let resp = response::ok_if(resp, ok).await?;
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body = resp.text().await.map_err(Error::BadResponseBody)?;
+        if let Some(cached) = CachedResponse::new(status, &headers, body.clone()) {
+            self.1.put(&url, token, &cached);
+        }
+        Ok((status, body))
+    }
+
     async fn download<'a>(&'a self,
                           rb: RequestBuilder,
                           dst_path: &'a Path,
@@ -224,18 +281,12 @@ impl BuilderAPIClient {
                       .get_with_custom_url(&package_search(search_term), |url| {
                           url.set_query(Some(&format!("range={:?}&distinct=true", range)));
                       });
-        let resp = self.maybe_add_authz(req, token).send().await?;
-        let status = resp.status();
-        debug!("Response Status: {:?}", status);
+        let (status, encoded) =
+            self.get_cached(req, token, &[StatusCode::OK, StatusCode::PARTIAL_CONTENT])
+                .await?;
+        trace!(target: "habitat_http_client::api_client::search_package", "{:?}", encoded);
 
-        if status == StatusCode::OK || status == StatusCode::PARTIAL_CONTENT {
-            let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
-            trace!(target: "habitat_http_client::api_client::search_package", "{:?}", encoded);
-
-            Ok((serde_json::from_str(&encoded)?, status == StatusCode::PARTIAL_CONTENT))
-        } else {
-            Err(response::err_from_response(resp).await)
-        }
+        Ok((serde_json::from_str(&encoded)?, status == StatusCode::PARTIAL_CONTENT))
     }
 
     async fn search_package_impl<'a, F>(&'a self,
@@ -448,6 +499,23 @@ impl BuilderAPIClient {
             .await
     }
 
+    /// Fetch the signed key revocation list an origin publishes. The list is returned as is, for
+    /// the caller to verify.
+    ///
+    /// # Failures
+    ///
+    /// * The origin has no revocation list
+    /// * Remote Builder is not available
+    pub async fn fetch_origin_revocation_list(&self, origin: &str) -> Result<String> {
+        debug!("Fetching the key revocation list of {}", origin);
+
+        let path = format!("depot/origins/{}/revocation_list", origin);
+        let resp = self.0.get(&path).send().await?;
+        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
+
+        resp.text().await.map_err(Error::BadResponseBody)
+    }
+
     /// Create an origin
     ///
     ///  # Failures
@@ -857,10 +925,10 @@ impl BuilderAPIClient {
     pub async fn show_origin_keys(&self, origin: &str) -> Result<Vec<OriginKeyIdent>> {
         debug!("Showing origin keys: {}", origin);
 
-        let resp = self.0.get(&origin_keys_path(origin)).send().await?;
-        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
-
-        let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
+        let (_, encoded) = self.get_cached(self.0.get(&origin_keys_path(origin)),
+                                           None,
+                                           &[StatusCode::OK])
+                               .await?;
         trace!(target: "habitat_http_client::api_client::show_origin_keys", "{:?}", encoded);
 
         Ok(serde_json::from_str::<Vec<OriginKeyIdent>>(&encoded)?)
@@ -889,12 +957,10 @@ impl BuilderAPIClient {
                .append_pair("target", &target.to_string());
         };
 
-        let resp = self.maybe_add_authz(self.0.get_with_custom_url(&path, custom), token)
-                       .send()
-                       .await?;
-        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
-
-        let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
+        let (_, encoded) = self.get_cached(self.0.get_with_custom_url(&path, custom),
+                                           token,
+                                           &[StatusCode::OK])
+                               .await?;
         trace!(target: "habitat_http_client::api_client::package_channels", "{:?}", encoded);
 
         Ok(serde_json::from_str::<Vec<String>>(&encoded)?.into_iter()
@@ -1076,15 +1142,10 @@ impl BuilderAPIClient {
             url.push_str("/latest");
         }
 
-        let resp = self.maybe_add_authz(self.0.get_with_custom_url(&url, |u| {
-                                                  u.set_query(Some(&format!("target={}", target)))
-                                              }),
-                                        token)
-                       .send()
-                       .await?;
-        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
-
-        let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
+        let req =
+            self.0
+                .get_with_custom_url(&url, |u| u.set_query(Some(&format!("target={}", target))));
+        let (_, encoded) = self.get_cached(req, token, &[StatusCode::OK]).await?;
         trace!(target: "habitat_http_client::api_client::show_package_metadata", "{:?}", encoded);
 
         let package: Package = serde_json::from_str::<Package>(&encoded)?;
@@ -1357,25 +1418,48 @@ impl BuilderAPIClient {
         debug!("Listing channels for origin {}", origin);
 
         let path = format!("depot/channels/{}", origin);
-        let resp = if include_sandbox_channels {
+        let req = if include_sandbox_channels {
             self.0
                 .get_with_custom_url(&path, |url| url.set_query(Some("sandbox=true")))
-                .send()
-                .await?
         } else {
-            self.0.get(&path).send().await?
+            self.0.get(&path)
         };
-        debug!("Response Status: {:?}", resp.status());
+        let (_, encoded) =
+            self.get_cached(req, None, &[StatusCode::OK, StatusCode::PARTIAL_CONTENT])
+                .await?;
 
-        match resp.status() {
-            StatusCode::OK | StatusCode::PARTIAL_CONTENT => {
-                let encoded = resp.text().await.map_err(Error::BadResponseBody)?;
-                let results: Vec<OriginChannelIdent> = serde_json::from_str(&encoded)?;
-                let channels = results.into_iter().map(|o| o.name).collect();
-                Ok(channels)
-            }
-            _ => Err(response::err_from_response(resp).await),
-        }
+        let results: Vec<OriginChannelIdent> = serde_json::from_str(&encoded)?;
+        let channels = results.into_iter().map(|o| o.name).collect();
+        Ok(channels)
+    }
+
+    /// Return the latest release of every package in an origin's channel for a target
+    ///
+    /// # Failures
+    ///
+    /// * Remote Builder is not available
+    /// * Channel does not exist
+    pub async fn latest_channel_packages(&self,
+                                         origin: &str,
+                                         channel: &ChannelIdent,
+                                         target: PackageTarget,
+                                         token: Option<&str>)
+                                         -> Result<Vec<PackageIdent>> {
+        debug!("Listing latest packages of channel {} for origin {}, target {}",
+               channel, origin, target);
+
+        let path = format!("depot/channels/{}/{}/pkgs/_latest", origin, channel);
+        let custom = |url: &mut Url| {
+            url.query_pairs_mut()
+               .append_pair("target", &target.to_string());
+        };
+
+        let (_, encoded) = self.get_cached(self.0.get_with_custom_url(&path, custom),
+                                           token,
+                                           &[StatusCode::OK])
+                               .await?;
+        let results: json::ChannelPackages = serde_json::from_str(&encoded)?;
+        Ok(results.data.into_iter().map(Into::into).collect())
     }
 
     /// Get an origin member's role
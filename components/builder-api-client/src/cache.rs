@@ -0,0 +1,167 @@
+//! An on-disk cache of Builder metadata responses.
+//!
+//! Responses carrying an `ETag` or `Last-Modified` header are stored along with those
+//! validators. Later requests for the same URL are made conditional on them, so that Builder can
+//! answer with a bodiless `304 Not Modified` and the stored body is used instead. Responses are
+//! always revalidated, so the cache never serves data Builder considers stale.
+
+use habitat_core::{crypto::hash,
+                   env,
+                   fs::{atomic_write,
+                        cache_root_path}};
+use reqwest::{header::{HeaderMap,
+                       HeaderName,
+                       ETAG,
+                       LAST_MODIFIED},
+              StatusCode,
+              Url};
+use std::{fs,
+          io,
+          path::{Path,
+                 PathBuf}};
+
+/// Setting this environment variable disables the cache, so that every request fetches a full
+/// response from Builder.
+pub const DISABLE_CACHE_ENVVAR: &str = "HAB_BLDR_API_CACHE_DISABLED";
+
+const CACHE_DIR: &str = "api";
+
+/// A response stored in the cache, with the validators needed to revalidate it.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct CachedResponse {
+    pub status:        u16,
+    pub etag:          Option<String>,
+    pub last_modified: Option<String>,
+    pub body:          String,
+}
+
+impl CachedResponse {
+    /// Returns a response to be cached, or `None` if Builder gave no way to revalidate it.
+    pub fn new(status: StatusCode, headers: &HeaderMap, body: String) -> Option<Self> {
+        let header = |name: HeaderName| {
+            headers.get(name)
+                   .and_then(|value| value.to_str().ok())
+                   .map(String::from)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(CachedResponse { status: status.as_u16(),
+                              etag,
+                              last_modified,
+                              body })
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK)
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseCache {
+    /// The directory responses are stored in, or `None` if caching is disabled.
+    path: Option<PathBuf>,
+}
+
+impl ResponseCache {
+    /// Creates a cache in the `api` directory of the Habitat cache, optionally taking a custom
+    /// filesystem root.
+    pub fn new(fs_root_path: Option<&Path>) -> Self {
+        if env::var(DISABLE_CACHE_ENVVAR).is_ok() {
+            debug!("Builder API response cache disabled by {}",
+                   DISABLE_CACHE_ENVVAR);
+            return ResponseCache { path: None };
+        }
+        Self::at(cache_root_path(fs_root_path).join(CACHE_DIR))
+    }
+
+    /// Creates a cache stored in the given directory.
+    pub fn at(path: PathBuf) -> Self { ResponseCache { path: Some(path) } }
+
+    /// Returns the stored response to a request for `url` made with `token`, if there is one.
+    pub fn get(&self, url: &Url, token: Option<&str>) -> Option<CachedResponse> {
+        let path = self.entry_path(url, token)?;
+        let bytes = fs::read(&path).ok()?;
+        match serde_json::from_slice(&bytes) {
+            Ok(response) => Some(response),
+            Err(e) => {
+                debug!("Ignoring unreadable cached response {}: {}",
+                       path.display(),
+                       e);
+                None
+            }
+        }
+    }
+
+    /// Stores the response to a request for `url` made with `token`. Failing to store a response
+    /// only costs a full response next time, so errors are logged rather than returned.
+    pub fn put(&self, url: &Url, token: Option<&str>, response: &CachedResponse) {
+        let path = match self.entry_path(url, token) {
+            Some(path) => path,
+            None => return,
+        };
+        let result = serde_json::to_vec(response).map_err(io::Error::from)
+                                                 .and_then(|bytes| {
+                                                     if let Some(dir) = path.parent() {
+                                                         fs::create_dir_all(dir)?;
+                                                     }
+                                                     atomic_write(&path, bytes)
+                                                 });
+        if let Err(e) = result {
+            debug!("Failed to cache response for {} at {}: {}",
+                   url,
+                   path.display(),
+                   e);
+        }
+    }
+
+    /// Responses are stored by a hash of the URL and token, so that a response to an authorized
+    /// request is only ever revalidated with the same token.
+    fn entry_path(&self, url: &Url, token: Option<&str>) -> Option<PathBuf> {
+        let key = format!("{}\n{}", url, token.unwrap_or_default());
+        self.path
+            .as_ref()
+            .map(|path| path.join(hash::hash_string(&key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use tempfile::TempDir;
+
+    fn url() -> Url {
+        "https://bldr.habitat.sh/v1/depot/origins/core/keys".parse()
+                                                            .unwrap()
+    }
+
+    #[test]
+    fn responses_without_validators_are_not_cached() {
+        let mut headers = HeaderMap::new();
+        assert!(CachedResponse::new(StatusCode::OK, &headers, String::from("[]")).is_none());
+
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        let response = CachedResponse::new(StatusCode::PARTIAL_CONTENT, &headers, String::new());
+        let response = response.expect("response with an ETag is cached");
+        assert_eq!(response.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    }
+
+    #[test]
+    fn responses_are_cached_per_url_and_token() {
+        let dir = TempDir::new().expect("Could not create tempdir");
+        let cache = ResponseCache::at(dir.path().join(CACHE_DIR));
+        let response = CachedResponse { status:        200,
+                                        etag:          Some(String::from("\"abc\"")),
+                                        last_modified: None,
+                                        body:          String::from("[]"), };
+        cache.put(&url(), Some("token"), &response);
+
+        assert_eq!(cache.get(&url(), Some("token")), Some(response));
+        assert!(cache.get(&url(), None).is_none());
+        assert!(cache.get(&url(), Some("other-token")).is_none());
+    }
+}
@@ -16,6 +16,7 @@ extern crate serde_json;
 
 mod allow_std_io;
 pub mod builder;
+pub mod cache;
 pub mod error;
 pub mod response;
 
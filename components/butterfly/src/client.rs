@@ -10,18 +10,33 @@ use crate::{error::{Error,
                     service_file::ServiceFile,
                     Rumor},
             ZMQ_CONTEXT};
-use habitat_core::{crypto::SymKey,
+use habitat_core::{crypto::{SigKeyPair,
+                            SymKey},
                    service::ServiceGroup};
+use std::sync::Arc;
 
-/// Holds a ZMQ Push socket, and an optional ring encryption key.
+/// Holds a ZMQ Push socket, an optional ring encryption key, and the member the traffic is signed
+/// as, if it is encrypted.
 pub struct Client {
     socket:   zmq::Socket,
     ring_key: Option<SymKey>,
+    sender:   Option<(String, Arc<SigKeyPair>)>,
 }
 
 impl Client {
-    /// Connect this client to the address, and optionally encrypt the traffic.
+    /// Connect this client to the address, and optionally encrypt the traffic. Servers on an
+    /// encrypted ring only take messages which are signed, so encrypted traffic should be sent
+    /// with a client made by `Server::client` instead.
     pub fn new(addr: &str, ring_key: Option<SymKey>) -> Result<Client> {
+        Self::new_signed(addr, ring_key, None)
+    }
+
+    /// Connect this client to the address, and optionally encrypt the traffic, signing it as
+    /// `sender` with its member key.
+    pub(crate) fn new_signed(addr: &str,
+                             ring_key: Option<SymKey>,
+                             sender: Option<(String, Arc<SigKeyPair>)>)
+                             -> Result<Client> {
         let socket = (**ZMQ_CONTEXT).as_mut()
                                     .socket(zmq::PUSH)
                                     .expect("Failure to create the ZMQ push socket");
@@ -37,7 +52,9 @@ impl Client {
               .expect("Failure to set the ZMQ send timeout");
         let to_addr = format!("tcp://{}", addr);
         socket.connect(&to_addr).map_err(Error::ZmqConnectError)?;
-        Ok(Client { socket, ring_key })
+        Ok(Client { socket,
+                    ring_key,
+                    sender })
     }
 
     /// Create a departure notification and send it to the server.
@@ -46,32 +63,38 @@ impl Client {
         self.send(&departure)
     }
 
-    /// Create a service configuration and send it to the server.
+    /// Create a service configuration and send it to the server, along with the encoded
+    /// `signed_request` which set it, if it was signed.
     pub fn send_service_config(&mut self,
                                service_group: ServiceGroup,
                                incarnation: u64,
                                config: &[u8],
-                               encrypted: bool)
+                               encrypted: bool,
+                               signed_request: Option<Vec<u8>>)
                                -> Result<()> {
         let mut sc = ServiceConfig::new("butterflyclient", service_group, config.to_vec());
         sc.incarnation = incarnation;
         sc.encrypted = encrypted;
+        sc.signed_request = signed_request;
         self.send(&sc)
     }
 
-    /// Create a service file and send it to the server.
+    /// Create a service file and send it to the server, along with the encoded `signed_request`
+    /// which uploaded it, if it was signed.
     pub fn send_service_file<S>(&mut self,
                                 service_group: ServiceGroup,
                                 filename: S,
                                 incarnation: u64,
                                 body: &[u8],
-                                encrypted: bool)
+                                encrypted: bool,
+                                signed_request: Option<Vec<u8>>)
                                 -> Result<()>
         where S: Into<String>
     {
         let mut sf = ServiceFile::new("butterflyclient", service_group, filename, body.to_vec());
         sf.incarnation = incarnation;
         sf.encrypted = encrypted;
+        sf.signed_request = signed_request;
         self.send(&sf)
     }
 
@@ -80,7 +103,10 @@ impl Client {
         where T: Rumor
     {
         let bytes = rumor.write_to_bytes()?;
-        let wire_msg = message::generate_wire(bytes, self.ring_key.as_ref())?;
+        let sender = self.sender
+                         .as_ref()
+                         .map(|(member_id, key)| (member_id.as_str(), &**key));
+        let wire_msg = message::generate_wire(bytes, self.ring_key.as_ref(), sender)?;
         self.socket.send(&wire_msg, 0).map_err(Error::ZmqSendError)
     }
 }
@@ -20,16 +20,22 @@ pub enum Error {
     IncarnationIO(PathBuf, io::Error),
     IncarnationParse(PathBuf, num::ParseIntError),
     InvalidRumorShareLimit,
+    MemberHistoryIO(PathBuf, io::Error),
+    MemberKeyIO(PathBuf, io::Error),
+    MemberHistoryParse(PathBuf, serde_json::Error),
     NonExistentRumor(String, String),
     OsError(io::Error),
     ProtocolMismatch(&'static str),
+    RingNotEncrypted,
     ServiceConfigDecode(String, toml::de::Error),
     ServiceConfigNotUtf8(String, str::Utf8Error),
+    ServiceKeyMismatch(String, String),
     SocketCloneError,
     SocketSetReadTimeout(io::Error),
     SocketSetWriteTimeout(io::Error),
     Timeout(String),
     UnknownMember(String),
+    UnsignedMessage,
     ZmqConnectError(zmq::Error),
     ZmqSendError(zmq::Error),
     UnknownIOError(io::Error),
@@ -71,6 +77,21 @@ impl fmt::Display for Error {
             Error::InvalidRumorShareLimit => {
                 "Rumor share limit should be a positive integer".to_string()
             }
+            Error::MemberHistoryIO(ref path, ref err) => {
+                format!("Error reading or writing member history file {}: {}",
+                        path.display(),
+                        err)
+            }
+            Error::MemberHistoryParse(ref path, ref err) => {
+                format!("Error parsing member history file {}: {}",
+                        path.display(),
+                        err)
+            }
+            Error::MemberKeyIO(ref path, ref err) => {
+                format!("Error reading or writing member signing key file {}: {}",
+                        path.display(),
+                        err)
+            }
             Error::NonExistentRumor(ref member_id, ref rumor_id) => {
                 format!("Non existent rumor asked to be written to bytes: {} {}",
                         member_id, rumor_id)
@@ -80,12 +101,20 @@ impl fmt::Display for Error {
                 format!("Received an unsupported or bad protocol message. Missing field: {}",
                         field)
             }
+            Error::RingNotEncrypted => {
+                "The ring is not encrypted, so it has no key to rotate".to_string()
+            }
             Error::ServiceConfigDecode(ref sg, ref err) => {
                 format!("Cannot decode service config: group={}, {:?}", sg, err)
             }
             Error::ServiceConfigNotUtf8(ref sg, ref err) => {
                 format!("Cannot read service configuration: group={}, {}", sg, err)
             }
+            Error::ServiceKeyMismatch(ref sg, ref key) => {
+                format!("Cannot decrypt what was gossiped for {}, it is encrypted for {}, not its \
+                         own service key",
+                        sg, key)
+            }
             Error::SocketCloneError => "Cannot clone the underlying UDP socket".to_string(),
             Error::SocketSetReadTimeout(ref err) => {
                 format!("Cannot set UDP socket read timeout: {}", err)
@@ -95,6 +124,9 @@ impl fmt::Display for Error {
             }
             Error::Timeout(ref msg) => format!("Timed out {}", msg),
             Error::UnknownMember(ref member_id) => format!("Unknown member ID: {}", member_id),
+            Error::UnsignedMessage => {
+                "Encrypted message is not signed by the member which sent it".to_string()
+            }
             Error::ZmqConnectError(ref err) => format!("Cannot connect ZMQ socket: {}", err),
             Error::ZmqSendError(ref err) => {
                 format!("Cannot send message through ZMQ socket: {}", err)
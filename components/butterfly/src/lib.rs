@@ -44,6 +44,7 @@ pub mod message;
 pub mod protocol;
 pub mod rumor;
 pub mod server;
+pub mod simulation;
 pub mod swim;
 
 pub use crate::server::Server;
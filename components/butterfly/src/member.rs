@@ -1,5 +1,9 @@
 //! Tracks membership. Contains both the `Member` struct and the `MemberList`.
 
+pub mod history;
+
+use self::history::{MemberHistory,
+                    Transition};
 pub use crate::protocol::swim::Health;
 use crate::{error::{Error,
                     Result},
@@ -13,7 +17,10 @@ use crate::{error::{Error,
 use habitat_common::sync::{Lock,
                            ReadGuard,
                            WriteGuard};
-use habitat_core::util::ToI64;
+use habitat_core::{clock,
+                   crypto::{keys::ring_key::RingKeys,
+                            SymKey},
+                   util::ToI64};
 use prometheus::IntGaugeVec;
 use rand::{seq::{IteratorRandom,
                  SliceRandom},
@@ -31,12 +38,14 @@ use std::{collections::{hash_map,
           net::SocketAddr,
           num::ParseIntError,
           ops::Add,
+          path::Path,
           result,
           str::FromStr,
-          sync::atomic::{AtomicUsize,
-                         Ordering},
-          time::{Duration,
-                 Instant}};
+          sync::{atomic::{AtomicUsize,
+                          Ordering},
+                 Mutex,
+                 MutexGuard},
+          time::Duration};
 use uuid::Uuid;
 
 /// How many nodes do we target when we need to run PingReq.
@@ -136,6 +145,53 @@ pub struct Member {
     pub gossip_port: u16,
     pub persistent:  bool,
     pub departed:    bool,
+    /// Set while the member's services are being stopped for maintenance. The member is still
+    /// alive, but should not be sent new work.
+    #[serde(default)]
+    pub draining:    bool,
+    /// The public key the member signs its encrypted wire messages with, once it is known. Other
+    /// members only take messages which claim to be from the member at their word when it signed
+    /// them with this key.
+    #[serde(skip)]
+    pub signing_key: Option<Vec<u8>>,
+    /// Vouches for `signing_key` being the member's own. It only matters the first time we hear of
+    /// the member: the key we first know a member by is kept until the member is forgotten, as
+    /// anyone who holds the ring key can make a proof for any member.
+    #[serde(skip)]
+    pub key_proof:   Option<SigningKeyProof>,
+}
+
+/// A member's ID and signing key, sealed with the ring key. Only a holder of the ring key can
+/// make one, so a signing key which comes with its proof was given to the ring by one, rather than
+/// by whoever sent on the rumor it came in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigningKeyProof {
+    pub nonce:  Vec<u8>,
+    pub sealed: Vec<u8>,
+}
+
+impl SigningKeyProof {
+    pub fn new(ring_key: &SymKey, member_id: &str, signing_key: &[u8]) -> Result<Self> {
+        let (nonce, sealed) = ring_key.encrypt(&Self::proven(member_id, signing_key))?;
+        Ok(SigningKeyProof { nonce, sealed })
+    }
+
+    /// Whether this proves that `signing_key` is the key of `member_id`, with any revision of the
+    /// ring key in `ring_keys`.
+    pub fn proves(&self, ring_keys: &RingKeys, member_id: &str, signing_key: &[u8]) -> bool {
+        ring_keys.decrypt(&self.nonce, &self.sealed)
+                 .map(|opened| opened == Self::proven(member_id, signing_key))
+                 .unwrap_or(false)
+    }
+
+    /// What is sealed: the length of the member's ID, the ID, and then the key.
+    fn proven(member_id: &str, signing_key: &[u8]) -> Vec<u8> {
+        let mut proven = Vec::with_capacity(8 + member_id.len() + signing_key.len());
+        proven.extend_from_slice(&(member_id.len() as u64).to_be_bytes());
+        proven.extend_from_slice(member_id.as_bytes());
+        proven.extend_from_slice(signing_key);
+        proven
+    }
 }
 
 impl Member {
@@ -169,7 +225,10 @@ impl Default for Member {
                  swim_port:   0,
                  gossip_port: 0,
                  persistent:  false,
-                 departed:    false, }
+                 departed:    false,
+                 draining:    false,
+                 signing_key: None,
+                 key_proof:   None, }
     }
 }
 
@@ -193,7 +252,10 @@ impl From<Member> for proto::Member {
                         swim_port:   Some(value.swim_port.into()),
                         gossip_port: Some(value.gossip_port.into()),
                         persistent:  Some(value.persistent),
-                        departed:    Some(value.departed), }
+                        departed:    Some(value.departed),
+                        draining:    Some(value.draining),
+                        signing_key: value.signing_key,
+                        key_proof:   value.key_proof.map(Into::into), }
     }
 }
 
@@ -206,7 +268,7 @@ pub struct Membership {
 impl fmt::Display for Membership {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f,
-               "Member i/{} m/{} ad/{} sp/{} gp/{} p/{} d/{} h/{:?}",
+               "Member i/{} m/{} ad/{} sp/{} gp/{} p/{} d/{} dr/{} h/{:?}",
                self.member.incarnation,
                self.member.id,
                self.member.address,
@@ -214,6 +276,7 @@ impl fmt::Display for Membership {
                self.member.gossip_port,
                self.member.persistent,
                self.member.departed,
+               self.member.draining,
                self.health)
     }
 }
@@ -304,7 +367,26 @@ impl FromProto<proto::Member> for Member {
                                       .and_then(as_port)
                                       .ok_or(Error::ProtocolMismatch("gossip-port"))?,
                     persistent:  proto.persistent.unwrap_or(false),
-                    departed:    proto.departed.unwrap_or(false), })
+                    departed:    proto.departed.unwrap_or(false),
+                    draining:    proto.draining.unwrap_or(false),
+                    signing_key: proto.signing_key,
+                    key_proof:   proto.key_proof
+                                      .map(SigningKeyProof::from_proto)
+                                      .transpose()?, })
+    }
+}
+
+impl From<SigningKeyProof> for proto::SigningKeyProof {
+    fn from(value: SigningKeyProof) -> Self {
+        proto::SigningKeyProof { nonce:  Some(value.nonce),
+                                 sealed: Some(value.sealed), }
+    }
+}
+
+impl FromProto<proto::SigningKeyProof> for SigningKeyProof {
+    fn from_proto(proto: proto::SigningKeyProof) -> Result<Self> {
+        Ok(SigningKeyProof { nonce:  proto.nonce.ok_or(Error::ProtocolMismatch("nonce"))?,
+                             sealed: proto.sealed.ok_or(Error::ProtocolMismatch("sealed"))?, })
     }
 }
 
@@ -344,6 +426,12 @@ pub struct MemberList {
     entries:         Lock<HashMap<UuidSimple, member_list::Entry>>,
     initial_members: Lock<Vec<Member>>,
     update_counter:  AtomicUsize,
+    // This is a leaf lock; it is only ever taken after `entries` has
+    // been released and no other lock is acquired while it is held.
+    history:         Mutex<MemberHistory>,
+    // The incarnation of each departed member we have forgotten. This
+    // is a leaf lock; no other lock is acquired while it is held.
+    forgotten:       Mutex<HashMap<UuidSimple, Incarnation>>,
 }
 
 impl Serialize for MemberList {
@@ -380,9 +468,49 @@ impl MemberList {
     pub fn new() -> MemberList {
         MemberList { entries:         Lock::new(HashMap::new()),
                      initial_members: Lock::new(Vec::new()),
-                     update_counter:  AtomicUsize::new(0), }
+                     update_counter:  AtomicUsize::new(0),
+                     history:         Mutex::new(MemberHistory::default()),
+                     forgotten:       Mutex::new(HashMap::new()), }
+    }
+
+    fn history(&self) -> MutexGuard<'_, MemberHistory> {
+        self.history.lock().expect("MemberHistory lock poisoned")
+    }
+
+    fn forgotten(&self) -> MutexGuard<'_, HashMap<UuidSimple, Incarnation>> {
+        self.forgotten
+            .lock()
+            .expect("Forgotten members lock poisoned")
+    }
+
+    fn record_transitions(&self, transitions: Vec<(String, Option<Health>, Health)>) {
+        if transitions.is_empty() {
+            return;
+        }
+        let mut history = self.history();
+        for (member_id, from, to) in transitions {
+            history.record(&member_id, from, to);
+        }
+    }
+
+    /// A snapshot of the retained member health transitions, oldest
+    /// first.
+    pub fn history_transitions(&self) -> Vec<Transition> {
+        self.history().transitions().cloned().collect()
+    }
+
+    /// Replace the in-memory history with what was previously
+    /// persisted to `path`.
+    pub fn load_history(&self, path: &Path) -> Result<()> {
+        let capacity = self.history().capacity();
+        let loaded = MemberHistory::load(path, capacity)?;
+        *self.history() = loaded;
+        Ok(())
     }
 
+    /// Write the retained member health transitions to `path`.
+    pub fn persist_history(&self, path: &Path) -> Result<()> { self.history().persist(path) }
+
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (read)
     fn read_entries(&self) -> ReadGuard<'_, HashMap<UuidSimple, member_list::Entry>> {
@@ -493,6 +621,11 @@ impl MemberList {
     /// | Confirmed |       |           |           | propagate |
     /// | Departed  |       |           |           |           |
     ///
+    /// A member we have forgotten (see `forget_departed_mlw`) is only
+    /// accepted again at a higher incarnation than it was forgotten
+    /// at, which is how it rejoins; otherwise members that have yet to
+    /// forget it would keep gossiping it back to us.
+    ///
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (write)
     // TODO (CM): why don't we just insert a membership record here?
@@ -503,32 +636,61 @@ impl MemberList {
 
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (write)
-    fn insert_membership_mlw(&self, incoming: Membership) -> bool {
+    fn insert_membership_mlw(&self, mut incoming: Membership) -> bool {
         // Is this clone necessary, or can a key be a reference to a field contained in the value?
         // Maybe the members we store should not contain the ID to reduce the duplication?
-        let modified = match self.write_entries().entry(incoming.member.id.clone()) {
+        let member_id = incoming.member.id.clone();
+        let to = incoming.health;
+        let (modified, from) = match self.write_entries().entry(member_id.clone()) {
             hash_map::Entry::Occupied(mut entry) => {
                 let val = entry.get_mut();
-                if incoming.newer_or_less_healthy_than(val.member.incarnation, val.health) {
+                let rekeyed = match (&val.member.signing_key, &incoming.member.signing_key) {
+                    (Some(known), Some(claimed)) => known != claimed,
+                    _ => false,
+                };
+                if rekeyed {
+                    // Members keep their key for as long as they keep their ID; a rumor which
+                    // says otherwise, at whatever incarnation, comes from someone else.
+                    warn!("Ignoring a rumor of member {} with a signing key other than its own",
+                          member_id);
+                    (false, None)
+                } else if incoming.newer_or_less_healthy_than(val.member.incarnation, val.health) {
+                    // Members which don't know of signing keys leave them off the rumors they
+                    // pass on.
+                    if incoming.member.signing_key.is_none() {
+                        incoming.member.signing_key = val.member.signing_key.take();
+                        incoming.member.key_proof = val.member.key_proof.take();
+                    }
+                    let from = val.health;
                     *val = member_list::Entry { member:            incoming.member,
                                                 health:            incoming.health,
-                                                health_updated_at: Instant::now(), };
-                    true
+                                                health_updated_at: clock::instant(), };
+                    (true, Some(from))
                 } else {
-                    false
+                    (false, None)
                 }
             }
             hash_map::Entry::Vacant(entry) => {
-                entry.insert(member_list::Entry { member:            incoming.member,
-                                                  health:            incoming.health,
-                                                  health_updated_at: Instant::now(), });
-                true
+                let mut forgotten = self.forgotten();
+                match forgotten.get(entry.key()) {
+                    Some(incarnation) if incoming.member.incarnation <= *incarnation => {
+                        (false, None)
+                    }
+                    _ => {
+                        forgotten.remove(entry.key());
+                        entry.insert(member_list::Entry { member:            incoming.member,
+                                                          health:            incoming.health,
+                                                          health_updated_at: clock::instant(), });
+                        (true, None)
+                    }
+                }
             }
         };
 
         if modified {
             self.increment_update_counter();
             self.calculate_peer_health_metrics_mlr();
+            self.record_transitions(vec![(member_id, from, to)]);
         }
 
         modified
@@ -537,27 +699,30 @@ impl MemberList {
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (write)
     pub fn set_departed_mlw(&self, member_id: &str) {
-        if let Some(member_list::Entry { member, health, .. }) =
+        let from = if let Some(member_list::Entry { member,
+                                                    health,
+                                                    health_updated_at, }) =
             self.write_entries().get_mut(member_id)
         {
             debug!("Setting health of {:?}, {} -> {}",
                    member,
                    health,
                    Health::Departed);
+            let from = *health;
             *health = Health::Departed;
+            *health_updated_at = clock::instant();
+            from
         } else {
             trace!("set_departed called on unknown member {}", member_id);
-        }
+            return;
+        };
+        self.record_transitions(vec![(member_id.to_string(), Some(from), Health::Departed)]);
     }
 
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (read)
     fn calculate_peer_health_metrics_mlr(&self) {
-        let mut health_counts: HashMap<Health, i64> = HashMap::new();
-
-        for entry in self.read_entries().values() {
-            *health_counts.entry(entry.health).or_insert(0) += 1;
-        }
+        let health_counts = self.health_counts_mlr();
 
         for health in [Health::Alive,
                        Health::Suspect,
@@ -565,10 +730,25 @@ impl MemberList {
                        Health::Departed].iter()
         {
             PEER_HEALTH_COUNT.with_label_values(&[&health.to_string()])
-                             .set(*health_counts.get(health).unwrap_or(&0));
+                             .set(health_counts.get(health).unwrap_or(&0).to_i64());
         }
     }
 
+    /// Returns the number of members in each health state. States with
+    /// no members are absent.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn health_counts_mlr(&self) -> HashMap<Health, usize> {
+        let mut health_counts = HashMap::new();
+
+        for entry in self.read_entries().values() {
+            *health_counts.entry(entry.health).or_insert(0) += 1;
+        }
+
+        health_counts
+    }
+
     /// Returns the health of the member, if the member exists.
     ///
     /// # Locking (see locking.md)
@@ -709,6 +889,16 @@ impl MemberList {
             .map(|member_list::Entry { member, .. }| member.clone())
     }
 
+    /// The key the member signs its encrypted wire messages with, if it has told us.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn signing_key_mlr(&self, member_id: &str) -> Option<Vec<u8>> {
+        self.read_entries()
+            .get(member_id)
+            .and_then(|member_list::Entry { member, .. }| member.signing_key.clone())
+    }
+
     /// Iterates over the memberships list, calling the function for each membership.
     /// This could be return Result<T> instead, but there's only the one caller now.
     ///
@@ -769,7 +959,7 @@ impl MemberList {
     /// * `MemberList::entries` (write)
     // TODO (CM): Better return type than Vec<String>
     fn members_expired_to_mlw(&self, expiring_to: Health, timeout: Duration) -> Vec<String> {
-        let now = Instant::now();
+        let now = clock::instant();
         let precursor_health = match expiring_to {
             Health::Confirmed => Health::Suspect,
             Health::Departed => Health::Confirmed,
@@ -795,11 +985,50 @@ impl MemberList {
 
         if !expired.is_empty() {
             self.increment_update_counter();
+            self.record_transitions(expired.iter()
+                                           .map(|id| {
+                                               (id.clone(), Some(precursor_health), expiring_to)
+                                           })
+                                           .collect());
         }
 
         expired
     }
 
+    /// Remove the members which have been `Departed` for at least
+    /// `retention`, returning their IDs. They are remembered as
+    /// forgotten, so that gossip about them is ignored unless they
+    /// rejoin.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (write)
+    pub fn forget_departed_mlw(&self, retention: Duration) -> Vec<String> {
+        let now = clock::instant();
+        let mut forgotten = Vec::new();
+        self.write_entries().retain(|id, entry| {
+                                if entry.health == Health::Departed
+                                   && now >= entry.health_updated_at + retention
+                                {
+                                    forgotten.push((id.clone(), entry.member.incarnation));
+                                    false
+                                } else {
+                                    true
+                                }
+                            });
+
+        if !forgotten.is_empty() {
+            self.increment_update_counter();
+            self.calculate_peer_health_metrics_mlr();
+            self.forgotten().extend(forgotten.iter().cloned());
+        }
+
+        forgotten.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Returns true if the member was forgotten after departing, and
+    /// has not rejoined since.
+    pub fn is_forgotten(&self, member_id: &str) -> bool { self.forgotten().contains_key(member_id) }
+
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (read)
     pub fn contains_member_mlr(&self, member_id: &str) -> bool {
@@ -844,11 +1073,12 @@ impl<'a> Serialize for MemberProxy<'a> {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("member", 6)?;
+        let mut strukt = serializer.serialize_struct("member", 7)?;
         strukt.serialize_field("address", &self.0.address)?;
         strukt.serialize_field("gossip_port", &self.0.gossip_port)?;
         strukt.serialize_field("incarnation", &self.0.incarnation)?;
         strukt.serialize_field("persistent", &self.0.persistent)?;
+        strukt.serialize_field("draining", &self.0.draining)?;
         strukt.serialize_field("swim_port", &self.0.swim_port)?;
         strukt.serialize_field("health", &self.1)?;
         strukt.end()
@@ -879,7 +1109,10 @@ mod tests {
 
     mod member {
         use crate::member::{Incarnation,
-                            Member};
+                            Member,
+                            SigningKeyProof};
+        use habitat_core::crypto::{keys::ring_key::RingKeys,
+                                   SymKey};
 
         // Sets the uuid to simple, and the incarnation to the default.
         #[test]
@@ -888,6 +1121,19 @@ mod tests {
             assert_eq!(member.id.len(), 32);
             assert_eq!(member.incarnation, Incarnation::default());
         }
+
+        #[test]
+        fn signing_key_proofs_only_prove_the_key_of_the_member_they_were_made_for() {
+            let ring_key = SymKey::generate_pair_for_ring("beyonce");
+            let other_ring_key = SymKey::generate_pair_for_ring("beyonce");
+            let proof = SigningKeyProof::new(&ring_key, "member1", &[1; 32]).unwrap();
+
+            let ring_keys = RingKeys::new(ring_key);
+            assert!(proof.proves(&ring_keys, "member1", &[1; 32]));
+            assert!(!proof.proves(&ring_keys, "member1", &[2; 32]));
+            assert!(!proof.proves(&ring_keys, "member2", &[1; 32]));
+            assert!(!proof.proves(&RingKeys::new(other_ring_key), "member1", &[1; 32]));
+        }
     }
 
     mod membership {
@@ -1400,6 +1646,36 @@ mod tests {
             transition!(d_to_s, Health::Departed, Health::Suspect);
             transition!(d_to_c, Health::Departed, Health::Confirmed);
             transition!(d_to_d, Health::Departed, Health::Departed);
+
+            #[test]
+            fn signing_key_of_a_known_member_never_changes() {
+                let ml = MemberList::new();
+                let mut member = Member::default();
+                member.incarnation = Incarnation::from(1);
+                member.signing_key = Some(vec![1; 32]);
+                assert!(ml.insert_mlw(member.clone(), Health::Alive));
+
+                let mut stale = member.clone();
+                stale.signing_key = Some(vec![2; 32]);
+                assert!(!ml.insert_mlw(stale.clone(), Health::Suspect));
+                stale.incarnation = Incarnation::from(0);
+                assert!(!ml.insert_mlw(stale, Health::Alive));
+                assert_eq!(ml.signing_key_mlr(&member.id), Some(vec![1; 32]));
+
+                // Rumors passed on by members which don't know of signing keys keep the one we
+                // know the member by.
+                let mut keyless = member.clone();
+                keyless.incarnation = Incarnation::from(2);
+                keyless.signing_key = None;
+                assert!(ml.insert_mlw(keyless, Health::Alive));
+                assert_eq!(ml.signing_key_mlr(&member.id), Some(vec![1; 32]));
+
+                let mut rekeyed = member.clone();
+                rekeyed.incarnation = Incarnation::from(3);
+                rekeyed.signing_key = Some(vec![2; 32]);
+                assert!(!ml.insert_mlw(rekeyed, Health::Alive));
+                assert_eq!(ml.signing_key_mlr(&member.id), Some(vec![1; 32]));
+            }
         }
 
         /// Testing of
@@ -1408,13 +1684,17 @@ mod tests {
         /// - MemberList::members_expired_to_departed_mlw
         mod timed_expiration {
             use crate::member::{Health,
+                                Incarnation,
                                 Member,
                                 MemberList};
-            use std::{thread,
-                      time::Duration};
+            use habitat_core::clock::{self,
+                                      ManualClock};
+            use std::time::Duration;
 
             #[test]
             fn timing_out_from_suspect_to_confirmed() {
+                let clock = ManualClock::new();
+                let _clock = clock::set_for_thread(clock.clone());
                 let ml = MemberList::new();
                 let member_one = Member::default();
                 let small_seconds = 1;
@@ -1441,7 +1721,7 @@ mod tests {
                         "Nothing should have timed out to Confirmed with a large timeout");
 
                 // Allow the Suspect to age
-                thread::sleep(Duration::from_secs(small_seconds));
+                clock.advance(Duration::from_secs(small_seconds));
 
                 let newly_confirmed = ml.members_expired_to_confirmed_mlw(small_timeout);
                 assert!(newly_confirmed.contains(&member_one.id),
@@ -1454,6 +1734,8 @@ mod tests {
 
             #[test]
             fn timing_out_from_confirmed_to_departed() {
+                let clock = ManualClock::new();
+                let _clock = clock::set_for_thread(clock.clone());
                 let ml = MemberList::new();
                 let member_one = Member::default();
                 let small_seconds = 1;
@@ -1483,7 +1765,7 @@ mod tests {
                         "Nothing should have timed out to Departed with a large timeout");
 
                 // Allow the Confirmed to age
-                thread::sleep(Duration::from_secs(small_seconds));
+                clock.advance(Duration::from_secs(small_seconds));
 
                 let newly_departed = ml.members_expired_to_departed_mlw(small_timeout);
                 assert!(newly_departed.contains(&member_one.id),
@@ -1496,15 +1778,17 @@ mod tests {
 
             #[test]
             fn suspect_timeout_is_appropriately_selective() {
+                let clock = ManualClock::new();
+                let _clock = clock::set_for_thread(clock.clone());
                 let ml = MemberList::new();
                 let member_1 = Member::default();
                 let member_2 = Member::default();
                 let member_3 = Member::default();
 
                 assert!(ml.insert_mlw(member_1.clone(), Health::Suspect));
-                thread::sleep(Duration::from_secs(1));
+                clock.advance(Duration::from_secs(1));
                 assert!(ml.insert_mlw(member_2.clone(), Health::Suspect));
-                thread::sleep(Duration::from_secs(2)); // Give us a bit of padding
+                clock.advance(Duration::from_secs(2));
                 assert!(ml.insert_mlw(member_3.clone(), Health::Suspect));
 
                 let timeout = Duration::from_secs(2);
@@ -1531,15 +1815,17 @@ mod tests {
 
             #[test]
             fn confirmed_timeout_is_appropriately_selective() {
+                let clock = ManualClock::new();
+                let _clock = clock::set_for_thread(clock.clone());
                 let ml = MemberList::new();
                 let member_1 = Member::default();
                 let member_2 = Member::default();
                 let member_3 = Member::default();
 
                 assert!(ml.insert_mlw(member_1.clone(), Health::Confirmed));
-                thread::sleep(Duration::from_secs(1));
+                clock.advance(Duration::from_secs(1));
                 assert!(ml.insert_mlw(member_2.clone(), Health::Confirmed));
-                thread::sleep(Duration::from_secs(2)); // Give us a bit of padding
+                clock.advance(Duration::from_secs(2));
                 assert!(ml.insert_mlw(member_3.clone(), Health::Confirmed));
 
                 let timeout = Duration::from_secs(2);
@@ -1563,6 +1849,39 @@ mod tests {
                            "Member 3 should still have a health of Confirmed, because it hasn't \
                             timed out yet");
             }
+
+            #[test]
+            fn only_departed_members_are_forgotten_after_their_retention() {
+                let ml = MemberList::new();
+                let departed = Member::default();
+                let confirmed = Member::default();
+                assert!(ml.insert_mlw(departed.clone(), Health::Departed));
+                assert!(ml.insert_mlw(confirmed.clone(), Health::Confirmed));
+
+                assert!(ml.forget_departed_mlw(Duration::from_secs(60)).is_empty());
+                assert_eq!(ml.forget_departed_mlw(Duration::from_secs(0)),
+                           vec![departed.id.clone()]);
+                assert!(!ml.contains_member_mlr(&departed.id));
+                assert!(ml.contains_member_mlr(&confirmed.id));
+                assert!(ml.is_forgotten(&departed.id));
+                assert!(!ml.is_forgotten(&confirmed.id));
+            }
+
+            #[test]
+            fn forgotten_members_are_only_accepted_again_when_they_rejoin() {
+                let ml = MemberList::new();
+                let mut member = Member::default();
+                assert!(ml.insert_mlw(member.clone(), Health::Departed));
+                ml.forget_departed_mlw(Duration::from_secs(0));
+
+                assert!(!ml.insert_mlw(member.clone(), Health::Departed));
+                assert!(!ml.insert_mlw(member.clone(), Health::Alive));
+                assert!(!ml.contains_member_mlr(&member.id));
+
+                member.incarnation = Incarnation::from(1);
+                assert!(ml.insert_mlw(member.clone(), Health::Alive));
+                assert!(!ml.is_forgotten(&member.id));
+            }
         }
     }
 }
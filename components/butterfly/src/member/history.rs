@@ -0,0 +1,258 @@
+//! A bounded record of the health transitions that members of the
+//! ring have gone through.
+//!
+//! This allows operators to answer questions like "when did this
+//! Supervisor drop out of the ring?" and "how often has it flapped
+//! between Alive and Suspect?" long after the transitions themselves
+//! have been gossiped away.
+
+use super::Health;
+use crate::error::{Error,
+                   Result};
+use habitat_core::fs::atomic_write;
+use serde::{ser::SerializeSeq,
+            Serialize,
+            Serializer};
+use std::{collections::VecDeque,
+          fs::File,
+          io::BufReader,
+          path::{Path,
+                 PathBuf},
+          result,
+          time::{SystemTime,
+                 UNIX_EPOCH}};
+
+habitat_core::env_config_int!(/// The maximum number of member health transitions to retain.
+                              /// Once this limit is reached the oldest transitions are
+                              /// discarded first.
+                              #[derive(Debug, Clone, Copy)]
+                              MemberHistoryCapacity,
+                              usize,
+                              HAB_MEMBER_HISTORY_CAPACITY,
+                              1_000);
+
+/// A single change in the health of a member, as observed by this
+/// Supervisor.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Transition {
+    pub member_id: String,
+    /// The health we previously had recorded for the member. `None`
+    /// indicates this was the first time we learned of the member.
+    pub from:      Option<Health>,
+    pub to:        Health,
+    /// Seconds since the UNIX epoch at which the transition was
+    /// observed.
+    pub timestamp: u64,
+}
+
+impl Transition {
+    /// Whether this transition represents a member that was Alive
+    /// becoming less healthy.
+    pub fn is_flap(&self) -> bool { self.from == Some(Health::Alive) && self.to > Health::Alive }
+}
+
+/// An ordered, bounded list of `Transition`s, oldest first.
+#[derive(Debug)]
+pub struct MemberHistory {
+    transitions: VecDeque<Transition>,
+    capacity:    usize,
+}
+
+impl Default for MemberHistory {
+    fn default() -> Self { Self::new(MemberHistoryCapacity::configured_value().into()) }
+}
+
+impl MemberHistory {
+    pub fn new(capacity: usize) -> Self {
+        MemberHistory { transitions: VecDeque::with_capacity(capacity.min(1_024)),
+                        capacity }
+    }
+
+    /// Record that `member_id` moved from `from` to `to`. Transitions
+    /// that do not actually change the health are ignored.
+    ///
+    /// A `from` of `None` is compared against the last health we
+    /// recorded for the member, so re-learning about a member (e.g.
+    /// when rumors are reloaded from disk on restart) doesn't show up
+    /// as a spurious transition.
+    pub fn record(&mut self, member_id: &str, from: Option<Health>, to: Health) {
+        let from = from.or_else(|| self.for_member(member_id).last().map(|t| t.to));
+        if from == Some(to) {
+            return;
+        }
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+                                         .map(|d| d.as_secs())
+                                         .unwrap_or_default();
+        self.push(Transition { member_id: member_id.to_string(),
+                               from,
+                               to,
+                               timestamp });
+    }
+
+    fn push(&mut self, transition: Transition) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.transitions.len() >= self.capacity {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(transition);
+    }
+
+    pub fn capacity(&self) -> usize { self.capacity }
+
+    pub fn len(&self) -> usize { self.transitions.len() }
+
+    pub fn is_empty(&self) -> bool { self.transitions.is_empty() }
+
+    /// All retained transitions, oldest first.
+    pub fn transitions(&self) -> impl Iterator<Item = &Transition> { self.transitions.iter() }
+
+    /// All retained transitions for a single member, oldest first.
+    pub fn for_member<'a>(&'a self, member_id: &'a str) -> impl Iterator<Item = &'a Transition> {
+        self.transitions
+            .iter()
+            .filter(move |t| t.member_id == member_id)
+    }
+
+    /// The number of times `member_id` went from Alive to something
+    /// less healthy within the retained history.
+    pub fn flap_count(&self, member_id: &str) -> usize {
+        self.for_member(member_id).filter(|t| t.is_flap()).count()
+    }
+
+    /// The most recent transition of `member_id` to `Departed`, if any.
+    pub fn last_departure(&self, member_id: &str) -> Option<&Transition> {
+        self.for_member(member_id)
+            .filter(|t| t.to == Health::Departed)
+            .last()
+    }
+
+    /// Load previously persisted history from `path`, retaining at
+    /// most `capacity` of the newest transitions. A missing file is not
+    /// an error; it simply results in an empty history.
+    pub fn load<P>(path: P, capacity: usize) -> Result<Self>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut history = MemberHistory::new(capacity);
+        if !path.exists() {
+            return Ok(history);
+        }
+        let file = File::open(path).map_err(|e| Error::MemberHistoryIO(path.to_path_buf(), e))?;
+        let into_err = |e| Error::MemberHistoryParse(path.to_path_buf(), e);
+        let transitions: Vec<Transition> =
+            serde_json::from_reader(BufReader::new(file)).map_err(into_err)?;
+        for transition in transitions {
+            history.push(transition);
+        }
+        Ok(history)
+    }
+
+    /// Persist the history to `path`, replacing any previous contents.
+    pub fn persist<P>(&self, path: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let into_err = |e| Error::MemberHistoryParse(path.to_path_buf(), e);
+        let json = serde_json::to_string(self).map_err(into_err)?;
+        atomic_write(path, json).map_err(|e| Error::MemberHistoryIO(path.to_path_buf(), e))
+    }
+}
+
+impl Serialize for MemberHistory {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut seq = serializer.serialize_seq(Some(self.transitions.len()))?;
+        for transition in &self.transitions {
+            seq.serialize_element(transition)?;
+        }
+        seq.end()
+    }
+}
+
+/// Returns the location of the member history file within the given
+/// Butterfly data path.
+pub fn history_path<P>(data_path: P) -> PathBuf
+    where P: AsRef<Path>
+{
+    data_path.as_ref().join("MEMBER_HISTORY")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mktemp::Temp;
+
+    #[test]
+    fn identical_health_is_not_a_transition() {
+        let mut history = MemberHistory::new(10);
+        history.record("a", Some(Health::Alive), Health::Alive);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn relearning_a_member_resumes_from_its_last_recorded_health() {
+        let mut history = MemberHistory::new(10);
+        history.record("a", None, Health::Alive);
+        history.record("a", Some(Health::Alive), Health::Suspect);
+        history.record("a", None, Health::Suspect);
+        assert_eq!(history.len(), 2);
+        history.record("a", None, Health::Alive);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.transitions().last().unwrap().from,
+                   Some(Health::Suspect));
+    }
+
+    #[test]
+    fn oldest_transitions_are_evicted_first() {
+        let mut history = MemberHistory::new(2);
+        history.record("a", None, Health::Alive);
+        history.record("a", Some(Health::Alive), Health::Suspect);
+        history.record("a", Some(Health::Suspect), Health::Confirmed);
+        assert_eq!(history.len(), 2);
+        let first = history.transitions().next().unwrap();
+        assert_eq!(first.from, Some(Health::Alive));
+        assert_eq!(first.to, Health::Suspect);
+    }
+
+    #[test]
+    fn flaps_and_departures_are_tracked_per_member() {
+        let mut history = MemberHistory::new(10);
+        history.record("a", None, Health::Alive);
+        history.record("a", Some(Health::Alive), Health::Suspect);
+        history.record("a", Some(Health::Suspect), Health::Alive);
+        history.record("a", Some(Health::Alive), Health::Confirmed);
+        history.record("a", Some(Health::Confirmed), Health::Departed);
+        history.record("b", Some(Health::Alive), Health::Suspect);
+
+        assert_eq!(history.flap_count("a"), 2);
+        assert_eq!(history.flap_count("b"), 1);
+        assert_eq!(history.last_departure("a").unwrap().from,
+                   Some(Health::Confirmed));
+        assert!(history.last_departure("b").is_none());
+    }
+
+    #[test]
+    fn persist_and_load_roundtrip() {
+        let dir = Temp::new_dir().expect("Could not create temp dir");
+        let path = history_path(&dir);
+
+        let mut history = MemberHistory::new(10);
+        history.record("a", None, Health::Alive);
+        history.record("a", Some(Health::Alive), Health::Departed);
+        history.persist(&path).expect("Could not persist history");
+
+        let loaded = MemberHistory::load(&path, 1).expect("Could not load history");
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.transitions().next().unwrap().to, Health::Departed);
+    }
+
+    #[test]
+    fn loading_a_nonexistent_file_is_empty() {
+        let dir = Temp::new_dir().expect("Could not create temp dir");
+        let history = MemberHistory::load(history_path(&dir), 10).expect("Could not load");
+        assert!(history.is_empty());
+    }
+}
@@ -1,16 +1,45 @@
 use bytes::BytesMut;
-use habitat_core::crypto::SymKey;
+use habitat_core::crypto::{keys::ring_key::RingKeys,
+                           SigKeyPair,
+                           SymKey};
 use prost::Message;
 
 use crate::{error::{Error,
                     Result},
             protocol::Wire};
 
-pub fn generate_wire(payload: Vec<u8>, ring_key: Option<&SymKey>) -> Result<Vec<u8>> {
+/// The member an encrypted wire message says it was sent by, with its signature.
+#[derive(Debug)]
+pub struct Signed {
+    pub sender_id: String,
+    signature:     Vec<u8>,
+    signed:        Vec<u8>,
+}
+
+impl Signed {
+    /// Whether the message was signed with the secret half of `signing_key`, as gossiped in the
+    /// sender's `Member`.
+    pub fn verify(&self, signing_key: &[u8]) -> bool {
+        SigKeyPair::from_public_key_bytes(self.sender_id.clone(), String::new(), signing_key)
+            .and_then(|key| key.verify_payload(&self.signed, &self.signature))
+            .is_ok()
+    }
+}
+
+/// Encode `payload` for the wire, encrypting it with `ring_key` if there is one. Encrypted
+/// messages are also signed by the `sender`, if there is one, with the key it signs messages with.
+pub fn generate_wire(payload: Vec<u8>,
+                     ring_key: Option<&SymKey>,
+                     sender: Option<(&str, &SigKeyPair)>)
+                     -> Result<Vec<u8>> {
     let mut wire = Wire::default();
     if let Some(ring_key) = ring_key {
         wire.encrypted = Some(true);
         let (nonce, encrypted_payload) = ring_key.encrypt(&payload)?;
+        if let Some((sender_id, key)) = sender {
+            wire.sender_id = Some(sender_id.to_string());
+            wire.signature = Some(key.sign_payload(&signed_bytes(&nonce, &encrypted_payload))?);
+        }
         wire.nonce = Some(nonce);
         wire.payload = Some(encrypted_payload);
     } else {
@@ -21,14 +50,79 @@ pub fn generate_wire(payload: Vec<u8>, ring_key: Option<&SymKey>) -> Result<Vec<
     Ok(buf.to_vec())
 }
 
-pub fn unwrap_wire(payload: &[u8], ring_key: Option<&SymKey>) -> Result<Vec<u8>> {
+/// Decode a message from the wire, decrypting it with `ring_keys` if the ring is encrypted, and
+/// returning its sender's signature along with it. Encrypted messages which aren't signed are
+/// rejected; the signature is left to the caller to verify, with the key it knows the sender by.
+pub fn unwrap_wire(payload: &[u8],
+                   ring_keys: Option<&RingKeys>)
+                   -> Result<(Vec<u8>, Option<Signed>)> {
     let wire = Wire::decode(payload)?;
     let payload = wire.payload
                       .ok_or(Error::ProtocolMismatch("missing payload"))?;
-    if let Some(ring_key) = ring_key {
+    if let Some(ring_keys) = ring_keys {
         let nonce = wire.nonce.ok_or(Error::ProtocolMismatch("missing nonce"))?;
-        Ok(ring_key.decrypt(&nonce, &payload)?)
+        let decrypted = ring_keys.decrypt(&nonce, &payload)?;
+        let signed = match (wire.sender_id, wire.signature) {
+            (Some(sender_id), Some(signature)) => {
+                Signed { sender_id,
+                         signature,
+                         signed: signed_bytes(&nonce, &payload) }
+            }
+            _ => return Err(Error::UnsignedMessage),
+        };
+        Ok((decrypted, Some(signed)))
     } else {
-        Ok(payload)
+        Ok((payload, None))
+    }
+}
+
+/// What the sender signs: the nonce, followed by the payload encrypted with it.
+fn signed_bytes(nonce: &[u8], encrypted_payload: &[u8]) -> Vec<u8> {
+    let mut signed = Vec::with_capacity(nonce.len() + encrypted_payload.len());
+    signed.extend_from_slice(nonce);
+    signed.extend_from_slice(encrypted_payload);
+    signed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_messages_carry_a_verifiable_sender_signature() {
+        let ring_key = SymKey::generate_pair_for_ring("beyonce");
+        let member_key = SigKeyPair::generate_pair_for_origin("member1");
+        let other_key = SigKeyPair::generate_pair_for_origin("member2");
+        let wire = generate_wire(b"ping".to_vec(),
+                                 Some(&ring_key),
+                                 Some(("member1", &member_key))).unwrap();
+
+        let (payload, signed) = unwrap_wire(&wire, Some(&RingKeys::new(ring_key))).unwrap();
+        let signed = signed.expect("encrypted message is not signed");
+        assert_eq!(payload, b"ping".to_vec());
+        assert_eq!(signed.sender_id, "member1");
+        assert!(signed.verify(&member_key.public_key_bytes().unwrap()));
+        assert!(!signed.verify(&other_key.public_key_bytes().unwrap()));
+    }
+
+    #[test]
+    fn unsigned_encrypted_messages_are_rejected() {
+        let ring_key = SymKey::generate_pair_for_ring("beyonce");
+        let wire = generate_wire(b"ping".to_vec(), Some(&ring_key), None).unwrap();
+
+        match unwrap_wire(&wire, Some(&RingKeys::new(ring_key))) {
+            Err(Error::UnsignedMessage) => (),
+            other => panic!("Expected an unsigned message error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unencrypted_messages_are_not_signed() {
+        let member_key = SigKeyPair::generate_pair_for_origin("member1");
+        let wire = generate_wire(b"ping".to_vec(), None, Some(("member1", &member_key))).unwrap();
+
+        let (payload, signed) = unwrap_wire(&wire, None).unwrap();
+        assert_eq!(payload, b"ping".to_vec());
+        assert!(signed.is_none());
     }
 }
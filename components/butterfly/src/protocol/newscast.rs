@@ -3,6 +3,7 @@ use std::fmt;
 use crate::rumor::{departure::Departure as CDeparture,
                    election::{Election as CElection,
                               ElectionUpdate as CElectionUpdate},
+                   ring_command::RingCommand as CRingCommand,
                    service::Service as CService,
                    service_config::ServiceConfig as CServiceConfig,
                    service_file::ServiceFile as CServiceFile};
@@ -24,6 +25,7 @@ impl fmt::Display for RumorType {
             RumorType::Fake2 => "fake2",
             RumorType::ElectionUpdate => "election-update",
             RumorType::Departure => "departure",
+            RumorType::RingCommand => "ring-command",
         };
 
         write!(f, "{}", value)
@@ -78,7 +80,8 @@ impl From<CService> for Rumor {
                                 initialized:   Some(value.initialized),
                                 pkg:           Some(value.pkg),
                                 cfg:           Some(value.cfg),
-                                sys:           Some(value.sys.into()), };
+                                sys:           Some(value.sys.into()),
+                                healthy:       Some(value.healthy), };
         Rumor { r#type:  RumorType::Service as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id),
@@ -112,3 +115,13 @@ impl From<CServiceFile> for Rumor {
                 payload: Some(RumorPayload::ServiceFile(payload)), }
     }
 }
+
+impl From<CRingCommand> for Rumor {
+    fn from(value: CRingCommand) -> Self {
+        let from_id = value.member_id.clone();
+        Rumor { r#type:  RumorType::RingCommand as i32,
+                tag:     Vec::default(),
+                from_id: Some(from_id),
+                payload: Some(RumorPayload::RingCommand(value.into())), }
+    }
+}
@@ -10,6 +10,7 @@ pub mod dat_file;
 pub mod departure;
 pub mod election;
 pub mod heat;
+pub mod ring_command;
 pub mod service;
 pub mod service_config;
 pub mod service_file;
@@ -21,24 +22,36 @@ use crate::{error::{Error,
                        Message},
             rumor::election::ElectionRumor};
 use bytes::BytesMut;
-use prometheus::IntCounterVec;
+use habitat_core::{crypto::{keys::{self,
+                                   box_key_pair::WrappedSealedBox},
+                            BoxKeyPair},
+                   service::ServiceGroup};
+use prometheus::{IntCounterVec,
+                 IntGaugeVec};
 use prost::Message as ProstMessage;
 use std::{collections::{hash_map::Entry,
+                        BTreeMap,
                         HashMap},
           default::Default,
           fmt,
+          path::Path,
           result,
           sync::{atomic::{AtomicUsize,
                           Ordering},
-                 Arc}};
+                 Arc,
+                 Mutex,
+                 MutexGuard}};
 
 pub use self::{departure::Departure,
                election::{Election,
                           ElectionUpdate},
+               ring_command::{RingCommand,
+                              RingCommandOutcome},
                service::Service,
                service_config::ServiceConfig,
                service_file::ServiceFile,
-               storage::{RumorStore,
+               storage::{RumorLimit,
+                         RumorStore,
                          RumorStoreProxy}};
 pub use crate::protocol::newscast::{Rumor as ProtoRumor,
                                     RumorPayload,
@@ -49,6 +62,19 @@ lazy_static! {
         register_int_counter_vec!("hab_butterfly_ignored_rumor_total",
                                   "How many rumors we ignore",
                                   &["rumor"]).unwrap();
+    static ref EVICTED_RUMOR_COUNT: IntCounterVec =
+        register_int_counter_vec!("hab_butterfly_evicted_rumor_total",
+                                  "How many rumors we evict to stay within a rumor limit",
+                                  &["rumor"]).unwrap();
+    static ref STORED_RUMORS: IntGaugeVec =
+        register_int_gauge_vec!("hab_butterfly_stored_rumors",
+                                "How many rumors are stored in a store with a rumor limit",
+                                &["rumor"]).unwrap();
+    static ref STORED_RUMOR_BYTES: IntGaugeVec =
+        register_int_gauge_vec!("hab_butterfly_stored_rumor_bytes",
+                                "The encoded size of the rumors stored in a store with a byte \
+                                 limit",
+                                &["rumor"]).unwrap();
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -57,6 +83,7 @@ pub enum RumorKind {
     Election(Election),
     ElectionUpdate(ElectionUpdate),
     Membership(Membership),
+    RingCommand(RingCommand),
     Service(Box<Service>), // Boxed due to clippy::large_enum_variant
     ServiceConfig(ServiceConfig),
     ServiceFile(ServiceFile),
@@ -69,6 +96,7 @@ impl From<RumorKind> for RumorPayload {
             RumorKind::Election(election) => RumorPayload::Election(election.into()),
             RumorKind::ElectionUpdate(election) => RumorPayload::Election(election.into()),
             RumorKind::Membership(membership) => RumorPayload::Member(membership.into()),
+            RumorKind::RingCommand(ring_command) => RumorPayload::RingCommand(ring_command.into()),
             RumorKind::Service(service) => RumorPayload::Service((*service).into()),
             RumorKind::ServiceConfig(service_config) => {
                 RumorPayload::ServiceConfig(service_config.into())
@@ -138,6 +166,56 @@ impl<'a, T: Rumor> From<&'a T> for RumorKey {
 type RumorSubMap<T> = HashMap<RumorKeyId, T>;
 type RumorMap<T> = HashMap<RumorKeyKey, RumorSubMap<T>>;
 
+/// The limits applied to the stores of the rumor types which may safely be evicted. Departure and
+/// election rumors are never evicted, since forgetting them would let departed members back into
+/// the ring or restart settled elections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RumorLimits {
+    pub service:        RumorLimit,
+    pub service_config: RumorLimit,
+    pub service_file:   RumorLimit,
+}
+
+/// Tracks the rumors in a store with a `RumorLimit`, in the order they were last inserted or
+/// updated.
+#[derive(Debug, Default)]
+struct RumorUsage {
+    /// Incremented on every insert or update; lower values were updated less recently.
+    sequence: u64,
+    bytes:    usize,
+    entries:  HashMap<(RumorKeyKey, RumorKeyId), (u64, usize)>,
+    by_age:   BTreeMap<u64, (RumorKeyKey, RumorKeyId)>,
+}
+
+impl RumorUsage {
+    fn touch(&mut self, entry: (RumorKeyKey, RumorKeyId), bytes: usize) {
+        self.remove(&entry);
+        self.sequence += 1;
+        self.bytes += bytes;
+        self.by_age.insert(self.sequence, entry.clone());
+        self.entries.insert(entry, (self.sequence, bytes));
+    }
+
+    fn remove(&mut self, entry: &(RumorKeyKey, RumorKeyId)) {
+        if let Some((sequence, bytes)) = self.entries.remove(entry) {
+            self.by_age.remove(&sequence);
+            self.bytes -= bytes;
+        }
+    }
+
+    /// The least recently updated rumor that may be evicted. Neither the rumor which was just
+    /// stored nor rumors from `exempt_id` are candidates.
+    fn oldest_evictable(&self,
+                        newest: &(RumorKeyKey, RumorKeyId),
+                        exempt_id: &str)
+                        -> Option<(RumorKeyKey, RumorKeyId)> {
+        self.by_age
+            .values()
+            .find(|entry| *entry != newest && entry.1 != exempt_id)
+            .cloned()
+    }
+}
+
 /// To keep the details of the locking from being directly accessible to all the code in the
 /// rumor submodule.
 mod storage {
@@ -267,6 +345,26 @@ mod storage {
         fn deref(&self) -> &Self::Target { &self.0 }
     }
 
+    /// Caps on the number of rumors kept in a `RumorStore`, and on their total encoded size. Once
+    /// a cap is exceeded, the least recently updated rumors are evicted until the store is back
+    /// within it. Evicted rumors which are still current are simply stored again the next time
+    /// they are gossiped to us.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct RumorLimit {
+        pub max_rumors: Option<usize>,
+        pub max_bytes:  Option<usize>,
+    }
+
+    impl RumorLimit {
+        fn is_unlimited(&self) -> bool { self.max_rumors.is_none() && self.max_bytes.is_none() }
+
+        fn is_exceeded_by(&self, usage: &RumorUsage) -> bool {
+            self.max_rumors
+                .map_or(false, |max| usage.entries.len() > max)
+            || self.max_bytes.map_or(false, |max| usage.bytes > max)
+        }
+    }
+
     /// Storage for Rumors. It takes a rumor and stores it according to the member that produced it,
     /// and the service group it is related to.
     ///
@@ -275,6 +373,11 @@ mod storage {
     pub struct RumorStore<T> {
         list:           Arc<Lock<RumorMap<T>>>,
         update_counter: Arc<AtomicUsize>,
+        limit:          RumorLimit,
+        /// Rumors from this member are never evicted.
+        exempt_id:      String,
+        /// Only ever locked with `list` held for writing, so it needs no place in the lock order.
+        usage:          Arc<Mutex<RumorUsage>>,
     }
 
     impl<T> RumorStore<T> {
@@ -300,6 +403,20 @@ mod storage {
         pub fn remove_rsw(&self, key: &str, id: &str) {
             let mut list = self.list.write();
             list.get_mut(key).and_then(|r| r.remove(id));
+            if !self.limit.is_unlimited() {
+                self.usage().remove(&(key.to_string(), id.to_string()));
+            }
+        }
+
+        /// Limit the rumors kept in this store, never evicting those from `exempt_id`. This must
+        /// be set before any rumors are stored, and before the store is cloned.
+        pub fn set_limit(&mut self, limit: RumorLimit, exempt_id: &str) {
+            self.limit = limit;
+            self.exempt_id = exempt_id.to_string();
+        }
+
+        fn usage(&self) -> MutexGuard<'_, RumorUsage> {
+            self.usage.lock().expect("Rumor usage lock poisoned")
         }
     }
 
@@ -311,12 +428,13 @@ mod storage {
         /// * `RumorStore::list` (write)
         pub fn insert_rsw(&self, rumor: R) -> bool {
             let mut list = self.list.write();
-            let rumors = list.entry(String::from(rumor.key()))
-                             .or_insert_with(HashMap::new);
-            let kind_ignored_count =
-                IGNORED_RUMOR_COUNT.with_label_values(&[&rumor.kind().to_string()]);
+            let key = String::from(rumor.key());
+            let id = String::from(rumor.id());
+            let kind = rumor.kind().to_string();
+            let rumors = list.entry(key.clone()).or_insert_with(HashMap::new);
+            let kind_ignored_count = IGNORED_RUMOR_COUNT.with_label_values(&[&kind]);
             // Result reveals if there was a change so we can increment the counter if needed.
-            let result = match rumors.entry(rumor.id().into()) {
+            let result = match rumors.entry(id.clone()) {
                 Entry::Occupied(mut entry) => entry.get_mut().merge(rumor),
                 Entry::Vacant(entry) => {
                     entry.insert(rumor);
@@ -325,6 +443,9 @@ mod storage {
             };
             if result {
                 self.increment_update_counter();
+                if !self.limit.is_unlimited() {
+                    self.enforce_limit(&mut list, (key, id), &kind);
+                }
             } else {
                 // If we get here, it means nothing changed, which means we effectively ignored the
                 // rumor. Let's track that.
@@ -332,12 +453,52 @@ mod storage {
             }
             result
         }
+
+        /// Record that `newest` was just stored, then evict the least recently updated rumors
+        /// until the store is back within its limit.
+        fn enforce_limit(&self,
+                         list: &mut RumorMap<R>,
+                         newest: (RumorKeyKey, RumorKeyId),
+                         kind: &str) {
+            let bytes = match self.limit.max_bytes {
+                Some(_) => {
+                    list.get(&newest.0)
+                        .and_then(|rumors| rumors.get(&newest.1))
+                        .and_then(|rumor| rumor.write_to_bytes().ok())
+                        .map_or(0, |bytes| bytes.len())
+                }
+                None => 0,
+            };
+            let mut usage = self.usage();
+            usage.touch(newest.clone(), bytes);
+            while self.limit.is_exceeded_by(&usage) {
+                let oldest = match usage.oldest_evictable(&newest, &self.exempt_id) {
+                    Some(oldest) => oldest,
+                    None => break,
+                };
+                usage.remove(&oldest);
+                if let Some(rumors) = list.get_mut(&oldest.0) {
+                    rumors.remove(&oldest.1);
+                    if rumors.is_empty() {
+                        list.remove(&oldest.0);
+                    }
+                }
+                EVICTED_RUMOR_COUNT.with_label_values(&[kind]).inc();
+            }
+            STORED_RUMORS.with_label_values(&[kind])
+                         .set(usage.entries.len() as i64);
+            STORED_RUMOR_BYTES.with_label_values(&[kind])
+                              .set(usage.bytes as i64);
+        }
     }
 
     impl<T> Default for RumorStore<T> {
         fn default() -> RumorStore<T> {
             RumorStore { list:           Arc::default(),
-                         update_counter: Arc::default(), }
+                         update_counter: Arc::default(),
+                         limit:          RumorLimit::default(),
+                         exempt_id:      String::new(),
+                         usage:          Arc::default(), }
         }
     }
 
@@ -489,6 +650,7 @@ impl RumorEnvelope {
                 RumorKind::ElectionUpdate(ElectionUpdate::from_proto(proto)?)
             }
             RumorType::Member => RumorKind::Membership(Membership::from_proto(proto)?),
+            RumorType::RingCommand => RumorKind::RingCommand(RingCommand::from_proto(proto)?),
             RumorType::Service => RumorKind::Service(Box::new(Service::from_proto(proto)?)),
             RumorType::ServiceConfig => RumorKind::ServiceConfig(ServiceConfig::from_proto(proto)?),
             RumorType::ServiceFile => RumorKind::ServiceFile(ServiceFile::from_proto(proto)?),
@@ -516,6 +678,27 @@ impl From<RumorEnvelope> for ProtoRumor {
     }
 }
 
+/// Decrypts `payload`, configuration or a file gossiped for `service_group`, provided that it was
+/// encrypted for the group's own service key. Service keys are named after the group and its
+/// organization, so what is gossiped for a group in one organization is never opened with the key
+/// of an identically-named group in another.
+fn decrypt_for_service_group(payload: &[u8],
+                             service_group: &ServiceGroup,
+                             cache_key_path: &Path)
+                             -> Result<Vec<u8>> {
+    let sealed = WrappedSealedBox::from_bytes(payload).map_err(|e| {
+                     Error::ServiceConfigNotUtf8(service_group.to_string(), e)
+                 })?;
+    let receiver = match BoxKeyPair::secret_metadata(&sealed)?.receiver {
+        Some(receiver) => keys::parse_name_with_rev(receiver)?.0,
+        None => String::new(),
+    };
+    if receiver != service_group.to_string() {
+        return Err(Error::ServiceKeyMismatch(service_group.to_string(), receiver));
+    }
+    Ok(BoxKeyPair::decrypt_with_path(&sealed, cache_key_path)?)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{error::Result,
@@ -618,6 +801,7 @@ mod tests {
         use super::*;
         use crate::{error::Error,
                     rumor::{Rumor,
+                            RumorLimit,
                             RumorStore}};
 
         #[test]
@@ -698,6 +882,40 @@ mod tests {
             assert_eq!(rs.insert_rsw(f2), false);
         }
 
+        #[test]
+        fn insert_evicts_least_recently_updated_rumors_over_the_limit() {
+            let mut rs = RumorStore::default();
+            rs.set_limit(RumorLimit { max_rumors: Some(2),
+                                      max_bytes:  None, },
+                         "me");
+            let mine = FakeRumor { id:  "me".to_string(),
+                                   key: "fakerton".to_string(), };
+            let oldest = FakeRumor::default();
+            let newest = FakeRumor::default();
+            assert!(rs.insert_rsw(mine));
+            assert!(rs.insert_rsw(oldest.clone()));
+            assert!(rs.insert_rsw(newest.clone()));
+
+            let list = rs.lock_rsr();
+            let rumors = list.service_group("fakerton");
+            assert!(rumors.contains_id("me"));
+            assert!(!rumors.contains_id(&oldest.id));
+            assert!(rumors.contains_id(&newest.id));
+        }
+
+        #[test]
+        fn insert_evicts_rumors_over_the_byte_limit() {
+            let mut rs = RumorStore::default();
+            // Each rumor encodes to "<32 character id>-fakerton"
+            rs.set_limit(RumorLimit { max_rumors: None,
+                                      max_bytes:  Some(100), },
+                         "me");
+            for _ in 0..5 {
+                assert!(rs.insert_rsw(FakeRumor::default()));
+            }
+            assert_eq!(rs.lock_rsr().get("fakerton").unwrap().len(), 2);
+        }
+
         #[test]
         fn map_rumor_calls_closure_with_rumor() {
             let rs = RumorStore::default();
@@ -0,0 +1,203 @@
+//! The RingCommand rumor.
+//!
+//! A ring command is a request, such as a `SvcUpdate`, that an operator wants applied on every
+//! member of the ring. The operator signs its ID, expiry and body, and the signature travels with
+//! every copy of the command, so that each member can check it before applying the request.
+//!
+//! The member it is issued on gossips it with an `Issued` outcome; each member that receives it
+//! applies it and gossips its own copy with the outcome, which is how the issuing member collects
+//! acknowledgments. Every copy carries the request, so a member that only hears of an
+//! acknowledgment can still apply it.
+//!
+//! Ring commands are not written to the rumor file, and once they expire they are neither applied
+//! nor kept.
+
+pub use crate::protocol::newscast::ring_command::Outcome as RingCommandOutcome;
+use crate::{error::{Error,
+                    Result},
+            protocol::{self,
+                       newscast::{self,
+                                  Rumor as ProtoRumor},
+                       FromProto},
+            rumor::{Rumor,
+                    RumorPayload,
+                    RumorType}};
+use std::{fmt,
+          result};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RingCommand {
+    pub id:         String,
+    pub member_id:  String,
+    pub kind:       String,
+    pub body:       Vec<u8>,
+    pub expires_at: u64,
+    pub outcome:    RingCommandOutcome,
+    pub message:    String,
+    /// The name and revision of the key the command was signed with.
+    pub signer:     String,
+    pub signature:  Vec<u8>,
+}
+
+impl fmt::Display for RingCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,
+               "RingCommand id/{} m/{} k/{} o/{:?}",
+               self.id, self.member_id, self.kind, self.outcome)
+    }
+}
+
+impl RingCommand {
+    /// Creates a new command, issued by `member_id`.
+    pub fn new<S1, S2, S3>(id: S1, member_id: S2, kind: S3, body: Vec<u8>, expires_at: u64) -> Self
+        where S1: Into<String>,
+              S2: Into<String>,
+              S3: Into<String>
+    {
+        RingCommand { id: id.into(),
+                      member_id: member_id.into(),
+                      kind: kind.into(),
+                      body,
+                      expires_at,
+                      outcome: RingCommandOutcome::Issued,
+                      message: String::new(),
+                      signer: String::new(),
+                      signature: Vec::new() }
+    }
+
+    /// This command with the signature of the operator who requested it.
+    pub fn with_signature(self, signer: String, signature: Vec<u8>) -> Self {
+        RingCommand { signer,
+                      signature,
+                      ..self }
+    }
+
+    /// The acknowledgment of this command by `member_id`, with the result of applying it.
+    pub fn acknowledge(&self, member_id: &str, result: result::Result<String, String>) -> Self {
+        let (outcome, message) = match result {
+            Ok(message) => (RingCommandOutcome::Applied, message),
+            Err(message) => (RingCommandOutcome::Failed, message),
+        };
+        RingCommand { member_id: member_id.to_string(),
+                      outcome,
+                      message,
+                      ..self.clone() }
+    }
+
+    pub fn is_acknowledgment(&self) -> bool { self.outcome != RingCommandOutcome::Issued }
+
+    /// Whether the command may no longer be applied at `now`, in seconds since the UNIX epoch.
+    pub fn is_expired(&self, now: u64) -> bool { now >= self.expires_at }
+}
+
+impl protocol::Message<ProtoRumor> for RingCommand {
+    const MESSAGE_ID: &'static str = "RingCommand";
+}
+
+impl FromProto<ProtoRumor> for RingCommand {
+    fn from_proto(rumor: ProtoRumor) -> Result<Self> {
+        let payload = match rumor.payload.ok_or(Error::ProtocolMismatch("payload"))? {
+            RumorPayload::RingCommand(payload) => payload,
+            _ => panic!("from-bytes ring-command"),
+        };
+        Ok(RingCommand { id:         payload.id.ok_or(Error::ProtocolMismatch("id"))?,
+                         member_id:  payload.member_id
+                                            .ok_or(Error::ProtocolMismatch("member-id"))?,
+                         kind:       payload.kind.ok_or(Error::ProtocolMismatch("kind"))?,
+                         body:       payload.body.unwrap_or_default(),
+                         expires_at: payload.expires_at.unwrap_or(0),
+                         outcome:    payload.outcome
+                                            .and_then(RingCommandOutcome::from_i32)
+                                            .unwrap_or(RingCommandOutcome::Issued),
+                         message:    payload.message.unwrap_or_default(),
+                         signer:     payload.signer.unwrap_or_default(),
+                         signature:  payload.signature.unwrap_or_default(), })
+    }
+}
+
+impl From<RingCommand> for newscast::RingCommand {
+    fn from(value: RingCommand) -> Self {
+        newscast::RingCommand { id:         Some(value.id),
+                                member_id:  Some(value.member_id),
+                                kind:       Some(value.kind),
+                                body:       Some(value.body),
+                                expires_at: Some(value.expires_at),
+                                outcome:    Some(value.outcome as i32),
+                                message:    Some(value.message),
+                                signer:     Some(value.signer),
+                                signature:  Some(value.signature), }
+    }
+}
+
+impl Rumor for RingCommand {
+    /// A member's copy only ever changes from issued to acknowledged.
+    fn merge(&mut self, other: RingCommand) -> bool {
+        if !self.is_acknowledgment() && other.is_acknowledgment() {
+            *self = other;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn kind(&self) -> RumorType { RumorType::RingCommand }
+
+    fn key(&self) -> &str { &self.id }
+
+    fn id(&self) -> &str { &self.member_id }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rumor::RumorStore;
+
+    fn create_ring_command(member_id: &str) -> RingCommand {
+        RingCommand::new("cmd", member_id, "SvcUpdate", b"update".to_vec(), 100)
+    }
+
+    #[test]
+    fn acknowledgments_are_kept_per_member_under_the_command_id() {
+        let rs: RumorStore<RingCommand> = RumorStore::default();
+        let issued = create_ring_command("issuer");
+        rs.insert_rsw(issued.clone());
+        rs.insert_rsw(issued.acknowledge("member_1", Ok(String::from("Updated"))));
+        rs.insert_rsw(issued.acknowledge("member_2", Err(String::from("Not loaded"))));
+
+        let list = rs.lock_rsr();
+        assert_eq!(list.len(), 1);
+        let acks = list.service_group("cmd")
+                       .rumors()
+                       .filter(|c| c.is_acknowledgment())
+                       .count();
+        assert_eq!(acks, 2);
+    }
+
+    #[test]
+    fn merge_only_moves_from_issued_to_acknowledged() {
+        let mut command = create_ring_command("member_1");
+        let applied = command.acknowledge("member_1", Ok(String::from("Updated")));
+        assert!(!command.merge(create_ring_command("member_1")));
+        assert!(command.merge(applied.clone()));
+        assert_eq!(command, applied);
+        assert!(!command.merge(create_ring_command("member_1")));
+        assert!(!command.merge(applied.acknowledge("member_1", Err(String::from("nope")))));
+        assert_eq!(command.outcome, RingCommandOutcome::Applied);
+    }
+
+    #[test]
+    fn acknowledgments_carry_the_signature_of_the_command() {
+        let command = create_ring_command("issuer").with_signature(String::from("acme-1"),
+                                                                   b"signature".to_vec());
+        let ack = command.acknowledge("member_1", Ok(String::from("Updated")));
+        assert_eq!(ack.signer, "acme-1");
+        assert_eq!(ack.signature, b"signature".to_vec());
+    }
+
+    #[test]
+    fn commands_expire() {
+        let command = create_ring_command("member_1");
+        assert!(!command.is_expired(99));
+        assert!(command.is_expired(100));
+    }
+}
@@ -30,6 +30,8 @@ pub struct Service {
     pub pkg:           String,
     pub cfg:           Vec<u8>,
     pub sys:           SysInfo,
+    /// Whether the service's health check last came back other than critical
+    pub healthy:       bool,
 }
 
 impl fmt::Display for Service {
@@ -45,7 +47,7 @@ impl Serialize for Service {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("service", 7)?;
+        let mut strukt = serializer.serialize_struct("service", 8)?;
         let cfg: toml::value::Table = toml::from_slice(&self.cfg).unwrap_or_default();
         strukt.serialize_field("member_id", &self.member_id)?;
         strukt.serialize_field("service_group", &self.service_group)?;
@@ -54,6 +56,7 @@ impl Serialize for Service {
         strukt.serialize_field("cfg", &cfg)?;
         strukt.serialize_field("sys", &self.sys)?;
         strukt.serialize_field("initialized", &self.initialized)?;
+        strukt.serialize_field("healthy", &self.healthy)?;
         strukt.end()
     }
 }
@@ -97,6 +100,7 @@ impl Service {
                   service_group,
                   incarnation: 0,
                   initialized: false,
+                  healthy: true,
                   pkg: package.to_string(),
                   sys,
                   cfg: cfg.map(|v| {
@@ -128,6 +132,7 @@ impl FromProto<newscast::Rumor> for Service {
                                 .and_then(|s| ServiceGroup::from_str(&s).map_err(Error::from))?,
                      incarnation:   payload.incarnation.unwrap_or(0),
                      initialized:   payload.initialized.unwrap_or(false),
+                     healthy:       payload.healthy.unwrap_or(true),
                      pkg:           payload.pkg.ok_or(Error::ProtocolMismatch("pkg"))?,
                      cfg:           payload.cfg.unwrap_or_default(),
                      sys:           payload.sys
@@ -144,7 +149,8 @@ impl From<Service> for newscast::Service {
                             initialized:   Some(value.initialized),
                             pkg:           Some(value.pkg),
                             cfg:           Some(value.cfg),
-                            sys:           Some(value.sys.into()), }
+                            sys:           Some(value.sys.into()),
+                            healthy:       Some(value.healthy), }
     }
 }
 
@@ -167,7 +173,7 @@ impl Rumor for Service {
     fn key(&self) -> &str { self.service_group.as_ref() }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct SysInfo {
     pub ip:                String,
     pub hostname:          String,
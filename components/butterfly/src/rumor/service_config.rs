@@ -8,12 +8,12 @@ use crate::{error::{Error,
                        newscast::{self,
                                   Rumor as ProtoRumor},
                        FromProto},
-            rumor::{ConstIdRumor,
+            rumor::{self,
+                    ConstIdRumor,
                     Rumor,
                     RumorPayload,
                     RumorType}};
-use habitat_core::{crypto::{keys::box_key_pair::WrappedSealedBox,
-                            BoxKeyPair},
+use habitat_core::{crypto::BoxKeyPair,
                    service::ServiceGroup};
 use std::{cmp::Ordering,
           fmt,
@@ -24,11 +24,14 @@ use std::{cmp::Ordering,
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ServiceConfig {
-    pub from_id:       String,
-    pub service_group: ServiceGroup,
-    pub incarnation:   u64,
-    pub encrypted:     bool,
-    pub config:        Vec<u8>, // TODO: make this a String
+    pub from_id:        String,
+    pub service_group:  ServiceGroup,
+    pub incarnation:    u64,
+    pub encrypted:      bool,
+    pub config:         Vec<u8>, // TODO: make this a String
+    /// The encoded, signed request which set this configuration, if it was signed, so that every
+    /// member can check who made the change before applying it.
+    pub signed_request: Option<Vec<u8>>,
 }
 
 impl fmt::Display for ServiceConfig {
@@ -67,7 +70,8 @@ impl ServiceConfig {
                         service_group,
                         incarnation: 0,
                         encrypted: false,
-                        config }
+                        config,
+                        signed_request: None }
     }
 
     pub fn encrypt(&mut self, user_pair: &BoxKeyPair, service_pair: &BoxKeyPair) -> Result<()> {
@@ -79,11 +83,9 @@ impl ServiceConfig {
 
     pub fn config(&self, cache_key_path: &Path) -> Result<toml::value::Table> {
         let config = if self.encrypted {
-            let bytes = BoxKeyPair::decrypt_with_path(
-                    &WrappedSealedBox::from_bytes(&self.config)
-                        .map_err(|e| Error::ServiceConfigNotUtf8(self.service_group.to_string(), e))?,
-                    cache_key_path,
-                    )?;
+            let bytes = rumor::decrypt_for_service_group(&self.config,
+                                                         &self.service_group,
+                                                         cache_key_path)?;
             let encoded = str::from_utf8(&bytes).map_err(|e| {
                                                     Error::ServiceConfigNotUtf8(self.service_group
                                                                                     .to_string(),
@@ -116,26 +118,28 @@ impl FromProto<ProtoRumor> for ServiceConfig {
             RumorPayload::ServiceConfig(payload) => payload,
             _ => panic!("from-bytes service-config"),
         };
-        Ok(ServiceConfig { from_id:       rumor.from_id
-                                               .ok_or(Error::ProtocolMismatch("from-id"))?,
+        Ok(ServiceConfig { from_id:        rumor.from_id
+                                                .ok_or(Error::ProtocolMismatch("from-id"))?,
                            service_group:
                                payload.service_group
                                       .ok_or(Error::ProtocolMismatch("service-group"))
                                       .and_then(|s| {
                                           ServiceGroup::from_str(&s).map_err(Error::from)
                                       })?,
-                           incarnation:   payload.incarnation.unwrap_or(0),
-                           encrypted:     payload.encrypted.unwrap_or(false),
-                           config:        payload.config.unwrap_or_default(), })
+                           incarnation:    payload.incarnation.unwrap_or(0),
+                           encrypted:      payload.encrypted.unwrap_or(false),
+                           config:         payload.config.unwrap_or_default(),
+                           signed_request: payload.signed_request, })
     }
 }
 
 impl From<ServiceConfig> for newscast::ServiceConfig {
     fn from(value: ServiceConfig) -> Self {
-        newscast::ServiceConfig { service_group: Some(value.service_group.to_string()),
-                                  incarnation:   Some(value.incarnation),
-                                  encrypted:     Some(value.encrypted),
-                                  config:        Some(value.config), }
+        newscast::ServiceConfig { service_group:  Some(value.service_group.to_string()),
+                                  incarnation:    Some(value.incarnation),
+                                  encrypted:      Some(value.encrypted),
+                                  config:         Some(value.config),
+                                  signed_request: value.signed_request, }
     }
 }
 
@@ -168,7 +172,8 @@ mod tests {
     use crate::rumor::{ConstIdRumor as _,
                        Rumor,
                        RumorStore};
-    use habitat_core::service::ServiceGroup;
+    use habitat_core::{crypto::BoxKeyPair,
+                       service::ServiceGroup};
     use std::{cmp::Ordering,
               str::FromStr};
 
@@ -262,6 +267,26 @@ mod tests {
         assert_eq!(s1, s1_check);
     }
 
+    #[test]
+    fn encrypted_config_only_opens_for_its_own_organization() {
+        let cache = tempfile::tempdir().unwrap();
+        let user = BoxKeyPair::generate_pair_for_user("ruby").unwrap();
+        user.to_pair_files(cache.path()).unwrap();
+        let acme = BoxKeyPair::generate_pair_for_service("acme", "neurosis.production").unwrap();
+        acme.to_pair_files(cache.path()).unwrap();
+        let other = BoxKeyPair::generate_pair_for_service("other", "neurosis.production").unwrap();
+        other.to_pair_files(cache.path()).unwrap();
+
+        let acme_sg = ServiceGroup::new("neurosis", "production", Some("acme")).unwrap();
+        let mut s1 = ServiceConfig::new("adam", acme_sg, Vec::from("yep=1"));
+        s1.encrypt(&user, &acme).unwrap();
+        assert_eq!(s1.config(cache.path()).unwrap(),
+                   toml::from_str::<toml::value::Table>("yep=1").unwrap());
+
+        s1.service_group = ServiceGroup::new("neurosis", "production", Some("other")).unwrap();
+        assert!(s1.config(cache.path()).is_err());
+    }
+
     #[test]
     fn config_comes_back_as_a_toml_value() {
         let s1 = create_service_config("adam", "yep=1");
@@ -8,11 +8,11 @@ use crate::{error::{Error,
                        newscast::{self,
                                   Rumor as ProtoRumor},
                        FromProto},
-            rumor::{Rumor,
+            rumor::{self,
+                    Rumor,
                     RumorPayload,
                     RumorType}};
-use habitat_core::{crypto::{keys::box_key_pair::WrappedSealedBox,
-                            BoxKeyPair},
+use habitat_core::{crypto::BoxKeyPair,
                    service::ServiceGroup};
 use std::{cmp::Ordering,
           fmt,
@@ -22,12 +22,15 @@ use std::{cmp::Ordering,
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ServiceFile {
-    pub from_id:       String,
-    pub service_group: ServiceGroup,
-    pub incarnation:   u64,
-    pub encrypted:     bool,
-    pub filename:      String,
-    pub body:          Vec<u8>, // TODO: make this a String
+    pub from_id:        String,
+    pub service_group:  ServiceGroup,
+    pub incarnation:    u64,
+    pub encrypted:      bool,
+    pub filename:       String,
+    pub body:           Vec<u8>, // TODO: make this a String
+    /// The encoded, signed request which uploaded this file, if it was signed, so that every
+    /// member can check who made the change before applying it.
+    pub signed_request: Option<Vec<u8>>,
 }
 
 impl fmt::Display for ServiceFile {
@@ -73,7 +76,8 @@ impl ServiceFile {
                       incarnation: 0,
                       encrypted: false,
                       filename: filename.into(),
-                      body }
+                      body,
+                      signed_request: None }
     }
 
     /// Encrypt the contents of the service file
@@ -88,12 +92,7 @@ impl ServiceFile {
     /// the fact that we might be encrypted.
     pub fn body(&self, cache_key_path: &Path) -> Result<Vec<u8>> {
         if self.encrypted {
-            let bytes = BoxKeyPair::decrypt_with_path(
-                &WrappedSealedBox::from_bytes(&self.body)
-                    .map_err(|e| Error::ServiceConfigNotUtf8(self.service_group.to_string(), e))?,
-                cache_key_path,
-            )?;
-            Ok(bytes)
+            rumor::decrypt_for_service_group(&self.body, &self.service_group, cache_key_path)
         } else {
             Ok(self.body.to_vec())
         }
@@ -110,26 +109,29 @@ impl FromProto<ProtoRumor> for ServiceFile {
             RumorPayload::ServiceFile(payload) => payload,
             _ => panic!("from-bytes service-config"),
         };
-        Ok(ServiceFile { from_id:       rumor.from_id.ok_or(Error::ProtocolMismatch("from-id"))?,
+        Ok(ServiceFile { from_id:        rumor.from_id
+                                              .ok_or(Error::ProtocolMismatch("from-id"))?,
                          service_group:
                              payload.service_group
                                     .ok_or(Error::ProtocolMismatch("service-group"))
                                     .and_then(|s| ServiceGroup::from_str(&s).map_err(Error::from))?,
-                         incarnation:   payload.incarnation.unwrap_or(0),
-                         encrypted:     payload.encrypted.unwrap_or(false),
-                         filename:      payload.filename
-                                               .ok_or(Error::ProtocolMismatch("filename"))?,
-                         body:          payload.body.unwrap_or_default(), })
+                         incarnation:    payload.incarnation.unwrap_or(0),
+                         encrypted:      payload.encrypted.unwrap_or(false),
+                         filename:       payload.filename
+                                                .ok_or(Error::ProtocolMismatch("filename"))?,
+                         body:           payload.body.unwrap_or_default(),
+                         signed_request: payload.signed_request, })
     }
 }
 
 impl From<ServiceFile> for newscast::ServiceFile {
     fn from(value: ServiceFile) -> Self {
-        newscast::ServiceFile { service_group: Some(value.service_group.to_string()),
-                                incarnation:   Some(value.incarnation),
-                                encrypted:     Some(value.encrypted),
-                                filename:      Some(value.filename),
-                                body:          Some(value.body), }
+        newscast::ServiceFile { service_group:  Some(value.service_group.to_string()),
+                                incarnation:    Some(value.incarnation),
+                                encrypted:      Some(value.encrypted),
+                                filename:       Some(value.filename),
+                                body:           Some(value.body),
+                                signed_request: value.signed_request, }
     }
 }
 
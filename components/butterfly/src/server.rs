@@ -11,18 +11,26 @@ mod incarnation_store;
 mod outbound;
 mod pull;
 mod push;
+pub mod ring_health;
 pub mod timing;
 
 use self::{incarnation_store::IncarnationStore,
+           ring_health::{RingHealth,
+                         RingHealthReport},
            sync::Myself};
-use crate::{error::{Error,
+use crate::{client::Client,
+            error::{Error,
                     Result},
-            member::{Health,
+            member::{history,
+                     Health,
                      Incarnation,
                      Member,
                      MemberList,
-                     MemberListProxy},
-            message,
+                     MemberListProxy,
+                     Membership,
+                     SigningKeyProof},
+            message::{self,
+                      Signed},
             rumor::{dat_file::{DatFileReader,
                                DatFileWriter},
                     departure::Departure,
@@ -30,12 +38,14 @@ use crate::{error::{Error,
                                ElectionRumor,
                                ElectionUpdate},
                     heat::sync::RumorHeat,
+                    ring_command::RingCommand,
                     service::Service,
                     service_config::ServiceConfig,
                     service_file::ServiceFile,
                     ConstIdRumor,
                     Rumor,
                     RumorKey,
+                    RumorLimits,
                     RumorStore,
                     RumorStoreProxy,
                     RumorType},
@@ -43,10 +53,15 @@ use crate::{error::{Error,
 use habitat_common::{liveliness_checker,
                      sync::Lock,
                      FeatureFlag};
-use habitat_core::crypto::SymKey;
+use habitat_core::{crypto::{keys::{ring_key::RingKeys,
+                                   PairType},
+                            SigKeyPair,
+                            SymKey},
+                   fs::atomic_write};
 use prometheus::{HistogramTimer,
                  HistogramVec,
                  IntGauge};
+use rand::Rng;
 use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
@@ -68,7 +83,9 @@ use std::{collections::{HashMap,
                  mpsc::{self,
                         channel},
                  Arc,
-                 Mutex},
+                 Mutex,
+                 MutexGuard,
+                 RwLock},
           thread,
           time::{Duration,
                  Instant}};
@@ -139,7 +156,15 @@ pub(crate) mod sync {
 
         pub fn mark_departed(&mut self) { self.0.mark_departed() }
 
+        pub fn mark_draining(&mut self) { self.0.mark_draining() }
+
         pub fn set_persistent(&mut self) { self.0.set_persistent() }
+
+        pub fn set_signing_key(&mut self, key: Vec<u8>, proof: Option<SigningKeyProof>) {
+            self.0.set_signing_key(key, proof)
+        }
+
+        pub fn set_key_proof(&mut self, proof: SigningKeyProof) { self.0.set_key_proof(proof) }
     }
 
     /// Encapsulate a `Member` with the added understanding that this
@@ -255,6 +280,15 @@ pub(crate) mod sync {
 
         fn mark_departed(&mut self) { self.member.departed = true }
 
+        fn mark_draining(&mut self) { self.member.draining = true }
+
+        fn set_signing_key(&mut self, key: Vec<u8>, proof: Option<SigningKeyProof>) {
+            self.member.signing_key = Some(key);
+            self.member.key_proof = proof;
+        }
+
+        fn set_key_proof(&mut self, proof: SigningKeyProof) { self.member.key_proof = Some(proof) }
+
         /// Return a copy of the underlying `Member`.
         fn as_member(&self) -> Member { self.member.clone() }
 
@@ -267,6 +301,16 @@ pub(crate) mod sync {
     }
 }
 
+/// Faults injected into the traffic a server receives, to see how gossip copes with an
+/// unreliable network. Only used in testing and simulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    /// The fraction of messages, from 0 to 1, which never arrive
+    pub loss:    f64,
+    /// How long each message takes to arrive
+    pub latency: Duration,
+}
+
 /// The server struct. Is thread-safe.
 #[derive(Debug)]
 pub struct Server {
@@ -276,7 +320,10 @@ pub struct Server {
     // depends on it being so. Refactor so it can be private.
     myself:                   Arc<Myself>,
     pub member_list:          Arc<MemberList>,
-    ring_key:                 Arc<Option<SymKey>>,
+    // This is a leaf lock; no other lock is acquired while it is held.
+    ring_keys:                Option<Arc<RwLock<RingKeys>>>,
+    /// The key this member signs its encrypted messages with
+    member_key:               Arc<SigKeyPair>,
     rumor_heat:               Arc<RumorHeat>,
     pub service_store:        RumorStore<Service>,
     pub service_config_store: RumorStore<ServiceConfig>,
@@ -284,6 +331,7 @@ pub struct Server {
     pub election_store:       RumorStore<Election>,
     pub update_store:         RumorStore<ElectionUpdate>,
     pub departure_store:      RumorStore<Departure>,
+    pub ring_command_store:   RumorStore<RingCommand>,
     swim_addr:                SocketAddr,
     gossip_addr:              SocketAddr,
     suitability_lookup:       Arc<dyn Suitability>,
@@ -296,7 +344,10 @@ pub struct Server {
     swim_rounds:              Arc<AtomicIsize>,
     gossip_rounds:            Arc<AtomicIsize>,
     block_list:               Arc<Lock<HashSet<String>>>,
+    network_conditions:       Arc<Lock<NetworkConditions>>,
     election_timers:          Arc<Mutex<HashMap<String, ElectionTimer>>>,
+    // This is a leaf lock; no other lock is acquired while it is held.
+    ring_health:              Arc<Mutex<RingHealth>>,
 }
 
 impl Clone for Server {
@@ -305,7 +356,8 @@ impl Clone for Server {
                  member_id:            self.member_id.clone(),
                  myself:               self.myself.clone(),
                  member_list:          self.member_list.clone(),
-                 ring_key:             self.ring_key.clone(),
+                 ring_keys:            self.ring_keys.clone(),
+                 member_key:           self.member_key.clone(),
                  rumor_heat:           self.rumor_heat.clone(),
                  service_store:        self.service_store.clone(),
                  service_config_store: self.service_config_store.clone(),
@@ -313,6 +365,7 @@ impl Clone for Server {
                  election_store:       self.election_store.clone(),
                  update_store:         self.update_store.clone(),
                  departure_store:      self.departure_store.clone(),
+                 ring_command_store:   self.ring_command_store.clone(),
                  swim_addr:            self.swim_addr,
                  gossip_addr:          self.gossip_addr,
                  suitability_lookup:   self.suitability_lookup.clone(),
@@ -323,8 +376,10 @@ impl Clone for Server {
                  swim_rounds:          self.swim_rounds.clone(),
                  gossip_rounds:        self.gossip_rounds.clone(),
                  block_list:           self.block_list.clone(),
+                 network_conditions:   self.network_conditions.clone(),
                  socket:               None,
-                 election_timers:      self.election_timers.clone(), }
+                 election_timers:      self.election_timers.clone(),
+                 ring_health:          self.ring_health.clone(), }
     }
 }
 
@@ -353,6 +408,16 @@ impl Server {
                 member.gossip_port = gossip_socket_addr.port();
 
                 let member_id = member.id.clone();
+                // Replaced with the key persisted in the data path, if there is one, on start.
+                let member_key = SigKeyPair::generate_pair_for_origin(&member_id);
+                let signing_key = member_key.public_key_bytes()?;
+                member.key_proof = match ring_key {
+                    Some(ref ring_key) => {
+                        Some(SigningKeyProof::new(ring_key, &member_id, &signing_key)?)
+                    }
+                    None => None,
+                };
+                member.signing_key = Some(signing_key);
 
                 // TODO (CM): This None really wants to go away. Not
                 // currently setting a IncarnationStore, because it
@@ -367,7 +432,8 @@ impl Server {
                             member_id: Arc::new(member_id),
                             myself: Arc::new(myself),
                             member_list: Arc::new(MemberList::new()),
-                            ring_key: Arc::new(ring_key),
+                            ring_keys: ring_key.map(|k| Arc::new(RwLock::new(RingKeys::new(k)))),
+                            member_key: Arc::new(member_key),
                             rumor_heat: Arc::default(),
                             service_store: RumorStore::default(),
                             service_config_store: RumorStore::default(),
@@ -375,6 +441,7 @@ impl Server {
                             election_store: RumorStore::default(),
                             update_store: RumorStore::default(),
                             departure_store: RumorStore::default(),
+                            ring_command_store: RumorStore::default(),
                             swim_addr: swim_socket_addr,
                             gossip_addr: gossip_socket_addr,
                             suitability_lookup,
@@ -385,8 +452,10 @@ impl Server {
                             swim_rounds: Arc::new(AtomicIsize::new(0)),
                             gossip_rounds: Arc::new(AtomicIsize::new(0)),
                             block_list: Arc::new(Lock::new(HashSet::new())),
+                            network_conditions: Arc::default(),
                             socket: None,
-                            election_timers: Arc::new(Mutex::new(HashMap::new())) })
+                            election_timers: Arc::new(Mutex::new(HashMap::new())),
+                            ring_health: Arc::default() })
             }
             (Err(e), _) | (_, Err(e)) => Err(Error::CannotBind(e)),
             (Ok(None), _) | (_, Ok(None)) => {
@@ -463,6 +532,12 @@ impl Server {
                 return Err(Error::BadDataPath(path.to_path_buf(), err));
             }
 
+            // A damaged history file shouldn't prevent us from
+            // joining the ring; we'll just start a fresh history.
+            if let Err(err) = self.member_list.load_history(&history::history_path(path)) {
+                error!("{}", err);
+            }
+
             let dat_path = path.join(format!("{}.rst", &self.member_id));
             let mut reader = DatFileReader::read_or_create_rsr_mlr(dat_path.clone(),
                                                                    &self.member_list,
@@ -491,7 +566,23 @@ impl Server {
                 // persisted previously.
                 let mut store = incarnation_store::IncarnationStore::new(path.join("INCARNATION"));
                 store.initialize()?;
-                self.myself.lock_smw().sync_incarnation(store)?;
+                let member_key = load_or_create_member_key(&path.join("MEMBER_KEY"),
+                                                           &self.member_id)?;
+                let signing_key = member_key.public_key_bytes()?;
+                let key_proof = match self.ring_key() {
+                    Some(ring_key) => {
+                        Some(SigningKeyProof::new(&ring_key, &self.member_id, &signing_key)?)
+                    }
+                    None => None,
+                };
+                let mut myself = self.myself.lock_smw();
+                myself.sync_incarnation(store)?;
+                // Other members know us by the key we signed with before we restarted.
+                myself.set_signing_key(signing_key, key_proof);
+                self.member_key = Arc::new(member_key);
+                // Other members may still hold a rumor from before we restarted which says we
+                // are draining; a newer incarnation replaces it.
+                myself.increment_incarnation();
             }
         }
 
@@ -560,6 +651,30 @@ impl Server {
         self.block_list.read().contains(member_id)
     }
 
+    /// Degrade the traffic this server receives, as if it came over a lossy, slow network.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::network_conditions` (write)
+    pub fn set_network_conditions_sncw(&self, conditions: NetworkConditions) {
+        *self.network_conditions.write() = conditions;
+    }
+
+    /// Hold up a message just received for the network's latency, or return `true` if it was
+    /// lost on the way instead.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::network_conditions` (read)
+    fn message_lost_sncr(&self) -> bool {
+        let NetworkConditions { loss, latency } = *self.network_conditions.read();
+        if loss > 0.0 && rand::thread_rng().gen::<f64>() < loss {
+            return true;
+        }
+        if latency > Duration::from_secs(0) {
+            thread::sleep(latency);
+        }
+        false
+    }
+
     /// Stop the outbound and inbound threads from processing work.
     pub fn pause(&mut self) { self.pause.compare_and_swap(false, true, Ordering::Relaxed); }
 
@@ -581,6 +696,17 @@ impl Server {
     /// Return the name of this server.
     pub fn name(&self) -> &str { &self.name }
 
+    /// Limit the service, service config and service file rumors this server keeps. Our own
+    /// rumors are never evicted. This must be called before the server is started.
+    pub fn set_rumor_limits(&mut self, limits: RumorLimits) {
+        self.service_store
+            .set_limit(limits.service, &self.member_id);
+        self.service_config_store
+            .set_limit(limits.service_config, &self.member_id);
+        self.service_file_store
+            .set_limit(limits.service_file, &self.member_id);
+    }
+
     pub fn myself(&self) -> &Myself { self.myself.as_ref() }
 
     /// Insert a member to the `MemberList`, and update its `RumorKey` appropriately.
@@ -589,6 +715,7 @@ impl Server {
     /// * `MemberList::entries` (write)
     /// * `RumorHeat::inner` (write)
     pub fn insert_member_mlw_rhw(&self, member: Member, health: Health) {
+        let member = self.without_unproven_key(member);
         let rk: RumorKey = RumorKey::from(&member);
         let member_id = member.id.clone();
         if self.member_list.insert_mlw(member, health) {
@@ -605,6 +732,29 @@ impl Server {
         }
     }
 
+    /// Set our member to draining, so that other members stop sending new work to it while
+    /// its services are stopped for maintenance. The member remains alive in the ring.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (write)
+    /// * `Server::member` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn set_draining_mlw_smw_rhw(&self) {
+        {
+            let mut myself = self.myself.lock_smw();
+            myself.increment_incarnation();
+            myself.mark_draining();
+        }
+        let member = self.myself.lock_smr().to_member();
+        self.insert_member_mlw_rhw(member, Health::Alive);
+    }
+
+    /// Returns true if our member has been set to draining.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::member` (read)
+    pub fn is_draining_smr(&self) -> bool { self.myself.lock_smr().to_member().draining }
+
     /// Set our member to departed, then send up to 10 out of order ack messages to other
     /// members to seed our status.
     ///
@@ -665,6 +815,7 @@ impl Server {
                 .refute_incarnation(member.incarnation);
             health = Health::Alive;
         }
+        let member = self.without_unproven_key(member);
 
         let member_id = member.id.clone();
 
@@ -676,6 +827,80 @@ impl Server {
         }
     }
 
+    fn ring_health(&self) -> MutexGuard<'_, RingHealth> {
+        self.ring_health.lock().expect("Ring health lock poisoned")
+    }
+
+    /// Compare the membership rumors that `reported_by` sent us with our
+    /// own view of those members, noting any disagreement about whether
+    /// they are reachable. This must be called before the rumors are
+    /// inserted, as inserting them may overwrite our view.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    fn record_membership_view_mlr(&self, reported_by: &str, memberships: &[Membership]) {
+        let views: Vec<_> =
+            memberships.iter()
+                       .filter(|m| m.member.id != self.member_id() && m.member.id != reported_by)
+                       .filter_map(|m| {
+                           self.member_list
+                               .health_of_by_id_mlr(&m.member.id)
+                               .map(|local| (&m.member.id, local, m.health))
+                       })
+                       .collect();
+        let mut ring_health = self.ring_health();
+        for (member_id, local, reported) in views {
+            ring_health.record_view(reported_by, member_id, local, reported);
+        }
+    }
+
+    /// Summarize the health of the ring as seen from this member.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn ring_health_report_mlr(&self) -> RingHealthReport {
+        let health_counts = self.member_list.health_counts_mlr();
+        self.ring_health().report(&health_counts)
+    }
+
+    /// If `election` is finished, and we have already stored a finished
+    /// election for the same term with a different leader, record the
+    /// conflict. This must be called before the election is inserted.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    fn record_election_conflict_rsr<E>(&self, store: &RumorStore<E>, election: &E)
+        where E: ElectionRumor
+    {
+        if !election.is_finished() {
+            return;
+        }
+        let other_leader = store.lock_rsr()
+                                .service_group(election.key())
+                                .map_rumor(E::const_id(), |stored| {
+                                    if stored.is_finished()
+                                       && stored.term() == election.term()
+                                       && stored.member_id() != election.member_id()
+                                    {
+                                        Some(stored.member_id().to_string())
+                                    } else {
+                                        None
+                                    }
+                                })
+                                .flatten();
+        if let Some(other_leader) = other_leader {
+            warn!("Election for {} term {} was won by both {} and {}",
+                  election.key(),
+                  election.term(),
+                  election.member_id(),
+                  other_leader);
+            self.ring_health().record_election_conflict(election.key(),
+                                                        election.term(),
+                                                        election.member_id(),
+                                                        &other_leader);
+        }
+    }
+
     /// Insert a service rumor into the service store.
     /// If we're adding a new service group member, we want to avoid the
     /// situation where we could lose quorum due to Confirmed but not yet
@@ -710,6 +935,11 @@ impl Server {
                        id: member_id,
                        .. } = &rk;
 
+        if member_list.is_forgotten(member_id) {
+            trace!("Ignoring service rumor of forgotten member {}", member_id);
+            return;
+        }
+
         // True if rumors exist for the service group, but none containing the given member.
         let inserting_new_group_member =
             service_store.lock_rsr()
@@ -791,6 +1021,124 @@ impl Server {
         }
     }
 
+    /// Issue a ring command from this member, with the ID, expiry and signature its requestor
+    /// chose. Returns false if the command has already expired, in which case it isn't issued.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn issue_ring_command_rsw_rhw(&self,
+                                      id: &str,
+                                      kind: &str,
+                                      body: Vec<u8>,
+                                      expires_at: u64,
+                                      signer: String,
+                                      signature: Vec<u8>)
+                                      -> bool {
+        let command = RingCommand::new(id, self.member_id(), kind, body, expires_at);
+        if command.is_expired(ring_health::now()) {
+            return false;
+        }
+        self.insert_ring_command_rsw_rhw(command.with_signature(signer, signature));
+        true
+    }
+
+    /// Insert a ring command rumor into the ring command store, unless it has expired.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn insert_ring_command_rsw_rhw(&self, ring_command: RingCommand) {
+        if ring_command.is_expired(ring_health::now()) {
+            debug!("Ignoring expired {}", ring_command);
+            return;
+        }
+        let rk = RumorKey::from(&ring_command);
+        if self.ring_command_store.insert_rsw(ring_command) {
+            self.rumor_heat.lock_rhw().start_hot_rumor(rk);
+        }
+    }
+
+    /// The unexpired copies of each ring command which this member has not acknowledged yet. Any
+    /// member can gossip a copy of a ring command, so it is up to the caller to pick one whose
+    /// signature it trusts.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn pending_ring_commands_rsr(&self) -> Vec<Vec<RingCommand>> {
+        let now = ring_health::now();
+        self.ring_command_store
+            .lock_rsr()
+            .values()
+            .filter(|copies| {
+                copies.get(self.member_id())
+                      .map_or(true, |ours| !ours.is_acknowledgment())
+            })
+            .map(|copies| {
+                copies.values()
+                      .filter(|command| !command.is_expired(now))
+                      .cloned()
+                      .collect::<Vec<_>>()
+            })
+            .filter(|copies| !copies.is_empty())
+            .collect()
+    }
+
+    /// Gossip this member's acknowledgment of `ring_command`, with the result of applying it.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn acknowledge_ring_command_rsw_rhw(&self,
+                                            ring_command: &RingCommand,
+                                            result: result::Result<String, String>) {
+        self.insert_ring_command_rsw_rhw(ring_command.acknowledge(self.member_id(), result));
+    }
+
+    /// Forget the ring commands which have expired, along with their acknowledgments.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    pub fn expire_ring_commands_rsw(&self) {
+        let now = ring_health::now();
+        let expired = self.ring_command_store
+                          .lock_rsr()
+                          .rumors()
+                          .filter(|command| command.is_expired(now))
+                          .map(|command| (command.id.clone(), command.member_id.clone()))
+                          .collect::<Vec<_>>();
+        for (id, member_id) in expired {
+            self.ring_command_store.remove_rsw(&id, &member_id);
+        }
+    }
+
+    /// Forget the members which have been departed for at least `retention`, along with their
+    /// service rumors, so that they no longer appear in the census. Returns their IDs.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (write)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn forget_departed_members_mlw_rsw_rhw(&self, retention: Duration) -> Vec<String> {
+        let forgotten = self.member_list.forget_departed_mlw(retention);
+        if forgotten.is_empty() {
+            return forgotten;
+        }
+        let services = self.service_store
+                           .lock_rsr()
+                           .rumors()
+                           .filter(|service| forgotten.contains(&service.member_id))
+                           .map(|service| (service.key().to_string(), service.member_id.clone()))
+                           .collect::<Vec<_>>();
+        for (service_group, member_id) in services {
+            self.service_store.remove_rsw(&service_group, &member_id);
+        }
+        for member_id in &forgotten {
+            self.rumor_heat.lock_rhw().purge(member_id);
+        }
+        forgotten
+    }
+
     /// Get all the Member ID's who are present in a given service group, and eligible to vote
     /// (alive)
     ///
@@ -1125,6 +1473,7 @@ impl Server {
             }
         }
 
+        self.record_election_conflict_rsr(&self.election_store, &election);
         if self.election_store.insert_rsw(election) {
             self.rumor_heat.lock_rhw().start_hot_rumor(rk);
         }
@@ -1201,17 +1550,149 @@ impl Server {
             }
         }
 
+        self.record_election_conflict_rsr(&self.update_store, &election);
         if self.update_store.insert_rsw(election) {
             self.rumor_heat.lock_rhw().start_hot_rumor(rk);
         }
     }
 
     fn generate_wire(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
-        message::generate_wire(payload, (*self.ring_key).as_ref())
+        let sender = Some((self.member_id(), &*self.member_key));
+        match self.ring_keys {
+            Some(ref ring_keys) => {
+                let ring_keys = ring_keys.read().expect("Ring keys lock poisoned");
+                message::generate_wire(payload, Some(ring_keys.active()), sender)
+            }
+            None => message::generate_wire(payload, None, sender),
+        }
+    }
+
+    /// Decode a message from the wire, returning it with its sender's signature. Every message
+    /// on an encrypted ring must be signed; see `is_sender_authentic_mlr`.
+    fn unwrap_wire(&self, payload: &[u8]) -> Result<(Vec<u8>, Option<Signed>)> {
+        match self.ring_keys {
+            Some(ref ring_keys) => {
+                let ring_keys = ring_keys.read().expect("Ring keys lock poisoned");
+                message::unwrap_wire(payload, Some(&ring_keys))
+            }
+            None => message::unwrap_wire(payload, None),
+        }
+    }
+
+    /// Whether a message which says it is from `member_id` was signed by that member. It is
+    /// checked against the key we know the member by or, if we don't know it by one yet, against
+    /// the proven key of the member the message carries, which is how we come to know new
+    /// members. Messages on an unencrypted ring aren't signed, and are taken at their word.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    fn is_sender_authentic_mlr(&self,
+                               member_id: &str,
+                               signed: Option<&Signed>,
+                               carried: Option<&Member>)
+                               -> bool {
+        if self.ring_keys.is_none() {
+            return true;
+        }
+        let signed = match signed {
+            Some(signed) if signed.sender_id == member_id => signed,
+            _ => return false,
+        };
+        // Members keep the key we first knew them by, so a proof that comes with the message
+        // counts for nothing once we know one.
+        match self.member_list.signing_key_mlr(&signed.sender_id) {
+            Some(key) => signed.verify(&key),
+            None => {
+                carried.filter(|member| member.id == member_id)
+                       .and_then(|member| self.proven_signing_key(member))
+                       .map_or(false, |key| signed.verify(&key))
+            }
+        }
+    }
+
+    /// Whether `signed` was signed by the member it names, with the key we know it by.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    fn is_signed_by_member_mlr(&self, signed: &Signed) -> bool {
+        self.member_list
+            .signing_key_mlr(&signed.sender_id)
+            .map_or(false, |key| signed.verify(&key))
+    }
+
+    /// Whether `signed` was signed by us, say by a `Client` made with `Server::client`.
+    fn is_signed_by_myself(&self, signed: &Signed) -> bool {
+        signed.sender_id == *self.member_id
+        && self.member_key
+               .public_key_bytes()
+               .map_or(false, |key| signed.verify(&key))
+    }
+
+    /// The signing key of `member`, if it comes with a proof made with a revision of the ring key
+    /// we hold.
+    fn proven_signing_key(&self, member: &Member) -> Option<Vec<u8>> {
+        let ring_keys = self.ring_keys.as_ref()?;
+        let ring_keys = ring_keys.read().expect("Ring keys lock poisoned");
+        match (&member.signing_key, &member.key_proof) {
+            (Some(key), Some(proof)) if proof.proves(&ring_keys, &member.id, key) => {
+                Some(key.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Leave the signing key off `member` unless it is proven, so that whoever passes on a rumor
+    /// of a member can't give it a key of their own. Signing keys mean nothing on an unencrypted
+    /// ring, so they're kept as they are.
+    fn without_unproven_key(&self, mut member: Member) -> Member {
+        if self.ring_keys.is_some() && self.proven_signing_key(&member).is_none() {
+            member.signing_key = None;
+            member.key_proof = None;
+        }
+        member
     }
 
-    fn unwrap_wire(&self, payload: &[u8]) -> Result<Vec<u8>> {
-        message::unwrap_wire(payload, (*self.ring_key).as_ref())
+    /// A client which injects rumors into this server, over its gossip address `addr`, signed
+    /// with our member key so that they are taken on an encrypted ring.
+    pub fn client(&self, addr: &str) -> Result<Client> {
+        Client::new_signed(addr,
+                           self.ring_key(),
+                           Some((self.member_id().to_string(), self.member_key.clone())))
+    }
+
+    /// The revision of the ring key messages are currently encrypted with, if the ring is
+    /// encrypted.
+    pub fn ring_key(&self) -> Option<SymKey> {
+        self.ring_keys
+            .as_ref()
+            .map(|k| k.read().expect("Ring keys lock poisoned").active().clone())
+    }
+
+    /// The revisions of the ring key this server holds, if the ring is encrypted.
+    pub fn ring_keys(&self) -> Option<RingKeys> {
+        self.ring_keys
+            .as_ref()
+            .map(|k| k.read().expect("Ring keys lock poisoned").clone())
+    }
+
+    /// Apply a phase of a ring key rotation; see `habitat_core::crypto::keys::ring_key`. Our
+    /// signing key is proven anew with whichever revision is active afterwards.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::member` (write)
+    pub fn rotate_ring_key_smw<F, T>(&self, phase: F) -> Result<T>
+        where F: FnOnce(&mut RingKeys) -> habitat_core::error::Result<T>
+    {
+        let ring_keys = self.ring_keys.as_ref().ok_or(Error::RingNotEncrypted)?;
+        let (result, active) = {
+            let mut ring_keys = ring_keys.write().expect("Ring keys lock poisoned");
+            (phase(&mut ring_keys)?, ring_keys.active().clone())
+        };
+        let proof = SigningKeyProof::new(&active,
+                                         &self.member_id,
+                                         &self.member_key.public_key_bytes()?)?;
+        self.myself.lock_smw().set_key_proof(proof);
+        Ok(result)
     }
 
     /// # Locking (see locking.md)
@@ -1234,6 +1715,13 @@ impl Server {
                 info!("Rumors persisted to disk: {}", dat_file.path().display());
             }
         }
+        if let Some(ref path) = self.data_path {
+            if let Err(err) = self.member_list
+                                  .persist_history(&history::history_path(path))
+            {
+                error!("Error persisting member history to disk, {}", err);
+            }
+        }
     }
 
     #[allow(dead_code)]
@@ -1414,6 +1902,20 @@ mod election_trigger {
     }
 }
 
+/// Loads the key this member signs its messages with from `path`, creating it if there isn't one,
+/// so that other members still know its messages for its own after it restarts.
+fn load_or_create_member_key(path: &Path, member_id: &str) -> Result<SigKeyPair> {
+    let into_err = |e: io::Error| Error::MemberKeyIO(path.to_path_buf(), e);
+    if path.exists() {
+        let jwk = fs::read_to_string(path).map_err(into_err)?;
+        Ok(SigKeyPair::from_jwk(&jwk)?)
+    } else {
+        let key = SigKeyPair::generate_pair_for_origin(member_id);
+        atomic_write(path, key.to_jwk(PairType::Secret)?).map_err(into_err)?;
+        Ok(key)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1430,7 +1932,8 @@ mod tests {
                   initialized:   Default::default(),
                   pkg:           Default::default(),
                   cfg:           Default::default(),
-                  sys:           Default::default(), }
+                  sys:           Default::default(),
+                  healthy:       true, }
     }
 
     #[test]
@@ -1696,7 +2199,9 @@ mod tests {
             fn suitability_for_msr(&self, _service_group: &str) -> u64 { 0 }
         }
 
-        fn start_server() -> Server {
+        fn start_server() -> Server { start_server_with_ring_key(None) }
+
+        fn start_server_with_ring_key(ring_key: Option<SymKey>) -> Server {
             let swim_port;
             {
                 let mut swim_port_guard = SWIM_PORT.lock().expect("SWIM_PORT poisoned");
@@ -1717,7 +2222,7 @@ mod tests {
             Server::new(swim_listen,
                         gossip_listen,
                         member,
-                        None,
+                        ring_key,
                         None,
                         None,
                         Arc::new(ZeroSuitability)).unwrap()
@@ -1766,6 +2271,63 @@ mod tests {
                   .expect("Server failed to start");
         }
 
+        #[test]
+        fn members_are_only_known_by_signing_keys_proven_with_the_ring_key() {
+            let ring_key = SymKey::generate_pair_for_ring("beyonce");
+            let server = start_server_with_ring_key(Some(ring_key.clone()));
+
+            let mut unproven = Member::default();
+            unproven.signing_key = Some(vec![1; 32]);
+            server.insert_member_mlw_rhw(unproven.clone(), Health::Alive);
+            assert_eq!(server.member_list.signing_key_mlr(&unproven.id), None);
+
+            let mut proven = Member::default();
+            proven.signing_key = Some(vec![1; 32]);
+            proven.key_proof = Some(SigningKeyProof::new(&ring_key, &proven.id, &[1; 32]).unwrap());
+            server.insert_member_mlw_rhw(proven.clone(), Health::Alive);
+            assert_eq!(server.member_list.signing_key_mlr(&proven.id), Some(vec![1; 32]));
+        }
+
+        #[test]
+        fn a_ring_key_holder_can_not_re_key_another_member() {
+            let ring_key = SymKey::generate_pair_for_ring("beyonce");
+            let server = start_server_with_ring_key(Some(ring_key.clone()));
+
+            let mut member = Member::default();
+            member.signing_key = Some(vec![1; 32]);
+            member.key_proof = Some(SigningKeyProof::new(&ring_key, &member.id, &[1; 32]).unwrap());
+            server.insert_member_mlw_rhw(member.clone(), Health::Alive);
+
+            let mut impostor = member.clone();
+            impostor.incarnation = Incarnation::from(10);
+            impostor.signing_key = Some(vec![2; 32]);
+            impostor.key_proof =
+                Some(SigningKeyProof::new(&ring_key, &member.id, &[2; 32]).unwrap());
+            server.insert_member_mlw_rhw(impostor, Health::Alive);
+            assert_eq!(server.member_list.signing_key_mlr(&member.id), Some(vec![1; 32]));
+        }
+
+        #[test]
+        fn every_copy_of_a_pending_ring_command_is_offered() {
+            let server = start_server();
+            let expires_at = ring_health::now() + 300;
+            let genuine =
+                RingCommand::new("cmd", "member-a", "SvcUpdate", b"genuine".to_vec(), expires_at);
+            let forged =
+                RingCommand::new("cmd", "member-b", "SvcUpdate", b"forged".to_vec(), expires_at);
+            server.insert_ring_command_rsw_rhw(forged.clone());
+            server.insert_ring_command_rsw_rhw(genuine.clone());
+
+            let pending = server.pending_ring_commands_rsr();
+            assert_eq!(pending.len(), 1);
+            assert_eq!(pending[0].len(), 2);
+            assert!(pending[0].contains(&genuine));
+            assert!(pending[0].contains(&forged));
+
+            server.acknowledge_ring_command_rsw_rhw(&genuine, Ok("updated".to_string()));
+            assert!(server.pending_ring_commands_rsr().is_empty());
+        }
+
         #[test]
         fn start_listener() {
             let mut server = start_server();
@@ -1,6 +1,7 @@
 //! Periodically check membership rumors to automatically "time out"
 //! `Suspect` rumors to `Confirmed`, and `Confirmed` rumors to
-//! `Departed`.
+//! `Departed`, and forget ring commands which have expired along with
+//! members which departed long enough ago.
 
 use crate::{rumor::{RumorKey,
                     RumorType},
@@ -19,6 +20,15 @@ pub fn spawn_thread(name: String, server: Server, timing: Timing) -> std::io::Re
 }
 
 fn run_loop(server: &Server, timing: &Timing) -> ! {
+    habitat_core::env_config_duration!(
+        /// How long departed members, and their services, are kept in the census before they are
+        /// forgotten. Autoscaled fleets otherwise accumulate them indefinitely.
+        DepartedMemberRetention,
+        HAB_DEPARTED_MEMBER_RETENTION_SECS => from_secs,
+        Duration::from_secs(72 * 60 * 60));
+
+    let departed_member_retention: Duration = DepartedMemberRetention::configured_value().into();
+
     loop {
         liveliness_checker::mark_thread_alive().and_divergent();
 
@@ -41,6 +51,12 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
                   .start_hot_rumor(RumorKey::new(RumorType::Member, &id, ""));
         }
 
+        server.expire_ring_commands_rsw();
+
+        for id in server.forget_departed_members_mlw_rsw_rhw(departed_member_retention) {
+            debug!("Forgot departed member {}", id);
+        }
+
         thread::sleep(Duration::from_millis(LOOP_DELAY_MS));
     }
 }
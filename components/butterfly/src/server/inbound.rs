@@ -4,6 +4,7 @@
 
 use super::AckSender;
 use crate::{member::Health,
+            message::Signed,
             server::{outbound,
                      Server},
             swim::{Ack,
@@ -56,8 +57,9 @@ pub fn run_loop(server: &Server, socket: &UdpSocket, tx_outbound: &AckSender) ->
 
         match socket.recv_from(&mut recv_buffer[..]) {
             Ok((length, addr)) => {
-                let swim_payload = match server.unwrap_wire(&recv_buffer[0..length]) {
-                    Ok(swim_payload) => swim_payload,
+                let unwrapped = server.unwrap_wire(&recv_buffer[0..length]);
+                let (swim_payload, signed) = match unwrapped {
+                    Ok(unwrapped) => unwrapped,
                     Err(e) => {
                         // NOTE: In the future, we might want to block people who send us
                         // garbage all the time.
@@ -85,6 +87,17 @@ pub fn run_loop(server: &Server, socket: &UdpSocket, tx_outbound: &AckSender) ->
                     }
                 };
 
+                if !is_swim_sender_authentic_mlr(server, &msg, signed.as_ref()) {
+                    warn!("Not processing {} message which is not signed by the member it is \
+                           from",
+                          msg.kind.as_str());
+                    let label_values = &["unauthenticated", "failure"];
+                    SWIM_BYTES_RECEIVED.with_label_values(label_values)
+                                       .set(bytes_received.to_i64());
+                    SWIM_MESSAGES_RECEIVED.with_label_values(label_values).inc();
+                    continue;
+                }
+
                 // Setting a label_values variable here throws errors about moving borrowed
                 // content that I couldn't solve w/o clones. Leaving this for now. I'm sure
                 // there's a better way.
@@ -94,6 +107,10 @@ pub fn run_loop(server: &Server, socket: &UdpSocket, tx_outbound: &AckSender) ->
                                       .inc();
 
                 trace!("SWIM Message: {:?}", msg);
+                if server.message_lost_sncr() {
+                    trace!("Dropping SWIM message lost to network conditions");
+                    continue;
+                }
                 match msg.kind {
                     SwimKind::Ping(ping) => {
                         if server.is_member_blocked_sblr(&ping.from.id) {
@@ -142,6 +159,26 @@ pub fn run_loop(server: &Server, socket: &UdpSocket, tx_outbound: &AckSender) ->
     }
 }
 
+/// Whether `msg` was signed by the member it says it is from, which may prove the key it signs
+/// with in the `Member` it sends. An Ack relayed to us in answer to a PingReq is signed by the
+/// member which relayed it rather than the one which sent it, so any member we know the key of may
+/// have signed it.
+///
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (read)
+fn is_swim_sender_authentic_mlr(server: &Server, msg: &Swim, signed: Option<&Signed>) -> bool {
+    let (from, relayed_to_us) = match &msg.kind {
+        SwimKind::Ping(ping) => (&ping.from, false),
+        SwimKind::Ack(ack) => {
+            let forward_to = ack.forward_to.as_ref().map(|member| member.id.as_str());
+            (&ack.from, forward_to == Some(server.member_id()))
+        }
+        SwimKind::PingReq(pingreq) => (&pingreq.from, false),
+    };
+    server.is_sender_authentic_mlr(&from.id, signed, Some(from))
+    || (relayed_to_us && signed.map_or(false, |signed| server.is_signed_by_member_mlr(signed)))
+}
+
 /// Process pingreq messages.
 ///
 /// # Locking (see locking.md)
@@ -206,6 +243,7 @@ fn process_ack_mlw_smw_rhw(server: &Server,
         return;
     }
     let memberships = msg.membership.clone();
+    server.record_membership_view_mlr(&msg.from.id, &memberships);
     match tx_outbound.send((addr, msg)) {
         Ok(()) => {
             for membership in memberships {
@@ -225,11 +263,13 @@ fn process_ping_mlw_smw_rhw(server: &Server, socket: &UdpSocket, addr: SocketAdd
     // Populate the member for this sender with its remote address
     msg.from.address = addr.ip().to_string();
     trace!("Ping from {}@{}", msg.from.id, addr);
+    let from_id = msg.from.id.clone();
     if msg.from.departed {
         server.insert_member_mlw_rhw(msg.from, Health::Departed);
     } else {
         server.insert_member_mlw_rhw(msg.from, Health::Alive);
     }
+    server.record_membership_view_mlr(&from_id, &msg.membership);
     for membership in msg.membership {
         server.insert_member_from_rumor_mlw_smw_rhw(membership.member, membership.health);
     }
@@ -307,6 +307,13 @@ pub fn populate_membership_rumors_mlr_rhw(server: &Server,
             swim.membership.push(member);
         }
     }
+    // Signing keys spread with the Member rumors sent over gossip, and every member sends its own
+    // along with its messages, so they're left off here to keep SWIM messages within the size
+    // members receive.
+    for membership in swim.membership.iter_mut() {
+        membership.member.signing_key = None;
+        membership.member.key_proof = None;
+    }
     // We don't want to update the heat for rumors that we know we are sending to a target that is
     // confirmed dead; the odds are, they won't receive them. Lets spam them a little harder with
     // rumors.
@@ -10,7 +10,8 @@ use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
 use prometheus::{IntCounterVec,
                  IntGaugeVec};
-use std::{thread,
+use std::{slice,
+          thread,
           time::Duration};
 
 lazy_static! {
@@ -68,8 +69,8 @@ fn run_loop(server: &Server) -> ! {
             }
         };
 
-        let payload = match server.unwrap_wire(&msg) {
-            Ok(payload) => payload,
+        let (payload, signed) = match server.unwrap_wire(&msg) {
+            Ok(unwrapped) => unwrapped,
             Err(e) => {
                 // NOTE: In the future, we might want to block people who send us
                 // garbage all the time.
@@ -96,6 +97,21 @@ fn run_loop(server: &Server) -> ! {
             }
         };
 
+        // Rumors we inject ourselves, with a client from `Server::client`, are signed by us
+        // whoever they say they're from.
+        let injected = signed.as_ref()
+                             .map_or(false, |signed| server.is_signed_by_myself(signed));
+        if !injected && !server.is_sender_authentic_mlr(&proto.from_id, signed.as_ref(), None) {
+            warn!("Not processing message from {} - it is not signed by that member",
+                  proto.from_id);
+            let label_values = &[&proto.r#type.to_string(), "unauthenticated", "unknown"];
+            GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
+                                 .set(payload.len().to_i64());
+            GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
+                                    .inc();
+            continue 'recv;
+        }
+
         let blocked = server.is_member_blocked_sblr(&proto.from_id);
         let blocked_label = if blocked { "true" } else { "false" };
         let label_values = &[&proto.r#type.to_string(), "success", blocked_label];
@@ -111,8 +127,14 @@ fn run_loop(server: &Server) -> ! {
             continue 'recv;
         }
 
+        if server.message_lost_sncr() {
+            trace!("Dropping gossip from {} lost to network conditions", proto.from_id);
+            continue 'recv;
+        }
+
         match proto.kind {
             RumorKind::Membership(membership) => {
+                server.record_membership_view_mlr(&proto.from_id, slice::from_ref(&membership));
                 server.insert_member_from_rumor_mlw_smw_rhw(membership.member, membership.health);
             }
             RumorKind::Service(service) => server.insert_service_rsw_mlw_rhw(*service),
@@ -131,6 +153,9 @@ fn run_loop(server: &Server) -> ! {
             RumorKind::Departure(departure) => {
                 server.insert_departure_rsw_mlw_rhw(departure);
             }
+            RumorKind::RingCommand(ring_command) => {
+                server.insert_ring_command_rsw_rhw(ring_command);
+            }
         }
     }
 }
@@ -0,0 +1,338 @@
+//! Analysis of the health of the ring as a whole, as seen from this
+//! member.
+//!
+//! SWIM converges on a single view of membership, so a partial
+//! partition rarely shows up directly in the member list. Instead it
+//! shows up as disagreement: peers keep refuting members that we
+//! cannot reach (and vice versa), and service groups on either side of
+//! the partition finish the same election term with different
+//! leaders. `RingHealth` records both kinds of disagreement as they
+//! are learned via gossip, and summarizes them in a
+//! `RingHealthReport`.
+
+use crate::member::Health;
+use std::{collections::HashMap,
+          fmt,
+          time::{SystemTime,
+                 UNIX_EPOCH}};
+
+habitat_core::env_config_int!(/// How long, in seconds, a disagreement between this member and its
+                              /// peers is considered relevant to the health of the ring.
+                              #[derive(Debug, Clone, Copy)]
+                              RingHealthWindow,
+                              u64,
+                              HAB_RING_HEALTH_WINDOW_SECS,
+                              300);
+
+/// The number of distinct members which must be reachable by our peers
+/// but not by us before we suspect that we have been partitioned from
+/// part of the ring, rather than simply having a flaky peer.
+const PARTITION_MEMBER_THRESHOLD: usize = 2;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum RingHealthStatus {
+    Healthy,
+    /// Peers disagree with us about the reachability of some members.
+    Degraded,
+    /// Either several members are reachable by our peers but not by us,
+    /// or an election term has been won by different leaders.
+    PartitionSuspected,
+}
+
+impl Default for RingHealthStatus {
+    fn default() -> Self { RingHealthStatus::Healthy }
+}
+
+impl fmt::Display for RingHealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            RingHealthStatus::Healthy => "healthy",
+            RingHealthStatus::Degraded => "degraded",
+            RingHealthStatus::PartitionSuspected => "partition-suspected",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// A peer reported a member with a health that disagrees with ours
+/// about whether the member is reachable.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ViewConflict {
+    pub member_id:   String,
+    /// The member that sent us the conflicting membership rumor.
+    pub reported_by: String,
+    pub local:       Health,
+    pub reported:    Health,
+    /// Seconds since the UNIX epoch at which the conflict was last
+    /// observed.
+    pub timestamp:   u64,
+}
+
+impl ViewConflict {
+    /// Whether the reporting peer can reach a member that we cannot.
+    pub fn is_unreachable_locally(&self) -> bool {
+        self.reported == Health::Alive && self.local != Health::Alive
+    }
+}
+
+/// A service group finished the same election term with more than one
+/// leader.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ElectionConflict {
+    pub service_group: String,
+    pub term:          u64,
+    /// The member ids of every leader seen for `term`, sorted.
+    pub leaders:       Vec<String>,
+    /// Seconds since the UNIX epoch at which the conflict was last
+    /// observed.
+    pub timestamp:     u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RingHealthReport {
+    pub status:             RingHealthStatus,
+    pub alive:              usize,
+    pub suspect:            usize,
+    pub confirmed:          usize,
+    pub departed:           usize,
+    pub view_conflicts:     Vec<ViewConflict>,
+    pub election_conflicts: Vec<ElectionConflict>,
+    /// Seconds since the UNIX epoch at which the report was generated.
+    pub generated_at:       u64,
+}
+
+impl RingHealthReport {
+    /// The members that peers report as alive but that we cannot reach,
+    /// sorted and without duplicates.
+    pub fn unreachable_members(&self) -> Vec<&str> {
+        let mut members: Vec<_> = self.view_conflicts
+                                      .iter()
+                                      .filter(|c| c.is_unreachable_locally())
+                                      .map(|c| c.member_id.as_str())
+                                      .collect();
+        members.sort();
+        members.dedup();
+        members
+    }
+}
+
+#[derive(Debug)]
+pub struct RingHealth {
+    /// Keyed by (member_id, reported_by)
+    view_conflicts:     HashMap<(String, String), ViewConflict>,
+    /// Keyed by service group
+    election_conflicts: HashMap<String, ElectionConflict>,
+    window:             u64,
+}
+
+impl Default for RingHealth {
+    fn default() -> Self { Self::new(RingHealthWindow::configured_value().into()) }
+}
+
+impl RingHealth {
+    /// Create a new `RingHealth` which forgets disagreements older than
+    /// `window` seconds.
+    pub fn new(window: u64) -> Self {
+        RingHealth { view_conflicts: HashMap::new(),
+                     election_conflicts: HashMap::new(),
+                     window }
+    }
+
+    /// Compare the `reported` health of `member_id` that we learned from
+    /// `reported_by` with our own `local` view of it.
+    pub fn record_view(&mut self,
+                       reported_by: &str,
+                       member_id: &str,
+                       local: Health,
+                       reported: Health) {
+        self.record_view_at(reported_by, member_id, local, reported, now());
+    }
+
+    fn record_view_at(&mut self,
+                      reported_by: &str,
+                      member_id: &str,
+                      local: Health,
+                      reported: Health,
+                      timestamp: u64) {
+        let key = (member_id.to_string(), reported_by.to_string());
+        // Departed is terminal and propagates regardless of
+        // reachability, and Suspect versus Confirmed is only a matter of
+        // timing; neither says anything about a partition.
+        let conflicting = local != Health::Departed
+                          && reported != Health::Departed
+                          && (local == Health::Alive) != (reported == Health::Alive);
+        if conflicting {
+            self.view_conflicts.insert(key,
+                                       ViewConflict { member_id: member_id.to_string(),
+                                                      reported_by: reported_by.to_string(),
+                                                      local,
+                                                      reported,
+                                                      timestamp });
+        } else {
+            self.view_conflicts.remove(&key);
+        }
+    }
+
+    /// Record that `service_group` finished election `term` with both
+    /// `leader` and `other_leader` as its winner.
+    pub fn record_election_conflict(&mut self,
+                                    service_group: &str,
+                                    term: u64,
+                                    leader: &str,
+                                    other_leader: &str) {
+        self.record_election_conflict_at(service_group, term, leader, other_leader, now());
+    }
+
+    fn record_election_conflict_at(&mut self,
+                                   service_group: &str,
+                                   term: u64,
+                                   leader: &str,
+                                   other_leader: &str,
+                                   timestamp: u64) {
+        let conflict = self.election_conflicts
+                           .entry(service_group.to_string())
+                           .or_insert_with(|| {
+                               ElectionConflict { service_group: service_group.to_string(),
+                                                  term,
+                                                  leaders: Vec::new(),
+                                                  timestamp }
+                           });
+        if conflict.term != term {
+            conflict.term = term;
+            conflict.leaders.clear();
+        }
+        conflict.leaders.push(leader.to_string());
+        conflict.leaders.push(other_leader.to_string());
+        conflict.leaders.sort();
+        conflict.leaders.dedup();
+        conflict.timestamp = timestamp;
+    }
+
+    /// Summarize the disagreements observed within the window, along
+    /// with the number of members we currently know in each health
+    /// state.
+    pub fn report(&mut self, health_counts: &HashMap<Health, usize>) -> RingHealthReport {
+        self.report_at(health_counts, now())
+    }
+
+    fn report_at(&mut self, health_counts: &HashMap<Health, usize>, now: u64) -> RingHealthReport {
+        let oldest = now.saturating_sub(self.window);
+        self.view_conflicts.retain(|_, c| c.timestamp >= oldest);
+        self.election_conflicts.retain(|_, c| c.timestamp >= oldest);
+
+        let mut view_conflicts: Vec<_> = self.view_conflicts.values().cloned().collect();
+        view_conflicts.sort_by(|a, b| {
+                          (&a.member_id, &a.reported_by).cmp(&(&b.member_id, &b.reported_by))
+                      });
+        let mut election_conflicts: Vec<_> = self.election_conflicts.values().cloned().collect();
+        election_conflicts.sort_by(|a, b| a.service_group.cmp(&b.service_group));
+
+        let count = |health| health_counts.get(&health).copied().unwrap_or(0);
+        let mut report = RingHealthReport { status: RingHealthStatus::Healthy,
+                                            alive: count(Health::Alive),
+                                            suspect: count(Health::Suspect),
+                                            confirmed: count(Health::Confirmed),
+                                            departed: count(Health::Departed),
+                                            view_conflicts,
+                                            election_conflicts,
+                                            generated_at: now };
+        report.status = if !report.election_conflicts.is_empty()
+                           || report.unreachable_members().len() >= PARTITION_MEMBER_THRESHOLD
+        {
+            RingHealthStatus::PartitionSuspected
+        } else if !report.view_conflicts.is_empty() {
+            RingHealthStatus::Degraded
+        } else {
+            RingHealthStatus::Healthy
+        };
+        report
+    }
+}
+
+/// Seconds since the UNIX epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+                     .map(|d| d.as_secs())
+                     .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts() -> HashMap<Health, usize> {
+        let mut counts = HashMap::new();
+        counts.insert(Health::Alive, 3);
+        counts.insert(Health::Suspect, 1);
+        counts
+    }
+
+    #[test]
+    fn agreement_is_healthy() {
+        let mut ring_health = RingHealth::new(60);
+        ring_health.record_view_at("b", "c", Health::Alive, Health::Alive, 100);
+        ring_health.record_view_at("b", "d", Health::Suspect, Health::Confirmed, 100);
+        ring_health.record_view_at("b", "e", Health::Departed, Health::Alive, 100);
+        let report = ring_health.report_at(&counts(), 100);
+        assert_eq!(report.status, RingHealthStatus::Healthy);
+        assert_eq!(report.alive, 3);
+        assert_eq!(report.suspect, 1);
+        assert_eq!(report.confirmed, 0);
+    }
+
+    #[test]
+    fn a_single_unreachable_member_is_degraded() {
+        let mut ring_health = RingHealth::new(60);
+        ring_health.record_view_at("b", "c", Health::Suspect, Health::Alive, 100);
+        ring_health.record_view_at("d", "c", Health::Suspect, Health::Alive, 100);
+        let report = ring_health.report_at(&counts(), 100);
+        assert_eq!(report.status, RingHealthStatus::Degraded);
+        assert_eq!(report.view_conflicts.len(), 2);
+        assert_eq!(report.unreachable_members(), vec!["c"]);
+    }
+
+    #[test]
+    fn several_unreachable_members_suspect_a_partition() {
+        let mut ring_health = RingHealth::new(60);
+        ring_health.record_view_at("b", "c", Health::Suspect, Health::Alive, 100);
+        ring_health.record_view_at("b", "d", Health::Confirmed, Health::Alive, 100);
+        let report = ring_health.report_at(&counts(), 100);
+        assert_eq!(report.status, RingHealthStatus::PartitionSuspected);
+        assert_eq!(report.unreachable_members(), vec!["c", "d"]);
+    }
+
+    #[test]
+    fn later_agreement_clears_a_conflict() {
+        let mut ring_health = RingHealth::new(60);
+        ring_health.record_view_at("b", "c", Health::Suspect, Health::Alive, 100);
+        ring_health.record_view_at("b", "c", Health::Alive, Health::Alive, 101);
+        let report = ring_health.report_at(&counts(), 101);
+        assert_eq!(report.status, RingHealthStatus::Healthy);
+    }
+
+    #[test]
+    fn conflicts_expire_outside_the_window() {
+        let mut ring_health = RingHealth::new(60);
+        ring_health.record_view_at("b", "c", Health::Alive, Health::Suspect, 100);
+        ring_health.record_election_conflict_at("redis.default", 3, "a", "b", 100);
+        assert_eq!(ring_health.report_at(&counts(), 160).status,
+                   RingHealthStatus::PartitionSuspected);
+        assert_eq!(ring_health.report_at(&counts(), 161).status,
+                   RingHealthStatus::Healthy);
+    }
+
+    #[test]
+    fn election_conflicts_collect_leaders_per_term() {
+        let mut ring_health = RingHealth::new(60);
+        ring_health.record_election_conflict_at("redis.default", 3, "b", "a", 100);
+        ring_health.record_election_conflict_at("redis.default", 3, "c", "a", 100);
+        let report = ring_health.report_at(&counts(), 100);
+        assert_eq!(report.status, RingHealthStatus::PartitionSuspected);
+        assert_eq!(report.election_conflicts[0].leaders, vec!["a", "b", "c"]);
+
+        ring_health.record_election_conflict_at("redis.default", 4, "d", "a", 100);
+        let report = ring_health.report_at(&counts(), 100);
+        assert_eq!(report.election_conflicts[0].term, 4);
+        assert_eq!(report.election_conflicts[0].leaders, vec!["a", "d"]);
+    }
+}
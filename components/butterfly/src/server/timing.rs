@@ -16,7 +16,7 @@ const DEPARTURE_TIMEOUT_DEFAULT_MS: u64 = 259_200_000;
 
 /// Collects important timing durations and timekeeping activities for
 /// the underlying gossip protocols.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Timing {
     ping:      Duration,
     pingreq:   Duration,
@@ -42,6 +42,19 @@ impl Default for Timing {
 }
 
 impl Timing {
+    /// The default timings with every protocol period and timeout, apart from the departure
+    /// timeout, multiplied by `factor`. Members probe and gossip less often, at the cost of taking
+    /// proportionally longer to detect failures and spread rumors.
+    pub fn slowed_by(factor: u32) -> Timing {
+        let default = Timing::default();
+        Timing { ping:                default.ping * factor,
+                 pingreq:             default.pingreq * factor,
+                 confirm:             default.confirm * factor,
+                 departure:           default.departure,
+                 gossip_interval:     default.gossip_interval * factor,
+                 swim_probe_interval: default.swim_probe_interval * factor, }
+    }
+
     /// How long a ping has to timeout.
     pub fn ping(&self) -> Duration { self.ping }
 
@@ -0,0 +1,229 @@
+//! A gossip network of many members in one process, for seeing how membership, elections and
+//! rumors spread at scale without provisioning machines.
+//!
+//! Every member is a full `Server` listening on its own pair of loopback ports, so the protocols
+//! run exactly as they do between Supervisors. Only the network between them is simulated:
+//! messages can be lost or held up with `NetworkConditions`, and the members can be split into
+//! partitions which can't hear each other until they are healed.
+
+use crate::{error::{Error,
+                    Result},
+            member::{Health,
+                     Member},
+            rumor::{election::ElectionStatus,
+                    service::{Service,
+                              SysInfo},
+                    service_config::ServiceConfig,
+                    ConstIdRumor as _,
+                    Election},
+            server::{timing::Timing,
+                     NetworkConditions,
+                     Server,
+                     Suitability}};
+use habitat_core::{package::PackageIdent,
+                   service::ServiceGroup};
+use std::{io,
+          net::{Ipv4Addr,
+                SocketAddr},
+          sync::Arc};
+
+/// Every simulated member is as suitable as any other to lead, so elections are decided by
+/// member ID.
+#[derive(Debug)]
+struct EqualSuitability;
+
+impl Suitability for EqualSuitability {
+    fn suitability_for_msr(&self, _service_group: &str) -> u64 { 0 }
+}
+
+/// The members of a simulated gossip network.
+pub struct SimulatedNetwork {
+    members:    Vec<Server>,
+    /// The partition each member is in, by index
+    partitions: Vec<usize>,
+}
+
+impl SimulatedNetwork {
+    /// Starts `count` members listening on loopback, taking two ports each from `first_port` up.
+    /// Every member joins through the first one, which is a permanent peer so that partitioned
+    /// members find their way back to each other once healed.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `MemberList::initial_members` (write)
+    /// * `MemberList::entries` (write)
+    /// * `Server::member` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn start_rsw_imlw_mlw_smw_rhw(count: usize,
+                                      first_port: u16,
+                                      timing: &Timing)
+                                      -> Result<Self> {
+        if u32::from(first_port) + 2 * count as u32 > u32::from(u16::MAX) + 1 {
+            let msg = format!("{} members don't fit in the ports from {}", count, first_port);
+            return Err(Error::CannotBind(io::Error::new(io::ErrorKind::AddrNotAvailable, msg)));
+        }
+        let mut members = Vec::with_capacity(count);
+        for i in 0..count {
+            let swim_port = first_port + 2 * i as u16;
+            let mut server = Server::new(local_addr(swim_port),
+                                         local_addr(swim_port + 1),
+                                         Member::default(),
+                                         None,
+                                         Some(format!("sim-{}", i)),
+                                         None,
+                                         Arc::new(EqualSuitability))?;
+            if i == 0 {
+                server.myself().lock_smw().set_persistent();
+            } else {
+                let mut seed = Member::default();
+                seed.address = Ipv4Addr::LOCALHOST.to_string();
+                seed.swim_port = first_port;
+                seed.gossip_port = first_port + 1;
+                server.member_list.add_initial_member_imlw(seed);
+            }
+            server.start_rsw_mlw_smw_rhw_msr(timing)?;
+            members.push(server);
+        }
+        Ok(SimulatedNetwork { members,
+                              partitions: vec![0; count] })
+    }
+
+    pub fn len(&self) -> usize { self.members.len() }
+
+    pub fn is_empty(&self) -> bool { self.members.is_empty() }
+
+    /// Has every member receive its traffic under `conditions`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::network_conditions` (write)
+    pub fn set_conditions_sncw(&self, conditions: NetworkConditions) {
+        for member in &self.members {
+            member.set_network_conditions_sncw(conditions);
+        }
+    }
+
+    /// Splits the members into `count` partitions of roughly equal size, taking them in turn, so
+    /// that members only hear from others in the same partition.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::block_list` (write)
+    pub fn partition_sblw(&mut self, count: usize) {
+        self.heal_sblw();
+        for (i, partition) in self.partitions.iter_mut().enumerate() {
+            *partition = i % count.max(1);
+        }
+        for (i, member) in self.members.iter().enumerate() {
+            for (j, other) in self.members.iter().enumerate() {
+                if self.partitions[i] != self.partitions[j] {
+                    member.add_to_block_list_sblw(other.member_id().to_string());
+                }
+            }
+        }
+    }
+
+    /// Puts all the members back in one network.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::block_list` (write)
+    pub fn heal_sblw(&mut self) {
+        for (i, member) in self.members.iter().enumerate() {
+            for (j, other) in self.members.iter().enumerate() {
+                if self.partitions[i] != self.partitions[j] {
+                    member.remove_from_block_list_sblw(other.member_id());
+                }
+            }
+        }
+        self.partitions.iter_mut().for_each(|p| *p = 0);
+    }
+
+    /// Whether every member sees the network as its partition does: the members it can hear from
+    /// alive, and the rest not.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn converged_mlr(&self) -> bool {
+        self.members.iter().enumerate().all(|(i, member)| {
+            self.members.iter().enumerate().all(|(j, other)| {
+                let alive = member.member_list.health_of_by_id_mlr(other.member_id())
+                            == Some(Health::Alive);
+                i == j || alive == (self.partitions[i] == self.partitions[j])
+            })
+        })
+    }
+
+    /// Has every member join `service_group`, then has the first one start an election for its
+    /// leader.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `MemberList::entries` (write)
+    /// * `RumorHeat::inner` (write)
+    /// * `ManagerServices::inner` (read)
+    pub fn start_election_rsw_mlw_rhw_msr(&self, service_group: &ServiceGroup) {
+        let ident = PackageIdent::new("sim", service_group.service(), Some("0.1.0"), Some("1"));
+        for member in &self.members {
+            member.insert_service_rsw_mlw_rhw(Service::new(member.member_id(),
+                                                           &ident,
+                                                           service_group.clone(),
+                                                           SysInfo::default(),
+                                                           None));
+        }
+        if let Some(first) = self.members.first() {
+            first.start_election_rsw_mlr_rhw_msr(service_group, 0);
+        }
+    }
+
+    /// The leader of `service_group`, once every member agrees on who it is.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn elected_rsr(&self, service_group: &ServiceGroup) -> Option<String> {
+        let mut leaders = self.members.iter().map(|member| {
+            member.election_store
+                  .lock_rsr()
+                  .service_group(service_group)
+                  .map_rumor(Election::const_id(), |election| {
+                      if election.status == ElectionStatus::Finished {
+                          Some(election.member_id.clone())
+                      } else {
+                          None
+                      }
+                  })
+                  .flatten()
+        });
+        let leader = leaders.next().flatten()?;
+        if leaders.all(|other| other.as_ref() == Some(&leader)) {
+            Some(leader)
+        } else {
+            None
+        }
+    }
+
+    /// Starts a configuration rumor for `service_group` from the first member, returning it so
+    /// its spread can be followed with `count_with_rumor_rsr`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn spread_rumor_rsw_rhw(&self, service_group: &ServiceGroup) -> Option<ServiceConfig> {
+        let first = self.members.first()?;
+        let rumor = ServiceConfig::new(first.member_id(),
+                                       service_group.clone(),
+                                       b"simulated = true".to_vec());
+        first.insert_service_config_rsw_rhw(rumor.clone());
+        Some(rumor)
+    }
+
+    /// How many members have heard `rumor`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn count_with_rumor_rsr(&self, rumor: &ServiceConfig) -> usize {
+        self.members
+            .iter()
+            .filter(|member| member.service_config_store.lock_rsr().contains_rumor(rumor))
+            .count()
+    }
+}
+
+fn local_addr(port: u16) -> SocketAddr { (Ipv4Addr::LOCALHOST, port).into() }
@@ -1,6 +1,10 @@
 use crate::btest;
 use habitat_butterfly::member::Health;
-use habitat_core::crypto::keys::sym_key::SymKey;
+use habitat_core::{clock::{self,
+                          ManualClock},
+                   crypto::keys::{ring_key,
+                                  sym_key::SymKey}};
+use std::time::Duration;
 
 #[test]
 fn symmetric_encryption_of_wire_payloads() {
@@ -15,3 +19,42 @@ fn symmetric_encryption_of_wire_payloads() {
                   .service_group("beast.prod")
                   .contains_id(net[0].member_id()));
 }
+
+#[test]
+fn ring_key_rotation_without_restart() {
+    let ring_key = SymKey::generate_pair_for_ring("wolverine");
+    let mut net = btest::SwimNet::new_ring_encryption_rhw(2, &ring_key);
+    net.connect_smr(0, 1);
+    assert_wait_for_health_of_mlr!(net, [0..2, 0..2], Health::Alive);
+
+    // Revisions are timestamps, so the next one can't be generated within the same second
+    let next = {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        clock.advance(Duration::from_secs(1));
+        ring_key::generate_next_revision(&ring_key).unwrap()
+    };
+    for server in 0..2 {
+        net[server].rotate_ring_key_smw(|keys| keys.stage(next.clone()))
+                   .unwrap();
+    }
+    // Only one member has activated the new revision, but both can still talk
+    net[0].rotate_ring_key_smw(|keys| keys.activate(&next.name_with_rev()))
+          .unwrap();
+    net.add_service(0, "core/beast/1.2.3/20161208121212");
+    net.wait_for_gossip_rounds(2);
+    assert!(net[1].service_store
+                  .lock_rsr()
+                  .service_group("beast.prod")
+                  .contains_id(net[0].member_id()));
+
+    net[1].rotate_ring_key_smw(|keys| keys.activate(&next.name_with_rev()))
+          .unwrap();
+    for server in 0..2 {
+        net[server].rotate_ring_key_smw(|keys| Ok(keys.retire()))
+                   .unwrap();
+        assert_eq!(net[server].ring_key().unwrap().name_with_rev(),
+                   next.name_with_rev());
+    }
+    assert_wait_for_health_of_mlr!(net, [0..2, 0..2], Health::Alive);
+}
@@ -29,7 +29,8 @@ fn service_config_via_client() {
     client.send_service_config(ServiceGroup::new("witcher", "prod", None).unwrap(),
                                0,
                                payload,
-                               false)
+                               false,
+                               None)
           .expect("Cannot send the service configuration");
     net.wait_for_gossip_rounds(1);
     assert!(net[1].service_config_store
@@ -31,7 +31,8 @@ fn service_file_via_client() {
                              "devil-wears-prada.txt",
                              0,
                              payload,
-                             false)
+                             false,
+                             None)
           .expect("Cannot send the service file");
     net.wait_for_gossip_rounds(1);
     assert!(net[1].service_file_store
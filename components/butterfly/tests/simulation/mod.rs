@@ -0,0 +1,43 @@
+use habitat_butterfly::{server::{timing::Timing,
+                                 NetworkConditions},
+                        simulation::SimulatedNetwork};
+use habitat_core::service::ServiceGroup;
+use std::{thread,
+          time::{Duration,
+                 Instant}};
+
+/// Well clear of the ports `SwimNet` hands out
+const FIRST_PORT: u16 = 46666;
+
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < TIMEOUT {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    false
+}
+
+#[test]
+fn simulated_network_converges_through_loss_and_a_partition() {
+    let mut net =
+        SimulatedNetwork::start_rsw_imlw_mlw_smw_rhw(6, FIRST_PORT, &Timing::default()).unwrap();
+    net.set_conditions_sncw(NetworkConditions { loss:    0.1,
+                                                latency: Duration::from_millis(5), });
+    assert!(wait_for(|| net.converged_mlr()));
+
+    net.partition_sblw(2);
+    assert!(wait_for(|| net.converged_mlr()));
+    net.heal_sblw();
+    assert!(wait_for(|| net.converged_mlr()));
+
+    let service_group = ServiceGroup::new("witcher", "prod", None).unwrap();
+    let rumor = net.spread_rumor_rsw_rhw(&service_group).unwrap();
+    assert!(wait_for(|| net.count_with_rumor_rsr(&rumor) == net.len()));
+    net.start_election_rsw_mlw_rhw_msr(&service_group);
+    assert!(wait_for(|| net.elected_rsr(&service_group).is_some()));
+}
@@ -66,6 +66,12 @@ pub fn file_into_idents(path: &str) -> Result<Vec<PackageIdent>, habitat_core::e
                 habitat_core::error::Error::FileNotFound(format!("Could not open file {}", path))
             })?;
 
+    str_into_idents(&s)
+}
+
+/// Parse newline separated package identifiers, ignoring blank lines
+/// and `#` comments.
+pub fn str_into_idents(s: &str) -> Result<Vec<PackageIdent>, habitat_core::error::Error> {
     s.lines().filter_map(line_to_ident).collect()
 }
 
@@ -99,4 +105,15 @@ mod tests {
 
         assert!(line_to_ident("core # not").unwrap().is_err());
     }
+
+    #[test]
+    fn test_str_into_idents() {
+        let input = "# release train\ncore/gzip/1.2.3/20200101000000\n\ncore/zlib # latest\n";
+        let idents = str_into_idents(input).unwrap();
+        assert_eq!(idents,
+                   vec![PackageIdent::from_str("core/gzip/1.2.3/20200101000000").unwrap(),
+                        PackageIdent::from_str("core/zlib").unwrap()]);
+
+        assert!(str_into_idents("core/gzip\ncore\n").is_err());
+    }
 }
@@ -14,19 +14,27 @@
 //!
 //! This would install the `3.0.1` version of redis.
 //!
+//! ```bash
+//! $ hab pkg install https://example.com/core-redis-3.0.1-20160614050524-x86_64-linux.hart
+//! ```
+//!
+//! Will download that artifact, verify its signature and install it.
+//!
 //! # Internals
 //!
 //! * Download the artifact
 //! * Verify it is un-altered
 //! * Unpack it
 
+use super::license::LicensePolicy;
 use crate::{api_client::{self,
                          BuilderAPIClient,
                          Client,
                          Error::APIError},
             error::{Error,
                     Result},
-            templating::hooks::{InstallHook,
+            templating::hooks::{HookSandbox,
+                                InstallHook,
                                 PackageMaintenanceHookExt},
             ui::{Status,
                  UIWriter}};
@@ -39,6 +47,7 @@ use habitat_core::{self,
                         AtomicWriter,
                         DEFAULT_CACHED_ARTIFACT_PERMISSIONS},
                    package::{list::temp_package_directory,
+                             ArtifactCache,
                              FullyQualifiedPackageIdent,
                              Identifiable,
                              PackageArchive,
@@ -53,7 +62,8 @@ use std::{convert::TryFrom,
                File},
           io::{self,
                BufRead,
-               BufReader},
+               BufReader,
+               Write},
           path::{Path,
                  PathBuf},
           pin::Pin,
@@ -302,7 +312,8 @@ pub async fn start<U>(ui: &mut U,
                       token: Option<&str>,
                       install_mode: &InstallMode,
                       local_package_usage: &LocalPackageUsage,
-                      install_hook_mode: InstallHookMode)
+                      install_hook_mode: InstallHookMode,
+                      license_policy: &LicensePolicy)
                       -> Result<PackageInstall>
     where U: UIWriter
 {
@@ -317,7 +328,8 @@ pub async fn start<U>(ui: &mut U,
                              fs_root_path,
                              artifact_cache_path,
                              key_cache_path,
-                             install_hook_mode };
+                             install_hook_mode,
+                             license_policy };
 
     match *install_source {
         InstallSource::Ident(ref ident, target) => {
@@ -343,7 +355,8 @@ pub fn type_erased_start<'a, U>(
     token: Option<&'a str>,
     install_mode: &'a InstallMode,
     local_package_usage: &'a LocalPackageUsage,
-    install_hook_mode: InstallHookMode)
+    install_hook_mode: InstallHookMode,
+    license_policy: &'a LicensePolicy)
     -> Pin<Box<dyn std::future::Future<Output = Result<PackageInstall>> + Send + 'a>>
     where U: UIWriter + Send + Sync
 {
@@ -358,7 +371,79 @@ pub fn type_erased_start<'a, U>(
                    token,
                    install_mode,
                    local_package_usage,
-                   install_hook_mode))
+                   install_hook_mode,
+                   license_policy))
+}
+
+/// Whether `s` is the URL of a Habitat Artifact to download and install, rather than a package
+/// identifier or the path of a local artifact.
+pub fn is_artifact_url(s: &str) -> bool {
+    reqwest::Url::parse(s).map_or(false, |url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Downloads the Habitat Artifact at `url` to the artifact cache and verifies its signature,
+/// returning the `InstallSource` which installs it. When the key cache doesn't have the public key
+/// of the signer named in the artifact's header, it is fetched from Builder at `bldr_url`, unless
+/// installing offline.
+///
+/// The download only takes its place in the artifact cache once it is verified.
+#[allow(clippy::too_many_arguments)]
+pub async fn fetch_remote_archive<U>(ui: &mut U,
+                                     url: &str,
+                                     bldr_url: &str,
+                                     product: &str,
+                                     version: &str,
+                                     fs_root_path: &Path,
+                                     artifact_cache_path: &Path,
+                                     token: Option<&str>,
+                                     install_mode: &InstallMode)
+                                     -> Result<InstallSource>
+    where U: UIWriter
+{
+    let download_failed =
+        |e: reqwest::Error| Error::DownloadFailed(format!("Unable to download {}: {}", url, e));
+    ui.status(Status::Downloading, url)?;
+    fs::create_dir_all(artifact_cache_path)?;
+    let mut download = tempfile::NamedTempFile::new_in(artifact_cache_path)?;
+    let mut response = reqwest::get(url).await
+                                        .and_then(reqwest::Response::error_for_status)
+                                        .map_err(download_failed)?;
+    let mut progress = ui.progress();
+    if let (Some(progress), Some(size)) = (progress.as_mut(), response.content_length()) {
+        progress.size(size);
+    }
+    while let Some(chunk) = response.chunk().await.map_err(download_failed)? {
+        download.write_all(&chunk)?;
+        if let Some(progress) = progress.as_mut() {
+            progress.write_all(&chunk)?;
+        }
+    }
+    if let Some(mut progress) = progress {
+        progress.finish();
+    }
+
+    let key_cache_path = cache_key_path(fs_root_path);
+    let signer = artifact::artifact_signer(download.path())?;
+    if SigKeyPair::get_public_key_path(&signer, &key_cache_path).is_err() {
+        if *install_mode == InstallMode::Offline {
+            return Err(Error::OfflineOriginKeyNotFound(signer));
+        }
+        ui.status(Status::Downloading,
+                  format!("{} public origin key", &signer))?;
+        let (name, rev) = parse_name_with_rev(&signer)?;
+        Client::new(bldr_url, product, version, Some(fs_root_path))?
+            .fetch_origin_key(&name, &rev, token, &key_cache_path, ui.progress())
+            .await?;
+        ui.status(Status::Cached, format!("{} public origin key", &signer))?;
+    }
+    artifact::verify(download.path(), &key_cache_path)?;
+    ui.status(Status::Verified, format!("{} signed with {}", url, &signer))?;
+
+    let ident = PackageArchive::new(download.path())?.ident()?;
+    let path =
+        artifact_cache_path.join(FullyQualifiedPackageIdent::try_from(&ident)?.archive_name());
+    download.persist(&path).map_err(|e| Error::IO(e.error))?;
+    Ok(path.to_string_lossy().parse()?)
 }
 
 pub async fn check_install_hooks<T, P>(ui: &mut T,
@@ -389,7 +474,10 @@ async fn run_install_hook_unless_already_successful<T>(ui: &mut T,
 {
     match read_install_hook_status(package.installed_path.join(InstallHook::STATUS_FILE))? {
         Some(0) => Ok(()),
-        _ => InstallHook::find_run_and_error_for_status(ui, package).await,
+        _ => {
+            let sandbox = HookSandbox::for_origin(&package.ident().origin)?;
+            InstallHook::find_run_and_error_for_status(ui, package, Some(sandbox)).await
+        }
     }
 }
 
@@ -421,6 +509,7 @@ struct InstallTask<'a> {
     artifact_cache_path: &'a Path,
     key_cache_path:      &'a Path,
     install_hook_mode:   InstallHookMode,
+    license_policy:      &'a LicensePolicy,
 }
 
 impl<'a> InstallTask<'a> {
@@ -630,6 +719,10 @@ impl<'a> InstallTask<'a> {
         // it does.
         artifacts_to_install.push(artifact);
 
+        if !self.license_policy.is_empty() {
+            self.check_licenses(ui, &dependencies, &mut artifacts_to_install)?;
+        }
+
         // Ensure all uninstalled artifacts get installed
         for artifact in artifacts_to_install.iter_mut() {
             self.unpack_artifact(ui, artifact)?;
@@ -649,6 +742,29 @@ impl<'a> InstallTask<'a> {
         PackageInstall::load(ident.as_ref(), Some(self.fs_root_path)).map_err(Error::from)
     }
 
+    /// Apply the license policy to the package being installed and its whole transitive
+    /// dependency set, including dependencies which are already installed.
+    fn check_licenses<T>(&self,
+                         ui: &mut T,
+                         dependencies: &[PackageIdent],
+                         artifacts_to_install: &mut [PackageArchive])
+                         -> Result<()>
+        where T: UIWriter
+    {
+        let mut licenses = Vec::with_capacity(dependencies.len() + 1);
+        for artifact in artifacts_to_install.iter_mut() {
+            licenses.push((artifact.ident()?, artifact.licenses()));
+        }
+        for dependency in dependencies {
+            if let Some(package) =
+                self.installed_package(&FullyQualifiedPackageIdent::try_from(dependency)?)
+            {
+                licenses.push((dependency.clone(), package.licenses()?));
+            }
+        }
+        self.license_policy.check(ui, &licenses)
+    }
+
     /// This ensures the identified package is in the local cache,
     /// verifies it, and returns a handle to the package's metadata.
     async fn get_cached_artifact<T>(&self,
@@ -658,12 +774,57 @@ impl<'a> InstallTask<'a> {
                                     -> Result<PackageArchive>
         where T: UIWriter
     {
-        if self.is_artifact_cached(&ident) {
+        let cached = self.is_artifact_cached(&ident);
+        if cached {
             debug!("Found {} in artifact cache, skipping remote download",
                    ident);
         } else if self.is_offline() {
             return Err(Error::OfflineArtifactNotFound(ident.as_ref().clone()));
-        } else if let Err(err) =
+        } else {
+            self.download_artifact(ui, (ident, target), token).await?;
+        }
+
+        let mut artifact = PackageArchive::new(self.cached_artifact_path(ident))?;
+        ui.status(Status::Verifying, artifact.ident()?)?;
+        let verified = self.verify_artifact(ui, ident, token, &mut artifact).await;
+        match verified {
+            // A cached artifact is verified again each time it is reused, and replaced if it has
+            // been corrupted since it was downloaded.
+            Err(Error::HabitatCore(habitat_core::Error::CryptoError(e)))
+                if cached && !self.is_offline() =>
+            {
+                ui.warn(format!("Cached artifact {} failed verification, downloading it again: \
+                                 {}",
+                                artifact.path.display(),
+                                e))?;
+                fs::remove_file(&artifact.path)?;
+                self.download_artifact(ui, (ident, target), token).await?;
+                artifact = PackageArchive::new(self.cached_artifact_path(ident))?;
+                self.verify_artifact(ui, ident, token, &mut artifact)
+                    .await?;
+            }
+            result => result?,
+        }
+
+        ArtifactCache::touch(&artifact.path)?;
+        let pruned = ArtifactCache::new(self.artifact_cache_path)
+            .prune(ArtifactCache::configured_max_size(), Some(&artifact.path))?;
+        if !pruned.is_empty() {
+            debug!("Pruned {} least recently used artifact(s) from the artifact cache",
+                   pruned.len());
+        }
+        Ok(artifact)
+    }
+
+    /// Downloads the identified package into the artifact cache, retrying on failure.
+    async fn download_artifact<T>(&self,
+                                  ui: &mut T,
+                                  (ident, target): (&FullyQualifiedPackageIdent, PackageTarget),
+                                  token: Option<&str>)
+                                  -> Result<()>
+        where T: UIWriter
+    {
+        if let Err(err) =
             retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES),
                                  self.fetch_artifact(ui, (ident, target), token)).await
         {
@@ -672,12 +833,7 @@ impl<'a> InstallTask<'a> {
                                                       Last error was: {}",
                                                      RETRIES, ident, err)));
         }
-
-        let mut artifact = PackageArchive::new(self.cached_artifact_path(ident))?;
-        ui.status(Status::Verifying, artifact.ident()?)?;
-        self.verify_artifact(ui, ident, token, &mut artifact)
-            .await?;
-        Ok(artifact)
+        Ok(())
     }
 
     /// Adapter function wrapping `PackageArchive::unpack`
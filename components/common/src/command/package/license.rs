@@ -0,0 +1,137 @@
+//! License policy applied when installing packages.
+//!
+//! A policy is a list of denied licenses together with what to do when a package being
+//! installed, or any of its transitive dependencies, is released under one of them. Licenses are
+//! compared case-insensitively against the identifiers recorded in each package's `LICENSE`
+//! metafile (or, for older packages, its `MANIFEST`), so they should be given as the same SPDX
+//! identifiers plans use in `pkg_license` (ex: `AGPL-3.0`).
+
+use crate::{error::{Error,
+                    Result},
+            ui::UIWriter};
+use habitat_core::package::PackageIdent;
+use std::{fmt,
+          str::FromStr};
+
+/// What happens when a package is released under a denied license.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LicensePolicyMode {
+    /// Report the offending packages, but install them anyway
+    Warn,
+    /// Refuse to install anything if any package is under a denied license
+    Enforce,
+}
+
+impl Default for LicensePolicyMode {
+    fn default() -> Self { LicensePolicyMode::Enforce }
+}
+
+impl LicensePolicyMode {
+    pub const VARIANTS: &'static [&'static str] = &["warn", "enforce"];
+}
+
+impl FromStr for LicensePolicyMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "warn" => Ok(LicensePolicyMode::Warn),
+            "enforce" => Ok(LicensePolicyMode::Enforce),
+            _ => Err(Error::InvalidLicensePolicyMode(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LicensePolicyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicensePolicyMode::Warn => f.write_str("warn"),
+            LicensePolicyMode::Enforce => f.write_str("enforce"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LicensePolicy {
+    denied: Vec<String>,
+    mode:   LicensePolicyMode,
+}
+
+impl LicensePolicy {
+    pub fn new(denied: Vec<String>, mode: LicensePolicyMode) -> Self {
+        LicensePolicy { denied, mode }
+    }
+
+    /// A policy which denies nothing has nothing to check.
+    pub fn is_empty(&self) -> bool { self.denied.is_empty() }
+
+    /// The denied licenses in `licenses`.
+    pub fn denied_in<'a>(&self, licenses: &'a [String]) -> Vec<&'a str> {
+        licenses.iter()
+                .filter(|l| self.denied.iter().any(|d| d.eq_ignore_ascii_case(l)))
+                .map(String::as_str)
+                .collect()
+    }
+
+    /// Check the licenses of every package about to be installed, reporting each package under a
+    /// denied license. When enforcing, any such package is an error.
+    pub fn check<T>(&self, ui: &mut T, packages: &[(PackageIdent, Vec<String>)]) -> Result<()>
+        where T: UIWriter
+    {
+        let mut violations = Vec::new();
+        for (ident, licenses) in packages {
+            let denied = self.denied_in(licenses);
+            if !denied.is_empty() {
+                let violation = format!("{} ({})", ident, denied.join(", "));
+                ui.warn(format!("{} is released under a denied license: {}",
+                                ident,
+                                denied.join(", ")))?;
+                violations.push(violation);
+            }
+        }
+        if violations.is_empty() || self.mode == LicensePolicyMode::Warn {
+            Ok(())
+        } else {
+            Err(Error::LicensePolicyViolation(violations))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::UI;
+
+    fn packages() -> Vec<(PackageIdent, Vec<String>)> {
+        vec![("core/zlib/1.2.11/20200305230542".parse().unwrap(), vec!["zlib".to_string()]),
+             ("acme/server/1.0.0/20200305230542".parse().unwrap(),
+              vec!["Apache-2.0".to_string(), "AGPL-3.0".to_string()]),]
+    }
+
+    #[test]
+    fn licenses_are_matched_case_insensitively() {
+        let policy = LicensePolicy::new(vec!["agpl-3.0".to_string()], LicensePolicyMode::Enforce);
+        let licenses = vec!["MIT".to_string(), "AGPL-3.0".to_string()];
+        assert_eq!(policy.denied_in(&licenses), vec!["AGPL-3.0"]);
+    }
+
+    #[test]
+    fn enforcing_fails_on_any_denied_dependency() {
+        let mut ui = UI::with_sinks();
+        let policy = LicensePolicy::new(vec!["AGPL-3.0".to_string()], LicensePolicyMode::Enforce);
+        match policy.check(&mut ui, &packages()) {
+            Err(Error::LicensePolicyViolation(violations)) => {
+                assert_eq!(violations,
+                           vec!["acme/server/1.0.0/20200305230542 (AGPL-3.0)".to_string()]);
+            }
+            other => panic!("Expected a license policy violation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn warning_does_not_fail() {
+        let mut ui = UI::with_sinks();
+        let policy = LicensePolicy::new(vec!["AGPL-3.0".to_string()], LicensePolicyMode::Warn);
+        assert!(policy.check(&mut ui, &packages()).is_ok());
+    }
+}
@@ -14,7 +14,8 @@ use std::{env,
           path::PathBuf,
           result,
           str,
-          string};
+          string,
+          time::Duration};
 
 pub const DEFAULT_ERROR_EXIT_CODE: i32 = 1;
 
@@ -78,11 +79,18 @@ pub enum Error {
         hook:          &'static str,
         error:         CommandExecutionError,
     },
+    HookInterpreterNotFound(PathBuf, String),
+    /// Occurs when a hook runs past its timeout and is killed.
+    HookTimedOut(&'static str, Duration),
     InvalidEventStreamToken(String),
+    InvalidLicensePolicyMode(String),
     /// Occurs when making lower level IO calls.
     IO(io::Error),
     /// Errors when joining paths :)
     JoinPathsError(env::JoinPathsError),
+    /// Occurs when packages to be installed are released under licenses denied by the license
+    /// policy.
+    LicensePolicyViolation(Vec<String>),
     MissingCLIInputError(String),
     NamedPipeTimeoutOnStart(String, String, io::Error),
     NativeTls(native_tls::Error),
@@ -175,11 +183,29 @@ impl fmt::Display for Error {
                                 ref error, } => {
                 format!("{} {} hook failed: {}", package_ident, hook, error)
             }
+            Error::HookInterpreterNotFound(ref hook, ref interpreter) => {
+                format!("Unable to find the interpreter '{}' declared by {} in the service's PATH",
+                        interpreter,
+                        hook.display())
+            }
+            Error::HookTimedOut(hook, timeout) => {
+                format!("{} hook did not finish within {}s and was killed",
+                        hook,
+                        timeout.as_secs())
+            }
             Error::InvalidEventStreamToken(ref s) => {
                 format!("Invalid event stream token provided: '{}'", s)
             }
+            Error::InvalidLicensePolicyMode(ref s) => {
+                format!("Invalid license policy '{}', expected 'warn' or 'enforce'",
+                        s)
+            }
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
+            Error::LicensePolicyViolation(ref packages) => {
+                format!("Refusing to install packages released under a denied license: {}",
+                        packages.join(", "))
+            }
             Error::NamedPipeTimeoutOnStart(ref group, ref hook, ref err) => {
                 format!("Unable to start powershell named pipe for {} hook of {}: {}",
                         hook, group, err)
@@ -17,7 +17,17 @@
 //! on the tin :) JSON-formatted output emits this information as a
 //! JSON object. It ignores the coloring option, and does _not_ ever log
 //! with ANSI color codes, but does honor the verbose flag.
+//!
+//! Lines printed with `println` and `eprintln` can also be redirected to
+//! the platform's native log facility with `set_log_sink`; see the
+//! `sink` module.
+
+mod sink;
+
+pub use self::sink::{set_log_sink,
+                     LogSink};
 
+use self::sink::Severity;
 use crate::PROGRAM_NAME;
 use serde::{ser::SerializeMap,
             Serialize,
@@ -119,13 +129,31 @@ impl<'a> StructuredOutput<'a> {
     }
 
     pub fn println(&self) -> io::Result<()> {
+        if self.write_to_sink(Severity::Info) {
+            return Ok(());
+        }
         self.println_to_writer(&BufferWriter::stdout(self.format.color_choice()))
     }
 
     pub fn eprintln(&self) -> io::Result<()> {
+        if self.write_to_sink(Severity::Error) {
+            return Ok(());
+        }
         self.println_to_writer(&BufferWriter::stderr(self.format.color_choice()))
     }
 
+    /// Returns `true` if the output was handled by a log sink. If the sink fails we fall back to
+    /// stdout or stderr rather than lose the line.
+    fn write_to_sink(&self, severity: Severity) -> bool {
+        match sink::write(self, severity) {
+            Ok(written) => written,
+            Err(e) => {
+                debug!("Unable to write output to log sink: {}", e);
+                false
+            }
+        }
+    }
+
     fn print_to_writer(&self, writer: &BufferWriter) -> io::Result<()> {
         let mut buffer = writer.buffer();
         self.format(&mut buffer)?;
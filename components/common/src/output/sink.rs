@@ -0,0 +1,149 @@
+//! Alternate destinations for `StructuredOutput`.
+//!
+//! By default output is written to stdout and stderr, but when running under an init system it is
+//! often more useful to hand each line directly to the platform's native log facility, so that
+//! the preamble and logkey are preserved as structured data rather than being flattened into
+//! text.
+
+use super::StructuredOutput;
+use std::{fmt,
+          io,
+          result,
+          str::FromStr,
+          sync::Mutex};
+
+#[cfg(windows)]
+mod event_log;
+#[cfg(target_os = "linux")]
+mod journald;
+
+lazy_static! {
+    static ref SINK: Mutex<Option<Sink>> = Mutex::new(None);
+}
+
+/// Where Supervisor output should be written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSink {
+    Stdout,
+    #[cfg(target_os = "linux")]
+    Journald,
+    #[cfg(windows)]
+    EventLog,
+}
+
+impl LogSink {
+    #[cfg(target_os = "linux")]
+    pub const VARIANTS: &'static [&'static str] = &["stdout", "journald"];
+    #[cfg(windows)]
+    pub const VARIANTS: &'static [&'static str] = &["stdout", "eventlog"];
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub const VARIANTS: &'static [&'static str] = &["stdout"];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogSink::Stdout => "stdout",
+            #[cfg(target_os = "linux")]
+            LogSink::Journald => "journald",
+            #[cfg(windows)]
+            LogSink::EventLog => "eventlog",
+        }
+    }
+}
+
+impl Default for LogSink {
+    fn default() -> Self { LogSink::Stdout }
+}
+
+impl FromStr for LogSink {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(LogSink::Stdout),
+            #[cfg(target_os = "linux")]
+            "journald" => Ok(LogSink::Journald),
+            #[cfg(windows)]
+            "eventlog" => Ok(LogSink::EventLog),
+            _ => {
+                Err(format!("Invalid log sink '{}', expected one of: {}",
+                            s,
+                            LogSink::VARIANTS.join(", ")))
+            }
+        }
+    }
+}
+
+impl fmt::Display for LogSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
+/// Whether a line of output is informational or reports a problem. This maps to the priority or
+/// event type of the underlying log facility.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Severity {
+    Info,
+    Error,
+}
+
+enum Sink {
+    #[cfg(target_os = "linux")]
+    Journald(journald::Journal),
+    #[cfg(windows)]
+    EventLog(event_log::EventLog),
+}
+
+/// Route all subsequent output to `log_sink`. Selecting `LogSink::Stdout` restores the default
+/// behavior.
+///
+/// # Failures
+///
+/// * The native log facility cannot be reached
+pub fn set_log_sink(log_sink: LogSink) -> io::Result<()> {
+    let sink = match log_sink {
+        LogSink::Stdout => None,
+        #[cfg(target_os = "linux")]
+        LogSink::Journald => Some(Sink::Journald(journald::Journal::open()?)),
+        #[cfg(windows)]
+        LogSink::EventLog => Some(Sink::EventLog(event_log::EventLog::open()?)),
+    };
+    *SINK.lock().expect("SINK lock poisoned") = sink;
+    Ok(())
+}
+
+/// Write `output` to the configured sink, if there is one. Returns `false` when no sink is
+/// configured and the caller should write to stdout or stderr itself.
+pub(super) fn write(output: &StructuredOutput<'_>, severity: Severity) -> io::Result<bool> {
+    match *SINK.lock().expect("SINK lock poisoned") {
+        None => Ok(false),
+        #[cfg(target_os = "linux")]
+        Some(Sink::Journald(ref journal)) => journal.send(output, severity).map(|_| true),
+        #[cfg(windows)]
+        Some(Sink::EventLog(ref event_log)) => event_log.report(output, severity).map(|_| true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_sink_from_str_round_trips() {
+        for variant in LogSink::VARIANTS {
+            let sink = variant.parse::<LogSink>().unwrap();
+            assert_eq!(sink.as_str(), *variant);
+        }
+        assert!("syslog".parse::<LogSink>().is_err());
+    }
+
+    #[test]
+    fn log_sink_toml_deserialize() {
+        #[derive(Deserialize)]
+        struct Data {
+            sink: LogSink,
+        }
+        let data: Data = toml::from_str("sink = \"stdout\"").unwrap();
+        assert_eq!(data.sink, LogSink::Stdout);
+        assert!(toml::from_str::<Data>("sink = \"nope\"").is_err());
+    }
+}
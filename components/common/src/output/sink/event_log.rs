@@ -0,0 +1,107 @@
+//! Writes output to the Windows Event Log under an event source named after the program.
+//!
+//! Events are reported with the following IDs so that Supervisor and service messages can be
+//! told apart and filtered on in the Event Viewer:
+//!
+//! | ID   | Meaning                  |
+//! |------|--------------------------|
+//! | 1000 | Supervisor information   |
+//! | 1001 | Supervisor error         |
+//! | 2000 | Service information      |
+//! | 2001 | Service error            |
+
+use super::{super::StructuredOutput,
+            Severity};
+use crate::PROGRAM_NAME;
+use std::{ffi::OsStr,
+          io,
+          iter,
+          os::windows::ffi::OsStrExt,
+          ptr};
+use winapi::{shared::minwindef::WORD,
+             um::{winbase::{DeregisterEventSource,
+                            RegisterEventSourceW,
+                            ReportEventW},
+                  winnt::{EVENTLOG_ERROR_TYPE,
+                          EVENTLOG_INFORMATION_TYPE,
+                          HANDLE}}};
+
+const SUPERVISOR_INFO: u32 = 1000;
+const SUPERVISOR_ERROR: u32 = 1001;
+const SERVICE_INFO: u32 = 2000;
+const SERVICE_ERROR: u32 = 2001;
+
+pub struct EventLog {
+    handle: HANDLE,
+}
+
+// The handle returned by `RegisterEventSourceW` may be used from any thread, and all access to
+// it is serialized by the sink's mutex.
+unsafe impl Send for EventLog {}
+
+impl EventLog {
+    pub fn open() -> io::Result<Self> {
+        let source = to_wide(PROGRAM_NAME.as_str());
+        let handle = unsafe { RegisterEventSourceW(ptr::null(), source.as_ptr()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(EventLog { handle })
+    }
+
+    pub fn report(&self, output: &StructuredOutput<'_>, severity: Severity) -> io::Result<()> {
+        let message =
+            to_wide(&format!("{}({}): {}", output.preamble, output.logkey, output.content));
+        let mut strings = [message.as_ptr()];
+        let event_type: WORD = match severity {
+            Severity::Info => EVENTLOG_INFORMATION_TYPE,
+            Severity::Error => EVENTLOG_ERROR_TYPE,
+        };
+        let ok = unsafe {
+            ReportEventW(self.handle,
+                         event_type,
+                         0,
+                         event_id(output.preamble, severity),
+                         ptr::null_mut(),
+                         strings.len() as WORD,
+                         0,
+                         strings.as_mut_ptr(),
+                         ptr::null_mut())
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) { unsafe { DeregisterEventSource(self.handle) }; }
+}
+
+/// Output whose preamble is anything other than the program name comes from a service.
+fn event_id(preamble: &str, severity: Severity) -> u32 {
+    match (preamble == PROGRAM_NAME.as_str(), severity) {
+        (true, Severity::Info) => SUPERVISOR_INFO,
+        (true, Severity::Error) => SUPERVISOR_ERROR,
+        (false, Severity::Info) => SERVICE_INFO,
+        (false, Severity::Error) => SERVICE_ERROR,
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> { OsStr::new(s).encode_wide().chain(iter::once(0)).collect() }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_ids_distinguish_supervisor_and_service_output() {
+        assert_eq!(event_id(PROGRAM_NAME.as_str(), Severity::Info),
+                   SUPERVISOR_INFO);
+        assert_eq!(event_id(PROGRAM_NAME.as_str(), Severity::Error),
+                   SUPERVISOR_ERROR);
+        assert_eq!(event_id("redis.default", Severity::Info), SERVICE_INFO);
+        assert_eq!(event_id("redis.default", Severity::Error), SERVICE_ERROR);
+    }
+}
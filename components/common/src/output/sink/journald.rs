@@ -0,0 +1,111 @@
+//! Writes output to the systemd journal using its native protocol, which lets us attach the
+//! preamble and logkey as fields that can be filtered on with `journalctl`, e.g.
+//! `journalctl HAB_PREAMBLE=redis.default`.
+//!
+//! See https://systemd.io/JOURNAL_NATIVE_PROTOCOL/ for details of the protocol.
+
+use super::{super::{OutputContext,
+                    OutputVerbosityInternal,
+                    StructuredOutput},
+            Severity};
+use crate::PROGRAM_NAME;
+use std::{io,
+          os::unix::net::UnixDatagram};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+// Syslog priorities, as understood by journald.
+const PRIORITY_ERR: u8 = 3;
+const PRIORITY_INFO: u8 = 6;
+
+pub struct Journal {
+    socket: UnixDatagram,
+}
+
+impl Journal {
+    pub fn open() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(JOURNAL_SOCKET)?;
+        Ok(Journal { socket })
+    }
+
+    pub fn send(&self, output: &StructuredOutput<'_>, severity: Severity) -> io::Result<()> {
+        self.socket.send(&encode(output, severity)).map(|_| ())
+    }
+}
+
+fn encode(output: &StructuredOutput<'_>, severity: Severity) -> Vec<u8> {
+    let priority = match severity {
+        Severity::Info => PRIORITY_INFO,
+        Severity::Error => PRIORITY_ERR,
+    };
+    let mut buf = Vec::new();
+    append_field(&mut buf, "MESSAGE", output.content);
+    append_field(&mut buf, "PRIORITY", &priority.to_string());
+    append_field(&mut buf, "SYSLOG_IDENTIFIER", PROGRAM_NAME.as_str());
+    append_field(&mut buf, "HAB_PREAMBLE", output.preamble);
+    append_field(&mut buf, "HAB_LOGKEY", output.logkey);
+    if let OutputVerbosityInternal::Verbose(OutputContext { line, file, .. }) = output.verbosity {
+        append_field(&mut buf, "CODE_FILE", file);
+        append_field(&mut buf, "CODE_LINE", &line.to_string());
+    }
+    buf
+}
+
+/// Values which contain a newline can't use the simple `KEY=value` form and must instead be sent
+/// with an explicit little-endian length.
+fn append_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{OutputFormat,
+                        OutputVerbosity};
+
+    #[test]
+    fn simple_fields_use_key_equals_value() {
+        let mut buf = Vec::new();
+        append_field(&mut buf, "HAB_LOGKEY", "SR");
+        assert_eq!(buf, b"HAB_LOGKEY=SR\n");
+    }
+
+    #[test]
+    fn multiline_fields_are_length_prefixed() {
+        let mut buf = Vec::new();
+        append_field(&mut buf, "MESSAGE", "a\nb");
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn encode_includes_structured_fields() {
+        let so = StructuredOutput::new("redis.default",
+                                       "SR",
+                                       OutputContext { line:   7,
+                                                       file:   "manager.rs",
+                                                       column: 1, },
+                                       OutputFormat::NoColor,
+                                       OutputVerbosity::Verbose,
+                                       "Starting");
+        let encoded = String::from_utf8(encode(&so, Severity::Error)).unwrap();
+        let lines = encoded.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"MESSAGE=Starting"));
+        assert!(lines.contains(&"PRIORITY=3"));
+        assert!(lines.contains(&"HAB_PREAMBLE=redis.default"));
+        assert!(lines.contains(&"HAB_LOGKEY=SR"));
+        assert!(lines.contains(&"CODE_FILE=manager.rs"));
+        assert!(lines.contains(&"CODE_LINE=7"));
+    }
+}
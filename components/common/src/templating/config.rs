@@ -13,12 +13,14 @@ use serde::{Serialize,
 use std::{self,
           borrow::Cow,
           clone::Clone,
+          collections::HashMap,
           env,
           fs::File,
           io::prelude::*,
           path::{Path,
                  PathBuf},
-          result};
+          result,
+          sync::Mutex};
 
 static LOGKEY: &str = "CF";
 static ENV_VAR_PREFIX: &str = "HAB";
@@ -191,6 +193,14 @@ impl Cfg {
         self.gossip = Some(gossip);
     }
 
+    /// Returns the configuration with all of its layers merged, as templates see it.
+    pub fn merged(&self) -> toml::value::Table {
+        match toml::Value::try_from(&self).expect("Cfg -> TOML conversion") {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("Cfg serializes as a table"),
+        }
+    }
+
     /// Returns a subset of the overall configuration which intersects with the given package
     /// exports.
     pub fn to_exported(&self, pkg: &Pkg) -> Result<toml::value::Table> {
@@ -386,9 +396,32 @@ impl Serialize for Cfg {
     }
 }
 
+/// What compiling a service's configuration templates did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CfgCompileSummary {
+    /// Files written, because they didn't exist yet or their templates rendered to something new
+    pub written:   usize,
+    /// Files written again because they no longer held what their templates rendered to last
+    /// time, say after being edited or removed outside of the Supervisor
+    pub restored:  usize,
+    /// Files rendered to the same content they already had, and left alone
+    pub unchanged: usize,
+}
+
+impl CfgCompileSummary {
+    /// Returns `true` if any configuration file changed.
+    pub fn changed(&self) -> bool { self.written > 0 || self.restored > 0 }
+}
+
 #[derive(Debug)]
 /// Renders configuration templates into config files.
-pub struct CfgRenderer(TemplateRenderer);
+pub struct CfgRenderer {
+    templates: TemplateRenderer,
+    /// The hash of what each template was last rendered to, which tells files whose templates
+    /// rendered to something new from files which were changed outside of the Supervisor. Whether
+    /// a file is written is always down to what it holds.
+    rendered:  Mutex<HashMap<String, String>>,
+}
 
 impl CfgRenderer {
     /// Create a new `CfgRenderer` and load template files from a
@@ -396,24 +429,26 @@ impl CfgRenderer {
     pub fn new<T>(templates_path: T) -> Result<Self>
         where T: AsRef<Path>
     {
-        if templates_path.as_ref().is_dir() {
+        let templates = if templates_path.as_ref().is_dir() {
             load_templates(templates_path.as_ref(),
                            &PathBuf::new(),
-                           TemplateRenderer::new()).map(CfgRenderer)
+                           TemplateRenderer::new())?
         } else {
-            Ok(CfgRenderer(TemplateRenderer::new()))
-        }
+            TemplateRenderer::new()
+        };
+        Ok(CfgRenderer { templates,
+                         rendered: Mutex::new(HashMap::new()) })
     }
 
-    /// Compile and write all configuration files to the configuration directory.
-    ///
-    /// Returns `true` if the configuration has changed.
+    /// Compile and write all configuration files to the configuration directory. A file is only
+    /// written when its rendered content differs from what it holds, so a census change which
+    /// none of the templates use leaves the files, and the service, alone.
     pub fn compile<P, T>(&self,
                          service_group_name: &str,
                          pkg: &Pkg,
                          render_path: P,
                          ctx: &T)
-                         -> Result<bool>
+                         -> Result<CfgCompileSummary>
         where P: AsRef<Path>,
               T: Serialize
     {
@@ -422,11 +457,13 @@ impl CfgRenderer {
         // through this and pipe the service group through to let people know which service is
         // having issues and be more descriptive about what happened.
 
-        let mut changed = false;
-        for template in self.0.get_templates().keys() {
-            let compiled = self.0.render(&template, ctx)?;
+        let mut summary = CfgCompileSummary::default();
+        let mut rendered = self.rendered.lock().expect("Rendered templates lock is poisoned");
+        for template in self.templates.get_templates().keys() {
+            let compiled = self.templates.render(&template, ctx)?;
             let compiled_hash = crypto::hash::hash_string(&compiled);
             let cfg_dest = render_path.as_ref().join(&template);
+            let rendered_as_before = rendered.get(template) == Some(&compiled_hash);
             let file_hash = match crypto::hash::hash_file(&cfg_dest) {
                 Ok(file_hash) => file_hash,
                 Err(e) => {
@@ -434,7 +471,7 @@ impl CfgRenderer {
                     String::new()
                 }
             };
-            changed |= if file_hash.is_empty() {
+            if file_hash.is_empty() {
                 debug!("Configuration {} does not exist; restarting",
                        cfg_dest.display());
 
@@ -449,12 +486,26 @@ impl CfgRenderer {
                     cfg_dest.display()
                 );
 
-                true
+                if rendered_as_before {
+                    summary.restored += 1;
+                } else {
+                    summary.written += 1;
+                }
             } else if file_hash == compiled_hash {
                 debug!("Configuration {} {} has not changed; not restarting.",
                        cfg_dest.display(),
                        file_hash);
-                false
+                summary.unchanged += 1;
+            } else if rendered_as_before {
+                debug!("Configuration {} was changed outside of the Supervisor; restoring it",
+                       cfg_dest.display());
+                write_templated_file(&cfg_dest, &compiled, &pkg.svc_user, &pkg.svc_group)?;
+                outputln!(
+                    preamble service_group_name,
+                    "Restored configuration file {}, which was changed outside of the Supervisor",
+                    cfg_dest.display()
+                );
+                summary.restored += 1;
             } else {
                 debug!("Configuration {} has changed; restarting",
                        cfg_dest.display());
@@ -464,13 +515,48 @@ impl CfgRenderer {
                     "Modified configuration file {}",
                     cfg_dest.display()
                 );
-                true
-            };
+                summary.written += 1;
+            }
+            rendered.insert(template.clone(), compiled_hash);
+        }
+        Ok(summary)
+    }
+}
+
+/// Returns the dotted paths of the keys whose values differ between `old` and `new`, descending
+/// into tables present in both. Added and removed keys count as changed.
+pub fn changed_keys(old: &toml::value::Table, new: &toml::value::Table) -> Vec<String> {
+    let mut changed = Vec::new();
+    changed_keys_recurse(old, new, "", &mut changed);
+    changed
+}
+
+fn changed_keys_recurse(old: &toml::value::Table,
+                        new: &toml::value::Table,
+                        prefix: &str,
+                        changed: &mut Vec<String>) {
+    let removed = old.keys().filter(|key| !new.contains_key(*key));
+    for key in new.keys().chain(removed) {
+        let path = format!("{}{}", prefix, key);
+        match (old.get(key), new.get(key)) {
+            (Some(toml::Value::Table(old)), Some(toml::Value::Table(new))) => {
+                changed_keys_recurse(old, new, &format!("{}.", path), changed)
+            }
+            (old, new) if old != new => changed.push(path),
+            _ => {}
         }
-        Ok(changed)
     }
 }
 
+/// Returns whether changing the configuration key `key` changes `other`, because one of them is
+/// the other or contains it.
+pub fn keys_overlap(key: &str, other: &str) -> bool {
+    let contains = |outer: &str, inner: &str| {
+        inner.starts_with(outer) && inner[outer.len()..].starts_with('.')
+    };
+    key == other || contains(key, other) || contains(other, key)
+}
+
 // Recursively merges the `other` TOML table into `me`
 fn toml_merge(me: &mut toml::value::Table, other: &toml::value::Table) -> Result<()> {
     toml_merge_recurse(me, other, 0)
@@ -627,6 +713,38 @@ mod test {
                                })
     }
 
+    #[test]
+    fn changed_keys_are_dotted_paths_to_the_differing_values() {
+        let old = toml_from_str(
+                                r#"
+            port = 80
+            name = "web"
+            [tls]
+            cert = "a.pem"
+            key = "a.key"
+            "#,
+        );
+        let new = toml_from_str(
+                                r#"
+            port = 8080
+            [tls]
+            cert = "b.pem"
+            key = "a.key"
+            [log]
+            level = "debug"
+            "#,
+        );
+        assert_eq!(changed_keys(&old, &new),
+                   vec!["port", "tls.cert", "log", "name"]);
+        assert!(changed_keys(&new, &new).is_empty());
+
+        assert!(keys_overlap("tls", "tls.cert"));
+        assert!(keys_overlap("tls.cert", "tls"));
+        assert!(keys_overlap("port", "port"));
+        assert!(!keys_overlap("port", "ports"));
+        assert!(!keys_overlap("tls.cert", "tls.key"));
+    }
+
     #[test]
     fn merge_with_empty_me_table() {
         let mut me = toml_from_str("");
@@ -1159,4 +1277,61 @@ mod test {
         assert_eq!(file_content(deep_output_dir.join("config.txt")),
                    "config message is Hello");
     }
+
+    #[tokio::test]
+    async fn test_compile_skips_unchanged_output() {
+        let root = TempDir::new().expect("create temp dir");
+        let pkg_dir = root.path().join("pkg/testing/test");
+        fs::create_dir_all(&pkg_dir).expect("create pkg dir");
+        let pg_id = PackageIdent::new("testing", "test", Some("1.0.0"), Some("20170712000000"));
+        let pkg_install = PackageInstall::new_from_parts(pg_id,
+                                                         pkg_dir.clone(),
+                                                         pkg_dir.clone(),
+                                                         pkg_dir.clone());
+        create_with_content(pkg_dir.join("default.toml"), "message = \"Hello\"");
+        let config_dir = pkg_dir.join("config");
+        fs::create_dir_all(&config_dir).expect("create config dir");
+        create_with_content(config_dir.join("config.txt"), "config message is {{cfg.message}}");
+        let output_dir = root.path().join("output");
+        fs::create_dir_all(&output_dir).expect("create output dir");
+
+        let pkg = Pkg::from_install(&pkg_install).await.unwrap();
+        let cfg = Cfg::new(&pkg, None).unwrap();
+        let ctx = RenderContext::new(&pkg, &cfg);
+        let renderer = CfgRenderer::new(&config_dir).expect("create cfg renderer");
+
+        let summary = renderer.compile("test", &pkg, &output_dir, &ctx)
+                              .expect("compile");
+        assert_eq!(summary,
+                   CfgCompileSummary { written:   1,
+                                       restored:  0,
+                                       unchanged: 0, });
+        assert!(summary.changed());
+
+        let summary = renderer.compile("test", &pkg, &output_dir, &ctx)
+                              .expect("compile");
+        assert_eq!(summary,
+                   CfgCompileSummary { written:   0,
+                                       restored:  0,
+                                       unchanged: 1, });
+        assert!(!summary.changed());
+
+        // Files edited or removed from under the Supervisor are written again
+        create_with_content(output_dir.join("config.txt"), "config message is Goodbye");
+        let summary = renderer.compile("test", &pkg, &output_dir, &ctx)
+                              .expect("compile");
+        assert_eq!(summary,
+                   CfgCompileSummary { written:   0,
+                                       restored:  1,
+                                       unchanged: 0, });
+        assert_eq!(file_content(output_dir.join("config.txt")),
+                   "config message is Hello");
+
+        fs::remove_file(output_dir.join("config.txt")).expect("remove config file");
+        let summary = renderer.compile("test", &pkg, &output_dir, &ctx)
+                              .expect("compile");
+        assert!(summary.changed());
+        assert_eq!(file_content(output_dir.join("config.txt")),
+                   "config message is Hello");
+    }
 }
@@ -1,10 +1,16 @@
+mod sandbox;
+
+use self::sandbox::Watchdog;
+pub use self::sandbox::{HookSandbox,
+                        OriginTrust};
 use super::{package::Pkg,
             TemplateRenderer};
 use crate::{error::{Error,
                     Result},
             outputln,
             templating,
-            ui::{Status,
+            ui::{Glyph,
+                 Status,
                  UIWriter},
             FeatureFlag};
 #[cfg(windows)]
@@ -13,6 +19,7 @@ use habitat_core::os::process::windows_child::{Child,
 use habitat_core::{crypto,
                    fs,
                    fs::svc_hooks_path,
+                   os::process::interpreter::Interpreter,
                    package::PackageInstall,
                    util::BufReadLossy};
 use serde::{Serialize,
@@ -25,16 +32,38 @@ use std::process::{Child,
 use std::{ffi::OsStr,
           fmt,
           fs::File,
-          io::{prelude::*,
+          io::{self,
+               prelude::*,
                BufReader},
           path::{Path,
                  PathBuf},
-          result};
+          result,
+          thread,
+          time::Duration};
 
 #[cfg(not(windows))]
 pub const HOOK_PERMISSIONS: u32 = 0o755;
 static LOGKEY: &str = "HK";
 
+/// The interpreter declared by the hook at `path`, along with its executable found on the
+/// service's `PATH`.
+fn interpreter_for(path: &Path, pkg: &Pkg) -> Result<Option<(PathBuf, Interpreter)>> {
+    match Interpreter::from_hook(path)? {
+        Some(interpreter) => {
+            let paths = pkg.env.get("PATH").map(String::as_str).unwrap_or_default();
+            match interpreter.resolve(OsStr::new(paths)) {
+                Some(program) => Ok(Some((program, interpreter))),
+                None => {
+                    Err(Error::HookInterpreterNotFound(path.to_path_buf(),
+                                                       interpreter.program()
+                                                                  .to_string()))
+                }
+            }
+        }
+        None => Ok(None),
+    }
+}
+
 pub fn stdout_log_path<T>(package_name: &str) -> PathBuf
     where T: Hook
 {
@@ -199,49 +228,133 @@ pub trait Hook: fmt::Debug + Sized + Send {
               -> Result<Self::ExitValue>
         where T: ToString
     {
-        self.run_impl(service_group, pkg, svc_encrypted_password)
+        self.run_impl(service_group, pkg, svc_encrypted_password, None)
+    }
+
+    /// Run a compiled hook, killing it if it has not finished within `timeout`.
+    fn run_with_timeout<T>(&self,
+                           service_group: &str,
+                           pkg: &Pkg,
+                           svc_encrypted_password: Option<T>,
+                           timeout: Option<Duration>)
+                           -> Result<Self::ExitValue>
+        where T: ToString
+    {
+        self.run_impl(service_group, pkg, svc_encrypted_password, timeout)
     }
 
     fn run_impl<T>(&self,
                    service_group: &str,
                    pkg: &Pkg,
-                   svc_encrypted_password: Option<T>)
+                   svc_encrypted_password: Option<T>,
+                   timeout: Option<Duration>)
                    -> Result<Self::ExitValue>
         where T: ToString
     {
-        let mut child = Self::exec(self.path(), &pkg, svc_encrypted_password).map_err(|err| {
+        let mut child =
+            Self::exec(self.path(), &pkg, svc_encrypted_password, None).map_err(|err| {
+                outputln!(preamble service_group,
+                          "Hook failed to run, {}, {}", Self::FILE_NAME, err);
+                err
+            })?;
+        let watchdog = timeout.map(|timeout| (Watchdog::start(child.id(), timeout), timeout));
+        let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
+        hook_output.output_standard_streams::<Self>(service_group, &mut child);
+        let status = child.wait();
+        if let Some((watchdog, timeout)) = watchdog {
+            if watchdog.stop() {
+                outputln!(preamble service_group,
+                          "{} hook did not finish within {}s and was killed",
+                          Self::FILE_NAME,
+                          timeout.as_secs());
+                return Err(Error::HookTimedOut(Self::FILE_NAME, timeout));
+            }
+        }
+        Ok(status.map_err(|err| {
+                     outputln!(preamble service_group,
+                               "Hook failed to run, {}, {}", Self::FILE_NAME, err);
+                     err
+                 })
+                 .map(|status| self.handle_exit(pkg, &hook_output, status))?)
+    }
+
+    /// Run a compiled hook under the restrictions of `sandbox`.
+    ///
+    /// Unlike `run`, the hook's output is only written to its log files rather than being
+    /// interleaved with our own; it is up to the caller to report it.
+    fn run_sandboxed(&self,
+                     service_group: &str,
+                     pkg: &Pkg,
+                     sandbox: &HookSandbox)
+                     -> Result<Self::ExitValue> {
+        let pkg = sandbox.pkg(pkg);
+        let mut child = Self::exec(self.path(), &pkg, None::<&str>, Some(sandbox)).map_err(|err| {
                             outputln!(preamble service_group,
                                       "Hook failed to run, {}, {}", Self::FILE_NAME, err);
                             err
                         })?;
+        let watchdog = sandbox.watch(child.id());
         let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
-        hook_output.output_standard_streams::<Self>(service_group, &mut child);
-        Ok(child.wait()
-                .map_err(|err| {
-                    outputln!(preamble service_group,
-                              "Hook failed to run, {}, {}", Self::FILE_NAME, err);
-                    err
-                })
-                .map(|status| self.handle_exit(pkg, &hook_output, status))?)
+        hook_output.capture_standard_streams(&mut child);
+        let status = child.wait();
+        if watchdog.stop() {
+            outputln!(preamble service_group,
+                      "{} hook did not finish within {}s and was killed",
+                      Self::FILE_NAME,
+                      sandbox.timeout().as_secs());
+        }
+        Ok(status.map_err(|err| {
+                     outputln!(preamble service_group,
+                               "Hook failed to run, {}, {}", Self::FILE_NAME, err);
+                     err
+                 })
+                 .map(|status| self.handle_exit(&pkg, &hook_output, status))?)
     }
 
     #[cfg(windows)]
-    fn exec<T, S>(path: S, pkg: &Pkg, svc_encrypted_password: Option<T>) -> Result<Child>
+    fn exec<T, S>(path: S,
+                  pkg: &Pkg,
+                  svc_encrypted_password: Option<T>,
+                  _sandbox: Option<&HookSandbox>)
+                  -> Result<Child>
         where T: ToString,
               S: AsRef<OsStr>
     {
         use habitat_core::util;
 
-        let ps_cmd = format!("iex $(gc {} | out-string)", path.as_ref().to_string_lossy());
-        Ok(Child::spawn("pwsh.exe",
-                        &util::pwsh_args(ps_cmd.as_str()),
-                        &pkg.env.to_hash_map(),
-                        &pkg.svc_user,
-                        svc_encrypted_password)?)
+        let env = pkg.env.to_hash_map();
+        let hook = path.as_ref().to_string_lossy();
+        let ps_cmd = format!("iex $(gc {} | out-string)", hook);
+        let child = match interpreter_for(Path::new(path.as_ref()), pkg)? {
+            Some((program, interpreter)) if !interpreter.is_powershell() => {
+                let mut args: Vec<&str> = interpreter.arg().into_iter().collect();
+                args.push(&hook);
+                Child::spawn(&program.to_string_lossy(),
+                             &args,
+                             &env,
+                             &pkg.svc_user,
+                             svc_encrypted_password)?
+            }
+            Some((program, _)) => {
+                Child::spawn(&program.to_string_lossy(),
+                             &util::pwsh_args(ps_cmd.as_str()),
+                             &env,
+                             &pkg.svc_user,
+                             svc_encrypted_password)?
+            }
+            None => {
+                Child::spawn("pwsh.exe",
+                             &util::pwsh_args(ps_cmd.as_str()),
+                             &env,
+                             &pkg.svc_user,
+                             svc_encrypted_password)?
+            }
+        };
+        Ok(child)
     }
 
     #[cfg(unix)]
-    fn exec<T, S>(path: S, pkg: &Pkg, _: Option<T>) -> Result<Child>
+    fn exec<T, S>(path: S, pkg: &Pkg, _: Option<T>, sandbox: Option<&HookSandbox>) -> Result<Child>
         where T: ToString,
               S: AsRef<OsStr>
     {
@@ -269,7 +382,17 @@ pub trait Hook: fmt::Debug + Sized + Send {
             None
         };
 
-        let mut cmd = process::exec::unix::hook_command(path, pkg.env.deref(), ids);
+        let mut cmd = match interpreter_for(Path::new(path.as_ref()), pkg)? {
+            Some((program, interpreter)) => {
+                let mut cmd = process::exec::unix::hook_command(program, pkg.env.deref(), ids);
+                cmd.args(interpreter.arg()).arg(path.as_ref());
+                cmd
+            }
+            None => process::exec::unix::hook_command(path, pkg.env.deref(), ids),
+        };
+        if let Some(sandbox) = sandbox {
+            sandbox.restrict(&mut cmd, ids)?;
+        }
         Ok(cmd.spawn()?)
     }
 
@@ -308,8 +431,12 @@ pub trait PackageMaintenanceHookExt: Hook<ExitValue = ExitStatus> + Sync {
     ///   `templating::compile_for_package_install`)
     /// * run the hook
     /// * return an error if we get a non-zero exit code
+    ///
+    /// When a `sandbox` is given the hook is run under its restrictions, and the location of the
+    /// hook's output is reported once it finishes.
     async fn find_run_and_error_for_status<U: UIWriter>(ui: &mut U,
-                                                        package: &PackageInstall)
+                                                        package: &PackageInstall,
+                                                        sandbox: Option<HookSandbox>)
                                                         -> Result<()> {
         let feature_flags = FeatureFlag::from_env(ui);
         let package_name = &package.ident.name;
@@ -337,7 +464,20 @@ pub trait PackageMaintenanceHookExt: Hook<ExitValue = ExitStatus> + Sync {
             #[cfg(not(target_os = "windows"))]
             let pkg = Pkg::from_install(package).await?;
 
-            match hook.run(package_name, &pkg, None::<&str>) {
+            let result = match sandbox {
+                Some(ref sandbox) => {
+                    ui.status(Status::Custom(Glyph::Elipses, "Sandboxing".to_string()),
+                              format!("{} hook for {} origin '{}'",
+                                      hook_name,
+                                      sandbox.trust(),
+                                      package.ident().origin))?;
+                    let result = hook.run_sandboxed(package_name, &pkg, sandbox);
+                    report_sandboxed_output(ui, hook, &result)?;
+                    result
+                }
+                None => hook.run(package_name, &pkg, None::<&str>),
+            };
+            match result {
                 Ok(exit_status) if exit_status.success() => Ok(()),
                 Ok(exit_status) => {
                     Err(Error::hook_exit_status(pkg.ident.clone(),
@@ -354,6 +494,32 @@ pub trait PackageMaintenanceHookExt: Hook<ExitValue = ExitStatus> + Sync {
 
 impl<T: Hook<ExitValue = ExitStatus> + Sync> PackageMaintenanceHookExt for T {}
 
+/// Tell the user where a sandboxed hook's output went, including the start of its stderr if it
+/// failed.
+fn report_sandboxed_output<H, U>(ui: &mut U,
+                                 hook: &H,
+                                 result: &Result<ExitStatus>)
+                                 -> io::Result<()>
+    where H: Hook,
+          U: UIWriter
+{
+    ui.info(format!("{} hook output was written to {} and {}",
+                    H::FILE_NAME,
+                    hook.stdout_log_path().display(),
+                    hook.stderr_log_path().display()))?;
+    if let Ok(status) = result {
+        if !status.success() {
+            let hook_output = HookOutput::new(hook.stdout_log_path(), hook.stderr_log_path());
+            if let Ok(stderr) = hook_output.stderr_str() {
+                if !stderr.is_empty() {
+                    ui.warn(format!("{} hook stderr:\n{}", H::FILE_NAME, stderr.trim_end()))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Serialize)]
 pub struct InstallHook {
     render_pair:     RenderPair,
@@ -489,7 +655,8 @@ fn hash_content<T>(path: T) -> Result<String>
     }
 }
 
-fn write_hook<T>(content: &str, path: T) -> Result<bool>
+/// Writes the compiled `content` of a hook to `path`, returning whether it changed.
+pub fn write_hook<T>(content: &str, path: T) -> Result<bool>
     where T: AsRef<Path>
 {
     let content_hash = crypto::hash::hash_string(&content);
@@ -586,6 +753,42 @@ impl<'a> HookOutput<'a> {
         }
     }
 
+    /// Write the stdout and stderr of a process to the log files only. Stderr is read on a thread
+    /// of its own while stdout is read, so that a process which fills the pipe of one stream while
+    /// the other is being read doesn't block.
+    fn capture_standard_streams(&mut self, process: &mut Child) {
+        let stderr = process.stderr.take().map(|stderr| {
+                                              let path = self.stderr_log_file.to_path_buf();
+                                              thread::spawn(move || {
+                                                  HookOutput::write_standard_stream(stderr, &path)
+                                              })
+                                          });
+        if let Some(stdout) = &mut process.stdout {
+            Self::write_standard_stream(stdout, &self.stdout_log_file);
+        }
+        if let Some(stderr) = stderr {
+            if stderr.join().is_err() {
+                error!("Failed to write hook output to {:?}", self.stderr_log_file);
+            }
+        }
+    }
+
+    fn write_standard_stream(reader: impl Read, path: &Path) {
+        match File::create(path) {
+            Ok(mut file) => {
+                if let Err(e) = io::copy(&mut BufReader::new(reader), &mut file) {
+                    error!("Failed to write hook output to {:?}, {}", path, e);
+                }
+            }
+            Err(e) => {
+                error!("Failed to create file {:?} to write hook output, {}",
+                       path, e);
+                // Drain the stream so the hook doesn't block writing to it.
+                let _ = io::copy(&mut BufReader::new(reader), &mut io::sink());
+            }
+        }
+    }
+
     /// Try to write a stream to stdout and to `path`
     fn tee_standard_stream(preamble_str: &str, reader: impl Read, path: &Path) {
         let mut file_result = File::create(path);
@@ -956,4 +1159,32 @@ echo "The message is Hello"
 
         stdfs::remove_dir_all(tmp_dir).expect("remove temp dir");
     }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn capturing_output_does_not_block_on_a_full_stderr_pipe() {
+        use std::{fs as stdfs,
+                  process::{Command,
+                            Stdio}};
+
+        let tmp_dir = TempDir::new().expect("create temp dir");
+        let mut child = Command::new("sh").arg("-c")
+                                          .arg("head -c 1000000 /dev/zero >&2; echo done")
+                                          .stdin(Stdio::null())
+                                          .stdout(Stdio::piped())
+                                          .stderr(Stdio::piped())
+                                          .spawn()
+                                          .expect("couldn't run hook");
+        let stdout_log = tmp_dir.path().join("stdout.log");
+        let stderr_log = tmp_dir.path().join("stderr.log");
+        let mut hook_output = HookOutput::new(&stdout_log, &stderr_log);
+
+        hook_output.capture_standard_streams(&mut child);
+        assert!(child.wait().expect("hook to finish").success());
+
+        assert_eq!(stdfs::read_to_string(&stdout_log).expect("to read stdout"),
+                   "done\n");
+        assert_eq!(stdfs::metadata(&stderr_log).expect("to stat stderr").len(),
+                   1_000_000);
+    }
 }
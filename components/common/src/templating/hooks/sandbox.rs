@@ -0,0 +1,217 @@
+//! Restrictions placed on package install hooks.
+//!
+//! Install hooks are arbitrary scripts shipped inside packages, and are run by whoever happens to
+//! be installing the package. Unless the package comes from a trusted origin, its install hook
+//! is run:
+//!
+//! * without network access (on Linux, in a new network namespace)
+//! * with `HOME` pointing at a fresh, empty directory which is removed afterwards
+//! * for at most `HAB_INSTALL_HOOK_TIMEOUT_SECS`, after which it is killed
+//!
+//! Hooks from the origins listed in `HAB_INSTALL_HOOK_TRUSTED_ORIGINS` keep their network access
+//! and the caller's `HOME`, but are still subject to the timeout.
+
+use super::super::package::{Env,
+                            Pkg};
+use crate::error::Result;
+use std::{fmt,
+          sync::mpsc::{self,
+                       RecvTimeoutError},
+          thread::{self,
+                   JoinHandle},
+          time::Duration};
+use tempfile::TempDir;
+
+habitat_core::env_config_duration!(InstallHookTimeout,
+                                   HAB_INSTALL_HOOK_TIMEOUT_SECS => from_secs,
+                                   Duration::from_secs(600));
+
+habitat_core::env_config_string!(TrustedOrigins, HAB_INSTALL_HOOK_TRUSTED_ORIGINS, "core");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OriginTrust {
+    Trusted,
+    Untrusted,
+}
+
+impl OriginTrust {
+    /// Determine the trust level of `origin` from `HAB_INSTALL_HOOK_TRUSTED_ORIGINS`, a comma
+    /// separated list of origin names.
+    pub fn of(origin: &str) -> Self {
+        let trusted = String::from(TrustedOrigins::configured_value());
+        if trusted.split(',').map(str::trim).any(|o| o == origin) {
+            OriginTrust::Trusted
+        } else {
+            OriginTrust::Untrusted
+        }
+    }
+}
+
+impl fmt::Display for OriginTrust {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OriginTrust::Trusted => f.write_str("trusted"),
+            OriginTrust::Untrusted => f.write_str("untrusted"),
+        }
+    }
+}
+
+/// The restrictions for a single run of a hook.
+#[derive(Debug)]
+pub struct HookSandbox {
+    trust:   OriginTrust,
+    home:    Option<TempDir>,
+    timeout: Duration,
+}
+
+impl HookSandbox {
+    pub fn for_origin(origin: &str) -> Result<Self> {
+        let trust = OriginTrust::of(origin);
+        let home = match trust {
+            OriginTrust::Trusted => None,
+            OriginTrust::Untrusted => {
+                Some(tempfile::Builder::new().prefix("hab-install-hook-home-")
+                                             .tempdir()?)
+            }
+        };
+        Ok(HookSandbox { trust,
+                         home,
+                         timeout: InstallHookTimeout::configured_value().into() })
+    }
+
+    pub fn trust(&self) -> OriginTrust { self.trust }
+
+    pub fn allows_network(&self) -> bool { self.trust == OriginTrust::Trusted }
+
+    pub fn timeout(&self) -> Duration { self.timeout }
+
+    /// A copy of `pkg` whose environment points `HOME` at the sandbox's home directory.
+    pub(super) fn pkg(&self, pkg: &Pkg) -> Pkg {
+        let mut pkg = pkg.clone();
+        if let Some(ref home) = self.home {
+            let home = home.path().to_string_lossy().into_owned();
+            let mut env = (*pkg.env).clone();
+            #[cfg(windows)]
+            env.insert("USERPROFILE".to_string(), home.clone());
+            env.insert("HOME".to_string(), home);
+            pkg.env = Env::from(env);
+        }
+        pkg
+    }
+
+    /// Apply the restrictions which must be in place before the hook process starts.
+    #[cfg(unix)]
+    pub(super) fn restrict(&self,
+                           cmd: &mut std::process::Command,
+                           ids: Option<(nix::unistd::Uid, nix::unistd::Gid)>)
+                           -> Result<()> {
+        if let (Some(home), Some((uid, gid))) = (&self.home, ids) {
+            nix::unistd::chown(home.path(), Some(uid), Some(gid)).map_err(|e| {
+                crate::error::Error::PermissionFailed(format!("Unable to give the install hook \
+                                                               ownership of {}: {}",
+                                                              home.path().display(),
+                                                              e))
+            })?;
+        }
+        if !self.allows_network() {
+            #[cfg(target_os = "linux")]
+            habitat_core::os::process::exec::unix::without_network(cmd);
+            #[cfg(not(target_os = "linux"))]
+            warn!("Network isolation of install hooks is not supported on this platform");
+        }
+        Ok(())
+    }
+
+    /// Start a watchdog which kills the hook process `pid` if it runs past the timeout.
+    pub(super) fn watch(&self, pid: u32) -> Watchdog { Watchdog::start(pid, self.timeout) }
+}
+
+pub(super) struct Watchdog {
+    done:   mpsc::Sender<()>,
+    thread: JoinHandle<bool>,
+}
+
+impl Watchdog {
+    pub(super) fn start(pid: u32, timeout: Duration) -> Self {
+        let (done, rx) = mpsc::channel();
+        let thread = thread::Builder::new().name(format!("hook-watchdog-{}", pid))
+                                           .spawn(move || {
+                                               match rx.recv_timeout(timeout) {
+                                                   Err(RecvTimeoutError::Timeout) => {
+                                                       kill(pid);
+                                                       true
+                                                   }
+                                                   _ => false,
+                                               }
+                                           })
+                                           .expect("unable to spawn hook watchdog thread");
+        Watchdog { done, thread }
+    }
+
+    /// Stop watching the process. Returns `true` if it had already been killed for running too
+    /// long.
+    pub(super) fn stop(self) -> bool {
+        // The watchdog may have already exited after killing the process, in which case there's
+        // nobody left to receive this.
+        let _ = self.done.send(());
+        self.thread.join().unwrap_or(false)
+    }
+}
+
+// Hooks run in their own process group, so we can take out anything they spawned along with them.
+#[cfg(unix)]
+fn kill(pid: u32) {
+    use habitat_core::os::process::{self,
+                                    Signal};
+
+    if let Err(e) = process::signal(-(pid as process::Pid), Signal::KILL) {
+        error!("Unable to kill hook process group {}: {}", pid, e);
+    }
+}
+
+#[cfg(windows)]
+fn kill(pid: u32) {
+    if let Err(e) = habitat_core::os::process::terminate(pid) {
+        error!("Unable to kill hook process {}: {}", pid, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::locked_env_var;
+
+    locked_env_var!(HAB_INSTALL_HOOK_TRUSTED_ORIGINS, lock_trusted_origins);
+
+    #[test]
+    fn core_is_trusted_by_default() {
+        let lock = lock_trusted_origins();
+        lock.unset();
+        assert_eq!(OriginTrust::of("core"), OriginTrust::Trusted);
+        assert_eq!(OriginTrust::of("someone"), OriginTrust::Untrusted);
+    }
+
+    #[test]
+    fn trusted_origins_can_be_configured() {
+        let lock = lock_trusted_origins();
+        lock.set("acme, someone");
+        assert_eq!(OriginTrust::of("someone"), OriginTrust::Trusted);
+        assert_eq!(OriginTrust::of("acme"), OriginTrust::Trusted);
+        assert_eq!(OriginTrust::of("core"), OriginTrust::Untrusted);
+    }
+
+    #[test]
+    fn untrusted_origins_get_an_isolated_home() {
+        let lock = lock_trusted_origins();
+        lock.set("");
+        let sandbox = HookSandbox::for_origin("someone").unwrap();
+        assert!(!sandbox.allows_network());
+        assert!(sandbox.home.as_ref().unwrap().path().is_dir());
+    }
+
+    #[test]
+    fn watchdog_reports_whether_it_fired() {
+        let watchdog = Watchdog::start(std::u32::MAX, Duration::from_secs(60));
+        assert!(!watchdog.stop());
+    }
+}
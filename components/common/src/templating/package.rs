@@ -1,7 +1,8 @@
 use crate::{error::{Error,
                     Result},
             hcore::{fs,
-                    os::{process::{ShutdownSignal,
+                    os::{process::{HookTimeouts,
+                                   ShutdownSignal,
                                    ShutdownTimeout},
                          users},
                     package::{FullyQualifiedPackageIdent,
@@ -79,6 +80,8 @@ pub struct Pkg {
     pub env:                     Env,
     pub exposes:                 Vec<String>,
     pub exports:                 BTreeMap<String, String>,
+    #[serde(default)]
+    pub proxy_port:              Option<u16>,
     pub path:                    PathBuf,
     pub svc_path:                PathBuf,
     pub svc_config_path:         PathBuf,
@@ -93,6 +96,9 @@ pub struct Pkg {
     pub svc_group:               String,
     pub shutdown_signal:         ShutdownSignal,
     pub shutdown_timeout:        ShutdownTimeout,
+    pub hook_timeouts:           HookTimeouts,
+    /// Configuration keys whose changes the `reconfigure` hook can't apply.
+    pub restart_keys:            Vec<String>,
 }
 
 impl Pkg {
@@ -115,6 +121,7 @@ impl Pkg {
                         deps: package.tdeps()?,
                         exposes: package.exposes()?,
                         exports: package.exports()?,
+                        proxy_port: package.proxy_port()?,
                         path: package.installed_path.clone(),
                         origin: package.ident.origin.clone(),
                         name: package.ident.name.clone(),
@@ -122,6 +129,8 @@ impl Pkg {
                         release: String::from(ident.release()),
                         shutdown_signal: package.shutdown_signal()?.unwrap_or_default(),
                         shutdown_timeout: package.shutdown_timeout()?.unwrap_or_default(),
+                        hook_timeouts: package.hook_timeouts()?,
+                        restart_keys: package.restart_keys()?,
                         ident };
         Ok(pkg)
     }
@@ -157,6 +166,7 @@ impl<'a> Serialize for PkgProxy<'a> {
         strukt.serialize_field("env", &p.env)?;
         strukt.serialize_field("exposes", &p.exposes)?;
         strukt.serialize_field("exports", &p.exports)?;
+        strukt.serialize_field("proxy_port", &p.proxy_port)?;
         strukt.serialize_field("path", &p.path)?;
         strukt.serialize_field("svc_path", &p.svc_path)?;
         strukt.serialize_field("svc_config_path", &p.svc_config_path)?;
@@ -1,5 +1,7 @@
 use crate::error::Error;
 use clap::ArgMatches;
+use habitat_core::util::units::{self,
+                                UnitValue};
 use native_tls::Certificate;
 use std::{collections::HashMap,
           fmt,
@@ -10,7 +12,6 @@ use std::{collections::HashMap,
                 SocketAddr,
                 SocketAddrV4,
                 ToSocketAddrs},
-          num::ParseIntError,
           ops::{Deref,
                 DerefMut},
           option,
@@ -40,6 +41,280 @@ pub struct UserInfo {
     pub gid:       Option<u32>,
 }
 
+/// What to do with a new event when the event stream buffer is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum EventStreamBufferPolicy {
+    /// Evict the oldest buffered event to make room for the new one
+    DropOldest,
+    /// Discard the new event, keeping those already buffered
+    DropNewest,
+}
+
+impl Default for EventStreamBufferPolicy {
+    fn default() -> Self { EventStreamBufferPolicy::DropOldest }
+}
+
+impl FromStr for EventStreamBufferPolicy {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "drop-oldest" => Ok(EventStreamBufferPolicy::DropOldest),
+            "drop-newest" => Ok(EventStreamBufferPolicy::DropNewest),
+            _ => {
+                let e = format!("Invalid event stream buffer policy '{}' (must be 'drop-oldest' \
+                                 or 'drop-newest')",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for EventStreamBufferPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventStreamBufferPolicy::DropOldest => write!(f, "drop-oldest"),
+            EventStreamBufferPolicy::DropNewest => write!(f, "drop-newest"),
+        }
+    }
+}
+
+impl std::convert::TryFrom<String> for EventStreamBufferPolicy {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for EventStreamBufferPolicy {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// How an ACME server is shown that the Supervisor controls the domains of the certificate it
+/// requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AcmeChallenge {
+    /// Answer on port 80 of each domain with a token from the ACME server
+    Http01,
+    /// Publish a token from the ACME server in a DNS TXT record of each domain
+    Dns01,
+}
+
+impl AcmeChallenge {
+    pub const VARIANTS: &'static [&'static str] = &["http-01", "dns-01"];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AcmeChallenge::Http01 => "http-01",
+            AcmeChallenge::Dns01 => "dns-01",
+        }
+    }
+}
+
+impl Default for AcmeChallenge {
+    fn default() -> Self { AcmeChallenge::Http01 }
+}
+
+impl FromStr for AcmeChallenge {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "http-01" => Ok(AcmeChallenge::Http01),
+            "dns-01" => Ok(AcmeChallenge::Dns01),
+            _ => {
+                let e = format!("Invalid ACME challenge '{}' (must be 'http-01' or 'dns-01')",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AcmeChallenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.as_str()) }
+}
+
+impl std::convert::TryFrom<String> for AcmeChallenge {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for AcmeChallenge {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// How the Supervisor authenticates to Kafka brokers it publishes events to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum KafkaSaslMechanism {
+    /// Send the username and password as they are, which should only be done over TLS
+    Plain,
+    /// Salted challenge-response with SHA-256
+    ScramSha256,
+    /// Salted challenge-response with SHA-512
+    ScramSha512,
+}
+
+impl KafkaSaslMechanism {
+    pub const VARIANTS: &'static [&'static str] = &["PLAIN", "SCRAM-SHA-256", "SCRAM-SHA-512"];
+
+    /// The mechanism's name as Kafka knows it
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KafkaSaslMechanism::Plain => "PLAIN",
+            KafkaSaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            KafkaSaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+impl FromStr for KafkaSaslMechanism {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "PLAIN" => Ok(KafkaSaslMechanism::Plain),
+            "SCRAM-SHA-256" => Ok(KafkaSaslMechanism::ScramSha256),
+            "SCRAM-SHA-512" => Ok(KafkaSaslMechanism::ScramSha512),
+            _ => {
+                let e = format!("Invalid Kafka SASL mechanism '{}' (must be 'PLAIN', \
+                                 'SCRAM-SHA-256' or 'SCRAM-SHA-512')",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for KafkaSaslMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.as_str()) }
+}
+
+impl std::convert::TryFrom<String> for KafkaSaslMechanism {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for KafkaSaslMechanism {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// The kinds of event the Supervisor can POST to event stream webhooks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum EventStreamWebhookEvent {
+    ServiceStarted,
+    ServiceStopped,
+    ServiceUpdateStarted,
+    HealthCheck,
+}
+
+impl EventStreamWebhookEvent {
+    pub const ALL: &'static [Self] = &[EventStreamWebhookEvent::ServiceStarted,
+                                       EventStreamWebhookEvent::ServiceStopped,
+                                       EventStreamWebhookEvent::ServiceUpdateStarted,
+                                       EventStreamWebhookEvent::HealthCheck];
+    pub const VARIANTS: &'static [&'static str] =
+        &["service_started", "service_stopped", "service_update_started", "healthcheck"];
+
+    /// The kind's name, which is the last part of its events' subject
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventStreamWebhookEvent::ServiceStarted => "service_started",
+            EventStreamWebhookEvent::ServiceStopped => "service_stopped",
+            EventStreamWebhookEvent::ServiceUpdateStarted => "service_update_started",
+            EventStreamWebhookEvent::HealthCheck => "healthcheck",
+        }
+    }
+}
+
+impl FromStr for EventStreamWebhookEvent {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "service_started" => Ok(EventStreamWebhookEvent::ServiceStarted),
+            "service_stopped" => Ok(EventStreamWebhookEvent::ServiceStopped),
+            "service_update_started" => Ok(EventStreamWebhookEvent::ServiceUpdateStarted),
+            "healthcheck" => Ok(EventStreamWebhookEvent::HealthCheck),
+            _ => {
+                let e = format!("Invalid webhook event '{}' (must be 'service_started', \
+                                 'service_stopped', 'service_update_started' or 'healthcheck')",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for EventStreamWebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.as_str()) }
+}
+
+impl std::convert::TryFrom<String> for EventStreamWebhookEvent {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for EventStreamWebhookEvent {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// A set of Supervisor defaults tuned for the machine it runs on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SupervisorProfile {
+    /// The standard Supervisor configuration
+    Default,
+    /// Trade features and responsiveness for a small memory footprint on edge devices
+    Minimal,
+}
+
+impl Default for SupervisorProfile {
+    fn default() -> Self { SupervisorProfile::Default }
+}
+
+impl FromStr for SupervisorProfile {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(SupervisorProfile::Default),
+            "minimal" => Ok(SupervisorProfile::Minimal),
+            _ => {
+                let e = format!("Invalid Supervisor profile '{}' (must be 'default' or 'minimal')",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SupervisorProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SupervisorProfile::Default => write!(f, "default"),
+            SupervisorProfile::Minimal => write!(f, "minimal"),
+        }
+    }
+}
+
+impl std::convert::TryFrom<String> for SupervisorProfile {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for SupervisorProfile {
+    fn into(self) -> String { self.to_string() }
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 // TODO (DM): This is unnecessarily difficult due to this issue in serde
 // https://github.com/serde-rs/serde/issues/723. The easiest way to get around the issue is to use
@@ -161,9 +436,10 @@ impl fmt::Display for EventStreamToken {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.0) }
 }
 
-/// The event stream connection method.
+/// The event stream connection method, parsed from a connection timeout which may be given with
+/// units (ex: 30s, 2m).
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
-#[serde(from = "u64", into = "u64")]
+#[serde(try_from = "UnitValue", into = "u64")]
 pub enum EventStreamConnectMethod {
     /// Immediately start the supervisor regardless of the event stream status.
     Immediate,
@@ -180,11 +456,18 @@ impl EventStreamConnectMethod {
 }
 
 impl FromStr for EventStreamConnectMethod {
-    type Err = ParseIntError;
+    type Err = habitat_core::Error;
 
     fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
-        let secs = s.parse::<u64>()?;
-        Ok(Self::from(secs))
+        Ok(Self::from(units::parse_secs(s)?))
+    }
+}
+
+impl std::convert::TryFrom<UnitValue> for EventStreamConnectMethod {
+    type Error = habitat_core::Error;
+
+    fn try_from(value: UnitValue) -> result::Result<Self, Self::Error> {
+        Ok(Self::from(value.into_secs()?))
     }
 }
 
@@ -419,6 +702,139 @@ impl AsRef<SocketAddr> for ListenCtlAddr {
     fn as_ref(&self) -> &SocketAddr { &self.0 }
 }
 
+/// Where a Supervisor's member-id comes from.
+///
+/// By default a random member-id is generated the first time a Supervisor starts and saved in
+/// its data directory. Deriving it from a stable machine identity instead means a re-provisioned
+/// machine, whose data directory was wiped, rejoins its ring as the same member rather than as a
+/// new one alongside the departed ghost of its former self.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum MemberIdSource {
+    /// Generate a random member-id
+    Random,
+    /// The systemd machine-id (`/etc/machine-id`)
+    MachineId,
+    /// The cloud instance-id recorded by cloud-init (`/var/lib/cloud/data/instance-id`)
+    CloudInstanceId,
+    /// The contents of a file, such as a digest of a TPM endorsement key written at provisioning
+    File(PathBuf),
+}
+
+impl MemberIdSource {
+    const FILE_PREFIX: &'static str = "file:";
+
+    /// The file holding the machine identity, if the member-id is not random.
+    pub fn identity_path(&self) -> Option<PathBuf> {
+        match self {
+            MemberIdSource::Random => None,
+            MemberIdSource::MachineId => Some(PathBuf::from("/etc/machine-id")),
+            MemberIdSource::CloudInstanceId => {
+                Some(PathBuf::from("/var/lib/cloud/data/instance-id"))
+            }
+            MemberIdSource::File(path) => Some(path.clone()),
+        }
+    }
+}
+
+impl Default for MemberIdSource {
+    fn default() -> Self { MemberIdSource::Random }
+}
+
+impl FromStr for MemberIdSource {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(MemberIdSource::Random),
+            "machine-id" => Ok(MemberIdSource::MachineId),
+            "cloud-instance-id" => Ok(MemberIdSource::CloudInstanceId),
+            _ if s.starts_with(Self::FILE_PREFIX) && s.len() > Self::FILE_PREFIX.len() => {
+                Ok(MemberIdSource::File(PathBuf::from(&s[Self::FILE_PREFIX.len()..])))
+            }
+            _ => {
+                let e = format!("Invalid member-id source '{}' (must be 'random', 'machine-id', \
+                                 'cloud-instance-id' or 'file:<PATH>')",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for MemberIdSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemberIdSource::Random => write!(f, "random"),
+            MemberIdSource::MachineId => write!(f, "machine-id"),
+            MemberIdSource::CloudInstanceId => write!(f, "cloud-instance-id"),
+            MemberIdSource::File(path) => write!(f, "{}{}", Self::FILE_PREFIX, path.display()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<String> for MemberIdSource {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for MemberIdSource {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// A cap on the rumors of one type a Supervisor keeps, given as `TYPE=VALUE` (ex:
+/// `service=10000`).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RumorCap {
+    pub rumor_type: String,
+    pub value:      usize,
+}
+
+impl RumorCap {
+    /// The rumor types which may be capped.
+    pub const RUMOR_TYPES: &'static [&'static str] = &["service", "service-config", "service-file"];
+}
+
+impl FromStr for RumorCap {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || {
+            let e = format!("Invalid rumor cap '{}' (must be TYPE=VALUE, where TYPE is one of {} \
+                             and VALUE is a number, or a size such as 64MiB)",
+                            s,
+                            Self::RUMOR_TYPES.join(", "));
+            io::Error::new(io::ErrorKind::InvalidInput, e)
+        };
+        match s.split('=').collect::<Vec<_>>().as_slice() {
+            [rumor_type, value] if Self::RUMOR_TYPES.contains(rumor_type) => {
+                let value = units::parse_size(value).map_err(|_| invalid())?;
+                Ok(Self { rumor_type: String::from(*rumor_type),
+                          value:      value as usize, })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for RumorCap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.rumor_type, self.value)
+    }
+}
+
+impl std::convert::TryFrom<String> for RumorCap {
+    type Error = io::Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { Self::from_str(&s) }
+}
+
+impl Into<String> for RumorCap {
+    fn into(self) -> String { self.to_string() }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -430,6 +846,47 @@ mod tests {
         fn cannot_parse_from_empty_string() { assert!("".parse::<EventStreamToken>().is_err()) }
     }
 
+    mod member_id_source {
+        use super::*;
+
+        #[test]
+        fn round_trips_through_strings() {
+            for s in &["random",
+                       "machine-id",
+                       "cloud-instance-id",
+                       "file:/etc/hab-identity"]
+            {
+                let source: MemberIdSource = s.parse().unwrap();
+                assert_eq!(source.to_string(), *s);
+            }
+            assert_eq!("file:/etc/hab-identity".parse::<MemberIdSource>()
+                                               .unwrap()
+                                               .identity_path(),
+                       Some(PathBuf::from("/etc/hab-identity")));
+        }
+
+        #[test]
+        fn rejects_unknown_sources() {
+            assert!("uuid".parse::<MemberIdSource>().is_err());
+            assert!("file:".parse::<MemberIdSource>().is_err());
+        }
+    }
+
+    mod rumor_cap {
+        use super::*;
+
+        #[test]
+        fn parses_capped_rumor_types() {
+            assert_eq!("service-config=500".parse::<RumorCap>().unwrap(),
+                       RumorCap { rumor_type: "service-config".to_string(),
+                                  value:      500, });
+            assert!("election=10".parse::<RumorCap>().is_err());
+            assert!("service=lots".parse::<RumorCap>().is_err());
+            assert_eq!("service-file=64MiB".parse::<RumorCap>().unwrap().value,
+                       64 * 1024 * 1024);
+        }
+    }
+
     mod gossip_listen_addr {
         use super::*;
         #[test]
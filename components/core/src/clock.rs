@@ -0,0 +1,140 @@
+//! Where Habitat gets the time from.
+//!
+//! Code whose behavior depends on the time, such as key revisions and timeouts, asks this module
+//! for it rather than calling `SystemTime::now()` or `Instant::now()` directly. Normally this is
+//! the system clock, but a test can swap in a `ManualClock` for the thread it runs on and move
+//! time forward itself, rather than sleeping until enough of it has passed.
+
+use chrono::{DateTime,
+             Utc};
+use std::{cell::RefCell,
+          sync::{Arc,
+                 Mutex},
+          time::{Duration,
+                 Instant,
+                 SystemTime}};
+
+thread_local! {
+    /// The clock set for this thread with `set_for_thread`, if any
+    static THREAD_CLOCK: RefCell<Option<Arc<dyn Clock>>> = RefCell::new(None);
+}
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// The current wall clock time.
+    fn now(&self) -> SystemTime;
+
+    /// The current monotonic time, for measuring how long things take.
+    fn instant(&self) -> Instant;
+}
+
+/// The operating system's clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime { SystemTime::now() }
+
+    fn instant(&self) -> Instant { Instant::now() }
+}
+
+/// A clock which only moves when it's told to. It starts at the time it was created, and clones
+/// share the same time.
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    time: Arc<Mutex<(SystemTime, Instant)>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock { time: Arc::new(Mutex::new((SystemTime::now(), Instant::now()))) }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut time = self.time.lock().expect("Manual clock lock is poisoned");
+        time.0 += duration;
+        time.1 += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self { Self::new() }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime { self.time.lock().expect("Manual clock lock is poisoned").0 }
+
+    fn instant(&self) -> Instant { self.time.lock().expect("Manual clock lock is poisoned").1 }
+}
+
+/// Puts the system clock back for the thread when dropped.
+#[must_use = "the clock is only set until the guard is dropped"]
+pub struct ThreadClockGuard {
+    previous: Option<Arc<dyn Clock>>,
+}
+
+impl Drop for ThreadClockGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD_CLOCK.with(|clock| *clock.borrow_mut() = previous);
+    }
+}
+
+/// Has the time on this thread come from `clock`, until the returned guard is dropped. Other
+/// threads, including those of any async runtime, keep using the system clock.
+pub fn set_for_thread<C>(clock: C) -> ThreadClockGuard
+    where C: Clock + 'static
+{
+    let previous = THREAD_CLOCK.with(|current| current.replace(Some(Arc::new(clock))));
+    ThreadClockGuard { previous }
+}
+
+/// The current wall clock time on this thread.
+pub fn now() -> SystemTime {
+    THREAD_CLOCK.with(|clock| {
+                    clock.borrow()
+                         .as_ref()
+                         .map_or_else(SystemTime::now, |clock| clock.now())
+                })
+}
+
+/// The current wall clock time on this thread, in UTC.
+pub fn utc_now() -> DateTime<Utc> { now().into() }
+
+/// The current monotonic time on this thread.
+pub fn instant() -> Instant {
+    THREAD_CLOCK.with(|clock| {
+                    clock.borrow()
+                         .as_ref()
+                         .map_or_else(Instant::now, |clock| clock.instant())
+                })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_when_advanced() {
+        let clock = ManualClock::new();
+        let (then, started) = (clock.now(), clock.instant());
+        assert_eq!(clock.now(), then);
+        clock.clone().advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), then + Duration::from_secs(90));
+        assert_eq!(clock.instant() - started, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn thread_clock_is_restored_when_the_guard_drops() {
+        let clock = ManualClock::new();
+        let then = clock.now();
+        {
+            let _guard = set_for_thread(clock.clone());
+            clock.advance(Duration::from_secs(3600));
+            assert_eq!(now(), then + Duration::from_secs(3600));
+            assert_eq!(instant(), clock.instant());
+        }
+        assert!(now() < then + Duration::from_secs(3600));
+    }
+}
@@ -113,7 +113,7 @@
 //!
 //! 1. The artifact format version
 //! 1. The name with revision of the origin key which was used to sign the artifact
-//! 1. The hashing algorithm used, which at present is only `BLAKE2b`, but may expand in the future
+//! 1. The hashing algorithm used, which is `BLAKE2b`, `SHA256` or `SHA512`
 //! 1. A Base64 *signed* value of the binary blob's Base64 file hash
 //! 1. The last line is left empty, meaning that 2 newline characters (`\n`) separate the header
 //!    from the payload
@@ -127,6 +127,10 @@
 //! about the hashing strategy can be found in the [libsodium hashing
 //! documentation](https://download.libsodium.org/doc/hashing/generic_hashing.html).
 //!
+//! Artifacts are signed over a BLAKE2b hash unless `HAB_ARTIFACT_HASH_TYPE` names another
+//! algorithm, which is how artifacts are signed where only FIPS-approved algorithms may be used.
+//! Verifying and re-signing an artifact always use the algorithm named in its header.
+//!
 //! Signing uses a secret origin key, while verifying uses the public origin key. Thus, it it safe
 //! to distribute public origin keys.
 //!
@@ -175,6 +179,36 @@
 //! is **not** a supported workflow for working with Habitat artifacts--they are signed for very
 //! important reasons.
 //!
+//! ### HART-2
+//!
+//! A `HART-2` artifact describes its package in its header, so that it can be inspected without
+//! unpacking it. After the signature line comes a metadata section of `name=value` lines, ended
+//! by the empty line:
+//!
+//! ```text
+//! HART-2
+//! habitat-20160405144945
+//! BLAKE2b
+//! signed BLAKE2b signature
+//! ident=core/glibc/2.22/20160310192356
+//! target=x86_64-linux
+//! build_timestamp=2016-03-10T19:23:56Z
+//! content_length=1234
+//!
+//! <binary-blob>
+//! ```
+//!
+//! The `content_length` is the length of the binary blob in bytes. The signed value is the hash
+//! of the metadata section (each of its lines with its newline) followed by the binary blob's
+//! hash, so the metadata can't be altered without invalidating the signature. Fields other than
+//! these are ignored when reading, though they are still signed. `HART-1` artifacts can still be
+//! read, and `hab pkg upgrade-hart` rewrites them as `HART-2`.
+//!
+//! A `HART-2` artifact may be signed by more than one origin key. Each signature after the first
+//! is a `signature=<key-name-with-revision> <signature>` line appended to the metadata section.
+//! These lines are left out of the signed value, so every signature signs the same value, and the
+//! artifact verifies if any of them is valid (or, optionally, only if all of them are).
+//!
 //! ## Encrypted payloads
 //!
 //! The first 4 lines of an encrypted payload are as follows:
@@ -210,9 +244,10 @@
 //! <symkey_base64>
 //! ```
 
-pub use self::keys::{box_key_pair::BoxKeyPair,
-                     sig_key_pair::SigKeyPair,
-                     sym_key::SymKey};
+pub use self::{keys::{box_key_pair::BoxKeyPair,
+                      sig_key_pair::SigKeyPair,
+                      sym_key::SymKey},
+               signing_backend::SigningBackend};
 use crate::error::{Error,
                    Result};
 
@@ -224,13 +259,18 @@ pub static SECRET_SIG_KEY_SUFFIX: &str = "sig.key";
 pub static SECRET_BOX_KEY_SUFFIX: &str = "box.key";
 /// The suffix on the end of a secret symmetric key file
 pub static SECRET_SYM_KEY_SUFFIX: &str = "sym.key";
-/// The hashing function we're using during sign/verify
+/// The hashing function we're using during sign/verify, unless `HAB_ARTIFACT_HASH_TYPE` names
+/// another one
 /// See also: https://download.libsodium.org/doc/hashing/generic_hashing.html
 pub static SIG_HASH_TYPE: &str = "BLAKE2b";
 /// This environment variable allows you to override the fs::CACHE_KEY_PATH
 /// at runtime. This is useful for testing.
 pub static CACHE_KEY_PATH_ENV_VAR: &str = "HAB_CACHE_KEY_PATH";
+/// This environment variable names a file holding the passphrase of encrypted secret keys.
+pub static KEY_PASSPHRASE_FILE_ENV_VAR: &str = "HAB_KEY_PASSPHRASE_FILE";
 pub static HART_FORMAT_VERSION: &str = "HART-1";
+/// The format version of artifacts with a metadata section in their header.
+pub static HART_2_FORMAT_VERSION: &str = "HART-2";
 pub static BOX_FORMAT_VERSION: &str = "BOX-1";
 pub static ANONYMOUS_BOX_FORMAT_VERSION: &str = "ANONYMOUS-BOX-1";
 
@@ -245,6 +285,7 @@ pub mod artifact;
 pub mod dpapi;
 pub mod hash;
 pub mod keys;
+pub mod signing_backend;
 
 pub fn init() -> Result<()> { sodiumoxide::init().map_err(|_| Error::SodiumInitFailed) }
 
@@ -263,11 +304,11 @@ pub fn secure_eq<T, U>(t: T, u: U) -> bool
 pub mod test_support {
     use std::{fs::File,
               io::Read,
-              path::PathBuf,
-              time::{Duration,
-                     Instant}};
+              path::PathBuf};
 
-    use crate::error as herror;
+    use crate::clock::{self,
+                       ManualClock,
+                       ThreadClockGuard};
 
     pub fn fixture(name: &str) -> PathBuf {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
@@ -286,16 +327,12 @@ pub mod test_support {
         content
     }
 
-    pub fn wait_until_ok<F, T>(some_fn: F) -> Option<T>
-        where F: Fn() -> Result<T, herror::Error>
-    {
-        let wait_duration = Duration::from_secs(30);
-        let current_time = Instant::now();
-        while current_time.elapsed() < wait_duration {
-            if let Ok(s) = some_fn() {
-                return Some(s);
-            }
-        }
-        None
+    /// Stops the clock on the calling thread until the returned guard is dropped. Key revisions
+    /// are to the second, so tests advance the clock a second between generating revisions of a
+    /// key rather than waiting for one to pass.
+    pub fn manual_clock() -> (ManualClock, ThreadClockGuard) {
+        let clock = ManualClock::new();
+        let guard = clock::set_for_thread(clock.clone());
+        (clock, guard)
     }
 }
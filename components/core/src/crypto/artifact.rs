@@ -1,73 +1,458 @@
-use super::{hash,
-            keys::parse_name_with_rev,
+use super::{hash::{ArtifactHashType,
+                   HashType},
+            keys::{parse_name_with_rev,
+                   revocation,
+                   trust_policy::TrustPolicy},
             SigKeyPair,
-            HART_FORMAT_VERSION,
-            SIG_HASH_TYPE};
-use crate::error::{Error,
-                   Result};
+            SigningBackend,
+            HART_2_FORMAT_VERSION,
+            HART_FORMAT_VERSION};
+use crate::{env::Config as _,
+            error::{Error,
+                    Result},
+            fs::{AtomicWriter,
+                 Permissions},
+            package::{PackageArchive,
+                      PackageIdent,
+                      PackageTarget}};
+use chrono::{DateTime,
+             SecondsFormat,
+             TimeZone,
+             Utc};
 use sodiumoxide::crypto::sign;
-use std::{fs::File,
+use std::{fs::{self,
+               File},
           io::{self,
                prelude::*,
                BufReader,
                BufWriter},
           path::Path};
 
-/// Generate and sign a package
-pub fn sign<P1: ?Sized, P2: ?Sized>(src: &P1, dst: &P2, pair: &SigKeyPair) -> Result<()>
+/// The format of a package release, which is the time it was built.
+const RELEASE_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// The metadata field of each signature added to a HART-2 artifact after its first.
+const SIGNATURE_FIELD: &str = "signature";
+
+/// Generate and sign a package with `signer`, such as a `SigKeyPair`.
+pub fn sign<P1: ?Sized, P2: ?Sized>(src: &P1, dst: &P2, signer: &dyn SigningBackend) -> Result<()>
     where P1: AsRef<Path>,
           P2: AsRef<Path>
 {
-    let hash = hash::hash_file(&src)?;
-    debug!("File hash for {} = {}", src.as_ref().display(), &hash);
+    let header = sign_reader(&mut BufReader::new(File::open(&src)?), signer)?;
+    debug!("Signed {} with {}",
+           src.as_ref().display(),
+           signer.name_with_rev());
 
-    let signature = sign::sign(&hash.as_bytes(), pair.secret()?);
     let output_file = File::create(dst)?;
     let mut writer = BufWriter::new(&output_file);
-    write!(writer,
-           "{}\n{}\n{}\n{}\n\n",
-           HART_FORMAT_VERSION,
-           pair.name_with_rev(),
-           SIG_HASH_TYPE,
-           base64::encode(&signature))?;
+    writer.write_all(header.as_bytes())?;
     let mut file = File::open(src)?;
     io::copy(&mut file, &mut writer)?;
     Ok(())
 }
 
-/// return a BufReader to the .tar bytestream, skipping the signed header
-pub fn get_archive_reader<P: AsRef<Path>>(src: P) -> Result<BufReader<File>> {
-    let f = File::open(src)?;
-    let mut your_format_version = String::new();
-    let mut your_key_name = String::new();
-    let mut your_hash_type = String::new();
-    let mut your_signature_raw = String::new();
-    let mut empty_line = String::new();
+/// Sign everything read from `payload` with `signer`, returning the artifact header carrying the
+/// signature. The payload is hashed a chunk at a time rather than held in memory, so an artifact
+/// of any size can be signed; the signed artifact is the header followed by the payload, which
+/// the caller reads again to write it out.
+///
+/// The payload is hashed with the algorithm named by `HAB_ARTIFACT_HASH_TYPE`, BLAKE2b by default.
+pub fn sign_reader<R: Read>(payload: &mut R, signer: &dyn SigningBackend) -> Result<String> {
+    let hash_type = HashType::from(ArtifactHashType::configured_value());
+    let hash = hash_type.hash_reader(payload)?;
+    debug!("Payload hash = {}", &hash);
 
-    let mut reader = BufReader::new(f);
-    if reader.read_line(&mut your_format_version)? == 0 {
-        return Err(Error::CryptoError("Can't read format version".to_string()));
+    let signature = signer.sign(&hash.as_bytes())?;
+    Ok(format_header(signer, hash_type, &signature, None))
+}
+
+/// Generate and sign a package in the HART-2 format, whose header describes the package `ident`
+/// for `target`, built at `build_timestamp`.
+pub fn sign_with_metadata<P1: ?Sized, P2: ?Sized>(src: &P1,
+                                                  dst: &P2,
+                                                  signer: &dyn SigningBackend,
+                                                  ident: PackageIdent,
+                                                  target: PackageTarget,
+                                                  build_timestamp: DateTime<Utc>)
+                                                  -> Result<()>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>
+{
+    let metadata = ArtifactMetadata { ident,
+                                      target,
+                                      build_timestamp,
+                                      content_length: fs::metadata(&src)?.len() };
+    let section = metadata.to_section();
+    let hash_type = HashType::from(ArtifactHashType::configured_value());
+    let hash = signed_hash_of(hash_type, Some(&section), &hash_type.hash_file(&src)?);
+    let signature = signer.sign(hash.as_bytes())?;
+    debug!("Signed {} with {}",
+           src.as_ref().display(),
+           signer.name_with_rev());
+
+    let header = format_header(signer, hash_type, &signature, Some(&section));
+    let archive = BufReader::new(File::open(&src)?);
+    AtomicWriter::new(dst.as_ref())?.with_writer(move |file| {
+                                        write_resigned(file,
+                                                       &header,
+                                                       archive,
+                                                       hash_type,
+                                                       &hash,
+                                                       Some(&section))
+                                    })
+}
+
+/// Rewrite the HART-1 artifact at `src` in the HART-2 format, writing the result to `dst`, which
+/// may be `src` itself.
+///
+/// The artifact is verified first, then signed again with the same key, whose secret half must
+/// be in `cache_key_path`. The metadata is read from the package in the archive; its build
+/// timestamp is the package's release. The artifact keeps the hash type it was signed with.
+pub fn upgrade<P1: ?Sized, P2: ?Sized, P3: ?Sized>(src: &P1,
+                                                   dst: &P2,
+                                                   cache_key_path: &P3)
+                                                   -> Result<()>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>,
+          P3: AsRef<Path>
+{
+    let current = get_artifact_header(src)?;
+    if current.format_version != HART_FORMAT_VERSION {
+        let msg = format!("Only {} artifacts can be upgraded, not {}",
+                          HART_FORMAT_VERSION, current.format_version);
+        return Err(Error::CryptoError(msg));
     }
-    if reader.read_line(&mut your_key_name)? == 0 {
-        return Err(Error::CryptoError("Can't read keyname".to_string()));
+    let hash_type = parse_hash_type(&current.hash_type)?;
+    let (signer, payload_hash) = verify(src, cache_key_path)?;
+    let pair = SigKeyPair::get_pair_for(&signer, cache_key_path)?;
+    let (ident, target) = {
+        let mut package = PackageArchive::new(src.as_ref())?;
+        (package.ident()?, package.target()?)
+    };
+    let build_timestamp = match &ident.release {
+        Some(release) => {
+            Utc.datetime_from_str(release, RELEASE_FORMAT)
+               .map_err(|e| {
+                   Error::CryptoError(format!("Can't read the build timestamp of {}: {}", ident, e))
+               })?
+        }
+        None => {
+            return Err(Error::CryptoError(format!("{} has no release", ident)));
+        }
+    };
+    let content_length = io::copy(&mut get_archive_reader(src)?, &mut io::sink())?;
+    let metadata = ArtifactMetadata { ident,
+                                      target,
+                                      build_timestamp,
+                                      content_length };
+    let section = metadata.to_section();
+    let hash = signed_hash_of(hash_type, Some(&section), &payload_hash);
+    let signature = sign::sign(hash.as_bytes(), pair.secret()?);
+    let header = format_header(&pair, hash_type, &signature, Some(&section));
+    let archive = get_archive_reader(src)?;
+    // As with `resign`, the archive is hashed again as it is copied, in case it changed since it
+    // was verified.
+    let writer = AtomicWriter::new_with_permissions(dst.as_ref(), permissions_of(src.as_ref())?)?;
+    writer.with_writer(move |file| {
+              write_resigned(file, &header, archive, hash_type, &hash, Some(&section))
+          })
+}
+
+/// Add `signer`'s signature to the HART-2 artifact at `src`, writing the result to `dst`, which may
+/// be `src` itself. The signatures already there are kept, and the new one signs the same value,
+/// so any of them can verify the artifact.
+///
+/// The artifact must verify with the keys in `cache_key_path` before it is signed again, so a
+/// signature is never added to a header nobody can vouch for.
+pub fn add_signature<P1: ?Sized, P2: ?Sized, P3: ?Sized>(src: &P1,
+                                                         dst: &P2,
+                                                         signer: &dyn SigningBackend,
+                                                         cache_key_path: &P3)
+                                                         -> Result<()>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>,
+          P3: AsRef<Path>
+{
+    let (current, section) = read_header(&mut BufReader::new(File::open(&src)?))?;
+    let section = match section {
+        Some(section) => section,
+        None => {
+            let msg = format!("Only {} artifacts can carry more than one signature; upgrade {} \
+                               with `hab pkg upgrade-hart` first",
+                              HART_2_FORMAT_VERSION,
+                              src.as_ref().display());
+            return Err(Error::CryptoError(msg));
+        }
+    };
+    let hash_type = parse_hash_type(&current.hash_type)?;
+    verify(src, cache_key_path)?;
+    let name_with_rev = signer.name_with_rev();
+    if current.key_name == name_with_rev
+       || additional_signatures(&section)?.iter()
+                                          .any(|(name, _)| *name == name_with_rev)
+    {
+        let msg = format!("{} is already signed with {}",
+                          src.as_ref().display(),
+                          name_with_rev);
+        return Err(Error::CryptoError(msg));
+    }
+    let expected_hash = signed_hash(&current.signature_raw)?;
+    let signature = signer.sign(expected_hash.as_bytes())?;
+    let section = format!("{}{}={} {}\n",
+                          section,
+                          SIGNATURE_FIELD,
+                          name_with_rev,
+                          base64::encode(&signature));
+    let header = format!("{}\n{}\n{}\n{}\n{}\n",
+                         current.format_version,
+                         current.key_name,
+                         current.hash_type,
+                         current.signature_raw,
+                         section);
+    let archive = get_archive_reader(src)?;
+    let writer = AtomicWriter::new_with_permissions(dst.as_ref(), permissions_of(src.as_ref())?)?;
+    writer.with_writer(move |file| {
+              write_resigned(file, &header, archive, hash_type, &expected_hash, Some(&section))
+          })
+}
+
+/// Sign the archive of the artifact at `src` with `pair`, writing the result to `dst`, which may
+/// be `src` itself.
+///
+/// The hash covered by the existing signature is reused for the new one, so the archive is read
+/// only once: it is hashed as it is copied to `dst`, and `dst` is left untouched if that hash
+/// doesn't match the embedded one. The header's signature must be valid for the key of its
+/// current signer, which must be in `cache_key_path`, so only a signature that is already valid
+/// is ever replaced; that vouches for the embedded hash without hashing the archive again.
+pub fn resign<P1: ?Sized, P2: ?Sized, P3: ?Sized>(src: &P1,
+                                                  dst: &P2,
+                                                  pair: &SigKeyPair,
+                                                  cache_key_path: &P3)
+                                                  -> Result<()>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>,
+          P3: AsRef<Path>
+{
+    let (current, section) = read_header(&mut BufReader::new(File::open(&src)?))?;
+    if current.format_version != HART_FORMAT_VERSION
+       && current.format_version != HART_2_FORMAT_VERSION
+    {
+        let msg = format!("Unsupported format version: {}", current.format_version);
+        return Err(Error::CryptoError(msg));
+    }
+    let hash_type = parse_hash_type(&current.hash_type)?;
+    let current_signature = base64::decode(&current.signature_raw).map_err(|e| {
+                                Error::CryptoError(format!("Can't decode signature: {}", e))
+                            })?;
+    let trust = TrustPolicy::load(cache_key_path)?;
+    let current_signer = verifying_pair(&current.key_name, cache_key_path, &trust)?;
+    let expected_hash = verified_hash(&current_signer, &current_signature)?;
+    let signature = sign::sign(expected_hash.as_bytes(), pair.secret()?);
+    let header = format_header(pair, hash_type, &signature, section.as_deref());
+    let archive = get_archive_reader(src)?;
+    // The closure takes ownership of the reader so that `src` is closed before `dst` is replaced,
+    // which matters when they are the same file.
+    let writer = AtomicWriter::new_with_permissions(dst.as_ref(), permissions_of(src.as_ref())?)?;
+    writer.with_writer(move |file| {
+              write_resigned(file, &header, archive, hash_type, &expected_hash, section.as_deref())
+          })
+}
+
+/// The permissions of the artifact at `src`, which one rewritten from it keeps, even when it
+/// replaces `src` itself.
+#[cfg(not(windows))]
+fn permissions_of(src: &Path) -> Result<Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(Permissions::Explicit(fs::metadata(src)?.permissions().mode() & 0o7777))
+}
+
+#[cfg(windows)]
+fn permissions_of(_src: &Path) -> Result<Permissions> { Ok(Permissions::Standard) }
+
+fn write_resigned(file: &mut File,
+                  header: &str,
+                  mut archive: BufReader<File>,
+                  hash_type: HashType,
+                  expected_hash: &str,
+                  metadata_section: Option<&str>)
+                  -> Result<()> {
+    let mut writer = BufWriter::new(file);
+    writer.write_all(header.as_bytes())?;
+    let computed_hash = signed_hash_of(hash_type,
+                                       metadata_section,
+                                       &hash_type.copy_and_hash(&mut archive, &mut writer)?);
+    if computed_hash != expected_hash {
+        let msg = format!("Habitat artifact is invalid, hashes don't match (expected: {}, \
+                           computed: {})",
+                          expected_hash, computed_hash);
+        return Err(Error::CryptoError(msg));
     }
-    if reader.read_line(&mut your_hash_type)? == 0 {
-        return Err(Error::CryptoError("Can't read hash type".to_string()));
+    writer.flush()?;
+    Ok(())
+}
+
+/// The header of an artifact signed with `signer` over a hash of `hash_type`. A HART-2 header is
+/// written when there is a `metadata_section`.
+fn format_header(signer: &dyn SigningBackend,
+                 hash_type: HashType,
+                 signature: &[u8],
+                 metadata_section: Option<&str>)
+                 -> String {
+    match metadata_section {
+        Some(section) => {
+            format!("{}\n{}\n{}\n{}\n{}\n",
+                    HART_2_FORMAT_VERSION,
+                    signer.name_with_rev(),
+                    hash_type,
+                    base64::encode(signature),
+                    section)
+        }
+        None => {
+            format!("{}\n{}\n{}\n{}\n\n",
+                    HART_FORMAT_VERSION,
+                    signer.name_with_rev(),
+                    hash_type,
+                    base64::encode(signature))
+        }
     }
-    if reader.read_line(&mut your_signature_raw)? == 0 {
-        return Err(Error::CryptoError("Can't read signature".to_string()));
+}
+
+/// The hash signed for a payload hashing to `payload_hash`. A HART-2 signature covers the
+/// metadata section as well, so that it can't be altered either, except for the signatures in it
+/// so that more can be added.
+fn signed_hash_of(hash_type: HashType,
+                  metadata_section: Option<&str>,
+                  payload_hash: &str)
+                  -> String {
+    match metadata_section {
+        Some(section) => {
+            let signed = section.lines()
+                                .filter(|line| !is_signature_field(line))
+                                .map(|line| format!("{}\n", line))
+                                .collect::<String>();
+            hash_type.hash_string(&format!("{}{}", signed, payload_hash))
+        }
+        None => payload_hash.to_string(),
     }
-    if reader.read_line(&mut empty_line)? == 0 {
-        return Err(Error::CryptoError("Can't end of header".to_string()));
+}
+
+/// The hash type named on the hash type line of an artifact header.
+fn parse_hash_type(name: &str) -> Result<HashType> {
+    name.parse()
+        .map_err(|_| Error::CryptoError(format!("Unsupported signature type: {}", name)))
+}
+
+fn is_signature_field(line: &str) -> bool { line.splitn(2, '=').next() == Some(SIGNATURE_FIELD) }
+
+/// The signer and decoded signature of each signature in a HART-2 metadata section after the
+/// first, which is in the header itself.
+fn additional_signatures(metadata_section: &str) -> Result<Vec<(String, Vec<u8>)>> {
+    metadata_section.lines()
+                    .filter(|line| is_signature_field(line))
+                    .map(|line| {
+                        let mut value = line[SIGNATURE_FIELD.len() + 1..].split_whitespace();
+                        match (value.next(), value.next()) {
+                            (Some(name), Some(signature)) => {
+                                let signature =
+                                    base64::decode(signature).map_err(|e| {
+                                        Error::CryptoError(format!("Can't decode signature: {}", e))
+                                    })?;
+                                Ok((name.to_string(), signature))
+                            }
+                            _ => {
+                                let msg = format!("Malformed artifact signature: {}", line);
+                                Err(Error::CryptoError(msg))
+                            }
+                        }
+                    })
+                    .collect()
+}
+
+/// The hash covered by a base64 encoded signature. Signed messages carry the message itself after
+/// the signature bytes, so this doesn't need the signer's key.
+fn signed_hash(signature_raw: &str) -> Result<String> {
+    let signed = base64::decode(signature_raw).map_err(|e| {
+                                                  Error::CryptoError(format!("Can't decode \
+                                                                              signature: {}",
+                                                                             e))
+                                              })?;
+    if signed.len() <= sign::SIGNATUREBYTES {
+        return Err(Error::CryptoError("Corrupt payload, signature is too \
+                                       short"
+                                             .to_string()));
     }
+    String::from_utf8(signed[sign::SIGNATUREBYTES..].to_vec()).map_err(|_| {
+        Error::CryptoError("Error parsing artifact signature".to_string())
+    })
+}
+
+/// return a BufReader to the .tar bytestream, skipping the signed header
+pub fn get_archive_reader<P: AsRef<Path>>(src: P) -> Result<BufReader<File>> {
+    let mut reader = BufReader::new(File::open(src)?);
+    read_header(&mut reader)?;
     Ok(reader)
 }
 
+/// Reads the header of an artifact from `reader`, leaving it at the start of the payload. The
+/// metadata section of a HART-2 artifact is also returned as it was signed.
+fn read_header<R: BufRead>(reader: &mut R) -> Result<(ArtifactHeader, Option<String>)> {
+    let format_version = read_header_line(reader, "format version")?;
+    let key_name = read_header_line(reader, "keyname")?;
+    let hash_type = read_header_line(reader, "hash type")?;
+    let signature_raw = read_header_line(reader, "signature")?;
+    let section = if format_version == HART_2_FORMAT_VERSION {
+        Some(read_metadata_section(reader)?)
+    } else {
+        let mut empty_line = String::new();
+        if reader.read_line(&mut empty_line)? == 0 {
+            return Err(Error::CryptoError("Can't end of header".to_string()));
+        }
+        None
+    };
+    let metadata = section.as_deref()
+                          .map(ArtifactMetadata::from_section)
+                          .transpose()?;
+    Ok((ArtifactHeader { format_version,
+                         key_name,
+                         hash_type,
+                         signature_raw,
+                         metadata },
+        section))
+}
+
+fn read_header_line<R: BufRead>(reader: &mut R, name: &str) -> Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(Error::CryptoError(format!("Can't read {}", name)));
+    }
+    Ok(line.trim().to_string())
+}
+
+/// Reads the lines of a HART-2 metadata section, up to the empty line ending the header.
+fn read_metadata_section<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut section = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(Error::CryptoError("Corrupt payload, can't find end of \
+                                           header"
+                                                  .to_string()));
+        }
+        if line.trim().is_empty() {
+            return Ok(section);
+        }
+        section.push_str(&line);
+    }
+}
+
 pub struct ArtifactHeader {
     pub format_version: String,
     pub key_name:       String,
     pub hash_type:      String,
     pub signature_raw:  String,
+    /// The metadata of a HART-2 artifact.
+    pub metadata:       Option<ArtifactMetadata>,
 }
 
 impl ArtifactHeader {
@@ -79,7 +464,80 @@ impl ArtifactHeader {
         ArtifactHeader { format_version,
                          key_name,
                          hash_type,
-                         signature_raw }
+                         signature_raw,
+                         metadata: None }
+    }
+}
+
+/// The metadata section of a HART-2 artifact header, which describes its package without
+/// unpacking it. It is written as one `name=value` line per field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArtifactMetadata {
+    pub ident:           PackageIdent,
+    pub target:          PackageTarget,
+    pub build_timestamp: DateTime<Utc>,
+    /// The length of the payload in bytes.
+    pub content_length:  u64,
+}
+
+impl ArtifactMetadata {
+    fn to_section(&self) -> String {
+        format!("ident={}\ntarget={}\nbuild_timestamp={}\ncontent_length={}\n",
+                self.ident,
+                self.target,
+                self.build_timestamp
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+                self.content_length)
+    }
+
+    fn from_section(section: &str) -> Result<Self> {
+        let mut ident = None;
+        let mut target = None;
+        let mut build_timestamp = None;
+        let mut content_length = None;
+        for line in section.lines() {
+            let mut field = line.splitn(2, '=');
+            let (name, value) = match (field.next(), field.next()) {
+                (Some(name), Some(value)) => (name, value),
+                _ => {
+                    let msg = format!("Malformed artifact metadata: {}", line);
+                    return Err(Error::CryptoError(msg));
+                }
+            };
+            match name {
+                "ident" => ident = Some(value.parse::<PackageIdent>()?),
+                "target" => target = Some(value.parse::<PackageTarget>()?),
+                "build_timestamp" => {
+                    build_timestamp = Some(DateTime::parse_from_rfc3339(value).map_err(|e| {
+                                               Error::CryptoError(format!("Invalid build \
+                                                                           timestamp {}: {}",
+                                                                          value, e))
+                                           })?
+                                           .with_timezone(&Utc))
+                }
+                "content_length" => {
+                    content_length =
+                        Some(value.parse::<u64>().map_err(|e| {
+                                                      Error::CryptoError(format!("Invalid content \
+                                                                          length {}: {}",
+                                                                         value, e))
+                                                  })?)
+                }
+                // Additional signatures are read by `additional_signatures`.
+                SIGNATURE_FIELD => {}
+                // Fields added later are still covered by the signature, so they can be skipped.
+                _ => debug!("Ignoring artifact metadata field {}", name),
+            }
+        }
+        match (ident, target, build_timestamp, content_length) {
+            (Some(ident), Some(target), Some(build_timestamp), Some(content_length)) => {
+                Ok(ArtifactMetadata { ident,
+                                      target,
+                                      build_timestamp,
+                                      content_length })
+            }
+            _ => Err(Error::CryptoError("Incomplete artifact metadata".to_string())),
+        }
     }
 }
 
@@ -89,38 +547,36 @@ impl ArtifactHeader {
 pub fn get_artifact_header<P: ?Sized>(src: &P) -> Result<ArtifactHeader>
     where P: AsRef<Path>
 {
-    let f = File::open(src)?;
-    let mut your_format_version = String::new();
-    let mut your_key_name = String::new();
-    let mut your_hash_type = String::new();
-    let mut your_signature_raw = String::new();
-    let mut empty_line = String::new();
+    let (header, _) = read_header(&mut BufReader::new(File::open(src)?))?;
+    Ok(header)
+}
 
-    let mut reader = BufReader::new(f);
-    if reader.read_line(&mut your_format_version)? == 0 {
-        return Err(Error::CryptoError("Can't read format version".to_string()));
-    }
-    if reader.read_line(&mut your_key_name)? == 0 {
-        return Err(Error::CryptoError("Can't read keyname".to_string()));
-    }
-    if reader.read_line(&mut your_hash_type)? == 0 {
-        return Err(Error::CryptoError("Can't read hash type".to_string()));
-    }
-    if reader.read_line(&mut your_signature_raw)? == 0 {
-        return Err(Error::CryptoError("Can't read signature".to_string()));
-    }
-    if reader.read_line(&mut empty_line)? == 0 {
-        return Err(Error::CryptoError("Can't end of header".to_string()));
+/// The key names with revision of every signer of the artifact at `src`, that of the header's own
+/// signature first. The signatures themselves are not checked.
+pub fn artifact_signers<P: ?Sized>(src: &P) -> Result<Vec<String>>
+    where P: AsRef<Path>
+{
+    let (header, section) = read_header(&mut BufReader::new(File::open(src)?))?;
+    let mut signers = vec![header.key_name];
+    if let Some(section) = section {
+        signers.extend(additional_signatures(&section)?.into_iter()
+                                                       .map(|(name, _)| name));
     }
-    let your_format_version = your_format_version.trim().to_string();
-    let your_key_name = your_key_name.trim().to_string();
-    let your_hash_type = your_hash_type.trim().to_string();
-    let your_signature_raw = your_signature_raw.trim().to_string();
+    Ok(signers)
+}
 
-    Ok(ArtifactHeader::new(your_format_version,
-                           your_key_name,
-                           your_hash_type,
-                           your_signature_raw))
+/// Which of the signatures of an artifact must be valid for it to verify.
+///
+/// The signatures added by `add_signature` are not covered by the signed hash, so anybody can
+/// remove one from an artifact without the others failing to verify. Requiring every signature
+/// to be valid says nothing about which ones an artifact should have: that is what the origins
+/// given with `All` are for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignaturePolicy {
+    /// At least one signature must be valid, which is all a single-signature artifact has.
+    Any,
+    /// Every signature must be valid, and one of them must be by a key of each of these origins.
+    All(Vec<String>),
 }
 
 /// verify the crypto signature of a .hart file
@@ -128,10 +584,69 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
     where P1: AsRef<Path>,
           P2: AsRef<Path>
 {
-    let f = File::open(src)?;
-    let mut reader = BufReader::new(f);
+    verify_with_policy(src, cache_key_path, SignaturePolicy::Any)
+}
 
-    let _ = {
+/// Verify the crypto signatures of a .hart file, requiring those chosen by `policy` to be valid.
+pub fn verify_with_policy<P1: ?Sized, P2: ?Sized>(src: &P1,
+                                                  cache_key_path: &P2,
+                                                  policy: SignaturePolicy)
+                                                  -> Result<(String, String)>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>
+{
+    verify_reader_with_policy(File::open(src)?, cache_key_path, policy)
+}
+
+/// Verify the crypto signatures of a .hart file as `verify_with_policy` does, but with the key
+/// trust policy `trust` in place of the one in the key cache.
+pub fn verify_with_trust_policy<P1: ?Sized, P2: ?Sized>(src: &P1,
+                                                        cache_key_path: &P2,
+                                                        policy: SignaturePolicy,
+                                                        trust: &TrustPolicy)
+                                                        -> Result<(String, String)>
+    where P1: AsRef<Path>,
+          P2: AsRef<Path>
+{
+    verify_reader_with_trust_policy(File::open(src)?, cache_key_path, policy, trust)
+}
+
+/// Verify the crypto signature of a signed artifact read from `src`, such as a .hart file being
+/// downloaded. The archive is hashed a chunk at a time as it is read, and is not kept.
+///
+/// Returns the signer's key name with revision, and the hash of the archive.
+pub fn verify_reader<R: Read, P: ?Sized>(src: R, cache_key_path: &P) -> Result<(String, String)>
+    where P: AsRef<Path>
+{
+    verify_reader_with_policy(src, cache_key_path, SignaturePolicy::Any)
+}
+
+/// Verify the crypto signatures of a signed artifact read from `src`, requiring those chosen by
+/// `policy` to be valid. The signer returned is the first one whose signature is valid.
+///
+/// Signers must also be trusted by the key trust policy of the key cache, if it has one.
+pub fn verify_reader_with_policy<R: Read, P: ?Sized>(src: R,
+                                                     cache_key_path: &P,
+                                                     policy: SignaturePolicy)
+                                                     -> Result<(String, String)>
+    where P: AsRef<Path>
+{
+    let trust = TrustPolicy::load(cache_key_path)?;
+    verify_reader_with_trust_policy(src, cache_key_path, policy, &trust)
+}
+
+/// Verify the crypto signatures of a signed artifact read from `src` as
+/// `verify_reader_with_policy` does, with signers checked against the key trust policy `trust`.
+pub fn verify_reader_with_trust_policy<R: Read, P: ?Sized>(src: R,
+                                                           cache_key_path: &P,
+                                                           policy: SignaturePolicy,
+                                                           trust: &TrustPolicy)
+                                                           -> Result<(String, String)>
+    where P: AsRef<Path>
+{
+    let mut reader = BufReader::new(src);
+
+    let format_version = {
         let mut buffer = String::new();
         match reader.read_line(&mut buffer) {
             Ok(0) => {
@@ -140,7 +655,7 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
                                                        .to_string()));
             }
             Ok(_) => {
-                if buffer.trim() != HART_FORMAT_VERSION {
+                if buffer.trim() != HART_FORMAT_VERSION && buffer.trim() != HART_2_FORMAT_VERSION {
                     let msg = format!("Unsupported format version: {}", &buffer.trim());
                     return Err(Error::CryptoError(msg));
                 }
@@ -149,16 +664,23 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
         };
         buffer.trim().to_string()
     };
-    let pair = {
+    let (key_name, pair) = {
         let mut buffer = String::new();
         if reader.read_line(&mut buffer)? == 0 {
             return Err(Error::CryptoError("Corrupt payload, can't read origin \
                                            key name"
                                                     .to_string()));
         }
-        SigKeyPair::get_pair_for(buffer.trim(), cache_key_path)?
+        // Only a HART-2 artifact can carry other signatures which may still be valid, so any
+        // other fails as soon as its signer's key can't be used.
+        let pair = if format_version == HART_2_FORMAT_VERSION {
+            verifying_pair(buffer.trim(), cache_key_path, trust)
+        } else {
+            Ok(verifying_pair(buffer.trim(), cache_key_path, trust)?)
+        };
+        (buffer.trim().to_string(), pair)
     };
-    {
+    let hash_type = {
         let mut buffer = String::new();
         match reader.read_line(&mut buffer) {
             Ok(0) => {
@@ -166,14 +688,9 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
                     "Corrupt payload, can't read hash type".to_string(),
                 ));
             }
-            Ok(_) => {
-                if buffer.trim() != SIG_HASH_TYPE {
-                    let msg = format!("Unsupported signature type: {}", &buffer.trim());
-                    return Err(Error::CryptoError(msg));
-                }
-            }
+            Ok(_) => parse_hash_type(buffer.trim())?,
             Err(e) => return Err(Error::from(e)),
-        };
+        }
     };
     let signature = {
         let mut buffer = String::new();
@@ -193,28 +710,116 @@ pub fn verify<P1: ?Sized, P2: ?Sized>(src: &P1, cache_key_path: &P2) -> Result<(
             Err(e) => return Err(Error::from(e)),
         }
     };
-    {
+    let metadata_section = if format_version == HART_2_FORMAT_VERSION {
+        Some(read_metadata_section(&mut reader)?)
+    } else {
         let mut buffer = String::new();
         if reader.read_line(&mut buffer)? == 0 {
             return Err(Error::CryptoError("Corrupt payload, can't find end of \
                                            header"
                                                   .to_string()));
         }
+        None
     };
-    let expected_hash = match sign::verify(signature.as_slice(), pair.public()?) {
-        Ok(signed_data) => String::from_utf8(signed_data).map_err(|_| {
-                               Error::CryptoError("Error parsing artifact signature".to_string())
-                           })?,
-        Err(_) => return Err(Error::CryptoError("Verification failed".to_string())),
+    let mut signatures = vec![(key_name, pair, signature)];
+    if let Some(ref section) = metadata_section {
+        for (name, signature) in additional_signatures(section)? {
+            let pair = verifying_pair(&name, cache_key_path, trust);
+            signatures.push((name, pair, signature));
+        }
+    }
+    let signer_origins = signatures.iter()
+                                   .filter_map(|(name, ..)| parse_name_with_rev(name).ok())
+                                   .map(|(origin, _)| origin)
+                                   .collect::<Vec<_>>();
+    let mut payload = CountingReader { inner: reader,
+                                       count: 0, };
+    let payload_hash = hash_type.hash_reader(&mut payload)?;
+    let computed_hash = signed_hash_of(hash_type, metadata_section.as_deref(), &payload_hash);
+    let mut signer = None;
+    let mut first_error = None;
+    for (name, pair, signature) in signatures {
+        match pair.and_then(|pair| check_signature(&pair, &signature, &computed_hash)) {
+            Ok(()) => {
+                signer.get_or_insert(name);
+            }
+            Err(e) => {
+                debug!("Signature of {} is not valid: {}", name, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    let signer = match (signer, first_error, &policy) {
+        (Some(signer), None, _) | (Some(signer), Some(_), SignaturePolicy::Any) => signer,
+        (_, Some(e), _) => return Err(e),
+        (None, None, _) => unreachable!("an artifact has at least one signature"),
     };
-    let computed_hash = hash::hash_reader(&mut reader)?;
-    if computed_hash == expected_hash {
-        Ok((pair.name_with_rev(), expected_hash))
-    } else {
+    if let SignaturePolicy::All(required_origins) = &policy {
+        if let Some(origin) = required_origins.iter()
+                                              .find(|origin| !signer_origins.contains(origin))
+        {
+            let msg = format!("Habitat artifact is not signed by a key of the {} origin",
+                              origin);
+            return Err(Error::CryptoError(msg));
+        }
+    }
+    if let Some(section) = metadata_section {
+        let metadata = ArtifactMetadata::from_section(&section)?;
+        if metadata.content_length != payload.count {
+            let msg = format!("Habitat artifact is invalid, its payload is {} bytes rather than \
+                               {}",
+                              payload.count, metadata.content_length);
+            return Err(Error::CryptoError(msg));
+        }
+    }
+    Ok((signer, payload_hash))
+}
+
+/// The key to verify a signature by `name_with_rev` with, unless it is revoked or not trusted.
+fn verifying_pair<P: ?Sized>(name_with_rev: &str,
+                             cache_key_path: &P,
+                             trust: &TrustPolicy)
+                             -> Result<SigKeyPair>
+    where P: AsRef<Path>
+{
+    revocation::check_key(name_with_rev, cache_key_path)?;
+    trust.check(name_with_rev)?;
+    SigKeyPair::get_pair_for(name_with_rev, cache_key_path)
+}
+
+/// Checks that `signature` is `pair`'s signature of `computed_hash`.
+fn check_signature(pair: &SigKeyPair, signature: &[u8], computed_hash: &str) -> Result<()> {
+    let expected_hash = verified_hash(pair, signature)?;
+    if computed_hash != expected_hash {
         let msg = format!("Habitat artifact is invalid, hashes don't match (expected: {}, \
                            computed: {})",
                           expected_hash, computed_hash);
-        Err(Error::CryptoError(msg))
+        return Err(Error::CryptoError(msg));
+    }
+    Ok(())
+}
+
+/// The hash signed in `signature`, which must be `pair`'s.
+fn verified_hash(pair: &SigKeyPair, signature: &[u8]) -> Result<String> {
+    match sign::verify(signature, pair.public()?) {
+        Ok(signed_data) => String::from_utf8(signed_data).map_err(|_| {
+                               Error::CryptoError("Error parsing artifact signature".to_string())
+                           }),
+        Err(_) => Err(Error::CryptoError("Verification failed".to_string())),
+    }
+}
+
+/// Counts the bytes read through it.
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
     }
 }
 
@@ -231,7 +836,7 @@ pub fn artifact_signer<P: AsRef<Path>>(src: &P) -> Result<String> {
                                                        .to_string()));
             }
             Ok(_) => {
-                if buffer.trim() != HART_FORMAT_VERSION {
+                if buffer.trim() != HART_FORMAT_VERSION && buffer.trim() != HART_2_FORMAT_VERSION {
                     let msg = format!("Unsupported format version: {}", &buffer.trim());
                     return Err(Error::CryptoError(msg));
                 }
@@ -260,13 +865,17 @@ mod test {
               io::{BufRead,
                    BufReader,
                    Read,
-                   Write}};
+                   Write},
+              time::Duration};
 
     use tempfile::Builder;
 
-    use super::{super::{keys::parse_name_with_rev,
+    use super::{super::{hash,
+                        keys::{parse_name_with_rev,
+                               trust_policy::TRUST_POLICY_FILE},
                         test_support::*,
                         SigKeyPair,
+                        HART_2_FORMAT_VERSION,
                         HART_FORMAT_VERSION,
                         SIG_HASH_TYPE},
                 *};
@@ -282,6 +891,170 @@ mod test {
         verify(&dst, cache.path()).unwrap();
     }
 
+    #[test]
+    fn sign_and_verify_readers() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let payload = fs::read(fixture("signme.dat")).unwrap();
+
+        let header = sign_reader(&mut payload.as_slice(), &pair).unwrap();
+        let mut signed = header.into_bytes();
+        signed.extend_from_slice(&payload);
+
+        let (signer, hash) = verify_reader(signed.as_slice(), cache.path()).unwrap();
+        assert_eq!(signer, pair.name_with_rev());
+        assert_eq!(hash, hash::hash_bytes(&payload));
+
+        let dst = cache.path().join("signed.dat");
+        sign(&fixture("signme.dat"), &dst, &pair).unwrap();
+        assert_eq!(fs::read(&dst).unwrap(), signed);
+
+        signed.extend_from_slice(b"payload");
+        let err = verify_reader(signed.as_slice(), cache.path()).unwrap_err();
+        assert!(err.to_string().contains("hashes don't match"));
+    }
+
+    #[test]
+    fn artifacts_are_verified_and_resigned_with_their_own_hash_type() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let payload = fs::read(fixture("signme.dat")).unwrap();
+        let payload_hash = HashType::Sha256.hash_bytes(&payload);
+        let signature = sign::sign(payload_hash.as_bytes(), pair.secret().unwrap());
+        let mut signed = format_header(&pair, HashType::Sha256, &signature, None).into_bytes();
+        signed.extend_from_slice(&payload);
+        let src = cache.path().join("signed.dat");
+        fs::write(&src, &signed).unwrap();
+
+        let (_, hash) = verify(&src, cache.path()).unwrap();
+        assert_eq!(hash, payload_hash);
+
+        let dst = cache.path().join("resigned.dat");
+        resign(&src, &dst, &pair, cache.path()).unwrap();
+        assert_eq!(get_artifact_header(&dst).unwrap().hash_type, "SHA256");
+        let (_, hash) = verify(&dst, cache.path()).unwrap();
+        assert_eq!(hash, payload_hash);
+
+        let header = format_header(&pair, HashType::Sha256, &signature, None);
+        let mut signed = header.replace("\nSHA256\n", "\nMD5\n").into_bytes();
+        signed.extend_from_slice(&payload);
+        let err = verify_reader(signed.as_slice(), cache.path()).unwrap_err();
+        assert!(err.to_string().contains("Unsupported signature type: MD5"));
+    }
+
+    #[test]
+    fn resign_replaces_only_the_header() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
+        let old = SigKeyPair::generate_pair_for_origin("unicorn");
+        old.to_pair_files(cache.path()).unwrap();
+        clock.advance(Duration::from_secs(1));
+        let new = SigKeyPair::generate_pair_for_origin("unicorn");
+        new.to_pair_files(cache.path()).unwrap();
+        let src = cache.path().join("signed.dat");
+        let dst = cache.path().join("resigned.dat");
+        sign(&fixture("signme.dat"), &src, &old).unwrap();
+        let (_, old_hash) = verify(&src, cache.path()).unwrap();
+
+        resign(&src, &dst, &new, cache.path()).unwrap();
+
+        let (signer, new_hash) = verify(&dst, cache.path()).unwrap();
+        assert_eq!(signer, new.name_with_rev());
+        assert_eq!(new_hash, old_hash);
+        let mut archive = Vec::new();
+        get_archive_reader(&dst).unwrap()
+                                .read_to_end(&mut archive)
+                                .unwrap();
+        assert_eq!(archive, fs::read(fixture("signme.dat")).unwrap());
+        assert_eq!(verify(&src, cache.path()).unwrap().0, old.name_with_rev());
+    }
+
+    #[test]
+    fn resign_in_place() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let old = SigKeyPair::generate_pair_for_origin("unicorn");
+        old.to_pair_files(cache.path()).unwrap();
+        let new = SigKeyPair::generate_pair_for_origin("dragon");
+        new.to_pair_files(cache.path()).unwrap();
+        let dst = cache.path().join("signed.dat");
+        sign(&fixture("signme.dat"), &dst, &old).unwrap();
+
+        resign(&dst, &dst, &new, cache.path()).unwrap();
+
+        assert_eq!(verify(&dst, cache.path()).unwrap().0, new.name_with_rev());
+    }
+
+    #[test]
+    fn resign_refuses_a_tampered_artifact() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let src = cache.path().join("signed.dat");
+        let dst = cache.path().join("resigned.dat");
+        sign(&fixture("signme.dat"), &src, &pair).unwrap();
+        let mut f = fs::OpenOptions::new().append(true).open(&src).unwrap();
+        f.write_all(b"payload").unwrap();
+
+        let err = resign(&src, &dst, &pair, cache.path()).unwrap_err();
+        assert!(err.to_string().contains("hashes don't match"));
+        assert!(!dst.exists());
+    }
+
+    #[test]
+    fn resign_refuses_an_artifact_its_header_signer_did_not_sign() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let victim = SigKeyPair::generate_pair_for_origin("unicorn");
+        victim.to_pair_files(cache.path()).unwrap();
+        let forger = SigKeyPair::generate_pair_for_origin("dragon");
+        let src = cache.path().join("signed.dat");
+        let dst = cache.path().join("resigned.dat");
+        sign(&fixture("signme.dat"), &src, &forger).unwrap();
+        let forged = fs::read_to_string(&src).unwrap()
+                                             .replacen(&forger.name_with_rev(),
+                                                       &victim.name_with_rev(),
+                                                       1);
+        fs::write(&src, forged).unwrap();
+
+        assert!(resign(&src, &dst, &victim, cache.path()).is_err());
+        assert!(!dst.exists());
+    }
+
+    #[test]
+    fn signatures_are_only_added_to_artifacts_that_verify() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let unknown = SigKeyPair::generate_pair_for_origin("unicorn");
+        let signer = SigKeyPair::generate_pair_for_origin("dragon");
+        signer.to_pair_files(cache.path()).unwrap();
+        let src = fixture("signme.dat");
+        let dst = cache.path().join("signed.dat");
+        let (ident, target, build_timestamp) = redis_metadata();
+        sign_with_metadata(&src, &dst, &unknown, ident, target, build_timestamp).unwrap();
+        let signed = fs::read(&dst).unwrap();
+
+        assert!(add_signature(&dst, &dst, &signer, cache.path()).is_err());
+        assert_eq!(fs::read(&dst).unwrap(), signed);
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn resign_in_place_keeps_the_permissions_of_the_artifact() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let old = SigKeyPair::generate_pair_for_origin("unicorn");
+        old.to_pair_files(cache.path()).unwrap();
+        let new = SigKeyPair::generate_pair_for_origin("dragon");
+        let dst = cache.path().join("signed.dat");
+        sign(&fixture("signme.dat"), &dst, &old).unwrap();
+        fs::set_permissions(&dst, fs::Permissions::from_mode(0o640)).unwrap();
+
+        resign(&dst, &dst, &new, cache.path()).unwrap();
+
+        assert_eq!(fs::metadata(&dst).unwrap().permissions().mode() & 0o7777, 0o640);
+    }
+
     #[test]
     #[should_panic(expected = "Secret key is required but not present for")]
     fn sign_missing_private_key() {
@@ -504,5 +1277,197 @@ mod test {
         assert_eq!("unicorn", key_name);
         assert_eq!(SIG_HASH_TYPE, hart_header.hash_type);
         assert!(!hart_header.signature_raw.is_empty());
+        assert!(hart_header.metadata.is_none());
+    }
+
+    fn redis_metadata() -> (PackageIdent, PackageTarget, DateTime<Utc>) {
+        ("core/redis/4.0.14/20200421191514".parse().unwrap(),
+         "x86_64-linux".parse().unwrap(),
+         Utc.ymd(2020, 4, 21).and_hms(19, 15, 14))
+    }
+
+    #[test]
+    fn sign_and_verify_with_metadata() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let src = fixture("signme.dat");
+        let dst = cache.path().join("signed.dat");
+        let (ident, target, build_timestamp) = redis_metadata();
+
+        sign_with_metadata(&src, &dst, &pair, ident.clone(), target, build_timestamp).unwrap();
+
+        let (signer, hash) = verify(&dst, cache.path()).unwrap();
+        assert_eq!(signer, pair.name_with_rev());
+        assert_eq!(hash, hash::hash_file(&src).unwrap());
+        let header = get_artifact_header(&dst).unwrap();
+        assert_eq!(header.format_version, HART_2_FORMAT_VERSION);
+        assert_eq!(header.metadata,
+                   Some(ArtifactMetadata { ident,
+                                           target,
+                                           build_timestamp,
+                                           content_length: fs::metadata(&src).unwrap().len() }));
+        let mut archive = Vec::new();
+        get_archive_reader(&dst).unwrap()
+                                .read_to_end(&mut archive)
+                                .unwrap();
+        assert_eq!(archive, fs::read(&src).unwrap());
+    }
+
+    #[test]
+    fn verify_refuses_altered_metadata() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let src = fixture("signme.dat");
+        let dst = cache.path().join("signed.dat");
+        let (ident, target, build_timestamp) = redis_metadata();
+        sign_with_metadata(&src, &dst, &pair, ident, target, build_timestamp).unwrap();
+
+        let signed = fs::read_to_string(&dst).unwrap();
+        let altered = signed.replace("core/redis/4.0.14", "core/redis/5.0.0");
+        assert_ne!(altered, signed);
+        let err = verify_reader(altered.as_bytes(), cache.path()).unwrap_err();
+        assert!(err.to_string().contains("hashes don't match"));
+    }
+
+    #[test]
+    fn verify_refuses_a_signer_the_trust_policy_does_not_allow() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let dst = cache.path().join("signed.dat");
+        sign(&fixture("signme.dat"), &dst, &pair).unwrap();
+        assert!(verify(&dst, cache.path()).is_ok());
+
+        fs::write(cache.path().join(TRUST_POLICY_FILE),
+                  "allowed_keys = [\"core\"]\n").unwrap();
+        match verify(&dst, cache.path()) {
+            Err(Error::KeyNotTrusted(key, _)) => assert_eq!(key, pair.name_with_rev()),
+            r => panic!("expected an untrusted key, got {:?}", r),
+        }
+        let trusted = TrustPolicy { allowed_keys: Some(vec!["unicorn".to_string()]),
+                                    ..Default::default() };
+        assert!(verify_with_trust_policy(&dst, cache.path(), SignaturePolicy::Any, &trusted)
+                    .is_ok());
+    }
+
+    #[test]
+    fn verify_refuses_a_payload_of_the_wrong_length() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let metadata = ArtifactMetadata { ident:
+                                              "core/redis/4.0.14/20200421191514".parse().unwrap(),
+                                          target:          "x86_64-linux".parse().unwrap(),
+                                          build_timestamp: Utc::now(),
+                                          content_length:  3, };
+        let section = metadata.to_section();
+        let payload_hash = hash::hash_bytes(b"payload");
+        let signed_hash = signed_hash_of(HashType::Blake2b, Some(&section), &payload_hash);
+        let signature = sign::sign(signed_hash.as_bytes(), pair.secret().unwrap());
+        let mut signed =
+            format_header(&pair, HashType::Blake2b, &signature, Some(&section)).into_bytes();
+        signed.extend_from_slice(b"payload");
+
+        let err = verify_reader(signed.as_slice(), cache.path()).unwrap_err();
+        assert!(err.to_string()
+                   .contains("its payload is 7 bytes rather than 3"));
+    }
+
+    #[test]
+    fn upgrade_rewrites_a_hart_1_artifact() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("happyhumans");
+        pair.to_pair_files(cache.path()).unwrap();
+        let fixture = fixture("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart");
+        let archive = cache.path().join("possums.tar.xz");
+        io::copy(&mut get_archive_reader(&fixture).unwrap(),
+                 &mut File::create(&archive).unwrap()).unwrap();
+        let hart = cache.path().join("possums.hart");
+        sign(&archive, &hart, &pair).unwrap();
+
+        upgrade(&hart, &hart, cache.path()).unwrap();
+
+        let (signer, hash) = verify(&hart, cache.path()).unwrap();
+        assert_eq!(signer, pair.name_with_rev());
+        assert_eq!(hash, hash::hash_file(&archive).unwrap());
+        let header = get_artifact_header(&hart).unwrap();
+        assert_eq!(header.format_version, HART_2_FORMAT_VERSION);
+        let metadata = header.metadata.unwrap();
+        assert_eq!(metadata.ident.to_string(),
+                   "happyhumans/possums/8.1.4/20160427165340");
+        assert_eq!(metadata.target.to_string(), "x86_64-linux");
+        assert_eq!(metadata.build_timestamp,
+                   Utc.ymd(2016, 4, 27).and_hms(16, 53, 40));
+        assert_eq!(metadata.content_length,
+                   fs::metadata(&archive).unwrap().len());
+
+        let err = upgrade(&hart, &hart, cache.path()).unwrap_err();
+        assert!(err.to_string()
+                   .contains("Only HART-1 artifacts can be upgraded"));
+    }
+
+    #[test]
+    fn added_signatures_verify_the_artifact_too() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let first = SigKeyPair::generate_pair_for_origin("unicorn");
+        first.to_pair_files(cache.path()).unwrap();
+        let second = SigKeyPair::generate_pair_for_origin("dragon");
+        second.to_pair_files(cache.path()).unwrap();
+        let src = fixture("signme.dat");
+        let dst = cache.path().join("signed.dat");
+        let (ident, target, build_timestamp) = redis_metadata();
+        sign_with_metadata(&src, &dst, &first, ident.clone(), target, build_timestamp).unwrap();
+
+        add_signature(&dst, &dst, &second, cache.path()).unwrap();
+        assert!(add_signature(&dst, &dst, &second, cache.path()).is_err());
+
+        assert_eq!(artifact_signers(&dst).unwrap(),
+                   vec![first.name_with_rev(), second.name_with_rev()]);
+        let header = get_artifact_header(&dst).unwrap();
+        assert_eq!(header.metadata.unwrap().ident, ident);
+        let both = SignaturePolicy::All(vec!["unicorn".to_string(), "dragon".to_string()]);
+        let (_, hash) = verify_with_policy(&dst, cache.path(), both.clone()).unwrap();
+        assert_eq!(hash, hash::hash_file(&src).unwrap());
+
+        // Either signer's key is enough, unless every signature is required.
+        let only_second = Builder::new().prefix("key_cache").tempdir().unwrap();
+        second.to_pair_files(only_second.path()).unwrap();
+        let (signer, _) = verify(&dst, only_second.path()).unwrap();
+        assert_eq!(signer, second.name_with_rev());
+        assert!(verify_with_policy(&dst, only_second.path(), SignaturePolicy::All(vec![]))
+                .is_err());
+
+        // An added signature can be stripped without invalidating the rest, which only the
+        // origins required by the policy catch.
+        let mut contents = fs::read(&dst).unwrap();
+        let start = find(&contents, b"\nsignature=").unwrap() + 1;
+        let end = start + find(&contents[start..], b"\n").unwrap() + 1;
+        contents.drain(start..end);
+        fs::write(&dst, contents).unwrap();
+        assert_eq!(artifact_signers(&dst).unwrap(), vec![first.name_with_rev()]);
+        assert!(verify_with_policy(&dst, cache.path(), SignaturePolicy::All(vec![])).is_ok());
+        let err = verify_with_policy(&dst, cache.path(), both).unwrap_err();
+        assert!(err.to_string()
+                   .contains("not signed by a key of the dragon origin"));
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len())
+                .position(|window| window == needle)
+    }
+
+    #[test]
+    fn signatures_can_only_be_added_to_hart_2_artifacts() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        let dst = cache.path().join("signed.dat");
+        sign(&fixture("signme.dat"), &dst, &pair).unwrap();
+
+        let other = SigKeyPair::generate_pair_for_origin("dragon");
+        let err = add_signature(&dst, &dst, &other, cache.path()).unwrap_err();
+        assert!(err.to_string().contains("Only HART-2 artifacts"));
     }
 }
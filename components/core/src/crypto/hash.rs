@@ -1,9 +1,16 @@
-use crate::error::Result;
-use std::{fs::File,
-          io::{BufReader,
-               Read},
+use crate::error::{Error,
+                   Result};
+use sodiumoxide::crypto::hash::{sha256,
+                                sha512};
+use std::{fmt,
+          fs::File,
+          io::{self,
+               BufReader,
+               Read,
+               Write},
           path::Path,
-          ptr};
+          ptr,
+          str::FromStr};
 
 const BUF_SIZE: usize = 1024;
 
@@ -44,7 +51,8 @@ pub fn hash_bytes(data: &[u8]) -> String {
     hex::encode(out)
 }
 
-pub fn hash_reader(reader: &mut BufReader<File>) -> Result<String> {
+/// Calculate the BLAKE2b hash of everything read from `reader`, a chunk at a time.
+pub fn hash_reader<R: Read>(reader: &mut R) -> Result<String> {
     let mut out = [0u8; libsodium_sys::crypto_generichash_BYTES as usize];
     let mut st = vec![0u8; unsafe { libsodium_sys::crypto_generichash_statebytes() }];
     #[allow(clippy::cast_ptr_alignment)]
@@ -69,6 +77,144 @@ pub fn hash_reader(reader: &mut BufReader<File>) -> Result<String> {
     Ok(hex::encode(out))
 }
 
+/// Copy everything from `reader` to `writer`, returning the BLAKE2b hash of what was copied.
+pub fn copy_and_hash<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> Result<String> {
+    let mut out = [0u8; libsodium_sys::crypto_generichash_BYTES as usize];
+    let mut st = vec![0u8; unsafe { libsodium_sys::crypto_generichash_statebytes() }];
+    #[allow(clippy::cast_ptr_alignment)]
+    let pst = st.as_mut_ptr() as *mut libsodium_sys::crypto_generichash_state;
+    unsafe {
+        libsodium_sys::crypto_generichash_init(pst, ptr::null_mut(), 0, out.len());
+    }
+    let mut buf = [0u8; BUF_SIZE];
+    loop {
+        let bytes_read = reader.read(&mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let chunk = &buf[0..bytes_read];
+        writer.write_all(chunk)?;
+        unsafe {
+            libsodium_sys::crypto_generichash_update(pst, chunk.as_ptr(), chunk.len() as u64);
+        }
+    }
+    unsafe {
+        libsodium_sys::crypto_generichash_final(pst, out.as_mut_ptr(), out.len());
+    }
+    Ok(hex::encode(out))
+}
+
+crate::env_config!(
+    /// The hash type new artifact signatures are made with. Set it to `SHA256` or `SHA512` where
+    /// only FIPS-approved algorithms may be used.
+    pub ArtifactHashType,
+    HashType,
+    HAB_ARTIFACT_HASH_TYPE,
+    HashType::Blake2b,
+    Error,
+    s,
+    Ok(Self(s.parse()?)));
+
+/// A hash algorithm an artifact can be signed with. The name of the one used is on the hash type
+/// line of the artifact header, so an artifact is always verified with the algorithm it was
+/// signed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashType {
+    Blake2b,
+    Sha256,
+    Sha512,
+}
+
+impl HashType {
+    /// The hash type named on an artifact header line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashType::Blake2b => "BLAKE2b",
+            HashType::Sha256 => "SHA256",
+            HashType::Sha512 => "SHA512",
+        }
+    }
+
+    /// Calculate the hash of a file, return as a hex string.
+    pub fn hash_file<P: AsRef<Path>>(self, filename: P) -> Result<String> {
+        let mut reader = BufReader::new(File::open(filename.as_ref())?);
+        self.hash_reader(&mut reader)
+    }
+
+    pub fn hash_string(self, data: &str) -> String { self.hash_bytes(data.as_bytes()) }
+
+    pub fn hash_bytes(self, data: &[u8]) -> String {
+        match self {
+            HashType::Blake2b => hash_bytes(data),
+            HashType::Sha256 => hex::encode(sha256::hash(data)),
+            HashType::Sha512 => hex::encode(&sha512::hash(data)[..]),
+        }
+    }
+
+    /// Calculate the hash of everything read from `reader`, a chunk at a time.
+    pub fn hash_reader<R: Read>(self, reader: &mut R) -> Result<String> {
+        self.copy_and_hash(reader, &mut io::sink())
+    }
+
+    /// Copy everything from `reader` to `writer`, returning the hash of what was copied.
+    pub fn copy_and_hash<R: Read, W: Write>(self,
+                                            reader: &mut R,
+                                            writer: &mut W)
+                                            -> Result<String> {
+        match self {
+            HashType::Blake2b => copy_and_hash(reader, writer),
+            HashType::Sha256 => {
+                let mut state = sha256::State::new();
+                copy_chunks(reader, writer, |chunk| state.update(chunk))?;
+                Ok(hex::encode(state.finalize()))
+            }
+            HashType::Sha512 => {
+                let mut state = sha512::State::new();
+                copy_chunks(reader, writer, |chunk| state.update(chunk))?;
+                Ok(hex::encode(&state.finalize()[..]))
+            }
+        }
+    }
+}
+
+impl Default for HashType {
+    fn default() -> Self { HashType::Blake2b }
+}
+
+impl fmt::Display for HashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.as_str()) }
+}
+
+impl FromStr for HashType {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_uppercase().as_str() {
+            "BLAKE2B" => Ok(HashType::Blake2b),
+            "SHA256" => Ok(HashType::Sha256),
+            "SHA512" => Ok(HashType::Sha512),
+            _ => Err(Error::CryptoError(format!("Unsupported hash type: {}", value))),
+        }
+    }
+}
+
+fn copy_chunks<R, W, F>(reader: &mut R, writer: &mut W, mut update: F) -> Result<()>
+    where R: Read,
+          W: Write,
+          F: FnMut(&[u8])
+{
+    let mut buf = [0u8; BUF_SIZE];
+    loop {
+        let bytes_read = reader.read(&mut buf)?;
+        if bytes_read == 0 {
+            return Ok(());
+        }
+        let chunk = &buf[0..bytes_read];
+        writer.write_all(chunk)?;
+        update(chunk);
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[allow(unused_imports)]
@@ -123,6 +269,42 @@ mod test {
         assert_eq!(computed, expected);
     }
 
+    #[test]
+    fn copy_and_hash_copies_and_hashes() {
+        let data = fs::read(fixture("signme.dat")).unwrap();
+        let mut copy = Vec::new();
+        let computed = copy_and_hash(&mut data.as_slice(), &mut copy).unwrap();
+        assert_eq!(computed, hash_file(&fixture("signme.dat")).unwrap());
+        assert_eq!(copy, data);
+    }
+
+    #[test]
+    fn hash_types_match_their_reference_digests() {
+        // The SHA digests are those given by `sha256sum` and `sha512sum` for "abc".
+        assert_eq!(HashType::Blake2b.hash_string("abc"), hash_string("abc"));
+        assert_eq!(HashType::Sha256.hash_string("abc"),
+                   "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(HashType::Sha512.hash_string("abc"),
+                   "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a8\
+                    36ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+        let data = fs::read(fixture("signme.dat")).unwrap();
+        let mut copy = Vec::new();
+        let computed = HashType::Sha256.copy_and_hash(&mut data.as_slice(), &mut copy)
+                                       .unwrap();
+        assert_eq!(computed, HashType::Sha256.hash_bytes(&data));
+        assert_eq!(copy, data);
+    }
+
+    #[test]
+    fn hash_types_parse_from_their_names() {
+        for hash_type in &[HashType::Blake2b, HashType::Sha256, HashType::Sha512] {
+            assert_eq!(hash_type.to_string().parse::<HashType>().unwrap(),
+                       *hash_type);
+        }
+        assert_eq!("sha256".parse::<HashType>().unwrap(), HashType::Sha256);
+        assert!("MD5".parse::<HashType>().is_err());
+    }
+
     #[test]
     #[cfg(feature = "functional")]
     fn hash_file_large_binary() {
@@ -7,12 +7,12 @@ use super::{PUBLIC_BOX_KEY_VERSION,
             SECRET_SIG_KEY_VERSION,
             SECRET_SYM_KEY_SUFFIX,
             SECRET_SYM_KEY_VERSION};
-use crate::{error::{Error,
+use crate::{clock,
+            error::{Error,
                     Result},
             fs::{Permissions,
                  DEFAULT_PUBLIC_KEY_PERMISSIONS,
                  DEFAULT_SECRET_KEY_PERMISSIONS}};
-use chrono::Utc;
 use regex::Regex;
 use serde::Deserialize;
 use std::{collections::HashSet,
@@ -34,11 +34,22 @@ lazy_static::lazy_static! {
 }
 
 pub mod box_key_pair;
+pub mod cache;
+mod exposed_secret;
+mod interchange;
+pub mod passphrase;
+pub mod revocation;
+pub mod ring_key;
 pub mod sig_key_pair;
 pub mod sym_key;
+pub mod trust_policy;
 
-#[derive(Clone, Copy, Debug)]
-enum KeyType {
+pub use self::{exposed_secret::{ExposedSecret,
+                                SecretKeyFile},
+               passphrase::Passphrase};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyType {
     Sig,
     Box,
     Sym,
@@ -85,6 +96,28 @@ impl FromStr for PairType {
     }
 }
 
+/// The formats a key can be exported in: Habitat's own key file format, or PEM or a JWK for
+/// tooling outside of Habitat.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyFormat {
+    Habitat,
+    Pem,
+    Jwk,
+}
+
+impl FromStr for KeyFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value {
+            "habitat" => Ok(KeyFormat::Habitat),
+            "pem" => Ok(KeyFormat::Pem),
+            "jwk" => Ok(KeyFormat::Jwk),
+            _ => Err(Error::CryptoError(format!("Invalid key format {}", value))),
+        }
+    }
+}
+
 struct TmpKeyfile {
     pub path: PathBuf,
 }
@@ -317,10 +350,23 @@ fn mk_key_filename<P, S1, S2>(path: P, keyname: S1, suffix: S2) -> PathBuf
         .join(format!("{}.{}", keyname.as_ref(), suffix.as_ref()))
 }
 
-/// generates a revision string in the form:
+/// The revision of a newly generated key, in the form:
 /// `{year}{month}{day}{hour24}{minute}{second}`
-/// Timestamps are in UTC time.
-fn mk_revision_string() -> String { Utc::now().format("%Y%m%d%H%M%S").to_string() }
+/// Timestamps are in UTC time, taken from the thread's `clock`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct KeyRevision(String);
+
+impl KeyRevision {
+    pub fn new() -> Self { KeyRevision(clock::utc_now().format("%Y%m%d%H%M%S").to_string()) }
+}
+
+impl Default for KeyRevision {
+    fn default() -> Self { Self::new() }
+}
+
+impl fmt::Display for KeyRevision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.0) }
+}
 
 pub fn parse_name_with_rev<T>(name_with_rev: T) -> Result<(String, String)>
     where T: AsRef<str>
@@ -451,6 +497,9 @@ fn read_key_bytes(keyfile: &Path) -> Result<Vec<u8>> {
 }
 
 fn read_key_bytes_from_str(key: &str) -> Result<Vec<u8>> {
+    if passphrase::is_encrypted(key) {
+        return passphrase::decrypt_key_bytes_from_env(key);
+    }
     match key.lines().nth(3) {
         Some(encoded) => {
             let v = base64::decode(encoded).map_err(|e| {
@@ -545,7 +594,6 @@ mod test {
                    File},
               io::Write,
               path::Path,
-              thread,
               time::Duration};
     use tempfile::Builder;
 
@@ -643,10 +691,11 @@ mod test {
     #[test]
     fn get_key_revisions_can_return_everything() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
-        // we need to wait at least 1 second between generating keypairs to ensure uniqueness
-        thread::sleep(Duration::from_millis(1000));
+        // revisions are to the second, so move on a second between generating keypairs
+        clock.advance(Duration::from_secs(1));
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
         let revs = super::get_key_revisions("foo", cache.path(), None, KeyType::Sig).unwrap();
@@ -656,12 +705,13 @@ mod test {
     #[test]
     fn get_key_revisions_can_only_return_keys_of_specified_type() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
         let revs = super::get_key_revisions("foo", cache.path(), None, KeyType::Sig).unwrap();
         assert_eq!(1, revs.len());
-        // we need to wait at least 1 second between generating keypairs to ensure uniqueness
-        thread::sleep(Duration::from_millis(1000));
+        // revisions are to the second, so move on a second between generating keypairs
+        clock.advance(Duration::from_secs(1));
         let pair = BoxKeyPair::generate_pair_for_user("foo-user");
         pair.unwrap().to_pair_files(cache.path()).unwrap();
         let revs = super::get_key_revisions("foo-user", cache.path(), None, KeyType::Sig).unwrap();
@@ -671,10 +721,11 @@ mod test {
     #[test]
     fn get_key_revisions_can_return_secret_keys() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
-        // we need to wait at least 1 second between generating keypairs to ensure uniqueness
-        thread::sleep(Duration::from_millis(1000));
+        // revisions are to the second, so move on a second between generating keypairs
+        clock.advance(Duration::from_secs(1));
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
         let revs = super::get_key_revisions("foo",
@@ -687,10 +738,11 @@ mod test {
     #[test]
     fn get_key_revisions_can_return_public_keys() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
-        // we need to wait at least 1 second between generating keypairs to ensure uniqueness
-        thread::sleep(Duration::from_millis(1000));
+        // revisions are to the second, so move on a second between generating keypairs
+        clock.advance(Duration::from_secs(1));
         SigKeyPair::generate_pair_for_origin("foo").to_pair_files(cache.path())
                                                    .unwrap();
         let revs = super::get_key_revisions("foo",
@@ -703,12 +755,11 @@ mod test {
     #[test]
     fn get_user_key_revisions() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         for _ in 0..3 {
-            wait_until_ok(|| {
-                let pair = BoxKeyPair::generate_pair_for_user("wecoyote")?;
-                pair.to_pair_files(cache.path())?;
-                Ok(())
-            });
+            let pair = BoxKeyPair::generate_pair_for_user("wecoyote").unwrap();
+            pair.to_pair_files(cache.path()).unwrap();
+            clock.advance(Duration::from_secs(1));
         }
         BoxKeyPair::generate_pair_for_user("wecoyote-foo").unwrap()
                                                           .to_pair_files(cache.path())
@@ -728,12 +779,11 @@ mod test {
     fn get_service_key_revisions() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
 
+        let (clock, _clock) = manual_clock();
         for _ in 0..3 {
-            wait_until_ok(|| {
-                let pair = BoxKeyPair::generate_pair_for_service("acme", "tnt.default")?;
-                pair.to_pair_files(cache.path())?;
-                Ok(())
-            });
+            let pair = BoxKeyPair::generate_pair_for_service("acme", "tnt.default").unwrap();
+            pair.to_pair_files(cache.path()).unwrap();
+            clock.advance(Duration::from_secs(1));
         }
 
         BoxKeyPair::generate_pair_for_service("acyou", "tnt.default").unwrap()
@@ -755,12 +805,11 @@ mod test {
     fn get_ring_key_revisions() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
 
+        let (clock, _clock) = manual_clock();
         for _ in 0..3 {
-            wait_until_ok(|| {
-                let pair = SymKey::generate_pair_for_ring("acme");
-                pair.to_pair_files(cache.path())?;
-                Ok(())
-            });
+            let pair = SymKey::generate_pair_for_ring("acme");
+            pair.to_pair_files(cache.path()).unwrap();
+            clock.advance(Duration::from_secs(1));
         }
 
         SymKey::generate_pair_for_ring("acme-you").to_pair_files(cache.path())
@@ -778,12 +827,11 @@ mod test {
     fn get_origin_key_revisions() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
 
+        let (clock, _clock) = manual_clock();
         for _ in 0..3 {
-            wait_until_ok(|| {
-                let pair = SigKeyPair::generate_pair_for_origin("mutants");
-                pair.to_pair_files(cache.path())?;
-                Ok(())
-            });
+            let pair = SigKeyPair::generate_pair_for_origin("mutants");
+            pair.to_pair_files(cache.path()).unwrap();
+            clock.advance(Duration::from_secs(1));
         }
 
         SigKeyPair::generate_pair_for_origin("mutants-x").to_pair_files(cache.path())
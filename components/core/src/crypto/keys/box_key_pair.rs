@@ -5,14 +5,17 @@ use super::{super::{ANONYMOUS_BOX_FORMAT_VERSION,
                     SECRET_BOX_KEY_SUFFIX,
                     SECRET_BOX_KEY_VERSION},
             get_key_revisions,
+            interchange::{self,
+                          Curve},
             mk_key_filename,
-            mk_revision_string,
             parse_name_with_rev,
             read_key_bytes,
             read_key_bytes_from_str,
             write_keypair_files,
             KeyPair,
-            KeyType};
+            KeyRevision,
+            KeyType,
+            PairType};
 use crate::error::{Error,
                    Result};
 use serde_derive::{Deserialize,
@@ -69,7 +72,7 @@ impl BoxKeyPair {
         where S1: AsRef<str>,
               S2: AsRef<str>
     {
-        let revision = mk_revision_string();
+        let revision = KeyRevision::new().to_string();
         let keyname =
             Self::mk_key_name_for_service(org.as_ref(), service_group.as_ref(), &revision);
         debug!("new service box key name = {}", &keyname);
@@ -214,8 +217,68 @@ impl BoxKeyPair {
         }
     }
 
+    /// The public or secret key as PEM.
+    pub fn to_pem(&self, pair_type: PairType) -> Result<String> {
+        let key = match pair_type {
+            PairType::Public => self.public()?[..].to_vec(),
+            PairType::Secret => self.secret()?[..].to_vec(),
+        };
+        Ok(interchange::to_pem(Curve::X25519, pair_type, &key))
+    }
+
+    /// Reads a public or secret key from PEM as the key `name_with_rev`. The public key of a
+    /// secret key is derived from it.
+    pub fn from_pem(name_with_rev: &str, pem: &str) -> Result<Self> {
+        let (name, rev) = parse_name_with_rev(name_with_rev)?;
+        let (pair_type, key) = interchange::from_pem(Curve::X25519, pem)?;
+        match pair_type {
+            PairType::Public => {
+                Ok(Self::new(name,
+                             rev,
+                             Some(Self::public_key_from_bytes(&key)?),
+                             None))
+            }
+            PairType::Secret => {
+                let sk = Self::secret_key_from_bytes(&key)?;
+                Ok(Self::new(name, rev, Some(sk.public_key()), Some(sk)))
+            }
+        }
+    }
+
+    /// The key as a JWK, with its secret key if `pair_type` is `Secret`.
+    pub fn to_jwk(&self, pair_type: PairType) -> Result<String> {
+        let secret = match pair_type {
+            PairType::Public => None,
+            PairType::Secret => Some(&self.secret()?[..]),
+        };
+        let public = match self.secret.as_ref() {
+            Some(sk) if self.public.is_none() => sk.public_key(),
+            _ => *self.public()?,
+        };
+        interchange::to_jwk(Curve::X25519, &self.name_with_rev(), &public[..], secret)
+    }
+
+    /// Reads a key from a JWK, named by its `kid`.
+    pub fn from_jwk(jwk: &str) -> Result<Self> {
+        let jwk = interchange::from_jwk(Curve::X25519, jwk)?;
+        let (name, rev) = parse_name_with_rev(&jwk.name_with_rev)?;
+        let public = Self::public_key_from_bytes(&jwk.public)?;
+        match jwk.secret {
+            Some(secret) => {
+                let sk = Self::secret_key_from_bytes(&secret)?;
+                if sk.public_key() != public {
+                    return Err(Error::CryptoError(format!("The public and secret keys \
+                                                           of JWK {} do not match",
+                                                          jwk.name_with_rev)));
+                }
+                Ok(Self::new(name, rev, Some(public), Some(sk)))
+            }
+            None => Ok(Self::new(name, rev, Some(public), None)),
+        }
+    }
+
     fn generate_pair_for_string(string: &str) -> Result<Self> {
-        let revision = mk_revision_string();
+        let revision = KeyRevision::new().to_string();
         let keyname = Self::mk_key_name_for_string(string, &revision);
         debug!("new sig key name = {}", &keyname);
         let (pk, sk) = box_::gen_keypair();
@@ -457,7 +520,8 @@ impl BoxKeyPair {
 #[cfg(test)]
 mod test {
     use std::{fs,
-              str};
+              str,
+              time::Duration};
 
     use tempfile::Builder;
 
@@ -529,6 +593,7 @@ mod test {
     #[test]
     fn get_pairs_for() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         let pairs = BoxKeyPair::get_pairs_for("wecoyote", cache.path()).unwrap();
         assert_eq!(pairs.len(), 0);
 
@@ -538,14 +603,9 @@ mod test {
         let pairs = BoxKeyPair::get_pairs_for("wecoyote", cache.path()).unwrap();
         assert_eq!(pairs.len(), 1);
 
-        match wait_until_ok(|| {
-                  let pair = BoxKeyPair::generate_pair_for_user("wecoyote")?;
-                  pair.to_pair_files(cache.path())?;
-                  Ok(())
-              }) {
-            Some(_) => (),
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let pair = BoxKeyPair::generate_pair_for_user("wecoyote").unwrap();
+        pair.to_pair_files(cache.path()).unwrap();
         let pairs = BoxKeyPair::get_pairs_for("wecoyote", cache.path()).unwrap();
         assert_eq!(pairs.len(), 2);
 
@@ -560,16 +620,12 @@ mod test {
     #[test]
     fn get_pair_for() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         let p1 = BoxKeyPair::generate_pair_for_user("web").unwrap();
         p1.to_pair_files(cache.path()).unwrap();
-        let p2 = match wait_until_ok(|| {
-                  let upair = BoxKeyPair::generate_pair_for_user("web")?;
-                  upair.to_pair_files(cache.path())?;
-                  Ok(upair)
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let p2 = BoxKeyPair::generate_pair_for_user("web").unwrap();
+        p2.to_pair_files(cache.path()).unwrap();
 
         let p1_fetched = BoxKeyPair::get_pair_for(&p1.name_with_rev(), cache.path()).unwrap();
         assert_eq!(p1.name, p1_fetched.name);
@@ -600,17 +656,13 @@ mod test {
     #[test]
     fn get_latest_pair_for_multiple() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         BoxKeyPair::generate_pair_for_user("web").unwrap()
                                                  .to_pair_files(cache.path())
                                                  .unwrap();
-        let p2 = match wait_until_ok(|| {
-                  let upair = BoxKeyPair::generate_pair_for_user("web")?;
-                  upair.to_pair_files(cache.path())?;
-                  Ok(upair)
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let p2 = BoxKeyPair::generate_pair_for_user("web").unwrap();
+        p2.to_pair_files(cache.path()).unwrap();
 
         let latest = BoxKeyPair::get_latest_pair_for("web", cache.path()).unwrap();
         assert_eq!(latest.name, p2.name);
@@ -962,4 +1014,24 @@ mod test {
 
         BoxKeyPair::decrypt_with_path(&WrappedSealedBox::from(botched), cache.path()).unwrap();
     }
+
+    #[test]
+    fn pem_and_jwk_round_trip() {
+        let pair = BoxKeyPair::generate_pair_for_user("wecoyote").unwrap();
+        let name_with_rev = pair.name_with_rev();
+
+        let public =
+            BoxKeyPair::from_pem(&name_with_rev, &pair.to_pem(PairType::Public).unwrap()).unwrap();
+        assert_eq!(public.public().unwrap(), pair.public().unwrap());
+        assert!(public.secret().is_err());
+        let secret =
+            BoxKeyPair::from_pem(&name_with_rev, &pair.to_pem(PairType::Secret).unwrap()).unwrap();
+        assert!(secret == pair);
+
+        let secret = BoxKeyPair::from_jwk(&pair.to_jwk(PairType::Secret).unwrap()).unwrap();
+        assert_eq!(secret.name_with_rev(), name_with_rev);
+        assert!(secret == pair);
+        let public = pair.to_jwk(PairType::Public).unwrap();
+        assert!(crate::crypto::SigKeyPair::from_jwk(&public).is_err());
+    }
 }
@@ -0,0 +1,269 @@
+//! A directory of key files, such as the default key cache `hab` and the Supervisor read keys
+//! from and write keys to.
+
+use super::{super::{hash,
+                    PUBLIC_BOX_KEY_VERSION,
+                    PUBLIC_SIG_KEY_VERSION,
+                    SECRET_BOX_KEY_VERSION,
+                    SECRET_SIG_KEY_VERSION,
+                    SECRET_SYM_KEY_VERSION},
+            parse_name_with_rev,
+            passphrase,
+            read_key_bytes_from_str,
+            revocation::{KeyStatus,
+                         RevocationList},
+            KeyType,
+            PairType,
+            KEYFILE_RE};
+use crate::error::{Error,
+                   Result};
+use chrono::{DateTime,
+             NaiveDateTime,
+             Utc};
+use sodiumoxide::crypto::{box_::curve25519xsalsa20poly1305::SecretKey as BoxSecretKey,
+                          sign::ed25519::SecretKey as SigSecretKey};
+use std::{fs,
+          io,
+          path::{Path,
+                 PathBuf}};
+
+/// What can be learned about a key file without loading the key it contains.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMetadata {
+    pub key_type:    KeyType,
+    pub pair_type:   PairType,
+    /// The name of the key, ex: "core"
+    pub name:        String,
+    /// The revision of the key, ex: "20160517220007"
+    pub rev:         String,
+    /// When the revision was generated, according to its revision timestamp.
+    pub created:     DateTime<Utc>,
+    pub path:        PathBuf,
+    /// A hex encoded hash of the public key, or of the key itself for symmetric keys. The public
+    /// and secret key files of a pair share a fingerprint.
+    pub fingerprint: String,
+    /// Whether the key is encrypted with a passphrase.
+    pub encrypted:   bool,
+}
+
+impl KeyMetadata {
+    /// Returns a `String` containing the combination of the `name` and `rev` fields.
+    pub fn name_with_rev(&self) -> String { format!("{}-{}", self.name, self.rev) }
+}
+
+/// A directory of key files.
+#[derive(Clone, Debug)]
+pub struct KeyCache {
+    path: PathBuf,
+}
+
+impl KeyCache {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self { KeyCache { path: path.into() } }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Every key file in the cache, sorted by name and then newest revision first. Files which
+    /// are not keys, or whose contents do not match their file name, are skipped. A cache
+    /// directory which does not exist yet holds no keys.
+    pub fn list_all(&self) -> Result<impl Iterator<Item = KeyMetadata>> {
+        let dir_entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new().into_iter()),
+            Err(e) => {
+                return Err(Error::CryptoError(format!("Error reading key directory \
+                                                       {}: {}",
+                                                      self.path.display(),
+                                                      e)));
+            }
+        };
+
+        let mut keys = Vec::new();
+        for result in dir_entries {
+            let path =
+                result.map_err(|e| Error::CryptoError(format!("Error reading key path {}", e)))?
+                      .path();
+            // NB: this metadata() call traverses symlinks, which is exactly what we want.
+            if !path.metadata().map(|md| md.is_file()).unwrap_or(false) {
+                continue;
+            }
+            match key_metadata(&path) {
+                Ok(Some(metadata)) => keys.push(metadata),
+                Ok(None) => debug!("Skipping {}, which is not a key file", path.display()),
+                Err(e) => debug!("Skipping unreadable key file {}: {}", path.display(), e),
+            }
+        }
+        keys.sort_by(|a, b| {
+                a.name
+                 .cmp(&b.name)
+                 .then_with(|| b.rev.cmp(&a.rev))
+                 .then_with(|| a.path.cmp(&b.path))
+            });
+        Ok(keys.into_iter())
+    }
+
+    /// Whether the origin key revision `name_with_rev` has been revoked, or has expired,
+    /// according to its origin's revocation list in the cache.
+    pub fn is_revoked(&self, name_with_rev: &str) -> Result<bool> {
+        let (origin, _) = parse_name_with_rev(name_with_rev)?;
+        Ok(match RevocationList::load(&origin, &self.path)? {
+            Some(list) => {
+                matches!(list.status(name_with_rev),
+                         KeyStatus::Revoked(_) | KeyStatus::Expired(_))
+            }
+            None => false,
+        })
+    }
+}
+
+/// Returns the metadata of the key file at `path`, or `None` if it is not a key file.
+fn key_metadata(path: &Path) -> Result<Option<KeyMetadata>> {
+    let caps = match path.file_name()
+                         .and_then(|f| f.to_str())
+                         .and_then(|f| KEYFILE_RE.captures(f))
+    {
+        Some(caps) => caps,
+        None => return Ok(None),
+    };
+    let stem = format!("{}-{}", &caps["name"], &caps["rev"]);
+
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+    let (key_type, pair_type) = match lines.next() {
+        Some(PUBLIC_SIG_KEY_VERSION) => (KeyType::Sig, PairType::Public),
+        Some(SECRET_SIG_KEY_VERSION) => (KeyType::Sig, PairType::Secret),
+        Some(PUBLIC_BOX_KEY_VERSION) => (KeyType::Box, PairType::Public),
+        Some(SECRET_BOX_KEY_VERSION) => (KeyType::Box, PairType::Secret),
+        Some(SECRET_SYM_KEY_VERSION) => (KeyType::Sym, PairType::Secret),
+        _ => return Ok(None),
+    };
+    if lines.next() != Some(stem.as_str()) {
+        return Ok(None);
+    }
+    let (name, rev) = parse_name_with_rev(&stem)?;
+    let created = NaiveDateTime::parse_from_str(&rev, "%Y%m%d%H%M%S").map_err(|e| {
+                      Error::CryptoError(format!("Invalid key revision {}: {}", rev, e))
+                  })?;
+
+    let encrypted = passphrase::is_encrypted(&content);
+    let fingerprint = if encrypted {
+        passphrase::fingerprint(&content).ok_or_else(|| {
+                                             Error::CryptoError(String::from("Missing fingerprint \
+                                                                              of encrypted key"))
+                                         })?
+                                         .to_string()
+    } else {
+        fingerprint(key_type, pair_type, &read_key_bytes_from_str(&content)?)?
+    };
+
+    Ok(Some(KeyMetadata { key_type,
+                          pair_type,
+                          name,
+                          rev,
+                          created: DateTime::from_utc(created,
+                                                      Utc),
+                          path: path.to_path_buf(),
+                          fingerprint,
+                          encrypted }))
+}
+
+/// Returns the fingerprint of a key, given its raw `bytes`. The fingerprint of a secret sig or
+/// box key is that of its public key.
+pub(super) fn fingerprint(key_type: KeyType, pair_type: PairType, bytes: &[u8]) -> Result<String> {
+    let invalid = || Error::CryptoError(format!("Invalid {} {} key", key_type, pair_type));
+    let fingerprint = match (key_type, pair_type) {
+        (KeyType::Sig, PairType::Secret) => {
+            let secret = SigSecretKey::from_slice(bytes).ok_or_else(invalid)?;
+            hash::hash_bytes(secret.public_key().as_ref())
+        }
+        (KeyType::Box, PairType::Secret) => {
+            let secret = BoxSecretKey::from_slice(bytes).ok_or_else(invalid)?;
+            hash::hash_bytes(secret.public_key().as_ref())
+        }
+        _ => hash::hash_bytes(bytes),
+    };
+    Ok(fingerprint)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::{box_key_pair::BoxKeyPair,
+                        sig_key_pair::SigKeyPair,
+                        sym_key::SymKey,
+                        Passphrase},
+                *};
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn list_all_describes_every_key_file() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let sig = SigKeyPair::generate_pair_for_origin("unicorn");
+        sig.to_pair_files(cache.path()).unwrap();
+        let bx = BoxKeyPair::generate_pair_for_user("wecoyote").unwrap();
+        bx.to_pair_files(cache.path()).unwrap();
+        let sym = SymKey::generate_pair_for_ring("beyonce");
+        sym.to_pair_files(cache.path()).unwrap();
+        fs::write(cache.path().join("unicorn-20160517220007.pub"), "not a key").unwrap();
+        fs::write(cache.path().join("README"), "not a key either").unwrap();
+
+        let keys = KeyCache::new(cache.path()).list_all()
+                                              .unwrap()
+                                              .collect::<Vec<_>>();
+        let names = keys.iter()
+                        .map(|k| (k.name.as_str(), k.key_type, k.pair_type))
+                        .collect::<Vec<_>>();
+        assert_eq!(names.len(), 5);
+        assert_eq!(names[0], ("beyonce", KeyType::Sym, PairType::Secret));
+        assert_eq!(names[1].0, "unicorn");
+        assert_eq!(names[3].0, "wecoyote");
+
+        let unicorn = keys.iter()
+                          .filter(|k| k.name == "unicorn")
+                          .collect::<Vec<_>>();
+        assert_eq!(unicorn[0].fingerprint, unicorn[1].fingerprint);
+        assert_eq!(unicorn[0].name_with_rev(), sig.name_with_rev());
+        assert_eq!(unicorn[0].created.format("%Y%m%d%H%M%S").to_string(),
+                   unicorn[0].rev);
+        assert_ne!(keys[3].fingerprint, keys[1].fingerprint);
+        assert!(keys.iter().all(|k| k.path.starts_with(cache.path())));
+        assert!(keys.iter().all(|k| !k.encrypted));
+    }
+
+    #[test]
+    fn list_all_describes_encrypted_keys_without_their_passphrase() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let sig = SigKeyPair::generate_pair_for_origin("unicorn");
+        sig.to_pair_files_with_passphrase(cache.path(), Some(&Passphrase::new("secret")))
+           .unwrap();
+
+        let keys = KeyCache::new(cache.path()).list_all()
+                                              .unwrap()
+                                              .collect::<Vec<_>>();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].fingerprint, keys[1].fingerprint);
+        assert_eq!(keys.iter().filter(|k| k.encrypted).count(), 1);
+    }
+
+    #[test]
+    fn is_revoked_consults_the_origins_revocation_list() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let keys = KeyCache::new(cache.path());
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
+        assert!(!keys.is_revoked("unicorn-20160101000000").unwrap());
+
+        let mut list = RevocationList::new("unicorn");
+        list.revoke("unicorn-20160101000000", "key material leaked")
+            .unwrap();
+        RevocationList::install(&list.to_signed_string(&pair).unwrap(), cache.path()).unwrap();
+        assert!(keys.is_revoked("unicorn-20160101000000").unwrap());
+        assert!(!keys.is_revoked(&pair.name_with_rev()).unwrap());
+    }
+
+    #[test]
+    fn a_missing_cache_holds_no_keys() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let keys = KeyCache::new(cache.path().join("nope")).list_all().unwrap();
+        assert_eq!(keys.count(), 0);
+    }
+}
@@ -0,0 +1,207 @@
+//! Serde support for secret key material.
+//!
+//! Keys do not implement `Serialize` or `Deserialize` themselves, so that a secret key can never
+//! end up in a log line, an API response or a rumor by accident. Tooling which has to embed a
+//! secret key in generated configuration (for example an exporter writing a Kubernetes secret)
+//! opts in by wrapping the key in an `ExposedSecret`.
+//!
+//! An exposed key is serialized as a string in the same format as its key file, so it can be
+//! imported again with `hab ring key import` or `hab origin key import`.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate habitat_core;
+//! extern crate serde_json;
+//!
+//! use habitat_core::crypto::{keys::ExposedSecret,
+//!                            SymKey};
+//!
+//! let key = SymKey::generate_pair_for_ring("beyonce");
+//! let json = serde_json::to_string(&ExposedSecret::new(key)).unwrap();
+//! let key: ExposedSecret<SymKey> = serde_json::from_str(&json).unwrap();
+//! assert_eq!(key.into_inner().name_with_rev().split('-').next(),
+//!            Some("beyonce"));
+//! ```
+
+use super::{super::{SECRET_BOX_KEY_VERSION,
+                    SECRET_SIG_KEY_VERSION,
+                    SECRET_SYM_KEY_VERSION},
+            parse_name_with_rev,
+            BoxKeyPair,
+            KeyPair,
+            SigKeyPair,
+            SymKey};
+use crate::error::{Error,
+                   Result};
+use serde::{de,
+            ser,
+            Deserialize,
+            Deserializer,
+            Serialize,
+            Serializer};
+use sodiumoxide::crypto::{box_::curve25519xsalsa20poly1305::SecretKey as BoxSecretKey,
+                          secretbox::Key as SymSecretKey,
+                          sign::ed25519::SecretKey as SigSecretKey};
+use std::fmt;
+
+/// A key which can be converted to and from the contents of its secret key file.
+pub trait SecretKeyFile: Sized {
+    fn to_secret_string(&self) -> Result<String>;
+
+    /// Reads a key from the contents of its secret key file. Unlike the key cache functions,
+    /// errors never include the content, as it is secret.
+    fn from_secret_str(content: &str) -> Result<Self>;
+}
+
+/// A key which is serialized with its secret key material.
+///
+/// Only the name of the key is included in its `Debug` output.
+#[derive(Clone, PartialEq)]
+pub struct ExposedSecret<K>(K);
+
+impl<K> ExposedSecret<K> {
+    pub fn new(key: K) -> Self { ExposedSecret(key) }
+
+    pub fn get(&self) -> &K { &self.0 }
+
+    pub fn into_inner(self) -> K { self.0 }
+}
+
+impl<P: PartialEq, S: PartialEq> fmt::Debug for ExposedSecret<KeyPair<P, S>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExposedSecret({})", self.0.name_with_rev())
+    }
+}
+
+impl<K: SecretKeyFile> Serialize for ExposedSecret<K> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let content = self.0.to_secret_string().map_err(ser::Error::custom)?;
+        serializer.serialize_str(&content)
+    }
+}
+
+impl<'de, K: SecretKeyFile> Deserialize<'de> for ExposedSecret<K> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let content = String::deserialize(deserializer)?;
+        K::from_secret_str(&content).map(ExposedSecret)
+                                    .map_err(de::Error::custom)
+    }
+}
+
+impl SecretKeyFile for SymKey {
+    fn to_secret_string(&self) -> Result<String> { SymKey::to_secret_string(self) }
+
+    fn from_secret_str(content: &str) -> Result<Self> {
+        let (name, rev, bytes) = parse_secret_str(content, SECRET_SYM_KEY_VERSION)?;
+        let secret =
+            SymSecretKey::from_slice(&bytes).ok_or_else(|| malformed(SECRET_SYM_KEY_VERSION))?;
+        Ok(SymKey::new(name, rev, Some(()), Some(secret)))
+    }
+}
+
+impl SecretKeyFile for SigKeyPair {
+    fn to_secret_string(&self) -> Result<String> { SigKeyPair::to_secret_string(self) }
+
+    fn from_secret_str(content: &str) -> Result<Self> {
+        let (name, rev, bytes) = parse_secret_str(content, SECRET_SIG_KEY_VERSION)?;
+        let secret =
+            SigSecretKey::from_slice(&bytes).ok_or_else(|| malformed(SECRET_SIG_KEY_VERSION))?;
+        Ok(SigKeyPair::new(name,
+                           rev,
+                           Some(secret.public_key()),
+                           Some(secret)))
+    }
+}
+
+impl SecretKeyFile for BoxKeyPair {
+    fn to_secret_string(&self) -> Result<String> { BoxKeyPair::to_secret_string(self) }
+
+    fn from_secret_str(content: &str) -> Result<Self> {
+        let (name, rev, bytes) = parse_secret_str(content, SECRET_BOX_KEY_VERSION)?;
+        let secret =
+            BoxSecretKey::from_slice(&bytes).ok_or_else(|| malformed(SECRET_BOX_KEY_VERSION))?;
+        Ok(BoxKeyPair::new(name,
+                           rev,
+                           Some(secret.public_key()),
+                           Some(secret)))
+    }
+}
+
+/// Returns the name, revision and decoded key bytes of a secret key file's contents.
+fn parse_secret_str(content: &str, version: &str) -> Result<(String, String, Vec<u8>)> {
+    let mut lines = content.lines();
+    if lines.next() != Some(version) {
+        return Err(malformed(version));
+    }
+    let (name, rev) = parse_name_with_rev(lines.next().ok_or_else(|| malformed(version))?)?;
+    let bytes = lines.nth(1)
+                     .and_then(|body| base64::decode(body.trim()).ok())
+                     .ok_or_else(|| malformed(version))?;
+    Ok((name, rev, bytes))
+}
+
+fn malformed(version: &str) -> Error {
+    Error::CryptoError(format!("Malformed {} secret key", version))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exposed_keys_round_trip() {
+        let sym = SymKey::generate_pair_for_ring("beyonce");
+        let json = serde_json::to_string(&ExposedSecret::new(sym.clone())).unwrap();
+        let decoded: ExposedSecret<SymKey> = serde_json::from_str(&json).unwrap();
+        assert!(decoded.get() == &sym);
+
+        let sig = SigKeyPair::generate_pair_for_origin("unicorn");
+        let json = serde_json::to_string(&ExposedSecret::new(sig.clone())).unwrap();
+        let decoded: ExposedSecret<SigKeyPair> = serde_json::from_str(&json).unwrap();
+        assert!(decoded.get() == &sig);
+
+        let bx = BoxKeyPair::generate_pair_for_user("wecoyote").unwrap();
+        let json = serde_json::to_string(&ExposedSecret::new(bx.clone())).unwrap();
+        let decoded: ExposedSecret<BoxKeyPair> = serde_json::from_str(&json).unwrap();
+        assert!(decoded.get() == &bx);
+    }
+
+    #[test]
+    fn keys_serialize_as_their_secret_key_file() {
+        let sym = SymKey::generate_pair_for_ring("beyonce");
+        let json = serde_json::to_value(&ExposedSecret::new(sym.clone())).unwrap();
+        assert_eq!(json, sym.to_secret_string().unwrap());
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected_without_revealing_them() {
+        let sig = SigKeyPair::generate_pair_for_origin("unicorn").to_secret_string()
+                                                                 .unwrap();
+        let err = serde_json::from_value::<ExposedSecret<SymKey>>(sig.clone().into()).unwrap_err();
+        assert!(!err.to_string().contains(sig.lines().last().unwrap()));
+
+        let truncated = sig.lines().take(2).collect::<Vec<_>>().join("\n");
+        assert!(serde_json::from_value::<ExposedSecret<SigKeyPair>>(truncated.into()).is_err());
+    }
+
+    #[test]
+    fn keys_without_a_secret_do_not_serialize() {
+        let sym = SymKey::new("beyonce".to_string(),
+                              "20160504220722".to_string(),
+                              None,
+                              None);
+        assert!(serde_json::to_string(&ExposedSecret::new(sym)).is_err());
+    }
+
+    #[test]
+    fn debug_output_omits_the_secret() {
+        let sym = SymKey::generate_pair_for_ring("beyonce");
+        assert_eq!(format!("{:?}", ExposedSecret::new(sym.clone())),
+                   format!("ExposedSecret({})", sym.name_with_rev()));
+    }
+}
@@ -0,0 +1,224 @@
+//! Passphrase encryption of secret key files.
+//!
+//! An encrypted secret key file keeps the version and name lines of its plaintext form, so it is
+//! still found and listed as a key of its type. The third line, which is empty in a plaintext key
+//! file, names the encryption format and carries the fingerprint of the key, and the fourth line
+//! holds the salt, the nonce and the sealed key, together in Base64:
+//!
+//! ```text
+//! SIG-SEC-1
+//! unicorn-20160517220007
+//! ENCRYPTED-1 <fingerprint>
+//! <salt_nonce_ciphertext_base64>
+//! ```
+//!
+//! The sealing key is derived from the passphrase with Argon2id. Commands which read a secret key
+//! take the passphrase from the file named by `HAB_KEY_PASSPHRASE_FILE`.
+
+use super::{super::KEY_PASSPHRASE_FILE_ENV_VAR,
+            cache,
+            KeyType,
+            PairType};
+use crate::error::{Error,
+                   Result};
+use sodiumoxide::crypto::{pwhash::argon2id13,
+                          secretbox};
+use std::{env,
+          fmt,
+          fs,
+          path::Path};
+
+/// The third line of an encrypted secret key file starts with this.
+pub const ENCRYPTED_KEY_FORMAT_VERSION: &str = "ENCRYPTED-1";
+
+/// A passphrase protecting secret key files.
+pub struct Passphrase(String);
+
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "Passphrase") }
+}
+
+impl Passphrase {
+    pub fn new<S: Into<String>>(passphrase: S) -> Self { Passphrase(passphrase.into()) }
+
+    /// Reads the passphrase from the first line of the file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+                                                  Error::CryptoError(format!("Can't read \
+                                                                              passphrase file \
+                                                                              {}: {}",
+                                                                             path.display(),
+                                                                             e))
+                                              })?;
+        match content.lines().next() {
+            Some(line) if !line.is_empty() => Ok(Self::new(line)),
+            _ => Err(Error::CryptoError(format!("Passphrase file {} is empty", path.display()))),
+        }
+    }
+
+    /// The passphrase in the file named by `HAB_KEY_PASSPHRASE_FILE`, if it is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        match env::var_os(KEY_PASSPHRASE_FILE_ENV_VAR) {
+            Some(path) => Self::from_file(Path::new(&path)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn sealing_key(&self, salt: &argon2id13::Salt) -> Result<secretbox::Key> {
+        self.derive_key(salt,
+                        argon2id13::OPSLIMIT_INTERACTIVE,
+                        argon2id13::MEMLIMIT_INTERACTIVE)
+    }
+
+    /// Derives a key from the passphrase and `salt` with Argon2id, at the cost given by
+    /// `opslimit` and `memlimit`.
+    pub(super) fn derive_key(&self,
+                             salt: &argon2id13::Salt,
+                             opslimit: argon2id13::OpsLimit,
+                             memlimit: argon2id13::MemLimit)
+                             -> Result<secretbox::Key> {
+        let mut key = secretbox::Key([0; secretbox::KEYBYTES]);
+        let derived =
+            argon2id13::derive_key(&mut key.0, self.0.as_bytes(), salt, opslimit, memlimit);
+        derived.map_err(|_| {
+                   Error::CryptoError(String::from("Can't derive a key from the passphrase"))
+               })?;
+        Ok(key)
+    }
+}
+
+/// Whether `content`, the contents of a key file, is encrypted with a passphrase.
+pub fn is_encrypted(content: &str) -> bool {
+    content.lines()
+           .nth(2)
+           .map_or(false, |line| line.starts_with(ENCRYPTED_KEY_FORMAT_VERSION))
+}
+
+/// The fingerprint recorded in an encrypted key file, which can be shown without the passphrase.
+pub(super) fn fingerprint(content: &str) -> Option<&str> {
+    content.lines()
+           .nth(2)
+           .filter(|line| line.starts_with(ENCRYPTED_KEY_FORMAT_VERSION))
+           .and_then(|line| line.split_whitespace().nth(1))
+}
+
+/// Encrypts `content`, the contents of a plaintext secret key file of `key_type`, with
+/// `passphrase`.
+pub fn encrypt(content: &str, key_type: KeyType, passphrase: &Passphrase) -> Result<String> {
+    let mut lines = content.lines();
+    let (version, name_with_rev) = match (lines.next(), lines.next()) {
+        (Some(version), Some(name_with_rev)) => (version, name_with_rev),
+        _ => return Err(Error::CryptoError("Malformed key contents".to_string())),
+    };
+    if is_encrypted(content) {
+        return Err(Error::CryptoError(format!("Key {} is already encrypted", name_with_rev)));
+    }
+    let bytes = super::read_key_bytes_from_str(content)?;
+    let fingerprint = cache::fingerprint(key_type, PairType::Secret, &bytes)?;
+
+    let salt = argon2id13::gen_salt();
+    let nonce = secretbox::gen_nonce();
+    let ciphertext = secretbox::seal(&bytes, &nonce, &passphrase.sealing_key(&salt)?);
+    let mut sealed = salt.as_ref().to_vec();
+    sealed.extend_from_slice(nonce.as_ref());
+    sealed.extend(ciphertext);
+    Ok(format!("{}\n{}\n{} {}\n{}",
+               version,
+               name_with_rev,
+               ENCRYPTED_KEY_FORMAT_VERSION,
+               fingerprint,
+               base64::encode(&sealed)))
+}
+
+/// Decrypts the key in `content`, the contents of an encrypted key file, with `passphrase`.
+pub(super) fn decrypt_key_bytes(content: &str, passphrase: &Passphrase) -> Result<Vec<u8>> {
+    let sealed = match content.lines().nth(3).map(base64::decode) {
+        Some(Ok(sealed)) => sealed,
+        _ => return Err(Error::CryptoError("Malformed encrypted key contents".to_string())),
+    };
+    let nonce_start = argon2id13::SALTBYTES;
+    let ciphertext_start = nonce_start + secretbox::NONCEBYTES;
+    if sealed.len() < ciphertext_start {
+        return Err(Error::CryptoError("Malformed encrypted key contents".to_string()));
+    }
+    let salt = argon2id13::Salt::from_slice(&sealed[..nonce_start]).unwrap();
+    let nonce = secretbox::Nonce::from_slice(&sealed[nonce_start..ciphertext_start]).unwrap();
+    secretbox::open(&sealed[ciphertext_start..],
+                    &nonce,
+                    &passphrase.sealing_key(&salt)?).map_err(|_| {
+        Error::CryptoError(String::from("Can't decrypt the key; is the passphrase correct?"))
+    })
+}
+
+/// The key in `content`, the contents of an encrypted key file, decrypted with the passphrase in
+/// the file named by `HAB_KEY_PASSPHRASE_FILE`.
+pub(super) fn decrypt_key_bytes_from_env(content: &str) -> Result<Vec<u8>> {
+    match Passphrase::from_env()? {
+        Some(passphrase) => decrypt_key_bytes(content, &passphrase),
+        None => {
+            Err(Error::CryptoError(format!("The key is encrypted; set {} \
+                                            to the path of a file \
+                                            holding its passphrase",
+                                           KEY_PASSPHRASE_FILE_ENV_VAR)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::{read_key_bytes_from_str,
+                        sig_key_pair::SigKeyPair,
+                        sym_key::SymKey},
+                *};
+    use std::fs;
+    use tempfile::Builder;
+
+    crate::locked_env_var!(HAB_KEY_PASSPHRASE_FILE, lock_passphrase_file);
+
+    #[test]
+    fn encrypted_keys_decrypt_only_with_their_passphrase() {
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        let plaintext = pair.to_secret_string().unwrap();
+        let passphrase = Passphrase::new("correct horse battery staple");
+        let encrypted = encrypt(&plaintext, KeyType::Sig, &passphrase).unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(&plaintext));
+        assert!(!encrypted.contains(plaintext.lines().nth(3).unwrap()));
+        assert_eq!(encrypted.lines().take(2).collect::<Vec<_>>(),
+                   plaintext.lines().take(2).collect::<Vec<_>>());
+        assert_eq!(decrypt_key_bytes(&encrypted, &passphrase).unwrap(),
+                   read_key_bytes_from_str(&plaintext).unwrap());
+        assert!(decrypt_key_bytes(&encrypted, &Passphrase::new("wrong")).is_err());
+        assert!(encrypt(&encrypted, KeyType::Sig, &passphrase).is_err());
+    }
+
+    #[test]
+    fn encrypted_keys_record_their_fingerprint() {
+        let key = SymKey::generate_pair_for_ring("beyonce");
+        let plaintext = key.to_secret_string().unwrap();
+        let encrypted = encrypt(&plaintext, KeyType::Sym, &Passphrase::new("secret")).unwrap();
+        let expected = cache::fingerprint(KeyType::Sym,
+                                          PairType::Secret,
+                                          &read_key_bytes_from_str(&plaintext).unwrap()).unwrap();
+        assert_eq!(fingerprint(&encrypted), Some(expected.as_str()));
+        assert_eq!(fingerprint(&plaintext), None);
+    }
+
+    #[test]
+    fn encrypted_keys_are_read_with_the_passphrase_file_from_the_environment() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let key = SymKey::generate_pair_for_ring("beyonce");
+        key.to_pair_files_with_passphrase(cache.path(), Some(&Passphrase::new("secret")))
+           .unwrap();
+        let passphrase_file = cache.path().join("passphrase");
+        fs::write(&passphrase_file, "secret\n").unwrap();
+
+        let lock = lock_passphrase_file();
+        lock.unset();
+        assert!(SymKey::get_pair_for(&key.name_with_rev(), cache.path()).is_err());
+        lock.set(&passphrase_file);
+        let read = SymKey::get_pair_for(&key.name_with_rev(), cache.path()).unwrap();
+        assert_eq!(read.secret().unwrap(), key.secret().unwrap());
+    }
+}
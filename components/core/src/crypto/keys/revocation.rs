@@ -0,0 +1,558 @@
+//! Origin key revocation lists.
+//!
+//! An origin can publish a list of its key revisions which must no longer be trusted, either
+//! because they have been revoked outright or because they expire at a given time. The list is
+//! signed by one of the origin's own keys and stored in the key cache alongside the keys it
+//! describes, where `artifact::verify` consults it.
+//!
+//! Every list carries a serial number, which each new list must increase, and the time it was
+//! issued. A list only replaces the installed one if it is not signed by a key the installed list
+//! already distrusts, nor by a key older than the one which signed the installed list, and if its
+//! serial is higher when both are signed by the same key. So an old list can't be replayed to
+//! reinstate a revoked key, nor a revoked key be used to lift its own revocation. A list signed by
+//! a newer key replaces the installed one whatever its serial, which is how an origin recovers
+//! from a compromised key having published a list with a serial too high to ever exceed: it signs
+//! a list revoking that key with a new one.
+//!
+//! The format is a header followed by one entry per line:
+//!
+//! ```text
+//! HAB-CRL-1
+//! core-20200101000000
+//! <base64 detached signature of everything after the blank line>
+//!
+//! serial 2
+//! issued-at 2020-06-01T00:00:00Z
+//! revoke core-20160810182414 key material leaked
+//! expire core-20180119235000 2021-01-01T00:00:00Z
+//! ```
+
+use super::{super::SigKeyPair,
+            parse_name_with_rev};
+use crate::{clock,
+            error::{Error,
+                    Result},
+            fs::AtomicWriter};
+use chrono::{DateTime,
+             Duration as ChronoDuration,
+             SecondsFormat,
+             Utc};
+use std::{collections::BTreeMap,
+          fs,
+          path::{Path,
+                 PathBuf},
+          time::Duration};
+
+pub const REVOCATION_LIST_VERSION: &str = "HAB-CRL-1";
+/// The suffix on the end of a revocation list file
+pub const REVOCATION_LIST_SUFFIX: &str = "crl";
+
+crate::env_config_duration!(
+    /// How long before a key's expiry verification starts warning about it.
+    KeyExpiryWarning,
+    HAB_KEY_EXPIRY_WARNING_SECS => from_secs,
+    Duration::from_secs(30 * 24 * 60 * 60));
+
+/// Where a key revision stands according to its origin's revocation list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyStatus {
+    Valid,
+    /// The key is still valid, but expires within the `KeyExpiryWarning` window.
+    Expiring(DateTime<Utc>),
+    Expired(DateTime<Utc>),
+    /// The key has been revoked for the given reason.
+    Revoked(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevocationList {
+    origin:    String,
+    serial:    u64,
+    issued_at: DateTime<Utc>,
+    revoked:   BTreeMap<String, String>,
+    expires:   BTreeMap<String, DateTime<Utc>>,
+}
+
+impl RevocationList {
+    pub fn new(origin: &str) -> Self {
+        RevocationList { origin:    origin.to_string(),
+                         serial:    0,
+                         issued_at: clock::utc_now(),
+                         revoked:   BTreeMap::new(),
+                         expires:   BTreeMap::new(), }
+    }
+
+    pub fn origin(&self) -> &str { &self.origin }
+
+    /// The serial number of the list, which every list replacing it must exceed.
+    pub fn serial(&self) -> u64 { self.serial }
+
+    pub fn issued_at(&self) -> DateTime<Utc> { self.issued_at }
+
+    /// Make this list the successor of the one it was loaded from, with the next serial number,
+    /// issued now. Call this before signing a changed list, or it won't replace the installed one.
+    pub fn reissue(&mut self) {
+        self.serial += 1;
+        self.issued_at = clock::utc_now();
+    }
+
+    /// The number of revoked key revisions.
+    pub fn revoked_count(&self) -> usize { self.revoked.len() }
+
+    pub fn revoke(&mut self, name_with_rev: &str, reason: &str) -> Result<()> {
+        self.check_origin(name_with_rev)?;
+        self.revoked
+            .insert(name_with_rev.to_string(), reason.to_string());
+        Ok(())
+    }
+
+    pub fn expire(&mut self, name_with_rev: &str, at: DateTime<Utc>) -> Result<()> {
+        self.check_origin(name_with_rev)?;
+        self.expires.insert(name_with_rev.to_string(), at);
+        Ok(())
+    }
+
+    pub fn status(&self, name_with_rev: &str) -> KeyStatus {
+        self.status_at(name_with_rev, Utc::now())
+    }
+
+    fn status_at(&self, name_with_rev: &str, now: DateTime<Utc>) -> KeyStatus {
+        if let Some(reason) = self.revoked.get(name_with_rev) {
+            return KeyStatus::Revoked(reason.clone());
+        }
+        match self.expires.get(name_with_rev) {
+            Some(at) if *at <= now => KeyStatus::Expired(*at),
+            Some(at) => {
+                let warning = ChronoDuration::from_std(KeyExpiryWarning::configured_value().into())
+                    .unwrap_or_else(|_| ChronoDuration::max_value());
+                if *at - now <= warning {
+                    KeyStatus::Expiring(*at)
+                } else {
+                    KeyStatus::Valid
+                }
+            }
+            None => KeyStatus::Valid,
+        }
+    }
+
+    /// Render the list, signed with `pair`, which must belong to the list's origin.
+    pub fn to_signed_string(&self, pair: &SigKeyPair) -> Result<String> {
+        self.check_origin(&pair.name_with_rev())?;
+        let body = self.body();
+        let signature = pair.sign_payload(body.as_bytes())?;
+        Ok(format!("{}\n{}\n{}\n\n{}",
+                   REVOCATION_LIST_VERSION,
+                   pair.name_with_rev(),
+                   base64::encode(&signature),
+                   body))
+    }
+
+    /// Parse a signed list, verifying its signature with the signer's public key from
+    /// `cache_key_path`. A list may not be signed by a key it revokes or which it says has
+    /// expired.
+    pub fn from_signed_str<P>(content: &str, cache_key_path: &P) -> Result<Self>
+        where P: AsRef<Path> + ?Sized
+    {
+        Self::from_signed_str_with_signer(content, cache_key_path).map(|(list, _)| list)
+    }
+
+    /// As `from_signed_str`, also returning the name of the key which signed the list.
+    fn from_signed_str_with_signer<P>(content: &str, cache_key_path: &P) -> Result<(Self, String)>
+        where P: AsRef<Path> + ?Sized
+    {
+        let mut parts = content.splitn(4, '\n');
+        let mut next = |what: &str| {
+            parts.next()
+                 .ok_or_else(|| {
+                     Error::CryptoError(format!("Corrupt revocation list, can't read {}", what))
+                 })
+        };
+        let version = next("format version")?;
+        if version.trim() != REVOCATION_LIST_VERSION {
+            return Err(Error::CryptoError(format!("Unsupported revocation list \
+                                                   version: {}",
+                                                  version.trim())));
+        }
+        let signer = next("signer")?.trim().to_string();
+        let signature =
+            base64::decode(next("signature")?.trim()).map_err(|e| {
+                Error::CryptoError(format!("Can't decode revocation list signature: {}", e))
+            })?;
+        let rest = next("end of header")?;
+        if !rest.starts_with('\n') {
+            return Err(Error::CryptoError("Corrupt revocation list, can't find \
+                                           end of header"
+                                                         .to_string()));
+        }
+        let body = &rest[1..];
+
+        SigKeyPair::get_pair_for(&signer, cache_key_path)?.verify_payload(body.as_bytes(),
+                                                                          &signature)?;
+        let (origin, _) = parse_name_with_rev(&signer)?;
+        let list = Self::parse_body(&origin, body)?;
+        match list.status(&signer) {
+            KeyStatus::Revoked(_) | KeyStatus::Expired(_) => {
+                Err(Error::CryptoError(format!("Revocation list for {} is \
+                                                signed with {}, which it \
+                                                does not trust",
+                                               origin, signer)))
+            }
+            _ => Ok((list, signer)),
+        }
+    }
+
+    /// Verify a signed list and save it to `cache_key_path`, replacing any previous list for its
+    /// origin.
+    ///
+    /// A list replaces the installed one only if it is signed by a key which the installed list
+    /// still trusts, and which is no older than the key which signed the installed list. A list
+    /// signed by the same key must have a higher serial number. Installing the installed list
+    /// again changes nothing.
+    pub fn install<P>(content: &str, cache_key_path: &P) -> Result<Self>
+        where P: AsRef<Path> + ?Sized
+    {
+        let (list, signer) = Self::from_signed_str_with_signer(content, cache_key_path)?;
+        if let Some((installed, installed_signer)) =
+            Self::load_with_signer(&list.origin, cache_key_path)?
+        {
+            if list == installed {
+                return Ok(list);
+            }
+            if let KeyStatus::Revoked(_) | KeyStatus::Expired(_) = installed.status(&signer) {
+                return Err(Error::CryptoError(format!("Revocation list for {} is signed with \
+                                                       {}, which the installed list does not \
+                                                       trust",
+                                                      list.origin, signer)));
+            }
+            // Key revisions are timestamps of the same length, so they sort by age.
+            let (_, revision) = parse_name_with_rev(&signer)?;
+            let (_, installed_revision) = parse_name_with_rev(&installed_signer)?;
+            if revision < installed_revision {
+                return Err(Error::CryptoError(format!("Revocation list for {} is signed with \
+                                                       {}, which is older than {}, which signed \
+                                                       the installed list",
+                                                      list.origin, signer, installed_signer)));
+            }
+            if revision == installed_revision && list.serial <= installed.serial {
+                return Err(Error::CryptoError(format!("Revocation list {} for {} is not newer \
+                                                       than the installed list {}",
+                                                      list.serial,
+                                                      list.origin,
+                                                      installed.serial)));
+            }
+        }
+        let path = Self::path_for(&list.origin, cache_key_path);
+        AtomicWriter::new(&path)?.with_writer(|f| {
+                                     use std::io::Write;
+                                     f.write_all(content.as_bytes())
+                                 })?;
+        Ok(list)
+    }
+
+    /// Load the installed list for `origin`, if there is one.
+    pub fn load<P>(origin: &str, cache_key_path: &P) -> Result<Option<Self>>
+        where P: AsRef<Path> + ?Sized
+    {
+        Ok(Self::load_with_signer(origin, cache_key_path)?.map(|(list, _)| list))
+    }
+
+    /// As `load`, also returning the name of the key which signed the list.
+    fn load_with_signer<P>(origin: &str, cache_key_path: &P) -> Result<Option<(Self, String)>>
+        where P: AsRef<Path> + ?Sized
+    {
+        let path = Self::path_for(origin, cache_key_path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        Self::from_signed_str_with_signer(&content, cache_key_path).map(Some)
+    }
+
+    pub fn path_for<P>(origin: &str, cache_key_path: &P) -> PathBuf
+        where P: AsRef<Path> + ?Sized
+    {
+        cache_key_path.as_ref()
+                      .join(format!("{}.{}", origin, REVOCATION_LIST_SUFFIX))
+    }
+
+    fn body(&self) -> String {
+        let mut body = format!("serial {}\nissued-at {}\n",
+                               self.serial,
+                               self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true));
+        for (key, reason) in &self.revoked {
+            body.push_str(&format!("revoke {} {}\n", key, reason));
+        }
+        for (key, at) in &self.expires {
+            body.push_str(&format!("expire {} {}\n",
+                                   key,
+                                   at.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        }
+        body
+    }
+
+    fn parse_body(origin: &str, body: &str) -> Result<Self> {
+        let mut list = Self::new(origin);
+        let mut lines = body.lines().map(str::trim).filter(|l| !l.is_empty());
+        let mut header = |name: &str| {
+            match lines.next().map(|l| l.splitn(2, ' ').collect::<Vec<_>>()).as_deref() {
+                Some([field, value]) if *field == name => Ok(value.trim().to_string()),
+                _ => {
+                    Err(Error::CryptoError(format!("Corrupt revocation list, can't read {}",
+                                                   name)))
+                }
+            }
+        };
+        list.serial = header("serial")?.parse().map_err(|e| {
+                          Error::CryptoError(format!("Invalid revocation list serial: {}", e))
+                      })?;
+        let issued_at = DateTime::parse_from_rfc3339(&header("issued-at")?).map_err(|e| {
+                            Error::CryptoError(format!("Invalid revocation list issue time: {}", e))
+                        })?;
+        list.issued_at = issued_at.with_timezone(&Utc);
+        for line in lines {
+            let mut fields = line.splitn(3, ' ');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some("revoke"), Some(key), reason) => {
+                    list.revoke(key, reason.unwrap_or("no reason given"))?
+                }
+                (Some("expire"), Some(key), Some(at)) => {
+                    let at = DateTime::parse_from_rfc3339(at.trim()).map_err(|e| {
+                                 Error::CryptoError(format!("Invalid expiry for {} in revocation \
+                                                             list: {}",
+                                                            key, e))
+                             })?;
+                    list.expire(key, at.with_timezone(&Utc))?
+                }
+                _ => {
+                    return Err(Error::CryptoError(format!("Invalid revocation list \
+                                                           entry: {}",
+                                                          line)));
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    /// An origin may only make statements about its own keys.
+    fn check_origin(&self, name_with_rev: &str) -> Result<()> {
+        let (name, _) = parse_name_with_rev(name_with_rev)?;
+        if name == self.origin {
+            Ok(())
+        } else {
+            Err(Error::CryptoError(format!("Revocation list for {} can't \
+                                            include {}",
+                                           self.origin, name_with_rev)))
+        }
+    }
+}
+
+/// Check `name_with_rev` against its origin's installed revocation list, failing if the key has
+/// been revoked or has expired and warning if it expires soon.
+pub fn check_key<P>(name_with_rev: &str, cache_key_path: &P) -> Result<()>
+    where P: AsRef<Path> + ?Sized
+{
+    let (origin, _) = parse_name_with_rev(name_with_rev)?;
+    let list = match RevocationList::load(&origin, cache_key_path)? {
+        Some(list) => list,
+        None => return Ok(()),
+    };
+    match list.status(name_with_rev) {
+        KeyStatus::Valid => Ok(()),
+        KeyStatus::Expiring(at) => {
+            warn!("Origin key {} expires at {}. Artifacts signed with it should be re-signed \
+                   with a newer key before then.",
+                  name_with_rev,
+                  at.to_rfc3339_opts(SecondsFormat::Secs, true));
+            Ok(())
+        }
+        KeyStatus::Expired(at) => {
+            Err(Error::KeyExpired(name_with_rev.to_string(),
+                                  at.to_rfc3339_opts(SecondsFormat::Secs,
+                                                     true)))
+        }
+        KeyStatus::Revoked(reason) => Err(Error::KeyRevoked(name_with_rev.to_string(), reason)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_support::manual_clock;
+    use tempfile::Builder;
+
+    fn signed_list(cache: &Path) -> (SigKeyPair, String) {
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache).unwrap();
+        let mut list = RevocationList::new("unicorn");
+        list.revoke("unicorn-20160101000000", "key material leaked")
+            .unwrap();
+        list.expire("unicorn-20170101000000",
+                    "2000-01-01T00:00:00Z".parse().unwrap())
+            .unwrap();
+        let signed = list.to_signed_string(&pair).unwrap();
+        (pair, signed)
+    }
+
+    #[test]
+    fn round_trips_through_its_signed_form() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (pair, signed) = signed_list(cache.path());
+
+        let list = RevocationList::from_signed_str(&signed, cache.path()).unwrap();
+        assert_eq!(list.revoked_count(), 1);
+        assert_eq!(list.status("unicorn-20160101000000"),
+                   KeyStatus::Revoked("key material leaked".to_string()));
+        assert_eq!(list.status("unicorn-20170101000000"),
+                   KeyStatus::Expired("2000-01-01T00:00:00Z".parse().unwrap()));
+        assert_eq!(list.status(&pair.name_with_rev()), KeyStatus::Valid);
+    }
+
+    #[test]
+    fn tampered_lists_are_rejected() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (_, signed) = signed_list(cache.path());
+        let tampered = signed.replace("key material leaked", "just because");
+
+        assert!(RevocationList::from_signed_str(&tampered, cache.path()).is_err());
+    }
+
+    #[test]
+    fn only_keys_of_the_same_origin_can_be_listed() {
+        let mut list = RevocationList::new("unicorn");
+        assert!(list.revoke("dragon-20160101000000", "nope").is_err());
+
+        let dragon = SigKeyPair::generate_pair_for_origin("dragon");
+        assert!(list.to_signed_string(&dragon).is_err());
+    }
+
+    #[test]
+    fn keys_expiring_soon_are_flagged() {
+        let mut list = RevocationList::new("unicorn");
+        let now = Utc::now();
+        list.expire("unicorn-20160101000000", now + ChronoDuration::days(1))
+            .unwrap();
+        list.expire("unicorn-20170101000000", now + ChronoDuration::days(365))
+            .unwrap();
+
+        assert!(matches!(list.status_at("unicorn-20160101000000", now),
+                         KeyStatus::Expiring(_)));
+        assert_eq!(list.status_at("unicorn-20170101000000", now),
+                   KeyStatus::Valid);
+    }
+
+    #[test]
+    fn check_key_uses_the_installed_list() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (pair, signed) = signed_list(cache.path());
+        assert!(check_key("unicorn-20160101000000", cache.path()).is_ok());
+
+        RevocationList::install(&signed, cache.path()).unwrap();
+
+        match check_key("unicorn-20160101000000", cache.path()) {
+            Err(Error::KeyRevoked(key, reason)) => {
+                assert_eq!(key, "unicorn-20160101000000");
+                assert_eq!(reason, "key material leaked");
+            }
+            other => panic!("Expected KeyRevoked, got {:?}", other),
+        }
+        assert!(matches!(check_key("unicorn-20170101000000", cache.path()),
+                         Err(Error::KeyExpired(..))));
+        assert!(check_key(&pair.name_with_rev(), cache.path()).is_ok());
+        assert!(check_key("dragon-20160101000000", cache.path()).is_ok());
+    }
+
+    #[test]
+    fn only_newer_lists_replace_the_installed_one() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (pair, signed) = signed_list(cache.path());
+        let mut list = RevocationList::install(&signed, cache.path()).unwrap();
+        // Installing the same list again, as a Supervisor fetching it periodically does, is fine.
+        RevocationList::install(&signed, cache.path()).unwrap();
+
+        let mut lifted = RevocationList::new("unicorn");
+        lifted.serial = list.serial();
+        assert!(RevocationList::install(&lifted.to_signed_string(&pair).unwrap(), cache.path())
+                    .is_err());
+
+        list.revoke("unicorn-20180101000000", "lost").unwrap();
+        list.reissue();
+        RevocationList::install(&list.to_signed_string(&pair).unwrap(), cache.path()).unwrap();
+        assert!(RevocationList::install(&signed, cache.path()).is_err());
+        let installed = RevocationList::load("unicorn", cache.path()).unwrap()
+                                                                    .unwrap();
+        assert_eq!(installed.serial(), list.serial());
+    }
+
+    #[test]
+    fn lists_signed_by_a_key_the_installed_list_revokes_are_rejected() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
+        let old = SigKeyPair::generate_pair_for_origin("unicorn");
+        old.to_pair_files(cache.path()).unwrap();
+        clock.advance(std::time::Duration::from_secs(1));
+        let new = SigKeyPair::generate_pair_for_origin("unicorn");
+        new.to_pair_files(cache.path()).unwrap();
+        let mut list = RevocationList::new("unicorn");
+        list.revoke(&old.name_with_rev(), "key material leaked")
+            .unwrap();
+        RevocationList::install(&list.to_signed_string(&new).unwrap(), cache.path()).unwrap();
+
+        let mut lifted = RevocationList::new("unicorn");
+        lifted.serial = list.serial() + 1;
+        let err = RevocationList::install(&lifted.to_signed_string(&old).unwrap(), cache.path())
+            .unwrap_err();
+        assert!(err.to_string().contains("which the installed list does not trust"));
+        assert!(check_key(&old.name_with_rev(), cache.path()).is_err());
+    }
+
+    #[test]
+    fn a_newer_key_replaces_a_list_published_with_a_runaway_serial() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
+        let compromised = SigKeyPair::generate_pair_for_origin("unicorn");
+        compromised.to_pair_files(cache.path()).unwrap();
+        let mut runaway = RevocationList::new("unicorn");
+        runaway.serial = u64::max_value();
+        RevocationList::install(&runaway.to_signed_string(&compromised).unwrap(),
+                                cache.path()).unwrap();
+
+        clock.advance(std::time::Duration::from_secs(1));
+        let new = SigKeyPair::generate_pair_for_origin("unicorn");
+        new.to_pair_files(cache.path()).unwrap();
+        let mut list = RevocationList::new("unicorn");
+        list.revoke(&compromised.name_with_rev(), "key material leaked")
+            .unwrap();
+        list.reissue();
+        RevocationList::install(&list.to_signed_string(&new).unwrap(), cache.path()).unwrap();
+        assert!(check_key(&compromised.name_with_rev(), cache.path()).is_err());
+
+        // Nor can a key which is older than the one the installed list was signed with take it
+        // back, even one the list still trusts.
+        clock.advance(std::time::Duration::from_secs(1));
+        let newest = SigKeyPair::generate_pair_for_origin("unicorn");
+        newest.to_pair_files(cache.path()).unwrap();
+        list.reissue();
+        RevocationList::install(&list.to_signed_string(&newest).unwrap(), cache.path()).unwrap();
+        list.reissue();
+        let err = RevocationList::install(&list.to_signed_string(&new).unwrap(), cache.path())
+            .unwrap_err();
+        assert!(err.to_string().contains("which is older than"));
+    }
+
+    #[test]
+    fn lists_without_a_serial_are_rejected() {
+        let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (pair, signed) = signed_list(cache.path());
+        let body = "revoke unicorn-20160101000000 key material leaked\n";
+        let signature = pair.sign_payload(body.as_bytes()).unwrap();
+        let unserialed = format!("{}\n{}\n{}\n\n{}",
+                                 REVOCATION_LIST_VERSION,
+                                 pair.name_with_rev(),
+                                 base64::encode(&signature),
+                                 body);
+
+        assert!(RevocationList::from_signed_str(&signed, cache.path()).is_ok());
+        let err = RevocationList::from_signed_str(&unserialed, cache.path()).unwrap_err();
+        assert!(err.to_string().contains("can't read serial"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Rotation of the ring key a Supervisor encrypts gossip with.
+//!
+//! A ring is moved to a new revision of its key without restarting its Supervisors in three
+//! phases. Each phase has to complete on every Supervisor in the ring before the next one starts:
+//!
+//! 1. **Stage**: the new revision is accepted when decrypting messages, but messages are still
+//!    encrypted with the current revision.
+//! 2. **Activate**: messages are encrypted with the new revision. The previous revision is still
+//!    accepted, as some Supervisors may not have activated the new one yet.
+//! 3. **Retire**: revisions other than the active one are no longer accepted.
+//!
+//! A single Supervisor can also be switched to the latest revision in its key cache, which stages
+//! and activates it in one step.
+
+use super::SymKey;
+use crate::error::{Error,
+                   Result};
+use std::fmt;
+
+/// Generates the revision of `current` that a ring is rotated to.
+pub fn generate_next_revision(current: &SymKey) -> Result<SymKey> {
+    let next = SymKey::generate_pair_for_ring(&current.name);
+    if next.rev <= current.rev {
+        return Err(Error::CryptoError(format!("Cannot generate a revision \
+                                               of {} newer than {}; try \
+                                               again in a second",
+                                              current.name,
+                                              current.name_with_rev())));
+    }
+    Ok(next)
+}
+
+/// The revisions of a ring key that a Supervisor holds while the ring is rotated.
+#[derive(Clone)]
+pub struct RingKeys {
+    /// The revision outgoing messages are encrypted with.
+    active:   SymKey,
+    /// Revisions which are accepted but not encrypted with: a staged next revision, or previous
+    /// revisions which have not been retired.
+    accepted: Vec<SymKey>,
+}
+
+impl fmt::Debug for RingKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingKeys")
+         .field("active", &self.active.name_with_rev())
+         .field("accepted", &self.accepted_revisions())
+         .finish()
+    }
+}
+
+impl RingKeys {
+    pub fn new(active: SymKey) -> Self {
+        RingKeys { active,
+                   accepted: Vec::new() }
+    }
+
+    pub fn active(&self) -> &SymKey { &self.active }
+
+    /// The names and revisions of the keys which are accepted but not encrypted with.
+    pub fn accepted_revisions(&self) -> Vec<String> {
+        self.accepted.iter().map(SymKey::name_with_rev).collect()
+    }
+
+    /// Accept messages encrypted with `key`, a newer revision of the active key. Staging a
+    /// revision which is already held has no effect.
+    pub fn stage(&mut self, key: SymKey) -> Result<()> {
+        key.secret()?;
+        if key.name != self.active.name {
+            return Err(Error::CryptoError(format!("Cannot stage {}, the ring \
+                                                   key is {}",
+                                                  key.name_with_rev(),
+                                                  self.active.name)));
+        }
+        if key.rev == self.active.rev || self.accepted.iter().any(|k| k.rev == key.rev) {
+            return Ok(());
+        }
+        if key.rev < self.active.rev {
+            return Err(Error::CryptoError(format!("Cannot stage {}, which is \
+                                                   older than the active \
+                                                   revision {}",
+                                                  key.name_with_rev(),
+                                                  self.active.name_with_rev())));
+        }
+        self.accepted.push(key);
+        Ok(())
+    }
+
+    /// Encrypt messages with the staged revision `name_with_rev`, while still accepting the
+    /// previously active one. Activating the active revision has no effect.
+    pub fn activate(&mut self, name_with_rev: &str) -> Result<()> {
+        if self.active.name_with_rev() == name_with_rev {
+            return Ok(());
+        }
+        let position = self.accepted
+                           .iter()
+                           .position(|k| k.name_with_rev() == name_with_rev)
+                           .ok_or_else(|| {
+                               Error::CryptoError(format!("Cannot activate {}, which has not \
+                                                           been staged",
+                                                          name_with_rev))
+                           })?;
+        let next = self.accepted.remove(position);
+        let previous = std::mem::replace(&mut self.active, next);
+        self.accepted.push(previous);
+        Ok(())
+    }
+
+    /// Stage and activate `key` in one step, returning whether it was not already active. The
+    /// previously active revision is still accepted until it is retired.
+    pub fn switch_to(&mut self, key: SymKey) -> Result<bool> {
+        if key.name_with_rev() == self.active.name_with_rev() {
+            return Ok(false);
+        }
+        let name_with_rev = key.name_with_rev();
+        self.stage(key)?;
+        self.activate(&name_with_rev)?;
+        Ok(true)
+    }
+
+    /// Stop accepting every revision other than the active one, returning the names and
+    /// revisions of those that were retired.
+    pub fn retire(&mut self) -> Vec<String> {
+        self.accepted.drain(..).map(|k| k.name_with_rev()).collect()
+    }
+
+    /// Encrypts `data` with the active revision, returning the nonce and ciphertext.
+    pub fn encrypt(&self, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> { self.active.encrypt(data) }
+
+    /// Decrypts `ciphertext` with whichever held revision it was encrypted with.
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        match self.active.decrypt(nonce, ciphertext) {
+            Ok(plaintext) => Ok(plaintext),
+            Err(e) => {
+                self.accepted
+                    .iter()
+                    .find_map(|k| k.decrypt(nonce, ciphertext).ok())
+                    .ok_or(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn revision(rev: &str) -> SymKey {
+        let mut key = SymKey::generate_pair_for_ring("beyonce");
+        key.rev = rev.to_string();
+        key
+    }
+
+    #[test]
+    fn rotation_accepts_both_revisions_until_retired() {
+        let current = revision("20200101000000");
+        let next = revision("20200102000000");
+        let mut keys = RingKeys::new(current.clone());
+        let (nonce, from_next) = next.encrypt(b"sup").unwrap();
+
+        assert!(keys.decrypt(&nonce, &from_next).is_err());
+        keys.stage(next.clone()).unwrap();
+        assert_eq!(keys.decrypt(&nonce, &from_next).unwrap(), b"sup");
+        assert_eq!(keys.active().name_with_rev(), current.name_with_rev());
+
+        keys.activate(&next.name_with_rev()).unwrap();
+        assert_eq!(keys.active().name_with_rev(), next.name_with_rev());
+        let (nonce, from_current) = current.encrypt(b"sup").unwrap();
+        assert_eq!(keys.decrypt(&nonce, &from_current).unwrap(), b"sup");
+
+        assert_eq!(keys.retire(), vec![current.name_with_rev()]);
+        assert!(keys.decrypt(&nonce, &from_current).is_err());
+    }
+
+    #[test]
+    fn phases_can_be_repeated() {
+        let next = revision("20200102000000");
+        let mut keys = RingKeys::new(revision("20200101000000"));
+        keys.stage(next.clone()).unwrap();
+        keys.stage(next.clone()).unwrap();
+        assert_eq!(keys.accepted_revisions().len(), 1);
+
+        keys.activate(&next.name_with_rev()).unwrap();
+        keys.activate(&next.name_with_rev()).unwrap();
+        keys.stage(next).unwrap();
+        assert_eq!(keys.accepted_revisions().len(), 1);
+    }
+
+    #[test]
+    fn switching_stages_and_activates_at_once() {
+        let current = revision("20200101000000");
+        let next = revision("20200102000000");
+        let mut keys = RingKeys::new(current.clone());
+        assert!(!keys.switch_to(current.clone()).unwrap());
+        assert!(keys.switch_to(next.clone()).unwrap());
+        assert_eq!(keys.active().name_with_rev(), next.name_with_rev());
+        assert_eq!(keys.accepted_revisions(), vec![current.name_with_rev()]);
+        assert!(keys.switch_to(current).is_err());
+    }
+
+    #[test]
+    fn foreign_and_older_keys_are_not_staged() {
+        let mut keys = RingKeys::new(revision("20200102000000"));
+        assert!(keys.stage(SymKey::generate_pair_for_ring("jay-z")).is_err());
+        assert!(keys.stage(revision("20200101000000")).is_err());
+        assert!(keys.activate("beyonce-20200103000000").is_err());
+    }
+}
@@ -4,14 +4,17 @@ use super::{super::{hash,
                     SECRET_SIG_KEY_SUFFIX,
                     SECRET_SIG_KEY_VERSION},
             get_key_revisions,
+            interchange::{self,
+                          Curve},
             mk_key_filename,
-            mk_revision_string,
             parse_name_with_rev,
             read_key_bytes,
             write_keypair_files,
             KeyPair,
+            KeyRevision,
             KeyType,
             PairType,
+            Passphrase,
             TmpKeyfile};
 use crate::error::{Error,
                    Result};
@@ -19,15 +22,20 @@ use sodiumoxide::{crypto::sign::{self,
                                  ed25519::{PublicKey as SigPublicKey,
                                            SecretKey as SigSecretKey}},
                   randombytes::randombytes};
-use std::{fs,
+use std::{fmt,
+          fs,
           path::{Path,
                  PathBuf}};
 
 pub type SigKeyPair = KeyPair<SigPublicKey, SigSecretKey>;
 
+impl fmt::Debug for SigKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "SigKeyPair") }
+}
+
 impl SigKeyPair {
     pub fn generate_pair_for_origin(name: &str) -> Self {
-        let revision = mk_revision_string();
+        let revision = KeyRevision::new().to_string();
         let (pk, sk) = sign::gen_keypair();
         Self::new(name.to_string(), revision, Some(pk), Some(sk))
     }
@@ -271,15 +279,133 @@ impl SigKeyPair {
     }
 
     pub fn to_pair_files<P: AsRef<Path> + ?Sized>(&self, path: &P) -> Result<()> {
+        self.to_pair_files_with_passphrase(path, None)
+    }
+
+    /// Writes the key files of the pair to `path`, encrypting the secret key with `passphrase`
+    /// if one is given.
+    pub fn to_pair_files_with_passphrase<P: AsRef<Path> + ?Sized>(&self,
+                                                                  path: &P,
+                                                                  passphrase: Option<&Passphrase>)
+                                                                  -> Result<()> {
         let public_keyfile = mk_key_filename(path, self.name_with_rev(), PUBLIC_KEY_SUFFIX);
         let secret_keyfile = mk_key_filename(path, self.name_with_rev(), SECRET_SIG_KEY_SUFFIX);
         debug!("public sig keyfile = {}", public_keyfile.display());
         debug!("secret sig keyfile = {}", secret_keyfile.display());
 
+        let secret = match passphrase {
+            Some(passphrase) => {
+                super::passphrase::encrypt(&self.to_secret_string()?, KeyType::Sig, passphrase)?
+            }
+            None => self.to_secret_string()?,
+        };
         write_keypair_files(Some(&public_keyfile),
                             Some(self.to_public_string()?),
                             Some(&secret_keyfile),
-                            Some(self.to_secret_string()?))
+                            Some(secret))
+    }
+
+    /// The public or secret key as PEM. A secret key is written as its seed, as PKCS #8 expects.
+    pub fn to_pem(&self, pair_type: PairType) -> Result<String> {
+        let key = match pair_type {
+            PairType::Public => self.public()?[..].to_vec(),
+            PairType::Secret => self.secret()?[..sign::SEEDBYTES].to_vec(),
+        };
+        Ok(interchange::to_pem(Curve::Ed25519, pair_type, &key))
+    }
+
+    /// Reads a public or secret key from PEM as the key `name_with_rev`. The public key of a
+    /// secret key is derived from it.
+    pub fn from_pem(name_with_rev: &str, pem: &str) -> Result<Self> {
+        let (name, rev) = parse_name_with_rev(name_with_rev)?;
+        let (pair_type, key) = interchange::from_pem(Curve::Ed25519, pem)?;
+        match pair_type {
+            PairType::Public => {
+                Ok(Self::new(name,
+                             rev,
+                             Some(Self::public_key_from(&key)?),
+                             None))
+            }
+            PairType::Secret => {
+                let (pk, sk) = Self::pair_from_seed(&key)?;
+                Ok(Self::new(name, rev, Some(pk), Some(sk)))
+            }
+        }
+    }
+
+    /// The key as a JWK, with its secret key if `pair_type` is `Secret`.
+    pub fn to_jwk(&self, pair_type: PairType) -> Result<String> {
+        let secret = match pair_type {
+            PairType::Public => None,
+            PairType::Secret => Some(&self.secret()?[..sign::SEEDBYTES]),
+        };
+        let public = match self.secret.as_ref() {
+            Some(sk) if self.public.is_none() => sk.public_key(),
+            _ => *self.public()?,
+        };
+        interchange::to_jwk(Curve::Ed25519, &self.name_with_rev(), &public[..], secret)
+    }
+
+    /// Reads a key from a JWK, named by its `kid`.
+    pub fn from_jwk(jwk: &str) -> Result<Self> {
+        let jwk = interchange::from_jwk(Curve::Ed25519, jwk)?;
+        let (name, rev) = parse_name_with_rev(&jwk.name_with_rev)?;
+        let public = Self::public_key_from(&jwk.public)?;
+        match jwk.secret {
+            Some(seed) => {
+                let (pk, sk) = Self::pair_from_seed(&seed)?;
+                if pk != public {
+                    return Err(Error::CryptoError(format!("The public and secret keys \
+                                                           of JWK {} do not match",
+                                                          jwk.name_with_rev)));
+                }
+                Ok(Self::new(name, rev, Some(pk), Some(sk)))
+            }
+            None => Ok(Self::new(name, rev, Some(public), None)),
+        }
+    }
+
+    /// The raw bytes of the public key, for handing to whoever will verify this pair's signatures.
+    pub fn public_key_bytes(&self) -> Result<Vec<u8>> { Ok(self.public()?.as_ref().to_vec()) }
+
+    /// A pair of just the public key in `bytes`, as returned by `public_key_bytes`, for verifying
+    /// signatures.
+    pub fn from_public_key_bytes(name: String, rev: String, bytes: &[u8]) -> Result<Self> {
+        Ok(Self::new(name, rev, Some(Self::public_key_from(bytes)?), None))
+    }
+
+    fn public_key_from(bytes: &[u8]) -> Result<SigPublicKey> {
+        let err = || Error::CryptoError(String::from("Can't convert key bytes to SigPublicKey"));
+        SigPublicKey::from_slice(bytes).ok_or_else(err)
+    }
+
+    fn pair_from_seed(seed: &[u8]) -> Result<(SigPublicKey, SigSecretKey)> {
+        let err = || Error::CryptoError(String::from("Can't convert key bytes to a seed"));
+        let seed = sign::Seed::from_slice(seed).ok_or_else(err)?;
+        Ok(sign::keypair_from_seed(&seed))
+    }
+
+    /// Create a detached signature over the hash of `payload`.
+    pub fn sign_payload(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let hash = hash::hash_bytes(payload);
+        Ok(sign::sign_detached(hash.as_bytes(), self.secret()?).as_ref()
+                                                               .to_vec())
+    }
+
+    /// Verify a signature created by `sign_payload`.
+    pub fn verify_payload(&self, payload: &[u8], signature: &[u8]) -> Result<()> {
+        let signature = sign::Signature::from_slice(signature).ok_or_else(|| {
+                            Error::CryptoError(format!("Malformed signature from {}",
+                                                       self.name_with_rev()))
+                        })?;
+        let hash = hash::hash_bytes(payload);
+        if sign::verify_detached(&signature, hash.as_bytes(), self.public()?) {
+            Ok(())
+        } else {
+            Err(Error::CryptoError(format!("Signature verification with \
+                                            {} failed",
+                                           self.name_with_rev())))
+        }
     }
 
     fn get_public_key(key_with_rev: &str, cache_key_path: &Path) -> Result<SigPublicKey> {
@@ -313,7 +439,8 @@ impl SigKeyPair {
 mod test {
     use std::{fs::{self,
                    File},
-              io::Read};
+              io::Read,
+              time::Duration};
 
     use tempfile::Builder;
 
@@ -363,6 +490,7 @@ mod test {
     #[test]
     fn get_pairs_for() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         let pairs = SigKeyPair::get_pairs_for("unicorn", cache.path(), None).unwrap();
         assert_eq!(pairs.len(), 0);
 
@@ -371,14 +499,9 @@ mod test {
         let pairs = SigKeyPair::get_pairs_for("unicorn", cache.path(), None).unwrap();
         assert_eq!(pairs.len(), 1);
 
-        match wait_until_ok(|| {
-                  let p = SigKeyPair::generate_pair_for_origin("unicorn");
-                  p.to_pair_files(cache.path())?;
-                  Ok(())
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        pair.to_pair_files(cache.path()).unwrap();
         let pairs = SigKeyPair::get_pairs_for("unicorn", cache.path(), None).unwrap();
         assert_eq!(pairs.len(), 2);
 
@@ -401,16 +524,12 @@ mod test {
     #[test]
     fn get_pair_for() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         let p1 = SigKeyPair::generate_pair_for_origin("unicorn");
         p1.to_pair_files(cache.path()).unwrap();
-        let p2 = match wait_until_ok(|| {
-                  let p = SigKeyPair::generate_pair_for_origin("unicorn");
-                  p.to_pair_files(cache.path())?;
-                  Ok(p)
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let p2 = SigKeyPair::generate_pair_for_origin("unicorn");
+        p2.to_pair_files(cache.path()).unwrap();
 
         let p1_fetched = SigKeyPair::get_pair_for(&p1.name_with_rev(), cache.path()).unwrap();
         assert_eq!(p1.name, p1_fetched.name);
@@ -441,16 +560,12 @@ mod test {
     #[test]
     fn get_latest_pair_for_multiple() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         SigKeyPair::generate_pair_for_origin("unicorn").to_pair_files(cache.path())
                                                        .unwrap();
-        let p2 = match wait_until_ok(|| {
-                  let p = SigKeyPair::generate_pair_for_origin("unicorn");
-                  p.to_pair_files(cache.path())?;
-                  Ok(p)
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let p2 = SigKeyPair::generate_pair_for_origin("unicorn");
+        p2.to_pair_files(cache.path()).unwrap();
 
         let latest = SigKeyPair::get_latest_pair_for("unicorn", cache.path(), None).unwrap();
         assert_eq!(latest.name, p2.name);
@@ -638,6 +753,30 @@ mod test {
         SigKeyPair::write_file_from_str("SIG-PUB-1\n", cache.path()).unwrap();
     }
 
+    #[test]
+    fn sign_and_verify_payload() {
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        let signature = pair.sign_payload(b"some payload").unwrap();
+
+        assert!(pair.verify_payload(b"some payload", &signature).is_ok());
+        assert!(pair.verify_payload(b"another payload", &signature).is_err());
+        assert!(pair.verify_payload(b"some payload", &signature[1..])
+                    .is_err());
+    }
+
+    #[test]
+    fn verify_payload_with_public_key_bytes() {
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        let signature = pair.sign_payload(b"some payload").unwrap();
+        let public = SigKeyPair::from_public_key_bytes(pair.name.clone(),
+                                                       pair.rev.clone(),
+                                                       &pair.public_key_bytes().unwrap()).unwrap();
+
+        assert!(public.secret().is_err());
+        assert!(public.verify_payload(b"some payload", &signature).is_ok());
+        assert!(SigKeyPair::from_public_key_bytes(pair.name, pair.rev, b"short").is_err());
+    }
+
     #[test]
     #[should_panic(expected = "write_key_from_str:3 Malformed key string")]
     fn write_file_from_str_missing_key_secret() {
@@ -684,6 +823,26 @@ mod test {
         SigKeyPair::write_file_from_str(k, cache.path()).unwrap();
     }
 
+    #[test]
+    fn pem_and_jwk_round_trip() {
+        let pair = SigKeyPair::generate_pair_for_origin("unicorn");
+        let name_with_rev = pair.name_with_rev();
+
+        let public =
+            SigKeyPair::from_pem(&name_with_rev, &pair.to_pem(PairType::Public).unwrap()).unwrap();
+        assert_eq!(public.public().unwrap(), pair.public().unwrap());
+        assert!(public.secret().is_err());
+        let secret =
+            SigKeyPair::from_pem(&name_with_rev, &pair.to_pem(PairType::Secret).unwrap()).unwrap();
+        assert!(secret == pair);
+
+        let public = SigKeyPair::from_jwk(&pair.to_jwk(PairType::Public).unwrap()).unwrap();
+        assert_eq!(public.name_with_rev(), name_with_rev);
+        assert!(public.secret().is_err());
+        let secret = SigKeyPair::from_jwk(&pair.to_jwk(PairType::Secret).unwrap()).unwrap();
+        assert!(secret == pair);
+    }
+
     #[test]
     #[should_panic(expected = "Existing key file")]
     fn write_file_from_str_key_exists_but_hashes_differ_public() {
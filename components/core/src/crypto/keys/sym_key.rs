@@ -3,18 +3,20 @@ use super::{super::{hash,
                     SECRET_SYM_KEY_VERSION},
             get_key_revisions,
             mk_key_filename,
-            mk_revision_string,
             parse_name_with_rev,
             read_key_bytes,
             write_keypair_files,
             KeyPair,
+            KeyRevision,
             KeyType,
             PairType,
+            Passphrase,
             TmpKeyfile};
 use crate::error::{Error,
                    Result};
-use sodiumoxide::{crypto::secretbox::{self,
-                                      Key as SymSecretKey},
+use sodiumoxide::{crypto::{pwhash::argon2id13,
+                           secretbox::{self,
+                                       Key as SymSecretKey}},
                   randombytes::randombytes};
 use std::{fmt,
           fs,
@@ -29,11 +31,31 @@ impl fmt::Debug for SymKey {
 
 impl SymKey {
     pub fn generate_pair_for_ring(name: &str) -> Self {
-        let revision = mk_revision_string();
+        let revision = KeyRevision::new().to_string();
         let secret_key = secretbox::gen_key();
         SymKey::new(name.to_string(), revision, Some(()), Some(secret_key))
     }
 
+    /// Derives revision `revision` of the ring key `name` from `passphrase` with Argon2id, salted
+    /// with the name and revision, so that every Supervisor of a ring can derive the same key
+    /// instead of having a key file copied to it. Each revision derives a different key, even
+    /// from the same passphrase, so a ring can be rotated to a newer derived key.
+    pub fn from_passphrase(name: &str, revision: &str, passphrase: &Passphrase) -> Result<Self> {
+        let name_with_rev = format!("{}-{}", name, revision);
+        parse_name_with_rev(&name_with_rev)?;
+        let name_hash = hash::hash_string(&format!("habitat-ring-key:{}", name_with_rev));
+        let mut salt = argon2id13::Salt([0; argon2id13::SALTBYTES]);
+        hex::decode_to_slice(&name_hash[..2 * argon2id13::SALTBYTES], &mut salt.0)
+            .expect("hashes are hex");
+        let secret_key = passphrase.derive_key(&salt,
+                                               argon2id13::OPSLIMIT_MODERATE,
+                                               argon2id13::MEMLIMIT_MODERATE)?;
+        Ok(SymKey::new(name.to_string(),
+                       revision.to_string(),
+                       Some(()),
+                       Some(secret_key)))
+    }
+
     pub fn get_pairs_for<P: AsRef<Path> + ?Sized>(name: &str,
                                                   cache_key_path: &P)
                                                   -> Result<Vec<Self>> {
@@ -208,13 +230,24 @@ impl SymKey {
     }
 
     pub fn to_pair_files<P: AsRef<Path> + ?Sized>(&self, path: &P) -> Result<()> {
+        self.to_pair_files_with_passphrase(path, None)
+    }
+
+    /// Writes the key file to `path`, encrypted with `passphrase` if one is given.
+    pub fn to_pair_files_with_passphrase<P: AsRef<Path> + ?Sized>(&self,
+                                                                  path: &P,
+                                                                  passphrase: Option<&Passphrase>)
+                                                                  -> Result<()> {
         let secret_keyfile = mk_key_filename(path, self.name_with_rev(), SECRET_SYM_KEY_SUFFIX);
         debug!("secret sym keyfile = {}", secret_keyfile.display());
 
-        write_keypair_files(None,
-                            None,
-                            Some(&secret_keyfile),
-                            Some(self.to_secret_string()?))
+        let secret = match passphrase {
+            Some(passphrase) => {
+                super::passphrase::encrypt(&self.to_secret_string()?, KeyType::Sym, passphrase)?
+            }
+            None => self.to_secret_string()?,
+        };
+        write_keypair_files(None, None, Some(&secret_keyfile), Some(secret))
     }
 
     fn get_secret_key(key_with_rev: &str, cache_key_path: &Path) -> Result<SymSecretKey> {
@@ -348,12 +381,14 @@ impl SymKey {
 mod test {
     use std::{fs::{self,
                    File},
-              io::Read};
+              io::Read,
+              time::Duration};
 
     use tempfile::Builder;
 
     use super::{super::{super::test_support::*,
-                        PairType},
+                        PairType,
+                        Passphrase},
                 SymKey};
 
     static VALID_KEY: &str = "ring-key-valid-20160504220722.sym.key";
@@ -391,9 +426,37 @@ mod test {
                      .exists());
     }
 
+    #[test]
+    fn ring_keys_derived_from_a_passphrase_are_the_same_everywhere() {
+        let passphrase = Passphrase::new("correct horse battery staple");
+        let key = SymKey::from_passphrase("beyonce", "20200601000000", &passphrase).unwrap();
+        let again = SymKey::from_passphrase("beyonce", "20200601000000", &passphrase).unwrap();
+        assert_eq!(key.name_with_rev(), "beyonce-20200601000000");
+        assert_eq!(key.secret().unwrap(), again.secret().unwrap());
+
+        let other_ring = SymKey::from_passphrase("jayz", "20200601000000", &passphrase).unwrap();
+        assert_ne!(key.secret().unwrap(), other_ring.secret().unwrap());
+        let other_passphrase =
+            SymKey::from_passphrase("beyonce", "20200601000000", &Passphrase::new("tr0ub4dor"));
+        assert_ne!(key.secret().unwrap(),
+                   other_passphrase.unwrap().secret().unwrap());
+    }
+
+    #[test]
+    fn ring_keys_derived_from_a_passphrase_differ_by_revision() {
+        let passphrase = Passphrase::new("correct horse battery staple");
+        let key = SymKey::from_passphrase("beyonce", "20200601000000", &passphrase).unwrap();
+        let next = SymKey::from_passphrase("beyonce", "20201201000000", &passphrase).unwrap();
+        assert_eq!(next.name_with_rev(), "beyonce-20201201000000");
+        assert_ne!(key.secret().unwrap(), next.secret().unwrap());
+
+        assert!(SymKey::from_passphrase("beyonce", "next", &passphrase).is_err());
+    }
+
     #[test]
     fn get_pairs_for() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         let pairs = SymKey::get_pairs_for("beyonce", cache.path()).unwrap();
         assert_eq!(pairs.len(), 0);
 
@@ -402,14 +465,9 @@ mod test {
         let pairs = SymKey::get_pairs_for("beyonce", cache.path()).unwrap();
         assert_eq!(pairs.len(), 1);
 
-        match wait_until_ok(|| {
-                  let rpair = SymKey::generate_pair_for_ring("beyonce");
-                  rpair.to_pair_files(cache.path())?;
-                  Ok(())
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let pair = SymKey::generate_pair_for_ring("beyonce");
+        pair.to_pair_files(cache.path()).unwrap();
         let pairs = SymKey::get_pairs_for("beyonce", cache.path()).unwrap();
         assert_eq!(pairs.len(), 2);
 
@@ -423,16 +481,12 @@ mod test {
     #[test]
     fn get_pair_for() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         let p1 = SymKey::generate_pair_for_ring("beyonce");
         p1.to_pair_files(cache.path()).unwrap();
-        let p2 = match wait_until_ok(|| {
-                  let rpath = SymKey::generate_pair_for_ring("beyonce");
-                  rpath.to_pair_files(cache.path())?;
-                  Ok(rpath)
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let p2 = SymKey::generate_pair_for_ring("beyonce");
+        p2.to_pair_files(cache.path()).unwrap();
 
         let p1_fetched = SymKey::get_pair_for(&p1.name_with_rev(), cache.path()).unwrap();
         assert_eq!(p1.name, p1_fetched.name);
@@ -463,16 +517,12 @@ mod test {
     #[test]
     fn get_latest_pair_for_multiple() {
         let cache = Builder::new().prefix("key_cache").tempdir().unwrap();
+        let (clock, _clock) = manual_clock();
         SymKey::generate_pair_for_ring("beyonce").to_pair_files(cache.path())
                                                  .unwrap();
-        let p2 = match wait_until_ok(|| {
-                  let rpath = SymKey::generate_pair_for_ring("beyonce");
-                  rpath.to_pair_files(cache.path())?;
-                  Ok(rpath)
-              }) {
-            Some(pair) => pair,
-            None => panic!("Failed to generate another keypair after waiting"),
-        };
+        clock.advance(Duration::from_secs(1));
+        let p2 = SymKey::generate_pair_for_ring("beyonce");
+        p2.to_pair_files(cache.path()).unwrap();
 
         let latest = SymKey::get_latest_pair_for("beyonce", cache.path()).unwrap();
         assert_eq!(latest.name, p2.name);
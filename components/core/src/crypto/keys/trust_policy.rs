@@ -0,0 +1,119 @@
+//! Policies on which origin keys are trusted to sign artifacts, over and above a valid signature.
+//!
+//! A policy is written in TOML, and every part of it is optional:
+//!
+//! ```toml
+//! # Reject keys whose revision is more than this many days old
+//! max_key_age_days = 365
+//! # Only accept keys of these origins, or these exact revisions
+//! allowed_keys = ["core", "acme-20200101000000"]
+//! # Reject these revisions, whether or not their origin has revoked them
+//! revoked_keys = ["core-20160810182414"]
+//! ```
+//!
+//! `artifact::verify` consults the policy kept as `trust-policy.toml` in the key cache, if there
+//! is one, and `hab pkg verify --policy` can be given another.
+
+use super::parse_name_with_rev;
+use crate::error::{Error,
+                   Result};
+use chrono::{DateTime,
+             Duration,
+             NaiveDateTime,
+             Utc};
+use serde_derive::Deserialize;
+use std::{fs,
+          path::Path};
+
+/// The file in the key cache holding its trust policy.
+pub const TRUST_POLICY_FILE: &str = "trust-policy.toml";
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TrustPolicy {
+    /// How many days old a key revision may be.
+    pub max_key_age_days: Option<u32>,
+    /// The origins, or origin key revisions, which are trusted. Every key is when this is unset.
+    pub allowed_keys:     Option<Vec<String>>,
+    #[serde(default)]
+    pub revoked_keys:     Vec<String>,
+}
+
+impl TrustPolicy {
+    /// Reads the policy in the file at `path`.
+    pub fn from_file<P: AsRef<Path> + ?Sized>(path: &P) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref()).map_err(|e| {
+                          Error::CryptoError(format!("Can't read trust policy {}: {}",
+                                                     path.as_ref().display(),
+                                                     e))
+                      })?;
+        toml::from_str(&content).map_err(Error::ConfigFileSyntax)
+    }
+
+    /// The policy of the key cache at `cache_key_path`, which trusts every key if the cache has
+    /// none.
+    pub fn load<P: AsRef<Path> + ?Sized>(cache_key_path: &P) -> Result<Self> {
+        let path = cache_key_path.as_ref().join(TRUST_POLICY_FILE);
+        if path.is_file() {
+            Self::from_file(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Checks that the origin key revision `name_with_rev` is trusted to sign artifacts.
+    pub fn check(&self, name_with_rev: &str) -> Result<()> {
+        self.check_at(name_with_rev, Utc::now())
+    }
+
+    fn check_at(&self, name_with_rev: &str, now: DateTime<Utc>) -> Result<()> {
+        let (origin, rev) = parse_name_with_rev(name_with_rev)?;
+        let reject = |reason: String| Err(Error::KeyNotTrusted(name_with_rev.to_string(), reason));
+        if self.revoked_keys.iter().any(|k| k == name_with_rev) {
+            return reject("the policy revokes it".to_string());
+        }
+        if let Some(ref allowed) = self.allowed_keys {
+            if !allowed.iter().any(|k| *k == origin || k == name_with_rev) {
+                return reject("the policy does not allow it".to_string());
+            }
+        }
+        if let Some(days) = self.max_key_age_days {
+            let created = NaiveDateTime::parse_from_str(&rev, "%Y%m%d%H%M%S").map_err(|e| {
+                              Error::CryptoError(format!("Invalid key revision {}: {}", rev, e))
+                          })?;
+            let age = now.signed_duration_since(DateTime::<Utc>::from_utc(created, Utc));
+            if age > Duration::days(days.into()) {
+                return reject(format!("it is {} days old, and the policy allows at most {}",
+                                      age.num_days(),
+                                      days));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    const POLICY: &str = r#"
+max_key_age_days = 365
+allowed_keys = ["core", "acme-20200101000000"]
+revoked_keys = ["core-20200301000000"]
+"#;
+
+    #[test]
+    fn keys_are_rejected_for_age_allowlist_or_revocation() {
+        let policy: TrustPolicy = toml::from_str(POLICY).unwrap();
+        let now = Utc.ymd(2020, 6, 1).and_hms(0, 0, 0);
+        assert!(policy.check_at("core-20200101000000", now).is_ok());
+        assert!(policy.check_at("acme-20200101000000", now).is_ok());
+        assert!(policy.check_at("acme-20200201000000", now).is_err());
+        assert!(policy.check_at("core-20200301000000", now).is_err());
+        assert!(policy.check_at("core-20190101000000", now).is_err());
+        assert!(TrustPolicy::default().check_at("anyone-20100101000000", now)
+                                      .is_ok());
+        assert!(toml::from_str::<TrustPolicy>("max_age = 1").is_err());
+    }
+}
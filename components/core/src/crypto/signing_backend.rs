@@ -0,0 +1,31 @@
+//! Where artifact signatures are made.
+//!
+//! A `SigKeyPair` signs with its secret key in memory. Built with the `pkcs11` feature, a
+//! `Pkcs11Signer` has a hardware token sign instead, so that the secret key never leaves it.
+//! Either signs with Ed25519, and the artifact is verified with the public key in the key cache
+//! in the same way.
+
+use super::{keys::KeyPair,
+            SigKeyPair};
+use crate::error::Result;
+use sodiumoxide::crypto::sign;
+
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+
+#[cfg(feature = "pkcs11")]
+pub use self::pkcs11::Pkcs11Signer;
+
+pub trait SigningBackend {
+    /// The name with revision of the signing key, by which verifiers find its public key.
+    fn name_with_rev(&self) -> String;
+
+    /// `message`, signed: its signature followed by the message itself.
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>>;
+}
+
+impl SigningBackend for SigKeyPair {
+    fn name_with_rev(&self) -> String { KeyPair::name_with_rev(self) }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> { Ok(sign::sign(message, self.secret()?)) }
+}
@@ -0,0 +1,115 @@
+//! Signing with an Ed25519 key held by a PKCS #11 token, such as an HSM.
+//!
+//! The token's key is labelled with its name with revision, like the files of a key cache, and
+//! its public key is exported to the key caches of whoever verifies what it signs. The module is
+//! named by `HAB_PKCS11_MODULE`, and the PIN of the token is read from the file named by
+//! `HAB_PKCS11_PIN_FILE`.
+
+use super::SigningBackend;
+use crate::{crypto::keys::parse_name_with_rev,
+            error::{Error,
+                    Result}};
+use cryptoki::{context::{CInitializeArgs,
+                         Pkcs11},
+               mechanism::Mechanism,
+               object::{Attribute,
+                        AttributeType,
+                        KeyType,
+                        ObjectClass,
+                        ObjectHandle},
+               session::{Session,
+                         UserType},
+               types::AuthPin};
+use std::{env,
+          fs};
+
+/// This environment variable names the PKCS #11 module to load.
+pub static PKCS11_MODULE_ENV_VAR: &str = "HAB_PKCS11_MODULE";
+/// This environment variable names a file holding the PIN of the PKCS #11 token.
+pub static PKCS11_PIN_FILE_ENV_VAR: &str = "HAB_PKCS11_PIN_FILE";
+
+pub struct Pkcs11Signer {
+    name_with_rev: String,
+    session:       Session,
+    key:           ObjectHandle,
+}
+
+impl Pkcs11Signer {
+    /// Logs in to the first token of the PKCS #11 module and finds its newest Ed25519 private
+    /// key named `name`.
+    pub fn for_latest_key(name: &str) -> Result<Self> {
+        let module = env::var_os(PKCS11_MODULE_ENV_VAR).ok_or_else(|| {
+                         Error::CryptoError(format!("Set {} to the path of a PKCS #11 module",
+                                                    PKCS11_MODULE_ENV_VAR))
+                     })?;
+        let pkcs11 = Pkcs11::new(module).map_err(pkcs11_error)?;
+        pkcs11.initialize(CInitializeArgs::OsThreads)
+              .map_err(pkcs11_error)?;
+        let slot = pkcs11.get_slots_with_token()
+                         .map_err(pkcs11_error)?
+                         .into_iter()
+                         .next()
+                         .ok_or_else(|| Error::CryptoError(String::from("No PKCS #11 token")))?;
+        let session = pkcs11.open_ro_session(slot).map_err(pkcs11_error)?;
+        session.login(UserType::User, Some(&AuthPin::new(pin()?)))
+               .map_err(pkcs11_error)?;
+
+        let template = [Attribute::Class(ObjectClass::PRIVATE_KEY),
+                        Attribute::KeyType(KeyType::EC_EDWARDS)];
+        let mut keys = Vec::new();
+        for key in session.find_objects(&template).map_err(pkcs11_error)? {
+            for attribute in session.get_attributes(key, &[AttributeType::Label])
+                                    .map_err(pkcs11_error)?
+            {
+                if let Attribute::Label(label) = attribute {
+                    let label = String::from_utf8_lossy(&label).into_owned();
+                    if matches!(parse_name_with_rev(&label), Ok((ref n, _)) if n == name) {
+                        keys.push((label, key));
+                    }
+                }
+            }
+        }
+        // Revisions are timestamps, so the newest key sorts last.
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+        let (name_with_rev, key) = keys.pop().ok_or_else(|| {
+                                                  Error::CryptoError(format!("No Ed25519 private \
+                                                                              key for {} on the \
+                                                                              PKCS #11 token",
+                                                                             name))
+                                              })?;
+        Ok(Pkcs11Signer { name_with_rev,
+                          session,
+                          key })
+    }
+}
+
+impl SigningBackend for Pkcs11Signer {
+    fn name_with_rev(&self) -> String { self.name_with_rev.clone() }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut signed = self.session
+                             .sign(&Mechanism::Eddsa, self.key, message)
+                             .map_err(pkcs11_error)?;
+        signed.extend_from_slice(message);
+        Ok(signed)
+    }
+}
+
+/// The PIN in the first line of the file named by `HAB_PKCS11_PIN_FILE`.
+fn pin() -> Result<String> {
+    let path = env::var_os(PKCS11_PIN_FILE_ENV_VAR).ok_or_else(|| {
+                   Error::CryptoError(format!("Set {} to the path of a file holding the PIN of \
+                                               the PKCS #11 token",
+                                              PKCS11_PIN_FILE_ENV_VAR))
+               })?;
+    let content = fs::read_to_string(&path).map_err(|e| {
+                                               Error::CryptoError(format!("Can't read the PKCS \
+                                                                           #11 PIN file: {}",
+                                                                          e))
+                                           })?;
+    Ok(content.lines().next().unwrap_or_default().to_string())
+}
+
+fn pkcs11_error(err: cryptoki::error::Error) -> Error {
+    Error::CryptoError(format!("PKCS #11: {}", err))
+}
@@ -81,6 +81,18 @@ pub enum Error {
     FullyQualifiedPackageIdentRequired(String),
     /// Occurs when a service binding cannot be successfully parsed.
     InvalidBinding(String),
+    /// Occurs when a CPU limit is not a positive number of CPUs.
+    InvalidCpuLimit(String),
+    /// Occurs when a list of CPUs cannot be successfully parsed.
+    InvalidCpuSet(String),
+    /// Occurs when a duration is not a number of seconds or a number with a known unit.
+    InvalidDuration(String),
+    /// Occurs when a list of hook timeouts cannot be successfully parsed.
+    InvalidHookTimeouts(String),
+    /// Occurs when a key permission policy is not one of the known policies.
+    InvalidKeyPermissionPolicy(String),
+    /// Occurs when a nice level is not a number between -20 and 19.
+    InvalidNiceLevel(String),
     /// Occurs when an origin is in an invalid format
     InvalidOrigin(String),
     /// Occurs when a package identifier string cannot be successfully parsed.
@@ -93,14 +105,31 @@ pub enum Error {
     InvalidPort(ParseIntError),
     /// Occurs when an OsString path cannot be converted to a String
     InvalidPathString(ffi::OsString),
+    /// Occurs when a process priority class is not recognized.
+    InvalidPriorityClass(String),
+    /// Occurs when resource limits are not given as LIMIT=VALUE pairs of known limits.
+    InvalidResourceLimits(String),
+    /// Occurs when a scheduled task is not a hook name and a valid cron expression.
+    InvalidScheduledTask(String),
     /// Occurs when a service group string cannot be successfully parsed.
     InvalidServiceGroup(String),
+    /// Occurs when a size is not a number of bytes or a number with a known unit.
+    InvalidSize(String),
+    /// Occurs when an update window is not a time range with optional days and time zone.
+    InvalidUpdateWindow(String),
     /// Occurs when a Url is in an invalid format.
     InvalidUrl(String),
     /// Occurs when making lower level IO calls.
     IO(io::Error),
     /// Errors when joining paths :)
     JoinPathsError(env::JoinPathsError),
+    /// Occurs when an origin key revision is past the expiry date given by its origin's
+    /// revocation list.
+    KeyExpired(String, String),
+    /// Occurs when an origin key revision is rejected by the trust policy in force.
+    KeyNotTrusted(String, String),
+    /// Occurs when an origin key revision has been revoked by its origin's revocation list.
+    KeyRevoked(String, String),
     // When LogonUserW does not have the correct logon type
     LogonTypeNotGranted,
     /// Occurs when a call to LogonUserW fails
@@ -272,6 +301,37 @@ impl fmt::Display for Error {
                          <NAME> is a service name, and <SERVICE_GROUP> is a valid service group",
                         binding)
             }
+            Error::InvalidCpuLimit(ref e) => {
+                format!("Invalid CPU limit: {}. A CPU limit is a positive number of CPUs, which \
+                         may be fractional (example: 0.5)",
+                        e)
+            }
+            Error::InvalidCpuSet(ref e) => {
+                format!("Invalid CPU set: {}. A valid CPU set is a comma-separated list of CPU \
+                         numbers and ranges (example: 0-3,8)",
+                        e)
+            }
+            Error::InvalidDuration(ref e) => {
+                format!("Invalid duration: {}. A duration is a number of seconds, or numbers with \
+                         the units ms, s, m, h or d (example: 90s, 5m, 1h30m)",
+                        e)
+            }
+            Error::InvalidHookTimeouts(ref e) => {
+                format!("Invalid hook timeouts: {}. Hook timeouts are given as HOOK=SECONDS, \
+                         separated by commas, for the init, run-startup-grace, post-stop and \
+                         reconfigure hooks (example: init=600,post-stop=30)",
+                        e)
+            }
+            Error::InvalidKeyPermissionPolicy(ref e) => {
+                format!("Invalid key permission policy: {}. Valid policies are strict and \
+                         group-readable.",
+                        e)
+            }
+            Error::InvalidNiceLevel(ref e) => {
+                format!("Invalid nice level: {}. Nice levels range from -20 (highest priority) to \
+                         19 (lowest priority)",
+                        e)
+            }
             Error::InvalidOrigin(ref origin) => {
                 format!("Invalid origin: {}. Origins must begin with a lowercase letter or \
                          number. Allowed characters include lowercase letters, numbers, -, and _. \
@@ -293,14 +353,59 @@ impl fmt::Display for Error {
                 format!("Could not generate String from path: {:?}", s)
             }
             Error::InvalidPort(ref e) => format!("Invalid port: {}.", e),
+            Error::InvalidPriorityClass(ref e) => {
+                format!("Invalid priority class: {}. Valid priority classes are idle, \
+                         below-normal, normal, above-normal and high",
+                        e)
+            }
+            Error::InvalidResourceLimits(ref e) => {
+                format!("Invalid resource limits: {}. Resource limits are given as LIMIT=VALUE, \
+                         separated by commas, for the nofile, nproc and core limits, where the \
+                         value is a number or 'unlimited' (example: nofile=65536,core=unlimited)",
+                        e)
+            }
+            Error::InvalidScheduledTask(ref e) => {
+                format!("Invalid scheduled task: {}. A scheduled task is given as HOOK=SCHEDULE, \
+                         where the schedule is a cron expression of five fields, or one of \
+                         @hourly, @daily, @weekly and @monthly, optionally followed by ~SECONDS \
+                         of jitter (example: backup=30 2 * * *~300)",
+                        e)
+            }
             Error::InvalidServiceGroup(ref e) => {
                 format!("Invalid service group: {}. A valid service group string is in the form \
                          service.group (example: redis.production)",
                         e)
             }
+            Error::InvalidSize(ref e) => {
+                format!("Invalid size: {}. A size is a number of bytes, or a number with one of \
+                         the units B, KB, MB, GB, KiB, MiB or GiB (example: 512MB)",
+                        e)
+            }
+            Error::InvalidUpdateWindow(ref e) => {
+                format!("Invalid update window: {}. An update window is a range of times as \
+                         HH:MM-HH:MM, optionally preceded by days of the week and followed by UTC \
+                         or local, the default (example: Sat,Sun 02:00-04:00 UTC)",
+                        e)
+            }
             Error::InvalidUrl(ref url) => format!("Invalid url: {}", url),
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
+            Error::KeyExpired(ref key, ref at) => {
+                format!("Origin key {} expired at {}. Install a release signed with a current \
+                         key, or re-sign your own artifacts with `hab pkg re-sign`.",
+                        key, at)
+            }
+            Error::KeyNotTrusted(ref key, ref reason) => {
+                format!("Origin key {} is not trusted, as {}. Install a release signed with a \
+                         trusted key, or change the trust policy.",
+                        key, reason)
+            }
+            Error::KeyRevoked(ref key, ref reason) => {
+                format!("Origin key {} has been revoked ({}). Artifacts signed with it are no \
+                         longer trusted. Install a release signed with a current key, or re-sign \
+                         your own artifacts with `hab pkg re-sign`.",
+                        key, reason)
+            }
             Error::LogonTypeNotGranted => {
                 "hab_svc_user user must possess the 'SE_SERVICE_LOGON_NAME' account right to be \
                  spawned as a service by the Supervisor"
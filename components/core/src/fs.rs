@@ -10,7 +10,8 @@ use std::{iter,
 #[cfg(windows)]
 use winapi::um::winbase::MoveFileExW;
 
-use crate::{env as henv,
+use crate::{crypto::keys::PairType,
+            env as henv,
             error::{Error,
                     Result},
             os::{process,
@@ -20,6 +21,8 @@ use crate::{env as henv,
                       PackageIdent,
                       PackageInstall}};
 use std::{env,
+          ffi::OsStr,
+          fmt,
           fs,
           io::{self,
                Write},
@@ -87,6 +90,14 @@ pub const DEFAULT_SECRET_KEY_PERMISSIONS: Permissions = Permissions::Explicit(0o
 #[cfg(windows)]
 pub const DEFAULT_SECRET_KEY_PERMISSIONS: Permissions = Permissions::Standard;
 
+/// Permissions applied to downloaded secret keys which the members of their group share, such as
+/// the users of a CI runner.
+#[cfg(not(windows))]
+pub const GROUP_READABLE_SECRET_KEY_PERMISSIONS: Permissions = Permissions::Explicit(0o440);
+/// Permissions applied to downloaded secret keys which the members of their group share.
+#[cfg(windows)]
+pub const GROUP_READABLE_SECRET_KEY_PERMISSIONS: Permissions = Permissions::Standard;
+
 /// An `Option`-like abstraction over platform-specific ways to model
 /// file permissions.
 pub enum Permissions {
@@ -113,6 +124,93 @@ impl Default for Permissions {
     fn default() -> Permissions { Permissions::Standard }
 }
 
+/// Which permissions downloaded keys are given.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyPermissionPolicy {
+    /// Public keys can be read by anyone, and secret keys only by their owner
+    Strict,
+    /// As `Strict`, except that secret keys can also be read by their group
+    GroupReadable,
+}
+
+impl Default for KeyPermissionPolicy {
+    fn default() -> Self { KeyPermissionPolicy::Strict }
+}
+
+impl KeyPermissionPolicy {
+    pub const VARIANTS: &'static [&'static str] = &["strict", "group-readable"];
+
+    /// The permissions a downloaded key of `pair_type` is given under this policy.
+    pub fn permissions(self, pair_type: PairType) -> Permissions {
+        match (pair_type, self) {
+            (PairType::Public, _) => DEFAULT_PUBLIC_KEY_PERMISSIONS,
+            (PairType::Secret, KeyPermissionPolicy::Strict) => DEFAULT_SECRET_KEY_PERMISSIONS,
+            (PairType::Secret, KeyPermissionPolicy::GroupReadable) => {
+                GROUP_READABLE_SECRET_KEY_PERMISSIONS
+            }
+        }
+    }
+}
+
+impl FromStr for KeyPermissionPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "strict" => Ok(KeyPermissionPolicy::Strict),
+            "group-readable" => Ok(KeyPermissionPolicy::GroupReadable),
+            _ => Err(Error::InvalidKeyPermissionPolicy(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for KeyPermissionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyPermissionPolicy::Strict => f.write_str("strict"),
+            KeyPermissionPolicy::GroupReadable => f.write_str("group-readable"),
+        }
+    }
+}
+
+/// Gives the key file at `path` the permissions of `policy` for its `pair_type` and, if `group`
+/// is given, hands it to that group, keeping the current user as its owner.
+#[cfg(not(windows))]
+pub fn set_key_permissions(path: &Path,
+                           pair_type: PairType,
+                           policy: KeyPermissionPolicy,
+                           group: Option<&str>)
+                           -> Result<()> {
+    if let Some(group) = group {
+        let owner = users::get_current_username()?.ok_or_else(|| {
+                        Error::PermissionFailed(format!("Can't change the group of {} to {},                                                          error getting the current user.",
+                                                        path.display(),
+                                                        group))
+                    })?;
+        posix_perm::set_owner(path, owner.as_str(), group)?;
+    }
+    match policy.permissions(pair_type) {
+        Permissions::Explicit(mode) => set_permissions(path, mode),
+        Permissions::Standard => Ok(()),
+    }
+}
+
+/// Gives the key file at `path` the permissions of `policy` for its `pair_type`. Keys are
+/// hardened to their owner on Windows whatever the policy, and can't be handed to a group.
+#[cfg(windows)]
+pub fn set_key_permissions(path: &Path,
+                           _pair_type: PairType,
+                           _policy: KeyPermissionPolicy,
+                           group: Option<&str>)
+                           -> Result<()> {
+    if let Some(group) = group {
+        return Err(Error::PermissionFailed(format!("Can't change the group of {} to {},                                                     key groups aren't supported on Windows.",
+                                                   path.display(),
+                                                   group)));
+    }
+    win_perm::harden_path(path)
+}
+
 lazy_static::lazy_static! {
     /// The default filesystem root path to base all commands from. This is lazily generated on
     /// first call and reflects on the presence and value of the environment variable keyed as
@@ -578,21 +676,29 @@ pub fn find_command<T>(command: T) -> Option<PathBuf>
     // Find the command by checking each entry in `PATH`. If we still can't find it, give up and
     // return `None`.
     match henv::var_os("PATH") {
-        Some(paths) => {
-            for path in env::split_paths(&paths) {
-                let candidate = PathBuf::from(&path).join(command.as_ref());
-                if let Some(result) = find_command_with_pathext(&candidate) {
-                    return Some(result);
-                } else if candidate.is_file() {
-                    return Some(candidate);
-                }
-            }
-            None
-        }
+        Some(paths) => find_command_in_path(command, &paths),
         None => None,
     }
 }
 
+/// Returns the absolute path to the given command by checking each directory in `paths`, a
+/// `PATH`-style list of directories.
+///
+/// If the command is not found, then `None` is returned.
+pub fn find_command_in_path<T>(command: T, paths: &OsStr) -> Option<PathBuf>
+    where T: AsRef<Path>
+{
+    for path in env::split_paths(paths) {
+        let candidate = path.join(command.as_ref());
+        if let Some(result) = find_command_with_pathext(&candidate) {
+            return Some(result);
+        } else if candidate.is_file() {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
 /// Returns the absolute path to the given command from a given package installation.
 ///
 /// If the command is not found, then `None` is returned.
@@ -916,6 +1022,34 @@ mod tests {
             assert!(!sub_file_2.exists());
         }
     }
+
+    mod key_permissions {
+        use super::*;
+
+        #[test]
+        fn policies_round_trip_through_their_names() {
+            for name in KeyPermissionPolicy::VARIANTS {
+                let policy = name.parse::<KeyPermissionPolicy>().unwrap();
+                assert_eq!(&policy.to_string(), name);
+            }
+            assert!("loose".parse::<KeyPermissionPolicy>().is_err());
+        }
+
+        #[test]
+        #[cfg(not(windows))]
+        fn only_secret_keys_are_shared_with_the_group() {
+            let mode = |policy: KeyPermissionPolicy, pair_type| {
+                match policy.permissions(pair_type) {
+                    Permissions::Explicit(mode) => mode,
+                    Permissions::Standard => panic!("Keys should get explicit permissions"),
+                }
+            };
+            assert_eq!(mode(KeyPermissionPolicy::Strict, PairType::Public), 0o444);
+            assert_eq!(mode(KeyPermissionPolicy::GroupReadable, PairType::Public), 0o444);
+            assert_eq!(mode(KeyPermissionPolicy::Strict, PairType::Secret), 0o400);
+            assert_eq!(mode(KeyPermissionPolicy::GroupReadable, PairType::Secret), 0o440);
+        }
+    }
 }
 
 #[cfg(test)]
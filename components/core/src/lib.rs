@@ -8,6 +8,7 @@ pub use self::error::{Error,
                       Result};
 
 pub mod binlink;
+pub mod clock;
 pub mod config;
 pub mod crypto;
 pub mod env;
@@ -18,6 +19,8 @@ pub mod origin;
 pub mod os;
 pub mod package;
 pub mod service;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod url;
 pub mod util;
 
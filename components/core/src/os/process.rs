@@ -13,6 +13,7 @@ mod unix;
 use unix as implementation;
 
 pub mod exec;
+pub mod interpreter;
 
 // Common platform-independent interface
 pub use implementation::{become_command,
@@ -31,18 +32,22 @@ pub use windows::{handle_from_pid,
                   terminate};
 
 use crate::{error::Error,
-            util};
+            util::{self,
+                   units::{self,
+                           UnitValue}}};
 use serde_derive::{Deserialize,
                    Serialize};
-use std::{fmt,
+use std::{convert::TryFrom,
+          fmt,
           result,
           str::FromStr,
           time::Duration};
 
 /// This type encapsulates the number of seconds we should wait after
-/// send a shutdown signal to a process before we kill it.
+/// send a shutdown signal to a process before we kill it. It is parsed
+/// as a duration, so it may be given with units (ex: 90s, 2m).
 #[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Copy, Hash)]
-#[serde(from = "u32", into = "u32")]
+#[serde(try_from = "UnitValue", into = "u32")]
 pub struct ShutdownTimeout(u32);
 
 impl Default for ShutdownTimeout {
@@ -54,7 +59,26 @@ impl Default for ShutdownTimeout {
 impl FromStr for ShutdownTimeout {
     type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> { Ok(ShutdownTimeout(s.parse()?)) }
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::try_from(units::parse_secs(s)?) }
+}
+
+impl TryFrom<u64> for ShutdownTimeout {
+    type Error = Error;
+
+    fn try_from(secs: u64) -> Result<Self, Self::Error> {
+        match u32::try_from(secs) {
+            Ok(secs) => Ok(ShutdownTimeout(secs)),
+            Err(_) => Err(Error::InvalidDuration(format!("{} seconds is too long", secs))),
+        }
+    }
+}
+
+impl TryFrom<UnitValue> for ShutdownTimeout {
+    type Error = Error;
+
+    fn try_from(value: UnitValue) -> Result<Self, Self::Error> {
+        Self::try_from(value.into_secs()?)
+    }
 }
 
 impl fmt::Display for ShutdownTimeout {
@@ -169,6 +193,434 @@ impl From<ShutdownSignal> for Signal {
     fn from(shutdown_signal: ShutdownSignal) -> Self { shutdown_signal.0 }
 }
 
+/// The most CPUs a `CpuSet` may name; the size of the kernel's `cpu_set_t`.
+const MAX_CPUS: usize = 1024;
+
+/// The CPUs a process may be scheduled on, written as a comma-separated list of CPU numbers and
+/// inclusive ranges (ex: `0-3,8`), as `taskset --cpu-list` takes them.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct CpuSet(Vec<usize>);
+
+impl CpuSet {
+    /// The CPU numbers in the set, in ascending order.
+    pub fn cpus(&self) -> &[usize] { &self.0 }
+}
+
+impl FromStr for CpuSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidCpuSet(s.to_string());
+        let mut cpus = Vec::new();
+        for part in s.split(',').map(str::trim) {
+            let mut bounds = part.splitn(2, '-');
+            let start: usize = bounds.next()
+                                     .unwrap_or_default()
+                                     .parse()
+                                     .map_err(|_| invalid())?;
+            let end: usize = match bounds.next() {
+                Some(end) => end.parse().map_err(|_| invalid())?,
+                None => start,
+            };
+            if end < start || end >= MAX_CPUS {
+                return Err(invalid());
+            }
+            cpus.extend(start..=end);
+        }
+        cpus.sort_unstable();
+        cpus.dedup();
+        Ok(CpuSet(cpus))
+    }
+}
+
+impl fmt::Display for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut ranges = Vec::new();
+        let mut cpus = self.0.iter().peekable();
+        while let Some(&start) = cpus.next() {
+            let mut end = start;
+            while cpus.peek() == Some(&&(end + 1)) {
+                end += 1;
+                cpus.next();
+            }
+            if start == end {
+                ranges.push(start.to_string());
+            } else {
+                ranges.push(format!("{}-{}", start, end));
+            }
+        }
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+impl TryFrom<String> for CpuSet {
+    type Error = Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { s.parse() }
+}
+
+impl From<CpuSet> for String {
+    fn from(cpuset: CpuSet) -> Self { cpuset.to_string() }
+}
+
+/// How long, in seconds, the Supervisor lets a service's hooks run before killing them. The
+/// `run` hook is not limited in how long it runs, but in how long it has after starting to pass
+/// its first health check.
+///
+/// These are given as `HOOK=SECONDS`, separated by commas or whitespace (ex:
+/// `init=600,post-stop=30`), both to `hab svc load` and in a package's `HOOK_TIMEOUTS` metafile.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct HookTimeouts {
+    pub init:              Option<u32>,
+    pub run_startup_grace: Option<u32>,
+    pub post_stop:         Option<u32>,
+    pub reconfigure:       Option<u32>,
+}
+
+impl HookTimeouts {
+    /// These timeouts, with any that are unset taken from `other`.
+    pub fn or(self, other: HookTimeouts) -> Self {
+        HookTimeouts { init:              self.init.or(other.init),
+                       run_startup_grace: self.run_startup_grace.or(other.run_startup_grace),
+                       post_stop:         self.post_stop.or(other.post_stop),
+                       reconfigure:       self.reconfigure.or(other.reconfigure), }
+    }
+}
+
+impl FromStr for HookTimeouts {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidHookTimeouts(s.to_string());
+        let mut timeouts = HookTimeouts::default();
+        for part in s.split(|c: char| c == ',' || c.is_whitespace())
+                     .filter(|part| !part.is_empty())
+        {
+            let mut pair = part.splitn(2, '=');
+            let hook = pair.next().unwrap_or_default();
+            let seconds = pair.next()
+                              .and_then(|seconds| seconds.parse().ok())
+                              .ok_or_else(invalid)?;
+            let timeout = match hook {
+                "init" => &mut timeouts.init,
+                "run-startup-grace" => &mut timeouts.run_startup_grace,
+                "post-stop" => &mut timeouts.post_stop,
+                "reconfigure" => &mut timeouts.reconfigure,
+                _ => return Err(invalid()),
+            };
+            *timeout = Some(seconds);
+        }
+        Ok(timeouts)
+    }
+}
+
+impl fmt::Display for HookTimeouts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut timeouts = Vec::new();
+        for (hook, seconds) in &[("init", self.init),
+                                 ("run-startup-grace", self.run_startup_grace),
+                                 ("post-stop", self.post_stop),
+                                 ("reconfigure", self.reconfigure)]
+        {
+            if let Some(seconds) = seconds {
+                timeouts.push(format!("{}={}", hook, seconds));
+            }
+        }
+        write!(f, "{}", timeouts.join(","))
+    }
+}
+
+/// The niceness of a Unix process, from -20 (scheduled most favorably) to 19 (least favorably).
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[serde(try_from = "i32", into = "i32")]
+pub struct NiceLevel(i32);
+
+impl FromStr for NiceLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let level: i32 = s.trim()
+                          .parse()
+                          .map_err(|_| Error::InvalidNiceLevel(s.to_string()))?;
+        NiceLevel::try_from(level)
+    }
+}
+
+impl fmt::Display for NiceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.0) }
+}
+
+impl TryFrom<i32> for NiceLevel {
+    type Error = Error;
+
+    fn try_from(level: i32) -> result::Result<Self, Self::Error> {
+        if (-20..=19).contains(&level) {
+            Ok(NiceLevel(level))
+        } else {
+            Err(Error::InvalidNiceLevel(level.to_string()))
+        }
+    }
+}
+
+impl From<NiceLevel> for i32 {
+    fn from(level: NiceLevel) -> Self { level.0 }
+}
+
+/// The priority class of a Windows process.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum PriorityClass {
+    Idle,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl PriorityClass {
+    pub const VARIANTS: &'static [&'static str] =
+        &["idle", "below-normal", "normal", "above-normal", "high"];
+}
+
+impl FromStr for PriorityClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "idle" => Ok(PriorityClass::Idle),
+            "below-normal" => Ok(PriorityClass::BelowNormal),
+            "normal" => Ok(PriorityClass::Normal),
+            "above-normal" => Ok(PriorityClass::AboveNormal),
+            "high" => Ok(PriorityClass::High),
+            _ => Err(Error::InvalidPriorityClass(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PriorityClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            PriorityClass::Idle => "idle",
+            PriorityClass::BelowNormal => "below-normal",
+            PriorityClass::Normal => "normal",
+            PriorityClass::AboveNormal => "above-normal",
+            PriorityClass::High => "high",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// Limits on the resources a service's process may use, given as `LIMIT=VALUE` pairs separated
+/// by commas (ex: `nofile=65536,nproc=4096,core=unlimited`). Each limit is set as both the soft and
+/// the hard limit of the process.
+///
+/// On Linux these are the `RLIMIT_NOFILE`, `RLIMIT_NPROC` and `RLIMIT_CORE` limits. Windows has no
+/// equivalent of `nofile` or `core`, and limits the number of processes in the service's job
+/// object instead of `nproc`.
+#[derive(Deserialize,
+         Serialize,
+         Eq,
+         PartialEq,
+         Debug,
+         Clone,
+         Copy,
+         Default,
+         Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct ResourceLimits {
+    /// The number of files the process may have open
+    pub nofile: Option<u64>,
+    /// The number of processes the service user may have
+    pub nproc:  Option<u64>,
+    /// The size in bytes of the core dumps the process may write
+    pub core:   Option<u64>,
+}
+
+impl ResourceLimits {
+    /// The value of a limit which is not limited at all.
+    pub const UNLIMITED: u64 = u64::MAX;
+
+    pub fn is_empty(&self) -> bool { *self == ResourceLimits::default() }
+
+    /// These limits, with any that are unset taken from `other`.
+    pub fn or(self, other: ResourceLimits) -> Self {
+        ResourceLimits { nofile: self.nofile.or(other.nofile),
+                         nproc:  self.nproc.or(other.nproc),
+                         core:   self.core.or(other.core), }
+    }
+}
+
+impl FromStr for ResourceLimits {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidResourceLimits(s.to_string());
+        let mut limits = ResourceLimits::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let mut pair = part.splitn(2, '=');
+            let name = pair.next().unwrap_or_default().trim();
+            let value = match pair.next().map(str::trim) {
+                Some("unlimited") => ResourceLimits::UNLIMITED,
+                Some(value) => value.parse().map_err(|_| invalid())?,
+                None => return Err(invalid()),
+            };
+            let limit = match name {
+                "nofile" => &mut limits.nofile,
+                "nproc" => &mut limits.nproc,
+                "core" => &mut limits.core,
+                _ => return Err(invalid()),
+            };
+            *limit = Some(value);
+        }
+        Ok(limits)
+    }
+}
+
+impl fmt::Display for ResourceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut limits = Vec::new();
+        for (name, value) in &[("nofile", self.nofile),
+                               ("nproc", self.nproc),
+                               ("core", self.core)]
+        {
+            match value {
+                Some(ResourceLimits::UNLIMITED) => limits.push(format!("{}=unlimited", name)),
+                Some(value) => limits.push(format!("{}={}", name, value)),
+                None => {}
+            }
+        }
+        write!(f, "{}", limits.join(","))
+    }
+}
+
+impl TryFrom<String> for ResourceLimits {
+    type Error = Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { s.parse() }
+}
+
+impl From<ResourceLimits> for String {
+    fn from(limits: ResourceLimits) -> Self { limits.to_string() }
+}
+
+/// A limit on the CPU time a service may use, as a number of CPUs which may be fractional (ex:
+/// `0.5`, `2`). It is kept as a whole number of thousandths of a CPU.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct CpuLimit(u32);
+
+impl CpuLimit {
+    /// The limit in thousandths of a CPU.
+    pub fn millis(self) -> u32 { self.0 }
+}
+
+impl FromStr for CpuLimit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidCpuLimit(s.to_string());
+        let cpus: f64 = s.trim().parse().map_err(|_| invalid())?;
+        let millis = (cpus * 1000.0).round();
+        if !millis.is_finite() || millis < 1.0 || millis > f64::from(u32::MAX) {
+            return Err(invalid());
+        }
+        Ok(CpuLimit(millis as u32))
+    }
+}
+
+impl fmt::Display for CpuLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fraction = format!("{:03}", self.0 % 1000);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}", self.0 / 1000)
+        } else {
+            write!(f, "{}.{}", self.0 / 1000, fraction)
+        }
+    }
+}
+
+impl TryFrom<String> for CpuLimit {
+    type Error = Error;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> { s.parse() }
+}
+
+impl From<CpuLimit> for String {
+    fn from(limit: CpuLimit) -> Self { limit.to_string() }
+}
+
+impl From<u32> for CpuLimit {
+    fn from(millis: u32) -> Self { CpuLimit(millis) }
+}
+
+/// A limit on the memory a service may use, in bytes. It may be given with units (ex: `512MiB`).
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[serde(from = "u64", into = "u64")]
+pub struct MemoryLimit(u64);
+
+impl MemoryLimit {
+    pub fn bytes(self) -> u64 { self.0 }
+}
+
+impl FromStr for MemoryLimit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        Ok(MemoryLimit(units::parse_size(s)?))
+    }
+}
+
+impl fmt::Display for MemoryLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.0) }
+}
+
+impl From<u64> for MemoryLimit {
+    fn from(bytes: u64) -> Self { MemoryLimit(bytes) }
+}
+
+impl From<MemoryLimit> for u64 {
+    fn from(limit: MemoryLimit) -> Self { limit.0 }
+}
+
+/// Limits on the resources a service's process and all of its descendants may use together.
+///
+/// Unlike `ResourceLimits`, which apply to the process alone, these confine the whole service:
+/// on Linux, the Launcher puts the process in a cgroup (v2) of its own, and on Windows, in a job
+/// object.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SandboxLimits {
+    /// The CPU time the service may use
+    pub cpu:    Option<CpuLimit>,
+    /// The memory the service may use
+    pub memory: Option<MemoryLimit>,
+    /// The number of processes the service may have
+    pub pids:   Option<u64>,
+}
+
+impl SandboxLimits {
+    pub fn is_empty(&self) -> bool { *self == SandboxLimits::default() }
+}
+
+/// How a service's process is scheduled relative to the other processes on its host, and the
+/// resources it may use. This is applied by the Launcher when it spawns the process; settings
+/// which do not apply to the platform the Launcher is running on are ignored.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Scheduling {
+    /// The CPUs the process may run on (Linux only)
+    pub cpuset:         Option<CpuSet>,
+    /// The niceness of the process (Unix only)
+    pub nice:           Option<NiceLevel>,
+    /// The priority class of the process (Windows only)
+    pub priority_class: Option<PriorityClass>,
+    /// The resource limits of the process (Linux and Windows)
+    pub limits:         ResourceLimits,
+    /// The limits on the resources of the process and its descendants (Linux and Windows)
+    pub sandbox:        SandboxLimits,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -179,6 +631,109 @@ mod test {
         assert!("hup".parse::<Signal>().is_err());
     }
 
+    #[test]
+    fn cpusets_are_normalized() {
+        let cpuset: CpuSet = "8, 2-3,0,1".parse().unwrap();
+        assert_eq!(cpuset.cpus(), &[0, 1, 2, 3, 8]);
+        assert_eq!(cpuset.to_string(), "0-3,8");
+    }
+
+    #[test]
+    fn invalid_cpusets_are_rejected() {
+        for cpuset in &["", "a", "3-1", "0,", "1-2-3", "1024"] {
+            assert!(cpuset.parse::<CpuSet>().is_err(),
+                    "'{}' should not be a valid CPU set",
+                    cpuset);
+        }
+    }
+
+    #[test]
+    fn cpu_limits_are_kept_in_thousandths_of_a_cpu() {
+        let limit: CpuLimit = "0.5".parse().unwrap();
+        assert_eq!(limit.millis(), 500);
+        assert_eq!(limit.to_string(), "0.5");
+        assert_eq!("2".parse::<CpuLimit>().unwrap().to_string(), "2");
+        assert_eq!("1.25".parse::<CpuLimit>().unwrap().to_string(), "1.25");
+        for limit in &["0", "-1", "0.0001", "a", ""] {
+            assert!(limit.parse::<CpuLimit>().is_err(),
+                    "'{}' should not be a valid CPU limit",
+                    limit);
+        }
+    }
+
+    #[test]
+    fn memory_limits_accept_units() {
+        assert_eq!("512MiB".parse::<MemoryLimit>().unwrap().bytes(),
+                   512 * 1024 * 1024);
+        assert_eq!("1000".parse::<MemoryLimit>().unwrap().bytes(), 1000);
+        assert!("lots".parse::<MemoryLimit>().is_err());
+    }
+
+    #[test]
+    fn hook_timeouts_round_trip_and_fall_back() {
+        let timeouts: HookTimeouts = "post-stop=30, init=600".parse().unwrap();
+        assert_eq!(timeouts.init, Some(600));
+        assert_eq!(timeouts.to_string(), "init=600,post-stop=30");
+        assert_eq!("init=600\nreconfigure=5\n".parse::<HookTimeouts>()
+                                              .unwrap()
+                                              .reconfigure,
+                   Some(5));
+
+        let plan = HookTimeouts { init: Some(10),
+                                  run_startup_grace: Some(60),
+                                  ..Default::default() };
+        let merged = timeouts.or(plan);
+        assert_eq!(merged.init, Some(600));
+        assert_eq!(merged.run_startup_grace, Some(60));
+
+        for invalid in &["init", "init=soon", "run=5"] {
+            assert!(invalid.parse::<HookTimeouts>().is_err());
+        }
+    }
+
+    #[test]
+    fn nice_levels_must_be_in_range() {
+        assert_eq!(i32::from("-20".parse::<NiceLevel>().unwrap()), -20);
+        assert_eq!(i32::from("19".parse::<NiceLevel>().unwrap()), 19);
+        assert!("20".parse::<NiceLevel>().is_err());
+        assert!("-21".parse::<NiceLevel>().is_err());
+    }
+
+    #[test]
+    fn shutdown_timeouts_are_durations() {
+        assert_eq!(u32::from("12".parse::<ShutdownTimeout>().unwrap()), 12);
+        assert_eq!(u32::from("2m".parse::<ShutdownTimeout>().unwrap()), 120);
+        assert!("1500ms".parse::<ShutdownTimeout>().is_err());
+        assert!("10000000000".parse::<ShutdownTimeout>().is_err());
+        let timeout: ShutdownTimeout = serde_json::from_str(r#""90s""#).unwrap();
+        assert_eq!(timeout, ShutdownTimeout::from(90));
+        assert_eq!(serde_json::from_str::<ShutdownTimeout>("90").unwrap(),
+                   timeout);
+    }
+
+    #[test]
+    fn resource_limits_round_trip_through_parsing() {
+        let limits: ResourceLimits = "core=unlimited, nofile=65536".parse().unwrap();
+        assert_eq!(limits.nofile, Some(65536));
+        assert_eq!(limits.nproc, None);
+        assert_eq!(limits.core, Some(ResourceLimits::UNLIMITED));
+        assert_eq!(limits.to_string(), "nofile=65536,core=unlimited");
+        assert!("".parse::<ResourceLimits>().unwrap().is_empty());
+
+        for invalid in &["nofile", "nofile=many", "stack=8192", "nproc=-1"] {
+            assert!(invalid.parse::<ResourceLimits>().is_err(),
+                    "'{}' should not be valid resource limits",
+                    invalid);
+        }
+    }
+
+    #[test]
+    fn priority_classes_can_round_trip_through_parsing() {
+        for class in PriorityClass::VARIANTS {
+            assert_eq!(*class, class.parse::<PriorityClass>().unwrap().to_string());
+        }
+    }
+
     #[test]
     fn signals_can_render_as_strings() {
         assert_eq!("HUP", Signal::HUP.to_string());
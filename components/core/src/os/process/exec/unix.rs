@@ -1,11 +1,14 @@
-use crate::os::process::can_run_services_as_svc_user;
+use crate::os::process::{can_run_services_as_svc_user,
+                         ResourceLimits,
+                         Scheduling};
 use nix::unistd::{setgid,
                   setuid,
                   Gid,
                   Uid};
 use std::{ffi::OsStr,
           io,
-          os::unix::process::CommandExt,
+          os::unix::{io::RawFd,
+                     process::CommandExt},
           process::{Command,
                     Stdio},
           result};
@@ -19,6 +22,27 @@ pub fn hook_command<X, I, K, V>(executable: X, env: I, ids: Option<(Uid, Gid)>)
           I: IntoIterator<Item = (K, V)>,
           K: AsRef<OsStr>,
           V: AsRef<OsStr>
+{
+    service_command(executable, env, ids, &Scheduling::default(), None)
+}
+
+/// Prepare a `Command` to execute a service's `run` hook, scheduled according to `scheduling`.
+/// If `cgroup_procs` is given, it is the open `cgroup.procs` file of the cgroup the process
+/// joins.
+///
+/// The scheduling settings are applied, and the cgroup joined, before switching to the service
+/// user, so that a service can be given a higher priority than its user would be allowed to give
+/// it.
+pub fn service_command<X, I, K, V>(executable: X,
+                                   env: I,
+                                   ids: Option<(Uid, Gid)>,
+                                   scheduling: &Scheduling,
+                                   cgroup_procs: Option<RawFd>)
+                                   -> Command
+    where X: AsRef<OsStr>,
+          I: IntoIterator<Item = (K, V)>,
+          K: AsRef<OsStr>,
+          V: AsRef<OsStr>
 {
     let mut cmd = Command::new(executable);
 
@@ -31,6 +55,12 @@ pub fn hook_command<X, I, K, V>(executable: X, env: I, ids: Option<(Uid, Gid)>)
        .envs(env);
 
     with_own_process_group(&mut cmd);
+    if scheduling != &Scheduling::default() {
+        with_scheduling(&mut cmd, scheduling.clone());
+    }
+    if let Some(procs) = cgroup_procs {
+        with_cgroup(&mut cmd, procs);
+    }
     if let Some((uid, gid)) = ids {
         with_user_and_group_information(&mut cmd, uid, gid);
     }
@@ -158,3 +188,124 @@ fn set_supplementary_groups(user_id: Uid,
         Ok(())
     }
 }
+
+/// Applies the niceness, CPU affinity and resource limits in `scheduling`
+/// to the process. The priority class is a Windows concept and is ignored.
+fn with_scheduling(cmd: &mut Command, scheduling: Scheduling) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(move || set_scheduling(&scheduling));
+    }
+    cmd
+}
+
+/// Intended for use in a
+/// `std::os::unix::process::CommandExt::pre_exec` callback.
+fn set_scheduling(scheduling: &Scheduling) -> result::Result<(), io::Error> {
+    if let Some(nice) = scheduling.nice {
+        // Lowering a niceness below 0 requires CAP_SYS_NICE
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice.into()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    if let Some(ref cpuset) = scheduling.cpuset {
+        set_cpu_affinity(cpuset.cpus())?;
+    }
+    set_resource_limits(&scheduling.limits)
+}
+
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(cpus: &[usize]) -> result::Result<(), io::Error> {
+    use nix::{sched::{sched_setaffinity,
+                      CpuSet},
+              unistd::Pid};
+
+    let mut cpu_set = CpuSet::new();
+    for cpu in cpus {
+        cpu_set.set(*cpu)
+               .map_err(io_error!("Invalid CPU in CPU set: {:?}"))?;
+    }
+    sched_setaffinity(Pid::from_raw(0), &cpu_set).map_err(io_error!("Unable to set CPU affinity: \
+                                                                     {:?}"))
+}
+
+/// CPU affinity can only be set on Linux.
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(_cpus: &[usize]) -> result::Result<(), io::Error> { Ok(()) }
+
+/// Moves the process into the cgroup whose `cgroup.procs` file is open as `procs`, so that it and
+/// every process it starts are held to the cgroup's limits from the start.
+fn with_cgroup(cmd: &mut Command, procs: RawFd) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(move || join_cgroup(procs));
+    }
+    cmd
+}
+
+/// Intended for use in a
+/// `std::os::unix::process::CommandExt::pre_exec` callback. Writing `0` to `cgroup.procs` moves
+/// the writing process.
+fn join_cgroup(procs: RawFd) -> result::Result<(), io::Error> {
+    let pid = b"0";
+    if unsafe { libc::write(procs, pid.as_ptr() as *const libc::c_void, pid.len()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Raising a hard limit above its current value requires
+/// `CAP_SYS_RESOURCE`, which is why this happens before switching to the
+/// service user.
+#[cfg(target_os = "linux")]
+fn set_resource_limits(limits: &ResourceLimits) -> result::Result<(), io::Error> {
+    for &(resource, value) in &[(libc::RLIMIT_NOFILE, limits.nofile),
+                                (libc::RLIMIT_NPROC, limits.nproc),
+                                (libc::RLIMIT_CORE, limits.core)]
+    {
+        if let Some(value) = value {
+            let value = if value == ResourceLimits::UNLIMITED {
+                libc::RLIM_INFINITY
+            } else {
+                value as libc::rlim_t
+            };
+            let limit = libc::rlimit { rlim_cur: value,
+                                       rlim_max: value, };
+            if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resource limits are only set on Linux.
+#[cfg(not(target_os = "linux"))]
+fn set_resource_limits(_limits: &ResourceLimits) -> result::Result<(), io::Error> { Ok(()) }
+
+/// Runs the `Command` in a new network namespace, where the only
+/// interface is an unconfigured loopback device.
+///
+/// Creating a network namespace requires `CAP_SYS_ADMIN`. Since this
+/// runs after the process has switched to its final user, we fall
+/// back to doing so within a new user namespace, which unprivileged
+/// users can create on most distributions. If neither works the
+/// command fails to spawn, rather than silently running with network
+/// access.
+#[cfg(target_os = "linux")]
+pub fn without_network(cmd: &mut Command) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(unshare_network);
+    }
+    cmd
+}
+
+#[cfg(target_os = "linux")]
+fn unshare_network() -> result::Result<(), io::Error> {
+    use nix::sched::{unshare,
+                     CloneFlags};
+
+    unshare(CloneFlags::CLONE_NEWNET).or_else(|_| {
+                                         unshare(CloneFlags::CLONE_NEWUSER
+                                                 | CloneFlags::CLONE_NEWNET)
+                                     })
+                                     .map_err(io_error!("Unable to isolate network: {:?}"))
+}
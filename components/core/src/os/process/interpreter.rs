@@ -0,0 +1,138 @@
+//! Interpreters declared on the first line of a hook.
+//!
+//! A hook starting with `#!<interpreter> [<argument>]` is run by that interpreter on every
+//! platform, including Windows, where the line would otherwise be ignored. The interpreter is
+//! looked up in the hook's own `PATH`, which lists the package's and its dependencies' binary
+//! directories ahead of the host's. A hook can therefore rely on an interpreter its package
+//! depends on (ex: `#!/usr/bin/env python3` in a package depending on `core/python`) rather than
+//! on whatever the host image happens to provide.
+
+use crate::fs::find_command_in_path;
+use std::{ffi::OsStr,
+          fs::File,
+          io::{self,
+               BufRead,
+               BufReader},
+          path::{Path,
+                 PathBuf}};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interpreter {
+    /// As written in the hook: either a path or a bare name
+    program: String,
+    arg:     Option<String>,
+}
+
+impl Interpreter {
+    /// The interpreter declared by the hook at `path`, if it declares one.
+    pub fn from_hook(path: &Path) -> io::Result<Option<Self>> {
+        let mut line = String::new();
+        BufReader::new(File::open(path)?).read_line(&mut line)?;
+        Ok(Self::parse(&line))
+    }
+
+    /// Parse a shebang line. As on Linux, everything after the interpreter is passed to it as a
+    /// single argument. `/usr/bin/env <NAME>` is treated as just `<NAME>`, since finding `NAME`
+    /// on the `PATH` is what happens to every interpreter anyway.
+    pub fn parse(line: &str) -> Option<Self> {
+        if !line.starts_with("#!") {
+            return None;
+        }
+        let (program, arg) = split_first_word(&line[2..])?;
+        if Path::new(program).file_name() == Some(OsStr::new("env")) {
+            let (program, arg) = split_first_word(arg?)?;
+            return Some(Self::new(program, arg));
+        }
+        Some(Self::new(program, arg))
+    }
+
+    fn new(program: &str, arg: Option<&str>) -> Self {
+        Interpreter { program: program.to_string(),
+                      arg:     arg.map(String::from), }
+    }
+
+    pub fn program(&self) -> &str { &self.program }
+
+    pub fn arg(&self) -> Option<&str> { self.arg.as_deref() }
+
+    /// PowerShell will not run a script without a `.ps1` extension as a file, so hooks for it are
+    /// evaluated instead.
+    pub fn is_powershell(&self) -> bool {
+        Path::new(&self.program).file_stem()
+                                .and_then(OsStr::to_str)
+                                .map_or(false, |stem| {
+                                    stem.eq_ignore_ascii_case("pwsh")
+                                    || stem.eq_ignore_ascii_case("powershell")
+                                })
+    }
+
+    /// The interpreter's executable. A path which exists is used as is; otherwise the
+    /// interpreter is looked up by name in `paths`, a `PATH`-style list of directories. This lets
+    /// a hook written for Linux, such as one starting with `#!/bin/bash`, find `bash.exe` on
+    /// Windows.
+    pub fn resolve(&self, paths: &OsStr) -> Option<PathBuf> {
+        let program = Path::new(&self.program);
+        if program.is_absolute() && program.is_file() {
+            return Some(program.to_path_buf());
+        }
+        find_command_in_path(program.file_name()?, paths)
+    }
+}
+
+/// Split `s` into its first word and the rest, if there is a first word.
+fn split_first_word(s: &str) -> Option<(&str, Option<&str>)> {
+    let mut words = s.trim().splitn(2, char::is_whitespace);
+    let first = words.next().filter(|w| !w.is_empty())?;
+    let rest = words.next().map(str::trim).filter(|r| !r.is_empty());
+    Some((first, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn parses_interpreter_and_argument() {
+        let interpreter = Interpreter::parse("#!/bin/bash -e -u\n").unwrap();
+        assert_eq!(interpreter.program(), "/bin/bash");
+        assert_eq!(interpreter.arg(), Some("-e -u"));
+        assert_eq!(Interpreter::parse("#! pwsh").unwrap().program(), "pwsh");
+    }
+
+    #[test]
+    fn env_interpreters_are_named_directly() {
+        let interpreter = Interpreter::parse("#!/usr/bin/env python3 -u").unwrap();
+        assert_eq!(interpreter.program(), "python3");
+        assert_eq!(interpreter.arg(), Some("-u"));
+        assert_eq!(Interpreter::parse("#!/usr/bin/env"), None);
+    }
+
+    #[test]
+    fn hooks_without_a_shebang_have_no_interpreter() {
+        assert_eq!(Interpreter::parse("Write-Host 'hi'"), None);
+        assert_eq!(Interpreter::parse("#!"), None);
+        assert_eq!(Interpreter::parse("# a comment"), None);
+    }
+
+    #[test]
+    fn recognizes_powershell() {
+        assert!(Interpreter::parse("#!pwsh").unwrap().is_powershell());
+        assert!(Interpreter::parse("#!C:\\hab\\bin\\PowerShell.exe").unwrap()
+                                                                    .is_powershell());
+        assert!(!Interpreter::parse("#!/bin/sh").unwrap().is_powershell());
+    }
+
+    #[test]
+    fn resolves_interpreters_by_name_in_the_given_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("fakeinterp");
+        File::create(&program).unwrap();
+        let paths = env::join_paths(vec![dir.path()]).unwrap();
+
+        let interpreter = Interpreter::parse("#!/nonexistent/bin/fakeinterp").unwrap();
+        assert_eq!(interpreter.resolve(&paths), Some(program));
+        let missing = Interpreter::parse("#!/usr/bin/env nosuchinterp").unwrap();
+        assert_eq!(missing.resolve(&paths), None);
+    }
+}
@@ -1,14 +1,17 @@
 pub mod archive;
+pub mod artifact_cache;
 pub mod ident;
 pub mod install;
 pub mod list;
 pub mod metadata;
 pub mod plan;
+pub mod schedule;
 pub mod target;
 
 pub use self::{archive::{FromArchive,
                          PackageArchive,
                          PackageArchiveInfo},
+               artifact_cache::ArtifactCache,
                ident::{FullyQualifiedPackageIdent,
                        Identifiable,
                        PackageIdent},
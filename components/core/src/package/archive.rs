@@ -1,4 +1,5 @@
-use super::{metadata::{MetaFile,
+use super::{metadata::{self,
+                       MetaFile,
                        PackageType},
             Identifiable,
             PackageIdent,
@@ -7,14 +8,21 @@ use crate::{crypto::{artifact,
                      hash},
             error::{Error,
                     Result},
+            fs,
             package::ident::FullyQualifiedPackageIdent};
 use regex::Regex;
 use serde::Serialize;
-use std::{collections::HashMap,
+use std::{collections::{HashMap,
+                        HashSet},
           convert::{TryFrom,
                     TryInto},
           error,
-          io::Read,
+          fmt,
+          fs as stdfs,
+          io::{self,
+               BufRead,
+               BufReader,
+               Read},
           path::{Path,
                  PathBuf},
           result,
@@ -107,6 +115,14 @@ lazy_static::lazy_static! {
             ))
             .unwrap(),
         );
+        map.insert(
+            MetaFile::License,
+            Regex::new(&format!(
+                r"^/?hab/pkgs/([^/]+)/([^/]+)/([^/]+)/([^/]+)/{}$",
+                MetaFile::License
+            ))
+            .unwrap(),
+        );
         map.insert(
             MetaFile::SvcUser,
             Regex::new(&format!(
@@ -281,6 +297,18 @@ impl PackageArchive {
 
     pub fn svc_group(&mut self) -> Option<&str> { self.read_metadata(MetaFile::SvcGroup) }
 
+    /// Returns the licenses the package is released under, falling back to its `MANIFEST` for
+    /// packages built without a `LICENSE` metafile.
+    pub fn licenses(&mut self) -> Vec<String> {
+        if let Some(data) = self.read_metadata(MetaFile::License) {
+            metadata::parse_licenses(data)
+        } else {
+            self.read_metadata(MetaFile::Manifest)
+                .map(metadata::licenses_from_manifest)
+                .unwrap_or_default()
+        }
+    }
+
     pub fn manifest(&mut self) -> Result<&str> {
         if let Some(data) = self.read_metadata(MetaFile::Manifest) {
             Ok(data)
@@ -351,6 +379,23 @@ impl PackageArchive {
         Ok(())
     }
 
+    /// Unpack the package into a temporary directory and check each of its files against the
+    /// checksums in its `FILES` manifest, returning those which don't match. The `FILES` and
+    /// `MANIFEST` files themselves are written after the checksums, so they aren't listed.
+    ///
+    /// # Failures
+    ///
+    /// * If the package cannot be unpacked
+    /// * If the package has no `FILES` manifest, as with packages built before it was introduced
+    pub fn tampered_files(&mut self) -> Result<Vec<TamperedFile>> {
+        let ident = self.ident()?;
+        let root = tempfile::Builder::new().prefix("hab-pkg-verify")
+                                           .tempdir()?;
+        self.unpack(Some(root.path()))?;
+        let pkg_path = fs::pkg_install_path(&ident, Some(root.path()));
+        tampered_files(root.path(), &pkg_path, true)
+    }
+
     fn read_deps(&mut self, file: MetaFile) -> Result<Vec<PackageIdent>> {
         let mut deps = vec![];
 
@@ -414,6 +459,108 @@ impl PackageArchive {
     }
 }
 
+/// How a file of a package differs from the package's `FILES` manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileProblem {
+    /// Its contents don't match its checksum.
+    Modified,
+    /// It is listed, but not in the package.
+    Missing,
+    /// It is in the package, but not listed.
+    Unlisted,
+}
+
+impl fmt::Display for FileProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self {
+            FileProblem::Modified => "modified",
+            FileProblem::Missing => "missing",
+            FileProblem::Unlisted => "not in the FILES manifest",
+        };
+        write!(f, "{}", problem)
+    }
+}
+
+/// A file of a package which doesn't match the package's `FILES` manifest, at its installed path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TamperedFile {
+    pub path:    PathBuf,
+    pub problem: FileProblem,
+}
+
+/// Checks each file of the package at `pkg_path` against the checksums in its `FILES` manifest,
+/// returning those which don't match. The manifest lists paths as installed, which are looked up
+/// under `root`. With `include_unlisted`, files in the package which aren't listed are returned
+/// too.
+///
+/// # Failures
+///
+/// * If the package has no `FILES` manifest, as with packages built before it was introduced
+pub(crate) fn tampered_files(root: &Path,
+                             pkg_path: &Path,
+                             include_unlisted: bool)
+                             -> Result<Vec<TamperedFile>> {
+    let manifest_path = pkg_path.join(MetaFile::Files.to_string());
+    if !manifest_path.is_file() {
+        return Err(Error::MetaFileNotFound(MetaFile::Files));
+    }
+
+    let mut tampered = Vec::new();
+    let mut listed = HashSet::new();
+    // The manifest is itself signed, so its checksums follow a signature header.
+    for line in BufReader::new(artifact::get_archive_reader(&manifest_path)?).lines() {
+        let line = line?;
+        let mut fields = line.splitn(2, "  ");
+        let (checksum, path) = match (fields.next(), fields.next()) {
+            (Some(checksum), Some(path)) => (checksum, Path::new(path)),
+            _ => continue,
+        };
+        let relative = path.strip_prefix("/").unwrap_or(path);
+        let unpacked = root.join(relative);
+        listed.insert(unpacked.clone());
+        let problem = if !unpacked.is_file() {
+            FileProblem::Missing
+        } else if hash::hash_file(&unpacked)? != checksum {
+            FileProblem::Modified
+        } else {
+            continue;
+        };
+        tampered.push(TamperedFile { path: path.to_path_buf(),
+                                     problem });
+    }
+
+    if include_unlisted {
+        let unlisted_metafiles = [manifest_path.clone(),
+                                  pkg_path.join(MetaFile::Manifest.to_string())];
+        let mut unpacked = Vec::new();
+        regular_files_under(pkg_path, &mut unpacked)?;
+        for path in unpacked {
+            if !listed.contains(&path) && !unlisted_metafiles.contains(&path) {
+                let path = Path::new("/").join(path.strip_prefix(root)
+                                                   .expect("unpacked into the root"));
+                tampered.push(TamperedFile { path,
+                                             problem: FileProblem::Unlisted });
+            }
+        }
+    }
+    tampered.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(tampered)
+}
+
+fn regular_files_under(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in stdfs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            regular_files_under(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
 pub trait FromArchive: Sized {
     type Error: error::Error;
 
@@ -442,6 +589,7 @@ pub struct PackageArchiveInfo {
     pub build_deps:     Vec<String>,
     pub build_tdeps:    Vec<String>,
     pub exposes:        Vec<u16>,
+    pub licenses:       Vec<String>,
     pub manifest:       String,
     pub config:         Option<String>,
     pub svc_user:       Option<String>,
@@ -486,6 +634,7 @@ impl TryFrom<PackageArchive> for PackageArchiveInfo {
                                                        .map(ToString::to_string)
                                                        .collect(),
                                 exposes:        archive.exposes()?,
+                                licenses:       archive.licenses(),
                                 manifest:       archive.manifest()?.to_string(),
                                 svc_user:       archive.svc_user().map(ToString::to_string),
                                 svc_group:      archive.svc_group().map(ToString::to_string),
@@ -507,7 +656,11 @@ impl PackageArchiveInfo {
 mod test {
     use super::{super::target,
                 *};
+    use crate::crypto::SigKeyPair;
     use std::path::PathBuf;
+    use tar::Builder;
+    use tempfile::TempDir;
+    use xz2::write::XzEncoder;
 
     #[test]
     fn reading_artifact_metadata() {
@@ -595,6 +748,71 @@ mod test {
         assert_eq!(1024, tdeps.len());
     }
 
+    const PKG_DIR: &str = "hab/pkgs/acme/app/1.0.0/20200101000000";
+
+    /// Writes a `FILES` manifest listing the current checksums of `files` of the package under
+    /// `root`.
+    fn write_files_manifest(root: &Path, pair: &SigKeyPair, files: &[&str]) {
+        let checksums = files.iter()
+                             .map(|file| {
+                                 let path = root.join(PKG_DIR).join(file);
+                                 format!("{}  /{}/{}\n",
+                                         hash::hash_file(&path).unwrap(),
+                                         PKG_DIR,
+                                         file)
+                             })
+                             .collect::<String>();
+        let sums = root.join("sums");
+        stdfs::write(&sums, checksums).unwrap();
+        artifact::sign(&sums, &root.join(PKG_DIR).join("FILES"), pair).unwrap();
+    }
+
+    /// Builds a signed artifact of the package under `root` as it is now.
+    fn build_artifact(root: &Path, pair: &SigKeyPair) -> PathBuf {
+        let tarball = root.join("app.tar.xz");
+        let mut builder = Builder::new(XzEncoder::new(stdfs::File::create(&tarball).unwrap(), 6));
+        builder.append_dir_all(PKG_DIR, root.join(PKG_DIR)).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        let hart = root.join("app.hart");
+        artifact::sign(&tarball, &hart, pair).unwrap();
+        hart
+    }
+
+    #[test]
+    fn tampered_files_are_found_with_the_files_manifest() {
+        let root = TempDir::new().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        let pkg_dir = root.path().join(PKG_DIR);
+        stdfs::create_dir_all(pkg_dir.join("bin")).unwrap();
+        stdfs::write(pkg_dir.join("IDENT"), "acme/app/1.0.0/20200101000000").unwrap();
+        stdfs::write(pkg_dir.join("bin/app"), "#!/bin/sh").unwrap();
+        stdfs::write(pkg_dir.join("bin/helper"), "#!/bin/sh").unwrap();
+        write_files_manifest(root.path(), &pair, &["IDENT", "bin/app", "bin/helper"]);
+        stdfs::write(pkg_dir.join("MANIFEST"), "acme app").unwrap();
+
+        let hart = build_artifact(root.path(), &pair);
+        assert!(PackageArchive::new(&hart).unwrap()
+                                          .tampered_files()
+                                          .unwrap()
+                                          .is_empty());
+
+        stdfs::write(pkg_dir.join("bin/app"), "#!/bin/sh\nexit 1").unwrap();
+        stdfs::remove_file(pkg_dir.join("bin/helper")).unwrap();
+        stdfs::write(pkg_dir.join("bin/backdoor"), "#!/bin/sh").unwrap();
+        let hart = build_artifact(root.path(), &pair);
+        let tampered = PackageArchive::new(&hart).unwrap()
+                                                 .tampered_files()
+                                                 .unwrap()
+                                                 .into_iter()
+                                                 .map(|file| (file.path, file.problem))
+                                                 .collect::<Vec<_>>();
+        let installed = Path::new("/").join(PKG_DIR);
+        assert_eq!(tampered,
+                   vec![(installed.join("bin/app"), FileProblem::Modified),
+                        (installed.join("bin/backdoor"), FileProblem::Unlisted),
+                        (installed.join("bin/helper"), FileProblem::Missing)]);
+    }
+
     #[test]
     #[cfg(feature = "x86_64-linux")]
     fn reading_artifact_target() {
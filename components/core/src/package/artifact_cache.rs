@@ -0,0 +1,163 @@
+//! The cache of downloaded Habitat Artifacts, `/hab/cache/artifacts` by default.
+//!
+//! The cache is bounded in size: once it holds more than `HAB_ARTIFACT_CACHE_MAX_SIZE_MB`
+//! megabytes of artifacts, the least recently used ones are pruned. An artifact's modification
+//! time records when it was last used, so reusing an artifact touches it.
+
+use crate::error::Result;
+use filetime::FileTime;
+use std::{fs,
+          io,
+          path::{Path,
+                 PathBuf},
+          time::SystemTime};
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+crate::env_config_int!(#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+                       MaxSizeMb,
+                       u64,
+                       HAB_ARTIFACT_CACHE_MAX_SIZE_MB,
+                       10 * 1024);
+
+/// An artifact in the cache.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedArtifact {
+    pub path:      PathBuf,
+    /// The size of the artifact in bytes.
+    pub size:      u64,
+    pub last_used: SystemTime,
+}
+
+pub struct ArtifactCache {
+    path: PathBuf,
+}
+
+impl ArtifactCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self { ArtifactCache { path: path.into() } }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// The size in bytes the cache is pruned to, as set by `HAB_ARTIFACT_CACHE_MAX_SIZE_MB`.
+    pub fn configured_max_size() -> u64 {
+        u64::from(MaxSizeMb::configured_value()).saturating_mul(BYTES_PER_MB)
+    }
+
+    /// Every artifact in the cache, least recently used first. A cache which doesn't exist yet is
+    /// empty.
+    pub fn list(&self) -> Result<Vec<CachedArtifact>> {
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut artifacts = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() || path.extension().map_or(true, |e| e != "hart") {
+                continue;
+            }
+            artifacts.push(CachedArtifact { path,
+                                            size: metadata.len(),
+                                            last_used: metadata.modified()? });
+        }
+        artifacts.sort_by(|a, b| a.last_used.cmp(&b.last_used).then(a.path.cmp(&b.path)));
+        Ok(artifacts)
+    }
+
+    /// Records that the cached artifact at `path` was just used, so that it is pruned last.
+    pub fn touch(path: &Path) -> Result<()> {
+        filetime::set_file_mtime(path, FileTime::now())?;
+        Ok(())
+    }
+
+    /// Removes the least recently used artifacts until the cache holds no more than `max_size`
+    /// bytes, returning the artifacts removed. The artifact at `keep`, such as one being
+    /// installed, is never removed.
+    pub fn prune(&self, max_size: u64, keep: Option<&Path>) -> Result<Vec<CachedArtifact>> {
+        let artifacts = self.list()?;
+        let mut size: u64 = artifacts.iter().map(|a| a.size).sum();
+        let mut removed = Vec::new();
+        for artifact in artifacts {
+            if size <= max_size {
+                break;
+            }
+            if keep.map_or(false, |keep| keep == artifact.path) {
+                continue;
+            }
+            debug!("Pruning {} from the artifact cache",
+                   artifact.path.display());
+            fs::remove_file(&artifact.path)?;
+            size -= artifact.size;
+            removed.push(artifact);
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cache_artifact(cache: &ArtifactCache, name: &str, size: usize, last_used: i64) -> PathBuf {
+        let path = cache.path().join(name);
+        fs::write(&path, vec![0; size]).unwrap();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(last_used, 0)).unwrap();
+        path
+    }
+
+    #[test]
+    fn artifacts_are_listed_least_recently_used_first() {
+        let dir = TempDir::new().unwrap();
+        let cache = ArtifactCache::new(dir.path());
+        let newer = cache_artifact(&cache,
+                                   "core-a-1.0.0-20200101000000-x86_64-linux.hart",
+                                   1,
+                                   200);
+        let older = cache_artifact(&cache,
+                                   "core-b-1.0.0-20200101000000-x86_64-linux.hart",
+                                   2,
+                                   100);
+        fs::write(dir.path().join("not-an-artifact.txt"), "").unwrap();
+
+        let paths = cache.list()
+                         .unwrap()
+                         .into_iter()
+                         .map(|a| a.path)
+                         .collect::<Vec<_>>();
+        assert_eq!(paths, vec![older.clone(), newer.clone()]);
+
+        ArtifactCache::touch(&older).unwrap();
+        let paths = cache.list()
+                         .unwrap()
+                         .into_iter()
+                         .map(|a| a.path)
+                         .collect::<Vec<_>>();
+        assert_eq!(paths, vec![newer, older]);
+        assert!(ArtifactCache::new(dir.path().join("missing")).list()
+                                                              .unwrap()
+                                                              .is_empty());
+    }
+
+    #[test]
+    fn prune_removes_the_least_recently_used_artifacts() {
+        let dir = TempDir::new().unwrap();
+        let cache = ArtifactCache::new(dir.path());
+        let oldest = cache_artifact(&cache, "a.hart", 10, 100);
+        let kept = cache_artifact(&cache, "b.hart", 10, 200);
+        let newest = cache_artifact(&cache, "c.hart", 10, 300);
+
+        let removed = cache.prune(20, None).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].path, oldest);
+        assert!(!oldest.exists());
+
+        let removed = cache.prune(0, Some(&kept)).unwrap();
+        assert_eq!(removed.into_iter().map(|a| a.path).collect::<Vec<_>>(),
+                   vec![newest]);
+        assert!(kept.exists());
+    }
+}
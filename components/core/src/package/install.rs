@@ -1,18 +1,25 @@
 #[cfg(test)]
 use super::PackageTarget;
-use super::{list::package_list_for_ident,
-            metadata::{parse_key_value,
+use super::{archive::{self,
+                      TamperedFile},
+            list::package_list_for_ident,
+            metadata::{licenses_from_manifest,
+                       parse_key_value,
+                       parse_licenses,
                        read_metafile,
                        Bind,
                        BindMapping,
                        MetaFile,
                        PackageType},
+            schedule::{self,
+                       ScheduledTask},
             Identifiable,
             PackageIdent};
 use crate::{error::{Error,
                     Result},
             fs,
-            os::process::{ShutdownSignal,
+            os::process::{HookTimeouts,
+                          ShutdownSignal,
                           ShutdownTimeout}};
 use serde_derive::{Deserialize,
                    Serialize};
@@ -395,8 +402,54 @@ impl PackageInstall {
         }
     }
 
+    /// The port of the service's administrative HTTP endpoints, which the Supervisor's HTTP
+    /// gateway may proxy requests to, or `None` if the package doesn't declare one.
+    pub fn proxy_port(&self) -> Result<Option<u16>> {
+        match self.read_metafile(MetaFile::ProxyPort) {
+            Ok(body) => {
+                body.trim()
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| Error::MetaFileMalformed(MetaFile::ProxyPort))
+            }
+            Err(Error::MetaFileNotFound(MetaFile::ProxyPort)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the directories the package's binaries find their shared libraries in, read from
+    /// its `LD_RUN_PATH` metafile and rooted under the filesystem root. Packages which don't link
+    /// against shared libraries have none.
+    pub fn library_paths(&self) -> Result<Vec<PathBuf>> {
+        match self.read_metafile(MetaFile::LdRunPath) {
+            Ok(body) => {
+                Ok(env::split_paths(body.trim()).filter(|p| !p.as_os_str().is_empty())
+                                                .map(|p| fs::fs_rooted_path(&p, &self.fs_root_path))
+                                                .collect())
+            }
+            Err(Error::MetaFileNotFound(MetaFile::LdRunPath)) => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn ident(&self) -> &PackageIdent { &self.ident }
 
+    /// Returns the licenses the package is released under, read from its `LICENSE` metafile or,
+    /// for packages built before that existed, its `MANIFEST`.
+    pub fn licenses(&self) -> Result<Vec<String>> {
+        match self.read_metafile(MetaFile::License) {
+            Ok(body) => Ok(parse_licenses(&body)),
+            Err(Error::MetaFileNotFound(MetaFile::License)) => {
+                match self.read_metafile(MetaFile::Manifest) {
+                    Ok(manifest) => Ok(licenses_from_manifest(&manifest)),
+                    Err(Error::MetaFileNotFound(MetaFile::Manifest)) => Ok(vec![]),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Returns the path elements of the package's `PATH` metafile if it exists, or an empty `Vec`
     /// if not found.
     ///
@@ -580,6 +633,17 @@ impl PackageInstall {
 
     pub fn installed_path(&self) -> &Path { &*self.installed_path }
 
+    /// Checks each installed file of the package against the checksums in its `FILES` manifest,
+    /// returning those which don't match. Files added to the package's directory after it was
+    /// installed aren't reported, as some software writes caches alongside itself.
+    ///
+    /// # Failures
+    ///
+    /// * If the package has no `FILES` manifest, as with packages built before it was introduced
+    pub fn tampered_files(&self) -> Result<Vec<TamperedFile>> {
+        archive::tampered_files(&self.fs_root_path, &self.installed_path, false)
+    }
+
     /// Returns the user that the package is specified to run as
     /// or None if the package doesn't contain a SVC_USER Metafile
     pub fn svc_user(&self) -> Result<Option<String>> {
@@ -620,6 +684,34 @@ impl PackageInstall {
         }
     }
 
+    /// Returns the timeouts of the package's hooks, from its `HOOK_TIMEOUTS` metafile.
+    pub fn hook_timeouts(&self) -> Result<HookTimeouts> {
+        match self.read_metafile(MetaFile::HookTimeouts) {
+            Ok(body) => body.parse(),
+            Err(Error::MetaFileNotFound(MetaFile::HookTimeouts)) => Ok(HookTimeouts::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The configuration keys which the package's `reconfigure` hook can't apply, so that a
+    /// change to any of them restarts the service instead.
+    pub fn restart_keys(&self) -> Result<Vec<String>> {
+        match self.read_metafile(MetaFile::RestartKeys) {
+            Ok(body) => Ok(body.split_whitespace().map(String::from).collect()),
+            Err(Error::MetaFileNotFound(MetaFile::RestartKeys)) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The hooks which the package asks the Supervisor to run on a schedule.
+    pub fn scheduled_tasks(&self) -> Result<Vec<ScheduledTask>> {
+        match self.read_metafile(MetaFile::ScheduledTasks) {
+            Ok(body) => schedule::parse_scheduled_tasks(&body),
+            Err(Error::MetaFileNotFound(MetaFile::ScheduledTasks)) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Read the contents of a given metafile.
     ///
     /// # Failures
@@ -1339,6 +1431,36 @@ core/bar=pub:core/publish sub:core/subscribe
         assert_eq!(expected, alpha.legacy_runtime_paths().unwrap());
     }
 
+    #[test]
+    fn proxy_port_is_read_from_its_metafile() {
+        let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
+        let pkg_install = testing_package_install("acme/proxied", fs_root.path());
+        assert_eq!(pkg_install.proxy_port().unwrap(), None);
+
+        write_metafile(&pkg_install, MetaFile::ProxyPort, "8080\n");
+        assert_eq!(pkg_install.proxy_port().unwrap(), Some(8080));
+
+        write_metafile(&pkg_install, MetaFile::ProxyPort, "http");
+        assert!(pkg_install.proxy_port().is_err());
+    }
+
+    #[test]
+    fn library_paths_are_read_from_the_ld_run_path_metafile() {
+        let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
+        let pkg_install = testing_package_install("acme/linked", fs_root.path());
+        assert!(pkg_install.library_paths().unwrap().is_empty());
+
+        let lib = pkg_prefix_for(&pkg_install).join("lib");
+        let dep_lib = PathBuf::from("/hab/pkgs/acme/dep/1.0.0/20200101000000/lib");
+        let body = env::join_paths(&[&lib, &dep_lib]).unwrap();
+        write_metafile(&pkg_install,
+                       MetaFile::LdRunPath,
+                       &format!("{}\n", body.to_string_lossy()));
+        assert_eq!(pkg_install.library_paths().unwrap(),
+                   vec![fs::fs_rooted_path(&lib, fs_root.path()),
+                        fs::fs_rooted_path(&dep_lib, fs_root.path())]);
+    }
+
     #[test]
     fn environment_for_command_missing_all_metafiles() {
         let fs_root = Builder::new().prefix("fs-root").tempdir().unwrap();
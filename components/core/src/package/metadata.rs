@@ -148,15 +148,21 @@ pub enum MetaFile {
     EnvironmentSep,
     Exports,
     Exposes,
+    Files,
+    HookTimeouts,
     Ident,
     LdFlags,
     LdRunPath,
+    License,
     Manifest,
     Path,
+    ProxyPort,
     ResolvedServices, // Composite-only
+    RestartKeys,
     RuntimeEnvironment,
     RuntimeEnvironmentPaths,
     RuntimePath,
+    ScheduledTasks,
     ShutdownSignal,
     ShutdownTimeout,
     Services, // Composite-only
@@ -182,15 +188,21 @@ impl fmt::Display for MetaFile {
             MetaFile::EnvironmentSep => "ENVIRONMENT_SEP",
             MetaFile::Exports => "EXPORTS",
             MetaFile::Exposes => "EXPOSES",
+            MetaFile::Files => "FILES",
+            MetaFile::HookTimeouts => "HOOK_TIMEOUTS",
             MetaFile::Ident => "IDENT",
             MetaFile::LdFlags => "LDFLAGS",
             MetaFile::LdRunPath => "LD_RUN_PATH",
+            MetaFile::License => "LICENSE",
             MetaFile::Manifest => "MANIFEST",
             MetaFile::Path => "PATH",
+            MetaFile::ProxyPort => "PROXY_PORT",
             MetaFile::ResolvedServices => "RESOLVED_SERVICES",
+            MetaFile::RestartKeys => "RESTART_KEYS",
             MetaFile::RuntimeEnvironment => "RUNTIME_ENVIRONMENT",
             MetaFile::RuntimeEnvironmentPaths => "RUNTIME_ENVIRONMENT_PATHS",
             MetaFile::RuntimePath => "RUNTIME_PATH",
+            MetaFile::ScheduledTasks => "SCHEDULED_TASKS",
             MetaFile::Services => "SERVICES",
             MetaFile::ShutdownSignal => "SHUTDOWN_SIGNAL",
             MetaFile::ShutdownTimeout => "SHUTDOWN_TIMEOUT",
@@ -204,6 +216,30 @@ impl fmt::Display for MetaFile {
     }
 }
 
+/// Parse the contents of a `LICENSE` metafile, which lists one license per line.
+pub fn parse_licenses(body: &str) -> Vec<String> {
+    body.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Packages built before the `LICENSE` metafile existed only record their licenses in the
+/// `* __License__:` line of their `MANIFEST`.
+pub fn licenses_from_manifest(manifest: &str) -> Vec<String> {
+    const LICENSE_LINE: &str = "* __License__:";
+    manifest.lines()
+            .map(str::trim)
+            .find(|l| l.starts_with(LICENSE_LINE))
+            .map(|l| {
+                l[LICENSE_LINE.len()..].split_whitespace()
+                                       .map(str::to_string)
+                                       .collect()
+            })
+            .unwrap_or_default()
+}
+
 /// Read a metadata file from within a package directory if it exists
 ///
 /// Returns the contents of the file
@@ -399,6 +435,19 @@ port=front-end.port
         assert_eq!(expected, bind_map);
     }
 
+    #[test]
+    fn can_parse_licenses() {
+        assert_eq!(parse_licenses("Apache-2.0\n\nMIT\n"),
+                   vec!["Apache-2.0", "MIT"]);
+    }
+
+    #[test]
+    fn can_read_licenses_from_an_older_manifest() {
+        let manifest = "# core / zlib\n\n* __Version__: 1.2.11\n* __License__: zlib MIT \n";
+        assert_eq!(licenses_from_manifest(manifest), vec!["zlib", "MIT"]);
+        assert!(licenses_from_manifest("# core / zlib\n").is_empty());
+    }
+
     #[test]
     fn reading_a_non_existing_metafile_is_an_error() {
         let pkg_root = Builder::new().prefix("pkg-root").tempdir().unwrap();
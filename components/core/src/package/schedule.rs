@@ -0,0 +1,271 @@
+//! Tasks which a package asks the Supervisor to run on a schedule, declared with
+//! `pkg_scheduled_tasks` in its plan.
+
+use crate::error::{Error,
+                   Result};
+use chrono::{DateTime,
+             Datelike,
+             Duration as ChronoDuration,
+             Local,
+             NaiveDate,
+             NaiveDateTime,
+             TimeZone,
+             Timelike};
+use std::{fmt,
+          result,
+          str::FromStr,
+          time::{Duration,
+                 SystemTime}};
+
+/// When a scheduled task runs, as a cron expression: five fields for the minute, hour, day of the
+/// month, month and day of the week, or one of `@hourly`, `@daily`, `@weekly` and `@monthly`.
+/// Times are in the host's local time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    expression: String,
+    minutes:    u64,
+    hours:      u64,
+    days:       u64,
+    months:     u64,
+    weekdays:   u64,
+    /// Whether both the day of the month and the day of the week are restricted, in which case
+    /// a day matching either of them is run on, as cron does.
+    either_day: bool,
+}
+
+impl Schedule {
+    /// The first time after `after` that the schedule calls for.
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let mut after = DateTime::<Local>::from(after).naive_local();
+        loop {
+            let next = self.next_after_local(after)?;
+            // Times skipped over when the clocks go forward don't happen at all.
+            if let Some(next) = Local.from_local_datetime(&next).earliest() {
+                return Some(next.into());
+            }
+            after = next;
+        }
+    }
+
+    /// The first minute after `after` that the schedule calls for, or `None` if it never does
+    /// (ex: `0 0 30 2 *`).
+    fn next_after_local(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut next = after.date().and_hms(after.hour(), after.minute(), 0)
+                       + ChronoDuration::minutes(1);
+        // Any day of the month is in every month within four years, leap days included.
+        let give_up = next + ChronoDuration::days(4 * 366);
+        while next < give_up {
+            if !has(self.months, next.month()) {
+                let (year, month) = if next.month() == 12 {
+                    (next.year() + 1, 1)
+                } else {
+                    (next.year(), next.month() + 1)
+                };
+                next = NaiveDate::from_ymd(year, month, 1).and_hms(0, 0, 0);
+            } else if !self.runs_on(next.date()) {
+                next = next.date().and_hms(0, 0, 0) + ChronoDuration::days(1);
+            } else if !has(self.hours, next.hour()) {
+                next = next.date().and_hms(next.hour(), 0, 0) + ChronoDuration::hours(1);
+            } else if !has(self.minutes, next.minute()) {
+                next = next + ChronoDuration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has(set: u64, value: u32) -> bool { set & (1 << value) != 0 }
+
+/// The set of values of one field of a cron expression, as a bit for each value, where each
+/// comma-separated part is `*`, a value or a range of values, optionally with a `/STEP`.
+fn field(s: &str, min: u32, max: u32) -> Option<u64> {
+    let mut set = 0;
+    for part in s.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(i) => (&part[..i], part[i + 1..].parse::<usize>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some(i) = range.find('-') {
+            (range[..i].parse().ok()?, range[i + 1..].parse().ok()?)
+        } else {
+            // `5/15` means every 15th value from 5 on.
+            let start = range.parse().ok()?;
+            (start, if step > 1 { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step) {
+            set |= 1 << value;
+        }
+    }
+    Some(set)
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidScheduledTask(s.to_string());
+        let expression = s.trim();
+        let fields = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            _ => expression,
+        };
+        let fields = fields.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(invalid());
+        }
+        let minutes = field(fields[0], 0, 59).ok_or_else(invalid)?;
+        let hours = field(fields[1], 0, 23).ok_or_else(invalid)?;
+        let days = field(fields[2], 1, 31).ok_or_else(invalid)?;
+        let months = field(fields[3], 1, 12).ok_or_else(invalid)?;
+        let mut weekdays = field(fields[4], 0, 7).ok_or_else(invalid)?;
+        // Both 0 and 7 are Sunday.
+        if has(weekdays, 7) {
+            weekdays |= 1;
+        }
+        Ok(Schedule { expression: expression.to_string(),
+                      minutes,
+                      hours,
+                      days,
+                      months,
+                      weekdays,
+                      either_day: !fields[2].starts_with('*') && !fields[4].starts_with('*') })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.expression) }
+}
+
+/// A hook which the Supervisor runs on a schedule, in the environment of the service, as
+/// `HOOK=SCHEDULE`. The schedule may be followed by `~SECONDS` to delay each run by a random
+/// amount of up to that many seconds, so that the members of a service group don't all run the
+/// task at once (ex: `backup=30 2 * * *~300`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduledTask {
+    /// The name of the hook in the package's `hooks` directory
+    pub hook:     String,
+    pub schedule: Schedule,
+    /// The most each run is delayed by
+    pub jitter:   Duration,
+}
+
+impl FromStr for ScheduledTask {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidScheduledTask(s.to_string());
+        let mut parts = s.trim().splitn(2, '=');
+        let hook = parts.next().unwrap_or_default().trim();
+        let rest = parts.next().ok_or_else(invalid)?;
+        if hook.is_empty()
+           || !hook.chars()
+                   .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid());
+        }
+        let (schedule, jitter) = match rest.rfind('~') {
+            Some(i) => (&rest[..i], rest[i + 1..].trim().parse().map_err(|_| invalid())?),
+            None => (rest, 0),
+        };
+        Ok(ScheduledTask { hook:     hook.to_string(),
+                           schedule: schedule.parse().map_err(|_| invalid())?,
+                           jitter:   Duration::from_secs(jitter), })
+    }
+}
+
+impl fmt::Display for ScheduledTask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.hook, self.schedule)?;
+        if self.jitter > Duration::from_secs(0) {
+            write!(f, "~{}", self.jitter.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the scheduled tasks of a package, one per line.
+pub fn parse_scheduled_tasks(body: &str) -> Result<Vec<ScheduledTask>> {
+    body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime { NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap() }
+
+    fn next(schedule: &str, after: &str) -> Option<NaiveDateTime> {
+        schedule.parse::<Schedule>()
+                .unwrap()
+                .next_after_local(at(after))
+    }
+
+    #[test]
+    fn next_run_follows_cron_fields() {
+        assert_eq!(next("*/15 * * * *", "2020-06-01 10:07"),
+                   Some(at("2020-06-01 10:15")));
+        assert_eq!(next("30 2 * * *", "2020-06-01 02:30"),
+                   Some(at("2020-06-02 02:30")));
+        assert_eq!(next("0 0 1 * *", "2020-12-15 00:00"),
+                   Some(at("2021-01-01 00:00")));
+        // 2020-06-06 is a Saturday; 7 is Sunday as well as 0.
+        assert_eq!(next("0 9 * * 7", "2020-06-06 12:00"),
+                   Some(at("2020-06-07 09:00")));
+        // With both days restricted, either one will do.
+        assert_eq!(next("0 0 15 * 1", "2020-06-01 12:00"),
+                   Some(at("2020-06-08 00:00")));
+        assert_eq!(next("@hourly", "2020-06-01 10:00"),
+                   Some(at("2020-06-01 11:00")));
+        assert_eq!(next("0 0 29 2 *", "2021-03-01 00:00"),
+                   Some(at("2024-02-29 00:00")));
+        assert_eq!(next("0 0 30 2 *", "2021-03-01 00:00"), None);
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        for schedule in &["* * * *", "60 * * * *", "* * 0 * *", "*/0 * * * *", "5-1 * * * *",
+                          "@sometimes"]
+        {
+            assert!(schedule.parse::<Schedule>().is_err(),
+                    "{} should not parse",
+                    schedule);
+        }
+    }
+
+    #[test]
+    fn scheduled_tasks_are_parsed_with_optional_jitter() {
+        let tasks = parse_scheduled_tasks("backup=30 2 * * *~300\n\nvacuum=@weekly\n").unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].hook, "backup");
+        assert_eq!(tasks[0].jitter, Duration::from_secs(300));
+        assert_eq!(tasks[0].to_string(), "backup=30 2 * * *~300");
+        assert_eq!(tasks[1].to_string(), "vacuum=@weekly");
+
+        assert!("../backup=@daily".parse::<ScheduledTask>().is_err());
+        assert!("backup".parse::<ScheduledTask>().is_err());
+        assert!("backup=@daily~soon".parse::<ScheduledTask>().is_err());
+    }
+}
@@ -1,5 +1,13 @@
 use crate::error::{Error,
                    Result};
+use chrono::{DateTime,
+             Datelike,
+             Duration as ChronoDuration,
+             Local,
+             NaiveDateTime,
+             TimeZone,
+             Timelike,
+             Utc};
 use regex::Regex;
 use serde_derive::{Deserialize,
                    Serialize};
@@ -9,7 +17,8 @@ use std::{fmt,
                 DerefMut},
           result,
           str::FromStr,
-          time::Duration};
+          time::{Duration,
+                 SystemTime}};
 
 lazy_static::lazy_static! {
     // Note that the application_environment portion of the patern is
@@ -210,6 +219,20 @@ impl ServiceGroup {
     pub fn set_org<T: AsRef<str>>(&mut self, org: T) {
         self.0 = Self::format(self.service(), self.group(), Some(org.as_ref()));
     }
+
+    /// This Service Group, or the same Service Group in `organization` if it names none of its
+    /// own. Binds and configuration which don't name an organization are scoped to the
+    /// Supervisor's this way, so identically-named groups of other organizations are left alone.
+    pub fn in_org(&self, organization: Option<&str>) -> ServiceGroup {
+        match (self.org(), organization) {
+            (None, Some(org)) => {
+                let mut service_group = self.clone();
+                service_group.set_org(org);
+                service_group
+            }
+            _ => self.clone(),
+        }
+    }
 }
 
 impl AsRef<str> for ServiceGroup {
@@ -303,6 +326,204 @@ impl From<Duration> for HealthCheckInterval {
     fn from(d: Duration) -> Self { Self(d) }
 }
 
+/// The days of the week, from Sunday, as update windows name them
+const WEEKDAYS: [&str; 7] = ["sunday",
+                             "monday",
+                             "tuesday",
+                             "wednesday",
+                             "thursday",
+                             "friday",
+                             "saturday"];
+
+/// The times at which the Supervisor may update a service: a range of times of day as
+/// `HH:MM-HH:MM`, optionally preceded by the days of the week it opens on and followed by the
+/// time zone, `UTC` or `local`, which is the default. Days are given by name, or as ranges of
+/// names, separated by commas. A range of times which ends before it starts closes the next day.
+/// (ex: `Sat 02:00-04:00 UTC`, `Mon-Fri 22:00-01:30`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UpdateWindow {
+    expression: String,
+    /// The days the window opens on, as a bit for each, from Sunday
+    days:       u8,
+    /// When the window opens and closes, in minutes since midnight
+    opens:      u32,
+    closes:     u32,
+    utc:        bool,
+}
+
+impl UpdateWindow {
+    /// Whether the window is open at `time`.
+    pub fn contains(&self, time: SystemTime) -> bool {
+        let time = self.naive(time);
+        let today = time.weekday().num_days_from_sunday();
+        let yesterday = (today + 6) % 7;
+        let minute = time.hour() * 60 + time.minute();
+        if self.opens < self.closes {
+            self.opens_on(today) && minute >= self.opens && minute < self.closes
+        } else {
+            (self.opens_on(today) && minute >= self.opens)
+            || (self.opens_on(yesterday) && minute < self.closes)
+        }
+    }
+
+    /// The first time after `after` that the window opens, or `after` itself if it is open then.
+    pub fn next_open(&self, after: SystemTime) -> Option<SystemTime> {
+        if self.contains(after) {
+            return Some(after);
+        }
+        let today = self.naive(after).date();
+        // A day the window opens on comes around within a week, one more in case today's opening
+        // has passed.
+        (0..=7).map(|days| today + ChronoDuration::days(days))
+               .filter(|date| self.opens_on(date.weekday().num_days_from_sunday()))
+               .filter_map(|date| {
+                   self.system_time(date.and_hms(self.opens / 60, self.opens % 60, 0))
+               })
+               .find(|opens| *opens > after)
+    }
+
+    fn opens_on(&self, weekday: u32) -> bool { self.days & (1 << weekday) != 0 }
+
+    fn naive(&self, time: SystemTime) -> NaiveDateTime {
+        if self.utc {
+            DateTime::<Utc>::from(time).naive_utc()
+        } else {
+            DateTime::<Local>::from(time).naive_local()
+        }
+    }
+
+    /// The system time of a time in the window's time zone, if there is one; local times skipped
+    /// over when the clocks go forward don't happen at all.
+    fn system_time(&self, time: NaiveDateTime) -> Option<SystemTime> {
+        if self.utc {
+            Some(Utc.from_utc_datetime(&time).into())
+        } else {
+            Local.from_local_datetime(&time).earliest().map(Into::into)
+        }
+    }
+}
+
+/// The minutes since midnight of a time given as `HH:MM`, where `24:00` is the end of the day.
+fn minute_of_day(s: &str) -> Option<u32> {
+    let mut parts = s.splitn(2, ':');
+    let hour = parts.next()?.parse::<u32>().ok()?;
+    let minute = parts.next()?.parse::<u32>().ok()?;
+    if minute > 59 || hour * 60 + minute > 24 * 60 {
+        return None;
+    }
+    Some(hour * 60 + minute)
+}
+
+/// The day of the week named by `s` or the first three or more letters of its name, from Sunday
+/// as 0.
+fn weekday(s: &str) -> Option<u32> {
+    let s = s.to_lowercase();
+    if s.len() < 3 {
+        return None;
+    }
+    WEEKDAYS.iter()
+            .position(|day| day.starts_with(&s))
+            .map(|day| day as u32)
+}
+
+/// The set of days given as comma-separated days and ranges of days, as a bit for each. Ranges
+/// may run past Saturday into the next week (ex: `Fri-Mon`).
+fn weekdays(s: &str) -> Option<u8> {
+    let mut days = 0;
+    for part in s.split(',') {
+        let mut range = part.splitn(2, '-');
+        let first = weekday(range.next()?)?;
+        let last = match range.next() {
+            Some(last) => weekday(last)?,
+            None => first,
+        };
+        let mut day = first;
+        loop {
+            days |= 1 << day;
+            if day == last {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Some(days)
+}
+
+impl FromStr for UpdateWindow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidUpdateWindow(s.to_string());
+        let expression = s.trim();
+        let mut fields = expression.split_whitespace().collect::<Vec<_>>();
+        let utc = match fields.last() {
+            Some(zone) if zone.eq_ignore_ascii_case("utc") => true,
+            Some(zone) if zone.eq_ignore_ascii_case("local") => false,
+            _ => {
+                fields.push("local");
+                false
+            }
+        };
+        fields.pop();
+        let (days, times) = match fields.as_slice() {
+            [times] => (0b111_1111, *times),
+            [days, times] => (weekdays(days).ok_or_else(invalid)?, *times),
+            _ => return Err(invalid()),
+        };
+        let mut times = times.splitn(2, '-');
+        let opens = times.next().and_then(minute_of_day).ok_or_else(invalid)?;
+        let closes = times.next().and_then(minute_of_day).ok_or_else(invalid)?;
+        // Opening at the end of the day is opening at midnight the next day.
+        if opens == 24 * 60 || opens == closes {
+            return Err(invalid());
+        }
+        Ok(UpdateWindow { expression: expression.to_string(),
+                          days,
+                          opens,
+                          closes,
+                          utc })
+    }
+}
+
+impl fmt::Display for UpdateWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.expression) }
+}
+
+impl<'de> serde::Deserialize<'de> for UpdateWindow {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        struct UpdateWindowVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for UpdateWindowVisitor {
+            type Value = UpdateWindow;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter,
+                       "an update window of days and times (example: Sat 02:00-04:00 UTC)")
+            }
+
+            fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+                where E: serde::de::Error
+            {
+                UpdateWindow::from_str(s).map_err(|_| {
+                    serde::de::Error::invalid_value(serde::de::Unexpected::Str(s), &self)
+                })
+            }
+        }
+
+        deserializer.deserialize_str(UpdateWindowVisitor)
+    }
+}
+
+impl serde::Serialize for UpdateWindow {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -324,6 +545,16 @@ mod test {
         assert!(ServiceGroup::from_str("foo@baz").is_err());
     }
 
+    #[test]
+    fn service_group_in_org_keeps_its_own_org() {
+        let sg = ServiceGroup::from_str("foo.bar").unwrap();
+        assert_eq!(sg.in_org(Some("baz")).as_ref(), "foo.bar@baz");
+        assert_eq!(sg.in_org(None), sg);
+
+        let scoped = ServiceGroup::from_str("foo.bar@acme").unwrap();
+        assert_eq!(scoped.in_org(Some("baz")), scoped);
+    }
+
     #[test]
     fn service_group_from_str_no_group() {
         let group = "foo@baz";
@@ -597,4 +828,61 @@ mod test {
                                                                               without app/env");
         assert_eq!(sg, *data.key.service_group());
     }
+
+    fn utc(s: &str) -> SystemTime {
+        Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap())
+           .into()
+    }
+
+    #[test]
+    fn update_window_is_open_within_its_times_on_its_days() {
+        // 2020-06-06 is a Saturday.
+        let window = UpdateWindow::from_str("Sat 02:00-04:00 UTC").unwrap();
+        assert!(!window.contains(utc("2020-06-06 01:59")));
+        assert!(window.contains(utc("2020-06-06 02:00")));
+        assert!(window.contains(utc("2020-06-06 03:59")));
+        assert!(!window.contains(utc("2020-06-06 04:00")));
+        assert!(!window.contains(utc("2020-06-07 03:00")));
+
+        let window = UpdateWindow::from_str("fri-mon 22:00-01:30 utc").unwrap();
+        assert!(window.contains(utc("2020-06-05 23:00")));
+        assert!(window.contains(utc("2020-06-09 01:00")));
+        assert!(!window.contains(utc("2020-06-09 23:00")));
+        assert!(!window.contains(utc("2020-06-05 01:00")));
+    }
+
+    #[test]
+    fn update_window_next_opens_on_its_next_day() {
+        let window = UpdateWindow::from_str("Sat,Sun 02:00-04:00 UTC").unwrap();
+        assert_eq!(window.next_open(utc("2020-06-03 12:00")),
+                   Some(utc("2020-06-06 02:00")));
+        assert_eq!(window.next_open(utc("2020-06-06 03:00")),
+                   Some(utc("2020-06-06 03:00")));
+        assert_eq!(window.next_open(utc("2020-06-07 05:00")),
+                   Some(utc("2020-06-13 02:00")));
+        let window = UpdateWindow::from_str("00:00-24:00 UTC").unwrap();
+        assert!(window.contains(utc("2020-06-03 23:59")));
+    }
+
+    #[test]
+    fn invalid_update_windows_are_rejected() {
+        for window in &["",
+                        "UTC",
+                        "02:00",
+                        "02:00-02:00",
+                        "24:00-02:00",
+                        "02:00-25:00",
+                        "Sa 02:00-04:00",
+                        "Caturday 02:00-04:00",
+                        "Sat 02:00-04:00 PST",
+                        "Sat Sun 02:00-04:00"]
+        {
+            assert!(UpdateWindow::from_str(window).is_err(),
+                    "{} should not parse",
+                    window);
+        }
+        assert_eq!(UpdateWindow::from_str(" Sat 02:00-04:00 UTC ").unwrap()
+                                                                 .to_string(),
+                   "Sat 02:00-04:00 UTC");
+    }
 }
@@ -0,0 +1,182 @@
+//! Fixtures for testing against Habitat from outside of this workspace: throwaway key caches,
+//! installed packages and free network ports.
+//!
+//! Only built with the `testing` feature.
+
+use crate::{crypto::keys::{box_key_pair::BoxKeyPair,
+                           cache::KeyCache,
+                           sig_key_pair::SigKeyPair,
+                           sym_key::SymKey},
+            error::{Error,
+                    Result},
+            fs,
+            os::users,
+            package::{metadata::MetaFile,
+                      PackageIdent,
+                      PackageInstall,
+                      PackageTarget}};
+use rand::Rng;
+use std::{collections::HashSet,
+          fs as stdfs,
+          net::TcpListener,
+          path::{Path,
+                 PathBuf},
+          sync::Mutex};
+use tempfile::TempDir;
+
+/// The version given to packages installed without one
+const DEFAULT_VERSION: &str = "0.1.0";
+
+/// The release given to packages installed without one
+const DEFAULT_RELEASE: &str = "20200101000000";
+
+/// How many ports to try before giving up on finding a free one
+const PORT_TRIES: usize = 100;
+
+lazy_static::lazy_static! {
+    /// Ports handed out by `unclaimed_port` which haven't been released yet, so that fixtures
+    /// used by tests running in parallel don't pick the same one between checking it's free and
+    /// binding it.
+    static ref CLAIMED_PORTS: Mutex<HashSet<u16>> = Mutex::new(HashSet::new());
+}
+
+/// A key cache in a temporary directory, which is removed when it is dropped.
+pub struct TestKeyCache {
+    dir:   TempDir,
+    cache: KeyCache,
+}
+
+impl TestKeyCache {
+    pub fn new() -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("hab-keys").tempdir()?;
+        let cache = KeyCache::new(dir.path());
+        Ok(TestKeyCache { dir, cache })
+    }
+
+    pub fn path(&self) -> &Path { self.dir.path() }
+
+    pub fn cache(&self) -> &KeyCache { &self.cache }
+
+    /// Generates a ring key named `ring` and writes it to the cache.
+    pub fn generate_ring_key(&self, ring: &str) -> Result<SymKey> {
+        let key = SymKey::generate_pair_for_ring(ring);
+        key.to_pair_files(self.path())?;
+        Ok(key)
+    }
+
+    /// Generates a signing key pair for `origin` and writes both halves to the cache.
+    pub fn generate_origin_key(&self, origin: &str) -> Result<SigKeyPair> {
+        let pair = SigKeyPair::generate_pair_for_origin(origin);
+        pair.to_pair_files(self.path())?;
+        Ok(pair)
+    }
+
+    /// Generates an encryption key pair for `service_group` of `org` and writes both halves to
+    /// the cache.
+    pub fn generate_service_key(&self, org: &str, service_group: &str) -> Result<BoxKeyPair> {
+        let pair = BoxKeyPair::generate_pair_for_service(org, service_group)?;
+        pair.to_pair_files(self.path())?;
+        Ok(pair)
+    }
+}
+
+/// Installs a package with nothing but its metadata under `fs_root`, for the system the tests
+/// are running on, to be run as the current user. Packages not given a version or release get
+/// placeholder ones. Hooks and further files can be written to the returned package's
+/// `installed_path`.
+pub fn install_package(ident: &str, fs_root: &Path) -> Result<PackageInstall> {
+    let mut ident = ident.parse::<PackageIdent>()?;
+    if ident.version.is_none() {
+        ident.version = Some(DEFAULT_VERSION.to_string());
+    }
+    if ident.release.is_none() {
+        ident.release = Some(DEFAULT_RELEASE.to_string());
+    }
+    let path = fs::pkg_install_path(&ident, Some(fs_root));
+    stdfs::create_dir_all(&path)?;
+    let user = users::get_current_username()?.unwrap_or_default();
+    let group = users::get_current_groupname()?.unwrap_or_default();
+    for (metafile, content) in &[(MetaFile::Ident, ident.to_string()),
+                                 (MetaFile::Target, PackageTarget::active_target().to_string()),
+                                 (MetaFile::SvcUser, user),
+                                 (MetaFile::SvcGroup, group)]
+    {
+        stdfs::write(path.join(metafile.to_string()), content)?;
+    }
+    PackageInstall::load(&ident, Some(fs_root))
+}
+
+/// A local TCP port which nothing is listening on, and which hasn't been handed out before
+/// without being released.
+pub fn unclaimed_port() -> Result<u16> {
+    let mut rng = rand::thread_rng();
+    for _ in 0..PORT_TRIES {
+        // IANA port registrations go to 49151
+        let port = rng.gen_range(49152, u32::from(u16::MAX) + 1) as u16;
+        if TcpListener::bind(("127.0.0.1", port)).is_ok()
+           && CLAIMED_PORTS.lock()
+                           .expect("Claimed ports lock is poisoned")
+                           .insert(port)
+        {
+            return Ok(port);
+        }
+    }
+    Err(Error::IO(std::io::Error::new(std::io::ErrorKind::AddrInUse,
+                                      "Couldn't find an unclaimed port")))
+}
+
+/// Makes `port` available to `unclaimed_port` again.
+pub fn release_port(port: u16) {
+    CLAIMED_PORTS.lock()
+                 .expect("Claimed ports lock is poisoned")
+                 .remove(&port);
+}
+
+/// The directory of binaries built alongside the running test executable, which cargo puts in
+/// `target/<profile>/deps`.
+pub fn target_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?
+                           .parent()?
+                           .parent()
+                           .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn installed_packages_get_placeholder_versions() {
+        let fs_root = TempDir::new().unwrap();
+        let install = install_package("acme/widget", fs_root.path()).unwrap();
+        assert_eq!(install.ident().to_string(),
+                   format!("acme/widget/{}/{}", DEFAULT_VERSION, DEFAULT_RELEASE));
+        assert!(install.installed_path().join("SVC_USER").is_file());
+    }
+
+    #[test]
+    fn keys_are_written_to_the_cache() {
+        let keys = TestKeyCache::new().unwrap();
+        let ring = keys.generate_ring_key("test-ring").unwrap();
+        keys.generate_origin_key("acme").unwrap();
+        let names = keys.cache()
+                        .list_all()
+                        .unwrap()
+                        .map(|k| k.name)
+                        .collect::<Vec<_>>();
+        assert!(names.contains(&"test-ring".to_string()));
+        assert!(names.contains(&"acme".to_string()));
+        assert_eq!(SymKey::get_latest_pair_for("test-ring", keys.path()).unwrap()
+                                                                       .name_with_rev(),
+                   ring.name_with_rev());
+    }
+
+    #[test]
+    fn claimed_ports_are_not_handed_out_twice() {
+        let first = unclaimed_port().unwrap();
+        let second = unclaimed_port().unwrap();
+        assert_ne!(first, second);
+        release_port(first);
+        release_port(second);
+    }
+}
@@ -4,6 +4,7 @@ pub mod posix_perm;
 pub mod serde;
 pub mod sys;
 pub mod text_render;
+pub mod units;
 #[cfg(windows)]
 pub mod win_perm;
 
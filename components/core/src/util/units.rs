@@ -0,0 +1,176 @@
+//! Parsing of the durations and sizes given to command line arguments and in config files.
+//!
+//! A duration is a number of seconds, or one or more numbers with a unit of `ms`, `s`, `m`, `h`
+//! or `d` (ex: `90`, `90s`, `5m`, `1h30m`). A size is a number of bytes, or a number with a
+//! unit: `KB`, `MB` and `GB` are powers of 1000, and `KiB`, `MiB` and `GiB` powers of 1024 (ex:
+//! `512MB`). Units are not case sensitive.
+//!
+//! Config files may give either a bare number or a string with units, which is what
+//! `UnitValue` deserializes.
+
+use crate::error::{Error,
+                   Result};
+use serde_derive::Deserialize;
+use std::time::Duration;
+
+const DURATION_UNITS: &[(&str, u64)] = &[("ms", 1),
+                                         ("s", 1000),
+                                         ("m", 60 * 1000),
+                                         ("h", 60 * 60 * 1000),
+                                         ("d", 24 * 60 * 60 * 1000)];
+
+const SIZE_UNITS: &[(&str, u64)] = &[("b", 1),
+                                     ("kb", 1000),
+                                     ("mb", 1000 * 1000),
+                                     ("gb", 1000 * 1000 * 1000),
+                                     ("kib", 1024),
+                                     ("mib", 1024 * 1024),
+                                     ("gib", 1024 * 1024 * 1024)];
+
+/// A duration or size in a config file.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum UnitValue {
+    Number(u64),
+    Text(String),
+}
+
+impl UnitValue {
+    /// This value as a whole number of seconds.
+    pub fn into_secs(self) -> Result<u64> {
+        match self {
+            UnitValue::Number(secs) => Ok(secs),
+            UnitValue::Text(s) => parse_secs(&s),
+        }
+    }
+
+    /// This value as a number of bytes.
+    pub fn into_bytes(self) -> Result<u64> {
+        match self {
+            UnitValue::Number(bytes) => Ok(bytes),
+            UnitValue::Text(s) => parse_size(&s),
+        }
+    }
+}
+
+/// Parses a duration, where a bare number is a number of seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let invalid = |reason: &str| Error::InvalidDuration(format!("'{}' {}", s, reason));
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    if s.is_empty() {
+        return Err(invalid("is empty"));
+    }
+    let mut millis = 0u64;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())
+                         .ok_or_else(|| invalid("has a number without a unit"))?;
+        if digits == 0 {
+            return Err(invalid("does not start with a number"));
+        }
+        let value = rest[..digits].parse::<u64>()
+                                  .map_err(|_| invalid("has a number which is too large"))?;
+        rest = &rest[digits..];
+        let unit_len = rest.find(|c: char| c.is_ascii_digit())
+                           .unwrap_or_else(|| rest.len());
+        let unit = rest[..unit_len].trim().to_lowercase();
+        let scale = scale_of(DURATION_UNITS, &unit).map_err(|e| invalid(&e))?;
+        millis = value.checked_mul(scale)
+                      .and_then(|m| millis.checked_add(m))
+                      .ok_or_else(|| invalid("is too long"))?;
+        rest = &rest[unit_len..];
+    }
+    Ok(Duration::from_millis(millis))
+}
+
+/// Parses a duration which has to be a whole number of seconds.
+pub fn parse_secs(s: &str) -> Result<u64> {
+    let duration = parse_duration(s)?;
+    if duration.subsec_nanos() != 0 {
+        return Err(Error::InvalidDuration(format!("'{}' is not a whole \
+                                                   number of seconds",
+                                                  s)));
+    }
+    Ok(duration.as_secs())
+}
+
+/// Parses a size, where a bare number is a number of bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let invalid = |reason: &str| Error::InvalidSize(format!("'{}' {}", s, reason));
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit())
+                  .unwrap_or_else(|| s.len());
+    if digits == 0 {
+        return Err(invalid("does not start with a number"));
+    }
+    let value = s[..digits].parse::<u64>()
+                           .map_err(|_| invalid("has a number which is too large"))?;
+    let unit = s[digits..].trim().to_lowercase();
+    if unit.is_empty() {
+        return Ok(value);
+    }
+    let scale = scale_of(SIZE_UNITS, &unit).map_err(|e| invalid(&e))?;
+    value.checked_mul(scale)
+         .ok_or_else(|| invalid("is too large"))
+}
+
+/// How many of the smallest unit of `units` there are in `unit`.
+fn scale_of(units: &[(&str, u64)], unit: &str) -> std::result::Result<u64, String> {
+    units.iter()
+         .find(|(name, _)| *name == unit)
+         .map(|(_, scale)| *scale)
+         .ok_or_else(|| format!("has an unknown unit '{}'", unit))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn durations_are_parsed_with_or_without_units() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration(" 2D ").unwrap(),
+                   Duration::from_secs(172_800));
+        assert_eq!(parse_duration("1s500ms").unwrap(),
+                   Duration::from_millis(1500));
+        for invalid in &["", "s", "5x", "5m3", "-5s", "99999999999999999999d"] {
+            assert!(parse_duration(invalid).is_err(),
+                    "'{}' should not be a valid duration",
+                    invalid);
+        }
+        assert_eq!(parse_secs("2m").unwrap(), 120);
+        assert!(parse_secs("1500ms").is_err());
+    }
+
+    #[test]
+    fn sizes_are_parsed_with_or_without_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("512B").unwrap(), 512);
+        assert_eq!(parse_size("64KB").unwrap(), 64_000);
+        assert_eq!(parse_size("512mb").unwrap(), 512_000_000);
+        assert_eq!(parse_size("1 GiB").unwrap(), 1_073_741_824);
+        for invalid in &["", "MB", "5XB", "-1", "1.5GB"] {
+            assert!(parse_size(invalid).is_err(),
+                    "'{}' should not be a valid size",
+                    invalid);
+        }
+    }
+
+    #[test]
+    fn config_values_are_numbers_or_strings_with_units() {
+        #[derive(Deserialize)]
+        struct Config {
+            period: UnitValue,
+            limit:  UnitValue,
+        }
+        let config: Config = toml::from_str("period = 60\nlimit = \"1KiB\"").unwrap();
+        assert_eq!(config.period.into_secs().unwrap(), 60);
+        assert_eq!(config.limit.into_bytes().unwrap(), 1024);
+    }
+}
@@ -1,16 +1,29 @@
 pub mod gateway_util;
 pub mod hab;
 
-use crate::{cli::hab::{origin::Rbac,
+use crate::{cli::hab::{cache::Cache,
+                       origin::{KeyBundle,
+                                KeyList,
+                                KeyRevoke,
+                                KeyRotate,
+                                Rbac},
                        pkg::{ExportCommand,
-                             PkgExec},
+                             PkgExec,
+                             PkgReSign,
+                             PkgShell,
+                             PkgUpgradeHart},
+                       ring::RingExec,
                        studio::Studio,
                        sup::{HabSup,
                              SupRun},
-                       svc::{BulkLoad as SvcBulkLoad,
+                       svc::{ApplyManifest as SvcApplyManifest,
+                             BulkLoad as SvcBulkLoad,
                              Load as SvcLoad,
+                             SvcEnv,
                              Update as SvcUpdate},
-                       util::CACHE_KEY_PATH_DEFAULT,
+                       util::{CtlTlsOptions,
+                              HttpGatewayOptions,
+                              CACHE_KEY_PATH_DEFAULT},
                        Hab},
             command::studio};
 use clap::{App,
@@ -22,21 +35,26 @@ use habitat_common::{cli::{file_into_idents,
                            BINLINK_DIR_ENVVAR,
                            DEFAULT_BINLINK_DIR,
                            PACKAGE_TARGET_ENVVAR},
+                     command::package::license::LicensePolicyMode,
                      FeatureFlag};
-use habitat_core::{crypto::{keys::PairType,
+use habitat_core::{crypto::{keys::{parse_name_with_rev,
+                                   PairType},
                             CACHE_KEY_PATH_ENV_VAR},
                    env::Config,
+                   fs::KeyPermissionPolicy,
                    origin::Origin,
                    os::process::ShutdownTimeout,
                    package::{Identifiable,
                              PackageIdent,
                              PackageTarget},
-                   service::ServiceGroup,
+                   service::{ServiceGroup,
+                             UpdateWindow},
                    ChannelIdent};
 use std::{path::Path,
           result,
           str::FromStr};
-use structopt::StructOpt;
+use structopt::{StructOpt,
+                StructOptInternal};
 use url::Url;
 
 /// Process exit code from Supervisor which indicates to Launcher that the Supervisor
@@ -87,6 +105,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@subcommand accept =>
                 (about: "Accept the Chef Binary Distribution Agreement without prompting"))
         )
+        (subcommand: Cache::clap())
         (@subcommand cli =>
             (about: "Commands relating to Habitat runtime config")
             (aliases: &["cl"])
@@ -94,6 +113,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@setting SubcommandRequiredElseHelp)
             (subcommand: sub_cli_setup().aliases(&["s", "se", "set", "setu"]))
             (subcommand: sub_cli_completers().aliases(&["c", "co", "com", "comp"]))
+            (subcommand: sub_cli_secret())
         )
         (@subcommand config =>
             (about: "Commands relating to a Service's runtime config")
@@ -101,21 +121,21 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (@setting ArgRequiredElseHelp)
             (@setting SubcommandRequiredElseHelp)
             (subcommand: sub_config_apply().aliases(&["ap", "app", "appl"]))
-            (@subcommand show =>
+            (subcommand: add_ctl_tls_options(clap_app!(@subcommand show =>
                 (about: "Displays the default configuration options for a service")
                 (aliases: &["sh", "sho"])
                 (@arg PKG_IDENT: +required +takes_value {valid_ident}
                     "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
                 (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
                     "Address to a remote Supervisor's Control Gateway")
-            )
+            )))
         )
         (@subcommand file =>
             (about: "Commands relating to Habitat files")
             (aliases: &["f", "fi", "fil"])
             (@setting ArgRequiredElseHelp)
             (@setting SubcommandRequiredElseHelp)
-            (@subcommand upload =>
+            (subcommand: add_ctl_tls_options(clap_app!(@subcommand upload =>
                 (about: "Uploads a file to be shared between members of a Service Group")
                 (aliases: &["u", "up", "upl", "uplo", "uploa"])
                 (@arg SERVICE_GROUP: +required +takes_value {valid_service_group}
@@ -124,10 +144,12 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     "A version number (positive integer) for this configuration (ex: 42)")
                 (@arg FILE: +required +takes_value {file_exists} "Path to local file on disk")
                 (@arg USER: -u --user +takes_value "Name of the user key")
+                (@arg SIGN_WITH: --("sign-with") +takes_value
+                    "Sign the request with the latest revision of this signing key (ex: acme)")
                 (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
                     "Address to a remote Supervisor's Control Gateway")
                 (arg: arg_cache_key_path())
-            )
+            )))
         )
         (@subcommand bldr =>
             (about: "Commands relating to Habitat Builder")
@@ -261,6 +283,27 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
 
                 )
+                (@subcommand diff =>
+                    (about: "Compares the latest packages of two channels")
+                    (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                        "Specify an alternate Builder endpoint. If not specified, the value will \
+                         be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                         https://bldr.habitat.sh)")
+                    (@arg ORIGIN: -o --origin +takes_value {valid_origin}
+                        "The origin for the channels. Default is from \
+                        'HAB_ORIGIN' or cli.toml")
+                    (@arg FROM_CHANNEL: +required +takes_value
+                        "The channel to compare from, such as the one packages would be \
+                        promoted to")
+                    (@arg TO_CHANNEL: +required +takes_value
+                        "The channel to compare to; its packages are listed as added or changed")
+                    (@arg PKG_TARGET: -t --target +takes_value {valid_target}
+                        env(PACKAGE_TARGET_ENVVAR)
+                        "A package target (ex: x86_64-windows) (default: system appropriate \
+                        target)")
+                    (@arg TO_JSON: -j --json "Output will be rendered in json")
+                    (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                )
                 (@subcommand create =>
                     (about: "Creates a new channel")
                     (aliases: &["c", "cr", "cre", "crea", "creat"])
@@ -423,8 +466,11 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (about: "Download origin key(s)")
                     (aliases: &["d", "do", "dow", "down", "downl", "downlo", "downloa"])
                     (arg: arg_cache_key_path())
-                    (@arg ORIGIN: +required +takes_value {valid_origin} "The origin name" )
-                    (@arg REVISION: +takes_value "The origin key revision")
+                    (@arg ORIGIN: +takes_value +use_delimiter required_unless[FROM_FILE] {valid_origin}
+                        "The origin name, or a comma-separated list of origin names (ex: core,acme)")
+                    (@arg REVISION: +takes_value conflicts_with[FROM_FILE] "The origin key revision")
+                    (@arg FROM_FILE: --("from-file") +takes_value {file_exists}
+                        "Also download the keys of the origins listed in this file, one per line")
                     (@arg BLDR_URL: -u --url +takes_value {valid_url}
                         "Specify an alternate Builder endpoint. If not specified, the value will \
                          be taken from the HAB_BLDR_URL environment variable if defined. (default: \
@@ -435,6 +481,15 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                         "Download public encryption key instead of origin public key")
                     (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder \
                         (required for downloading origin private keys)")
+                    (@arg JOBS: -j --jobs +takes_value {valid_numeric::<usize>}
+                        "The maximum number of origins, or revisions of an origin's public key, \
+                        to download at once (default: 8)")
+                    (@arg KEY_PERMISSIONS: --("key-permissions") +takes_value default_value[strict]
+                        possible_values(KeyPermissionPolicy::VARIANTS)
+                        "Whether downloaded secret keys can only be read by their owner, or by \
+                        their group as well")
+                    (@arg KEY_GROUP: --("key-group") +takes_value
+                        "The group to give downloaded keys to, such as one shared by CI runners")
                 )
                 (@subcommand export =>
                     (about: "Outputs the latest origin key contents to stdout")
@@ -442,12 +497,17 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (@arg ORIGIN: +required +takes_value {valid_origin} "The origin name")
                     (@arg PAIR_TYPE: -t --type +takes_value {valid_pair_type}
                         "Export either the 'public' or 'secret' key. The 'secret' key is the origin private key")
+                    (@arg FORMAT: --format +takes_value default_value[habitat]
+                        possible_values(&["habitat", "pem", "jwk"])
+                        "Export the key in Habitat's own format, or as PEM or a JWK")
                     (arg: arg_cache_key_path())
                 )
                 (@subcommand generate =>
                     (about: "Generates a Habitat origin key pair")
                     (aliases: &["g", "ge", "gen", "gene", "gener", "genera", "generat"])
                     (@arg ORIGIN: +takes_value {valid_origin} "The origin name")
+                    (@arg PASSPHRASE_FILE: --("passphrase-file") +takes_value {file_exists}
+                        "Encrypt the secret key with the passphrase on the first line of this file")
                     (arg: arg_cache_key_path())
 
                 )
@@ -457,6 +517,10 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (aliases: &["i", "im", "imp", "impo", "impor"])
                     (arg: arg_cache_key_path())
                 )
+                (subcommand: KeyBundle::clap())
+                (subcommand: KeyList::clap())
+                (subcommand: KeyRevoke::clap())
+                (subcommand: KeyRotate::clap())
                 (@subcommand upload =>
                     (@group upload =>
                         (@attributes +required)
@@ -608,6 +672,8 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg LIMIT: -l --limit +takes_value default_value("50") {valid_numeric::<usize>}
                     "Limit how many packages to retrieve")
             )
+            (subcommand: PkgReSign::clap())
+            (subcommand: PkgShell::clap())
             (@subcommand sign =>
                 (about: "Signs an archive with an origin key, generating a Habitat Artifact")
                 (aliases: &["s", "si", "sig"])
@@ -618,6 +684,12 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg DEST: +required +takes_value
                     "The destination path to the signed Habitat Artifact \
                     (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg APPEND: --append "Add a signature to SOURCE, a HART-2 Habitat Artifact, \
+                    keeping its existing signatures")
+                (@arg SIGNING_BACKEND: --("signing-backend") +takes_value default_value[sodium]
+                    possible_values(&["sodium", "pkcs11"])
+                    "Sign with the origin's secret key from the key cache, or with its key on the \
+                    PKCS #11 token loaded from HAB_PKCS11_MODULE")
                 (arg: arg_cache_key_path())
             )
             (@subcommand uninstall =>
@@ -656,6 +728,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg UPLOAD_DIRECTORY: +required +takes_value {dir_exists}
                     "Directory Path from which artifacts will be uploaded")
             )
+            (subcommand: PkgUpgradeHart::clap())
             (@subcommand upload =>
                 (about: "Uploads a local Habitat Artifact to Builder")
                 (aliases: &["u", "up", "upl", "uplo", "uploa"])
@@ -693,11 +766,15 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg BLDR_URL: -u --url +takes_value {valid_url} "Specify an alternate Builder \
                     endpoint. If not specified, the value will be taken from the HAB_BLDR_URL \
                     environment variable if defined. (default: https://bldr.habitat.sh)")
-                (@arg PKG_IDENT: +required +takes_value {valid_fully_qualified_ident} "A fully qualified package identifier \
-                    (ex: core/busybox-static/1.42.2/20170513215502)")
+                (@arg PKG_IDENT: +required +takes_value {valid_fully_qualified_ident_or_file} "A fully qualified package identifier \
+                    (ex: core/busybox-static/1.42.2/20170513215502), or a file of newline separated fully \
+                    qualified package identifiers ('-' reads them from stdin)")
                 (@arg CHANNEL: +required +takes_value "Promote to the specified release channel")
                 (arg: arg_target())
                 (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                (@arg CONCURRENCY: --concurrency +takes_value {valid_numeric::<usize>}
+                    "The maximum number of packages to promote at once when given a file of package \
+                    identifiers (default: 8)")
             )
             (@subcommand demote =>
                 (about: "Demote a package from a specified channel")
@@ -705,11 +782,15 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (@arg BLDR_URL: -u --url +takes_value {valid_url} "Specify an alternate Builder \
                     endpoint. If not specified, the value will be taken from the HAB_BLDR_URL \
                     environment variable if defined. (default: https://bldr.habitat.sh)")
-                (@arg PKG_IDENT: +required +takes_value {valid_fully_qualified_ident} "A fully qualified package identifier \
-                    (ex: core/busybox-static/1.42.2/20170513215502)")
+                (@arg PKG_IDENT: +required +takes_value {valid_fully_qualified_ident_or_file} "A fully qualified package identifier \
+                    (ex: core/busybox-static/1.42.2/20170513215502), or a file of newline separated fully \
+                    qualified package identifiers ('-' reads them from stdin)")
                 (@arg CHANNEL: +required +takes_value "Demote from the specified release channel")
                 (arg: arg_target())
                 (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                (@arg CONCURRENCY: --concurrency +takes_value {valid_numeric::<usize>}
+                    "The maximum number of packages to demote at once when given a file of package \
+                    identifiers (default: 8)")
             )
             (@subcommand channels =>
                 (about: "Find out what channels a package belongs to")
@@ -722,11 +803,36 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                 (arg: arg_target())
                 (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
             )
+            (@subcommand audit =>
+                (about: "Audits installed packages, checking their files against their FILES \
+                    manifests and for changed ownership or permissions")
+                (@arg PKG_IDENT: +takes_value {valid_ident} "A package identifier \
+                    (ex: core/redis, core/busybox-static/1.42.2). Every installed package is \
+                    audited when it is not given")
+                (@arg TO_JSON: -j --json "Output a summary of the audited packages in json")
+                (@arg QUARANTINE: --quarantine "Remove the binlinks to binaries of packages which \
+                    have drifted")
+                (@arg DEST_DIR: -d --dest +takes_value {non_empty} env(BINLINK_DIR_ENVVAR) default_value(DEFAULT_BINLINK_DIR)
+                    "The binlink directory to remove binlinks from with --quarantine")
+            )
             (@subcommand verify =>
-                (about: "Verifies a Habitat Artifact with an origin key")
+                (about: "Verifies a Habitat Artifact, or every Habitat Artifact in a directory, \
+                    with an origin key")
                 (aliases: &["v", "ve", "ver", "veri", "verif"])
-                (@arg SOURCE: +required +takes_value {file_exists} "A path to a Habitat Artifact \
-                    (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg SOURCE: +required +takes_value {file_or_dir_exists} "A path to a Habitat \
+                    Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart) or a \
+                    directory of them (ex: /hab/cache/artifacts)")
+                (@arg TO_JSON: -j --json "Output a summary of the verified artifacts in json")
+                (@arg REQUIRE_SIGNER: --("require-signer") +takes_value +multiple
+                    number_of_values(1) {valid_origin} "Require every signature of an artifact \
+                    to be valid, rather than at least one, and one of them to be by a key of this \
+                    origin. Repeat for each origin whose signature is required, as signatures can \
+                    be removed from an artifact without invalidating the others")
+                (@arg DEEP: --deep "Also unpack each artifact and check its files against the \
+                    checksums in its FILES manifest")
+                (@arg POLICY: --policy +takes_value {file_exists} "A key trust policy to check \
+                    signers against, in place of the one in the key cache \
+                    (ex: /etc/hab/trust-policy.toml)")
                 (arg: arg_cache_key_path())
             )
             (@subcommand header =>
@@ -737,11 +843,21 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
             )
             (@subcommand info =>
-                (about: "Returns the Habitat Artifact information")
+                (about: "Returns the information of a Habitat Artifact, an installed package, or \
+                    a package in Builder")
                 (aliases: &["inf", "info"])
                 (@arg TO_JSON: -j --json "Output will be rendered in json. (Includes extended metadata)")
-                (@arg SOURCE: +required +takes_value {file_exists} "A path to a Habitat Artifact \
-                    (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+                (@arg SOURCE: +required +takes_value {file_exists_or_ident} "A path to a Habitat \
+                    Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart) or a \
+                    package identifier (ex: core/redis)")
+                (@arg BLDR_URL: -u --url +takes_value {valid_url}
+                    "Specify an alternate Builder endpoint. If not specified, the value will \
+                     be taken from the HAB_BLDR_URL environment variable if defined. (default: \
+                     https://bldr.habitat.sh)")
+                (@arg CHANNEL: --channel -c +takes_value default_value[stable] env(ChannelIdent::ENVVAR)
+                    "Look the package up in the specified release channel when it is not installed")
+                (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
+                (arg: arg_target())
             )
             (@subcommand dependencies =>
                 (about: "Returns the Habitat Artifact dependencies. By default it will return \
@@ -790,6 +906,7 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (aliases: &["r", "ri", "rin"])
             (@setting ArgRequiredElseHelp)
             (@setting SubcommandRequiredElseHelp)
+            (subcommand: RingExec::clap())
             (@subcommand key =>
                 (about: "Commands relating to Habitat ring keys")
                 (aliases: &["k", "ke"])
@@ -805,14 +922,41 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
                     (about: "Reads a stdin stream containing ring key contents and writes \
                     the key to disk")
                     (aliases: &["i", "im", "imp", "impo", "impor"])
+                    (@arg FROM_STDIN: --("from-stdin")
+                        "Read the ring key from standard input even if it is a terminal")
                     (arg: arg_cache_key_path())
                 )
                 (@subcommand generate =>
                     (about: "Generates a Habitat ring key")
                     (aliases: &["g", "ge", "gen", "gene", "gener", "genera", "generat"])
                     (@arg RING: +required +takes_value "Ring key name")
+                    (@arg STDOUT: --stdout
+                        "Write the ring key to a redirected or piped standard output instead of \
+                         the key cache")
+                    (@arg FROM_PASSPHRASE: --("from-passphrase") +takes_value {file_exists}
+                        requires[REVISION]
+                        "Derive the ring key from the passphrase on the first line of this file, \
+                         so that the same key is generated wherever the passphrase is given")
+                    (@arg REVISION: --revision +takes_value {valid_key_revision}
+                        requires[FROM_PASSPHRASE]
+                        "The revision of the ring key to derive from the passphrase \
+                         (ex: 20200601000000). Give the same one wherever the key is derived, \
+                         and a newer one to rotate the ring to a new derived key")
+                    (@arg PASSPHRASE_FILE: --("passphrase-file") +takes_value {file_exists}
+                        "Encrypt the secret key with the passphrase on the first line of this file")
                     (arg: arg_cache_key_path())
                 )
+                (subcommand: add_ctl_tls_options(clap_app!(@subcommand rotate =>
+                    (about: "Rotates the ring key of running Supervisors to a new revision \
+                    without restarting them")
+                    (aliases: &["ro", "rot", "rota", "rotat"])
+                    (@arg RING: +required +takes_value "Ring key name")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value +multiple
+                        number_of_values(1) default_value("127.0.0.1:9632")
+                        "Address to the Control Gateway of a Supervisor in the ring. Give it \
+                         once for every Supervisor in the ring")
+                    (arg: arg_cache_key_path())
+                )))
             )
         )
         (subcommand: HabSup::clap())
@@ -821,7 +965,9 @@ pub fn get(feature_flags: FeatureFlag) -> App<'static, 'static> {
             (aliases: &["sv", "ser", "serv", "service"])
             (@setting ArgRequiredElseHelp)
             (@setting SubcommandRequiredElseHelp)
+            (subcommand: SvcApplyManifest::clap())
             (subcommand: SvcBulkLoad::clap())
+            (subcommand: SvcEnv::clap())
             (@subcommand key =>
                 (about: "Commands relating to Habitat service keys")
                 (aliases: &["k", "ke"])
@@ -911,6 +1057,28 @@ fn sub_cli_completers() -> App<'static, 'static> {
                                    .possible_values(&supported_shells))
 }
 
+fn sub_cli_secret() -> App<'static, 'static> {
+    clap_app!(@subcommand secret =>
+        (about: "Commands relating to the Supervisor control gateway secret used by the CLI")
+        (@setting ArgRequiredElseHelp)
+        (@setting SubcommandRequiredElseHelp)
+        (@subcommand set =>
+            (about: "Stores a control gateway secret in the OS keyring, falling back to the CLI \
+                config file on systems without a keyring")
+            (@arg SECRET_FILE: --("secret-file") +takes_value {file_exists}
+                "Read the control gateway secret from this file rather than from stdin, so that \
+                it is never given on the command line where other users could see it")
+        )
+        (@subcommand show =>
+            (about: "Displays the control gateway secret used by the CLI and where it was found")
+        )
+        (@subcommand clear =>
+            (about: "Removes the stored control gateway secret from the OS keyring and the CLI \
+                config file")
+        )
+    )
+}
+
 fn arg_cache_key_path() -> Arg<'static, 'static> {
     Arg::with_name("CACHE_KEY_PATH").long("cache-key-path")
                                     .validator(non_empty)
@@ -970,8 +1138,10 @@ fn sub_pkg_download() -> App<'static, 'static> {
         "File with newline separated package identifiers, or TOML file (ending with .toml extension)")
     (@arg PKG_IDENT: +multiple +takes_value {valid_ident}
             "One or more Habitat package identifiers (ex: acme/redis)")
-    (@arg PKG_TARGET: --target -t +takes_value {valid_target}
-            "Target architecture to fetch. E.g. x86_64-linux. Overridden if architecture is specified in toml file")
+    (@arg PKG_TARGET: --target -t +takes_value +multiple number_of_values(1) {valid_target}
+            "Target architecture to fetch. E.g. x86_64-linux. May be given more than once to fetch \
+             several targets, each into its own directory. Overridden if architecture is specified \
+             in toml file")
     (@arg VERIFY: --verify
             "Verify package integrity after download (Warning: this can be slow)")
     (@arg IGNORE_MISSING_SEEDS: --("ignore-missing-seeds")
@@ -991,7 +1161,8 @@ fn sub_pkg_install(feature_flags: FeatureFlag) -> App<'static, 'static> {
             "Install from the specified release channel")
         (@arg PKG_IDENT_OR_ARTIFACT: +required +multiple +takes_value
             "One or more Habitat package identifiers (ex: acme/redis) and/or filepaths \
-            to a Habitat Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
+            or HTTP(S) URLs of a Habitat Artifact \
+            (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)")
         (@arg BINLINK: -b --binlink
             "Binlink all binaries from installed package(s) into BINLINK_DIR")
         (@arg BINLINK_DIR: --("binlink-dir") +takes_value {non_empty} env(BINLINK_DIR_ENVVAR)
@@ -999,6 +1170,13 @@ fn sub_pkg_install(feature_flags: FeatureFlag) -> App<'static, 'static> {
         (@arg FORCE: -f --force "Overwrite existing binlinks")
         (@arg AUTH_TOKEN: -z --auth +takes_value "Authentication token for Builder")
         (@arg IGNORE_INSTALL_HOOK: --("ignore-install-hook") "Do not run any install hooks")
+        (@arg DENY_LICENSE: --("deny-license") +takes_value +multiple number_of_values(1)
+            use_delimiter(true) env("HAB_DENY_LICENSE")
+            "Do not install a package if it or any of its dependencies is released under this \
+            license (ex: AGPL-3.0)")
+        (@arg LICENSE_POLICY: --("license-policy") +takes_value default_value[enforce]
+            possible_values(LicensePolicyMode::VARIANTS)
+            "Whether a package released under a denied license fails the install or only warns")
     );
     sub = sub.arg(Arg::with_name("OFFLINE").help("Install packages in offline mode")
                                                .hidden(!feature_flags.contains(FeatureFlag::OFFLINE_INSTALL))
@@ -1013,7 +1191,7 @@ fn sub_pkg_install(feature_flags: FeatureFlag) -> App<'static, 'static> {
 }
 
 fn sub_config_apply() -> App<'static, 'static> {
-    clap_app!(@subcommand apply =>
+    let sub = clap_app!(@subcommand apply =>
     (about: "Sets a configuration to be shared by members of a Service Group")
     (@arg SERVICE_GROUP: +required +takes_value {valid_service_group}
         "Target service group service.group[@organization] (ex: redis.default or foo.default@bazcorp)")
@@ -1022,31 +1200,41 @@ fn sub_config_apply() -> App<'static, 'static> {
     (@arg FILE: +takes_value {file_exists_or_stdin}
         "Path to local file on disk (ex: /tmp/config.toml, default: <stdin>)")
     (@arg USER: -u --user +takes_value "Name of a user key to use for encryption")
+    (@arg SIGN_WITH: --("sign-with") +takes_value
+        "Sign the request with the latest revision of this signing key (ex: acme)")
+    (@arg DRY_RUN: --("dry-run")
+        "Report whether each member of the group would apply the configuration live or restart, without applying it")
     (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
         "Address to a remote Supervisor's Control Gateway")
     (arg: arg_cache_key_path())
-    )
+    );
+    add_ctl_tls_options(sub)
 }
 
 fn sub_svc_start() -> App<'static, 'static> {
-    clap_app!(@subcommand start =>
+    let sub = clap_app!(@subcommand start =>
         (about: "Start a loaded, but stopped, Habitat service")
         (@arg PKG_IDENT: +required +takes_value {valid_ident}
             "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
             "Address to a remote Supervisor's Control Gateway")
-    )
+    );
+    add_ctl_tls_options(HttpGatewayOptions::augment_clap(sub))
 }
 
 // `hab svc status` is the canonical location for this command, but we
 // have historically used `hab sup status` as an alias.
 fn sub_svc_status() -> App<'static, 'static> {
-    clap_app!(@subcommand status =>
+    let sub = clap_app!(@subcommand status =>
         (about: "Query the status of Habitat services")
         (@arg PKG_IDENT: +takes_value {valid_ident} "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
+        (@arg DETAILS: --details "Also show how each service's process is scheduled")
+        (@arg HISTORY: --history conflicts_with[DETAILS]
+            "Show recorded lifecycle transitions instead of current status")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
             "Address to a remote Supervisor's Control Gateway")
-    )
+    );
+    add_ctl_tls_options(sub)
 }
 
 pub fn parse_optional_arg<T: FromStr>(name: &str, m: &ArgMatches) -> Option<T>
@@ -1063,7 +1251,7 @@ fn sub_svc_stop() -> App<'static, 'static> {
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
             "Address to a remote Supervisor's Control Gateway")
     );
-    add_shutdown_timeout_option(sub)
+    add_ctl_tls_options(add_shutdown_timeout_option(sub))
 }
 
 fn sub_svc_unload() -> App<'static, 'static> {
@@ -1074,8 +1262,18 @@ fn sub_svc_unload() -> App<'static, 'static> {
             "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value default_value("127.0.0.1:9632")
             "Address to a remote Supervisor's Control Gateway")
+        (@arg RETAIN_DATA: --("retain-data") conflicts_with[PURGE_DATA]
+            "Keep the service's data directory (default)")
+        (@arg PURGE_DATA: --("purge-data")
+            "Delete the service's data directory once the service has stopped")
+        (@arg ARCHIVE_DATA: --("archive-data") +takes_value {non_empty}
+            "Write a tarball of the service's data directory to this path on the Supervisor's \
+            host before it is purged")
+        (@arg ENCRYPT_ARCHIVE: --("encrypt-archive") requires[ARCHIVE_DATA]
+            "Encrypt the tarball of --archive-data with the service group's key")
+        (@arg NO_PROMPT: -n --("no-prompt") "Do not prompt for confirmation of --purge-data")
     );
-    add_shutdown_timeout_option(sub)
+    add_ctl_tls_options(add_shutdown_timeout_option(sub))
 }
 
 // CLAP Validation Functions
@@ -1098,6 +1296,15 @@ fn valid_service_group(val: String) -> result::Result<(), String> {
     ServiceGroup::validate(&val).map_err(|e| e.to_string())
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_update_window_or_empty(val: String) -> result::Result<(), String> {
+    if val.is_empty() {
+        return Ok(());
+    }
+    UpdateWindow::from_str(&val).map(|_| ())
+                                .map_err(|e| e.to_string())
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn dir_exists(val: String) -> result::Result<(), String> {
     if Path::new(&val).is_dir() {
@@ -1116,6 +1323,24 @@ fn file_exists(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn file_or_dir_exists(val: String) -> result::Result<(), String> {
+    if Path::new(&val).exists() {
+        Ok(())
+    } else {
+        Err(format!("File or directory: '{}' cannot be found", &val))
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn file_exists_or_ident(val: String) -> result::Result<(), String> {
+    if Path::new(&val).is_file() {
+        Ok(())
+    } else {
+        valid_ident(val)
+    }
+}
+
 fn file_exists_or_stdin(val: String) -> result::Result<(), String> {
     if val == "-" {
         Ok(())
@@ -1185,6 +1410,15 @@ fn valid_target(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_fully_qualified_ident_or_file(val: String) -> result::Result<(), String> {
+    if val == "-" || Path::new(&val).is_file() {
+        Ok(())
+    } else {
+        valid_fully_qualified_ident(val)
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn valid_fully_qualified_ident(val: String) -> result::Result<(), String> {
     match PackageIdent::from_str(&val) {
@@ -1202,6 +1436,21 @@ fn valid_fully_qualified_ident(val: String) -> result::Result<(), String> {
 fn valid_origin(val: String) -> result::Result<(), String> { Origin::validate(val) }
 
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_name_with_rev(val: String) -> result::Result<(), String> {
+    parse_name_with_rev(&val).map(|_| ())
+                             .map_err(|e| e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_key_revision(val: String) -> result::Result<(), String> {
+    if val.len() == 14 && val.chars().all(|c| c.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(format!("Ring key revision: '{}' is not a timestamp like 20200601000000",
+                    &val))
+    }
+}
+
 fn valid_shutdown_timeout(val: String) -> result::Result<(), String> {
     match ShutdownTimeout::from_str(&val) {
         Ok(_) => Ok(()),
@@ -1224,14 +1473,20 @@ fn non_empty(val: String) -> result::Result<(), String> {
 
 /// Adds extra configuration option for shutting down a service with a customized timeout.
 fn add_shutdown_timeout_option(app: App<'static, 'static>) -> App<'static, 'static> {
-    app.arg(Arg::with_name("SHUTDOWN_TIMEOUT").help("The delay in seconds after sending the \
-                                                     shutdown signal to wait before killing the \
-                                                     service process")
+    app.arg(Arg::with_name("SHUTDOWN_TIMEOUT").help("The delay after sending the shutdown signal \
+                                                     to wait before killing the service process, \
+                                                     in seconds or with units (ex: 90s, 2m)")
                                               .long("shutdown-timeout")
                                               .validator(valid_shutdown_timeout)
                                               .takes_value(true))
 }
 
+/// Adds the options for connecting to the Control Gateway with TLS, which are defined once, for
+/// both command lines, by `CtlTlsOptions`.
+fn add_ctl_tls_options(app: App<'static, 'static>) -> App<'static, 'static> {
+    CtlTlsOptions::augment_clap(app)
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -1294,6 +1549,54 @@ mod tests {
             let run_matches = run_matches.expect("Error while getting run matches");
             assert_eq!(run_matches.value_of("PEER"), Some("1.1.1.1"));
         }
+
+        #[test]
+        fn sup_subcommand_simulate_validates_network_conditions() {
+            let r = get(no_feature_flags()).get_matches_from_safe(vec!["hab",
+                                                                       "sup",
+                                                                       "simulate",
+                                                                       "--members",
+                                                                       "500",
+                                                                       "--loss",
+                                                                       "2.5",
+                                                                       "--partitions",
+                                                                       "2",
+                                                                       "--partition-for",
+                                                                       "1m"]);
+            assert!(r.is_ok());
+            let r = get(no_feature_flags()).get_matches_from_safe(vec!["hab", "sup", "simulate",
+                                                                       "--loss", "101"]);
+            assert_eq!(r.unwrap_err().kind, ErrorKind::ValueValidation);
+            let r = get(no_feature_flags()).get_matches_from_safe(vec!["hab",
+                                                                       "sup",
+                                                                       "simulate",
+                                                                       "--partition-for",
+                                                                       "1m"]);
+            assert_eq!(r.unwrap_err().kind, ErrorKind::MissingRequiredArgument);
+        }
+
+        #[test]
+        fn svc_status_ctl_client_certificate_requires_key_and_ca() {
+            let status = |tls_args: &[&'static str]| {
+                let mut args = vec!["hab", "svc", "status"];
+                args.extend_from_slice(tls_args);
+                get(no_feature_flags()).get_matches_from_safe(args)
+            };
+            let r = status(&["--ctl-server-ca-certificate",
+                             "/ca.pem",
+                             "--ctl-client-certificate",
+                             "/client.pem",
+                             "--ctl-client-key",
+                             "/client.key"]);
+            assert!(r.is_ok());
+            let r = status(&["--ctl-server-ca-certificate",
+                             "/ca.pem",
+                             "--ctl-client-certificate",
+                             "/client.pem"]);
+            assert_eq!(r.unwrap_err().kind, ErrorKind::MissingRequiredArgument);
+            let r = status(&["--ctl-client-certificate", "/client.pem", "--ctl-client-key", "/k"]);
+            assert_eq!(r.unwrap_err().kind, ErrorKind::MissingRequiredArgument);
+        }
     }
 
     mod event_stream_feature {
@@ -1595,5 +1898,129 @@ mod tests {
             let error = matches.unwrap_err();
             assert_eq!(error.kind, clap::ErrorKind::ValueValidation);
         }
+
+        #[test]
+        fn kafka_brokers_option_requires_topic_app_and_env() {
+            let matches = SupRun::clap().get_matches_from_safe(vec!["run",
+                                                                    "--event-stream-application",
+                                                                    "MY_APP",
+                                                                    "--event-stream-environment",
+                                                                    "MY_ENV",
+                                                                    "--event-stream-kafka-brokers",
+                                                                    "kafka1:9092"]);
+            assert_eq!(matches.unwrap_err().kind,
+                       clap::ErrorKind::MissingRequiredArgument);
+            let matches = SupRun::clap().get_matches_from_safe(vec!["run",
+                                                                    "--event-stream-application",
+                                                                    "MY_APP",
+                                                                    "--event-stream-environment",
+                                                                    "MY_ENV",
+                                                                    "--event-stream-kafka-brokers",
+                                                                    "kafka1:9092,kafka2:9092",
+                                                                    "--event-stream-kafka-topic",
+                                                                    "habitat"]);
+            let matches = matches.expect("Kafka options should be accepted");
+            let brokers = matches.values_of("EVENT_STREAM_KAFKA_BROKERS")
+                                 .expect("didn't have brokers")
+                                 .collect::<Vec<_>>();
+            assert_eq!(brokers, ["kafka1:9092", "kafka2:9092"]);
+        }
+
+        #[test]
+        fn kafka_brokers_option_conflicts_with_url_option() {
+            let matches = SupRun::clap().get_matches_from_safe(vec!["run",
+                                                                    "--event-stream-application",
+                                                                    "MY_APP",
+                                                                    "--event-stream-environment",
+                                                                    "MY_ENV",
+                                                                    "--event-stream-token",
+                                                                    "MY_TOKEN",
+                                                                    "--event-stream-url",
+                                                                    "127.0.0.1:4222",
+                                                                    "--event-stream-kafka-brokers",
+                                                                    "kafka1:9092",
+                                                                    "--event-stream-kafka-topic",
+                                                                    "habitat"]);
+            assert_eq!(matches.unwrap_err().kind, clap::ErrorKind::ArgumentConflict);
+        }
+
+        #[test]
+        fn kafka_sasl_mechanism_option_requires_credentials() {
+            let args = vec!["run",
+                            "--event-stream-application",
+                            "MY_APP",
+                            "--event-stream-environment",
+                            "MY_ENV",
+                            "--event-stream-kafka-brokers",
+                            "kafka1:9092",
+                            "--event-stream-kafka-topic",
+                            "habitat",
+                            "--event-stream-kafka-sasl-mechanism",
+                            "SCRAM-SHA-512",
+                            "--event-stream-kafka-sasl-username",
+                            "hab"];
+            let matches = SupRun::clap().get_matches_from_safe(args.clone());
+            assert_eq!(matches.unwrap_err().kind,
+                       clap::ErrorKind::MissingRequiredArgument);
+            let args = [&args[..], &["--event-stream-kafka-sasl-password", "secret"]].concat();
+            assert!(SupRun::clap().get_matches_from_safe(args).is_ok());
+        }
+
+        #[test]
+        fn webhook_url_option_requires_app_and_env_and_can_be_repeated() {
+            let matches = SupRun::clap().get_matches_from_safe(vec!["run",
+                                                                    "--event-stream-webhook-url",
+                                                                    "https://hooks.example.com"]);
+            assert_eq!(matches.unwrap_err().kind,
+                       clap::ErrorKind::MissingRequiredArgument);
+            let matches = SupRun::clap().get_matches_from_safe(vec!["run",
+                                                                    "--event-stream-application",
+                                                                    "MY_APP",
+                                                                    "--event-stream-environment",
+                                                                    "MY_ENV",
+                                                                    "--event-stream-webhook-url",
+                                                                    "https://hooks.example.com/a",
+                                                                    "--event-stream-webhook-url",
+                                                                    "https://hooks.example.com/b"]);
+            let matches = matches.expect("webhook options should be accepted");
+            let urls = matches.values_of("EVENT_STREAM_WEBHOOK_URL")
+                              .expect("didn't have webhook urls")
+                              .collect::<Vec<_>>();
+            assert_eq!(urls,
+                       ["https://hooks.example.com/a", "https://hooks.example.com/b"]);
+        }
+
+        #[test]
+        fn webhook_url_option_conflicts_with_kafka_brokers_option() {
+            let matches = SupRun::clap().get_matches_from_safe(vec!["run",
+                                                                    "--event-stream-application",
+                                                                    "MY_APP",
+                                                                    "--event-stream-environment",
+                                                                    "MY_ENV",
+                                                                    "--event-stream-kafka-brokers",
+                                                                    "kafka1:9092",
+                                                                    "--event-stream-kafka-topic",
+                                                                    "habitat",
+                                                                    "--event-stream-webhook-url",
+                                                                    "https://hooks.example.com"]);
+            assert_eq!(matches.unwrap_err().kind, clap::ErrorKind::ArgumentConflict);
+        }
+
+        #[test]
+        fn webhook_events_option_only_takes_lifecycle_and_health_events() {
+            let args = vec!["run",
+                            "--event-stream-application",
+                            "MY_APP",
+                            "--event-stream-environment",
+                            "MY_ENV",
+                            "--event-stream-webhook-url",
+                            "https://hooks.example.com",
+                            "--event-stream-webhook-events"];
+            let invalid = [&args[..], &["healthcheck,disk_space"]].concat();
+            let matches = SupRun::clap().get_matches_from_safe(invalid);
+            assert_eq!(matches.unwrap_err().kind, clap::ErrorKind::InvalidValue);
+            let valid = [&args[..], &["service_started,healthcheck"]].concat();
+            assert!(SupRun::clap().get_matches_from_safe(valid).is_ok());
+        }
     }
 }
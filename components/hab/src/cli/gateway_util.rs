@@ -5,10 +5,10 @@ use crate::{config,
             error::Result};
 use futures::stream::StreamExt;
 use habitat_common as common;
-use habitat_common::{types::ListenCtlAddr,
-                     ui::{UIWriter,
-                          UI}};
-use habitat_sup_client::{SrvClient,
+use habitat_common::ui::{UIWriter,
+                         UI};
+use habitat_sup_client::{CtlGateway,
+                         SrvClient,
                          SrvClientError};
 use habitat_sup_protocol as sup_proto;
 use habitat_sup_protocol::codec::SrvMessage;
@@ -25,7 +25,7 @@ use termcolor::{self,
 ///
 /// Unfortunately not all control gateway-interacting functions use
 /// this logic yet.
-pub async fn send(remote_sup_addr: &ListenCtlAddr,
+pub async fn send(remote_sup_addr: &CtlGateway,
                   msg: impl Into<SrvMessage> + fmt::Debug)
                   -> Result<()> {
     let cfg = config::load()?;
@@ -1,4 +1,5 @@
 mod bldr;
+pub mod cache;
 mod cli;
 mod config;
 mod file;
@@ -17,6 +18,8 @@ pub mod util;
 
 use self::{bldr::{Bldr,
                   ConfigOptBldr},
+           cache::{Cache,
+                   ConfigOptCache},
            cli::{Cli,
                  ConfigOptCli},
            config::{ConfigOptServiceConfig,
@@ -72,6 +75,8 @@ pub enum Hab {
     #[structopt(no_version)]
     Bldr(Bldr),
     #[structopt(no_version)]
+    Cache(Cache),
+    #[structopt(no_version)]
     Cli(Cli),
     #[structopt(no_version)]
     Config(ServiceConfig),
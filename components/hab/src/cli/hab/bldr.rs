@@ -59,6 +59,34 @@ pub enum Channel {
         #[structopt(flatten)]
         auth_token:     AuthToken,
     },
+    /// Compares the latest packages of two channels
+    Diff {
+        #[structopt(flatten)]
+        bldr_url:     BldrUrl,
+        /// The origin for the channels. Default is from 'HAB_ORIGIN' or cli.toml
+        #[structopt(name = "ORIGIN",
+                    short = "o",
+                    long = "origin",
+                    validator = valid_origin)]
+        origin:       Option<String>,
+        /// The channel to compare from, such as the one packages would be promoted to
+        #[structopt(name = "FROM_CHANNEL")]
+        from_channel: String,
+        /// The channel to compare to; its packages are listed as added or changed
+        #[structopt(name = "TO_CHANNEL")]
+        to_channel:   String,
+        /// A package target (ex: x86_64-windows) (default: system appropriate target)
+        #[structopt(name = "PKG_TARGET",
+                    short = "t",
+                    long = "target",
+                    env = PACKAGE_TARGET_ENVVAR)]
+        pkg_target:   Option<PackageTarget>,
+        /// Output will be rendered in json
+        #[structopt(name = "TO_JSON", short = "j", long = "json")]
+        to_json:      bool,
+        #[structopt(flatten)]
+        auth_token:   AuthToken,
+    },
     /// Destroys a channel
     Destroy {
         #[structopt(flatten)]
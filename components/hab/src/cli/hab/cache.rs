@@ -0,0 +1,35 @@
+use super::util::{CacheKeyPath,
+                  ConfigOptCacheKeyPath};
+use configopt::ConfigOpt;
+use structopt::StructOpt;
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "cache", no_version)]
+/// Commands relating to Habitat's local caches
+pub enum Cache {
+    Artifacts(CacheArtifacts),
+}
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to the cache of downloaded Habitat Artifacts
+pub enum CacheArtifacts {
+    /// Lists the cached Habitat Artifacts, least recently used first
+    List,
+    /// Removes the least recently used Habitat Artifacts until the cache is no larger than its
+    /// maximum size
+    Prune {
+        /// The size in megabytes to prune the cache to, instead of the value of
+        /// HAB_ARTIFACT_CACHE_MAX_SIZE_MB (default: 10240)
+        #[structopt(name = "MAX_SIZE_MB", long = "max-size-mb")]
+        max_size_mb: Option<u64>,
+    },
+    /// Verifies every cached Habitat Artifact with its origin key
+    Verify {
+        /// Output a summary of the verified artifacts in json
+        #[structopt(name = "TO_JSON", short = "j", long = "json")]
+        to_json:        bool,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
+}
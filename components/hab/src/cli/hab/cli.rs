@@ -1,6 +1,8 @@
 use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath};
+use crate::cli::file_exists;
 use configopt::ConfigOpt;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 arg_enum! {
@@ -31,4 +33,24 @@ pub enum Cli {
                     case_insensitive = true)]
         shell: Shell,
     },
+    #[structopt(no_version)]
+    Secret(CtlSecret),
+}
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to the Supervisor control gateway secret used by the CLI
+pub enum CtlSecret {
+    /// Stores a control gateway secret in the OS keyring, falling back to the CLI config file on
+    /// systems without a keyring
+    Set {
+        /// Read the control gateway secret from this file rather than from stdin, so that it is
+        /// never given on the command line where other users could see it
+        #[structopt(name = "SECRET_FILE", long = "secret-file", validator = file_exists)]
+        secret_file: Option<PathBuf>,
+    },
+    /// Displays the control gateway secret used by the CLI and where it was found
+    Show,
+    /// Removes the stored control gateway secret from the OS keyring and the CLI config file
+    Clear,
 }
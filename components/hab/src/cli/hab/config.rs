@@ -40,6 +40,13 @@ pub struct ServiceConfigApply {
     /// Name of a user key to use for encryption
     #[structopt(short = "u", long = "user")]
     user:           Option<String>,
+    /// Sign the request with the latest revision of this signing key (ex: acme)
+    #[structopt(long = "sign-with")]
+    sign_with:      Option<String>,
+    /// Report whether each member of the group would apply the configuration live or restart,
+    /// without applying it
+    #[structopt(long = "dry-run")]
+    dry_run:        bool,
     #[structopt(flatten)]
     remote_sup:     RemoteSup,
     #[structopt(flatten)]
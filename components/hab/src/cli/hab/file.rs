@@ -26,6 +26,9 @@ pub enum File {
         /// Name of the user key
         #[structopt(name = "USER", short = "u", long = "user")]
         user:           Option<String>,
+        /// Sign the request with the latest revision of this signing key (ex: acme)
+        #[structopt(long = "sign-with")]
+        sign_with:      Option<String>,
         #[structopt(flatten)]
         remote_sup:     RemoteSup,
         #[structopt(flatten)]
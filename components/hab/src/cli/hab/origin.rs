@@ -6,9 +6,13 @@ use super::util::{AuthToken,
                   ConfigOptBldrOrigin,
                   ConfigOptBldrUrl,
                   ConfigOptCacheKeyPath};
-use crate::cli::valid_origin;
+use crate::cli::{dir_exists,
+                 file_exists,
+                 valid_name_with_rev,
+                 valid_origin};
 use configopt::ConfigOpt;
 use habitat_core::{crypto::keys::PairType,
+                   fs::KeyPermissionPolicy,
                    origin::OriginMemberRole};
 use std::path::PathBuf;
 use structopt::{clap::ArgGroup,
@@ -160,16 +164,24 @@ pub enum Invitations {
 #[structopt(no_version)]
 /// Commands relating to Habitat origin key maintenance
 pub enum Key {
+    /// Bundles origin keys for carrying to or from an environment without access to Builder
+    Bundle(KeyBundle),
     /// Download origin key(s)
     Download {
         #[structopt(flatten)]
         cache_key_path:  CacheKeyPath,
-        /// The origin name
-        #[structopt(name = "ORIGIN", validator = valid_origin)]
-        origin:          String,
+        /// The origin name, or a comma-separated list of origin names (ex: core,acme)
+        #[structopt(name = "ORIGIN",
+                    validator = valid_origin,
+                    use_delimiter = true,
+                    required_unless = "FROM_FILE")]
+        origin:          Vec<String>,
         /// The origin key revision
-        #[structopt(name = "REVISION")]
+        #[structopt(name = "REVISION", conflicts_with = "FROM_FILE")]
         revision:        Option<String>,
+        /// Also download the keys of the origins listed in this file, one per line
+        #[structopt(name = "FROM_FILE", long = "from-file", validator = file_exists)]
+        from_file:       Option<PathBuf>,
         #[structopt(flatten)]
         bldr_url:        BldrUrl,
         /// Download origin private key instead of origin public key
@@ -181,6 +193,20 @@ pub enum Key {
         /// Authentication token for Builder (required for downloading origin private keys)
         #[structopt(name = "AUTH_TOKEN", short = "z", long = "auth")]
         auth_token:      Option<String>,
+        /// The maximum number of origins, or revisions of an origin's public key, to download at
+        /// once (default: 8)
+        #[structopt(name = "JOBS", short = "j", long = "jobs")]
+        jobs:            Option<usize>,
+        /// Whether downloaded secret keys can only be read by their owner, or by their group as
+        /// well
+        #[structopt(name = "KEY_PERMISSIONS",
+                    long = "key-permissions",
+                    default_value = "strict",
+                    possible_values = KeyPermissionPolicy::VARIANTS)]
+        key_permissions: String,
+        /// The group to give downloaded keys to, such as one shared by CI runners
+        #[structopt(name = "KEY_GROUP", long = "key-group")]
+        key_group:       Option<String>,
     },
     /// Outputs the latest origin key contents to stdout
     Export {
@@ -197,9 +223,12 @@ pub enum Key {
     Generate {
         /// The origin name
         #[structopt(name = "ORIGIN", validator = valid_origin)]
-        origin:         Option<String>,
+        origin:          Option<String>,
+        /// Encrypt the secret key with the passphrase on the first line of this file
+        #[structopt(name = "PASSPHRASE_FILE", long = "passphrase-file", validator = file_exists)]
+        passphrase_file: Option<PathBuf>,
         #[structopt(flatten)]
-        cache_key_path: CacheKeyPath,
+        cache_key_path:  CacheKeyPath,
     },
     /// Reads a stdin stream containing a public or private origin key contents and writes the key
     /// to disk
@@ -207,6 +236,15 @@ pub enum Key {
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
+    /// Lists the origin keys in the key cache, or with --remote, the public origin keys on
+    /// Builder
+    List(KeyList),
+    /// Revokes an origin key revision in its origin's revocation list, which is signed with
+    /// another of the origin's keys
+    Revoke(KeyRevoke),
+    /// Generates a new origin key revision, uploads it to Builder and re-signs artifacts signed
+    /// with the previous revision, or shows how to
+    Rotate(KeyRotate),
     /// Upload origin keys to Builder
     Upload {
         #[structopt(flatten)]
@@ -229,6 +267,106 @@ pub enum Key {
     },
 }
 
+/// Revokes an origin key revision in its origin's revocation list, which is signed with another of
+/// the origin's keys
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "revoke", no_version, rename_all = "screamingsnake")]
+pub struct KeyRevoke {
+    /// The origin key revision to revoke (ex: core-20160810182414)
+    #[structopt(validator = valid_name_with_rev)]
+    pub revision:       String,
+    /// Why the revision is revoked, which is kept in the revocation list
+    #[structopt(long = "reason", default_value = "no reason given")]
+    pub reason:         String,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
+/// Generates a new origin key revision, uploads it to Builder and re-signs artifacts signed
+/// with the previous revision, or shows how to
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "rotate", no_version, rename_all = "screamingsnake")]
+pub struct KeyRotate {
+    /// The origin name
+    #[structopt(validator = valid_origin)]
+    pub origin:          String,
+    /// Upload the new origin private key in addition to the public key
+    #[structopt(short = "s", long = "secret")]
+    pub with_secret:     bool,
+    /// Encrypt the new secret key with the passphrase on the first line of this file
+    #[structopt(long = "passphrase-file", validator = file_exists)]
+    pub passphrase_file: Option<PathBuf>,
+    /// Re-sign the Habitat Artifacts in this directory which were signed with the previous key
+    /// revision, using the new one (ex: /hab/cache/artifacts)
+    #[structopt(long = "re-sign", validator = dir_exists)]
+    pub re_sign:         Option<PathBuf>,
+    #[structopt(flatten)]
+    pub bldr_url:        BldrUrl,
+    #[structopt(flatten)]
+    pub auth_token:      AuthToken,
+    #[structopt(flatten)]
+    pub cache_key_path:  CacheKeyPath,
+}
+
+/// Writes the keys of origins in the key cache to a tarball signed with the latest private key of
+/// the first of them
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version, rename_all = "screamingsnake")]
+pub struct KeyBundleExport {
+    /// The origins whose keys are bundled (ex: core acme)
+    #[structopt(required = true, validator = valid_origin)]
+    pub origins:        Vec<String>,
+    /// Bundle the origin private keys in addition to the public keys
+    #[structopt(short = "s", long = "secret")]
+    pub with_secret:    bool,
+    /// The file to write the bundle to (ex: bundle.tar)
+    #[structopt(short = "o", long = "output")]
+    pub output:         PathBuf,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
+/// Adds the keys in a bundle to the key cache, leaving any which conflict with keys already in it
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version, rename_all = "screamingsnake")]
+pub struct KeyBundleImport {
+    /// The bundle written by `hab origin key bundle export`
+    #[structopt(validator = file_exists)]
+    pub bundle:         PathBuf,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "bundle", no_version)]
+/// Bundles origin keys for carrying to or from an environment without access to Builder
+pub enum KeyBundle {
+    /// Writes the keys of origins in the key cache to a tarball signed with the latest private key
+    /// of the first of them
+    Export(KeyBundleExport),
+    /// Adds the keys in a bundle to the key cache, leaving any which conflict with keys already in
+    /// it
+    Import(KeyBundleImport),
+}
+
+/// Lists the origin keys in the key cache, or with --remote, the public origin keys on Builder
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "list", no_version, rename_all = "screamingsnake")]
+pub struct KeyList {
+    /// Only list the keys of this origin
+    #[structopt(validator = valid_origin)]
+    pub origin:         Option<String>,
+    /// List the public keys of ORIGIN on Builder instead of the keys in the key cache
+    #[structopt(short = "r", long = "remote", requires = "ORIGIN")]
+    pub remote:         bool,
+    #[structopt(flatten)]
+    pub bldr_url:       BldrUrl,
+    #[structopt(flatten)]
+    pub auth_token:     AuthToken,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
 #[derive(ConfigOpt, StructOpt, Debug)]
 #[structopt(group = ArgGroup::with_name("upload").required(true), no_version)]
 pub struct UploadGroup {
@@ -7,19 +7,24 @@ use super::util::{AuthToken,
                   ConfigOptExternalCommandArgs,
                   ConfigOptExternalCommandArgsWithHelpAndVersion,
                   ConfigOptFullyQualifiedPkgIdent,
+                  ConfigOptFullyQualifiedPkgIdentOrFile,
                   ConfigOptPkgIdent,
                   ExternalCommandArgs,
                   ExternalCommandArgsWithHelpAndVersion,
                   FullyQualifiedPkgIdent,
+                  FullyQualifiedPkgIdentOrFile,
                   PkgIdent};
 use crate::cli::{dir_exists,
                  file_exists,
+                 file_exists_or_ident,
+                 file_or_dir_exists,
                  valid_ident_or_toml_file,
                  valid_origin};
 use configopt::ConfigOpt;
 use habitat_common::{cli::{BINLINK_DIR_ENVVAR,
                            DEFAULT_BINLINK_DIR,
                            PACKAGE_TARGET_ENVVAR},
+                     command::package::license::LicensePolicyMode,
                      FeatureFlag,
                      FEATURE_FLAGS};
 use habitat_core::{env::Config,
@@ -53,6 +58,27 @@ pub struct List {
 #[allow(clippy::large_enum_variant)]
 /// Commands relating to Habitat packages
 pub enum Pkg {
+    /// Audits installed packages, checking their files against their FILES manifests and for
+    /// changed ownership or permissions
+    Audit {
+        /// A package identifier (ex: core/redis, core/busybox-static/1.42.2). Every installed
+        /// package is audited when it is not given
+        #[structopt(name = "PKG_IDENT")]
+        pkg_ident:  Option<PackageIdent>,
+        /// Output a summary of the audited packages in json
+        #[structopt(name = "TO_JSON", short = "j", long = "json")]
+        to_json:    bool,
+        /// Remove the binlinks to binaries of packages which have drifted
+        #[structopt(name = "QUARANTINE", long = "quarantine")]
+        quarantine: bool,
+        /// The binlink directory to remove binlinks from with --quarantine
+        #[structopt(name = "DEST_DIR",
+                    short = "d",
+                    long = "dest",
+                    env = BINLINK_DIR_ENVVAR,
+                    default_value = DEFAULT_BINLINK_DIR)]
+        dest_dir:   PathBuf,
+    },
     /// Displays the binds for a service
     Binds {
         #[structopt(flatten)]
@@ -161,17 +187,21 @@ pub enum Pkg {
     /// Demote a package from a specified channel
     Demote {
         #[structopt(flatten)]
-        bldr_url:   BldrUrl,
+        bldr_url:    BldrUrl,
         #[structopt(flatten)]
-        pkg_ident:  FullyQualifiedPkgIdent,
+        pkg_ident:   FullyQualifiedPkgIdentOrFile,
         /// Demote from the specified release channel
         #[structopt(name = "CHANNEL")]
-        channel:    String,
+        channel:     String,
         /// A package target (ex: x86_64-windows) (default: system appropriate target)
         #[structopt(name = "PKG_TARGET", env = PACKAGE_TARGET_ENVVAR)]
-        pkg_target: Option<PackageTarget>,
+        pkg_target:  Option<PackageTarget>,
         #[structopt(flatten)]
-        auth_token: AuthToken,
+        auth_token:  AuthToken,
+        /// The maximum number of packages to demote at once when given a file of package
+        /// identifiers (default: 8)
+        #[structopt(name = "CONCURRENCY", long = "concurrency")]
+        concurrency: Option<usize>,
     },
     /// Returns the Habitat Artifact dependencies. By default it will return the direct
     /// dependencies of the package
@@ -209,10 +239,14 @@ pub enum Pkg {
         /// One or more Habitat package identifiers (ex: acme/redis)
         #[structopt(name = "PKG_IDENT")]
         pkg_ident:           Vec<PackageIdent>,
-        /// Target architecture to fetch. E.g. x86_64-linux. Overridden if architecture is
-        /// specified in toml file
-        #[structopt(name = "PKG_TARGET", short = "t", long = "target")]
-        pkg_target:          Option<PackageTarget>,
+        /// Target architecture to fetch. E.g. x86_64-linux. May be given more than once to fetch
+        /// several targets, each into its own directory. Overridden if architecture is specified
+        /// in toml file
+        #[structopt(name = "PKG_TARGET",
+                    short = "t",
+                    long = "target",
+                    number_of_values = 1)]
+        pkg_target:          Vec<PackageTarget>,
         /// Verify package integrity after download (Warning: this can be slow)
         #[structopt(name = "VERIFY", long = "verify")]
         verify:              bool,
@@ -241,15 +275,31 @@ pub enum Pkg {
         #[structopt(name = "SOURCE", validator = file_exists)]
         source: PathBuf,
     },
-    /// Returns the Habitat Artifact information
+    /// Returns the information of a Habitat Artifact, an installed package, or a package in
+    /// Builder
     Info {
         /// Output will be rendered in json. (Includes extended metadata)
         #[structopt(name = "TO_JSON", short = "j", long = "json")]
-        to_json: bool,
+        to_json:    bool,
         /// A path to a Habitat Artifact (ex:
-        /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
-        #[structopt(name = "SOURCE", validator = file_exists)]
-        source:  PathBuf,
+        /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart) or a package identifier (ex:
+        /// core/redis)
+        #[structopt(name = "SOURCE", validator = file_exists_or_ident)]
+        source:     String,
+        #[structopt(flatten)]
+        bldr_url:   BldrUrl,
+        /// Look the package up in the specified release channel when it is not installed
+        #[structopt(name = "CHANNEL",
+                    short = "c",
+                    long = "channel",
+                    default_value = "stable",
+                    env = ChannelIdent::ENVVAR)]
+        channel:    String,
+        #[structopt(flatten)]
+        auth_token: AuthToken,
+        /// A package target (ex: x86_64-windows) (default: system appropriate target)
+        #[structopt(name = "PKG_TARGET", env = PACKAGE_TARGET_ENVVAR)]
+        pkg_target: Option<PackageTarget>,
     },
     Install(PkgInstall),
     /// List all versions of installed packages
@@ -262,17 +312,21 @@ pub enum Pkg {
     /// Promote a package to a specified channel
     Promote {
         #[structopt(flatten)]
-        bldr_url:   BldrUrl,
+        bldr_url:    BldrUrl,
         #[structopt(flatten)]
-        pkg_ident:  FullyQualifiedPkgIdent,
+        pkg_ident:   FullyQualifiedPkgIdentOrFile,
         /// Promote to the specified release channel
         #[structopt(name = "CHANNEL")]
-        channel:    String,
+        channel:     String,
         /// A package target (ex: x86_64-windows) (default: system appropriate target)
         #[structopt(name = "PKG_TARGET", env = PACKAGE_TARGET_ENVVAR)]
-        pkg_target: Option<PackageTarget>,
+        pkg_target:  Option<PackageTarget>,
         #[structopt(flatten)]
-        auth_token: AuthToken,
+        auth_token:  AuthToken,
+        /// The maximum number of packages to promote at once when given a file of package
+        /// identifiers (default: 8)
+        #[structopt(name = "CONCURRENCY", long = "concurrency")]
+        concurrency: Option<usize>,
     },
     /// Search installed Habitat packages for a given file
     Provides {
@@ -299,6 +353,10 @@ pub enum Pkg {
         #[structopt(name = "LIMIT", short = "l", long = "limit", default_value = "50")]
         limit:       usize,
     },
+    /// Re-signs the Habitat Artifacts in a directory which were signed with one origin key
+    /// revision using another, leaving their contents untouched
+    ReSign(PkgReSign),
+    Shell(PkgShell),
     /// Signs an archive with an origin key, generating a Habitat Artifact
     Sign {
         /// Origin key used to create signature
@@ -314,6 +372,9 @@ pub enum Pkg {
         /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
         #[structopt(name = "DEST")]
         dest:           PathBuf,
+        /// Add a signature to SOURCE, a HART-2 Habitat Artifact, keeping its existing signatures
+        #[structopt(name = "APPEND", long = "append")]
+        append:         bool,
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
@@ -338,6 +399,9 @@ pub enum Pkg {
         #[structopt(name = "IGNORE_UNINSTALL_HOOK", long = "ignore-uninstall-hook")]
         ignore_uninstall_hook: bool,
     },
+    /// Rewrites HART-1 Habitat Artifacts in the HART-2 format, which describes the package in the
+    /// artifact's header
+    UpgradeHart(PkgUpgradeHart),
     /// Uploads a local Habitat Artifact to Builder
     Upload {
         #[structopt(flatten)]
@@ -362,12 +426,27 @@ pub enum Pkg {
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
-    /// Verifies a Habitat Artifact with an origin key
+    /// Verifies a Habitat Artifact, or every Habitat Artifact in a directory, with an origin key
     Verify {
         /// A path to a Habitat Artifact (ex:
-        /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
-        #[structopt(name = "SOURCE", validator = file_exists)]
+        /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart) or a directory of them (ex:
+        /// /hab/cache/artifacts)
+        #[structopt(name = "SOURCE", validator = file_or_dir_exists)]
         source:         PathBuf,
+        /// Output a summary of the verified artifacts in json
+        #[structopt(name = "TO_JSON", short = "j", long = "json")]
+        to_json:        bool,
+        /// Require every signature of an artifact to be valid, rather than at least one, and one
+        /// of them to be by a key of this origin. Repeat for each origin whose signature is
+        /// required, as signatures can be removed from an artifact without invalidating the others
+        #[structopt(name = "REQUIRE_SIGNER",
+                    long = "require-signer",
+                    number_of_values = 1,
+                    validator = valid_origin)]
+        require_signer: Vec<String>,
+        /// A key trust policy to check signers against, in place of the one in the key cache
+        #[structopt(name = "POLICY", long = "policy", validator = file_exists)]
+        policy:         Option<PathBuf>,
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
@@ -386,6 +465,56 @@ pub struct PkgExec {
     pub args:      ExternalCommandArgsWithHelpAndVersion,
 }
 
+/// Opens a shell with the runtime environment of an installed package applied, without a Studio
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "shell", no_version, rename_all = "screamingsnake")]
+pub struct PkgShell {
+    #[structopt(flatten)]
+    pub pkg_ident: PkgIdent,
+    /// The shell to open [default: $SHELL, or /bin/sh; powershell.exe on Windows]
+    #[structopt(long = "shell")]
+    pub shell:     Option<PathBuf>,
+    /// Also apply the runtime environments of the package's transitive dependencies, for the
+    /// variables the package doesn't set itself
+    #[structopt(long = "tdeps")]
+    pub tdeps:     bool,
+}
+
+/// Re-signs the Habitat Artifacts in a directory which were signed with one origin key revision
+/// using another, leaving their contents untouched
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "re-sign", no_version, rename_all = "screamingsnake")]
+pub struct PkgReSign {
+    /// The origin of the keys
+    #[structopt(long = "origin", validator = valid_origin)]
+    pub origin:         String,
+    /// The revision of the key the artifacts are currently signed with (ex: 20160101000000)
+    #[structopt(long = "from-rev")]
+    pub from_rev:       String,
+    /// The revision of the key to sign the artifacts with
+    #[structopt(long = "to-rev")]
+    pub to_rev:         String,
+    /// A directory of Habitat Artifacts to re-sign (ex: /hab/cache/artifacts)
+    #[structopt(validator = dir_exists)]
+    pub dir:            PathBuf,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
+/// Rewrites HART-1 Habitat Artifacts in the HART-2 format, which describes the package in the
+/// artifact's header
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "upgrade-hart", no_version, rename_all = "screamingsnake")]
+pub struct PkgUpgradeHart {
+    /// One or more Habitat Artifacts to upgrade in place, each signed with an origin key whose
+    /// secret key is in the key cache (ex:
+    /// /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
+    #[structopt(required = true, validator = file_exists)]
+    pub hart_file:      Vec<PathBuf>,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
 /// Installs a Habitat package from Builder or locally from a Habitat Artifact
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version, rename_all = "screamingsnake")]
@@ -398,8 +527,8 @@ pub struct PkgInstall {
                 default_value = "stable",
                 env = ChannelIdent::ENVVAR)]
     channel:               String,
-    /// One or more Habitat package identifiers (ex: acme/redis) and/or filepaths to a Habitat
-    /// Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
+    /// One or more Habitat package identifiers (ex: acme/redis) and/or filepaths or HTTP(S) URLs
+    /// of a Habitat Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
     #[structopt(required = true)]
     pkg_ident_or_artifact: Vec<String>,
     /// Binlink all binaries from installed package(s) into BINLINK_DIR
@@ -418,6 +547,18 @@ pub struct PkgInstall {
     /// Do not run any install hooks
     #[structopt(long = "ignore-install-hook")]
     ignore_install_hook:   bool,
+    /// Do not install a package if it or any of its dependencies is released under this license
+    /// (ex: AGPL-3.0)
+    #[structopt(long = "deny-license",
+                number_of_values = 1,
+                use_delimiter = true,
+                env = "HAB_DENY_LICENSE")]
+    deny_license:          Vec<String>,
+    /// Whether a package released under a denied license fails the install or only warns
+    #[structopt(long = "license-policy",
+                default_value = "enforce",
+                possible_values = LicensePolicyMode::VARIANTS)]
+    license_policy:        String,
     /// Install packages in offline mode
     #[structopt(long = "offline",
                 hidden = !FEATURE_FLAGS.contains(FeatureFlag::OFFLINE_INSTALL))]
@@ -429,6 +570,54 @@ pub struct PkgInstall {
     ignore_local:          bool,
 }
 
+/// Generates Kubernetes manifests which run a package under Habitat Supervisors
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "k8s",
+            aliases = &["kubernetes"],
+            group = ArgGroup::with_name("source").required(true),
+            no_version,
+            rename_all = "screamingsnake")]
+pub struct PkgExportK8s {
+    /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
+    #[structopt(name = "PKG_IDENT", group = "source")]
+    pub pkg_ident:      Option<PackageIdent>,
+    /// The spec file of a service loaded by a Supervisor, to take the package identifier,
+    /// service group, channel, topology and binds from (ex: /hab/sup/default/specs/redis.spec)
+    #[structopt(long = "spec", group = "source", validator = file_exists)]
+    pub spec:           Option<PathBuf>,
+    /// The service group with shared config and topology (default: default)
+    #[structopt(long = "group")]
+    pub group:          Option<String>,
+    /// Receive updates from the specified release channel (default: stable)
+    #[structopt(long = "channel")]
+    pub channel:        Option<String>,
+    /// Service topology; the leader topology runs as a StatefulSet (default: standalone)
+    #[structopt(long = "topology",
+                short = "t",
+                possible_values = &["standalone", "leader"])]
+    pub topology:       Option<String>,
+    /// One or more service groups to bind to a configuration
+    #[structopt(long = "bind")]
+    pub bind:           Vec<String>,
+    /// The container image made by `hab pkg export container` to run (default: ORIGIN/NAME)
+    #[structopt(long = "image")]
+    pub image:          Option<String>,
+    /// The number of Supervisors to run
+    #[structopt(long = "count", default_value = "1")]
+    pub count:          u32,
+    /// A ring whose latest key the Supervisors encrypt their gossip with
+    #[structopt(long = "ring")]
+    pub ring:           Option<String>,
+    /// Emit a resource for the Habitat operator instead of the workload itself
+    #[structopt(long = "operator")]
+    pub operator:       bool,
+    /// The file to write the manifests to, rather than standard output
+    #[structopt(short = "o", long = "output")]
+    pub output:         Option<PathBuf>,
+    #[structopt(flatten)]
+    pub cache_key_path: CacheKeyPath,
+}
+
 /// Exports the package to the specified format
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(name = "export", aliases = &["e", "ex", "exp", "expo", "expor"], no_version)]
@@ -442,6 +631,8 @@ pub enum ExportCommand {
     #[cfg(any(target_os = "linux", target_os = "windows"))]
     #[structopt(settings = &[AppSettings::Hidden])]
     Docker(ExternalCommandArgs),
+    /// Kubernetes exporter
+    K8s(PkgExportK8s),
     /// Mesos exporter
     #[cfg(target_os = "linux")]
     Mesos(ExternalCommandArgs),
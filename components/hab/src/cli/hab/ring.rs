@@ -1,15 +1,59 @@
-use super::util::{CacheKeyPath,
-                  ConfigOptCacheKeyPath};
+use super::{svc::{ConfigOptUpdate,
+                  Update},
+            util::{CacheKeyPath,
+                   ConfigOptCacheKeyPath,
+                   ConfigOptCtlTlsOptions,
+                   ConfigOptRemoteSup,
+                   CtlTlsOptions,
+                   RemoteSup}};
+use crate::cli::{file_exists,
+                 valid_key_revision};
 use configopt::ConfigOpt;
+use habitat_common::types::ListenCtlAddr;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to Habitat rings
 pub enum Ring {
+    /// Applies a signed command on every Supervisor in the ring, by gossiping it from one of them
+    Exec(RingExec),
     Key(Key),
 }
 
+/// Applies a signed command on every Supervisor in the ring, by gossiping it from one of them
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "exec", no_version)]
+pub enum RingExec {
+    /// Updates how every Supervisor in the ring manages a service, as `hab svc update` does for
+    /// one. The update must be signed with --sign-with, by a key every Supervisor trusts with
+    /// --trusted-signer
+    Update(RingExecUpdate),
+    /// Shows which Supervisors have acknowledged a ring command, as heard by the Supervisor it was
+    /// issued from
+    Status {
+        /// The ID of the ring command
+        #[structopt(name = "ID")]
+        id:         String,
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
+}
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version, rename_all = "screamingsnake")]
+pub struct RingExecUpdate {
+    #[structopt(flatten)]
+    pub update: Update,
+    /// How many seconds Supervisors may still apply the update for after it is issued
+    #[structopt(long = "ttl", default_value = "600")]
+    pub ttl:    u32,
+    /// How many seconds to wait for every alive Supervisor to acknowledge the update
+    #[structopt(long = "wait", default_value = "30")]
+    pub wait:   u64,
+}
+
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to Habitat ring keys
@@ -26,12 +70,55 @@ pub enum Key {
     Generate {
         /// Ring key name
         #[structopt(name = "RING")]
-        ring:           String,
+        ring:            String,
+        /// Write the ring key to a redirected or piped standard output instead of the key cache
+        #[structopt(name = "STDOUT", long = "stdout")]
+        stdout:          bool,
+        /// Derive the ring key from the passphrase on the first line of this file, so that the
+        /// same key is generated wherever the passphrase is given
+        #[structopt(name = "FROM_PASSPHRASE",
+                    long = "from-passphrase",
+                    validator = file_exists,
+                    requires = "REVISION")]
+        from_passphrase: Option<PathBuf>,
+        /// The revision of the ring key to derive from the passphrase (ex: 20200601000000). Give
+        /// the same one wherever the key is derived, and a newer one to rotate the ring to a new
+        /// derived key
+        #[structopt(name = "REVISION",
+                    long = "revision",
+                    validator = valid_key_revision,
+                    requires = "FROM_PASSPHRASE")]
+        revision:        Option<String>,
+        /// Encrypt the secret key with the passphrase on the first line of this file
+        #[structopt(name = "PASSPHRASE_FILE", long = "passphrase-file", validator = file_exists)]
+        passphrase_file: Option<PathBuf>,
         #[structopt(flatten)]
-        cache_key_path: CacheKeyPath,
+        cache_key_path:  CacheKeyPath,
     },
     /// Reads a stdin stream containing ring key contents and writes the key to disk
     Import {
+        /// Read the ring key from standard input even if it is a terminal
+        #[structopt(name = "FROM_STDIN", long = "from-stdin")]
+        from_stdin:     bool,
+        #[structopt(flatten)]
+        cache_key_path: CacheKeyPath,
+    },
+    /// Rotates the ring key of running Supervisors to a new revision without restarting them
+    Rotate {
+        /// Ring key name
+        #[structopt(name = "RING")]
+        ring:           String,
+        /// Address to the Control Gateway of a Supervisor in the ring. Give it once for every
+        /// Supervisor in the ring
+        #[structopt(name = "REMOTE_SUP",
+                    long = "remote-sup",
+                    short = "r",
+                    number_of_values = 1,
+                    default_value = ListenCtlAddr::default_as_str(),
+                    parse(try_from_str = ListenCtlAddr::resolve_listen_ctl_addr))]
+        remote_sup:     Vec<ListenCtlAddr>,
+        #[structopt(flatten)]
+        ctl_tls:        CtlTlsOptions,
         #[structopt(flatten)]
         cache_key_path: CacheKeyPath,
     },
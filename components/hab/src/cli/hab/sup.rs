@@ -4,26 +4,40 @@ use super::{svc::{ConfigOptSharedLoad,
             util::{self,
                    CacheKeyPath,
                    ConfigOptCacheKeyPath,
+                   ConfigOptCtlTlsOptions,
+                   ConfigOptHttpGatewayOptions,
                    ConfigOptRemoteSup,
+                   CtlTlsOptions,
                    DurationProxy,
+                   HttpGatewayOptions,
                    RemoteSup}};
-use crate::VERSION;
+use crate::{command::service::status::StatusFormat,
+            VERSION};
 use configopt::{self,
                 configopt_fields,
                 ConfigOpt};
 use habitat_common::{cli::{RING_ENVVAR,
                            RING_KEY_ENVVAR},
                      command::package::install::InstallSource,
-                     types::{EventStreamConnectMethod,
+                     output::LogSink,
+                     types::{AcmeChallenge,
+                             EventStreamBufferPolicy,
+                             EventStreamConnectMethod,
                              EventStreamMetaPair,
                              EventStreamServerCertificate,
                              EventStreamToken,
+                             EventStreamWebhookEvent,
                              GossipListenAddr,
                              HttpListenAddr,
-                             ListenCtlAddr},
+                             KafkaSaslMechanism,
+                             ListenCtlAddr,
+                             MemberIdSource,
+                             RumorCap,
+                             SupervisorProfile},
                      FeatureFlag,
                      FEATURE_FLAGS};
 use habitat_core::{env::Config,
+                   os::process::ShutdownTimeout,
                    package::PackageIdent,
                    util as core_util};
 use rants::{error::Error as RantsError,
@@ -53,6 +67,39 @@ pub enum HabSup {
         #[structopt(flatten)]
         remote_sup: RemoteSup,
     },
+    /// Drain a Supervisor for maintenance: mark it as draining in the gossip ring, stop accepting
+    /// service loads and stop its services. Returns once every service has stopped; restart the
+    /// Supervisor to bring it back into service
+    #[structopt(no_version)]
+    Drain {
+        /// The delay after sending the shutdown signal to wait before killing each service
+        /// process, in seconds or with units (ex: 90s, 2m)
+        ///
+        /// The default value is set in each package's plan file.
+        #[structopt(name = "TIMEOUT", long = "timeout")]
+        timeout:    Option<ShutdownTimeout>,
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
+    /// Forget the departed members of the gossip ring, and their services, without waiting for
+    /// the Supervisor's departed member retention to pass
+    #[structopt(no_version)]
+    ForgetDeparted {
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
+    /// Query the members of the gossip ring known to a Supervisor
+    #[structopt(no_version, aliases = &["m", "me", "mem", "memb", "membe", "member"])]
+    Members {
+        /// Only show information for the Supervisor with this member-id
+        #[structopt(name = "MEMBER_ID")]
+        member_id:  Option<String>,
+        /// Show recorded health transitions instead of current membership
+        #[structopt(long = "history")]
+        history:    bool,
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
     #[structopt(no_version, aliases = &["sec", "secr"])]
     Secret(Secret),
     /// Query the status of Habitat services
@@ -61,8 +108,34 @@ pub enum HabSup {
         /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
         #[structopt(name = "PKG_IDENT")]
         pkg_ident:  Option<PackageIdent>,
+        /// Also show how each service's process is scheduled
+        #[structopt(name = "DETAILS", long = "details")]
+        details:    bool,
+        /// Show recorded lifecycle transitions instead of current status
+        #[structopt(name = "HISTORY", long = "history", conflicts_with = "DETAILS")]
+        history:    bool,
+        /// How to print the status: `table`, `wide` (a table including the columns of
+        /// --details), or `json` or `yaml` for scripts
+        #[structopt(name = "FORMAT",
+                    long = "format",
+                    default_value = "table",
+                    possible_values = StatusFormat::VARIANTS)]
+        format:     String,
+        /// Keep refreshing the status, highlighting the services whose state changed
+        #[structopt(name = "WATCH",
+                    long = "watch",
+                    conflicts_with = "HISTORY")]
+        watch:      bool,
+        /// How often --watch refreshes the status, in seconds or with units (ex: 5s, 1m)
+        #[structopt(name = "INTERVAL",
+                    long = "interval",
+                    default_value = "2",
+                    requires = "WATCH")]
+        interval:   DurationProxy,
         #[structopt(flatten)]
         remote_sup: RemoteSup,
+        #[structopt(flatten)]
+        http:       HttpGatewayOptions,
     },
     /// Restart a Supervisor without restarting its services
     #[structopt(no_version)]
@@ -70,6 +143,21 @@ pub enum HabSup {
         #[structopt(flatten)]
         remote_sup: RemoteSup,
     },
+    #[structopt(no_version)]
+    Ring(SupRing),
+    /// Report on the health of the gossip ring as seen by a Supervisor, including signs of a
+    /// partial network partition
+    #[structopt(no_version)]
+    RingHealth {
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
+    /// Report on the origin key revocation lists a Supervisor fetches
+    #[structopt(no_version)]
+    KeyRevocations {
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
     #[cfg(not(target_os = "macos"))]
     #[structopt(flatten)]
     Sup(Sup),
@@ -93,9 +181,66 @@ pub enum Sup {
     /// Start an interactive Bourne-like shell
     #[structopt(no_version)]
     Sh,
+    #[structopt(no_version)]
+    Simulate(SupSimulate),
     /// Gracefully terminate the Habitat Supervisor and all of its running services
     #[structopt(no_version, aliases = &["ter"])]
-    Term,
+    Term {
+        /// Address to a remote Supervisor's Control Gateway to terminate, rather than the local
+        /// Supervisor
+        #[structopt(name = "REMOTE_SUP",
+                    long = "remote-sup",
+                    short = "r",
+                    parse(try_from_str = ListenCtlAddr::resolve_listen_ctl_addr))]
+        remote_sup: Option<ListenCtlAddr>,
+        /// Confirm terminating a remote Supervisor; required with --remote-sup
+        #[structopt(name = "YES", long = "yes", requires = "REMOTE_SUP")]
+        yes:        bool,
+        /// Permanently depart the remote Supervisor from the gossip ring before terminating it,
+        /// banning it from rejoining with the same member-id
+        #[structopt(name = "DEPART", long = "depart", requires = "REMOTE_SUP")]
+        depart:     bool,
+        #[structopt(flatten)]
+        ctl_tls:    CtlTlsOptions,
+    },
+}
+
+/// Simulate a gossip network of many Supervisors in one process, to see how membership, elections
+/// and rumors spread under poor network conditions without provisioning machines
+#[derive(ConfigOpt, StructOpt)]
+#[configopt(derive(Serialize))]
+#[structopt(name = "simulate", no_version, rename_all = "screamingsnake")]
+pub struct SupSimulate {
+    /// The number of members to simulate
+    #[structopt(long = "members", default_value = "50")]
+    pub members:       usize,
+    /// The percentage of messages which never arrive (ex: 5, 0.5)
+    #[structopt(long = "loss", default_value = "0", parse(try_from_str = parse_percent))]
+    pub loss:          f64,
+    /// How long each message takes to arrive, in milliseconds
+    #[structopt(long = "latency", default_value = "0")]
+    pub latency:       u64,
+    /// Once membership has converged, split the members into this many partitions which can't
+    /// hear each other, then heal them
+    #[structopt(long = "partitions")]
+    pub partitions:    Option<usize>,
+    /// How long to keep the members partitioned, in seconds or with units (ex: 30s, 2m)
+    #[structopt(long = "partition-for", default_value = "30", requires = "PARTITIONS")]
+    pub partition_for: DurationProxy,
+    /// How long to wait for each stage of the simulation before giving up on it, in seconds or
+    /// with units (ex: 90s, 5m)
+    #[structopt(long = "timeout", default_value = "300")]
+    pub timeout:       DurationProxy,
+    /// The first port the members listen on. Each member takes two ports, counting up from it
+    #[structopt(long = "port", default_value = "40000")]
+    pub port:          u16,
+}
+
+fn parse_percent(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err(format!("'{}' is not a percentage from 0 to 100", s)),
+    }
 }
 
 // TODO (DM): This is unnecessarily difficult due to this issue in serde
@@ -118,6 +263,9 @@ impl From<EventStreamAddress> for NatsAddress {
     fn from(address: EventStreamAddress) -> Self { address.0 }
 }
 
+/// The production directory of Let's Encrypt.
+const LETS_ENCRYPT_DIRECTORY: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
 fn parse_peer(s: &str) -> io::Result<SocketAddr> {
     util::socket_addr_with_default_port(s, GossipListenAddr::DEFAULT_PORT)
 }
@@ -137,6 +285,17 @@ fn parse_peer(s: &str) -> io::Result<SocketAddr> {
             rename_all = "screamingsnake",
         )]
 pub struct SupRun {
+    /// The set of defaults to run with: `default` or `minimal`
+    ///
+    /// The `minimal` profile targets resource-constrained edge devices. See `--profile-help` for
+    /// the trade-offs it makes.
+    #[structopt(long = "profile", default_value = "default")]
+    #[serde(default)]
+    pub profile: SupervisorProfile,
+    /// Describe the trade-offs made by each profile and exit
+    #[structopt(long = "profile-help")]
+    #[serde(default)]
+    pub profile_help: bool,
     /// The listen address for the Gossip Gateway
     #[structopt(long = "listen-gossip",
                 env = GossipListenAddr::ENVVAR,
@@ -154,12 +313,19 @@ pub struct SupRun {
     /// Disable the HTTP Gateway completely
     #[structopt(long = "http-disable", short = "D")]
     pub http_disable: bool,
+    /// Require a bearer token on requests to the HTTP Gateway
+    ///
+    /// The token is read from HTTP_AUTH_TOKEN_FILE, and takes the place of any set with
+    /// HAB_SUP_GATEWAY_AUTH_TOKEN.
+    #[structopt(long = "http-auth-token-file", conflicts_with = "HTTP_DISABLE")]
+    pub http_auth_token_file: Option<PathBuf>,
     /// The listen address for the Control Gateway
     #[structopt(long = "listen-ctl",
                 env = ListenCtlAddr::ENVVAR,
                 default_value = ListenCtlAddr::default_as_str())]
     pub listen_ctl: ListenCtlAddr,
-    /// The organization the Supervisor and its services are part of
+    /// The organization the Supervisor and its services are part of. Binds, configuration and
+    /// files for service groups which name no organization are scoped to it
     #[structopt(long = "org")]
     pub organization: Option<String>,
     /// The listen address of one or more initial peers (IP[:PORT])
@@ -174,6 +340,30 @@ pub struct SupRun {
     /// Watch this file for connecting to the ring
     #[structopt(long = "peer-watch-file", conflicts_with = "PEER")]
     pub peer_watch_file: Option<PathBuf>,
+    /// Where the Supervisor's member-id comes from: `random`, `machine-id`, `cloud-instance-id`
+    /// or `file:<PATH>`
+    ///
+    /// Anything but `random` derives the member-id from a hash of a stable machine identity, so
+    /// that a re-provisioned machine rejoins the ring as the same member. A derived member-id
+    /// replaces any member-id previously saved by the Supervisor.
+    #[structopt(long = "member-id-source", default_value = "random")]
+    #[serde(default)]
+    pub member_id_source: MemberIdSource,
+    /// Keep at most this many rumors of a type, given as TYPE=COUNT (ex: --max-rumors
+    /// service=10000)
+    ///
+    /// TYPE is one of service, service-config or service-file. Past the limit, the least
+    /// recently updated rumors are evicted; this Supervisor's own rumors are always kept.
+    #[structopt(long = "max-rumors", number_of_values = 1)]
+    #[serde(default)]
+    pub max_rumors: Vec<RumorCap>,
+    /// Keep at most this many bytes of encoded rumors of a type, given as TYPE=BYTES where BYTES
+    /// may have a unit (ex: --max-rumor-bytes service-file=64MiB)
+    ///
+    /// TYPE is one of service, service-config or service-file.
+    #[structopt(long = "max-rumor-bytes", number_of_values = 1)]
+    #[serde(default)]
+    pub max_rumor_bytes: Vec<RumorCap>,
     #[structopt(flatten)]
     #[serde(flatten)]
     pub cache_key_path: CacheKeyPath,
@@ -195,10 +385,10 @@ pub struct SupRun {
     /// Enable automatic updates for the Supervisor itself
     #[structopt(long = "auto-update", short = "A")]
     pub auto_update: bool,
-    /// The period of time in seconds between Supervisor update checks
+    /// The period of time between Supervisor update checks, in seconds or with units (ex: 5m)
     #[structopt(long = "auto-update-period", default_value = "60")]
     pub auto_update_period: DurationProxy,
-    /// The period of time in seconds between service update checks
+    /// The period of time between service update checks, in seconds or with units (ex: 5m)
     #[structopt(long = "service-update-period", default_value = "60")]
     pub service_update_period: DurationProxy,
     /// The private key for HTTP Gateway TLS encryption
@@ -221,6 +411,65 @@ pub struct SupRun {
     #[structopt(long = "ca-certs",
                 requires_all = &["CERT_FILE", "KEY_FILE"])]
     pub ca_cert_file: Option<PathBuf>,
+    /// The server certificates for Control Gateway TLS encryption
+    ///
+    /// Read the Control Gateway's certificates from CTL_SERVER_CERTIFICATE, in the same format as
+    /// CERT_FILE. Clients must then connect with TLS, trusting the CA which signed them.
+    #[structopt(long = "ctl-server-certificate", requires = "CTL_SERVER_KEY")]
+    pub ctl_server_certificate: Option<PathBuf>,
+    /// The private key for Control Gateway TLS encryption
+    ///
+    /// Read the private key from CTL_SERVER_KEY, in the same format as KEY_FILE.
+    #[structopt(long = "ctl-server-key", requires = "CTL_SERVER_CERTIFICATE")]
+    pub ctl_server_key: Option<PathBuf>,
+    /// The CA certificate for authenticating Control Gateway clients
+    ///
+    /// Read the CA certificate from CTL_CLIENT_CA. Clients must then present a certificate it
+    /// signed, as well as the Control Gateway secret.
+    #[structopt(long = "ctl-client-ca",
+                requires_all = &["CTL_SERVER_CERTIFICATE", "CTL_SERVER_KEY"])]
+    pub ctl_client_ca: Option<PathBuf>,
+    /// Refuse Control Gateway clients which send the secret key itself
+    ///
+    /// Clients must instead prove they know it by opening a session, as every hab since sessions
+    /// were introduced does, so that the secret key is never sent over the connection.
+    #[structopt(long = "ctl-require-session")]
+    pub ctl_require_session: bool,
+    /// Obtain and renew the HTTP Gateway certificate for this domain from an ACME server such as
+    /// Let's Encrypt (ex: --acme-domain sup.example.com)
+    ///
+    /// The certificate and its private key are written to CERT_FILE and KEY_FILE, and renewed
+    /// when they have less than 30 days left. The first domain is the subject of the certificate;
+    /// any others are its alternative names.
+    #[structopt(long = "acme-domain",
+                number_of_values = 1,
+                requires_all = &["CERT_FILE", "KEY_FILE", "ACME_EMAIL"])]
+    #[serde(default)]
+    pub acme_domain: Vec<String>,
+    /// The contact email address of the ACME account
+    #[structopt(long = "acme-email")]
+    pub acme_email: Option<String>,
+    /// The directory URL of the ACME server
+    #[structopt(long = "acme-directory", default_value = LETS_ENCRYPT_DIRECTORY)]
+    pub acme_directory: String,
+    /// How the ACME server checks that the Supervisor controls each domain: `http-01` or `dns-01`
+    #[structopt(long = "acme-challenge",
+                default_value = AcmeChallenge::Http01.as_str(),
+                possible_values = AcmeChallenge::VARIANTS)]
+    #[serde(default)]
+    pub acme_challenge: AcmeChallenge,
+    /// The listen address answering `http-01` challenges
+    ///
+    /// The ACME server connects to port 80 of each domain, which must reach this address.
+    #[structopt(long = "acme-http-listen", default_value = "0.0.0.0:80")]
+    pub acme_http_listen: SocketAddr,
+    /// The program publishing the DNS records of `dns-01` challenges
+    ///
+    /// Before a domain is checked, it is run as `ACME_DNS_HOOK deploy DOMAIN VALUE` and should
+    /// return once the `_acme-challenge.DOMAIN` TXT record holds VALUE. It is run as
+    /// `ACME_DNS_HOOK clean DOMAIN VALUE` afterwards.
+    #[structopt(long = "acme-dns-hook")]
+    pub acme_dns_hook: Option<PathBuf>,
     /// Load a Habitat package as part of the Supervisor startup
     ///
     /// The package can be specified by a package identifier (ex: core/redis) or filepath to a
@@ -238,6 +487,16 @@ pub struct SupRun {
     /// This option also sets NO_COLOR.
     #[structopt(long = "json-logging")]
     pub json_logging: bool,
+    /// Where to write Supervisor and service output
+    ///
+    /// `journald` sends each line to the systemd journal with the preamble and logkey attached as
+    /// the `HAB_PREAMBLE` and `HAB_LOGKEY` fields. `eventlog` reports each line to the Windows
+    /// Event Log.
+    #[structopt(long = "log-sink",
+                default_value = LogSink::Stdout.as_str(),
+                possible_values = LogSink::VARIANTS)]
+    #[serde(default)]
+    pub log_sink: LogSink,
     /// The IPv4 address to use as the `sys.ip` template variable
     ///
     /// If this argument is not set, the supervisor tries to dynamically determine an IP address.
@@ -254,7 +513,8 @@ pub struct SupRun {
     /// This will be attached to all events generated by this Supervisor.
     #[structopt(long = "event-stream-environment", empty_values = false)]
     pub event_stream_environment: Option<String>,
-    /// Event stream connection timeout before exiting the Supervisor
+    /// Event stream connection timeout before exiting the Supervisor, in seconds or with units
+    /// (ex: 30s)
     ///
     /// Set to '0' to immediately start the Supervisor and continue running regardless of the
     /// initial connection status.
@@ -286,6 +546,90 @@ pub struct SupRun {
     /// The certificate should be in PEM format.
     #[structopt(long = "event-stream-server-certificate")]
     pub event_stream_server_certificate: Option<EventStreamServerCertificate>,
+    /// The maximum number of events to hold while the event stream is unreachable
+    ///
+    /// Buffered events are saved in the Supervisor's data directory, so they survive a restart,
+    /// and are published once the connection is restored. Set to '0' to drop events that cannot
+    /// be published immediately.
+    #[structopt(long = "event-stream-buffer-size",
+                env = "HAB_EVENT_STREAM_BUFFER_SIZE",
+                default_value = "10000")]
+    pub event_stream_buffer_size: usize,
+    /// Which events to drop when the event stream buffer is full: `drop-oldest` or `drop-newest`
+    #[structopt(long = "event-stream-buffer-policy", default_value = "drop-oldest")]
+    pub event_stream_buffer_policy: EventStreamBufferPolicy,
+    /// Publish events to these Kafka brokers instead of a NATS server (ex:
+    /// --event-stream-kafka-brokers kafka1:9092,kafka2:9092)
+    ///
+    /// This enables the event stream and requires EVENT_STREAM_KAFKA_TOPIC,
+    /// EVENT_STREAM_APPLICATION, and EVENT_STREAM_ENVIRONMENT also be set. The Supervisor must
+    /// have been built with the kafka feature.
+    #[structopt(long = "event-stream-kafka-brokers",
+                use_delimiter = true,
+                conflicts_with = "EVENT_STREAM_URL",
+                requires_all = &["EVENT_STREAM_KAFKA_TOPIC",
+                                 "EVENT_STREAM_APPLICATION",
+                                 "EVENT_STREAM_ENVIRONMENT"])]
+    #[serde(default)]
+    pub event_stream_kafka_brokers: Vec<String>,
+    /// The Kafka topic to publish events to
+    ///
+    /// Each event is keyed by its type (ex: habitat.event.healthcheck).
+    #[structopt(long = "event-stream-kafka-topic",
+                empty_values = false,
+                requires = "EVENT_STREAM_KAFKA_BROKERS")]
+    pub event_stream_kafka_topic: Option<String>,
+    /// Connect to the Kafka brokers with TLS
+    ///
+    /// The brokers' certificates are verified against EVENT_STREAM_SERVER_CERTIFICATE if it is
+    /// set, and the system's trusted certificates otherwise.
+    #[structopt(long = "event-stream-kafka-tls", requires = "EVENT_STREAM_KAFKA_BROKERS")]
+    pub event_stream_kafka_tls: bool,
+    /// Authenticate to the Kafka brokers with SASL: `PLAIN`, `SCRAM-SHA-256` or `SCRAM-SHA-512`
+    #[structopt(long = "event-stream-kafka-sasl-mechanism",
+                possible_values = KafkaSaslMechanism::VARIANTS,
+                requires_all = &["EVENT_STREAM_KAFKA_BROKERS",
+                                 "EVENT_STREAM_KAFKA_SASL_USERNAME",
+                                 "EVENT_STREAM_KAFKA_SASL_PASSWORD"])]
+    pub event_stream_kafka_sasl_mechanism: Option<KafkaSaslMechanism>,
+    /// The username to authenticate to the Kafka brokers with
+    #[structopt(long = "event-stream-kafka-sasl-username",
+                requires = "EVENT_STREAM_KAFKA_SASL_MECHANISM")]
+    pub event_stream_kafka_sasl_username: Option<String>,
+    /// The password to authenticate to the Kafka brokers with
+    #[structopt(long = "event-stream-kafka-sasl-password",
+                env = "HAB_EVENT_STREAM_KAFKA_SASL_PASSWORD",
+                hide_env_values = true,
+                requires = "EVENT_STREAM_KAFKA_SASL_MECHANISM")]
+    pub event_stream_kafka_sasl_password: Option<String>,
+    /// POST events to this webhook instead of publishing them to a NATS server (ex:
+    /// --event-stream-webhook-url https://hooks.example.com/habitat)
+    ///
+    /// May be given more than once. Only service lifecycle and health check events are sent to
+    /// webhooks. This enables the event stream and requires EVENT_STREAM_APPLICATION and
+    /// EVENT_STREAM_ENVIRONMENT also be set.
+    #[structopt(long = "event-stream-webhook-url",
+                number_of_values = 1,
+                conflicts_with_all = &["EVENT_STREAM_URL", "EVENT_STREAM_KAFKA_BROKERS"],
+                requires_all = &["EVENT_STREAM_APPLICATION", "EVENT_STREAM_ENVIRONMENT"])]
+    #[serde(default)]
+    pub event_stream_webhook_url: Vec<String>,
+    /// A Handlebars template for the JSON body POSTed to webhooks
+    ///
+    /// The template is rendered with the event, as it is sent when no template is given. Use the
+    /// `toJson` helper to quote strings (ex: {"text": {{toJson service.service_group}}}).
+    #[structopt(long = "event-stream-webhook-template", requires = "EVENT_STREAM_WEBHOOK_URL")]
+    pub event_stream_webhook_template: Option<PathBuf>,
+    /// Only POST these kinds of event to webhooks (ex: --event-stream-webhook-events
+    /// service_stopped,healthcheck)
+    ///
+    /// All service lifecycle and health check events are sent if this is not set.
+    #[structopt(long = "event-stream-webhook-events",
+                use_delimiter = true,
+                possible_values = EventStreamWebhookEvent::VARIANTS,
+                requires = "EVENT_STREAM_WEBHOOK_URL")]
+    #[serde(default)]
+    pub event_stream_webhook_events: Vec<EventStreamWebhookEvent>,
     /// Automatically cleanup old packages
     ///
     /// The Supervisor will automatically cleanup old packages only keeping the
@@ -293,6 +637,59 @@ pub struct SupRun {
     /// automatic package cleanup is performed.
     #[structopt(long = "keep-latest-packages", env = "HAB_KEEP_LATEST_PACKAGES")]
     pub keep_latest_packages: Option<usize>,
+    /// Require `svc load`, `svc update`, `config apply`, and `file upload` requests to be signed
+    /// by one of these signing keys (ex: --trusted-signer acme)
+    ///
+    /// The public keys of the trusted signers must be present in the Supervisor's key cache. A
+    /// signed request is only taken once, by the Supervisor it was signed for, within five minutes
+    /// of when it was signed. Configuration and files gossiped by other members are only applied
+    /// if they were set by a request signed by one of these keys. Signed requests and ring
+    /// commands from `hab ring exec` are refused when this is not set.
+    #[structopt(long = "trusted-signer", number_of_values = 1)]
+    #[serde(default)]
+    pub trusted_signer: Vec<String>,
+    /// Keep the signed origin key revocation list at this URL installed in the key cache (ex:
+    /// --key-revocation-url https://example.com/acme.crl)
+    ///
+    /// Artifacts signed with keys revoked or expired by an installed list fail verification.
+    #[structopt(long = "key-revocation-url", number_of_values = 1)]
+    #[serde(default)]
+    pub key_revocation_url: Vec<String>,
+    /// Keep the signed key revocation list which Builder publishes for this origin installed in
+    /// the key cache (ex: --key-revocation-origin acme)
+    ///
+    /// The list is fetched from the Builder at `--url`. A fetched list only replaces the
+    /// installed one if it is newer and signed by a key the installed one still trusts.
+    #[structopt(long = "key-revocation-origin", number_of_values = 1)]
+    #[serde(default)]
+    pub key_revocation_origin: Vec<String>,
+    /// Verify the files of a service's package against the checksums of its FILES manifest
+    /// before starting the service, and refuse to start it if they don't match
+    ///
+    /// Each package is verified once per Supervisor run; packages built without a FILES
+    /// manifest are started unverified.
+    #[structopt(long = "verify-on-start")]
+    pub verify_on_start: bool,
+    /// Load the services in the service list at this URL on startup (ex: --bootstrap-from
+    /// https://example.com/web.toml)
+    ///
+    /// The list has a `[[services]]` table for each service, in the format of a service config
+    /// file. Each service is loaded as if by `hab svc load --force`. The list must match
+    /// --bootstrap-checksum, or be signed with `hab pkg sign` by a key in the Supervisor's key
+    /// cache. When --trusted-signer is given it must be signed by one of the trusted signers.
+    #[structopt(long = "bootstrap-from")]
+    pub bootstrap_from: Option<String>,
+    /// The BLAKE2b checksum of the --bootstrap-from service list, as printed by `hab pkg hash`
+    #[structopt(long = "bootstrap-checksum", requires = "BOOTSTRAP_FROM")]
+    pub bootstrap_checksum: Option<String>,
+    /// Withhold the values of service environment variables whose names match this glob
+    /// pattern from `hab svc env` (ex: --env-redact-pattern 'AWS_*')
+    ///
+    /// Names are matched case-insensitively. Variables matching *PASSWORD*, *SECRET*, *TOKEN*,
+    /// *CREDENTIAL* or *_KEY are always withheld.
+    #[structopt(long = "env-redact-pattern", number_of_values = 1)]
+    #[serde(default)]
+    pub env_redact_pattern: Vec<String>,
     /// Paths to files or directories of service config files to load on startup
     ///
     /// See `hab svc bulkload --help` for details
@@ -305,10 +702,43 @@ pub struct SupRun {
     pub shared_load: SharedLoad,
 }
 
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to a Supervisor's gossip ring
+pub enum SupRing {
+    /// Switch a Supervisor's gossip encryption to the latest revision of its ring key in its key
+    /// cache, without restarting it
+    Reload {
+        /// Stop accepting gossip encrypted with earlier revisions. Only retire them once every
+        /// Supervisor in the ring has reloaded
+        #[structopt(long = "retire")]
+        retire:     bool,
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
+}
+
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to a Habitat Supervisor's Control Gateway secret
 pub enum Secret {
     /// Generate a secret key to use as a Supervisor's Control Gateway secret
     Generate,
+    /// Replace a Supervisor's Control Gateway secret with a newly generated one, which is
+    /// printed. The previous secret is still accepted for a grace period, and clients which use
+    /// it in that time are warned that it has been rotated
+    Rotate {
+        /// How long the Supervisor still accepts the previous secret, in seconds or with units
+        /// (ex: 90s, 10m)
+        #[structopt(name = "GRACE_PERIOD", long = "grace-period", default_value = "300")]
+        grace_period: DurationProxy,
+        #[structopt(flatten)]
+        remote_sup:   RemoteSup,
+    },
+    /// Revoke every session token a Supervisor has given out. Clients holding one have to prove
+    /// they know the Control Gateway secret again, which they do automatically
+    RevokeSessions {
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
 }
@@ -1,22 +1,36 @@
 use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath,
+                  ConfigOptHttpGatewayOptions,
                   ConfigOptPkgIdent,
                   ConfigOptRemoteSup,
+                  DurationProxy,
+                  HttpGatewayOptions,
                   PkgIdent,
                   RemoteSup};
-use crate::error::{Error,
-                   Result};
+use crate::{cli::{file_exists,
+                  valid_update_window_or_empty},
+            command::service::status::StatusFormat,
+            error::{Error,
+                    Result}};
 use clap::AppSettings;
 use configopt::{configopt_fields,
                 ConfigOpt};
 use habitat_common::{FeatureFlag,
                      FEATURE_FLAGS};
-use habitat_core::{os::process::ShutdownTimeout,
+use habitat_core::{os::process::{CpuLimit,
+                                 CpuSet,
+                                 HookTimeouts,
+                                 MemoryLimit,
+                                 NiceLevel,
+                                 PriorityClass,
+                                 ResourceLimits,
+                                 ShutdownTimeout},
                    package::PackageIdent,
                    service::{BindingMode,
                              HealthCheckInterval,
                              ServiceBind,
-                             ServiceGroup},
+                             ServiceGroup,
+                             UpdateWindow},
                    ChannelIdent};
 use habitat_sup_protocol::{ctl,
                            types::UpdateCondition};
@@ -36,8 +50,11 @@ pub const DEFAULT_SVC_CONFIG_DIR: &str = "/hab/sup/default/config/svc";
 #[structopt(no_version)]
 #[allow(clippy::large_enum_variant)]
 pub enum Svc {
+    #[structopt(name = "apply-manifest")]
+    ApplyManifest(ApplyManifest),
     #[structopt(name = "bulkload")]
     BulkLoad(BulkLoad),
+    Env(SvcEnv),
     Key(Key),
     #[structopt(no_version)]
     Load(Load),
@@ -50,8 +67,34 @@ pub enum Svc {
         /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
         #[structopt(name = "PKG_IDENT")]
         pkg_ident:  Option<PackageIdent>,
+        /// Also show how each service's process is scheduled
+        #[structopt(name = "DETAILS", long = "details")]
+        details:    bool,
+        /// Show recorded lifecycle transitions instead of current status
+        #[structopt(name = "HISTORY", long = "history", conflicts_with = "DETAILS")]
+        history:    bool,
+        /// How to print the status: `table`, `wide` (a table including the columns of
+        /// --details), or `json` or `yaml` for scripts
+        #[structopt(name = "FORMAT",
+                    long = "format",
+                    default_value = "table",
+                    possible_values = StatusFormat::VARIANTS)]
+        format:     String,
+        /// Keep refreshing the status, highlighting the services whose state changed
+        #[structopt(name = "WATCH",
+                    long = "watch",
+                    conflicts_with = "HISTORY")]
+        watch:      bool,
+        /// How often --watch refreshes the status, in seconds or with units (ex: 5s, 1m)
+        #[structopt(name = "INTERVAL",
+                    long = "interval",
+                    default_value = "2",
+                    requires = "WATCH")]
+        interval:   DurationProxy,
         #[structopt(flatten)]
         remote_sup: RemoteSup,
+        #[structopt(flatten)]
+        http:       HttpGatewayOptions,
     },
     Stop(SvcStop),
     /// Unload a service loaded by the Habitat Supervisor. If the service is running it will
@@ -61,12 +104,30 @@ pub enum Svc {
         pkg_ident:        PkgIdent,
         #[structopt(flatten)]
         remote_sup:       RemoteSup,
-        /// The delay in seconds after sending the shutdown signal to wait before killing the
-        /// service process
+        /// The delay after sending the shutdown signal to wait before killing the service
+        /// process, in seconds or with units (ex: 90s, 2m)
         ///
         /// The default value is set in the packages plan file.
         #[structopt(name = "SHUTDOWN_TIMEOUT", long = "shutdown-timeout")]
         shutdown_timeout: Option<ShutdownTimeout>,
+        /// Keep the service's data directory (default)
+        #[structopt(name = "RETAIN_DATA", long = "retain-data", conflicts_with = "PURGE_DATA")]
+        retain_data:      bool,
+        /// Delete the service's data directory once the service has stopped
+        #[structopt(name = "PURGE_DATA", long = "purge-data")]
+        purge_data:       bool,
+        /// Write a tarball of the service's data directory to this path on the Supervisor's host
+        /// before it is purged
+        #[structopt(name = "ARCHIVE_DATA", long = "archive-data")]
+        archive_data:     Option<PathBuf>,
+        /// Encrypt the tarball of --archive-data with the service group's key
+        #[structopt(name = "ENCRYPT_ARCHIVE",
+                    long = "encrypt-archive",
+                    requires = "ARCHIVE_DATA")]
+        encrypt_archive:  bool,
+        /// Do not prompt for confirmation of --purge-data
+        #[structopt(name = "NO_PROMPT", short = "n", long = "no-prompt")]
+        no_prompt:        bool,
     },
 }
 
@@ -88,6 +149,39 @@ pub struct BulkLoad {
     pub svc_config_paths: Vec<PathBuf>,
 }
 
+/// Load, update and unload services so that a Supervisor runs exactly the services in a manifest
+///
+/// The manifest lists the services with a `[[services]]` table for each, in the format of a
+/// service config file (the format generated by `hab svc load --generate-config`). Services which
+/// are not loaded are loaded, services which are loaded are reloaded with the manifest's options
+/// (the Supervisor only restarts those whose options changed), and loaded services which are not
+/// in the manifest are unloaded.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "apply-manifest", no_version, rename_all = "screamingsnake")]
+pub struct ApplyManifest {
+    /// Path to the manifest file
+    #[structopt(name = "MANIFEST", validator = file_exists)]
+    pub manifest:   PathBuf,
+    /// Print the changes which would be made without making them
+    #[structopt(long = "dry-run")]
+    pub dry_run:    bool,
+    #[structopt(flatten)]
+    pub remote_sup: RemoteSup,
+}
+
+/// Print the environment a running Habitat service was started with
+///
+/// Values of variables which look like secrets, or match a Supervisor `--env-redact-pattern`,
+/// are withheld.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "env", no_version, rename_all = "screamingsnake")]
+pub struct SvcEnv {
+    #[structopt(flatten)]
+    pub pkg_ident:  PkgIdent,
+    #[structopt(flatten)]
+    pub remote_sup: RemoteSup,
+}
+
 /// Start a loaded, but stopped, Habitat service.
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version, rename_all = "screamingsnake")]
@@ -106,8 +200,8 @@ pub struct SvcStop {
     pkg_ident:        PkgIdent,
     #[structopt(flatten)]
     remote_sup:       RemoteSup,
-    /// The delay in seconds after sending the shutdown signal to wait before killing the
-    /// service process
+    /// The delay after sending the shutdown signal to wait before killing the service process,
+    /// in seconds or with units (ex: 90s, 2m)
     ///
     /// The default value is set in the packages plan file.
     #[structopt(name = "SHUTDOWN_TIMEOUT", long = "shutdown-timeout")]
@@ -171,9 +265,22 @@ pub struct SharedLoad {
     #[structopt(long = "strategy",
                 short = "s",
                 default_value = "none",
-                possible_values = &["none", "at-once", "rolling"])]
+                possible_values = &["none", "at-once", "rolling", "canary"])]
     #[serde(default)]
     pub strategy:              habitat_sup_protocol::types::UpdateStrategy,
+    /// The percentage of the service group which updates first with the canary update strategy.
+    /// Defaults to 10
+    #[structopt(long = "canary-percent")]
+    pub canary_percent:        Option<u32>,
+    /// How long the updated members must stay healthy with the canary update strategy before
+    /// the rest of the service group updates, in seconds or with units (ex: 10m). Defaults to 10m
+    #[structopt(long = "canary-soak")]
+    pub canary_soak:           Option<DurationProxy>,
+    /// Only apply updates within this window: a range of times of day, optionally preceded by
+    /// days of the week and followed by UTC or local, the default (ex: "Sat 02:00-04:00 UTC").
+    /// Rolling back is not held up by the window
+    #[structopt(long = "update-window")]
+    pub update_window:         Option<UpdateWindow>,
     /// The condition dictating when this service should update
     ///
     /// latest: Runs the latest package that can be found in the configured channel and local
@@ -209,12 +316,51 @@ pub struct SharedLoad {
     #[structopt(long = "health-check-interval", short = "i", default_value = "30")]
     #[serde(default = "health_check_interval_default")]
     pub health_check_interval: u64,
-    /// The delay in seconds after sending the shutdown signal to wait before killing the service
-    /// process
+    /// The delay after sending the shutdown signal to wait before killing the service process,
+    /// in seconds or with units (ex: 90s, 2m)
     ///
     /// The default value can be set in the packages plan file.
     #[structopt(long = "shutdown-timeout")]
     pub shutdown_timeout:      Option<ShutdownTimeout>,
+    /// When the Supervisor shuts down, services with a higher shutdown order are stopped before
+    /// those with a lower one. Defaults to 0
+    #[structopt(long = "shutdown-order", allow_hyphen_values = true)]
+    pub shutdown_order:        Option<i32>,
+    /// How long the service's hooks may run before the Supervisor kills them, as HOOK=SECONDS
+    /// for the init, run-startup-grace, post-stop and reconfigure hooks, separated by commas
+    /// (ex: init=600,post-stop=30)
+    ///
+    /// The default values can be set in the packages plan file.
+    #[structopt(long = "hook-timeouts")]
+    pub hook_timeouts:         Option<HookTimeouts>,
+    /// The CPUs the service's process may run on, as a comma-separated list of CPU numbers and
+    /// ranges (ex: 0-3,8). Only applied on Linux
+    #[structopt(long = "cpuset")]
+    pub cpuset:                Option<CpuSet>,
+    /// The niceness of the service's process, from -20 (highest priority) to 19 (lowest
+    /// priority). Negative values require the Supervisor to run as root. Only applied on Linux
+    #[structopt(long = "nice", allow_hyphen_values = true)]
+    pub nice:                  Option<NiceLevel>,
+    /// The priority class of the service's process. Only applied on Windows
+    #[structopt(long = "priority-class", possible_values = PriorityClass::VARIANTS)]
+    pub priority_class:        Option<PriorityClass>,
+    /// The resource limits of the service's process, as LIMIT=VALUE for the nofile, nproc and
+    /// core limits, separated by commas (ex: nofile=65536,core=unlimited). Only nproc is applied
+    /// on Windows
+    #[structopt(long = "limits")]
+    pub limits:                Option<ResourceLimits>,
+    /// The CPU time the service and all of its processes may use together, as a number of CPUs
+    /// (ex: 0.5). Enforced with a cgroup on Linux and a job object on Windows
+    #[structopt(long = "cpu-limit")]
+    pub cpu_limit:             Option<CpuLimit>,
+    /// The memory the service and all of its processes may use together, in bytes or with units
+    /// (ex: 512MiB). Enforced with a cgroup on Linux and a job object on Windows
+    #[structopt(long = "memory-limit")]
+    pub memory_limit:          Option<MemoryLimit>,
+    /// The number of processes the service may have. Enforced with a cgroup on Linux and a job
+    /// object on Windows
+    #[structopt(long = "pids-limit")]
+    pub pids_limit:            Option<u64>,
     #[cfg(target_os = "windows")]
     /// Password of the service user
     #[structopt(long = "password")]
@@ -234,6 +380,11 @@ pub struct SharedLoad {
     /// Use the package config from this path rather than the package itself
     #[structopt(long = "config-from")]
     pub config_from:           Option<PathBuf>,
+    /// Re-render the service's configuration, or restart it if its hooks change, whenever the
+    /// files in the `--config-from` path change
+    #[structopt(long = "watch", requires = "config-from")]
+    #[serde(default)]
+    pub watch:                 bool,
 }
 
 fn load_default_config_files() -> Vec<PathBuf> {
@@ -256,18 +407,25 @@ fn load_default_config_files() -> Vec<PathBuf> {
 /// installed from Builder.
 pub struct Load {
     #[structopt(flatten)]
-    pub pkg_ident:   PkgIdent,
+    pub pkg_ident:      PkgIdent,
     /// Load or reload an already loaded service. If the service was previously loaded and
-    /// running this operation will also restart the service
+    /// running this operation will also restart the service. Also loads a service which exposes
+    /// a port another loaded service already exposes
     #[structopt(short = "f", long = "force")]
     #[serde(default)]
-    pub force:       bool,
+    pub force:          bool,
+    #[structopt(flatten)]
+    #[serde(flatten)]
+    pub remote_sup:     RemoteSup,
     #[structopt(flatten)]
     #[serde(flatten)]
-    pub remote_sup:  RemoteSup,
+    pub shared_load:    SharedLoad,
+    /// Sign the request with the latest revision of this signing key (ex: acme)
+    #[structopt(long = "sign-with")]
+    pub sign_with:      Option<String>,
     #[structopt(flatten)]
     #[serde(flatten)]
-    pub shared_load: SharedLoad,
+    pub cache_key_path: CacheKeyPath,
 }
 
 pub fn svc_loads_from_paths<T: AsRef<Path>>(paths: &[T]) -> Result<Vec<Load>> {
@@ -305,6 +463,30 @@ pub fn svc_loads_from_paths<T: AsRef<Path>>(paths: &[T]) -> Result<Vec<Load>> {
     Ok(svc_loads)
 }
 
+/// Parse a list of services with a `[[services]]` table for each, in the format of a service
+/// config file. Like service config files, each service is patched with the default values from
+/// `/hab/sup/default/config/svc.toml`.
+pub fn svc_loads_from_str(content: &str) -> Result<Vec<Load>> {
+    #[derive(Deserialize)]
+    struct ServiceList<T> {
+        #[serde(default)]
+        services: Vec<T>,
+    }
+
+    let default_svc_load = ConfigOptLoad::from_default_config_files()?;
+    let configopt_svc_loads = toml::from_str::<ServiceList<ConfigOptLoad>>(content)?.services;
+    let svc_loads = toml::from_str::<ServiceList<Load>>(content)?.services;
+    Ok(configopt_svc_loads.into_iter()
+                          .zip(svc_loads)
+                          .map(|(mut configopt_svc_load, mut svc_load)| {
+                              let mut default_svc_load = default_svc_load.clone();
+                              default_svc_load.take(&mut configopt_svc_load);
+                              default_svc_load.take_for(&mut svc_load);
+                              svc_load
+                          })
+                          .collect())
+}
+
 pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                               shared_load: SharedLoad,
                               force: bool)
@@ -360,15 +542,37 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                  bldr_url: Some(habitat_core::url::bldr_url(shared_load.bldr_url)),
                  bldr_channel: Some(shared_load.channel.to_string()),
                  config_from,
+                 watch_config_from: Some(shared_load.watch),
                  force: Some(force),
                  group: Some(shared_load.group),
                  svc_encrypted_password,
                  topology: shared_load.topology.map(i32::from),
                  update_strategy: Some(shared_load.strategy as i32),
+                 canary_percent: shared_load.canary_percent,
+                 canary_soak: shared_load.canary_soak.map(canary_soak_secs).transpose()?,
+                 update_window: shared_load.update_window.map(|w| w.to_string()),
                  health_check_interval:
                      Some(HealthCheckInterval { seconds: shared_load.health_check_interval, }),
                  shutdown_timeout: shared_load.shutdown_timeout.map(u32::from),
-                 update_condition: Some(shared_load.update_condition as i32) })
+                 shutdown_order: shared_load.shutdown_order,
+                 hook_timeouts: shared_load.hook_timeouts.map(|t| t.to_string()),
+                 update_condition: Some(shared_load.update_condition as i32),
+                 signature: None,
+                 cpuset: shared_load.cpuset.map(|c| c.to_string()),
+                 nice: shared_load.nice.map(i32::from),
+                 priority_class: shared_load.priority_class.map(|c| c.to_string()),
+                 limits: shared_load.limits.map(|l| l.to_string()),
+                 cpu_limit: shared_load.cpu_limit.map(|l| l.to_string()),
+                 memory_limit: shared_load.memory_limit.map(u64::from),
+                 pids_limit: shared_load.pids_limit })
+}
+
+fn canary_soak_secs(soak: DurationProxy) -> Result<u32> {
+    u32::try_from(u64::from(soak)).map_err(|_| {
+                                      Error::ArgumentError(format!("A canary soak of {} seconds \
+                                                                    is too long",
+                                                                   soak))
+                                  })
 }
 
 impl TryFrom<Load> for habitat_sup_protocol::ctl::SvcLoad {
@@ -423,9 +627,24 @@ pub struct Update {
     /// The update strategy
     #[structopt(long = "strategy",
                 short = "s",
-                possible_values = &["none", "at-once", "rolling"])]
+                possible_values = &["none", "at-once", "rolling", "canary"])]
     pub strategy: Option<habitat_sup_protocol::types::UpdateStrategy>,
 
+    /// The percentage of the service group which updates first with the canary update strategy
+    #[structopt(long = "canary-percent")]
+    pub canary_percent: Option<u32>,
+
+    /// How long the updated members must stay healthy with the canary update strategy before
+    /// the rest of the service group updates, in seconds or with units (ex: 10m)
+    #[structopt(long = "canary-soak")]
+    pub canary_soak: Option<DurationProxy>,
+
+    /// Only apply updates within this window: a range of times of day, optionally preceded by
+    /// days of the week and followed by UTC or local, the default (ex: "Sat 02:00-04:00 UTC").
+    /// An empty window lets updates be applied at any time
+    #[structopt(long = "update-window", validator = valid_update_window_or_empty)]
+    pub update_window: Option<String>,
+
     /// The condition dictating when this service should update
     ///
     /// latest: Runs the latest package that can be found in the configured channel and local
@@ -445,6 +664,17 @@ pub struct Update {
     #[serde(default)]
     pub bind: Option<Vec<ServiceBind>>,
 
+    /// One or more service groups to bind to, in addition to the service's current binds. A
+    /// current bind with the same name is replaced
+    #[structopt(long = "bind-add", conflicts_with = "bind")]
+    #[serde(default)]
+    pub bind_add: Vec<ServiceBind>,
+
+    /// The names of one or more of the service's current binds to remove
+    #[structopt(long = "bind-remove", conflicts_with = "bind")]
+    #[serde(default)]
+    pub bind_remove: Vec<String>,
+
     /// Governs how the presence or absence of binds affects service startup
     ///
     /// strict: blocks startup until all binds are present.
@@ -458,17 +688,84 @@ pub struct Update {
     #[structopt(long = "health-check-interval", short = "i")]
     pub health_check_interval: Option<HealthCheckInterval>,
 
-    /// The delay in seconds after sending the shutdown signal to wait before killing the service
-    /// process
+    /// The delay after sending the shutdown signal to wait before killing the service process,
+    /// in seconds or with units (ex: 90s, 2m)
     ///
     /// The default value can be set in the packages plan file.
     #[structopt(long = "shutdown-timeout")]
     pub shutdown_timeout: Option<ShutdownTimeout>,
 
+    /// When the Supervisor shuts down, services with a higher shutdown order are stopped before
+    /// those with a lower one
+    #[structopt(long = "shutdown-order", allow_hyphen_values = true)]
+    pub shutdown_order: Option<i32>,
+
+    /// How long the service's hooks may run before the Supervisor kills them, as HOOK=SECONDS
+    /// for the init, run-startup-grace, post-stop and reconfigure hooks, separated by commas
+    /// (ex: init=600,post-stop=30)
+    ///
+    /// Only the hooks given are changed.
+    #[structopt(long = "hook-timeouts")]
+    pub hook_timeouts: Option<HookTimeouts>,
+
+    /// The CPUs the service's process may run on, as a comma-separated list of CPU numbers and
+    /// ranges (ex: 0-3,8). Only applied on Linux
+    #[structopt(long = "cpuset")]
+    pub cpuset: Option<CpuSet>,
+
+    /// The niceness of the service's process, from -20 (highest priority) to 19 (lowest
+    /// priority). Negative values require the Supervisor to run as root. Only applied on Linux
+    #[structopt(long = "nice", allow_hyphen_values = true)]
+    pub nice: Option<NiceLevel>,
+
+    /// The priority class of the service's process. Only applied on Windows
+    #[structopt(long = "priority-class", possible_values = PriorityClass::VARIANTS)]
+    pub priority_class: Option<PriorityClass>,
+
+    /// The resource limits of the service's process, as LIMIT=VALUE for the nofile, nproc and
+    /// core limits, separated by commas (ex: nofile=65536,core=unlimited). Only nproc is applied
+    /// on Windows
+    ///
+    /// Only the limits given are changed.
+    #[structopt(long = "limits")]
+    pub limits: Option<ResourceLimits>,
+
+    /// The CPU time the service and all of its processes may use together, as a number of CPUs
+    /// (ex: 0.5). Enforced with a cgroup on Linux and a job object on Windows
+    #[structopt(long = "cpu-limit")]
+    pub cpu_limit: Option<CpuLimit>,
+
+    /// The memory the service and all of its processes may use together, in bytes or with units
+    /// (ex: 512MiB). Enforced with a cgroup on Linux and a job object on Windows
+    #[structopt(long = "memory-limit")]
+    pub memory_limit: Option<MemoryLimit>,
+
+    /// The number of processes the service may have. Enforced with a cgroup on Linux and a job
+    /// object on Windows
+    #[structopt(long = "pids-limit")]
+    pub pids_limit: Option<u64>,
+
     /// Password of the service user
     #[cfg(target_os = "windows")]
     #[structopt(long = "password")]
     pub password: Option<String>,
+
+    /// Save changes which need the service to restart without restarting it
+    ///
+    /// The service is shown as having a restart pending until the changes are applied, when it
+    /// is next started or its update window opens. An update with no such changes is applied
+    /// right away.
+    #[structopt(long = "no-restart")]
+    #[serde(default)]
+    pub no_restart: bool,
+
+    /// Sign the request with the latest revision of this signing key (ex: acme)
+    #[structopt(long = "sign-with")]
+    pub sign_with: Option<String>,
+
+    #[structopt(flatten)]
+    #[serde(flatten)]
+    pub cache_key_path: CacheKeyPath,
 }
 
 impl TryFrom<Update> for ctl::SvcUpdate {
@@ -481,37 +778,74 @@ impl TryFrom<Update> for ctl::SvcUpdate {
                                    bldr_url: u.bldr_url.map(|u| u.to_string()),
                                    bldr_channel: u.channel.map(Into::into),
                                    binds: u.bind.map(FromIterator::from_iter),
+                                   add_binds: if u.bind_add.is_empty() {
+                                       None
+                                   } else {
+                                       Some(u.bind_add.into_iter().collect())
+                                   },
+                                   remove_binds: u.bind_remove,
                                    group: u.group,
                                    health_check_interval: u.health_check_interval.map(Into::into),
                                    binding_mode: u.binding_mode.map(|v| v as i32),
                                    topology: u.topology.map(|v| v as i32),
                                    update_strategy: u.strategy.map(|v| v as i32),
+                                   canary_percent: u.canary_percent,
+                                   canary_soak: u.canary_soak.map(canary_soak_secs).transpose()?,
+                                   update_window: u.update_window,
                                    update_condition: u.update_condition.map(|v| v as i32),
                                    shutdown_timeout: u.shutdown_timeout.map(Into::into),
+                                   shutdown_order: u.shutdown_order,
+                                   hook_timeouts: u.hook_timeouts.map(|t| t.to_string()),
                                    #[cfg(windows)]
                                    svc_encrypted_password: u.password,
                                    #[cfg(not(windows))]
-                                   svc_encrypted_password: None, };
+                                   svc_encrypted_password: None,
+                                   signature: None,
+                                   cpuset: u.cpuset.map(|c| c.to_string()),
+                                   nice: u.nice.map(i32::from),
+                                   priority_class: u.priority_class.map(|c| c.to_string()),
+                                   limits: u.limits.map(|l| l.to_string()),
+                                   cpu_limit: u.cpu_limit.map(|l| l.to_string()),
+                                   memory_limit: u.memory_limit.map(u64::from),
+                                   pids_limit: u.pids_limit,
+                                   no_restart: if u.no_restart { Some(true) } else { None } };
 
         // Compiler-assisted validation that the user has indeed
         // specified *something* to change. If they didn't, all the
-        // fields would end up as `None`, and that would be an error.
-        if let ctl::SvcUpdate { ident: _,
-                                binds: None,
-                                binding_mode: None,
-                                bldr_url: None,
-                                bldr_channel: None,
-                                group: None,
-                                svc_encrypted_password: None,
-                                topology: None,
-                                update_strategy: None,
-                                health_check_interval: None,
-                                shutdown_timeout: None,
-                                update_condition: None, } = &msg
-        {
-            Err(Error::ArgumentError("No fields specified for update".to_string()))
-        } else {
-            Ok(msg)
+        // fields would end up as `None` (or empty), and that would be an
+        // error.
+        match &msg {
+            ctl::SvcUpdate { ident: _,
+                             binds: None,
+                             add_binds: None,
+                             remove_binds,
+                             binding_mode: None,
+                             bldr_url: None,
+                             bldr_channel: None,
+                             group: None,
+                             svc_encrypted_password: None,
+                             topology: None,
+                             update_strategy: None,
+                             canary_percent: None,
+                             canary_soak: None,
+                             update_window: None,
+                             health_check_interval: None,
+                             shutdown_timeout: None,
+                             shutdown_order: None,
+                             hook_timeouts: None,
+                             update_condition: None,
+                             signature: _,
+                             cpuset: None,
+                             nice: None,
+                             priority_class: None,
+                             limits: None,
+                             cpu_limit: None,
+                             memory_limit: None,
+                             pids_limit: None,
+                             no_restart: _, } if remove_binds.is_empty() => {
+                Err(Error::ArgumentError("No fields specified for update".to_string()))
+            }
+            _ => Ok(msg),
         }
     }
 }
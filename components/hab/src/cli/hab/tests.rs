@@ -450,6 +450,30 @@ fn test_hab_svc_load_flag_ordering() {
     assert_eq!(load.pkg_ident.pkg_ident(), pkg_ident);
 }
 
+#[test]
+fn test_hab_svc_load_and_update_sign_with() {
+    let hab = Hab::try_from_iter_with_configopt(&["hab", "svc", "load", "core/redis"]).unwrap();
+    assert_eq!(extract_hab_svc_load(hab).sign_with, None);
+
+    let hab = Hab::try_from_iter_with_configopt(&["hab",
+                                                  "svc",
+                                                  "load",
+                                                  "core/redis",
+                                                  "--sign-with",
+                                                  "acme"]).unwrap();
+    assert_eq!(extract_hab_svc_load(hab).sign_with,
+               Some("acme".to_string()));
+
+    let hab = Hab::try_from_iter_with_configopt(&["hab",
+                                                  "svc",
+                                                  "update",
+                                                  "core/redis",
+                                                  "--sign-with",
+                                                  "acme"]).unwrap();
+    assert_eq!(extract_hab_svc_update(hab).sign_with,
+               Some("acme".to_string()));
+}
+
 #[test]
 fn test_hab_svc_update_empty_binds() {
     let hab = Hab::try_from_iter_with_configopt(&["hab", "svc", "update", "core/redis", "--bind"]).unwrap();
@@ -465,3 +489,28 @@ fn test_hab_svc_update_empty_binds() {
     let update = extract_hab_svc_update(hab);
     assert_eq!(update.bind.unwrap().len(), 1);
 }
+
+#[test]
+fn test_hab_svc_update_bind_add_and_remove() {
+    let hab = Hab::try_from_iter_with_configopt(&["hab",
+                                                  "svc",
+                                                  "update",
+                                                  "core/redis",
+                                                  "--bind-add",
+                                                  "x:y.z",
+                                                  "--bind-remove",
+                                                  "w"]).unwrap();
+    let update = extract_hab_svc_update(hab);
+    assert_eq!(update.bind_add,
+               vec!["x:y.z".parse::<habitat_core::service::ServiceBind>().unwrap()]);
+    assert_eq!(update.bind_remove, vec!["w".to_string()]);
+
+    assert!(Hab::try_from_iter_with_configopt(&["hab",
+                                                "svc",
+                                                "update",
+                                                "core/redis",
+                                                "--bind",
+                                                "x:y.z",
+                                                "--bind-remove",
+                                                "w"]).is_err());
+}
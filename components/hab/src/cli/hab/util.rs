@@ -1,9 +1,19 @@
-use crate::{cli::valid_fully_qualified_ident,
+use crate::{cli::{file_exists,
+                  valid_fully_qualified_ident,
+                  valid_fully_qualified_ident_or_file},
+            command::service::status::HttpGateway,
             config,
-            error::Error};
+            error::Error,
+            CTL_CLIENT_CERTIFICATE_ENVVAR,
+            CTL_CLIENT_KEY_ENVVAR,
+            CTL_SERVER_CA_CERTIFICATE_ENVVAR,
+            CTL_SERVER_NAME_ENVVAR,
+            HTTP_GATEWAY_AUTH_TOKEN_ENVVAR};
 use configopt::{self,
                 ConfigOpt};
 use habitat_common::types::ListenCtlAddr;
+use habitat_sup_client::{CtlGateway,
+                         CtlTls};
 use habitat_core::{crypto::CACHE_KEY_PATH_ENV_VAR,
                    env as henv,
                    fs as hab_core_fs,
@@ -11,14 +21,17 @@ use habitat_core::{crypto::CACHE_KEY_PATH_ENV_VAR,
                    package::PackageIdent,
                    url::{bldr_url_from_env,
                          DEFAULT_BLDR_URL},
+                   util::units::{self,
+                                 UnitValue},
                    AUTH_TOKEN_ENVVAR};
 use lazy_static::lazy_static;
-use std::{ffi::OsString,
+use std::{convert::TryFrom,
+          ffi::OsString,
           fmt,
+          fs,
           io,
           net::{SocketAddr,
                 ToSocketAddrs},
-          num::ParseIntError,
           path::PathBuf,
           str::FromStr,
           time::Duration};
@@ -170,6 +183,16 @@ pub struct FullyQualifiedPkgIdent {
     pkg_ident: PackageIdent,
 }
 
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+#[allow(dead_code)]
+pub struct FullyQualifiedPkgIdentOrFile {
+    /// A fully qualified package identifier (ex: core/busybox-static/1.42.2/20170513215502), or a
+    /// file of newline separated fully qualified package identifiers ('-' reads them from stdin)
+    #[structopt(name = "PKG_IDENT", validator = valid_fully_qualified_ident_or_file)]
+    pkg_ident: String,
+}
+
 #[derive(ConfigOpt, StructOpt, Deserialize, Debug)]
 #[configopt(derive(Serialize, Clone, Debug))]
 #[structopt(no_version)]
@@ -182,10 +205,126 @@ pub struct RemoteSup {
                 parse(try_from_str = ListenCtlAddr::resolve_listen_ctl_addr))]
     #[serde(default)]
     remote_sup: ListenCtlAddr,
+    #[structopt(flatten)]
+    #[serde(flatten)]
+    ctl_tls:    CtlTlsOptions,
 }
 
 impl RemoteSup {
     pub fn to_listen_ctl_addr(&self) -> ListenCtlAddr { self.remote_sup }
+
+    pub fn to_ctl_gateway(&self) -> Result<CtlGateway, Error> {
+        Ok(CtlGateway { addr: self.remote_sup,
+                        tls:  self.ctl_tls.to_ctl_tls()?, })
+    }
+}
+
+#[derive(ConfigOpt, StructOpt, Deserialize, Debug)]
+#[configopt(derive(Serialize, Clone, Debug))]
+#[structopt(no_version)]
+pub struct CtlTlsOptions {
+    /// Connect to the Control Gateway with TLS, verifying its certificate with this CA
+    /// certificate
+    #[structopt(name = "CTL_SERVER_CA_CERTIFICATE",
+                long = "ctl-server-ca-certificate",
+                env = CTL_SERVER_CA_CERTIFICATE_ENVVAR)]
+    #[serde(default)]
+    ctl_server_ca_certificate: Option<PathBuf>,
+    /// The DNS name the Control Gateway's certificate is for (default: localhost)
+    #[structopt(name = "CTL_SERVER_NAME",
+                long = "ctl-server-name",
+                env = CTL_SERVER_NAME_ENVVAR,
+                requires = "CTL_SERVER_CA_CERTIFICATE")]
+    #[serde(default)]
+    ctl_server_name:           Option<String>,
+    /// The certificate to present to a Control Gateway which authenticates its clients
+    #[structopt(name = "CTL_CLIENT_CERTIFICATE",
+                long = "ctl-client-certificate",
+                env = CTL_CLIENT_CERTIFICATE_ENVVAR,
+                requires_all = &["CTL_CLIENT_KEY", "CTL_SERVER_CA_CERTIFICATE"])]
+    #[serde(default)]
+    ctl_client_certificate:    Option<PathBuf>,
+    /// The private key of CTL_CLIENT_CERTIFICATE
+    #[structopt(name = "CTL_CLIENT_KEY",
+                long = "ctl-client-key",
+                env = CTL_CLIENT_KEY_ENVVAR,
+                requires = "CTL_CLIENT_CERTIFICATE")]
+    #[serde(default)]
+    ctl_client_key:            Option<PathBuf>,
+}
+
+impl CtlTlsOptions {
+    /// The TLS settings to connect with, if any were given. Settings which can't be used without
+    /// a CA certificate, or a client certificate without its key, are refused rather than
+    /// connecting in the clear.
+    pub fn to_ctl_tls(&self) -> Result<Option<CtlTls>, Error> {
+        let client_certificate = match (&self.ctl_client_certificate, &self.ctl_client_key) {
+            (Some(cert), Some(key)) => Some((cert.clone(), key.clone())),
+            (None, None) => None,
+            _ => {
+                return Err(Error::ArgumentError(String::from("--ctl-client-certificate and \
+                                                              --ctl-client-key must be given \
+                                                              together")));
+            }
+        };
+        let server_name = self.ctl_server_name.clone();
+        let server_ca_certificate = match self.ctl_server_ca_certificate {
+            Some(ref path) => path.clone(),
+            None if server_name.is_none() && client_certificate.is_none() => return Ok(None),
+            None => {
+                return Err(Error::ArgumentError(String::from("--ctl-server-name and \
+                                                              --ctl-client-certificate require \
+                                                              --ctl-server-ca-certificate")));
+            }
+        };
+        Ok(Some(CtlTls { server_ca_certificate,
+                         server_name,
+                         client_certificate }))
+    }
+}
+
+#[derive(ConfigOpt, StructOpt, Deserialize, Debug)]
+#[configopt(derive(Serialize, Clone, Debug))]
+#[structopt(no_version)]
+pub struct HttpGatewayOptions {
+    /// Query the Supervisor's HTTP Gateway at this URL (ex: http://127.0.0.1:9631) rather than
+    /// its Control Gateway, and print the status of every service as the JSON it replies with
+    #[structopt(name = "HTTP_GATEWAY", long = "http-gateway")]
+    #[serde(default)]
+    http_gateway:         Option<Url>,
+    /// Authenticate with the HTTP Gateway with the bearer token on the first line of this file,
+    /// in place of one set with HAB_SUP_GATEWAY_AUTH_TOKEN
+    #[structopt(name = "HTTP_AUTH_TOKEN_FILE",
+                long = "http-auth-token-file",
+                validator = file_exists,
+                requires = "HTTP_GATEWAY")]
+    #[serde(default)]
+    http_auth_token_file: Option<PathBuf>,
+}
+
+impl HttpGatewayOptions {
+    /// The HTTP Gateway to query, if one was given, with the token read from
+    /// `--http-auth-token-file` or else from HAB_SUP_GATEWAY_AUTH_TOKEN, if either is set.
+    pub fn to_http_gateway(&self) -> Result<Option<HttpGateway>, Error> {
+        let url = match self.http_gateway {
+            Some(ref url) => url.clone(),
+            None => return Ok(None),
+        };
+        let auth_token = match self.http_auth_token_file {
+            Some(ref path) => {
+                let token = fs::read_to_string(path)?.trim().to_string();
+                if token.is_empty() {
+                    return Err(Error::ArgumentError(format!("{} holds no HTTP Gateway \
+                                                             authentication token",
+                                                            path.display())));
+                }
+                Some(token)
+            }
+            None => henv::var(HTTP_GATEWAY_AUTH_TOKEN_ENVVAR).ok()
+                                                             .filter(|token| !token.is_empty()),
+        };
+        Ok(Some(HttpGateway { url, auth_token }))
+    }
 }
 
 pub fn socket_addr_with_default_port<S: AsRef<str>>(addr: S,
@@ -204,8 +343,9 @@ pub fn socket_addr_with_default_port<S: AsRef<str>>(addr: S,
                })
 }
 
+/// A whole number of seconds, which may be given with units (ex: 90s, 5m, 1h).
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
-#[serde(from = "u64", into = "u64")]
+#[serde(try_from = "UnitValue", into = "u64")]
 pub struct DurationProxy(Duration);
 
 impl From<DurationProxy> for u64 {
@@ -224,10 +364,16 @@ impl From<Duration> for DurationProxy {
     fn from(d: Duration) -> Self { Self(d) }
 }
 
+impl TryFrom<UnitValue> for DurationProxy {
+    type Error = habitat_core::Error;
+
+    fn try_from(value: UnitValue) -> Result<Self, Self::Error> { Ok(value.into_secs()?.into()) }
+}
+
 impl FromStr for DurationProxy {
-    type Err = ParseIntError;
+    type Err = habitat_core::Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> { Ok(s.parse::<u64>()?.into()) }
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Ok(units::parse_secs(s)?.into()) }
 }
 
 impl fmt::Display for DurationProxy {
@@ -280,7 +426,11 @@ pub struct ExternalCommandArgsWithHelpAndVersion {
 
 #[cfg(test)]
 mod test {
-    use super::socket_addr_with_default_port;
+    use super::{socket_addr_with_default_port,
+                CtlTlsOptions,
+                HttpGatewayOptions};
+    use std::fs;
+    use tempfile::TempDir;
 
     #[test]
     fn test_socket_addr_with_default_port() {
@@ -290,4 +440,41 @@ mod test {
                    "1.2.3.4:1500".parse().expect(""));
         assert!(socket_addr_with_default_port("an_invalid_address", 89).is_err());
     }
+
+    #[test]
+    fn ctl_tls_options_which_need_a_ca_certificate_are_refused_without_one() {
+        let options = CtlTlsOptions { ctl_server_ca_certificate: None,
+                                      ctl_server_name:           Some("sup.example.com".into()),
+                                      ctl_client_certificate:    None,
+                                      ctl_client_key:            None, };
+        assert!(options.to_ctl_tls().is_err());
+
+        let options = CtlTlsOptions { ctl_server_ca_certificate: Some("ca.pem".into()),
+                                      ..options };
+        let tls = options.to_ctl_tls().unwrap().unwrap();
+        assert_eq!(tls.server_name.as_deref(), Some("sup.example.com"));
+
+        let options = CtlTlsOptions { ctl_client_certificate: Some("cert.pem".into()),
+                                      ..options };
+        assert!(options.to_ctl_tls().is_err());
+    }
+
+    #[test]
+    fn http_gateway_auth_token_is_read_from_its_file() {
+        let dir = TempDir::new().unwrap();
+        let token_file = dir.path().join("token");
+        fs::write(&token_file, "s3cr3t\n").unwrap();
+        let url = "http://127.0.0.1:9631".parse().unwrap();
+        let options = HttpGatewayOptions { http_gateway:         Some(url),
+                                           http_auth_token_file: Some(token_file.clone()), };
+        let gateway = options.to_http_gateway().unwrap().unwrap();
+        assert_eq!(gateway.auth_token.as_deref(), Some("s3cr3t"));
+
+        fs::write(&token_file, "\n").unwrap();
+        assert!(options.to_http_gateway().is_err());
+
+        let options = HttpGatewayOptions { http_gateway:         None,
+                                           http_auth_token_file: None, };
+        assert!(options.to_http_gateway().unwrap().is_none());
+    }
 }
@@ -1,4 +1,5 @@
 pub mod bldr;
+pub mod cache;
 pub mod cli;
 pub mod launcher;
 pub mod origin;
@@ -1,5 +1,6 @@
 pub mod create;
 pub mod demote;
 pub mod destroy;
+pub mod diff;
 pub mod list;
 pub mod promote;
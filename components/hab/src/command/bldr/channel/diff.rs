@@ -0,0 +1,126 @@
+use crate::{api_client::Client,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            hcore::{package::{PackageIdent,
+                              PackageTarget},
+                    ChannelIdent}};
+
+use crate::{error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use std::collections::BTreeMap;
+
+/// The differences between the latest packages of two channels, going from the first to the
+/// second.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct ChannelDiff {
+    /// Packages only in the second channel
+    added:   Vec<String>,
+    /// Packages only in the first channel
+    removed: Vec<String>,
+    /// Packages whose latest release differs between the channels
+    changed: Vec<ChangedPackage>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ChangedPackage {
+    from: String,
+    to:   String,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: Option<&str>,
+                   origin: &str,
+                   from_channel: &ChannelIdent,
+                   to_channel: &ChannelIdent,
+                   target: PackageTarget,
+                   to_json: bool)
+                   -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None).map_err(Error::APIClient)?;
+
+    ui.status(Status::Determining,
+              format!("differences between channels {} and {} of {} for {}.",
+                      from_channel, to_channel, origin, target))?;
+
+    let from = api_client.latest_channel_packages(origin, from_channel, target, token)
+                         .await
+                         .map_err(Error::APIClient)?;
+    let to = api_client.latest_channel_packages(origin, to_channel, target, token)
+                       .await
+                       .map_err(Error::APIClient)?;
+    let diff = diff(from, to);
+
+    if to_json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else if diff == ChannelDiff::default() {
+        println!("Channels {} and {} have the same packages",
+                 from_channel, to_channel);
+    } else {
+        for ident in &diff.added {
+            println!("+ {}", ident);
+        }
+        for ident in &diff.removed {
+            println!("- {}", ident);
+        }
+        for changed in &diff.changed {
+            println!("~ {} -> {}", changed.from, changed.to);
+        }
+    }
+    Ok(())
+}
+
+fn diff(from: Vec<PackageIdent>, to: Vec<PackageIdent>) -> ChannelDiff {
+    let by_name = |idents: Vec<PackageIdent>| {
+        idents.into_iter()
+              .map(|ident| (format!("{}/{}", ident.origin, ident.name), ident))
+              .collect::<BTreeMap<_, _>>()
+    };
+    let from = by_name(from);
+    let mut to = by_name(to);
+
+    let mut diff = ChannelDiff::default();
+    for (name, ident) in from {
+        match to.remove(&name) {
+            Some(other) if other != ident => {
+                diff.changed.push(ChangedPackage { from: ident.to_string(),
+                                                   to:   other.to_string(), })
+            }
+            Some(_) => {}
+            None => diff.removed.push(ident.to_string()),
+        }
+    }
+    diff.added = to.values().map(ToString::to_string).collect();
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn idents(idents: &[&str]) -> Vec<PackageIdent> {
+        idents.iter()
+              .map(|i| PackageIdent::from_str(i).unwrap())
+              .collect()
+    }
+
+    #[test]
+    fn channels_differ_by_added_removed_and_changed_packages() {
+        let stable = idents(&["core/redis/4.0.14/20200101000000",
+                              "core/nginx/1.17.4/20200101000000",
+                              "core/consul/1.6.2/20200101000000"]);
+        let unstable = idents(&["core/redis/5.0.7/20200301000000",
+                                "core/nginx/1.17.4/20200101000000",
+                                "core/haproxy/2.1.2/20200301000000"]);
+        let diff = diff(stable, unstable);
+        assert_eq!(diff.added, vec!["core/haproxy/2.1.2/20200301000000"]);
+        assert_eq!(diff.removed, vec!["core/consul/1.6.2/20200101000000"]);
+        assert_eq!(diff.changed,
+                   vec![ChangedPackage { from: "core/redis/4.0.14/20200101000000".to_string(),
+                                         to:   "core/redis/5.0.7/20200301000000".to_string(), }]);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result,
+            hcore::package::{artifact_cache::CachedArtifact,
+                             ArtifactCache}};
+use chrono::{DateTime,
+             SecondsFormat,
+             Utc};
+use std::io::{self,
+              Write};
+use tabwriter::TabWriter;
+
+const HEADER: &[&str] = &["artifact", "size", "last used"];
+
+/// Lists the artifacts in `cache`, least recently used first.
+pub fn list(cache: &ArtifactCache) -> Result<()> {
+    let artifacts = cache.list()?;
+    print_artifacts(&mut TabWriter::new(io::stdout()), &artifacts)?;
+    Ok(())
+}
+
+/// Removes the least recently used artifacts from `cache` until it holds no more than
+/// `max_size` bytes.
+pub fn prune(ui: &mut UI, cache: &ArtifactCache, max_size: u64) -> Result<()> {
+    ui.begin(format!("Pruning {} to {} bytes", cache.path().display(), max_size))?;
+    let removed = cache.prune(max_size, None)?;
+    for artifact in &removed {
+        ui.status(Status::Deleted, artifact.path.display())?;
+    }
+    ui.end(format!("Removed {} artifact(s), freeing {} bytes.",
+                   removed.len(),
+                   removed.iter().map(|a| a.size).sum::<u64>()))?;
+    Ok(())
+}
+
+fn print_artifacts<W: Write>(out: &mut W, artifacts: &[CachedArtifact]) -> io::Result<()> {
+    writeln!(out, "{}", HEADER.join("\t"))?;
+    for artifact in artifacts {
+        let file_name = artifact.path.file_name().unwrap_or_default();
+        writeln!(out,
+                 "{}\t{}\t{}",
+                 file_name.to_string_lossy(),
+                 artifact.size,
+                 DateTime::<Utc>::from(artifact.last_used).to_rfc3339_opts(SecondsFormat::Secs,
+                                                                           true))?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{path::PathBuf,
+              time::{Duration,
+                     UNIX_EPOCH}};
+
+    #[test]
+    fn artifacts_are_printed_with_their_size_and_last_use() {
+        let path = PathBuf::from("/hab/cache/artifacts/core-redis-x86_64-linux.hart");
+        let artifacts = [CachedArtifact { path,
+                                          size: 1024,
+                                          last_used: UNIX_EPOCH + Duration::from_secs(86_400) }];
+        let mut out = Vec::new();
+        print_artifacts(&mut out, &artifacts).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines,
+                   vec!["artifact\tsize\tlast used",
+                        "core-redis-x86_64-linux.hart\t1024\t1970-01-02T00:00:00Z"]);
+    }
+}
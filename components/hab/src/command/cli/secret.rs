@@ -0,0 +1,45 @@
+use crate::{common::ui::{Glyph,
+                         Status,
+                         UIWriter,
+                         UI},
+            config::{self,
+                     CtlSecretSource},
+            error::{Error,
+                    Result}};
+
+pub fn set(ui: &mut UI, secret: &str) -> Result<()> {
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(Error::ArgumentError(String::from("The control gateway \
+                                                      secret cannot be empty")));
+    }
+    let source = config::store_ctl_secret(secret)?;
+    if source != CtlSecretSource::Keyring {
+        ui.warn(format!("No OS keyring is available, so the control gateway secret is stored \
+                         in plaintext in {}",
+                        source))?;
+    }
+    ui.status(Status::Custom(Glyph::CheckMark, String::from("Stored")),
+              format!("control gateway secret in {}", source))?;
+    Ok(())
+}
+
+pub fn show(ui: &mut UI) -> Result<()> {
+    let (secret, source) = config::ctl_secret_key_with_source(&config::load()?)?;
+    ui.status(Status::Using,
+              format!("control gateway secret from {}", source))?;
+    println!("{}", secret);
+    Ok(())
+}
+
+pub fn clear(ui: &mut UI) -> Result<()> {
+    let cleared = config::clear_ctl_secret()?;
+    if cleared.is_empty() {
+        ui.info("No stored control gateway secret was found")?;
+    }
+    for source in cleared {
+        ui.status(Status::Deleted,
+                  format!("control gateway secret from {}", source))?;
+    }
+    Ok(())
+}
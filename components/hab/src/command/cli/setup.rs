@@ -235,9 +235,7 @@ fn write_cli_config_auth_token(auth_token: &str) -> Result<()> {
 }
 
 fn write_cli_config_ctl_secret(value: &str) -> Result<()> {
-    let mut config = config::load()?;
-    config.ctl_secret = Some(value.to_string());
-    config::save(&config)
+    config::store_ctl_secret(value).map(|_| ())
 }
 
 fn is_origin_in_cache(origin: &str, cache_path: &Path) -> bool {
@@ -253,7 +251,7 @@ fn is_origin_in_cache(origin: &str, cache_path: &Path) -> bool {
 }
 
 fn create_origin(ui: &mut UI, origin: &str, cache_path: &Path) -> Result<()> {
-    let result = command::origin::key::generate::start(ui, &origin, cache_path);
+    let result = command::origin::key::generate::start(ui, &origin, None, cache_path);
     ui.br()?;
     result
 }
@@ -1,7 +1,11 @@
+pub mod bundle;
 pub mod download;
 pub mod export;
 pub mod generate;
 pub mod import;
+pub mod list;
+pub mod revoke;
+pub mod rotate;
 pub mod upload;
 pub mod upload_latest;
 
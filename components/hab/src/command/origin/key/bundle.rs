@@ -0,0 +1,265 @@
+//! Bundles of origin keys, which carry keys from one key cache to another without going through
+//! Builder, such as into an air-gapped environment.
+//!
+//! A bundle is a tarball of key files along with a `MANIFEST` listing each of them by the hash of
+//! its contents. The manifest starts with its format and the origin key revision which signed it,
+//! and its signature is kept in `MANIFEST.sig`:
+//!
+//! ```text
+//! HAB-KEY-BUNDLE-1
+//! core-20160810182414
+//! <hash> core-20160810182414.pub
+//! <hash> core-20160810182414.sig.key
+//! ```
+//!
+//! Importing a bundle checks the signature with the signing key from the key cache, or with the
+//! copy of it in the bundle when the key cache doesn't have it yet. A key which is already in the
+//! key cache with different contents is a conflict, and is left as it is.
+
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::crypto::{hash,
+                            keys::{cache::KeyCache,
+                                   parse_key_str,
+                                   passphrase,
+                                   KeyType,
+                                   PairType},
+                            SigKeyPair}};
+use std::{collections::{BTreeMap,
+                        BTreeSet},
+          fs::{self,
+               File},
+          io::{Read,
+               Write},
+          path::Path};
+use tempfile::TempDir;
+
+const BUNDLE_FORMAT_VERSION: &str = "HAB-KEY-BUNDLE-1";
+const MANIFEST: &str = "MANIFEST";
+const SIGNATURE: &str = "MANIFEST.sig";
+
+/// Writes the public keys of `origins` in `cache`, and their secret keys if `with_secret` is set,
+/// to a bundle at `output`. The bundle is signed with the latest secret key of the first origin.
+pub fn export(ui: &mut UI,
+              origins: &[String],
+              with_secret: bool,
+              output: &Path,
+              cache: &Path)
+              -> Result<()> {
+    ui.begin(format!("Bundling origin keys of {}", origins.join(", ")))?;
+    let signer = SigKeyPair::get_latest_pair_for(&origins[0], cache, Some(PairType::Secret))?;
+
+    let mut files = BTreeMap::new();
+    let mut bundled = BTreeSet::new();
+    for key in KeyCache::new(cache).list_all()? {
+        if key.key_type != KeyType::Sig
+           || !origins.contains(&key.name)
+           || (key.pair_type == PairType::Secret && !with_secret)
+        {
+            continue;
+        }
+        let file_name = key.path.file_name().unwrap().to_string_lossy().into_owned();
+        if key.encrypted {
+            ui.warn(format!("Skipping {}, which is encrypted with a passphrase",
+                            file_name))?;
+            continue;
+        }
+        ui.status(Status::Adding, &file_name)?;
+        files.insert(file_name, fs::read_to_string(&key.path)?);
+        bundled.insert(key.name);
+    }
+    for origin in origins {
+        if !bundled.contains(origin) {
+            return Err(Error::ArgumentError(format!("No keys of {} in {}",
+                                                    origin,
+                                                    cache.display())));
+        }
+    }
+    // The signing key is always bundled, so that the bundle can be checked where it isn't known.
+    let signer_public_key = format!("{}.pub", signer.name_with_rev());
+    if !files.contains_key(&signer_public_key) {
+        files.insert(signer_public_key, signer.to_public_string()?);
+    }
+
+    let manifest = manifest(&signer.name_with_rev(), &files);
+    let signature = base64::encode(&signer.sign_payload(manifest.as_bytes())?);
+    let mut builder = tar::Builder::new(File::create(output)?);
+    for (name, content) in &files {
+        append(&mut builder, name, content.as_bytes())?;
+    }
+    append(&mut builder, MANIFEST, manifest.as_bytes())?;
+    append(&mut builder, SIGNATURE, signature.as_bytes())?;
+    builder.into_inner()?.flush()?;
+    ui.end(format!("Bundled {} origin keys, signed with {}, in {}.",
+                   files.len(),
+                   signer.name_with_rev(),
+                   output.display()))?;
+    Ok(())
+}
+
+/// Adds the origin keys in the bundle at `path` to `cache`, after checking its signature.
+///
+/// # Failures
+///
+/// * Fails with an `Error::ErrorPerKeyFile` listing the keys which conflict with those in `cache`,
+///   or couldn't be added to it, once all of the others have been added.
+pub fn import(ui: &mut UI, path: &Path, cache: &Path) -> Result<()> {
+    ui.begin(format!("Importing origin keys from {}", path.display()))?;
+    let mut files = read_bundle(path)?;
+    let invalid = |reason: &str| {
+        Error::ArgumentError(format!("{} is not a valid key bundle: {}", path.display(), reason))
+    };
+    let manifest = files.remove(MANIFEST)
+                        .ok_or_else(|| invalid("it has no manifest"))?;
+    let signature = files.remove(SIGNATURE)
+                         .ok_or_else(|| invalid("it is not signed"))?;
+    let mut lines = manifest.lines();
+    if lines.next() != Some(BUNDLE_FORMAT_VERSION) {
+        return Err(invalid("its format is unsupported"));
+    }
+    let signer = lines.next()
+                      .ok_or_else(|| invalid("its signer is missing"))?;
+    verify(ui, signer, &manifest, &signature, &files, cache)?;
+    if self::manifest(signer, &files) != manifest {
+        return Err(invalid("its keys do not match its manifest"));
+    }
+
+    let mut errors = BTreeMap::new();
+    for (name, content) in &files {
+        match SigKeyPair::write_file_from_str(content, cache) {
+            Ok((pair, pair_type)) => {
+                ui.status(Status::Added,
+                          format!("{} origin key {}", pair_type, pair.name_with_rev()))?
+            }
+            Err(e) => {
+                ui.warn(format!("Unable to import {}: {}", name, e))?;
+                errors.insert(name.clone(), Error::from(e));
+            }
+        }
+    }
+    ui.end(format!("Imported {} of {} origin keys.",
+                   files.len() - errors.len(),
+                   files.len()))?;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::ErrorPerKeyFile(errors))
+    }
+}
+
+/// The manifest of `files`, signed by the origin key revision `signer`.
+fn manifest(signer: &str, files: &BTreeMap<String, String>) -> String {
+    let mut manifest = format!("{}\n{}\n", BUNDLE_FORMAT_VERSION, signer);
+    for (name, content) in files {
+        manifest.push_str(&format!("{} {}\n", hash::hash_string(content), name));
+    }
+    manifest
+}
+
+/// Checks the signature of `manifest` with the public key of `signer` from `cache`, or failing
+/// that, from `files`.
+fn verify(ui: &mut UI,
+          signer: &str,
+          manifest: &str,
+          signature: &str,
+          files: &BTreeMap<String, String>,
+          cache: &Path)
+          -> Result<()> {
+    let signature = base64::decode(signature.trim()).map_err(|e| {
+                        Error::ArgumentError(format!("Malformed key bundle signature: {}", e))
+                    })?;
+    let key = match SigKeyPair::get_pair_for(signer, cache) {
+        Ok(key) if key.public().is_ok() => key,
+        _ => {
+            let content = files.get(&format!("{}.pub", signer)).ok_or_else(|| {
+                              Error::ArgumentError(format!("The key bundle's signing key {} is \
+                                                            neither in it nor in {}",
+                                                           signer,
+                                                           cache.display()))
+                          })?;
+            ui.warn(format!("{} is not in the key cache, so the bundle is checked with the \
+                             copy of it which the bundle carries",
+                            signer))?;
+            let dir = TempDir::new()?;
+            SigKeyPair::write_file_from_str(content, dir.path())?.0
+        }
+    };
+    key.verify_payload(manifest.as_bytes(), &signature)?;
+    ui.status(Status::Verified, format!("key bundle signed by {}", signer))?;
+    Ok(())
+}
+
+fn append<W: Write>(builder: &mut tar::Builder<W>, name: &str, content: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o600);
+    header.set_cksum();
+    builder.append_data(&mut header, name, content)?;
+    Ok(())
+}
+
+fn read_bundle(path: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for entry in tar::Archive::new(File::open(path)?).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        // Every key is written to the key cache by the name inside it, so a bundle can't be
+        // crafted to write anywhere else; those which don't parse are refused up front.
+        if name != MANIFEST && name != SIGNATURE {
+            parse_key_str(&content)?;
+            if passphrase::is_encrypted(&content) {
+                return Err(Error::ArgumentError(format!("{} holds the encrypted key \
+                                                         {}, which can't be imported",
+                                                        path.display(),
+                                                        name)));
+            }
+        }
+        files.insert(name, content);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundles_carry_keys_between_caches_and_conflicts_are_left_alone() {
+        let source = TempDir::new().unwrap();
+        let signer = SigKeyPair::generate_pair_for_origin("acme");
+        signer.to_pair_files(source.path()).unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("beta");
+        pair.to_pair_files(source.path()).unwrap();
+        let bundle = source.path().join("bundle.tar");
+        export(&mut UI::with_sinks(),
+               &["acme".to_string(), "beta".to_string()],
+               true,
+               &bundle,
+               source.path()).unwrap();
+
+        let destination = TempDir::new().unwrap();
+        import(&mut UI::with_sinks(), &bundle, destination.path()).unwrap();
+        let imported = SigKeyPair::get_pair_for(&pair.name_with_rev(), destination.path()).unwrap();
+        assert_eq!(imported.secret().unwrap(), pair.secret().unwrap());
+
+        // A different key under the same name is not overwritten.
+        let conflicting = TempDir::new().unwrap();
+        let public_key = format!("{}.pub", pair.name_with_rev());
+        let other = SigKeyPair::generate_pair_for_origin("beta");
+        fs::write(conflicting.path().join(&public_key),
+                  other.to_public_string()
+                       .unwrap()
+                       .replace(&other.name_with_rev(), &pair.name_with_rev())).unwrap();
+        match import(&mut UI::with_sinks(), &bundle, conflicting.path()) {
+            Err(Error::ErrorPerKeyFile(errors)) => {
+                assert_eq!(errors.keys().collect::<Vec<_>>(), vec![&public_key])
+            }
+            _ => panic!("expected a conflict"),
+        }
+    }
+}
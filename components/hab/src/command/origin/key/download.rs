@@ -1,5 +1,6 @@
 use crate::{api_client::{BuilderAPIClient,
-                         Client},
+                         Client,
+                         OriginKeyIdent},
             common::{self,
                      command::package::install::{RETRIES,
                                                  RETRY_WAIT},
@@ -8,11 +9,37 @@ use crate::{api_client::{BuilderAPIClient,
                           UI}},
             error::{Error,
                     Result},
-            hcore::crypto::SigKeyPair,
+            hcore::{crypto::{keys::PairType,
+                             SigKeyPair},
+                    fs::{self as hfs,
+                         KeyPermissionPolicy},
+                    origin::Origin},
             PRODUCT,
             VERSION};
+use futures::stream::{self,
+                      StreamExt};
 use retry::delay;
-use std::path::Path;
+use std::{collections::BTreeMap,
+          fs,
+          path::Path};
+
+/// How many origins, or revisions of an origin's public key, are downloaded at once unless
+/// `--jobs` says otherwise.
+pub const DEFAULT_JOBS: usize = 8;
+
+/// Who downloaded keys are readable by: the permissions they are given, and the group they are
+/// handed to, if any.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyAccess<'a> {
+    pub policy: KeyPermissionPolicy,
+    pub group:  Option<&'a str>,
+}
+
+impl KeyAccess<'_> {
+    fn apply(self, key_path: &Path, pair_type: PairType) -> Result<()> {
+        hfs::set_key_permissions(key_path, pair_type, self.policy, self.group).map_err(Error::from)
+    }
+}
 
 #[allow(clippy::too_many_arguments)]
 pub async fn start(ui: &mut UI,
@@ -22,31 +49,132 @@ pub async fn start(ui: &mut UI,
                    secret: bool,
                    encryption: bool,
                    token: Option<&str>,
-                   cache: &Path)
+                   cache: &Path,
+                   access: KeyAccess<'_>,
+                   jobs: usize)
                    -> Result<()> {
     let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
 
     if secret {
-        handle_secret(ui, &api_client, origin, token, cache).await
+        handle_secret(ui, &api_client, origin, token, cache, access).await
     } else if encryption {
-        handle_encryption(ui, &api_client, origin, token, cache).await
+        handle_encryption(ui, &api_client, origin, token, cache, access).await
     } else {
-        handle_public(ui, &api_client, origin, revision, token, cache).await
+        handle_public(ui, &api_client, origin, revision, token, cache, access, jobs).await
+    }
+}
+
+/// Download the keys of each of `origins` concurrently, then report which succeeded and which
+/// failed.
+#[allow(clippy::too_many_arguments)]
+pub async fn start_many(ui: &mut UI,
+                        bldr_url: &str,
+                        origins: &[String],
+                        secret: bool,
+                        encryption: bool,
+                        token: Option<&str>,
+                        cache: &Path,
+                        access: KeyAccess<'_>,
+                        jobs: usize)
+                        -> Result<()> {
+    if (secret || encryption) && token.is_none() {
+        ui.end("No auth token found. You must pass a token to download secret keys.")?;
+        return Ok(());
+    }
+
+    ui.begin(format!("Downloading origin keys for {} origins", origins.len()))?;
+    let results: Vec<(&str, Result<()>)> =
+        stream::iter(origins).map(|origin| {
+                                 download_quietly(bldr_url, origin, secret, encryption, token,
+                                                  cache, access)
+                             })
+                             .buffered(jobs.max(1))
+                             .collect()
+                             .await;
+
+    let mut errors = BTreeMap::new();
+    for (origin, result) in results {
+        match result {
+            Ok(()) => ui.status(Status::Cached, format!("keys for {}", origin))?,
+            Err(e) => {
+                ui.warn(format!("Unable to download keys for {}: {}", origin, e))?;
+                errors.insert(origin.to_string(), e);
+            }
+        }
+    }
+    ui.end(format!("Downloaded keys for {} of {} origins.",
+                   origins.len() - errors.len(),
+                   origins.len()))?;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::ErrorPerOrigin(errors))
+    }
+}
+
+/// Download the keys of `origin` without reporting progress, since progress output from
+/// concurrent downloads would be interleaved.
+async fn download_quietly<'a>(bldr_url: &str,
+                              origin: &'a str,
+                              secret: bool,
+                              encryption: bool,
+                              token: Option<&str>,
+                              cache: &Path,
+                              access: KeyAccess<'_>)
+                              -> (&'a str, Result<()>) {
+    let mut ui = UI::with_sinks();
+    // `jobs` origins are already being downloaded at once, so fetch each one's revisions in turn.
+    let result = start(&mut ui,
+                       bldr_url,
+                       origin,
+                       None,
+                       secret,
+                       encryption,
+                       token,
+                       cache,
+                       access,
+                       1).await;
+    (origin, result)
+}
+
+/// Read the origins listed in `path`, one per line. Blank lines and lines starting with `#` are
+/// ignored.
+pub fn origins_from_file(path: &Path) -> Result<Vec<String>> {
+    let mut origins = Vec::new();
+    for line in fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        line.parse::<Origin>()?;
+        origins.push(line.to_string());
     }
+    Ok(origins)
 }
 
+/// Download the public origin key `revision`, or else every revision of the public key of
+/// `origin`, `jobs` at a time.
+///
+/// # Failures
+///
+/// * Fails with an `Error::ErrorPerKeyRevision` listing the revisions which couldn't be downloaded,
+///   once all of the others have been.
+#[allow(clippy::too_many_arguments)]
 async fn handle_public(ui: &mut UI,
                        api_client: &BuilderAPIClient,
                        origin: &str,
                        revision: Option<&str>,
                        token: Option<&str>,
-                       cache: &Path)
+                       cache: &Path,
+                       access: KeyAccess<'_>,
+                       jobs: usize)
                        -> Result<()> {
     match revision {
         Some(revision) => {
             let nwr = format!("{}-{}", origin, revision);
             ui.begin(format!("Downloading public origin key {}", &nwr))?;
-            match download_key(ui, api_client, &nwr, origin, revision, token, cache).await {
+            match download_key(ui, api_client, &nwr, origin, revision, token, cache, access).await
+            {
                 Ok(()) => {
                     let msg = format!("Download of {} public origin key completed.", nwr);
                     ui.end(msg)?;
@@ -63,18 +191,8 @@ async fn handle_public(ui: &mut UI,
                     Ok(())
                 }
                 Ok(keys) => {
-                    for key in keys {
-                        let nwr = format!("{}-{}", key.origin, key.revision);
-                        download_key(ui,
-                                     api_client,
-                                     &nwr,
-                                     &key.origin,
-                                     &key.revision,
-                                     token,
-                                     cache).await?;
-                    }
-                    ui.end(format!("Download of {} public origin keys completed.", &origin))?;
-                    Ok(())
+                    download_revisions(ui, api_client, origin, keys, token, cache, access,
+                                       jobs).await
                 }
                 Err(e) => Err(Error::from(e)),
             }
@@ -82,11 +200,76 @@ async fn handle_public(ui: &mut UI,
     }
 }
 
+/// Download each of `keys`, the revisions of the public key of `origin`, reporting them as they
+/// finish.
+#[allow(clippy::too_many_arguments)]
+async fn download_revisions(ui: &mut UI,
+                            api_client: &BuilderAPIClient,
+                            origin: &str,
+                            keys: Vec<OriginKeyIdent>,
+                            token: Option<&str>,
+                            cache: &Path,
+                            access: KeyAccess<'_>,
+                            jobs: usize)
+                            -> Result<()> {
+    let total = keys.len();
+    let mut results = stream::iter(keys).map(|key| {
+                                            download_revision_quietly(api_client, key, token,
+                                                                      cache, access)
+                                        })
+                                        .buffer_unordered(jobs.max(1));
+
+    let mut done = 0;
+    let mut errors = BTreeMap::new();
+    while let Some((nwr, result)) = results.next().await {
+        done += 1;
+        match result {
+            Ok(()) => ui.status(Status::Cached, format!("{} ({}/{})", nwr, done, total))?,
+            Err(e) => {
+                ui.warn(format!("Unable to download {} ({}/{}): {}", nwr, done, total, e))?;
+                errors.insert(nwr, e);
+            }
+        }
+    }
+    if errors.is_empty() {
+        ui.end(format!("Download of {} public origin keys completed.", &origin))?;
+        Ok(())
+    } else {
+        ui.end(format!("Downloaded {} of {} public origin keys for {}.",
+                       total - errors.len(),
+                       total,
+                       origin))?;
+        Err(Error::ErrorPerKeyRevision(errors))
+    }
+}
+
+/// Download one revision of a public origin key without reporting progress, for the same reason
+/// as `download_quietly`.
+async fn download_revision_quietly(api_client: &BuilderAPIClient,
+                                   key: OriginKeyIdent,
+                                   token: Option<&str>,
+                                   cache: &Path,
+                                   access: KeyAccess<'_>)
+                                   -> (String, Result<()>) {
+    let nwr = format!("{}-{}", key.origin, key.revision);
+    let mut ui = UI::with_sinks();
+    let result = download_key(&mut ui,
+                              api_client,
+                              &nwr,
+                              &key.origin,
+                              &key.revision,
+                              token,
+                              cache,
+                              access).await;
+    (nwr, result)
+}
+
 async fn handle_secret(ui: &mut UI,
                        api_client: &BuilderAPIClient,
                        origin: &str,
                        token: Option<&str>,
-                       cache: &Path)
+                       cache: &Path,
+                       access: KeyAccess<'_>)
                        -> Result<()> {
     if token.is_none() {
         ui.end("No auth token found. You must pass a token to download secret keys.")?;
@@ -94,7 +277,7 @@ async fn handle_secret(ui: &mut UI,
     }
 
     ui.begin(format!("Downloading secret origin keys for {}", origin))?;
-    download_secret_key(ui, &api_client, origin, token.unwrap(), cache).await?; // unwrap is safe because we already checked it above
+    download_secret_key(ui, &api_client, origin, token.unwrap(), cache, access).await?; // unwrap is safe because we already checked it above
     ui.end(format!("Download of {} secret origin keys completed.", &origin))?;
     Ok(())
 }
@@ -103,7 +286,8 @@ async fn handle_encryption(ui: &mut UI,
                            api_client: &BuilderAPIClient,
                            origin: &str,
                            token: Option<&str>,
-                           cache: &Path)
+                           cache: &Path,
+                           access: KeyAccess<'_>)
                            -> Result<()> {
     if token.is_none() {
         ui.end("No auth token found. You must pass a token to download secret keys.")?;
@@ -111,7 +295,7 @@ async fn handle_encryption(ui: &mut UI,
     }
 
     ui.begin(format!("Downloading public encryption origin key for {}", origin))?;
-    download_public_encryption_key(ui, &api_client, origin, token.unwrap(), cache).await?; // unwrap is safe because we already checked it above
+    download_public_encryption_key(ui, &api_client, origin, token.unwrap(), cache, access).await?; // unwrap is safe because we already checked it above
     ui.end(format!("Download of {} public encryption keys completed.", &origin))?;
     Ok(())
 }
@@ -120,16 +304,17 @@ pub async fn download_public_encryption_key(ui: &mut UI,
                                             api_client: &BuilderAPIClient,
                                             name: &str,
                                             token: &str,
-                                            cache: &Path)
+                                            cache: &Path,
+                                            access: KeyAccess<'_>)
                                             -> Result<()> {
-    retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES), async {
+    let key_path = retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES), async {
         ui.status(Status::Downloading, "latest public encryption key")?;
         let key_path =
             api_client.fetch_origin_public_encryption_key(name, token, cache, ui.progress())
                       .await?;
         ui.status(Status::Cached,
                   key_path.file_name().unwrap().to_str().unwrap() /* lol */)?;
-        Ok::<_, Error>(())
+        Ok::<_, Error>(key_path)
     }).await
       .map_err(|_| {
           Error::from(common::error::Error::DownloadFailed(format!("We tried {} times but could \
@@ -137,22 +322,24 @@ pub async fn download_public_encryption_key(ui: &mut UI,
                                                                     public encryption key. \
                                                                     Giving up.",
                                                                    RETRIES,)))
-      })
+      })?;
+    access.apply(&key_path, PairType::Public)
 }
 
 async fn download_secret_key(ui: &mut UI,
                              api_client: &BuilderAPIClient,
                              name: &str,
                              token: &str,
-                             cache: &Path)
+                             cache: &Path,
+                             access: KeyAccess<'_>)
                              -> Result<()> {
-    retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES), async {
+    let key_path = retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES), async {
         ui.status(Status::Downloading, "latest secret key")?;
         let key_path = api_client.fetch_secret_origin_key(name, token, cache, ui.progress())
                                  .await?;
         ui.status(Status::Cached,
                   key_path.file_name().unwrap().to_str().unwrap() /* lol */)?;
-        Ok::<_, Error>(())
+        Ok::<_, Error>(key_path)
     }).await
       .map_err(|_| {
           Error::from(common::error::Error::DownloadFailed(format!("We tried {} times but could \
@@ -160,27 +347,30 @@ async fn download_secret_key(ui: &mut UI,
                                                                     secret origin key. Giving \
                                                                     up.",
                                                                    RETRIES,)))
-      })
+      })?;
+    access.apply(&key_path, PairType::Secret)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn download_key(ui: &mut UI,
                       api_client: &BuilderAPIClient,
                       nwr: &str,
                       name: &str,
                       rev: &str,
                       token: Option<&str>,
-                      cache: &Path)
+                      cache: &Path,
+                      access: KeyAccess<'_>)
                       -> Result<()> {
     if SigKeyPair::get_public_key_path(&nwr, &cache).is_ok() {
         ui.status(Status::Using, &format!("{} in {}", nwr, cache.display()))?;
         Ok(())
     } else {
-        retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES), async {
+        let key_path = retry::retry_future!(delay::Fixed::from(RETRY_WAIT).take(RETRIES), async {
             ui.status(Status::Downloading, &nwr)?;
-            api_client.fetch_origin_key(name, rev, token, cache, ui.progress())
-                      .await?;
+            let key_path = api_client.fetch_origin_key(name, rev, token, cache, ui.progress())
+                                     .await?;
             ui.status(Status::Cached, &format!("{} to {}", nwr, cache.display()))?;
-            Ok::<_, Error>(())
+            Ok::<_, Error>(key_path)
         }).await
           .map_err(|_| {
               Error::from(common::error::Error::DownloadFailed(format!("We tried {} times but \
@@ -188,6 +378,7 @@ async fn download_key(ui: &mut UI,
                                                                         {}/{} origin key. \
                                                                         Giving up.",
                                                                        RETRIES, &name, &rev)))
-          })
+          })?;
+        access.apply(&key_path, PairType::Public)
     }
 }
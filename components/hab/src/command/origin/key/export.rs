@@ -2,16 +2,31 @@ use std::{fs::File,
           io,
           path::Path};
 
-use crate::hcore::crypto::{keys::PairType,
+use crate::hcore::crypto::{keys::{KeyFormat,
+                                  PairType},
                            SigKeyPair};
 
 use crate::error::Result;
 
-pub fn start(origin: &str, pair_type: PairType, cache: &Path) -> Result<()> {
+pub fn start(origin: &str, pair_type: PairType, format: KeyFormat, cache: &Path) -> Result<()> {
     let latest = SigKeyPair::get_latest_pair_for(origin, cache, Some(pair_type))?;
+    match format {
+        KeyFormat::Habitat => stream_key_file(&latest, pair_type, cache),
+        KeyFormat::Pem => {
+            print!("{}", latest.to_pem(pair_type)?);
+            Ok(())
+        }
+        KeyFormat::Jwk => {
+            println!("{}", latest.to_jwk(pair_type)?);
+            Ok(())
+        }
+    }
+}
+
+fn stream_key_file(pair: &SigKeyPair, pair_type: PairType, cache: &Path) -> Result<()> {
     let path = match pair_type {
-        PairType::Public => SigKeyPair::get_public_key_path(&latest.name_with_rev(), cache)?,
-        PairType::Secret => SigKeyPair::get_secret_key_path(&latest.name_with_rev(), cache)?,
+        PairType::Public => SigKeyPair::get_public_key_path(&pair.name_with_rev(), cache)?,
+        PairType::Secret => SigKeyPair::get_secret_key_path(&pair.name_with_rev(), cache)?,
     };
     let mut file = File::open(&path)?;
     debug!("Streaming file contents of {} {} to standard out",
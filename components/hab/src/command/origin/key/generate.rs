@@ -2,20 +2,34 @@ use std::path::Path;
 
 use crate::{common::ui::{UIWriter,
                          UI},
-            hcore::{crypto::SigKeyPair,
+            hcore::{crypto::{keys::Passphrase,
+                             SigKeyPair},
                     package::ident,
                     Error::InvalidOrigin}};
 
 use crate::error::{Error,
                    Result};
 
-pub fn start(ui: &mut UI, origin: &str, cache: &Path) -> Result<()> {
+pub fn start(ui: &mut UI,
+             origin: &str,
+             passphrase: Option<&Passphrase>,
+             cache: &Path)
+             -> Result<()> {
+    generate(ui, origin, passphrase, cache).map(|_| ())
+}
+
+/// Generates a new revision of `origin`'s key pair in `cache`, returning it.
+pub fn generate(ui: &mut UI,
+                origin: &str,
+                passphrase: Option<&Passphrase>,
+                cache: &Path)
+                -> Result<SigKeyPair> {
     if ident::is_valid_origin_name(origin) {
         ui.begin(format!("Generating origin key for {}", &origin))?;
         let pair = SigKeyPair::generate_pair_for_origin(origin);
-        pair.to_pair_files(cache)?;
+        pair.to_pair_files_with_passphrase(cache, passphrase)?;
         ui.end(format!("Generated origin key pair {}.", &pair.name_with_rev()))?;
-        Ok(())
+        Ok(pair)
     } else {
         Err(Error::from(InvalidOrigin(origin.to_string())))
     }
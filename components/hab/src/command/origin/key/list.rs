@@ -0,0 +1,90 @@
+use crate::{api_client::Client,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result,
+            hcore::crypto::keys::{cache::{KeyCache,
+                                          KeyMetadata},
+                                  KeyType},
+            PRODUCT,
+            VERSION};
+use std::{io::{self,
+               Write},
+          path::Path};
+use tabwriter::TabWriter;
+use tempfile::TempDir;
+
+const HEADER: &[&str] = &["origin", "revision", "type", "fingerprint"];
+
+/// Lists the origin keys in the key cache at `cache`, optionally only those of `origin`.
+pub fn start(origin: Option<&str>, cache: &Path) -> Result<()> {
+    let keys = KeyCache::new(cache).list_all()?
+                                   .filter(|k| k.key_type == KeyType::Sig)
+                                   .filter(|k| origin.map_or(true, |o| k.name == o));
+    print_keys(&mut TabWriter::new(io::stdout()), keys)?;
+    Ok(())
+}
+
+/// Lists the public keys of `origin` on Builder. Builder does not report fingerprints, so each
+/// key is downloaded to a temporary directory, leaving the key cache untouched.
+pub async fn start_remote(ui: &mut UI,
+                          bldr_url: &str,
+                          origin: &str,
+                          token: Option<&str>)
+                          -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+    let idents = api_client.show_origin_keys(origin).await?;
+    if idents.is_empty() {
+        ui.warn(format!("No public keys for {} on {}", origin, bldr_url))?;
+        return Ok(());
+    }
+
+    let dir = TempDir::new()?;
+    for ident in idents {
+        ui.status(Status::Downloading,
+                  format!("{}-{}", ident.origin, ident.revision))?;
+        api_client.fetch_origin_key(&ident.origin, &ident.revision, token, dir.path(), None)
+                  .await?;
+    }
+    print_keys(&mut TabWriter::new(io::stdout()),
+               KeyCache::new(dir.path()).list_all()?)?;
+    Ok(())
+}
+
+fn print_keys<W: Write>(out: &mut W, keys: impl Iterator<Item = KeyMetadata>) -> io::Result<()> {
+    writeln!(out, "{}", HEADER.join("\t"))?;
+    for key in keys {
+        writeln!(out,
+                 "{}\t{}\t{}\t{}",
+                 key.name, key.rev, key.pair_type, key.fingerprint)?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hcore::crypto::SigKeyPair;
+
+    #[test]
+    fn keys_are_printed_with_their_fingerprint() {
+        let cache = TempDir::new().unwrap();
+        SigKeyPair::generate_pair_for_origin("unicorn").to_pair_files(cache.path())
+                                                       .unwrap();
+
+        let keys = KeyCache::new(cache.path()).list_all()
+                                              .unwrap()
+                                              .collect::<Vec<_>>();
+        let mut out = Vec::new();
+        print_keys(&mut out, keys.clone().into_iter()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "origin\trevision\ttype\tfingerprint");
+        assert_eq!(lines.len(), 3);
+        for (line, key) in lines[1..].iter().zip(&keys) {
+            assert_eq!(*line,
+                       format!("unicorn\t{}\t{}\t{}",
+                               key.rev, key.pair_type, key.fingerprint));
+        }
+    }
+}
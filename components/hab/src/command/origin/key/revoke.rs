@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::crypto::{keys::{cache::KeyCache,
+                                   parse_name_with_rev,
+                                   revocation::{KeyStatus,
+                                                RevocationList},
+                                   KeyType,
+                                   PairType},
+                            SigKeyPair}};
+
+/// Revokes the origin key revision `name_with_rev` in its origin's revocation list in `cache`,
+/// creating the list if the origin has none. The list is signed with the latest of the origin's
+/// secret keys which it still trusts, so a key can't revoke itself.
+pub fn start(ui: &mut UI, name_with_rev: &str, reason: &str, cache: &Path) -> Result<()> {
+    let (origin, _) = parse_name_with_rev(name_with_rev)?;
+    ui.begin(format!("Revoking origin key {}", name_with_rev))?;
+    let mut list =
+        RevocationList::load(&origin, cache)?.unwrap_or_else(|| RevocationList::new(&origin));
+    list.revoke(name_with_rev, reason)?;
+    list.reissue();
+    let signer = signing_key(&origin, &list, cache)?;
+    RevocationList::install(&list.to_signed_string(&signer)?, cache)?;
+    ui.status(Status::Signed,
+              format!("revocation list for {} with {}",
+                      origin,
+                      signer.name_with_rev()))?;
+    ui.end(format!("Revoked origin key {}; {} revision(s) of {} are now revoked.",
+                   name_with_rev,
+                   list.revoked_count(),
+                   origin))?;
+    ui.para(&format!("The list is at {}. Publish it where Supervisors fetch it with \
+                      --key-revocation-url so that they stop trusting the key too.",
+                     RevocationList::path_for(&origin, cache).display()))?;
+    Ok(())
+}
+
+/// The newest unencrypted secret key of `origin` which `list` still trusts.
+fn signing_key(origin: &str, list: &RevocationList, cache: &Path) -> Result<SigKeyPair> {
+    let trusted = |name_with_rev: &str| {
+        !matches!(list.status(name_with_rev),
+                  KeyStatus::Revoked(_) | KeyStatus::Expired(_))
+    };
+    let candidate =
+        KeyCache::new(cache).list_all()?
+                            .filter(|key| key.key_type == KeyType::Sig && key.name == origin)
+                            .filter(|key| key.pair_type == PairType::Secret && !key.encrypted)
+                            .find(|key| trusted(&key.name_with_rev()));
+    match candidate {
+        Some(key) => Ok(SigKeyPair::get_pair_for(&key.name_with_rev(), cache)?),
+        None => {
+            let msg = format!("No other secret key of {} in {} can sign its revocation list. \
+                               Generate a new revision with `hab origin key generate {}` first.",
+                              origin,
+                              cache.display(),
+                              origin);
+            Err(Error::ArgumentError(msg))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn a_key_is_revoked_by_a_newer_one_and_not_by_itself() {
+        let cache = TempDir::new().unwrap();
+        let new = SigKeyPair::generate_pair_for_origin("acme");
+        let old = "acme-20160101000000";
+        for content in &[new.to_public_string().unwrap(),
+                         new.to_secret_string().unwrap()]
+        {
+            SigKeyPair::write_file_from_str(&content.replace(&new.name_with_rev(), old),
+                                            cache.path()).unwrap();
+        }
+        assert!(start(&mut UI::with_sinks(), old, "leaked", cache.path()).is_err());
+
+        new.to_pair_files(cache.path()).unwrap();
+        start(&mut UI::with_sinks(), old, "leaked", cache.path()).unwrap();
+        let keys = KeyCache::new(cache.path());
+        assert!(keys.is_revoked(old).unwrap());
+        assert!(!keys.is_revoked(&new.name_with_rev()).unwrap());
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use crate::{command::pkg::resign,
+            common::ui::{UIWriter,
+                         UI},
+            error::Result,
+            hcore::crypto::{keys::{parse_name_with_rev,
+                                   PairType,
+                                   Passphrase},
+                            SigKeyPair}};
+
+use super::{generate,
+            upload_latest};
+
+/// Generate a new revision of `origin`'s key and upload it, then, if `re_sign_dir` is given,
+/// re-sign the artifacts in it which were signed with the previous revision. A summary of the
+/// rotation is printed at the end; when artifacts were not re-signed, it shows the
+/// `hab pkg re-sign` invocation which would do it.
+#[allow(clippy::too_many_arguments)]
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: &str,
+                   origin: &str,
+                   with_secret: bool,
+                   passphrase: Option<&Passphrase>,
+                   re_sign_dir: Option<&Path>,
+                   cache: &Path)
+                   -> Result<()> {
+    // Only the public key is needed to name the previous revision, and its secret key may be
+    // encrypted.
+    let previous = SigKeyPair::get_latest_pair_for(origin, cache, Some(PairType::Public)).ok();
+    let latest = generate::generate(ui, origin, passphrase, cache)?;
+    upload_latest::start(ui, bldr_url, token, origin, with_secret, cache).await?;
+    let resigned = match (&previous, re_sign_dir) {
+        (Some(previous), Some(dir)) => {
+            Some(resign::resign_signed_with(ui, previous.name_with_rev(), &latest, dir, cache)?)
+        }
+        _ => None,
+    };
+
+    ui.br()?;
+    ui.info(format!("Rotated origin key {}:", origin))?;
+    ui.info(format!("    new revision:  {}", latest.name_with_rev()))?;
+    ui.info(format!("    uploaded to:   {} ({})",
+                    bldr_url,
+                    if with_secret {
+                        "public and secret keys"
+                    } else {
+                        "public key"
+                    }))?;
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            ui.info("    re-signed:     nothing; there was no previous revision")?;
+            return Ok(());
+        }
+    };
+    ui.info(format!("    previous:      {}", previous.name_with_rev()))?;
+    match (resigned, re_sign_dir) {
+        (Some(count), Some(dir)) => {
+            ui.info(format!("    re-signed:     {} artifact(s) in {}",
+                            count,
+                            dir.display()))?;
+        }
+        _ => {
+            let (_, from_rev) = parse_name_with_rev(previous.name_with_rev())?;
+            let (_, to_rev) = parse_name_with_rev(latest.name_with_rev())?;
+            ui.info("    re-signed:     nothing; artifacts can be re-signed with the new key, \
+                     without being rebuilt, by running:")?;
+            ui.info(format!("        hab pkg re-sign --origin {} --from-rev {} --to-rev {} \
+                             <DIR>",
+                            origin, from_rev, to_rev))?;
+        }
+    }
+    Ok(())
+}
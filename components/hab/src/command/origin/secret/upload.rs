@@ -1,4 +1,5 @@
-use super::super::key::download::download_public_encryption_key;
+use super::super::key::download::{download_public_encryption_key,
+                                  KeyAccess};
 use crate::{api_client::Client,
             common::ui::{Status,
                          UIWriter,
@@ -25,7 +26,12 @@ pub async fn start(ui: &mut UI,
         Ok(key) => key,
         Err(_) => {
             debug!("Didn't find public encryption key in cache path");
-            download_public_encryption_key(ui, &api_client, origin, token, cache).await?;
+            download_public_encryption_key(ui,
+                                           &api_client,
+                                           origin,
+                                           token,
+                                           cache,
+                                           KeyAccess::default()).await?;
             BoxKeyPair::get_latest_pair_for(origin, cache)?
         }
     };
@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod binlink;
 pub mod build;
 pub mod bulkupload;
@@ -16,12 +17,30 @@ pub mod list;
 pub mod path;
 pub mod promote;
 pub mod provides;
+pub mod resign;
 pub mod search;
+pub mod shell;
 pub mod sign;
 pub mod uninstall;
+pub mod upgrade_hart;
 pub mod upload;
 pub mod verify;
 
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::package::PackageIdent};
+use futures::stream::{self,
+                      StreamExt};
+use std::{collections::HashMap,
+          future::Future};
+
+/// The number of packages operated on at once by commands which accept
+/// many package identifiers.
+pub const DEFAULT_BULK_CONCURRENCY: usize = 8;
+
 /// Used in commands like uninstall which provide a --dry-run option
 #[derive(Clone, Copy)]
 pub enum ExecutionStrategy {
@@ -48,3 +67,45 @@ pub enum DependencyRelation {
     Requires,
     Supports,
 }
+
+/// Run `op` for each of `idents`, with at most `concurrency` operations
+/// in flight at once. The outcome for each ident is reported as soon as
+/// it is known, using `done` for those that succeed.
+///
+/// # Failures
+///
+/// * Fails with an `Error::ErrorPerIdent` if `op` failed for any ident.
+pub async fn for_each_ident<F, Fut>(ui: &mut UI,
+                                    idents: Vec<PackageIdent>,
+                                    concurrency: usize,
+                                    done: fn() -> Status,
+                                    op: F)
+                                    -> Result<()>
+    where F: Fn(PackageIdent) -> Fut,
+          Fut: Future<Output = Result<()>>
+{
+    let total = idents.len();
+    let mut results = stream::iter(idents).map(|ident| {
+                                              let result = op(ident.clone());
+                                              async move { (ident, result.await) }
+                                          })
+                                          .buffer_unordered(concurrency.max(1));
+
+    let mut errors = HashMap::new();
+    while let Some((ident, result)) = results.next().await {
+        match result {
+            Ok(()) => ui.status(done(), &ident)?,
+            Err(e) => {
+                ui.warn(format!("{}: {}", ident, e))?;
+                errors.insert(ident, e);
+            }
+        }
+    }
+
+    ui.info(format!("{} of {} packages succeeded", total - errors.len(), total))?;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from(errors))
+    }
+}
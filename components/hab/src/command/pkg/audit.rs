@@ -0,0 +1,241 @@
+//! Audits the packages installed in `/hab/pkgs`, checking that their files still match the
+//! checksums of their FILES manifests and that nobody but their owner can change them.
+//!
+//! With `--quarantine`, the binlinks to the binaries of packages which have drifted are removed,
+//! so that they are no longer run from the `PATH`.
+
+use std::{path::{Path,
+                 PathBuf},
+          result};
+
+use crate::{command::pkg::binlink,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::{fs as hfs,
+                    package::{list,
+                              PackageIdent,
+                              PackageInstall}}};
+
+/// Audits the installed packages matching `ident`, or every installed package when it is `None`,
+/// removing the binlinks in `quarantine` to the binaries of those which have drifted. The outcome
+/// is printed as a summary, on stdout as JSON if `to_json` is set, and fails if any package has
+/// drifted.
+pub fn start(ui: &mut UI,
+             ident: Option<&PackageIdent>,
+             fs_root_path: &Path,
+             quarantine: Option<&Path>,
+             to_json: bool)
+             -> Result<()> {
+    let pkg_root = hfs::pkg_root_path(Some(fs_root_path));
+    let idents = match ident {
+        Some(ident) => list::package_list_for_ident(&pkg_root, ident)?,
+        None if pkg_root.is_dir() => list::all_packages(&pkg_root)?,
+        None => Vec::new(),
+    };
+    if !to_json {
+        ui.begin(format!("Auditing {} installed package(s)", idents.len()))?;
+    }
+    let mut reports = idents.iter()
+                            .map(|ident| audit_one(ident, fs_root_path))
+                            .collect::<Vec<_>>();
+    reports.sort_by(|a, b| a.ident.cmp(&b.ident));
+    if let Some(dest_path) = quarantine {
+        for report in reports.iter_mut()
+                             .filter(|r| r.outcome == Outcome::Drifted)
+        {
+            report.unlinked = binlink::unlink_pkg(&report.ident, dest_path, fs_root_path)?;
+        }
+    }
+    let summary = Summary::new(reports);
+    if to_json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        summary.report(ui)?;
+    }
+    if summary.drifted > 0 {
+        return Err(Error::PackagesDrifted(summary.drifted, summary.packages.len()));
+    }
+    Ok(())
+}
+
+/// The outcome of auditing one package.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Intact,
+    /// Files of the package don't match its FILES manifest, or can be changed by others than
+    /// their owner.
+    Drifted,
+    /// The package has no FILES manifest, or couldn't be read.
+    Unverifiable,
+}
+
+/// A file of a package which has drifted from what was installed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Finding {
+    path:    PathBuf,
+    problem: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PackageReport {
+    #[serde(serialize_with = "serialize_ident")]
+    ident:    PackageIdent,
+    outcome:  Outcome,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    findings: Vec<Finding>,
+    /// The binlinks removed by `--quarantine`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unlinked: Vec<PathBuf>,
+    error:    Option<String>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    intact:       usize,
+    drifted:      usize,
+    unverifiable: usize,
+    packages:     Vec<PackageReport>,
+}
+
+impl Summary {
+    fn new(packages: Vec<PackageReport>) -> Self {
+        let count = |outcome| packages.iter().filter(|p| p.outcome == outcome).count();
+        Summary { intact: count(Outcome::Intact),
+                  drifted: count(Outcome::Drifted),
+                  unverifiable: count(Outcome::Unverifiable),
+                  packages }
+    }
+
+    fn report(&self, ui: &mut UI) -> Result<()> {
+        for package in &self.packages {
+            match package.outcome {
+                Outcome::Intact => ui.status(Status::Verified, &package.ident)?,
+                Outcome::Drifted => {
+                    ui.warn(format!("{} has drifted", package.ident))?;
+                    for finding in &package.findings {
+                        ui.warn(format!("  {}: {}", finding.path.display(), finding.problem))?;
+                    }
+                    for link in &package.unlinked {
+                        ui.warn(format!("  removed binlink {}", link.display()))?;
+                    }
+                }
+                Outcome::Unverifiable => {
+                    ui.warn(format!("{} could not be audited: {}",
+                                    package.ident,
+                                    package.error.as_deref().unwrap_or_default()))?
+                }
+            }
+        }
+        ui.end(format!("Audited {} package(s); {} intact, {} drifted, {} could not be audited.",
+                       self.packages.len(),
+                       self.intact,
+                       self.drifted,
+                       self.unverifiable))?;
+        Ok(())
+    }
+}
+
+fn serialize_ident<S>(ident: &PackageIdent, serializer: S) -> result::Result<S::Ok, S::Error>
+    where S: serde::Serializer
+{
+    serializer.collect_str(ident)
+}
+
+fn audit_one(ident: &PackageIdent, fs_root_path: &Path) -> PackageReport {
+    let report = |outcome, findings, error: Option<String>| {
+        PackageReport { ident: ident.clone(),
+                        outcome,
+                        findings,
+                        unlinked: Vec::new(),
+                        error }
+    };
+    let package = match PackageInstall::load(ident, Some(fs_root_path)) {
+        Ok(package) => package,
+        Err(e) => return report(Outcome::Unverifiable, Vec::new(), Some(e.to_string())),
+    };
+    let mut findings = match package.tampered_files() {
+        Ok(tampered) => {
+            tampered.into_iter()
+                    .map(|file| {
+                        Finding { path:    file.path,
+                                  problem: file.problem.to_string(), }
+                    })
+                    .collect::<Vec<_>>()
+        }
+        // Packages built before FILES manifests were introduced can't be audited.
+        Err(e) => return report(Outcome::Unverifiable, Vec::new(), Some(e.to_string())),
+    };
+    findings.extend(ownership_findings(package.installed_path()));
+    if findings.is_empty() {
+        report(Outcome::Intact, findings, None)
+    } else {
+        report(Outcome::Drifted, findings, None)
+    }
+}
+
+/// The files of the package installed at `installed_path` which aren't owned by the owner of the
+/// package's directory, or which can be written by its group or others.
+#[cfg(unix)]
+fn ownership_findings(installed_path: &Path) -> Vec<Finding> {
+    use std::os::unix::fs::MetadataExt;
+    use walkdir::WalkDir;
+
+    let owner = match installed_path.metadata() {
+        Ok(metadata) => metadata.uid(),
+        Err(_) => return Vec::new(),
+    };
+    let mut findings = Vec::new();
+    for entry in WalkDir::new(installed_path).into_iter().filter_map(|e| e.ok()) {
+        if entry.path_is_symlink() {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let problem = if metadata.uid() != owner {
+            format!("owned by uid {} rather than {}", metadata.uid(), owner)
+        } else if metadata.mode() & 0o022 != 0 {
+            format!("writable by its group or others (mode {:o})",
+                    metadata.mode() & 0o7777)
+        } else {
+            continue;
+        };
+        findings.push(Finding { path: entry.into_path(),
+                                problem });
+    }
+    findings
+}
+
+/// Ownership isn't checked on Windows, where files carry ACLs rather than a mode.
+#[cfg(windows)]
+fn ownership_findings(_installed_path: &Path) -> Vec<Finding> { Vec::new() }
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use std::{fs,
+              os::unix::fs::PermissionsExt};
+    use tempfile::TempDir;
+
+    #[test]
+    fn files_writable_by_others_are_found() {
+        let dir = TempDir::new().unwrap();
+        let bin = dir.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(bin.join("app"), "#!/bin/sh").unwrap();
+        fs::set_permissions(bin.join("app"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(ownership_findings(dir.path()).is_empty());
+
+        fs::set_permissions(bin.join("app"), fs::Permissions::from_mode(0o777)).unwrap();
+        let findings = ownership_findings(dir.path());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].path, bin.join("app"));
+    }
+}
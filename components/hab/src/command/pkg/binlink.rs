@@ -232,6 +232,37 @@ pub fn binlink_all_in_pkg(ui: &mut UI,
     Ok(())
 }
 
+/// Removes the binlinks in `dest_path` to binaries of the package `ident`, returning the paths
+/// of the removed binlinks.
+pub fn unlink_pkg(ident: &PackageIdent,
+                  dest_path: &Path,
+                  fs_root_path: &Path)
+                  -> Result<Vec<PathBuf>> {
+    let dst_path = fs_root_path.join(dest_path.strip_prefix("/")?);
+    // Binlinks target binaries by the path they are installed at, as `start` creates them.
+    let mut pkg_path = hfs::pkg_install_path(ident, None::<&Path>);
+    if cfg!(target_os = "windows") {
+        pkg_path = fs_root_path.join(pkg_path.strip_prefix("/")?);
+    }
+    let mut removed = Vec::new();
+    if !dst_path.is_dir() {
+        return Ok(removed);
+    }
+    for entry in fs::read_dir(&dst_path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Ok(binlink) = Binlink::from_file(&entry.path()) {
+            if binlink.target.starts_with(&pkg_path) {
+                fs::remove_file(&binlink.link)?;
+                removed.push(binlink.link);
+            }
+        }
+    }
+    Ok(removed)
+}
+
 fn is_dest_on_path(dest_dir: &Path) -> bool {
     if let Some(val) = env::var_os("PATH") {
         env::split_paths(&val).any(|p| p == dest_dir)
@@ -245,6 +276,7 @@ fn is_dest_on_path(dest_dir: &Path) -> bool {
 mod test {
     use super::{binlink_all_in_pkg,
                 start,
+                unlink_pkg,
                 Binlink};
     use crate::{common::ui::UI,
                 hcore::{self,
@@ -374,6 +406,28 @@ mod test {
                                                                             .target);
     }
 
+    #[test]
+    fn unlink_pkg_removes_only_the_packages_binlinks() {
+        let rootfs = TempDir::new().unwrap();
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["magicate.exe", "hypnoanalyze.exe"]);
+        let ident = fake_bin_pkg_install("acme/cooltools", tools, rootfs.path());
+        let mut other_tools = HashMap::new();
+        other_tools.insert("bin", vec!["securitize.exe"]);
+        let other_ident = fake_bin_pkg_install("acme/securetools", other_tools, rootfs.path());
+        let dst_path = Path::new("/opt/bin");
+
+        let mut ui = UI::with_sinks();
+        binlink_all_in_pkg(&mut ui, &ident, &dst_path, rootfs.path(), true).unwrap();
+        binlink_all_in_pkg(&mut ui, &other_ident, &dst_path, rootfs.path(), true).unwrap();
+
+        let removed = unlink_pkg(&ident, &dst_path, rootfs.path()).unwrap();
+        assert_eq!(removed.len(), 2);
+        let remaining = fs::read_dir(rootfs.path().join("opt/bin")).unwrap()
+                                                                   .count();
+        assert_eq!(remaining, 1);
+    }
+
     #[test]
     #[cfg(target_os = "windows")]
     fn binlink_all_in_pkg_symlinks_only_executables() {
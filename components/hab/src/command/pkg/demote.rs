@@ -7,12 +7,19 @@
 //! ```
 //! This will demote the acme package specified from the stable channel, removing it.
 //!
+//! ```bash
+//! $ cat release-train.txt | hab pkg demote - stable
+//! ```
+//! This will demote every package read from stdin, one fully qualified
+//! identifier per line, from the stable channel.
+//!
 //! Notes:
 //!    The package should already have been uploaded to Builder.
 //!    If the specified channel does not exist, this will fail.
 
 use crate::{api_client::{self,
                          Client},
+            command::pkg::for_each_ident,
             common::ui::{Status,
                          UIWriter,
                          UI},
@@ -64,3 +71,45 @@ pub async fn start(ui: &mut UI,
 
     Ok(())
 }
+
+/// Demote each of `idents` from the specified channel, with at most
+/// `concurrency` demotions in flight at once.
+///
+/// # Failures
+///
+/// * Fails if "unstable" is the channel specified.
+/// * Fails if any of the packages could not be demoted, after attempting to demote all of them.
+pub async fn start_many(ui: &mut UI,
+                        bldr_url: &str,
+                        idents: Vec<PackageIdent>,
+                        target: PackageTarget,
+                        channel: &ChannelIdent,
+                        token: &str,
+                        concurrency: usize)
+                        -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+
+    ui.begin(format!("Demoting {} packages ({}) from {}",
+                     idents.len(),
+                     target,
+                     channel))?;
+
+    if channel == &ChannelIdent::unstable() {
+        return Err(Error::CannotRemoveFromChannel((format!("{} packages",
+                                                           idents.len()),
+                                                   channel.to_string())));
+    }
+
+    let api_client = &api_client;
+    for_each_ident(ui,
+                   idents,
+                   concurrency,
+                   || Status::Demoted,
+                   move |ident| {
+                       async move {
+                           api_client.demote_package((&ident, target), channel, token)
+                                     .await
+                                     .map_err(Error::from)
+                       }
+                   }).await
+}
@@ -19,6 +19,16 @@
 //! The most common usage will have a file containing newline separated list of package
 //! identifiers.
 //!
+//! Several targets may be downloaded at once:
+//!
+//! ```bash
+//! $ hab pkg download -t x86_64-linux -t x86_64-windows --download-directory download core/redis
+//! ```
+//!
+//! When more than one target is downloaded, the artifacts and keys of each target are stored in
+//! their own `<download_path>/<target>` directory, and a `manifest.toml` listing every downloaded
+//! package by target is written to the download directory.
+//!
 //! # Internals
 //!
 //! * Resolve the list of partial artifact identifiers to fully qualified idents
@@ -27,9 +37,11 @@
 //! * Verify it is un-altered
 //! * Fetch the signing keys
 
-use std::{collections::{HashMap,
+use std::{collections::{BTreeMap,
+                        HashMap,
                         HashSet},
-          fs::DirBuilder,
+          fs::{self,
+               DirBuilder},
           path::{Path,
                  PathBuf},
           time::Duration};
@@ -63,6 +75,7 @@ use crate::error::{Error,
 
 pub const RETRIES: usize = 5;
 pub const RETRY_WAIT: Duration = Duration::from_millis(3000);
+pub const MANIFEST_FILE: &str = "manifest.toml";
 
 #[derive(Debug, Deserialize)]
 pub struct PackageSetFile {
@@ -76,7 +89,7 @@ pub struct PackageSetFile {
     pub targets: HashMap<PackageTarget, Vec<PackageSetValue>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct PackageSetValue {
     pub channel:  ChannelIdent,
     pub packages: Vec<String>,
@@ -89,6 +102,16 @@ pub struct PackageSet {
     pub idents:  Vec<PackageIdent>,
 }
 
+/// The packages downloaded for each target, written in the same format as a `PackageSetFile` so
+/// that it can be passed back to `hab pkg download --file`.
+#[derive(Debug, Serialize)]
+struct DownloadManifest {
+    format_version:  u8,
+    file_descriptor: String,
+    #[serde(flatten)]
+    targets:         BTreeMap<String, Vec<PackageSetValue>>,
+}
+
 /// Download a Habitat package.
 ///
 /// If an `PackageIdent` is given, we retrieve the package from the specified Builder
@@ -101,7 +124,9 @@ pub struct PackageSet {
 /// At the end of this function, the specified package and all its
 /// dependencies will be downloaded on the system in the
 /// <download_path>/artifacts directory. Any signing keys will also be
-/// downloaded and put in the <download_path/keys> directory.
+/// downloaded and put in the <download_path/keys> directory. If the package sets
+/// span more than one target, each target is instead downloaded to
+/// <download_path>/<target>, and a manifest of the download is written.
 
 /// Also, in the future we may want to accept an alternate builder to 'filter' what we pull down by
 /// That would greatly optimize the 'sync' to on prem builder case, as we could point to that
@@ -152,13 +177,16 @@ pub async fn start<U>(ui: &mut U,
     // we don't want to override.
     let api_client = Client::new(url, product, version, None)?;
 
+    let targets: HashSet<PackageTarget> = package_sets.iter().map(|set| set.target).collect();
+
     let task = DownloadTask { package_sets,
                               url,
                               api_client,
                               token,
                               download_path: download_path_expanded,
                               verify,
-                              ignore_missing_seeds };
+                              ignore_missing_seeds,
+                              partitioned: targets.len() > 1 };
 
     let download_count = task.execute(ui).await?;
 
@@ -175,6 +203,8 @@ struct DownloadTask<'a> {
     download_path:        &'a Path,
     verify:               bool,
     ignore_missing_seeds: bool,
+    /// Whether each target is stored in its own directory.
+    partitioned:          bool,
 }
 
 impl<'a> DownloadTask<'a> {
@@ -185,7 +215,9 @@ impl<'a> DownloadTask<'a> {
         // Any or all of these phases should naturally fit a fork-join model
         ui.begin(format!("Storing in download directory {:?} ", self.download_path))?;
 
-        self.verify_and_prepare_download_directory(ui)?;
+        for package_set in self.package_sets {
+            self.verify_and_prepare_download_directory(ui, package_set.target)?;
+        }
 
         // Phase 1: Expand to fully qualified deps and TDEPS
         let expanded_idents = self.expand_sources(ui).await?;
@@ -193,6 +225,11 @@ impl<'a> DownloadTask<'a> {
         // Phase 2: Download artifacts
         let downloaded_artifacts = self.download_artifacts(ui, &expanded_idents).await?;
 
+        // Phase 3: Record what was downloaded for each target
+        if self.partitioned {
+            self.write_manifest(ui, &expanded_idents)?;
+        }
+
         Ok(downloaded_artifacts.len())
     }
 
@@ -367,7 +404,7 @@ impl<'a> DownloadTask<'a> {
         match self.api_client
                   .fetch_package((ident, target),
                                  self.token,
-                                 &self.path_for_artifact(),
+                                 &self.path_for_artifact(target),
                                  ui.progress())
                   .await
         {
@@ -384,13 +421,18 @@ impl<'a> DownloadTask<'a> {
     async fn fetch_origin_key<T>(&self,
                                  ui: &mut T,
                                  name_with_rev: &str,
+                                 target: PackageTarget,
                                  token: Option<&str>)
                                  -> Result<()>
         where T: UIWriter
     {
         let (name, rev) = parse_name_with_rev(&name_with_rev)?;
         self.api_client
-            .fetch_origin_key(&name, &rev, token, &self.path_for_keys(), ui.progress())
+            .fetch_origin_key(&name,
+                              &rev,
+                              token,
+                              &self.path_for_keys(target),
+                              ui.progress())
             .await?;
         Ok(())
     }
@@ -407,15 +449,16 @@ impl<'a> DownloadTask<'a> {
         // Once we have them, it's the natural time to verify.
         // Otherwise, it might make sense to take this fetch out of the verification code.
         let signer = artifact::artifact_signer(&artifact.path)?;
-        if SigKeyPair::get_public_key_path(&signer, &self.path_for_keys()).is_err() {
+        if SigKeyPair::get_public_key_path(&signer, &self.path_for_keys(target)).is_err() {
             ui.status(Status::Downloading,
                       format!("public key for signer {:?}", signer))?;
-            self.fetch_origin_key(ui, &signer, self.token).await?;
+            self.fetch_origin_key(ui, &signer, target, self.token)
+                .await?;
         }
 
         if self.verify {
             ui.status(Status::Verifying, artifact.ident()?)?;
-            artifact.verify(&self.path_for_keys())?;
+            artifact.verify(&self.path_for_keys(target))?;
             debug!("Verified {} for {} signed by {}", ident, target, &signer);
         }
         Ok(())
@@ -426,7 +469,7 @@ impl<'a> DownloadTask<'a> {
     /// the local package cache. It does not mean that the package is
     /// actually *in* the package download directory, though.
     fn downloaded_artifact_path(&self, ident: &PackageIdent, target: PackageTarget) -> PathBuf {
-        self.path_for_artifact()
+        self.path_for_artifact(target)
             .join(ident.archive_name_with_target(target).unwrap())
     }
 
@@ -442,24 +485,76 @@ impl<'a> DownloadTask<'a> {
             .map_err(Error::from)
     }
 
+    /// Write a manifest of the downloaded packages of every target to the download directory.
+    fn write_manifest<T>(&self,
+                         ui: &mut T,
+                         expanded_idents: &HashSet<(PackageIdent, PackageTarget)>)
+                         -> Result<()>
+        where T: UIWriter
+    {
+        let mut packages_by_target = BTreeMap::<String, Vec<String>>::new();
+        for (ident, target) in expanded_idents {
+            packages_by_target.entry(target.to_string())
+                              .or_default()
+                              .push(ident.to_string());
+        }
+        // Every package is fully qualified, and all packages are in the unstable channel, so
+        // downloading from the manifest fetches exactly these packages.
+        let targets =
+            packages_by_target.into_iter()
+                              .map(|(target, mut packages)| {
+                                  packages.sort();
+                                  let set = PackageSetValue { channel: ChannelIdent::unstable(),
+                                                              packages };
+                                  (target, vec![set])
+                              })
+                              .collect();
+        let manifest = DownloadManifest { format_version: 1,
+                                          file_descriptor: String::from("Packages downloaded \
+                                                                         by hab pkg download"),
+                                          targets };
+
+        let path = self.download_path.join(MANIFEST_FILE);
+        fs::write(&path, toml::to_string(&manifest)?)?;
+        ui.status(Status::Created, format!("manifest {}", path.display()))?;
+        Ok(())
+    }
+
+    /// The directory the artifacts and keys of `target` are stored in.
+    fn path_for_target(&self, target: PackageTarget) -> PathBuf {
+        if self.partitioned {
+            self.download_path.join(target.to_string())
+        } else {
+            self.download_path.to_path_buf()
+        }
+    }
+
     /// The cache_*_path functions in fs don't let you override a path base with Some(base)
     /// So we have to build our own paths.
-    fn path_for_keys(&self) -> PathBuf { self.download_path.join("keys") }
+    fn path_for_keys(&self, target: PackageTarget) -> PathBuf {
+        self.path_for_target(target).join("keys")
+    }
 
-    fn path_for_artifact(&self) -> PathBuf { self.download_path.join("artifacts") }
+    fn path_for_artifact(&self, target: PackageTarget) -> PathBuf {
+        self.path_for_target(target).join("artifacts")
+    }
 
     /// Sanity check the download directory tree. The errors from the api around permissions are
     /// opaque; this validates the directory in advance to help provide useful feedback.
-    fn verify_and_prepare_download_directory<T>(&self, ui: &mut T) -> Result<()>
+    fn verify_and_prepare_download_directory<T>(&self,
+                                                ui: &mut T,
+                                                target: PackageTarget)
+                                                -> Result<()>
         where T: UIWriter
     {
+        let target_path = self.path_for_target(target);
         let system_paths = [self.download_path,
-                            &self.path_for_keys(),
-                            &self.path_for_artifact()];
+                            &target_path,
+                            &self.path_for_keys(target),
+                            &self.path_for_artifact(target)];
 
         ui.status(Status::Verifying,
-                  format!("the download directory \"{}\"",
-                          self.download_path.display()))?;
+                  format!("the download directory \"{}\"", target_path.display()))?;
 
         let mut builder = DirBuilder::new();
         builder.recursive(true);
@@ -1,5 +1,6 @@
 pub mod cf;
 pub mod container;
 mod export_common;
+pub mod k8s;
 pub mod mesos;
 pub mod tar;
@@ -0,0 +1,485 @@
+//! Generates Kubernetes manifests which run a package under Habitat Supervisors, either from its
+//! package identifier or from the spec file of a service which a Supervisor has loaded.
+//!
+//! The Supervisors run from the image `hab pkg export container` makes of the package. By
+//! default the manifests are:
+//!
+//! * a ConfigMap holding the Supervisor's `sup.toml` and the peers it joins the ring through
+//! * a Secret holding its ctl secret and, with `--ring`, the ring key
+//! * a headless Service through which the Supervisors find each other to gossip
+//! * a Service for the Supervisors' HTTP gateways
+//! * a StatefulSet for the leader topology, whose members need stable names to elect a leader, and
+//!   otherwise a Deployment
+//!
+//! With `--operator`, a `Habitat` resource for the Habitat operator is emitted instead, along with
+//! the Secret for the ring key if there is one.
+
+use crate::{cli::hab::pkg::PkgExportK8s,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            protocol::{self,
+                       types::Topology}};
+use habitat_core::{crypto::SymKey,
+                   package::PackageIdent,
+                   service::ServiceBind,
+                   ChannelIdent};
+use serde_json::{json,
+                 Value};
+use std::{fs,
+          path::Path,
+          str::FromStr};
+
+const GOSSIP_PORT: u16 = 9638;
+const HTTP_PORT: u16 = 9631;
+const SUP_CONFIG_DIR: &str = "/hab/sup/default/config";
+const CTL_SECRET_PATH: &str = "/hab/sup/default/CTL_SECRET";
+
+/// The parts of a loaded service's spec file which carry over to Kubernetes.
+#[derive(Deserialize)]
+struct SpecFile {
+    ident:    String,
+    group:    Option<String>,
+    channel:  Option<String>,
+    topology: Option<String>,
+    #[serde(default)]
+    binds:    Vec<ServiceBind>,
+}
+
+/// A service to run in Kubernetes, with everything its manifests are made from.
+struct Workload {
+    name:       String,
+    ident:      PackageIdent,
+    image:      String,
+    group:      String,
+    channel:    ChannelIdent,
+    topology:   Topology,
+    binds:      Vec<ServiceBind>,
+    count:      u32,
+    ctl_secret: String,
+    ring_key:   Option<String>,
+}
+
+pub fn start(ui: &mut UI, args: PkgExportK8s) -> Result<()> {
+    let mut workload = workload(args.pkg_ident,
+                                args.spec.as_deref(),
+                                args.group,
+                                args.channel,
+                                args.topology,
+                                args.bind,
+                                args.image,
+                                args.count)?;
+    if let Some(ring) = args.ring {
+        let key = SymKey::get_latest_pair_for(&ring, &args.cache_key_path.cache_key_path)?;
+        workload.ring_key = Some(key.to_secret_string()?);
+    }
+    if workload.topology == Topology::Leader && workload.count < 3 {
+        ui.warn(format!("The leader topology needs at least 3 members to elect a leader, but \
+                         only {} will be run",
+                        workload.count))?;
+    }
+
+    let manifests = if args.operator {
+        to_yaml(&operator_manifests(&workload))?
+    } else {
+        to_yaml(&manifests(&workload))?
+    };
+    match args.output {
+        Some(path) => {
+            fs::write(&path, manifests)?;
+            ui.status(Status::Created,
+                      format!("Kubernetes manifests for {} at {}",
+                              workload.ident,
+                              path.display()))?;
+        }
+        None => print!("{}", manifests),
+    }
+    Ok(())
+}
+
+/// Settles what to run from the package identifier or spec file, with any options given on the
+/// command line taking precedence over the spec file.
+#[allow(clippy::too_many_arguments)]
+fn workload(pkg_ident: Option<PackageIdent>,
+            spec: Option<&Path>,
+            group: Option<String>,
+            channel: Option<String>,
+            topology: Option<String>,
+            binds: Vec<String>,
+            image: Option<String>,
+            count: u32)
+            -> Result<Workload> {
+    let spec = match spec {
+        Some(path) => Some(toml::from_str::<SpecFile>(&fs::read_to_string(path)?)?),
+        None => None,
+    };
+    let ident = match (pkg_ident, &spec) {
+        (Some(ident), _) => ident,
+        (None, Some(spec)) => PackageIdent::from_str(&spec.ident)?,
+        (None, None) => {
+            return Err(Error::ArgumentError(String::from("A package identifier \
+                                                          or spec file is \
+                                                          required")));
+        }
+    };
+    let spec_value = |value: Option<String>, from_spec: fn(&SpecFile) -> Option<String>| {
+        value.or_else(|| spec.as_ref().and_then(from_spec))
+    };
+    let group = spec_value(group, |s| s.group.clone()).unwrap_or_else(|| "default".to_string());
+    let channel = spec_value(channel, |s| s.channel.clone()).map_or_else(ChannelIdent::default,
+                                                                         ChannelIdent::from);
+    let topology = match spec_value(topology, |s| s.topology.clone()) {
+        Some(topology) => {
+            Topology::from_str(&topology).map_err(|e| Error::ArgumentError(e.to_string()))?
+        }
+        None => Topology::Standalone,
+    };
+    let binds = if binds.is_empty() {
+        spec.map(|s| s.binds).unwrap_or_default()
+    } else {
+        binds.iter()
+             .map(|bind| ServiceBind::from_str(bind))
+             .collect::<std::result::Result<_, _>>()?
+    };
+    let mut ctl_secret = String::new();
+    protocol::generate_secret_key(&mut ctl_secret);
+
+    Ok(Workload { name: resource_name(&ident.name),
+                  image: image.unwrap_or_else(|| format!("{}/{}", ident.origin, ident.name)),
+                  ident,
+                  group,
+                  channel,
+                  topology,
+                  binds,
+                  count,
+                  ctl_secret,
+                  ring_key: None })
+}
+
+/// Kubernetes resource names are lowercase DNS labels, which package names need not be.
+fn resource_name(pkg_name: &str) -> String {
+    pkg_name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>()
+            .trim_matches('-')
+            .to_string()
+}
+
+fn to_yaml(documents: &[Value]) -> Result<String> {
+    let mut yaml = String::new();
+    for document in documents {
+        let document = serde_yaml::to_string(document)?;
+        yaml.push_str("---\n");
+        yaml.push_str(document.trim_start_matches("---\n"));
+        yaml.push('\n');
+    }
+    Ok(yaml)
+}
+
+fn labels(workload: &Workload) -> Value {
+    json!({
+        "app.kubernetes.io/name": workload.name,
+        "app.kubernetes.io/managed-by": "habitat",
+        "habitat.sh/service-group": format!("{}.{}", workload.ident.name, workload.group),
+    })
+}
+
+fn metadata(workload: &Workload, name: &str) -> Value {
+    json!({ "name": name, "labels": labels(workload) })
+}
+
+/// Whether the Supervisors run as a StatefulSet, whose pods have stable DNS names to peer with.
+fn is_stateful(workload: &Workload) -> bool { workload.topology == Topology::Leader }
+
+/// The addresses each Supervisor tries to join the ring through.
+fn peers(workload: &Workload) -> String {
+    if is_stateful(workload) {
+        format!("{}-0.{}:{}\n", workload.name, workload.name, GOSSIP_PORT)
+    } else {
+        // The headless Service resolves to one of the Deployment's pods.
+        format!("{}:{}\n", workload.name, GOSSIP_PORT)
+    }
+}
+
+fn sup_toml() -> String {
+    format!("listen_gossip = \"0.0.0.0:{}\"\nlisten_http = \"0.0.0.0:{}\"\npeer_watch_file = \
+             \"{}/peers\"\n",
+            GOSSIP_PORT, HTTP_PORT, SUP_CONFIG_DIR)
+}
+
+fn config_map(workload: &Workload) -> Value {
+    json!({
+        "apiVersion": "v1",
+        "kind": "ConfigMap",
+        "metadata": metadata(workload, &format!("{}-sup-config", workload.name)),
+        "data": {
+            "sup.toml": sup_toml(),
+            "peers": peers(workload),
+        },
+    })
+}
+
+fn secret(workload: &Workload) -> Value {
+    let mut data = json!({ "CTL_SECRET": workload.ctl_secret });
+    if let Some(ring_key) = &workload.ring_key {
+        data["ring-key"] = json!(ring_key);
+    }
+    json!({
+        "apiVersion": "v1",
+        "kind": "Secret",
+        "metadata": metadata(workload, &format!("{}-keys", workload.name)),
+        "type": "Opaque",
+        "stringData": data,
+    })
+}
+
+fn services(workload: &Workload) -> Vec<Value> {
+    let selector = json!({ "app.kubernetes.io/name": workload.name });
+    vec![json!({
+             "apiVersion": "v1",
+             "kind": "Service",
+             "metadata": metadata(workload, &workload.name),
+             "spec": {
+                 "clusterIP": "None",
+                 "publishNotReadyAddresses": true,
+                 "selector": selector,
+                 "ports": [
+                     { "name": "gossip-tcp", "port": GOSSIP_PORT, "protocol": "TCP" },
+                     { "name": "gossip-udp", "port": GOSSIP_PORT, "protocol": "UDP" },
+                 ],
+             },
+         }),
+         json!({
+             "apiVersion": "v1",
+             "kind": "Service",
+             "metadata": metadata(workload, &format!("{}-gateway", workload.name)),
+             "spec": {
+                 "selector": selector,
+                 "ports": [{ "name": "http-gateway", "port": HTTP_PORT, "protocol": "TCP" }],
+             },
+         }),]
+}
+
+fn container(workload: &Workload) -> Value {
+    let mut args = vec!["run".to_string(),
+                        workload.ident.to_string(),
+                        "--group".to_string(),
+                        workload.group.clone(),
+                        "--channel".to_string(),
+                        workload.channel.to_string(),
+                        "--topology".to_string(),
+                        workload.topology.to_string(),];
+    for bind in &workload.binds {
+        args.push("--bind".to_string());
+        args.push(bind.to_string());
+    }
+    let mut env = Vec::new();
+    if workload.ring_key.is_some() {
+        env.push(json!({
+            "name": "HAB_RING_KEY",
+            "valueFrom": {
+                "secretKeyRef": { "name": format!("{}-keys", workload.name), "key": "ring-key" },
+            },
+        }));
+    }
+    json!({
+        "name": workload.name,
+        "image": workload.image,
+        "args": args,
+        "env": env,
+        "ports": [
+            { "name": "gossip-tcp", "containerPort": GOSSIP_PORT, "protocol": "TCP" },
+            { "name": "gossip-udp", "containerPort": GOSSIP_PORT, "protocol": "UDP" },
+            { "name": "http-gateway", "containerPort": HTTP_PORT, "protocol": "TCP" },
+        ],
+        "volumeMounts": [
+            { "name": "sup-config", "mountPath": SUP_CONFIG_DIR },
+            { "name": "keys", "mountPath": CTL_SECRET_PATH, "subPath": "CTL_SECRET" },
+        ],
+    })
+}
+
+fn workload_controller(workload: &Workload) -> Value {
+    let labels = labels(workload);
+    let mut spec = json!({
+        "replicas": workload.count,
+        "selector": { "matchLabels": { "app.kubernetes.io/name": workload.name } },
+        "template": {
+            "metadata": { "labels": labels },
+            "spec": {
+                "containers": [container(workload)],
+                "volumes": [
+                    {
+                        "name": "sup-config",
+                        "configMap": { "name": format!("{}-sup-config", workload.name) },
+                    },
+                    {
+                        "name": "keys",
+                        "secret": {
+                            "secretName": format!("{}-keys", workload.name),
+                            "items": [{ "key": "CTL_SECRET", "path": "CTL_SECRET" }],
+                        },
+                    },
+                ],
+            },
+        },
+    });
+    let kind = if is_stateful(workload) {
+        spec["serviceName"] = json!(workload.name);
+        spec["podManagementPolicy"] = json!("Parallel");
+        "StatefulSet"
+    } else {
+        "Deployment"
+    };
+    json!({
+        "apiVersion": "apps/v1",
+        "kind": kind,
+        "metadata": metadata(workload, &workload.name),
+        "spec": spec,
+    })
+}
+
+fn manifests(workload: &Workload) -> Vec<Value> {
+    let mut manifests = vec![config_map(workload), secret(workload)];
+    manifests.extend(services(workload));
+    manifests.push(workload_controller(workload));
+    manifests
+}
+
+/// A `Habitat` resource for the Habitat operator, which makes the Kubernetes resources itself.
+fn operator_manifests(workload: &Workload) -> Vec<Value> {
+    let binds = workload.binds
+                        .iter()
+                        .map(|bind| {
+                            json!({
+                                "name": bind.name(),
+                                "service": bind.service_group().service(),
+                                "group": bind.service_group().group(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+    let mut service = json!({
+        "name": workload.ident.name,
+        "topology": workload.topology.to_string(),
+        "group": workload.group,
+        "channel": workload.channel.to_string(),
+        "bind": binds,
+    });
+    let mut manifests = Vec::new();
+    if let Some(ring_key) = &workload.ring_key {
+        let name = format!("{}-ring-key", workload.name);
+        service["ringSecretName"] = json!(name);
+        manifests.push(json!({
+                           "apiVersion": "v1",
+                           "kind": "Secret",
+                           "metadata": metadata(workload, &name),
+                           "type": "Opaque",
+                           "stringData": { "ring-key": ring_key },
+                       }));
+    }
+    manifests.push(json!({
+                       "apiVersion": "habitat.sh/v1beta1",
+                       "kind": "Habitat",
+                       "metadata": metadata(workload, &workload.name),
+                       "customVersion": "v1beta2",
+                       "spec": {
+                           "v1beta2": {
+                               "image": workload.image,
+                               "count": workload.count,
+                               "service": service,
+                           },
+                       },
+                   }));
+    manifests
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn spec_files_are_overridden_by_the_command_line() {
+        let mut spec = NamedTempFile::new().unwrap();
+        write!(spec,
+               "ident = \"core/redis\"\ngroup = \"prod\"\nchannel = \"unstable\"\ntopology = \
+                \"leader\"\nbinds = [\"backend:postgres.prod\"]\n").unwrap();
+        let workload = workload(None,
+                                Some(spec.path()),
+                                None,
+                                Some("stable".to_string()),
+                                None,
+                                Vec::new(),
+                                None,
+                                3).unwrap();
+        assert_eq!(workload.name, "redis");
+        assert_eq!(workload.image, "core/redis");
+        assert_eq!(workload.group, "prod");
+        assert_eq!(workload.channel, ChannelIdent::stable());
+        assert_eq!(workload.topology, Topology::Leader);
+        assert_eq!(workload.binds.len(), 1);
+    }
+
+    #[test]
+    fn the_leader_topology_runs_as_a_stateful_set() {
+        let ident = PackageIdent::from_str("core/my_app").unwrap();
+        let mut workload = workload(Some(ident),
+                                    None,
+                                    None,
+                                    None,
+                                    Some("leader".to_string()),
+                                    vec!["db:postgres.default".to_string()],
+                                    None,
+                                    3).unwrap();
+        assert_eq!(workload.name, "my-app");
+        let generated = manifests(&workload);
+        let kinds = generated.iter()
+                             .map(|m| m["kind"].as_str().unwrap())
+                             .collect::<Vec<_>>();
+        assert_eq!(kinds,
+                   vec!["ConfigMap", "Secret", "Service", "Service", "StatefulSet"]);
+        assert_eq!(generated[0]["data"]["peers"], "my-app-0.my-app:9638\n");
+        assert!(generated[1]["stringData"]["ring-key"].is_null());
+        let args = &generated[4]["spec"]["template"]["spec"]["containers"][0]["args"];
+        assert!(args.as_array()
+                    .unwrap()
+                    .contains(&json!("db:postgres.default")));
+
+        workload.topology = Topology::Standalone;
+        workload.ring_key = Some("SYM-SEC-1".to_string());
+        let generated = manifests(&workload);
+        assert_eq!(generated[4]["kind"], "Deployment");
+        assert_eq!(generated[1]["stringData"]["ring-key"], "SYM-SEC-1");
+        assert!(to_yaml(&generated).unwrap()
+                                   .starts_with("---\napiVersion: v1\n"));
+    }
+
+    #[test]
+    fn operator_resources_carry_the_service_settings() {
+        let ident = PackageIdent::from_str("core/redis").unwrap();
+        let workload = workload(Some(ident),
+                                None,
+                                Some("prod".to_string()),
+                                None,
+                                None,
+                                vec!["db:postgres.default".to_string()],
+                                Some("acme/redis:1.0".to_string()),
+                                2).unwrap();
+        let manifests = operator_manifests(&workload);
+        assert_eq!(manifests.len(), 1);
+        let spec = &manifests[0]["spec"]["v1beta2"];
+        assert_eq!(spec["image"], "acme/redis:1.0");
+        assert_eq!(spec["count"], 2);
+        assert_eq!(spec["service"]["group"], "prod");
+        assert_eq!(spec["service"]["bind"][0]["service"], "postgres");
+    }
+}
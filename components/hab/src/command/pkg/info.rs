@@ -1,10 +1,44 @@
-use crate::{common::ui::{UIWriter,
+use crate::{api_client::Client,
+            common::ui::{Status,
+                         UIWriter,
                          UI},
             error::{Error,
                     Result},
-            hcore::package::PackageArchiveInfo};
+            hcore::{crypto::artifact,
+                    fs::cache_artifact_path,
+                    package::{metadata,
+                              PackageArchiveInfo,
+                              PackageIdent,
+                              PackageInstall,
+                              PackageTarget},
+                    ChannelIdent},
+            PRODUCT,
+            VERSION};
 use habitat_core::util::text_render::PortableText;
-use std::path::Path;
+use std::{collections::BTreeMap,
+          path::Path};
+use walkdir::WalkDir;
+
+/// Metadata about a package, read from the local package store if it is installed or from
+/// Builder otherwise.
+#[derive(Serialize)]
+struct PackageInfo {
+    ident:    String,
+    target:   String,
+    /// Where the metadata came from: "installed" or "builder"
+    source:   &'static str,
+    deps:     Vec<String>,
+    tdeps:    Vec<String>,
+    exports:  BTreeMap<String, String>,
+    exposes:  Vec<String>,
+    licenses: Vec<String>,
+    /// Bytes used by the installed package
+    size:     Option<u64>,
+    /// The origin key which signed the package's artifact, if it is in the artifact cache
+    signer:   Option<String>,
+    /// The Builder channels the package is in, if Builder could be reached
+    channels: Option<Vec<String>>,
+}
 
 pub fn start(ui: &mut UI, src: &Path, to_json: bool) -> Result<()> {
     let info = PackageArchiveInfo::from_path(src)?;
@@ -29,6 +63,123 @@ pub fn start(ui: &mut UI, src: &Path, to_json: bool) -> Result<()> {
         println!("Name           : {}", info.name);
         println!("Version        : {}", info.version);
         println!("Release        : {}", info.release);
+        println!("License        : {}", info.licenses.join(" "));
     }
     Ok(())
 }
+
+/// Show the metadata of the package `ident`, preferring an installed release which satisfies it
+/// and otherwise asking Builder for the latest release in `channel`.
+#[allow(clippy::too_many_arguments)]
+pub async fn start_for_ident(ui: &mut UI,
+                             ident: &PackageIdent,
+                             target: PackageTarget,
+                             bldr_url: &str,
+                             channel: &ChannelIdent,
+                             token: Option<&str>,
+                             fs_root_path: &Path,
+                             to_json: bool)
+                             -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+    let mut info = match PackageInstall::load(ident, Some(fs_root_path)) {
+        Ok(pkg_install) => installed_info(&pkg_install, target, fs_root_path)?,
+        Err(_) => {
+            if !to_json {
+                ui.status(Status::Determining,
+                          format!("{} in the '{}' channel", ident, channel))?;
+            }
+            let package = api_client.show_package_metadata((ident, target), channel, token)
+                                    .await?;
+            PackageInfo { ident:    package.ident.to_string(),
+                          target:   target.to_string(),
+                          source:   "builder",
+                          deps:     package.deps.iter().map(ToString::to_string).collect(),
+                          tdeps:    package.tdeps.iter().map(ToString::to_string).collect(),
+                          exports:  BTreeMap::new(),
+                          exposes:  package.exposes.iter().map(ToString::to_string).collect(),
+                          licenses: metadata::licenses_from_manifest(&package.manifest),
+                          size:     None,
+                          signer:   None,
+                          channels: None, }
+        }
+    };
+
+    let fully_qualified: PackageIdent = info.ident.parse()?;
+    info.channels = match api_client.package_channels((&fully_qualified, target), token)
+                                    .await
+    {
+        Ok(channels) => Some(channels),
+        Err(e) => {
+            debug!("Unable to find channels for {}: {}", fully_qualified, e);
+            None
+        }
+    };
+
+    if to_json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print_info(&info);
+    }
+    Ok(())
+}
+
+fn installed_info(pkg_install: &PackageInstall,
+                  target: PackageTarget,
+                  fs_root_path: &Path)
+                  -> Result<PackageInfo> {
+    let ident = pkg_install.ident();
+    let cached_artifact =
+        cache_artifact_path(Some(fs_root_path)).join(ident.archive_name_with_target(target)?);
+    let signer = artifact::get_artifact_header(&cached_artifact).ok()
+                                                                .map(|h| h.key_name);
+    let size: u64 = WalkDir::new(pkg_install.installed_path()).into_iter()
+                                                              .filter_map(|e| e.ok())
+                                                              .filter_map(|e| e.metadata().ok())
+                                                              .filter(|m| m.is_file())
+                                                              .map(|m| m.len())
+                                                              .sum();
+    Ok(PackageInfo { ident: ident.to_string(),
+                     target: target.to_string(),
+                     source: "installed",
+                     deps: pkg_install.deps()?
+                                      .iter()
+                                      .map(ToString::to_string)
+                                      .collect(),
+                     tdeps: pkg_install.tdeps()?
+                                       .iter()
+                                       .map(ToString::to_string)
+                                       .collect(),
+                     exports: pkg_install.exports()?,
+                     exposes: pkg_install.exposes()?,
+                     licenses: pkg_install.licenses()?,
+                     size: Some(size),
+                     signer,
+                     channels: None })
+}
+
+fn print_info(info: &PackageInfo) {
+    let or_dash = |s: Option<String>| s.unwrap_or_else(|| "-".to_string());
+    println!("Package        : {}", info.ident);
+    println!("Target         : {}", info.target);
+    println!("Source         : {}", info.source);
+    println!("License        : {}", info.licenses.join(" "));
+    println!("Size           : {}",
+             or_dash(info.size.map(|s| format!("{} bytes", s))));
+    println!("Signer         : {}", or_dash(info.signer.clone()));
+    println!("Channels       : {}",
+             or_dash(info.channels.as_ref().map(|c| c.join(", "))));
+    println!("Exposes        : {}", info.exposes.join(" "));
+    let exports: Vec<String> = info.exports
+                                   .iter()
+                                   .map(|(k, v)| format!("{}={}", k, v))
+                                   .collect();
+    println!("Exports        : {}", exports.join(" "));
+    println!("Dependencies   :");
+    for dep in &info.deps {
+        println!("    {}", dep);
+    }
+    println!("Transitive Deps :");
+    for dep in &info.tdeps {
+        println!("    {}", dep);
+    }
+}
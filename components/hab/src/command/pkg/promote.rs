@@ -7,12 +7,19 @@
 //! ```
 //! //! This will promote the acme package specified to the stable channel.
 //!
+//! ```bash
+//! $ hab pkg promote release-train.txt stable
+//! ```
+//! This will promote every package listed in `release-train.txt`, one
+//! fully qualified identifier per line, to the stable channel.
+//!
 //! Notes:
 //!    The package should already have been uploaded to Builder.
 //!    If the specified channel does not exist, it will be created.
 
 use crate::{api_client::{self,
                          Client},
+            command::pkg::for_each_ident,
             common::ui::{Status,
                          UIWriter,
                          UI},
@@ -20,6 +27,7 @@ use crate::{api_client::{self,
                               PackageTarget},
                     ChannelIdent}};
 use reqwest::StatusCode;
+use std::collections::HashSet;
 
 use crate::{error::{Error,
                     Result},
@@ -41,18 +49,7 @@ pub async fn start(ui: &mut UI,
 
     ui.begin(format!("Promoting {} ({}) to channel '{}'", ident, target, channel))?;
 
-    if channel != &ChannelIdent::stable() && channel != &ChannelIdent::unstable() {
-        match api_client.create_channel(&ident.origin, channel, token)
-                        .await
-        {
-            Ok(_) => (),
-            Err(api_client::Error::APIError(StatusCode::CONFLICT, _)) => (),
-            Err(e) => {
-                println!("Failed to create '{}' channel: {:?}", channel, e);
-                return Err(Error::from(e));
-            }
-        };
-    }
+    create_channel(&api_client, &ident.origin, channel, token).await?;
 
     match api_client.promote_package((ident, target), channel, token)
                     .await
@@ -71,3 +68,64 @@ pub async fn start(ui: &mut UI,
 
     Ok(())
 }
+
+/// Promote each of `idents` to the specified channel, with at most
+/// `concurrency` promotions in flight at once.
+///
+/// # Failures
+///
+/// * Fails if the channel cannot be created in one of the origins
+/// * Fails if any of the packages could not be promoted, after attempting to promote all of them
+pub async fn start_many(ui: &mut UI,
+                        bldr_url: &str,
+                        idents: Vec<PackageIdent>,
+                        target: PackageTarget,
+                        channel: &ChannelIdent,
+                        token: &str,
+                        concurrency: usize)
+                        -> Result<()> {
+    let api_client = Client::new(bldr_url, PRODUCT, VERSION, None)?;
+
+    ui.begin(format!("Promoting {} packages ({}) to channel '{}'",
+                     idents.len(),
+                     target,
+                     channel))?;
+
+    let origins: HashSet<_> = idents.iter().map(|ident| ident.origin.clone()).collect();
+    for origin in origins {
+        create_channel(&api_client, &origin, channel, token).await?;
+    }
+
+    let api_client = &api_client;
+    for_each_ident(ui,
+                   idents,
+                   concurrency,
+                   || Status::Promoted,
+                   move |ident| {
+                       async move {
+                           api_client.promote_package((&ident, target), channel, token)
+                                     .await
+                                     .map_err(Error::from)
+                       }
+                   }).await
+}
+
+/// Create `channel` in `origin` unless it is one of the built-in
+/// channels or already exists.
+async fn create_channel(api_client: &Client,
+                        origin: &str,
+                        channel: &ChannelIdent,
+                        token: &str)
+                        -> Result<()> {
+    if channel == &ChannelIdent::stable() || channel == &ChannelIdent::unstable() {
+        return Ok(());
+    }
+    match api_client.create_channel(origin, channel, token).await {
+        Ok(_) => Ok(()),
+        Err(api_client::Error::APIError(StatusCode::CONFLICT, _)) => Ok(()),
+        Err(e) => {
+            println!("Failed to create '{}' channel: {:?}", channel, e);
+            Err(Error::from(e))
+        }
+    }
+}
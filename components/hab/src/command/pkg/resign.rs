@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result,
+            hcore::crypto::{artifact,
+                            SigKeyPair}};
+
+/// Re-sign every Habitat Artifact under `dir` which is signed with the `from_rev` revision of
+/// `origin`'s key, using its `to_rev` revision. Artifacts signed with any other key are left
+/// alone.
+pub fn start(ui: &mut UI,
+             origin: &str,
+             from_rev: &str,
+             to_rev: &str,
+             dir: &Path,
+             cache: &Path)
+             -> Result<()> {
+    let from = format!("{}-{}", origin, from_rev);
+    let to = SigKeyPair::get_pair_for(&format!("{}-{}", origin, to_rev), cache)?;
+    resign_signed_with(ui, &from, &to, dir, cache).map(|_| ())
+}
+
+/// Re-sign every Habitat Artifact under `dir` which is signed with the key `from`, a key name
+/// with revision, using `to`. Only artifacts which verify with `from`'s key in `cache` are
+/// re-signed; the rest are skipped with a warning. Returns how many were re-signed.
+pub fn resign_signed_with(ui: &mut UI,
+                          from: &str,
+                          to: &SigKeyPair,
+                          dir: &Path,
+                          cache: &Path)
+                          -> Result<usize> {
+    ui.begin(format!("Re-signing artifacts in {} signed with {}",
+                     dir.display(),
+                     from))?;
+    let mut resigned = 0;
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().map_or(true, |e| e != "hart") {
+            continue;
+        }
+        match artifact::artifact_signer(&path) {
+            Ok(ref signer) if signer == from => {
+                ui.status(Status::Signing,
+                          format!("{} with {}", path.display(), to.name_with_rev()))?;
+                // The artifact is checked against its signature by `from` as it is re-signed, and
+                // left as it is if that fails.
+                match artifact::resign(path, path, to, cache) {
+                    Ok(()) => resigned += 1,
+                    Err(e) => {
+                        ui.warn(format!("Not re-signing {}, it does not verify: {}",
+                                        path.display(),
+                                        e))?;
+                    }
+                }
+            }
+            Ok(signer) => {
+                ui.status(Status::Skipping,
+                          format!("{}, signed with {}", path.display(), signer))?;
+            }
+            Err(e) => {
+                ui.status(Status::Skipping, format!("{}, {}", path.display(), e))?;
+            }
+        }
+    }
+    ui.end(format!("Re-signed {} artifact(s) with {}.",
+                   resigned,
+                   to.name_with_rev()))?;
+    Ok(resigned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcore::clock::{self,
+                              ManualClock};
+    use std::{fs,
+              time::Duration};
+    use tempfile::TempDir;
+
+    #[test]
+    fn only_artifacts_signed_with_the_old_revision_are_resigned() {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        let cache = TempDir::new().unwrap();
+        let old = SigKeyPair::generate_pair_for_origin("acme");
+        old.to_pair_files(cache.path()).unwrap();
+        let other = SigKeyPair::generate_pair_for_origin("other");
+        other.to_pair_files(cache.path()).unwrap();
+        // Revisions are timestamps, so the new key needs the clock to have moved on.
+        clock.advance(Duration::from_secs(1));
+        let new = SigKeyPair::generate_pair_for_origin("acme");
+        new.to_pair_files(cache.path()).unwrap();
+        let artifacts = TempDir::new().unwrap();
+        let archive = artifacts.path().join("archive.tar");
+        fs::write(&archive, b"not really a tarball").unwrap();
+        let mine = artifacts.path()
+                            .join("acme-thing-1.0.0-20200101000000-x86_64-linux.hart");
+        let theirs = artifacts.path()
+                              .join("other-thing-1.0.0-20200101000000-x86_64-linux.hart");
+        artifact::sign(&archive, &mine, &old).unwrap();
+        artifact::sign(&archive, &theirs, &other).unwrap();
+
+        let (_, from_rev) =
+            crate::hcore::crypto::keys::parse_name_with_rev(old.name_with_rev()).unwrap();
+        let (_, to_rev) =
+            crate::hcore::crypto::keys::parse_name_with_rev(new.name_with_rev()).unwrap();
+        start(&mut UI::with_sinks(),
+              "acme",
+              &from_rev,
+              &to_rev,
+              artifacts.path(),
+              cache.path()).unwrap();
+
+        assert_eq!(artifact::verify(&mine, cache.path()).unwrap().0,
+                   new.name_with_rev());
+        assert_eq!(artifact::verify(&theirs, cache.path()).unwrap().0,
+                   other.name_with_rev());
+    }
+
+    #[test]
+    fn artifacts_which_do_not_verify_are_not_resigned() {
+        let cache = TempDir::new().unwrap();
+        let old = SigKeyPair::generate_pair_for_origin("acme");
+        old.to_pair_files(cache.path()).unwrap();
+        let new = SigKeyPair::generate_pair_for_origin("other");
+        new.to_pair_files(cache.path()).unwrap();
+        let artifacts = TempDir::new().unwrap();
+        let archive = artifacts.path().join("archive.tar");
+        fs::write(&archive, b"not really a tarball").unwrap();
+        let tampered = artifacts.path()
+                                .join("acme-thing-1.0.0-20200101000000-x86_64-linux.hart");
+        artifact::sign(&archive, &tampered, &old).unwrap();
+        let mut bytes = fs::read(&tampered).unwrap();
+        bytes.extend_from_slice(b" and then some");
+        fs::write(&tampered, &bytes).unwrap();
+
+        let resigned = resign_signed_with(&mut UI::with_sinks(),
+                                          old.name_with_rev(),
+                                          &new,
+                                          artifacts.path(),
+                                          cache.path()).unwrap();
+
+        assert_eq!(resigned, 0);
+        assert_eq!(fs::read(&tampered).unwrap(), bytes);
+    }
+}
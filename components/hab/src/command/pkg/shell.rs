@@ -0,0 +1,98 @@
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::{fs::{find_command,
+                         FS_ROOT_PATH},
+                    os::process,
+                    package::{PackageIdent,
+                              PackageInstall}}};
+use std::{collections::BTreeMap,
+          env,
+          io,
+          path::PathBuf};
+
+const PATH_KEY: &str = "PATH";
+const LIBRARY_PATH_KEY: &str = "LD_LIBRARY_PATH";
+/// Set in the shell to the package it was opened for, so prompts and scripts can tell
+const SHELL_PKG_KEY: &str = "HAB_PKG_SHELL";
+
+#[cfg(windows)]
+const DEFAULT_SHELL: &str = "powershell.exe";
+#[cfg(not(windows))]
+const DEFAULT_SHELL: &str = "/bin/sh";
+
+/// Open `shell`, or the user's own shell, with the runtime environment of an installed package
+/// applied, and its programs ahead of the host's on the `PATH`. With `tdeps`, its transitive
+/// dependencies' runtime environments are applied as well, for the variables the package doesn't
+/// set itself.
+pub fn start(ui: &mut UI,
+             ident: &PackageIdent,
+             shell: Option<PathBuf>,
+             tdeps: bool)
+             -> Result<()> {
+    let pkg_install = PackageInstall::load(ident, Some(&*FS_ROOT_PATH))?;
+    let mut shell_env = pkg_install.environment_for_command()?;
+    let mut library_paths = pkg_install.library_paths()?;
+    if tdeps {
+        for dep in pkg_install.tdeps()? {
+            let dep_install = PackageInstall::load(&dep, Some(&*FS_ROOT_PATH))?;
+            for (key, value) in dep_install.environment_for_command()? {
+                shell_env.entry(key).or_insert(value);
+            }
+            for path in dep_install.library_paths()? {
+                if !library_paths.contains(&path) {
+                    library_paths.push(path);
+                }
+            }
+        }
+    }
+
+    prepend_host_paths(&mut shell_env, PATH_KEY, vec![])?;
+    // Windows finds a program's libraries on its `PATH`, which the package already sets.
+    if cfg!(not(windows)) {
+        prepend_host_paths(&mut shell_env, LIBRARY_PATH_KEY, library_paths)?;
+    }
+    shell_env.insert(SHELL_PKG_KEY.to_string(), pkg_install.ident().to_string());
+
+    for (key, value) in shell_env.into_iter() {
+        debug!("Setting: {}='{}'", key, value);
+        env::set_var(key, value);
+    }
+    let shell = shell.or_else(|| env::var_os("SHELL").filter(|s| !s.is_empty()).map(Into::into))
+                     .unwrap_or_else(|| DEFAULT_SHELL.into());
+    let shell = match find_command(&shell) {
+        Some(path) => path,
+        None => return Err(Error::ExecCommandNotFound(shell)),
+    };
+    ui.status(Status::Executing,
+              format!("{} with the environment of {}",
+                      shell.display(),
+                      pkg_install.ident()))?;
+    process::become_command(shell, &[])?;
+    Ok(())
+}
+
+/// Put `paths`, then those the package sets for `key`, ahead of the host's own value of `key`.
+fn prepend_host_paths(shell_env: &mut BTreeMap<String, String>,
+                      key: &str,
+                      mut paths: Vec<PathBuf>)
+                      -> Result<()> {
+    if let Some(pkg_paths) = shell_env.get(key) {
+        paths.extend(env::split_paths(pkg_paths));
+    }
+    if let Some(host_paths) = env::var_os(key) {
+        paths.extend(env::split_paths(&host_paths));
+    }
+    paths.retain(|p| !p.as_os_str().is_empty());
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let joined = env::join_paths(paths)?;
+    let joined =
+        joined.into_string()
+              .map_err(|s| io::Error::new(io::ErrorKind::InvalidData, s.to_string_lossy()))?;
+    shell_env.insert(key.to_string(), joined);
+    Ok(())
+}
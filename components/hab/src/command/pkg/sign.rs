@@ -4,18 +4,31 @@ use crate::{common::ui::{Status,
                          UIWriter,
                          UI},
             hcore::crypto::{artifact,
-                            SigKeyPair}};
+                            SigningBackend}};
 
 use crate::error::Result;
 
-pub fn start(ui: &mut UI, origin: &SigKeyPair, src: &Path, dst: &Path) -> Result<()> {
+/// Signs the archive at `src` with `origin`, writing the Habitat Artifact to `dst`. When `append`
+/// is set, `src` is a HART-2 artifact instead, which `origin`'s signature is added to once it
+/// verifies with the keys in `cache`.
+pub fn start(ui: &mut UI,
+             origin: &dyn SigningBackend,
+             src: &Path,
+             dst: &Path,
+             append: bool,
+             cache: &Path)
+             -> Result<()> {
     ui.begin(format!("Signing {}", src.display()))?;
     ui.status(Status::Signing,
               format!("{} with {} to create {}",
                       src.display(),
                       &origin.name_with_rev(),
                       dst.display()))?;
-    artifact::sign(src, dst, origin)?;
+    if append {
+        artifact::add_signature(src, dst, origin, cache)?;
+    } else {
+        artifact::sign(src, dst, origin)?;
+    }
     ui.end(format!("Signed artifact {}.", dst.display()))?;
     Ok(())
 }
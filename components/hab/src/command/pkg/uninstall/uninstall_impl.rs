@@ -18,7 +18,8 @@ use habitat_core::{error as herror,
                              Identifiable,
                              PackageIdent,
                              PackageInstall}};
-use habitat_sup_client::{SrvClient,
+use habitat_sup_client::{CtlGateway,
+                         SrvClient,
                          SrvClientError};
 use std::{fs,
           path::Path,
@@ -272,11 +273,11 @@ async fn supervisor_services() -> Result<Vec<PackageIdent>> {
 
     let cfg = config::load()?;
     let secret_key = config::ctl_secret_key(&cfg)?;
-    let listen_ctl_addr = ListenCtlAddr::default();
+    let gateway = CtlGateway::from(ListenCtlAddr::default());
     let msg = habitat_sup_protocol::ctl::SvcStatus::default();
 
     let mut out: Vec<PackageIdent> = vec![];
-    let mut response = SrvClient::request(&listen_ctl_addr, &secret_key, msg).await?;
+    let mut response = SrvClient::request(&gateway, &secret_key, msg).await?;
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
         match reply.message_id() {
@@ -399,7 +400,7 @@ async fn maybe_run_uninstall_hook<T>(ui: &mut T, package: &PackageInstall) -> Re
                           unqualified_ident))?;
         return Ok(());
     }
-    Ok(UninstallHook::find_run_and_error_for_status(ui, package).await?)
+    Ok(UninstallHook::find_run_and_error_for_status(ui, package, None).await?)
 }
 
 /// Delete empty parent directories from a given path. don't traverse above
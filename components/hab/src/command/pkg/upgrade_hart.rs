@@ -0,0 +1,31 @@
+use std::path::{Path,
+                PathBuf};
+
+use crate::{common::ui::{Status,
+                         UIWriter,
+                         UI},
+            error::Result,
+            hcore::crypto::{artifact,
+                            HART_FORMAT_VERSION}};
+
+/// Rewrite each of the `hart_files` which is still in the HART-1 format as HART-2, signing it
+/// again with the key it is signed with, from `cache`. Artifacts in any other format are left
+/// alone.
+pub fn start(ui: &mut UI, hart_files: &[PathBuf], cache: &Path) -> Result<()> {
+    ui.begin(format!("Upgrading {} artifact(s) to HART-2", hart_files.len()))?;
+    let mut upgraded = 0;
+    for path in hart_files {
+        let header = artifact::get_artifact_header(path)?;
+        if header.format_version != HART_FORMAT_VERSION {
+            ui.status(Status::Skipping,
+                      format!("{}, already {}", path.display(), header.format_version))?;
+            continue;
+        }
+        ui.status(Status::Updating,
+                  format!("{} with {}", path.display(), header.key_name))?;
+        artifact::upgrade(path, path, cache)?;
+        upgraded += 1;
+    }
+    ui.end(format!("Upgraded {} artifact(s).", upgraded))?;
+    Ok(())
+}
@@ -1,17 +1,313 @@
-use std::path::Path;
+use std::path::{Path,
+                PathBuf};
 
-use crate::{common::ui::{Status,
+use futures::stream::{self,
+                      StreamExt};
+use walkdir::WalkDir;
+
+use crate::{command::pkg::DEFAULT_BULK_CONCURRENCY,
+            common::ui::{Status,
                          UIWriter,
                          UI},
-            hcore::crypto::artifact};
+            hcore::{crypto::{artifact::{self,
+                                        SignaturePolicy},
+                             keys::trust_policy::TrustPolicy,
+                             SigKeyPair},
+                    error::Error as CoreError,
+                    package::{archive::TamperedFile,
+                              PackageArchive}}};
+
+use crate::error::{Error,
+                   Result};
 
-use crate::error::Result;
+/// Verifies the Habitat Artifact at `src`, or every Habitat Artifact under it when it is a
+/// directory, such as an artifact mirror. A directory is reported as a summary, on stdout as JSON
+/// if `to_json` is set, and fails if any of its artifacts could not be verified. The `policy`
+/// chooses which signatures of an artifact with several must be valid, and `trust` which of their
+/// signers are trusted. With `deep`, the files inside each artifact are also checked against the
+/// checksums of its FILES manifest.
+pub async fn start(ui: &mut UI,
+                   src: &Path,
+                   cache: &Path,
+                   policy: SignaturePolicy,
+                   trust: &TrustPolicy,
+                   deep: bool,
+                   to_json: bool)
+                   -> Result<()> {
+    if src.is_file() && !to_json {
+        ui.begin(format!("Verifying artifact {}", &src.display()))?;
+        let (name_with_rev, hash) =
+            artifact::verify_with_trust_policy(src, cache, policy.clone(), trust)?;
+        ui.status(Status::Verified,
+                  format!("checksum {} signed with {}", &hash, &name_with_rev))?;
+        if deep {
+            let tampered = PackageArchive::new(src)?.tampered_files()?;
+            for file in &tampered {
+                ui.warn(format!("{}: {}", file.path.display(), file.problem))?;
+            }
+            if !tampered.is_empty() {
+                return Err(Error::PackageFilesTampered(tampered.len(), src.to_path_buf()));
+            }
+            ui.status(Status::Verified, "every file against the FILES manifest")?;
+        }
+        ui.end(format!("Verified artifact {}.", &src.display()))?;
+        return Ok(());
+    }
 
-pub fn start(ui: &mut UI, src: &Path, cache: &Path) -> Result<()> {
-    ui.begin(format!("Verifying artifact {}", &src.display()))?;
-    let (name_with_rev, hash) = artifact::verify(src, cache)?;
-    ui.status(Status::Verified,
-              format!("checksum {} signed with {}", &hash, &name_with_rev))?;
-    ui.end(format!("Verified artifact {}.", &src.display()))?;
+    if !to_json {
+        ui.begin(format!("Verifying artifacts in {}", &src.display()))?;
+    }
+    let summary = Summary::new(verify_all(artifacts_in(src)?, cache, policy, trust, deep).await);
+    if to_json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        summary.report(ui)?;
+    }
+    let failed = summary.unknown_signer + summary.untrusted + summary.corrupt;
+    if failed > 0 {
+        return Err(Error::ArtifactsNotVerified(failed, summary.artifacts.len()));
+    }
     Ok(())
 }
+
+/// The outcome of verifying one artifact.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Verified,
+    /// The public key of none of the artifact's signers is in the key cache.
+    UnknownSigner,
+    /// A signer's key is revoked, expired, or rejected by the key trust policy.
+    Untrusted,
+    /// The artifact's header or archive is invalid, or doesn't match its signature or, when
+    /// checked, its FILES manifest.
+    Corrupt,
+}
+
+#[derive(Debug, Serialize)]
+struct ArtifactReport {
+    path:     PathBuf,
+    outcome:  Outcome,
+    signer:   Option<String>,
+    checksum: Option<String>,
+    error:    Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tampered: Vec<TamperedFile>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    verified:       usize,
+    unknown_signer: usize,
+    untrusted:      usize,
+    corrupt:        usize,
+    artifacts:      Vec<ArtifactReport>,
+}
+
+impl Summary {
+    fn new(mut artifacts: Vec<ArtifactReport>) -> Self {
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        let count = |outcome| artifacts.iter().filter(|a| a.outcome == outcome).count();
+        Summary { verified: count(Outcome::Verified),
+                  unknown_signer: count(Outcome::UnknownSigner),
+                  untrusted: count(Outcome::Untrusted),
+                  corrupt: count(Outcome::Corrupt),
+                  artifacts }
+    }
+
+    fn report(&self, ui: &mut UI) -> Result<()> {
+        for artifact in &self.artifacts {
+            let path = artifact.path.display();
+            let signer = artifact.signer.as_deref().unwrap_or_default();
+            match artifact.outcome {
+                Outcome::Verified => {
+                    ui.status(Status::Verified, format!("{} signed with {}", path, signer))?
+                }
+                Outcome::UnknownSigner => {
+                    ui.warn(format!("{}: no public key for its signer {}", path, signer))?
+                }
+                Outcome::Untrusted => {
+                    ui.warn(format!("{}: {}",
+                                    path,
+                                    artifact.error.as_deref().unwrap_or_default()))?
+                }
+                Outcome::Corrupt => {
+                    ui.warn(format!("{}: {}",
+                                    path,
+                                    artifact.error.as_deref().unwrap_or_default()))?;
+                    for file in &artifact.tampered {
+                        ui.warn(format!("  {}: {}", file.path.display(), file.problem))?;
+                    }
+                }
+            }
+        }
+        ui.end(format!("Verified {} of {} artifact(s); {} with an unknown signer, {} with an \
+                        untrusted signer, {} corrupt.",
+                       self.verified,
+                       self.artifacts.len(),
+                       self.unknown_signer,
+                       self.untrusted,
+                       self.corrupt))?;
+        Ok(())
+    }
+}
+
+/// `src` itself if it is a file, or else every Habitat Artifact under it.
+fn artifacts_in(src: &Path) -> Result<Vec<PathBuf>> {
+    if src.is_file() {
+        return Ok(vec![src.to_path_buf()]);
+    }
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        if entry.file_type().is_file() && entry.path().extension().map_or(false, |e| e == "hart") {
+            paths.push(entry.into_path());
+        }
+    }
+    Ok(paths)
+}
+
+/// Verifies each of `paths`, several at a time.
+async fn verify_all(paths: Vec<PathBuf>,
+                    cache: &Path,
+                    policy: SignaturePolicy,
+                    trust: &TrustPolicy,
+                    deep: bool)
+                    -> Vec<ArtifactReport> {
+    stream::iter(paths).map(|path| {
+                           let cache = cache.to_path_buf();
+                           let policy = policy.clone();
+                           let trust = trust.clone();
+                           tokio::task::spawn_blocking(move || {
+                               verify_one(path, &cache, policy, &trust, deep)
+                           })
+                       })
+                       .buffer_unordered(DEFAULT_BULK_CONCURRENCY)
+                       .filter_map(|report| async move { report.ok() })
+                       .collect()
+                       .await
+}
+
+fn verify_one(path: PathBuf,
+              cache: &Path,
+              policy: SignaturePolicy,
+              trust: &TrustPolicy,
+              deep: bool)
+              -> ArtifactReport {
+    let signers = match artifact::artifact_signers(&path) {
+        Ok(signers) => signers,
+        Err(e) => return ArtifactReport::failed(path, Outcome::Corrupt, None, &e),
+    };
+    let signer = signers.join(", ");
+    let missing = signers.iter()
+                         .filter_map(|signer| SigKeyPair::get_public_key_path(signer, cache).err())
+                         .collect::<Vec<_>>();
+    let unknown = match policy {
+        SignaturePolicy::Any => missing.len() == signers.len(),
+        SignaturePolicy::All(_) => !missing.is_empty(),
+    };
+    if unknown {
+        return ArtifactReport::failed(path, Outcome::UnknownSigner, Some(signer), &missing[0]);
+    }
+    let (signer, checksum) = match artifact::verify_with_trust_policy(&path, cache, policy, trust) {
+        Ok(verified) => verified,
+        Err(e @ CoreError::KeyExpired(..))
+        | Err(e @ CoreError::KeyNotTrusted(..))
+        | Err(e @ CoreError::KeyRevoked(..)) => {
+            return ArtifactReport::failed(path, Outcome::Untrusted, Some(signer), &e)
+        }
+        Err(e) => return ArtifactReport::failed(path, Outcome::Corrupt, Some(signer), &e),
+    };
+    let tampered = if deep {
+        match PackageArchive::new(&path).and_then(|mut archive| archive.tampered_files()) {
+            Ok(tampered) => tampered,
+            Err(e) => return ArtifactReport::failed(path, Outcome::Corrupt, Some(signer), &e),
+        }
+    } else {
+        Vec::new()
+    };
+    let (outcome, error) = if tampered.is_empty() {
+        (Outcome::Verified, None)
+    } else {
+        (Outcome::Corrupt,
+         Some(format!("{} file(s) do not match the FILES manifest", tampered.len())))
+    };
+    ArtifactReport { path,
+                     outcome,
+                     signer: Some(signer),
+                     checksum: Some(checksum),
+                     error,
+                     tampered }
+}
+
+impl ArtifactReport {
+    fn failed(path: PathBuf,
+              outcome: Outcome,
+              signer: Option<String>,
+              error: &dyn std::error::Error)
+              -> Self {
+        ArtifactReport { path,
+                         outcome,
+                         signer,
+                         checksum: None,
+                         error: Some(error.to_string()),
+                         tampered: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn every_artifact_in_a_directory_is_classified() {
+        let cache = TempDir::new().unwrap();
+        let known = SigKeyPair::generate_pair_for_origin("acme");
+        known.to_pair_files(cache.path()).unwrap();
+        let unknown = SigKeyPair::generate_pair_for_origin("stranger");
+        let mirror = TempDir::new().unwrap();
+        let archive = mirror.path().join("archive.tar");
+        fs::write(&archive, b"not really a tarball").unwrap();
+        fs::create_dir(mirror.path().join("x86_64-linux")).unwrap();
+        let good = mirror.path().join("x86_64-linux/acme-good.hart");
+        let stranger = mirror.path().join("stranger.hart");
+        let corrupt = mirror.path().join("acme-corrupt.hart");
+        artifact::sign(&archive, &good, &known).unwrap();
+        artifact::sign(&archive, &stranger, &unknown).unwrap();
+        artifact::sign(&archive, &corrupt, &known).unwrap();
+        let mut contents = fs::read(&corrupt).unwrap();
+        contents.extend_from_slice(b"truncated download");
+        fs::write(&corrupt, contents).unwrap();
+
+        let paths = artifacts_in(mirror.path()).unwrap();
+        assert_eq!(paths.len(), 3);
+        let summary = Summary::new(verify_all(paths.clone(),
+                                              cache.path(),
+                                              SignaturePolicy::Any,
+                                              &TrustPolicy::default(),
+                                              false).await);
+
+        assert_eq!((summary.verified, summary.unknown_signer, summary.untrusted, summary.corrupt),
+                   (1, 1, 0, 1));
+        let outcome_of = |path: &Path| {
+            summary.artifacts
+                   .iter()
+                   .find(|a| a.path == path)
+                   .map(|a| a.outcome)
+        };
+        assert_eq!(outcome_of(&good), Some(Outcome::Verified));
+        assert_eq!(outcome_of(&stranger), Some(Outcome::UnknownSigner));
+        assert_eq!(outcome_of(&corrupt), Some(Outcome::Corrupt));
+
+        let trust = TrustPolicy { revoked_keys: vec![known.name_with_rev()],
+                                  ..Default::default() };
+        let summary = Summary::new(verify_all(paths,
+                                              cache.path(),
+                                              SignaturePolicy::Any,
+                                              &trust,
+                                              false).await);
+        assert_eq!((summary.verified, summary.untrusted), (0, 2));
+    }
+}
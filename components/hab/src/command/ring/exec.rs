@@ -0,0 +1,192 @@
+use std::{io::{self,
+               Write},
+          time::{Duration,
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
+
+use futures::stream::StreamExt;
+use tabwriter::TabWriter;
+use tokio::time;
+use uuid::Uuid;
+
+use crate::{common::{types::ListenCtlAddr,
+                     ui::{Status,
+                          UIWriter,
+                          UI}},
+            config,
+            error::{Error,
+                    Result},
+            hcore::crypto::SigKeyPair,
+            protocol::{codec::SrvMessage,
+                       ctl::{SignedRequest,
+                             SupRingExec,
+                             SupRingExecStatus,
+                             SvcUpdate},
+                       net::NetErr,
+                       types::{RingCommandAck,
+                               RingCommandStatus}},
+            sup_client::{CtlGateway,
+                         SrvClient,
+                         SrvClientError}};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Gossips `svc_update` to every Supervisor in the ring from `remote_sup`, signed with `signer`
+/// along with a new ID and an expiry `ttl` from now, then waits up to `wait` for every alive
+/// Supervisor to acknowledge it.
+pub async fn start(ui: &mut UI,
+                   remote_sup: &CtlGateway,
+                   svc_update: SvcUpdate,
+                   signer: &SigKeyPair,
+                   ttl: Duration,
+                   wait: Duration)
+                   -> Result<()> {
+    let msg = signed_ring_exec(&remote_sup.addr, &svc_update, signer, SystemTime::now() + ttl)?;
+    let mut status = request_status(remote_sup, msg).await?;
+    ui.status(Status::Applying,
+              format!("ring command {} from {}", status.id, remote_sup))?;
+    let deadline = Instant::now() + wait;
+    while !is_complete(&status) && Instant::now() < deadline {
+        time::delay_for(POLL_INTERVAL).await;
+        let msg = SupRingExecStatus { id: Some(status.id.clone()), };
+        status = request_status(remote_sup, msg).await?;
+    }
+    report(ui, &status)
+}
+
+/// Shows the acknowledgments `remote_sup` has heard for the ring command `id`.
+pub async fn status(ui: &mut UI, remote_sup: &CtlGateway, id: String) -> Result<()> {
+    let status = request_status(remote_sup, SupRingExecStatus { id: Some(id) }).await?;
+    report(ui, &status)
+}
+
+fn signed_ring_exec(remote_sup: &ListenCtlAddr,
+                    svc_update: &SvcUpdate,
+                    signer: &SigKeyPair,
+                    expires_at: SystemTime)
+                    -> Result<SupRingExec> {
+    let expires_at = expires_at.duration_since(UNIX_EPOCH)
+                               .map(|d| d.as_secs())
+                               .unwrap_or_default();
+    let id = Uuid::new_v4().to_simple_ref().to_string();
+    let mut msg = SupRingExec::new(id, svc_update, expires_at);
+    msg.sign(signer, &remote_sup.to_string())?;
+    Ok(msg)
+}
+
+fn is_complete(status: &RingCommandStatus) -> bool {
+    status.acks.len() >= status.alive.unwrap_or_default() as usize
+}
+
+fn report(ui: &mut UI, status: &RingCommandStatus) -> Result<()> {
+    print_acks(&mut TabWriter::new(io::stdout()), &status.acks)?;
+    let failed = status.acks.iter().filter(|ack| !ack.applied).count();
+    if failed > 0 {
+        return Err(Error::RingExecIncomplete(status.id.clone(),
+                                             format!("{} Supervisor(s) \
+                                                      could not apply it",
+                                                     failed)));
+    }
+    if !is_complete(status) {
+        return Err(Error::RingExecIncomplete(status.id.clone(),
+                                             format!("{} of {} alive Supervisor(s) have \
+                                                      acknowledged it",
+                                                     status.acks.len(),
+                                                     status.alive.unwrap_or_default())));
+    }
+    ui.end(format!("Ring command {} was applied by {} Supervisor(s)",
+                   status.id,
+                   status.acks.len()))?;
+    Ok(())
+}
+
+async fn request_status(remote_sup: &CtlGateway,
+                        msg: impl Into<SrvMessage> + std::fmt::Debug)
+                        -> Result<RingCommandStatus> {
+    let cfg = config::load()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    let mut status = None;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "RingCommandStatus" => {
+                status = Some(reply.parse::<RingCommandStatus>()
+                                   .map_err(SrvClientError::Decode)?);
+            }
+            "NetErr" => {
+                let err = reply.parse::<NetErr>().map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => debug!("Ignoring ring command reply, {:?}", reply),
+        }
+    }
+    match status {
+        Some(status) => Ok(status),
+        None => Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+    }
+}
+
+fn print_acks<W: Write>(out: &mut W, acks: &[RingCommandAck]) -> io::Result<()> {
+    writeln!(out, "member\toutcome\tmessage")?;
+    for ack in acks {
+        writeln!(out,
+                 "{}\t{}\t{}",
+                 ack.member_id,
+                 if ack.applied { "applied" } else { "failed" },
+                 ack.message.as_deref().unwrap_or_default())?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ack(member_id: &str, applied: bool) -> RingCommandAck {
+        RingCommandAck { member_id: member_id.to_string(),
+                         applied,
+                         message: Some(String::from("Updated core/redis")) }
+    }
+
+    #[test]
+    fn a_command_is_complete_once_every_alive_supervisor_acknowledged_it() {
+        let mut status = RingCommandStatus { id:         String::from("cmd"),
+                                             expires_at: Some(600),
+                                             alive:      Some(2),
+                                             acks:       vec![ack("a", true)], };
+        assert!(!is_complete(&status));
+        status.acks.push(ack("b", false));
+        assert!(is_complete(&status));
+    }
+
+    #[test]
+    fn ring_commands_are_signed_with_a_fresh_id_and_their_expiry() {
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        let svc_update = SvcUpdate { group: Some(String::from("prod")),
+                                     ..Default::default() };
+        let expires_at = UNIX_EPOCH + Duration::from_secs(600);
+        let remote_sup = ListenCtlAddr::default();
+        let first = signed_ring_exec(&remote_sup, &svc_update, &pair, expires_at).unwrap();
+        let second = signed_ring_exec(&remote_sup, &svc_update, &pair, expires_at).unwrap();
+
+        assert_ne!(first.id, second.id);
+        assert_eq!(first.expires_at, Some(600));
+        assert_eq!(first.kind.as_deref(), Some("SvcUpdate"));
+        let signature = first.signature().unwrap();
+        assert_eq!(signature.signer, Some(pair.name_with_rev()));
+        assert!(pair.verify_payload(&first.signed_bytes(),
+                                    signature.signature.as_ref().unwrap())
+                    .is_ok());
+    }
+
+    #[test]
+    fn acks_are_printed_with_their_outcome() {
+        let mut out = Vec::new();
+        print_acks(&mut out, &[ack("a", true), ack("b", false)]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "member\toutcome\tmessage\na\tapplied\tUpdated core/redis\nb\tfailed\tUpdated \
+                    core/redis\n");
+    }
+}
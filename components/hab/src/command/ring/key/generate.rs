@@ -1,15 +1,60 @@
-use std::path::Path;
+use std::{io::{self,
+               Write},
+          path::Path};
 
 use crate::{common::ui::{UIWriter,
                          UI},
-            hcore::crypto::SymKey};
+            hcore::crypto::{keys::{passphrase,
+                                   KeyType,
+                                   Passphrase},
+                            SymKey}};
 
-use crate::error::Result;
+use crate::error::{Error,
+                   Result};
 
-pub fn start(ui: &mut UI, ring: &str, cache: &Path) -> Result<()> {
+/// Generates a ring key, or derives the given revision of it from a passphrase if one is given.
+fn new_key(ring: &str, from_passphrase: Option<(&Passphrase, &str)>) -> Result<SymKey> {
+    match from_passphrase {
+        Some((passphrase, revision)) => Ok(SymKey::from_passphrase(ring, revision, passphrase)?),
+        None => Ok(SymKey::generate_pair_for_ring(ring)),
+    }
+}
+
+pub fn start(ui: &mut UI,
+             ring: &str,
+             from_passphrase: Option<(&Passphrase, &str)>,
+             passphrase: Option<&Passphrase>,
+             cache: &Path)
+             -> Result<()> {
     ui.begin(format!("Generating ring key for {}", &ring))?;
-    let pair = SymKey::generate_pair_for_ring(ring);
-    pair.to_pair_files(cache)?;
+    let pair = new_key(ring, from_passphrase)?;
+    pair.to_pair_files_with_passphrase(cache, passphrase)?;
     ui.end(format!("Generated ring key pair {}.", &pair.name_with_rev()))?;
     Ok(())
 }
+
+/// Generates a ring key and writes it to standard output without saving it to the key cache, so
+/// that it can be piped straight into a secret manager. The key is never written to a terminal,
+/// where it would be left in scrollback or shown on a shared screen.
+pub fn start_to_stdout(ring: &str,
+                       from_passphrase: Option<(&Passphrase, &str)>,
+                       passphrase: Option<&Passphrase>)
+                       -> Result<()> {
+    if atty::is(atty::Stream::Stdout) {
+        return Err(Error::ArgumentError(String::from("Refusing to write a \
+                                                      ring key to a \
+                                                      terminal. Redirect or \
+                                                      pipe standard output \
+                                                      when using --stdout.")));
+    }
+    let pair = new_key(ring, from_passphrase)?;
+    debug!("Writing ring key {} to standard out", pair.name_with_rev());
+    let mut secret = pair.to_secret_string()?;
+    if let Some(passphrase) = passphrase {
+        secret = passphrase::encrypt(&secret, KeyType::Sym, passphrase)?;
+    }
+    let mut stdout = io::stdout();
+    stdout.write_all(secret.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use crate::{cli::gateway_util,
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
+            hcore::crypto::{keys::ring_key,
+                            SymKey},
+            protocol::ctl::{RingKeyRotationPhase,
+                            SupRingKeyRotate},
+            sup_client::CtlGateway};
+
+use crate::error::Result;
+
+/// Rotates the ring key of the given Supervisors to a new revision of the latest key in the
+/// cache. Each phase of the rotation is completed on every Supervisor before the next one
+/// starts, so that no Supervisor encrypts with a revision another cannot yet decrypt.
+///
+/// If the rotation fails part way through, running it again with the same Supervisors rotates
+/// them to a newer revision still; the revisions a Supervisor already staged or activated are
+/// retired along with the old one.
+pub async fn start(ui: &mut UI,
+                   ring: &str,
+                   remote_sups: &[CtlGateway],
+                   cache: &Path)
+                   -> Result<()> {
+    let current = SymKey::get_latest_pair_for(ring, cache)?;
+    let next = ring_key::generate_next_revision(&current)?;
+    let name_with_rev = next.name_with_rev();
+    ui.begin(format!("Rotating ring key {} to {} on {} Supervisor(s)",
+                     current.name_with_rev(),
+                     name_with_rev,
+                     remote_sups.len()))?;
+    next.to_pair_files(cache)?;
+
+    let phases = vec![(RingKeyRotationPhase::Stage,
+                       SupRingKeyRotate { key: Some(next.to_secret_string()?),
+                                          ..Default::default() }),
+                      (RingKeyRotationPhase::Activate,
+                       SupRingKeyRotate { name_with_rev: Some(name_with_rev.clone()),
+                                          ..Default::default() }),
+                      (RingKeyRotationPhase::Retire, SupRingKeyRotate::default())];
+    for (phase, mut msg) in phases {
+        msg.phase = Some(phase as i32);
+        for remote_sup in remote_sups {
+            ui.status(Status::Applying,
+                      format!("{:?} phase on {}", phase, remote_sup))?;
+            gateway_util::send(remote_sup, msg.clone()).await?;
+        }
+    }
+    ui.end(format!("Rotated ring key to {}. Supervisors started with the ring key content \
+                    instead of its name must be given the new key before they restart.",
+                   name_with_rev))?;
+    Ok(())
+}
@@ -1 +1,3 @@
 pub mod key;
+pub mod manifest;
+pub mod status;
@@ -5,11 +5,20 @@ use crate::{common::ui::{UIWriter,
             hcore::{crypto::BoxKeyPair,
                     service::ServiceGroup}};
 
-use crate::error::Result;
+use crate::error::{Error,
+                   Result};
 
+/// Generates the service key for `service_group` in `org`. Service keys are named after the group
+/// and its organization, so a group which names an organization of its own must name `org`.
 pub fn start(ui: &mut UI, org: &str, service_group: &ServiceGroup, cache: &Path) -> Result<()> {
-    ui.begin(format!("Generating service key for {} in {}", &service_group, org))?;
-    let pair = BoxKeyPair::generate_pair_for_service(org, &service_group.to_string())?;
+    let service_group = service_group.in_org(Some(org));
+    if service_group.org() != Some(org) {
+        return Err(Error::ArgumentError(format!("Service group {} is not in organization {}",
+                                                service_group, org)));
+    }
+    ui.begin(format!("Generating service key for {}", &service_group))?;
+    let name = format!("{}.{}", service_group.service(), service_group.group());
+    let pair = BoxKeyPair::generate_pair_for_service(org, &name)?;
     pair.to_pair_files(cache)?;
     ui.end(format!("Generated service key pair {}.", &pair.name_with_rev()))?;
     Ok(())
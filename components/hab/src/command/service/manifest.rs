@@ -0,0 +1,97 @@
+//! Works out what `hab svc apply-manifest` must do to make a Supervisor run the services of a
+//! manifest.
+
+use crate::cli::hab::svc::Load;
+use habitat_core::package::PackageIdent;
+use std::fmt;
+
+/// A change to the services of a Supervisor.
+#[derive(Debug)]
+pub enum Change {
+    /// Load a service which is not loaded.
+    Load(Load),
+    /// Load an already loaded service again, with the options of the manifest.
+    Reload(Load),
+    /// Unload a loaded service which is not in the manifest.
+    Unload(PackageIdent),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Load(svc_load) => write!(f, "load {}", load_ident(svc_load)),
+            Change::Reload(svc_load) => write!(f, "reload {}", load_ident(svc_load)),
+            Change::Unload(ident) => write!(f, "unload {}", ident),
+        }
+    }
+}
+
+/// The changes which converge the `loaded` services of a Supervisor to the `services` of a
+/// manifest. Services are matched by name, as a Supervisor runs at most one service of each name.
+/// Unloads come first, so that services replaced by another origin's package are unloaded before
+/// their replacement is loaded.
+pub fn plan(services: Vec<Load>, loaded: &[PackageIdent]) -> Vec<Change> {
+    let mut changes = loaded.iter()
+                            .filter(|ident| {
+                                !services.iter()
+                                         .any(|svc_load| load_ident(svc_load).name == ident.name)
+                            })
+                            .cloned()
+                            .map(Change::Unload)
+                            .collect::<Vec<_>>();
+    for mut svc_load in services {
+        let ident = load_ident(&svc_load);
+        match loaded.iter().find(|l| l.name == ident.name) {
+            Some(l) if l.origin == ident.origin => {
+                // The Supervisor only accepts a load of a loaded service when it is forced, and
+                // only restarts the service if its options changed.
+                svc_load.force = true;
+                changes.push(Change::Reload(svc_load));
+            }
+            Some(l) => {
+                changes.push(Change::Unload(l.clone()));
+                changes.push(Change::Load(svc_load));
+            }
+            None => changes.push(Change::Load(svc_load)),
+        }
+    }
+    changes
+}
+
+fn load_ident(svc_load: &Load) -> PackageIdent { svc_load.pkg_ident.clone().pkg_ident() }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::hab::svc;
+
+    #[test]
+    fn plan_loads_reloads_and_unloads() {
+        let manifest = r#"
+[[services]]
+pkg_ident = "core/redis"
+
+[[services]]
+pkg_ident = "core/nginx"
+channel = "unstable"
+
+[[services]]
+pkg_ident = "acme/app"
+"#;
+        let services = svc::svc_loads_from_str(manifest).unwrap();
+        let loaded = ["core/redis/4.0.14/20200421191514".parse().unwrap(),
+                      "core/postgresql/11.2.0/20200421191514".parse().unwrap(),
+                      "core/app/1.0.0/20200421191514".parse().unwrap()];
+
+        let changes = plan(services, &loaded).iter()
+                                             .map(ToString::to_string)
+                                             .collect::<Vec<_>>();
+
+        assert_eq!(changes,
+                   vec!["unload core/postgresql/11.2.0/20200421191514",
+                        "reload core/redis",
+                        "load core/nginx",
+                        "unload core/app/1.0.0/20200421191514",
+                        "load acme/app"]);
+    }
+}
@@ -0,0 +1,526 @@
+//! The output formats of `hab svc status`.
+//!
+//! The `table` format is kept exactly as it always was, since scripts parse it. `wide` adds the
+//! columns describing how each service's process is scheduled, and `json` and `yaml` give the
+//! same information in a form which doesn't need parsing.
+//!
+//! With `--watch`, the status is requested again every `--interval` and the table redrawn, with
+//! the services whose state changed since the previous refresh highlighted.
+//!
+//! With `--http-gateway`, the status is asked of the Supervisor's HTTP Gateway instead, and
+//! printed as the JSON it replies with.
+
+use crate::{common::{self,
+                     ui::{UIWriter,
+                          UI}},
+            config,
+            error::{Error,
+                    Result}};
+use futures::stream::StreamExt;
+use habitat_core::package::PackageIdent;
+use reqwest::StatusCode;
+use habitat_sup_client::{CtlGateway,
+                         SrvClient,
+                         SrvClientError};
+use habitat_sup_protocol::{self as sup_proto,
+                           codec::SrvMessage,
+                           types::{DesiredState,
+                                   ProcessState,
+                                   ServiceStatus,
+                                   ServiceTransition}};
+use std::{io::Write,
+          result,
+          str::FromStr,
+          time::Duration};
+use tabwriter::TabWriter;
+use termcolor::{Color,
+                ColorSpec};
+use tokio::time;
+use url::Url;
+
+/// The columns of the `table` format.
+pub const STATUS_HEADER: &[&str] =
+    &["package", "type", "desired", "state", "elapsed (s)", "pid", "group"];
+
+/// The columns the `wide` format adds to the `table` format.
+pub const STATUS_DETAILS_HEADER: &[&str] =
+    &["cpuset", "nice", "priority class", "limits", "restart pending"];
+
+/// How `hab svc status` prints the status of services.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusFormat {
+    Table,
+    Wide,
+    Json,
+    Yaml,
+}
+
+impl StatusFormat {
+    pub const VARIANTS: &'static [&'static str] = &["table", "wide", "json", "yaml"];
+
+    /// Whether the format is printed as one document, rather than line by line.
+    pub fn is_structured(self) -> bool {
+        match self {
+            StatusFormat::Table | StatusFormat::Wide => false,
+            StatusFormat::Json | StatusFormat::Yaml => true,
+        }
+    }
+}
+
+impl Default for StatusFormat {
+    fn default() -> Self { StatusFormat::Table }
+}
+
+impl FromStr for StatusFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "table" => Ok(StatusFormat::Table),
+            "wide" => Ok(StatusFormat::Wide),
+            "json" => Ok(StatusFormat::Json),
+            "yaml" => Ok(StatusFormat::Yaml),
+            _ => {
+                Err(Error::ArgumentError(format!("Invalid status format '{}', expected one of \
+                                                  {}",
+                                                 value,
+                                                 Self::VARIANTS.join(", "))))
+            }
+        }
+    }
+}
+
+/// The status of one service, as printed in the structured formats.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ServiceRecord {
+    pub package:         String,
+    pub service_group:   String,
+    pub desired:         Option<String>,
+    pub state:           String,
+    /// Seconds since the service's process last changed state.
+    pub elapsed:         Option<u64>,
+    pub pid:             Option<u32>,
+    pub cpuset:          Option<String>,
+    pub nice:            Option<i32>,
+    pub priority_class:  Option<String>,
+    pub limits:          Option<String>,
+    /// Whether changes made with `hab svc update --no-restart` are waiting for a restart.
+    pub restart_pending: bool,
+}
+
+impl From<ServiceStatus> for ServiceRecord {
+    fn from(status: ServiceStatus) -> Self {
+        let desired = status.desired_state
+                            .and_then(DesiredState::from_i32)
+                            .filter(|s| *s != DesiredState::DesiredNone)
+                            .map(|s| s.to_string());
+        let (state, elapsed, pid) = match status.process {
+            Some(process) => {
+                (ProcessState::from_i32(process.state).unwrap_or_default(),
+                 process.elapsed,
+                 process.pid)
+            }
+            None => (ProcessState::default(), None, None),
+        };
+        ServiceRecord { package: status.ident.to_string(),
+                        service_group: status.service_group.to_string(),
+                        desired,
+                        state: state.to_string(),
+                        elapsed,
+                        pid,
+                        cpuset: status.cpuset,
+                        nice: status.nice,
+                        priority_class: status.priority_class,
+                        limits: status.limits,
+                        restart_pending: status.restart_pending.unwrap_or(false) }
+    }
+}
+
+impl ServiceRecord {
+    /// The columns of the service's row in the `table` format, or in the `wide` format if
+    /// `details` is set.
+    fn columns(&self, details: bool) -> Vec<String> {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "<none>".to_string());
+        let mut columns = vec![self.package.clone(),
+                               "standalone".to_string(),
+                               or_none(self.desired.clone()),
+                               self.state.clone(),
+                               or_none(self.elapsed.map(|e| e.to_string())),
+                               or_none(self.pid.map(|p| p.to_string())),
+                               self.service_group.clone()];
+        if details {
+            columns.extend(vec![or_none(self.cpuset.clone()),
+                                or_none(self.nice.map(|n| n.to_string())),
+                                or_none(self.priority_class.clone()),
+                                or_none(self.limits.clone()),
+                                self.restart_pending.to_string()]);
+        }
+        columns
+    }
+}
+
+/// A lifecycle transition of a service, as printed in the structured formats with `--history`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TransitionRecord {
+    /// Seconds since the UNIX epoch.
+    pub timestamp:     u64,
+    pub package:       String,
+    pub service_group: String,
+    pub event:         String,
+    pub detail:        Option<String>,
+}
+
+impl From<ServiceTransition> for TransitionRecord {
+    fn from(transition: ServiceTransition) -> Self {
+        TransitionRecord { timestamp:     transition.timestamp,
+                           package:       transition.ident.to_string(),
+                           service_group: transition.service_group.to_string(),
+                           event:         transition.event,
+                           detail:        transition.detail, }
+    }
+}
+
+/// Everything a Supervisor replied with, in one of the structured formats.
+#[derive(Debug, Default, Serialize)]
+pub struct StatusReport {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub services:    Vec<ServiceRecord>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<TransitionRecord>,
+}
+
+impl StatusReport {
+    /// Adds a reply of the Supervisor to the report.
+    pub fn add(&mut self, reply: &SrvMessage) -> result::Result<(), SrvClientError> {
+        match reply.message_id() {
+            "ServiceStatus" => {
+                let status = reply.parse::<ServiceStatus>()
+                                  .map_err(SrvClientError::Decode)?;
+                self.services.push(status.into());
+            }
+            "ServiceTransition" => {
+                let transition = reply.parse::<ServiceTransition>()
+                                      .map_err(SrvClientError::Decode)?;
+                self.transitions.push(transition.into());
+            }
+            // No services are loaded, which is an empty report.
+            "NetOk" => {}
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err));
+            }
+            _ => {
+                warn!("Unexpected status message, {:?}", reply);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W, format: StatusFormat) -> Result<()> {
+        match format {
+            StatusFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, self)?;
+                writeln!(out)?;
+            }
+            StatusFormat::Yaml => write!(out, "{}", serde_yaml::to_string(self)?)?,
+            StatusFormat::Table | StatusFormat::Wide => {
+                unreachable!("tables are printed as the replies arrive")
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Asks `remote_sup` for the status of the services matching `pkg_ident`, or of every service,
+/// or for their recorded transitions if `history` is set.
+pub async fn request(remote_sup: &CtlGateway,
+                     pkg_ident: Option<PackageIdent>,
+                     history: bool)
+                     -> Result<StatusReport> {
+    let cfg = config::load()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let mut msg = sup_proto::ctl::SvcStatus::default();
+    msg.ident = pkg_ident.map(Into::into);
+    msg.history = Some(history);
+
+    let mut report = StatusReport::default();
+    let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    while let Some(message_result) = response.next().await {
+        report.add(&message_result?)?;
+    }
+    Ok(report)
+}
+
+/// A Supervisor's HTTP Gateway, and the bearer token to send it if it requires one.
+#[derive(Clone, Debug)]
+pub struct HttpGateway {
+    pub url:        Url,
+    pub auth_token: Option<String>,
+}
+
+/// Asks the HTTP Gateway `gateway` for the status of every service, returning the JSON it
+/// replies with.
+pub async fn request_http(gateway: &HttpGateway) -> Result<String> {
+    let mut request = reqwest::Client::new().get(gateway.url.join("services")?);
+    if let Some(ref token) = gateway.auth_token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send()
+                          .await
+                          .map_err(|e| Error::HttpGateway(e.to_string()))?;
+    match response.status() {
+        StatusCode::OK => {
+            response.text()
+                    .await
+                    .map_err(|e| Error::HttpGateway(e.to_string()))
+        }
+        StatusCode::UNAUTHORIZED => {
+            Err(Error::HttpGateway(String::from("it requires an authentication token; give it \
+                                                 with --http-auth-token-file or \
+                                                 HAB_SUP_GATEWAY_AUTH_TOKEN")))
+        }
+        status => Err(Error::HttpGateway(format!("it replied {}", status))),
+    }
+}
+
+/// How a service's row changed since the previous refresh of `hab svc status --watch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RowChange {
+    Unchanged,
+    /// The service was loaded.
+    Added,
+    /// The service's desired state, process state or pid changed.
+    Changed,
+    /// The service was unloaded. Its row is shown once more, as it was.
+    Removed,
+}
+
+impl RowChange {
+    fn color_spec(self) -> ColorSpec {
+        let mut spec = ColorSpec::new();
+        match self {
+            RowChange::Unchanged => {}
+            RowChange::Added => {
+                spec.set_fg(Some(Color::Green)).set_bold(true);
+            }
+            RowChange::Changed => {
+                spec.set_fg(Some(Color::Yellow)).set_bold(true);
+            }
+            RowChange::Removed => {
+                spec.set_fg(Some(Color::Red));
+            }
+        }
+        spec
+    }
+}
+
+/// Pairs each of the `current` services, and each of the `previous` ones which is gone, with how
+/// it changed. Services are matched by service group, which is unique on a Supervisor. The
+/// elapsed time is ignored, as it changes on every refresh.
+pub fn diff<'a>(previous: &'a [ServiceRecord],
+                current: &'a [ServiceRecord])
+                -> Vec<(&'a ServiceRecord, RowChange)> {
+    let mut rows = current.iter()
+                          .map(|record| {
+                              let change = match previous.iter().find(|p| {
+                                                                  p.service_group
+                                                                  == record.service_group
+                                                              }) {
+                                  None => RowChange::Added,
+                                  Some(p) if p.desired != record.desired
+                                             || p.state != record.state
+                                             || p.pid != record.pid =>
+                                  {
+                                      RowChange::Changed
+                                  }
+                                  Some(_) => RowChange::Unchanged,
+                              };
+                              (record, change)
+                          })
+                          .collect::<Vec<_>>();
+    rows.extend(previous.iter()
+                        .filter(|p| {
+                            !current.iter()
+                                    .any(|record| record.service_group == p.service_group)
+                        })
+                        .map(|p| (p, RowChange::Removed)));
+    rows
+}
+
+/// Redraws the status of the services matching `pkg_ident`, or of every service, every
+/// `interval` until interrupted.
+pub async fn watch(ui: &mut UI,
+                   remote_sup: &CtlGateway,
+                   pkg_ident: Option<PackageIdent>,
+                   details: bool,
+                   interval: Duration)
+                   -> Result<()> {
+    let mut previous = None;
+    loop {
+        let services = request(remote_sup, pkg_ident.clone(), false).await?.services;
+        // Nothing is highlighted when the table is first drawn.
+        let rows = diff(previous.as_ref().unwrap_or(&services), &services);
+        redraw(ui, remote_sup, &rows, details, interval)?;
+        previous = Some(services);
+        time::delay_for(interval).await;
+    }
+}
+
+fn redraw(ui: &mut UI,
+          remote_sup: &CtlGateway,
+          rows: &[(&ServiceRecord, RowChange)],
+          details: bool,
+          interval: Duration)
+          -> Result<()> {
+    let mut tw = TabWriter::new(Vec::new());
+    if details {
+        writeln!(tw,
+                 "{}\t{}",
+                 STATUS_HEADER.join("\t"),
+                 STATUS_DETAILS_HEADER.join("\t"))?;
+    } else {
+        writeln!(tw, "{}", STATUS_HEADER.join("\t"))?;
+    }
+    for (record, _) in rows {
+        writeln!(tw, "{}", record.columns(details).join("\t"))?;
+    }
+    tw.flush()?;
+    let table = String::from_utf8(tw.into_inner().unwrap()).unwrap();
+
+    if ui.is_out_a_terminal() {
+        // Clear the screen and move the cursor to its top left corner.
+        write!(ui.out(), "\x1b[2J\x1b[H")?;
+    }
+    writeln!(ui.out(),
+             "Every {}s: hab svc status --remote-sup {}\n",
+             interval.as_secs(),
+             remote_sup)?;
+    let mut lines = table.lines();
+    if let Some(header) = lines.next() {
+        writeln!(ui.out(), "{}", header)?;
+    }
+    for (line, (_, change)) in lines.zip(rows) {
+        common::ui::println(ui.out(), line.as_bytes(), &change.color_spec())?;
+    }
+    if rows.is_empty() {
+        writeln!(ui.out(), "No services loaded.")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::service::ServiceGroup;
+    use habitat_sup_protocol::types::ProcessStatus;
+    use std::{io::Read,
+              net::TcpListener,
+              thread};
+
+    #[tokio::test]
+    async fn http_gateway_requests_carry_the_auth_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: \
+                               close\r\n\r\n[]")
+                  .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let gateway = HttpGateway { url:        url.parse().unwrap(),
+                                    auth_token: Some("s3cr3t".to_string()), };
+
+        assert_eq!(request_http(&gateway).await.unwrap(), "[]");
+        let request = server.join().unwrap().to_lowercase();
+        assert!(request.starts_with("get /services "));
+        assert!(request.contains("\r\nauthorization: bearer s3cr3t\r\n"));
+    }
+
+    #[test]
+    fn json_report_of_a_running_service() {
+        let ident: PackageIdent = "core/redis/4.0.14/20200421191514".parse().unwrap();
+        let service_group: ServiceGroup = "redis.default".parse().unwrap();
+        let status = ServiceStatus { ident:           ident.into(),
+                                     process:         Some(ProcessStatus { elapsed: Some(42),
+                                                                           pid:     Some(1234),
+                                                                           state:
+                                                                               ProcessState::Up
+                                                                               as i32, }),
+                                     service_group:   service_group.into(),
+                                     desired_state:   Some(DesiredState::DesiredUp as i32),
+                                     cpuset:          None,
+                                     nice:            Some(5),
+                                     priority_class:  None,
+                                     limits:          None,
+                                     restart_pending: Some(true), };
+        let report = StatusReport { services:    vec![status.into()],
+                                    transitions: Vec::new(), };
+
+        let mut out = Vec::new();
+        report.write(&mut out, StatusFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(value["services"][0]["package"], "core/redis/4.0.14/20200421191514");
+        assert_eq!(value["services"][0]["service_group"], "redis.default");
+        assert_eq!(value["services"][0]["desired"], "up");
+        assert_eq!(value["services"][0]["state"], "up");
+        assert_eq!(value["services"][0]["pid"], 1234);
+        assert_eq!(value["services"][0]["nice"], 5);
+        assert_eq!(value["services"][0]["restart_pending"], true);
+        assert!(value.get("transitions").is_none());
+    }
+
+    fn record(service_group: &str, state: &str, pid: Option<u32>) -> ServiceRecord {
+        ServiceRecord { package: "core/redis/4.0.14/20200421191514".to_string(),
+                        service_group: service_group.to_string(),
+                        desired: Some("up".to_string()),
+                        state: state.to_string(),
+                        elapsed: Some(10),
+                        pid,
+                        cpuset: None,
+                        nice: None,
+                        priority_class: None,
+                        limits: None,
+                        restart_pending: false }
+    }
+
+    #[test]
+    fn diff_highlights_changed_added_and_removed_services() {
+        let previous = vec![record("redis.default", "up", Some(1)),
+                            record("nginx.default", "up", Some(2)),
+                            record("app.default", "up", Some(3))];
+        let mut unchanged = record("nginx.default", "up", Some(2));
+        unchanged.elapsed = Some(20);
+        let current = vec![record("redis.default", "down", None),
+                           unchanged,
+                           record("postgresql.default", "up", Some(4))];
+
+        let changes = diff(&previous, &current).into_iter()
+                                               .map(|(r, c)| (r.service_group.as_str(), c))
+                                               .collect::<Vec<_>>();
+
+        assert_eq!(changes,
+                   vec![("redis.default", RowChange::Changed),
+                        ("nginx.default", RowChange::Unchanged),
+                        ("postgresql.default", RowChange::Added),
+                        ("app.default", RowChange::Removed)]);
+    }
+
+    #[test]
+    fn parse_status_format() {
+        assert_eq!("wide".parse::<StatusFormat>().unwrap(), StatusFormat::Wide);
+        assert!("yaml".parse::<StatusFormat>().unwrap().is_structured());
+        assert!("xml".parse::<StatusFormat>().is_err());
+    }
+}
@@ -1,4 +1,6 @@
+pub mod cache;
 pub mod docker;
 pub mod enter;
+pub mod keys;
 
 pub fn native_studio_support() -> bool { cfg!(target_os = "linux") || cfg!(target_os = "windows") }
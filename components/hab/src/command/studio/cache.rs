@@ -0,0 +1,242 @@
+//! Sharing of the artifact and key caches with the Studio.
+//!
+//! `--cache-artifacts` and `--cache-keys` each take a mode, `rw`, `ro` or `off`, optionally
+//! followed by the origins whose artifacts or keys are shared (ex: `ro:core,acme`). When origins
+//! are given, the Docker Studio is given a staging directory holding only their artifacts in
+//! place of the whole artifact cache. Artifacts built in the Studio are moved into the cache
+//! when it exits, unless the cache is shared read-only.
+
+use crate::{error::{Error,
+                    Result},
+            hcore::{crypto::artifact,
+                    package::{PackageArchive,
+                              PackageIdent}}};
+use std::{collections::HashSet,
+          ffi::OsString,
+          fs,
+          path::{Path,
+                 PathBuf},
+          str::FromStr};
+
+/// How a cache is shared with the Studio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    ReadWrite,
+    ReadOnly,
+    Off,
+}
+
+impl Default for CacheMode {
+    fn default() -> Self { CacheMode::ReadWrite }
+}
+
+impl FromStr for CacheMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "rw" => Ok(CacheMode::ReadWrite),
+            "ro" => Ok(CacheMode::ReadOnly),
+            "off" => Ok(CacheMode::Off),
+            _ => {
+                Err(Error::ArgumentError(format!("Invalid cache mode '{}', expected one of rw, \
+                                                  ro or off",
+                                                 value)))
+            }
+        }
+    }
+}
+
+/// How one cache is shared with the Studio, and which origins' contents are shared.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheMount {
+    pub mode:    CacheMode,
+    /// Only the contents of these origins are shared; all of them are when this is empty.
+    pub origins: Vec<String>,
+}
+
+impl CacheMount {
+    /// The suffix of the Docker volume the cache is mounted with.
+    pub fn volume_suffix(&self) -> &'static str {
+        match self.mode {
+            CacheMode::ReadOnly => ":ro",
+            CacheMode::ReadWrite | CacheMode::Off => "",
+        }
+    }
+
+    /// Whether the artifact at `path` belongs to one of the shared origins. Its origin is the
+    /// one recorded in the artifact rather than guessed from its file name, as origins and
+    /// package names may both contain a `-`.
+    fn includes(&self, path: &Path) -> bool {
+        if self.origins.is_empty() {
+            return true;
+        }
+        match artifact_ident(path) {
+            Ok(ident) => self.origins.contains(&ident.origin),
+            Err(e) => {
+                debug!("Not staging {}: {}", path.display(), e);
+                false
+            }
+        }
+    }
+}
+
+/// The ident of the artifact at `path`, from the metadata in its header if it has any, and
+/// otherwise from its archive.
+fn artifact_ident(path: &Path) -> Result<PackageIdent> {
+    match artifact::get_artifact_header(path)?.metadata {
+        Some(metadata) => Ok(metadata.ident),
+        None => Ok(PackageArchive::new(path)?.ident()?),
+    }
+}
+
+impl FromStr for CacheMount {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut parts = value.splitn(2, ':');
+        let mode = parts.next().unwrap_or_default().parse()?;
+        let origins = parts.next()
+                           .unwrap_or_default()
+                           .split(',')
+                           .filter(|o| !o.is_empty())
+                           .map(str::to_string)
+                           .collect::<Vec<_>>();
+        if mode == CacheMode::Off && !origins.is_empty() {
+            return Err(Error::ArgumentError(format!("Cannot select origins of a cache which \
+                                                     is off: '{}'",
+                                                    value)));
+        }
+        Ok(CacheMount { mode, origins })
+    }
+}
+
+/// The artifacts of selected origins, hard linked (or copied) into a staging directory which is
+/// mounted into a Docker Studio in place of the artifact cache.
+#[derive(Debug)]
+pub struct StagedArtifacts {
+    dir:        PathBuf,
+    cache:      PathBuf,
+    staged:     HashSet<OsString>,
+    write_back: bool,
+}
+
+impl StagedArtifacts {
+    /// Stage the artifacts in `cache` which are included in `mount` into a new directory at
+    /// `dir`.
+    ///
+    /// # Failures
+    ///
+    /// * Fails if `dir` already exists
+    pub fn stage<P, Q>(mount: &CacheMount, cache: P, dir: Q) -> Result<Self>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        let cache = cache.as_ref();
+        let dir = dir.as_ref();
+        fs::create_dir(dir)?;
+        let mut staged = HashSet::new();
+        for entry in fs::read_dir(cache)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            if !entry.file_type()?.is_file() || !mount.includes(&entry.path()) {
+                continue;
+            }
+            let target = dir.join(&file_name);
+            if fs::hard_link(entry.path(), &target).is_err() {
+                fs::copy(entry.path(), &target)?;
+            }
+            staged.insert(file_name);
+        }
+        Ok(StagedArtifacts { dir: dir.to_path_buf(),
+                             cache: cache.to_path_buf(),
+                             staged,
+                             write_back: mount.mode == CacheMode::ReadWrite })
+    }
+
+    /// The directory to mount in place of the artifact cache.
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// The number of artifacts staged.
+    pub fn count(&self) -> usize { self.staged.len() }
+
+    /// Move the artifacts created in the Studio into the artifact cache, unless it is shared
+    /// read-only, and remove the staging directory.
+    pub fn finish(self) -> Result<()> {
+        if self.write_back {
+            for entry in fs::read_dir(&self.dir)? {
+                let entry = entry?;
+                let file_name = entry.file_name();
+                let target = self.cache.join(&file_name);
+                if !self.staged.contains(&file_name) && !target.exists() {
+                    debug!("Moving {} built in the Studio into the artifact cache",
+                           file_name.to_string_lossy());
+                    if fs::rename(entry.path(), &target).is_err() {
+                        fs::copy(entry.path(), &target)?;
+                    }
+                }
+            }
+        }
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcore::{crypto::SigKeyPair,
+                       package::target};
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_cache_mount() {
+        assert_eq!("rw".parse::<CacheMount>().unwrap(), CacheMount::default());
+        assert_eq!("ro:core,acme".parse::<CacheMount>().unwrap(),
+                   CacheMount { mode:    CacheMode::ReadOnly,
+                                origins: vec!["core".to_string(), "acme".to_string()], });
+        assert_eq!("off".parse::<CacheMount>().unwrap().mode, CacheMode::Off);
+        assert!("off:core".parse::<CacheMount>().is_err());
+        assert!("rx".parse::<CacheMount>().is_err());
+    }
+
+    #[test]
+    fn only_selected_origins_artifacts_are_staged_and_new_ones_written_back() {
+        let cache = TempDir::new().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        let archive = cache.path().join("archive.tar");
+        fs::write(&archive, b"not really a tarball").unwrap();
+        // An origin named like another followed by `-` mustn't be taken for it.
+        for ident in &["core/redis/4.0.14/20200421191514",
+                       "acme/app/1.0.0/20200421191514",
+                       "acme-corp/app/1.0.0/20200421191514"]
+        {
+            let ident = ident.parse::<PackageIdent>().unwrap();
+            let hart = cache.path()
+                            .join(ident.archive_name_with_target(target::X86_64_LINUX).unwrap());
+            artifact::sign_with_metadata(&archive,
+                                         &hart,
+                                         &pair,
+                                         ident,
+                                         target::X86_64_LINUX,
+                                         Utc::now()).unwrap();
+        }
+        fs::remove_file(&archive).unwrap();
+        let staging = TempDir::new().unwrap();
+        let dir = staging.path().join("artifacts");
+        let mount = "rw:acme".parse::<CacheMount>().unwrap();
+
+        let staged = StagedArtifacts::stage(&mount, cache.path(), &dir).unwrap();
+        assert_eq!(staged.count(), 1);
+        assert!(dir.join("acme-app-1.0.0-20200421191514-x86_64-linux.hart")
+                   .exists());
+
+        let built = "acme-app-1.0.1-20200521191514-x86_64-linux.hart";
+        fs::write(dir.join(built), built).unwrap();
+        staged.finish().unwrap();
+
+        assert!(!dir.exists());
+        assert!(cache.path().join(built).exists());
+    }
+}
@@ -1,7 +1,14 @@
-use crate::{command::studio::enter::{ARTIFACT_PATH_ENVVAR,
-                                     CERT_PATH_ENVVAR,
-                                     SSL_CERT_FILE_ENVVAR},
-            common::ui::UI,
+use crate::{command::studio::{cache::{CacheMode,
+                                      StagedArtifacts},
+                              enter::{CacheMounts,
+                                      Injection,
+                                      ARTIFACT_PATH_ENVVAR,
+                                      CERT_PATH_ENVVAR,
+                                      SSL_CERT_FILE_ENVVAR},
+                              keys::InjectedKeys},
+            common::ui::{Status,
+                         UIWriter,
+                         UI},
             error::{Error,
                     Result},
             hcore::{crypto::CACHE_KEY_PATH_ENV_VAR,
@@ -20,17 +27,23 @@ use std::{env,
                 OsString},
           path::{Path,
                  PathBuf},
-          process::{Command,
+          process::{self as std_process,
+                    Command,
                     Stdio}};
+use uuid::Uuid;
 
 const DOCKER_IMAGE: &str = "habitat/default-studio";
 const DOCKER_WINDOWS_IMAGE: &str = "habitat/win-studio";
 const DOCKER_IMAGE_ENVVAR: &str = "HAB_DOCKER_STUDIO_IMAGE";
 const DOCKER_OPTS_ENVVAR: &str = "HAB_DOCKER_OPTS";
 const DOCKER_SOCKET: &str = "/var/run/docker.sock";
-const HAB_STUDIO_SECRET: &str = "HAB_STUDIO_SECRET_";
+pub const HAB_STUDIO_SECRET: &str = "HAB_STUDIO_SECRET_";
 
-pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
+pub fn start_docker_studio(ui: &mut UI,
+                           args: &[OsString],
+                           injection: &Injection,
+                           cache_mounts: &CacheMounts)
+                           -> Result<()> {
     let mut args = args.to_vec();
     if args.get(0) == Some(&OsString::from("rm")) {
         return Err(Error::CannotRemoveDockerStudio);
@@ -53,7 +66,8 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
         Ok(val) => PathBuf::from(val),
         Err(_) => (&*CACHE_KEY_PATH).to_path_buf(),
     };
-    if !local_cache_key_path.exists() {
+    let mount_keys = cache_mounts.keys.mode != CacheMode::Off;
+    if mount_keys && !local_cache_key_path.exists() {
         return Err(Error::FileNotFound(format!("{}\nRun `hab setup` to \
                                                 create an origin or use \
                                                 `hab origin key` to \
@@ -61,18 +75,63 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
                                                local_cache_key_path.display())));
     }
 
+    // Mount only the selected origin keys, if any were selected, rather than the whole key cache.
+    let injected_keys = if !mount_keys || injection.keys.is_empty() {
+        None
+    } else {
+        let staging_dir =
+            local_cache_key_path.with_file_name(format!("studio-keys-{}", Uuid::new_v4()));
+        let injected = InjectedKeys::stage(&injection.keys, &local_cache_key_path, staging_dir)?;
+        for file_name in injected.file_names() {
+            ui.status(Status::Using, format!("{} in the Studio", file_name))?;
+        }
+        Some(injected)
+    };
+    let mounted_key_path = injected_keys.as_ref()
+                                        .map_or(local_cache_key_path.as_path(), |k| k.dir());
+
     let mut volumes = vec![format!("{}:{}{}",
                                    env::current_dir().unwrap().to_string_lossy(),
                                    mnt_prefix,
-                                   "/src"),
-                           format!("{}:{}/{}",
-                                   local_cache_key_path.display(),
-                                   mnt_prefix,
-                                   CACHE_KEY_PATH_POSTFIX),];
-    if let Ok(cache_artifact_path) = henv::var(ARTIFACT_PATH_ENVVAR) {
+                                   "/src")];
+    if mount_keys {
+        volumes.push(format!("{}:{}/{}{}",
+                             mounted_key_path.display(),
+                             mnt_prefix,
+                             CACHE_KEY_PATH_POSTFIX,
+                             cache_mounts.keys.volume_suffix()));
+    }
+    // Mount only the artifacts of the selected origins, if any were selected, rather than the
+    // whole artifact cache.
+    let mount_artifacts = cache_mounts.artifacts.mode != CacheMode::Off;
+    let mut staged_artifacts = None;
+    if let Some(cache_artifact_path) = henv::var(ARTIFACT_PATH_ENVVAR).ok()
+                                                                      .filter(|_| mount_artifacts)
+    {
+        let mounted_artifact_path = if cache_mounts.artifacts.origins.is_empty() {
+            cache_artifact_path
+        } else {
+            let staging_dir = Path::new(&cache_artifact_path).with_file_name(format!(
+                "studio-artifacts-{}",
+                Uuid::new_v4()
+            ));
+            let staged = StagedArtifacts::stage(&cache_mounts.artifacts,
+                                                &cache_artifact_path,
+                                                staging_dir)?;
+            ui.status(Status::Using,
+                      format!("{} artifacts of {} in the Studio",
+                              staged.count(),
+                              cache_mounts.artifacts.origins.join(", ")))?;
+            let dir = staged.dir().display().to_string();
+            staged_artifacts = Some(staged);
+            dir
+        };
         // Don't use Path::join here as "\" can cause problems in Docker mounts
-        volumes.push(format!("{}:{}/{}",
-                             cache_artifact_path, mnt_prefix, CACHE_ARTIFACT_PATH));
+        volumes.push(format!("{}:{}/{}{}",
+                             mounted_artifact_path,
+                             mnt_prefix,
+                             CACHE_ARTIFACT_PATH,
+                             cache_mounts.artifacts.volume_suffix()));
     }
     if let Ok(cache_ssl_path) = henv::var(CERT_PATH_ENVVAR) {
         // Don't use Path::join here as "\" can cause problems in Docker mounts
@@ -94,6 +153,7 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
                             String::from("HAB_ORIGIN"),
                             String::from("HAB_ORIGIN_KEYS"),
                             String::from("HAB_STUDIO_BACKLINE_PKG"),
+                            String::from("HAB_STUDIO_NO_KEYS"),
                             String::from("HAB_STUDIO_NOPROFILE"),
                             String::from("HAB_STUDIO_NOSTUDIORC"),
                             String::from("HAB_STUDIO_SUP"),
@@ -129,7 +189,9 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
                   volumes.iter(),
                   env_vars.iter(),
                   image,
-                  using_windows_containers)
+                  using_windows_containers,
+                  injected_keys,
+                  staged_artifacts)
 }
 
 fn update_ssl_cert_file_envvar(mnt_prefix: &str) {
@@ -235,7 +297,9 @@ fn run_container<I, J, S, T>(docker_cmd: PathBuf,
                              volumes: I,
                              env_vars: J,
                              image: String,
-                             using_windows_containers: bool)
+                             using_windows_containers: bool,
+                             injected_keys: Option<InjectedKeys>,
+                             staged_artifacts: Option<StagedArtifacts>)
                              -> Result<()>
     where I: IntoIterator<Item = S>,
           J: IntoIterator<Item = T>,
@@ -296,7 +360,21 @@ fn run_container<I, J, S, T>(docker_cmd: PathBuf,
     }
 
     unset_proxy_env_vars();
-    process::become_command(docker_cmd, &cmd_args)?;
+    if injected_keys.is_none() && staged_artifacts.is_none() {
+        process::become_command(docker_cmd, &cmd_args)?;
+    } else {
+        // The injected keys and staged artifacts must outlive the container so that they can be
+        // cleaned up once it exits, so the container can't simply replace this process.
+        debug!("Running command: {:?} {:?}", docker_cmd, cmd_args);
+        let status = Command::new(docker_cmd).args(&cmd_args).status();
+        if let Some(injected_keys) = injected_keys {
+            injected_keys.scrub()?;
+        }
+        if let Some(staged_artifacts) = staged_artifacts {
+            staged_artifacts.finish()?;
+        }
+        std_process::exit(status?.code().unwrap_or(1));
+    }
     Ok(())
 }
 
@@ -10,7 +10,10 @@ use crate::{common::ui::{UIWriter,
                     env as henv,
                     fs}};
 
-use crate::{config,
+use crate::{command::studio::{cache::{CacheMode,
+                                      CacheMount},
+                              docker::HAB_STUDIO_SECRET},
+            config,
             error::{Error,
                     Result},
             BLDR_URL_ENVVAR,
@@ -24,6 +27,147 @@ pub const CERT_PATH_ENVVAR: &str = "CERT_PATH";
 pub const SSL_CERT_FILE_ENVVAR: &str = "SSL_CERT_FILE";
 
 const STUDIO_PACKAGE_IDENT: &str = "core/hab-studio";
+const INJECT_KEY_OPTION: &str = "--inject-key";
+const INJECT_SECRET_OPTION: &str = "--inject-secret";
+const CACHE_ARTIFACTS_OPTION: &str = "--cache-artifacts";
+const CACHE_KEYS_OPTION: &str = "--cache-keys";
+const NO_ARTIFACT_PATH_ENVVAR: &str = "NO_ARTIFACT_PATH";
+const ARTIFACT_PATH_RO_ENVVAR: &str = "HAB_STUDIO_ARTIFACT_PATH_RO";
+const ARTIFACT_ORIGINS_ENVVAR: &str = "HAB_STUDIO_ARTIFACT_ORIGINS";
+const NO_KEYS_ENVVAR: &str = "HAB_STUDIO_NO_KEYS";
+const ORIGIN_KEYS_ENVVAR: &str = "HAB_ORIGIN_KEYS";
+/// Asks a chroot Studio to remove the secret keys it imported when it exits.
+const SCRUB_KEYS_ENVVAR: &str = "HAB_STUDIO_SCRUB_KEYS";
+/// Studio subcommands. Options following one of these belong to the subcommand (e.g. the command
+/// given to `hab studio run`) rather than to the Studio.
+const STUDIO_SUBCOMMANDS: &[&str] = &["build", "enter", "help", "new", "rm", "run", "version"];
+
+/// Origin keys and secrets explicitly selected for injection into the Studio with `--inject-key`
+/// and `--inject-secret`. When nothing is selected, the Studio is given access to the whole key
+/// cache and every `HAB_STUDIO_SECRET_*` variable.
+#[derive(Debug, Default, PartialEq)]
+pub struct Injection {
+    pub keys:    Vec<String>,
+    pub secrets: Vec<String>,
+}
+
+/// Remove the options named `names`, which are handled here rather than by the Studio itself,
+/// from `args`, returning each of them with its value in the order they were given.
+fn take_options(args: &mut Vec<OsString>, names: &[&str]) -> Result<Vec<(OsString, String)>> {
+    let mut taken = Vec::new();
+    let mut remaining = Vec::with_capacity(args.len());
+    let mut iter = args.drain(..);
+    while let Some(arg) = iter.next() {
+        if !names.iter().any(|n| arg == *n) {
+            let is_subcommand = STUDIO_SUBCOMMANDS.iter().any(|s| arg == *s);
+            remaining.push(arg);
+            if is_subcommand {
+                remaining.extend(iter.by_ref());
+            }
+            continue;
+        }
+        match iter.next() {
+            Some(value) => taken.push((arg, value.to_string_lossy().into_owned())),
+            None => {
+                return Err(Error::ArgumentError(format!("{} requires a value",
+                                                        arg.to_string_lossy())));
+            }
+        }
+    }
+    drop(iter);
+    *args = remaining;
+    Ok(taken)
+}
+
+impl Injection {
+    /// Remove the injection options from `args`. Each option may be repeated or given a comma
+    /// separated list.
+    fn take_from(args: &mut Vec<OsString>) -> Result<Self> {
+        let mut injection = Injection::default();
+        for (option, value) in take_options(args, &[INJECT_KEY_OPTION, INJECT_SECRET_OPTION])? {
+            let selected = if option == INJECT_KEY_OPTION {
+                &mut injection.keys
+            } else {
+                &mut injection.secrets
+            };
+            selected.extend(value.split(',')
+                                 .filter(|v| !v.is_empty())
+                                 .map(str::to_string));
+        }
+        Ok(injection)
+    }
+
+    /// Whether the `HAB_STUDIO_SECRET_*` variable `var` should be passed to the Studio.
+    fn includes_secret(&self, var: &str) -> bool {
+        self.secrets.is_empty()
+        || self.secrets
+               .iter()
+               .any(|s| s.trim_start_matches(HAB_STUDIO_SECRET) == &var[HAB_STUDIO_SECRET.len()..])
+    }
+
+    /// Apply the selection to the environment the Studio will inherit.
+    fn apply(&self, ui: &mut UI) -> Result<()> {
+        for (var, _) in env::vars() {
+            if var.starts_with(HAB_STUDIO_SECRET) && !self.includes_secret(&var) {
+                debug!("Not injecting {} into the Studio", var);
+                env::remove_var(var);
+            }
+        }
+        for secret in &self.secrets {
+            let var = format!("{}{}",
+                              HAB_STUDIO_SECRET,
+                              secret.trim_start_matches(HAB_STUDIO_SECRET));
+            if henv::var(&var).is_err() {
+                ui.warn(format!("{} was selected for injection but is not set", var))?;
+            }
+        }
+        if !self.keys.is_empty() {
+            env::set_var(ORIGIN_KEYS_ENVVAR, self.keys.join(","));
+            env::set_var(SCRUB_KEYS_ENVVAR, "true");
+        }
+        Ok(())
+    }
+}
+
+/// How the artifact and key caches are shared with the Studio, as selected with
+/// `--cache-artifacts` and `--cache-keys`.
+#[derive(Debug, Default, PartialEq)]
+pub struct CacheMounts {
+    pub artifacts: CacheMount,
+    pub keys:      CacheMount,
+}
+
+impl CacheMounts {
+    /// Remove the cache options from `args`. When an option is repeated, the last one wins.
+    fn take_from(args: &mut Vec<OsString>) -> Result<Self> {
+        let mut mounts = CacheMounts::default();
+        for (option, value) in take_options(args, &[CACHE_ARTIFACTS_OPTION, CACHE_KEYS_OPTION])? {
+            if option == CACHE_ARTIFACTS_OPTION {
+                mounts.artifacts = value.parse()?;
+            } else {
+                mounts.keys = value.parse()?;
+            }
+        }
+        Ok(mounts)
+    }
+
+    /// Tell a chroot Studio how to share the caches through the environment it will inherit. The
+    /// origin keys selected are injected like those given with `--inject-key`.
+    fn apply(&self, injection: &mut Injection) {
+        match self.artifacts.mode {
+            CacheMode::Off => env::set_var(NO_ARTIFACT_PATH_ENVVAR, "true"),
+            CacheMode::ReadOnly => env::set_var(ARTIFACT_PATH_RO_ENVVAR, "true"),
+            CacheMode::ReadWrite => (),
+        }
+        if !self.artifacts.origins.is_empty() {
+            env::set_var(ARTIFACT_ORIGINS_ENVVAR, self.artifacts.origins.join(","));
+        }
+        if self.keys.mode == CacheMode::Off {
+            env::set_var(NO_KEYS_ENVVAR, "true");
+        }
+        injection.keys.extend(self.keys.origins.iter().cloned());
+    }
+}
 
 #[derive(Clone, Copy)]
 enum Sensitivity {
@@ -67,6 +211,12 @@ fn cache_ssl_cert_file(cert_file: &str, cert_cache_dir: &Path) -> Result<()> {
 }
 
 pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
+    let mut args = args.to_vec();
+    let mut injection = Injection::take_from(&mut args)?;
+    let cache_mounts = CacheMounts::take_from(&mut args)?;
+    cache_mounts.apply(&mut injection);
+    injection.apply(ui)?;
+
     let config = config::load()?;
 
     set_env_var_from_config(AUTH_TOKEN_ENVVAR,
@@ -122,11 +272,13 @@ pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
         }
     }
 
-    inner::start(ui, args).await
+    inner::start(ui, &args, &injection, &cache_mounts).await
 }
 
 #[cfg(target_os = "linux")]
 mod inner {
+    use super::{CacheMounts,
+                Injection};
     use crate::{command::studio::docker,
                 common::ui::{UIWriter,
                              UI},
@@ -151,10 +303,14 @@ mod inner {
     const STUDIO_CMD: &str = "hab-studio";
     const STUDIO_CMD_ENVVAR: &str = "HAB_STUDIO_BINARY";
 
-    pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
+    pub async fn start(ui: &mut UI,
+                       args: &[OsString],
+                       injection: &Injection,
+                       cache_mounts: &CacheMounts)
+                       -> Result<()> {
         rerun_with_sudo_if_needed(ui, &args)?;
         if is_docker_studio(&args) {
-            docker::start_docker_studio(ui, args)
+            docker::start_docker_studio(ui, args, injection, cache_mounts)
         } else {
             let command = match henv::var(STUDIO_CMD_ENVVAR) {
                 Ok(command) => PathBuf::from(command),
@@ -245,6 +401,8 @@ mod inner {
 
 #[cfg(not(target_os = "linux"))]
 mod inner {
+    use super::{CacheMounts,
+                Injection};
     use crate::{command::studio::docker,
                 common::ui::UI,
                 error::{Error,
@@ -258,11 +416,15 @@ mod inner {
     use std::{ffi::OsString,
               str::FromStr};
 
-    pub async fn start(_ui: &mut UI, args: &[OsString]) -> Result<()> {
+    pub async fn start(_ui: &mut UI,
+                       args: &[OsString],
+                       injection: &Injection,
+                       cache_mounts: &CacheMounts)
+                       -> Result<()> {
         if is_windows_studio(&args) {
             start_windows_studio(_ui, args).await
         } else {
-            docker::start_docker_studio(_ui, args)
+            docker::start_docker_studio(_ui, args, injection, cache_mounts)
         }
     }
 
@@ -315,10 +477,76 @@ mod inner {
 
 #[cfg(test)]
 mod tests {
-    use super::cache_ssl_cert_file;
-    use std::fs::File;
+    use super::{cache_ssl_cert_file,
+                CacheMounts,
+                Injection};
+    use crate::command::studio::cache::CacheMode;
+    use std::{ffi::OsString,
+              fs::File};
     use tempfile::TempDir;
 
+    fn os_args(args: &[&str]) -> Vec<OsString> { args.iter().map(OsString::from).collect() }
+
+    #[test]
+    fn injection_options_are_removed_from_studio_args() {
+        let mut args = os_args(&["--inject-key",
+                                 "core,acme",
+                                 "-D",
+                                 "--inject-secret",
+                                 "TOKEN",
+                                 "--inject-key",
+                                 "other",
+                                 "enter"]);
+        let injection = Injection::take_from(&mut args).unwrap();
+        assert_eq!(injection.keys, vec!["core", "acme", "other"]);
+        assert_eq!(injection.secrets, vec!["TOKEN"]);
+        assert_eq!(args, os_args(&["-D", "enter"]));
+    }
+
+    #[test]
+    fn injection_options_after_the_studio_subcommand_are_left_alone() {
+        let mut args = os_args(&["run", "some-command", "--inject-key", "core"]);
+        let injection = Injection::take_from(&mut args).unwrap();
+        assert_eq!(injection, Injection::default());
+        assert_eq!(args,
+                   os_args(&["run", "some-command", "--inject-key", "core"]));
+    }
+
+    #[test]
+    fn injection_option_without_a_value_is_an_error() {
+        let mut args = os_args(&["--inject-key"]);
+        assert!(Injection::take_from(&mut args).is_err());
+    }
+
+    #[test]
+    fn cache_options_are_removed_from_studio_args() {
+        let mut args = os_args(&["--cache-artifacts",
+                                 "ro:core",
+                                 "--inject-key",
+                                 "acme",
+                                 "--cache-keys",
+                                 "off",
+                                 "build"]);
+        let injection = Injection::take_from(&mut args).unwrap();
+        let cache_mounts = CacheMounts::take_from(&mut args).unwrap();
+        assert_eq!(injection.keys, vec!["acme"]);
+        assert_eq!(cache_mounts.artifacts.mode, CacheMode::ReadOnly);
+        assert_eq!(cache_mounts.artifacts.origins, vec!["core"]);
+        assert_eq!(cache_mounts.keys.mode, CacheMode::Off);
+        assert_eq!(args, os_args(&["build"]));
+    }
+
+    #[test]
+    fn only_selected_secrets_are_included() {
+        let injection = Injection { keys:    vec![],
+                                    secrets: vec!["TOKEN".to_string(),
+                                                  "HAB_STUDIO_SECRET_PASSWORD".to_string()], };
+        assert!(injection.includes_secret("HAB_STUDIO_SECRET_TOKEN"));
+        assert!(injection.includes_secret("HAB_STUDIO_SECRET_PASSWORD"));
+        assert!(!injection.includes_secret("HAB_STUDIO_SECRET_OTHER"));
+        assert!(Injection::default().includes_secret("HAB_STUDIO_SECRET_OTHER"));
+    }
+
     #[test]
     fn cache_ssl_cert_file_caches_file() -> std::io::Result<()> {
         let cert_name = "ssl-test-cert.pem";
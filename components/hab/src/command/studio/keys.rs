@@ -0,0 +1,151 @@
+//! Staging of selected origin keys for injection into a Docker Studio.
+//!
+//! Rather than mounting the whole key cache into the container, only the latest key pair of each
+//! requested origin is copied into a private staging directory which is mounted in its place.
+//! Once the Studio exits the staged secret keys are overwritten and the directory is removed.
+
+use crate::{error::Result,
+            hcore::crypto::SigKeyPair};
+use std::{fs::{self,
+               OpenOptions},
+          io::Write,
+          path::{Path,
+                 PathBuf}};
+
+/// Origin keys which have been copied into a staging directory for a single Studio run.
+#[derive(Debug)]
+pub struct InjectedKeys {
+    dir:     PathBuf,
+    /// The staged copies of the injected public keys.
+    files:   Vec<PathBuf>,
+    /// The staged copies of the injected secret keys.
+    secrets: Vec<PathBuf>,
+}
+
+impl InjectedKeys {
+    /// Copy the latest public and secret key of each of `origins` from `cache_key_path` into a new
+    /// directory at `dir`.
+    ///
+    /// # Failures
+    ///
+    /// * Fails if `dir` already exists
+    /// * Fails if a secret key cannot be found for any of the origins
+    pub fn stage<P, Q>(origins: &[String], cache_key_path: P, dir: Q) -> Result<Self>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        let cache_key_path = cache_key_path.as_ref();
+        let dir = dir.as_ref();
+        fs::create_dir(dir)?;
+        let mut injected = InjectedKeys { dir:     dir.to_path_buf(),
+                                          files:   Vec::new(),
+                                          secrets: Vec::new(), };
+        set_private_permissions(dir)?;
+        for origin in origins {
+            let pair = SigKeyPair::get_latest_pair_for(origin, cache_key_path, None)?;
+            let name_with_rev = pair.name_with_rev();
+            if let Ok(public) = SigKeyPair::get_public_key_path(&name_with_rev, cache_key_path) {
+                injected.files.push(injected.copy(&public)?);
+            }
+            let secret = SigKeyPair::get_secret_key_path(&name_with_rev, cache_key_path)?;
+            injected.secrets.push(injected.copy(&secret)?);
+        }
+        Ok(injected)
+    }
+
+    /// The directory to mount in place of the key cache.
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// The names of all the staged key files, secret keys last.
+    pub fn file_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.files
+            .iter()
+            .chain(self.secrets.iter())
+            .filter_map(|f| f.file_name())
+            .map(|f| f.to_string_lossy().into_owned())
+    }
+
+    /// Overwrite the staged secret keys and remove the staging directory.
+    pub fn scrub(mut self) -> Result<()> { self.scrub_in_place() }
+
+    fn scrub_in_place(&mut self) -> Result<()> {
+        for secret in self.secrets.drain(..) {
+            let len = fs::metadata(&secret)?.len();
+            let mut file = OpenOptions::new().write(true).open(&secret)?;
+            file.write_all(&vec![0u8; len as usize])?;
+            file.sync_all()?;
+        }
+        self.files.clear();
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    fn copy(&self, key: &Path) -> Result<PathBuf> {
+        let dst = self.dir
+                      .join(key.file_name().expect("key files have a file name"));
+        debug!("Staging {} for injection as {}",
+               key.display(),
+               dst.display());
+        fs::copy(key, &dst)?;
+        Ok(dst)
+    }
+}
+
+// Scrub on any early return, e.g. if the container fails to start.
+impl Drop for InjectedKeys {
+    fn drop(&mut self) {
+        if let Err(e) = self.scrub_in_place() {
+            warn!("Unable to scrub injected keys from {}: {}",
+                  self.dir.display(),
+                  e);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_private_permissions(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_private_permissions(_dir: &Path) -> Result<()> { Ok(()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn stages_only_the_requested_origins_and_scrubs_them() {
+        let cache = TempDir::new().unwrap();
+        SigKeyPair::generate_pair_for_origin("wanted").to_pair_files(cache.path())
+                                                      .unwrap();
+        SigKeyPair::generate_pair_for_origin("unwanted").to_pair_files(cache.path())
+                                                        .unwrap();
+        let staging = TempDir::new().unwrap();
+        let dir = staging.path().join("keys");
+
+        let injected = InjectedKeys::stage(&["wanted".to_string()], cache.path(), &dir).unwrap();
+        let names = injected.file_names().collect::<Vec<_>>();
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().all(|n| n.starts_with("wanted-")));
+        assert!(names.last().unwrap().ends_with(".sig.key"));
+
+        injected.scrub().unwrap();
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn missing_origin_keys_are_an_error_and_leave_nothing_behind() {
+        let cache = TempDir::new().unwrap();
+        let staging = TempDir::new().unwrap();
+        let dir = staging.path().join("keys");
+
+        assert!(InjectedKeys::stage(&["missing".to_string()], cache.path(), &dir).is_err());
+        assert!(!dir.exists());
+    }
+}
@@ -3,15 +3,25 @@ use crate::{error::{Error,
             hcore::{config::ConfigFile,
                     fs::{am_i_root,
                          FS_ROOT_PATH}},
+            protocol,
             CTL_SECRET_ENVVAR};
 use habitat_core::env as henv;
 use habitat_sup_client::SrvClient;
-use std::{fs::{self,
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+use keyring::{Keyring,
+              KeyringError};
+use std::{fmt,
+          fs::{self,
                File},
           io::Write,
           path::PathBuf};
 
 const CLI_CONFIG_PATH: &str = "hab/etc/cli.toml";
+/// The service and user names under which the ctl secret is stored in the OS keyring.
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+const KEYRING_SERVICE: &str = "habitat";
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+const KEYRING_CTL_SECRET_USER: &str = "ctl-secret";
 
 lazy_static::lazy_static! {
     /// A cached reading of the config file. This avoids the need to continually read from disk.
@@ -70,20 +80,136 @@ pub fn save(config: &Config) -> Result<()> {
     Ok(())
 }
 
-/// Check if the HAB_CTL_SECRET env var. If not, check the CLI config to see if there is a ctl
-/// secret set and return a copy of that value.
+/// Check if the HAB_CTL_SECRET env var. If not, check the OS keyring and then the CLI config to
+/// see if there is a ctl secret set and return a copy of that value.
 pub fn ctl_secret_key(config: &Config) -> Result<String> {
-    match henv::var(CTL_SECRET_ENVVAR) {
-        Ok(v) => Ok(v),
-        Err(_) => {
-            match config.ctl_secret {
-                Some(ref v) => Ok(v.to_string()),
-                None => SrvClient::read_secret_key().map_err(Error::from),
+    ctl_secret_key_with_source(config).map(|(secret, _)| secret)
+}
+
+/// Where the ctl secret used by the CLI was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtlSecretSource {
+    Environment,
+    Keyring,
+    ConfigFile,
+    Supervisor,
+}
+
+impl fmt::Display for CtlSecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CtlSecretSource::Environment => {
+                write!(f, "the {} environment variable", CTL_SECRET_ENVVAR)
+            }
+            CtlSecretSource::Keyring => write!(f, "the OS keyring"),
+            CtlSecretSource::ConfigFile => write!(f, "{}", cli_config_path().display()),
+            CtlSecretSource::Supervisor => {
+                write!(f,
+                       "{}",
+                       protocol::secret_key_path(protocol::sup_root(None)).display())
             }
         }
     }
 }
 
+/// Same as `ctl_secret_key`, but also reports where the secret was found.
+pub fn ctl_secret_key_with_source(config: &Config) -> Result<(String, CtlSecretSource)> {
+    if let Ok(v) = henv::var(CTL_SECRET_ENVVAR) {
+        return Ok((v, CtlSecretSource::Environment));
+    }
+    if let Some(v) = keyring_ctl_secret() {
+        return Ok((v, CtlSecretSource::Keyring));
+    }
+    match config.ctl_secret {
+        Some(ref v) => Ok((v.to_string(), CtlSecretSource::ConfigFile)),
+        None => {
+            SrvClient::read_secret_key().map(|v| (v, CtlSecretSource::Supervisor))
+                                        .map_err(Error::from)
+        }
+    }
+}
+
+/// Store the ctl secret in the OS keyring, removing any plaintext copy from the CLI config. If no
+/// keyring is available (e.g. on a headless system without a Secret Service provider, or a Linux
+/// hab built without the `keyring` feature) the secret is stored in the CLI config instead.
+pub fn store_ctl_secret(secret: &str) -> Result<CtlSecretSource> {
+    let mut config = load()?;
+    if store_keyring_ctl_secret(secret) {
+        if config.ctl_secret.take().is_some() {
+            save(&config)?;
+        }
+        Ok(CtlSecretSource::Keyring)
+    } else {
+        config.ctl_secret = Some(secret.to_string());
+        save(&config)?;
+        Ok(CtlSecretSource::ConfigFile)
+    }
+}
+
+/// Remove the ctl secret from both the OS keyring and the CLI config, returning where it was
+/// removed from.
+pub fn clear_ctl_secret() -> Result<Vec<CtlSecretSource>> {
+    let mut cleared = Vec::new();
+    if clear_keyring_ctl_secret() {
+        cleared.push(CtlSecretSource::Keyring);
+    }
+    let mut config = load()?;
+    if config.ctl_secret.take().is_some() {
+        save(&config)?;
+        cleared.push(CtlSecretSource::ConfigFile);
+    }
+    Ok(cleared)
+}
+
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+fn store_keyring_ctl_secret(secret: &str) -> bool {
+    match ctl_secret_keyring().set_password(secret) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("Unable to store ctl secret in the OS keyring, {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(not(any(feature = "keyring", target_os = "macos", windows)))]
+fn store_keyring_ctl_secret(_secret: &str) -> bool { false }
+
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+fn clear_keyring_ctl_secret() -> bool {
+    match ctl_secret_keyring().delete_password() {
+        Ok(()) => true,
+        Err(KeyringError::NoPasswordFound) => false,
+        Err(e) => {
+            debug!("Unable to remove ctl secret from the OS keyring, {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(not(any(feature = "keyring", target_os = "macos", windows)))]
+fn clear_keyring_ctl_secret() -> bool { false }
+
+#[cfg(not(any(feature = "keyring", target_os = "macos", windows)))]
+fn keyring_ctl_secret() -> Option<String> { None }
+
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+fn keyring_ctl_secret() -> Option<String> {
+    match ctl_secret_keyring().get_password() {
+        Ok(v) => Some(v),
+        Err(KeyringError::NoPasswordFound) => None,
+        Err(e) => {
+            debug!("Unable to read ctl secret from the OS keyring, {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(any(feature = "keyring", target_os = "macos", windows))]
+fn ctl_secret_keyring() -> Keyring<'static> {
+    Keyring::new(KEYRING_SERVICE, KEYRING_CTL_SECRET_USER)
+}
+
 fn cli_config_path() -> PathBuf {
     if !am_i_root() {
         if let Some(home) = dirs::home_dir() {
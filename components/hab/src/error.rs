@@ -5,7 +5,8 @@ use crate::{api_client,
             sup_client::SrvClientError};
 use habitat_common::error::DEFAULT_ERROR_EXIT_CODE;
 use habitat_core::package::PackageIdent;
-use std::{collections::HashMap,
+use std::{collections::{BTreeMap,
+                        HashMap},
           env,
           error,
           ffi,
@@ -23,6 +24,7 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     APIClient(api_client::Error),
     ArgumentError(String),
+    ArtifactsNotVerified(usize, usize),
     ButterflyError(String),
     CacheSslCertError(String),
     CannotParseBinlinkBinaryName(PathBuf),
@@ -41,6 +43,9 @@ pub enum Error {
     DockerNetworkDown(String),
     EnvJoinPathsError(env::JoinPathsError),
     ErrorPerIdent(HashMap<PackageIdent, Error>),
+    ErrorPerKeyFile(BTreeMap<String, Error>),
+    ErrorPerKeyRevision(BTreeMap<String, Error>),
+    ErrorPerOrigin(BTreeMap<String, Error>),
     ExecCommandNotFound(PathBuf),
     FFINulError(ffi::NulError),
     FileNotFound(String),
@@ -48,6 +53,7 @@ pub enum Error {
     HabitatCore(hcore::Error),
     // Boxed due to clippy::large_enum_variant
     HandlebarsRenderError(Box<handlebars::TemplateRenderError>),
+    HttpGateway(String),
     IO(io::Error),
     JobGroupPromoteOrDemote(api_client::Error, bool /* promote */),
     JobGroupCancel(api_client::Error),
@@ -57,11 +63,14 @@ pub enum Error {
     NameLookup,
     NetErr(net::NetErr),
     PackageArchiveMalformed(String),
+    PackageFilesTampered(usize, PathBuf),
     PackageSetParseError(String),
+    PackagesDrifted(usize, usize),
     ParseIntError(num::ParseIntError),
     ParseUrlError(url::ParseError),
     PathPrefixError(path::StripPrefixError),
     ProvidesError(String),
+    RingExecIncomplete(String, String),
     RootRequired,
     ScheduleStatus(api_client::Error),
     SubcommandNotSupported(String),
@@ -78,6 +87,9 @@ impl fmt::Display for Error {
         let msg = match *self {
             Error::APIClient(ref e) => e.to_string(),
             Error::ArgumentError(ref e) => e.to_string(),
+            Error::ArtifactsNotVerified(failed, total) => {
+                format!("{} of {} artifact(s) could not be verified", failed, total)
+            }
             Error::ButterflyError(ref e) => e.to_string(),
             Error::CacheSslCertError(ref e) => format!("Cannot cache SSL_CERT_FILE: {}", e),
             Error::CannotParseBinlinkBinaryName(ref p) => {
@@ -139,6 +151,28 @@ impl fmt::Display for Error {
                  .collect::<Vec<_>>()
                  .join("\n")
             }
+            Error::ErrorPerKeyFile(ref e) => {
+                let failed = e.iter()
+                              .map(|(file, error)| format!("{}: {}", file, error))
+                              .collect::<Vec<_>>();
+                format!("Unable to import {} origin keys:\n{}",
+                        failed.len(),
+                        failed.join("\n"))
+            }
+            Error::ErrorPerKeyRevision(ref e) => {
+                let failed = e.iter()
+                              .map(|(nwr, error)| format!("{}: {}", nwr, error))
+                              .collect::<Vec<_>>();
+                format!("Unable to download {} origin key revisions:\n{}",
+                        failed.len(),
+                        failed.join("\n"))
+            }
+            Error::ErrorPerOrigin(ref e) => {
+                e.iter()
+                 .map(|(origin, error)| format!("{}: {}", origin, error))
+                 .collect::<Vec<_>>()
+                 .join("\n")
+            }
             Error::ExecCommandNotFound(ref c) => {
                 format!("`{}' was not found on the filesystem or in PATH",
                         c.display())
@@ -148,6 +182,7 @@ impl fmt::Display for Error {
             Error::HabitatCommon(ref e) => e.to_string(),
             Error::HabitatCore(ref e) => e.to_string(),
             Error::HandlebarsRenderError(ref e) => e.to_string(),
+            Error::HttpGateway(ref e) => format!("Unable to query the HTTP Gateway: {}", e),
             Error::IO(ref err) => format!("{}", err),
             Error::JobGroupPromoteOrDemoteUnprocessable(true) => {
                 "Failed to promote job group, the build job is still in progress".to_string()
@@ -169,13 +204,24 @@ impl fmt::Display for Error {
                 format!("Package archive was unreadable or contained unexpected contents: {:?}",
                         e)
             }
+            Error::PackageFilesTampered(count, ref path) => {
+                format!("{} file(s) in {} do not match its FILES manifest",
+                        count,
+                        path.display())
+            }
             Error::PackageSetParseError(ref e) => {
                 format!("Package set file could not be parsed: {:?}", e)
             }
+            Error::PackagesDrifted(drifted, total) => {
+                format!("{} of {} installed package(s) have drifted", drifted, total)
+            }
             Error::ParseIntError(ref err) => format!("{}", err),
             Error::ParseUrlError(ref err) => format!("{}", err),
             Error::PathPrefixError(ref err) => format!("{}", err),
             Error::ProvidesError(ref err) => format!("Can't find {}", err),
+            Error::RingExecIncomplete(ref id, ref reason) => {
+                format!("Ring command {} is incomplete: {}", id, reason)
+            }
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation".to_string()
             }
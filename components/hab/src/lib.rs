@@ -36,6 +36,11 @@ pub mod scaffolding;
 pub const PRODUCT: &str = "hab";
 pub const VERSION: &str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
 pub const CTL_SECRET_ENVVAR: &str = "HAB_CTL_SECRET";
+pub const CTL_SERVER_CA_CERTIFICATE_ENVVAR: &str = "HAB_CTL_SERVER_CA_CERTIFICATE";
+pub const CTL_SERVER_NAME_ENVVAR: &str = "HAB_CTL_SERVER_NAME";
+pub const CTL_CLIENT_CERTIFICATE_ENVVAR: &str = "HAB_CTL_CLIENT_CERTIFICATE";
+pub const CTL_CLIENT_KEY_ENVVAR: &str = "HAB_CTL_CLIENT_KEY";
+pub const HTTP_GATEWAY_AUTH_TOKEN_ENVVAR: &str = "HAB_SUP_GATEWAY_AUTH_TOKEN";
 pub const ORIGIN_ENVVAR: &str = "HAB_ORIGIN";
 pub const BLDR_URL_ENVVAR: &str = "HAB_BLDR_URL";
 
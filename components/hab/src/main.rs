@@ -7,6 +7,9 @@ extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
+use chrono::{SecondsFormat,
+             TimeZone,
+             Utc};
 use clap::{ArgMatches,
            ErrorKind as ClapErrorKind,
            Shell};
@@ -15,30 +18,54 @@ use configopt::{ConfigOpt,
 use futures::stream::StreamExt;
 use hab::{cli::{self,
                 gateway_util,
-                hab::{license::License,
-                      origin::{Origin,
+                hab::{cache::{Cache,
+                              CacheArtifacts},
+                      license::License,
+                      origin::{Key,
+                               KeyBundle,
+                               KeyList,
+                               KeyRevoke,
+                               KeyRotate,
+                               Origin,
                                Rbac,
                                RbacSet,
                                RbacShow},
                       pkg::{ExportCommand as PkgExportCommand,
                             Pkg,
-                            PkgExec},
+                            PkgExec,
+                            PkgReSign,
+                            PkgShell,
+                            PkgUpgradeHart},
+                      ring::{Ring,
+                             RingExec,
+                             RingExecUpdate},
                       sup::{HabSup,
                             Secret,
-                            Sup},
+                            Sup,
+                            SupRing},
                       svc::{self,
+                            ApplyManifest as SvcApplyManifest,
                             BulkLoad as SvcBulkLoad,
                             Load as SvcLoad,
-                            Svc},
+                            Svc,
+                            SvcEnv},
                       util::{bldr_auth_token_from_args_env_or_load,
-                             bldr_url_from_args_env_load_or_default},
+                             bldr_url_from_args_env_load_or_default,
+                             maybe_bldr_auth_token_from_args_or_load,
+                             CtlTlsOptions},
                       Hab},
                 parse_optional_arg},
           command::{self,
                     pkg::{download::{PackageSet,
                                      PackageSetFile},
                           list::ListingType,
-                          uninstall::UninstallHookMode}},
+                          uninstall::UninstallHookMode},
+                    service::{manifest::Change,
+                              status::{self,
+                                       HttpGateway,
+                                       StatusFormat,
+                                       STATUS_DETAILS_HEADER,
+                                       STATUS_HEADER}}},
           config,
           error::{Error,
                   Result},
@@ -52,39 +79,53 @@ use hab::{cli::{self,
 use habitat_api_client::BuildOnUpload;
 use habitat_common::{self as common,
                      cli::cache_key_path_from_matches,
-                     command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+                     command::package::{install::{self,
+                                                  InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        license::{LicensePolicy,
+                                                  LicensePolicyMode}},
                      types::ListenCtlAddr,
                      ui::{self,
                           Status,
                           UIWriter,
                           UI},
                      FeatureFlag};
-use habitat_core::{crypto::{init,
-                            keys::PairType,
+use habitat_core::{crypto::{artifact::SignaturePolicy,
+                            init,
+                            keys::{trust_policy::TrustPolicy,
+                                   KeyFormat,
+                                   PairType,
+                                   Passphrase},
                             BoxKeyPair,
-                            SigKeyPair},
+                            SigKeyPair,
+                            SigningBackend},
                    env::{self as henv,
                          Config as _},
                    fs::{cache_artifact_path,
+                        KeyPermissionPolicy,
                         FS_ROOT_PATH},
                    os::process::ShutdownTimeout,
                    package::{target,
+                             ArtifactCache,
                              PackageIdent,
                              PackageTarget},
                    service::ServiceGroup,
                    url::default_bldr_url,
                    ChannelIdent};
-use habitat_sup_client::{SrvClient,
+use habitat_sup_client::{CtlGateway,
+                         SrvClient,
                          SrvClientError};
 use habitat_sup_protocol::{self as sup_proto,
                            codec::*,
+                           ctl::SignedRequest,
                            net::ErrCode,
                            types::*};
-use std::{collections::HashMap,
-          convert::TryFrom,
+use std::{collections::{HashMap,
+                        HashSet},
+          convert::{TryFrom,
+                    TryInto},
           env,
           ffi::OsString,
           fs::File,
@@ -97,7 +138,9 @@ use std::{collections::HashMap,
           result,
           str::FromStr,
           string::ToString,
-          thread};
+          thread,
+          time::Duration};
+use structopt::StructOpt;
 use tabwriter::TabWriter;
 
 /// Makes the --org CLI param optional when this env var is set
@@ -106,15 +149,24 @@ const HABITAT_ORG_ENVVAR: &str = "HAB_ORG";
 const HABITAT_USER_ENVVAR: &str = "HAB_USER";
 
 lazy_static! {
-    static ref STATUS_HEADER: Vec<&'static str> = {
-        vec!["package",
-             "type",
-             "desired",
-             "state",
-             "elapsed (s)",
-             "pid",
-             "group",]
-    };
+    static ref SERVICE_ENV_HEADER: Vec<&'static str> = vec!["name", "value", "source"];
+    static ref MEMBERS_HEADER: Vec<&'static str> =
+        vec!["member", "address", "health", "flaps", "last departed"];
+    static ref SERVICE_HISTORY_HEADER: Vec<&'static str> =
+        vec!["time", "package", "group", "event", "detail"];
+    static ref MEMBER_HISTORY_HEADER: Vec<&'static str> = vec!["time", "member", "from", "to"];
+    static ref CFG_DRY_RUN_HEADER: Vec<&'static str> =
+        vec!["member", "package", "outcome", "changed keys"];
+    static ref VIEW_CONFLICTS_HEADER: Vec<&'static str> =
+        vec!["member", "reported by", "local", "reported", "last seen"];
+    static ref ELECTION_CONFLICTS_HEADER: Vec<&'static str> =
+        vec!["service group", "term", "leaders", "last seen"];
+    static ref KEY_REVOCATIONS_HEADER: Vec<&'static str> = vec!["url",
+                                                                "origin",
+                                                                "revoked",
+                                                                "last fetched",
+                                                                "last installed",
+                                                                "error"];
 }
 
 #[tokio::main]
@@ -178,6 +230,18 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
             match hab {
                 Hab::Origin(origin) => {
                     match origin {
+                        Origin::Key(Key::Bundle(bundle)) => {
+                            return sub_origin_key_bundle(ui, bundle);
+                        }
+                        Origin::Key(Key::List(list)) => {
+                            return sub_origin_key_list(ui, list).await;
+                        }
+                        Origin::Key(Key::Revoke(revoke)) => {
+                            return sub_origin_key_revoke(ui, revoke);
+                        }
+                        Origin::Key(Key::Rotate(rotate)) => {
+                            return sub_origin_key_rotate(ui, rotate).await;
+                        }
                         // hab origin rbac set|show
                         Origin::Rbac(action) => {
                             match action {
@@ -200,6 +264,19 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                              update your automation and processes accordingly.")?;
                     return command::launcher::start(ui, sup_run, &args_after_first(1)).await;
                 }
+                Hab::Cache(Cache::Artifacts(artifacts)) => {
+                    return sub_cache_artifacts(ui, artifacts).await;
+                }
+                Hab::Ring(Ring::Exec(exec)) => {
+                    match exec {
+                        RingExec::Update(update) => return sub_ring_exec_update(ui, update).await,
+                        RingExec::Status { id, remote_sup } => {
+                            return command::ring::exec::status(ui,
+                                                               &remote_sup.to_ctl_gateway()?,
+                                                               id).await;
+                        }
+                    }
+                }
                 Hab::Studio(studio) => {
                     return command::studio::enter::start(ui, studio.args()).await;
                 }
@@ -213,7 +290,15 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                             // command prefix and pass the rest of the args to underlying binary.
                             let args = args_after_first(2);
                             match sup {
-                                Sup::Bash | Sup::Sh | Sup::Term => {
+                                Sup::Term { remote_sup: Some(remote_sup),
+                                            yes,
+                                            depart,
+                                            ctl_tls, } => {
+                                    let remote_sup = CtlGateway { addr: remote_sup,
+                                                                  tls:  ctl_tls.to_ctl_tls()?, };
+                                    return sub_sup_term(remote_sup, yes, depart).await;
+                                }
+                                Sup::Bash | Sup::Sh | Sup::Simulate(_) | Sup::Term { .. } => {
                                     return command::sup::start(ui, &args).await;
                                 }
                                 Sup::Run(sup_run) => {
@@ -223,22 +308,70 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         }
                         HabSup::Depart { member_id,
                                          remote_sup, } => {
-                            return sub_sup_depart(member_id, &remote_sup.to_listen_ctl_addr()).await;
+                            return sub_sup_depart(member_id, &remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::Drain { timeout,
+                                        remote_sup, } => {
+                            return sub_sup_drain(timeout, &remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::ForgetDeparted { remote_sup } => {
+                            return sub_sup_forget_departed(&remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::Members { member_id,
+                                          history,
+                                          remote_sup, } => {
+                            return sub_sup_members(member_id,
+                                                   history,
+                                                   &remote_sup.to_ctl_gateway()?).await;
                         }
                         HabSup::Secret(Secret::Generate) => {
                             return sub_sup_secret_generate();
                         }
+                        HabSup::Secret(Secret::Rotate { grace_period,
+                                                        remote_sup, }) => {
+                            return sub_sup_secret_rotate(grace_period.into(),
+                                                         &remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::Secret(Secret::RevokeSessions { remote_sup }) => {
+                            let remote_sup = remote_sup.to_ctl_gateway()?;
+                            return sub_sup_secret_revoke_sessions(&remote_sup).await;
+                        }
                         HabSup::Status { pkg_ident,
-                                         remote_sup, } => {
-                            return sub_svc_status(pkg_ident, &remote_sup.to_listen_ctl_addr()).await;
+                                         details,
+                                         history,
+                                         format,
+                                         watch,
+                                         interval,
+                                         remote_sup,
+                                         http, } => {
+                            return sub_svc_status(pkg_ident,
+                                                  details,
+                                                  history,
+                                                  format.parse()?,
+                                                  if watch { Some(interval.into()) } else { None },
+                                                  &remote_sup.to_ctl_gateway()?,
+                                                  http.to_http_gateway()?).await;
                         }
                         HabSup::Restart { remote_sup } => {
-                            return sub_sup_restart(&remote_sup.to_listen_ctl_addr()).await;
+                            return sub_sup_restart(&remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::Ring(SupRing::Reload { retire, remote_sup }) => {
+                            return sub_sup_ring_reload(retire,
+                                                       &remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::RingHealth { remote_sup } => {
+                            return sub_sup_ring_health(&remote_sup.to_ctl_gateway()?).await;
+                        }
+                        HabSup::KeyRevocations { remote_sup } => {
+                            return sub_sup_key_revocations(&remote_sup.to_ctl_gateway()?).await;
                         }
                     }
                 }
                 Hab::Svc(svc) => {
                     match svc {
+                        Svc::ApplyManifest(svc_apply_manifest) => {
+                            return sub_svc_apply_manifest(svc_apply_manifest).await;
+                        }
                         Svc::BulkLoad(svc_bulk_load) => {
                             if feature_flags.contains(FeatureFlag::SERVICE_CONFIG_FILES) {
                                 return sub_svc_bulk_load(svc_bulk_load).await;
@@ -246,13 +379,26 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                                 return Err(Error::ArgumentError(String::from("`hab svc bulkload` is only available when `HAB_FEAT_SERVICE_CONFIG_FILES` is set")));
                             }
                         }
+                        Svc::Env(svc_env) => return sub_svc_env(svc_env).await,
                         Svc::Load(svc_load) => {
                             return sub_svc_load(svc_load).await;
                         }
                         Svc::Update(svc_update) => return sub_svc_update(svc_update).await,
                         Svc::Status { pkg_ident,
-                                      remote_sup, } => {
-                            return sub_svc_status(pkg_ident, &remote_sup.to_listen_ctl_addr()).await;
+                                      details,
+                                      history,
+                                      format,
+                                      watch,
+                                      interval,
+                                      remote_sup,
+                                      http, } => {
+                            return sub_svc_status(pkg_ident,
+                                                  details,
+                                                  history,
+                                                  format.parse()?,
+                                                  if watch { Some(interval.into()) } else { None },
+                                                  &remote_sup.to_ctl_gateway()?,
+                                                  http.to_http_gateway()?).await;
                         }
                         _ => {
                             // All other commands will be caught by the CLI parsing logic below.
@@ -284,6 +430,9 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                                              automation and processes accordingly.")?;
                                     return command::pkg::export::container::start(ui, &args.args).await;
                                 }
+                                PkgExportCommand::K8s(args) => {
+                                    return command::pkg::export::k8s::start(ui, args);
+                                }
                                 #[cfg(target_os = "linux")]
                                 PkgExportCommand::Mesos(args) => {
                                     return command::pkg::export::mesos::start(ui, &args.args).await;
@@ -294,6 +443,9 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                                 }
                             }
                         }
+                        Pkg::ReSign(re_sign) => return sub_pkg_re_sign(ui, re_sign),
+                        Pkg::UpgradeHart(upgrade) => return sub_pkg_upgrade_hart(ui, upgrade),
+                        Pkg::Shell(shell) => return sub_pkg_shell(ui, shell),
                         Pkg::Exec(PkgExec { pkg_ident,
                                             cmd,
                                             args, }) => {
@@ -342,6 +494,14 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
             match matches.subcommand() {
                 ("setup", Some(m)) => sub_cli_setup(ui, m)?,
                 ("completers", Some(m)) => sub_cli_completers(m, feature_flags)?,
+                ("secret", Some(m)) => {
+                    match m.subcommand() {
+                        ("set", Some(m)) => sub_cli_secret_set(ui, m)?,
+                        ("show", Some(_)) => command::cli::secret::show(ui)?,
+                        ("clear", Some(_)) => command::cli::secret::clear(ui)?,
+                        _ => unreachable!(),
+                    }
+                }
                 _ => unreachable!(),
             }
         }
@@ -421,6 +581,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         ("list", Some(m)) => sub_bldr_channel_list(ui, m).await?,
                         ("promote", Some(m)) => sub_bldr_channel_promote(ui, m).await?,
                         ("demote", Some(m)) => sub_bldr_channel_demote(ui, m).await?,
+                        ("diff", Some(m)) => sub_bldr_channel_diff(ui, m).await?,
                         _ => unreachable!(),
                     }
                 }
@@ -429,6 +590,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
         }
         ("pkg", Some(matches)) => {
             match matches.subcommand() {
+                ("audit", Some(m)) => sub_pkg_audit(ui, m)?,
                 ("binds", Some(m)) => sub_pkg_binds(m)?,
                 ("binlink", Some(m)) => sub_pkg_binlink(ui, m)?,
                 ("build", Some(m)) => sub_pkg_build(ui, m).await?,
@@ -448,9 +610,9 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 ("upload", Some(m)) => sub_pkg_upload(ui, m).await?,
                 ("bulkupload", Some(m)) => sub_pkg_bulkupload(ui, m).await?,
                 ("delete", Some(m)) => sub_pkg_delete(ui, m).await?,
-                ("verify", Some(m)) => sub_pkg_verify(ui, m)?,
+                ("verify", Some(m)) => sub_pkg_verify(ui, m).await?,
                 ("header", Some(m)) => sub_pkg_header(ui, m)?,
-                ("info", Some(m)) => sub_pkg_info(ui, m)?,
+                ("info", Some(m)) => sub_pkg_info(ui, m).await?,
                 ("promote", Some(m)) => sub_pkg_promote(ui, m).await?,
                 ("demote", Some(m)) => sub_pkg_demote(ui, m).await?,
                 _ => unreachable!(),
@@ -470,6 +632,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         ("export", Some(sc)) => sub_ring_key_export(sc)?,
                         ("import", Some(sc)) => sub_ring_key_import(ui, sc)?,
                         ("generate", Some(sc)) => sub_ring_key_generate(ui, sc)?,
+                        ("rotate", Some(sc)) => sub_ring_key_rotate(ui, sc).await?,
                         _ => unreachable!(),
                     }
                 }
@@ -484,7 +647,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         _ => unreachable!(),
                     }
                 }
-                ("unload", Some(m)) => sub_svc_unload(m).await?,
+                ("unload", Some(m)) => sub_svc_unload(ui, m).await?,
                 ("start", Some(m)) => sub_svc_start(m).await?,
                 ("stop", Some(m)) => sub_svc_stop(m).await?,
                 _ => unreachable!(),
@@ -522,6 +685,30 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
     Ok(())
 }
 
+async fn sub_cache_artifacts(ui: &mut UI, artifacts: CacheArtifacts) -> Result<()> {
+    let cache = ArtifactCache::new(cache_artifact_path(Some(&*FS_ROOT_PATH)));
+    match artifacts {
+        CacheArtifacts::List => command::cache::artifacts::list(&cache),
+        CacheArtifacts::Prune { max_size_mb } => {
+            let max_size = max_size_mb.map_or_else(ArtifactCache::configured_max_size,
+                                                   |mb| mb.saturating_mul(1024 * 1024));
+            command::cache::artifacts::prune(ui, &cache, max_size)
+        }
+        CacheArtifacts::Verify { to_json,
+                                 cache_key_path, } => {
+            init()?;
+            let trust = TrustPolicy::load(&cache_key_path.cache_key_path)?;
+            command::pkg::verify::start(ui,
+                                        cache.path(),
+                                        &cache_key_path.cache_key_path,
+                                        SignaturePolicy::Any,
+                                        &trust,
+                                        false,
+                                        to_json).await
+        }
+    }
+}
+
 fn sub_cli_setup(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let cache_key_path = cache_key_path_from_matches(&m);
     init()?;
@@ -542,40 +729,93 @@ fn sub_cli_completers(m: &ArgMatches<'_>, feature_flags: FeatureFlag) -> Result<
     Ok(())
 }
 
+fn sub_cli_secret_set(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let secret = match m.value_of("SECRET_FILE") {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            let mut secret = String::new();
+            io::stdin().read_to_string(&mut secret)?;
+            secret
+        }
+    };
+    command::cli::secret::set(ui, &secret)
+}
+
 async fn sub_origin_key_download(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
-    let origin = m.value_of("ORIGIN").unwrap(); // Required via clap
+    let mut origins: Vec<String> = m.values_of("ORIGIN")
+                                    .map(|v| v.map(String::from).collect())
+                                    .unwrap_or_default();
+    if let Some(path) = m.value_of("FROM_FILE") {
+        origins.extend(command::origin::key::download::origins_from_file(Path::new(path))?);
+    }
+    let mut seen = HashSet::new();
+    origins.retain(|origin| seen.insert(origin.clone()));
     let revision = m.value_of("REVISION");
     let with_secret = m.is_present("WITH_SECRET");
     let with_encryption = m.is_present("WITH_ENCRYPTION");
     let token = maybe_auth_token(&m);
     let url = bldr_url_from_matches(&m)?;
     let cache_key_path = cache_key_path_from_matches(&m);
-
-    command::origin::key::download::start(ui,
-                                          &url,
-                                          &origin,
-                                          revision,
-                                          with_secret,
-                                          with_encryption,
-                                          token.as_deref(),
-                                          &cache_key_path).await
+    let jobs = match m.value_of("JOBS") {
+        Some(jobs) => jobs.parse().map_err(Error::ParseIntError)?,
+        None => command::origin::key::download::DEFAULT_JOBS,
+    };
+    let policy = m.value_of("KEY_PERMISSIONS")
+                  .map(str::parse::<KeyPermissionPolicy>)
+                  .transpose()?
+                  .unwrap_or_default();
+    let access = command::origin::key::download::KeyAccess { policy,
+                                                             group: m.value_of("KEY_GROUP") };
+
+    match origins.as_slice() {
+        [] => Err(Error::ArgumentError("No origins given".to_string())),
+        [origin] => {
+            command::origin::key::download::start(ui,
+                                                  &url,
+                                                  origin,
+                                                  revision,
+                                                  with_secret,
+                                                  with_encryption,
+                                                  token.as_deref(),
+                                                  &cache_key_path,
+                                                  access,
+                                                  jobs).await
+        }
+        _ if revision.is_some() => {
+            let e = "A key revision can only be given for a single origin";
+            Err(Error::ArgumentError(e.to_string()))
+        }
+        _ => {
+            command::origin::key::download::start_many(ui,
+                                                       &url,
+                                                       &origins,
+                                                       with_secret,
+                                                       with_encryption,
+                                                       token.as_deref(),
+                                                       &cache_key_path,
+                                                       access,
+                                                       jobs).await
+        }
+    }
 }
 
 fn sub_origin_key_export(m: &ArgMatches<'_>) -> Result<()> {
     let origin = m.value_of("ORIGIN").unwrap(); // Required via clap
     let pair_type = PairType::from_str(m.value_of("PAIR_TYPE").unwrap_or("public"))?;
+    let format = KeyFormat::from_str(m.value_of("FORMAT").unwrap())?; // Defaulted via clap
     let cache_key_path = cache_key_path_from_matches(&m);
     init()?;
 
-    command::origin::key::export::start(origin, pair_type, &cache_key_path)
+    command::origin::key::export::start(origin, pair_type, format, &cache_key_path)
 }
 
 fn sub_origin_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let origin = origin_param_or_env(&m)?;
+    let passphrase = passphrase_from_matches(&m)?;
     let cache_key_path = cache_key_path_from_matches(&m);
     init()?;
 
-    command::origin::key::generate::start(ui, &origin, &cache_key_path)
+    command::origin::key::generate::start(ui, &origin, passphrase.as_ref(), &cache_key_path)
 }
 
 fn sub_origin_key_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -612,6 +852,66 @@ async fn sub_origin_key_upload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     }
 }
 
+fn sub_origin_key_bundle(ui: &mut UI, bundle: KeyBundle) -> Result<()> {
+    init()?;
+    match bundle {
+        KeyBundle::Export(e) => {
+            command::origin::key::bundle::export(ui,
+                                                 &e.origins,
+                                                 e.with_secret,
+                                                 &e.output,
+                                                 &e.cache_key_path.cache_key_path)
+        }
+        KeyBundle::Import(i) => {
+            command::origin::key::bundle::import(ui, &i.bundle, &i.cache_key_path.cache_key_path)
+        }
+    }
+}
+
+async fn sub_origin_key_list(ui: &mut UI, l: KeyList) -> Result<()> {
+    match l.origin {
+        // --remote requires an origin
+        Some(origin) if l.remote => {
+            let bldr_url = bldr_url_from_args_env_load_or_default(l.bldr_url.value)?;
+            let auth_token = maybe_bldr_auth_token_from_args_or_load(l.auth_token.value);
+            command::origin::key::list::start_remote(ui,
+                                                     bldr_url.as_str(),
+                                                     &origin,
+                                                     auth_token.as_deref()).await
+        }
+        origin => {
+            command::origin::key::list::start(origin.as_deref(), &l.cache_key_path.cache_key_path)
+        }
+    }
+}
+
+fn sub_origin_key_revoke(ui: &mut UI, r: KeyRevoke) -> Result<()> {
+    init()?;
+    command::origin::key::revoke::start(ui,
+                                        &r.revision,
+                                        &r.reason,
+                                        &r.cache_key_path.cache_key_path)
+}
+
+async fn sub_origin_key_rotate(ui: &mut UI, r: KeyRotate) -> Result<()> {
+    let bldr_url = bldr_url_from_args_env_load_or_default(r.bldr_url.value)?;
+    let auth_token = bldr_auth_token_from_args_env_or_load(r.auth_token.value)?;
+    let passphrase = r.passphrase_file
+                      .as_deref()
+                      .map(Passphrase::from_file)
+                      .transpose()?;
+    init()?;
+
+    command::origin::key::rotate::start(ui,
+                                        bldr_url.as_str(),
+                                        &auth_token,
+                                        &r.origin,
+                                        r.with_secret,
+                                        passphrase.as_ref(),
+                                        r.re_sign.as_deref(),
+                                        &r.cache_key_path.cache_key_path).await
+}
+
 async fn sub_origin_secret_upload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let token = auth_token_param_or_env(&m)?;
@@ -759,6 +1059,23 @@ async fn sub_origin_member_role_set(ui: &mut UI, r: RbacSet) -> Result<()> {
                                            r.no_prompt).await
 }
 
+fn sub_pkg_audit(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let ident = m.value_of("PKG_IDENT")
+                 .map(PackageIdent::from_str)
+                 .transpose()?;
+    let dest_dir = Path::new(m.value_of("DEST_DIR").unwrap()); // default value from clap
+    let quarantine = if m.is_present("QUARANTINE") {
+        Some(dest_dir)
+    } else {
+        None
+    };
+    command::pkg::audit::start(ui,
+                               ident.as_ref(),
+                               &FS_ROOT_PATH,
+                               quarantine,
+                               m.is_present("TO_JSON"))
+}
+
 fn sub_pkg_binlink(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     let dest_dir = Path::new(m.value_of("DEST_DIR").unwrap()); // required by clap
@@ -838,15 +1155,19 @@ async fn sub_pkg_download(ui: &mut UI,
 
     // Construct flat file based inputs
     let channel = channel_from_matches_or_default(m);
-    let target = target_from_matches(m)?;
+    let targets = targets_from_matches(m)?;
 
     let install_sources = idents_from_matches(m)?;
 
-    let mut package_sets = vec![PackageSet { target,
-                                             channel: channel.clone(),
-                                             idents: install_sources }];
+    let mut package_sets: Vec<_> = targets.iter()
+                                          .map(|&target| {
+                                              PackageSet { target,
+                                                           channel: channel.clone(),
+                                                           idents: install_sources.clone() }
+                                          })
+                                          .collect();
 
-    let mut install_sources_from_file = idents_from_file_matches(ui, m, &channel, target)?;
+    let mut install_sources_from_file = idents_from_file_matches(ui, m, &channel, &targets)?;
     package_sets.append(&mut install_sources_from_file);
     package_sets.retain(|set| !set.idents.is_empty());
 
@@ -971,6 +1292,23 @@ async fn sub_bldr_channel_demote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()>
                                           &target_channel).await
 }
 
+async fn sub_bldr_channel_diff(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let url = bldr_url_from_matches(&m)?;
+    let origin = origin_param_or_env(&m)?;
+    let token = maybe_auth_token(&m);
+    let from_channel = ChannelIdent::from(m.value_of("FROM_CHANNEL").unwrap());
+    let to_channel = ChannelIdent::from(m.value_of("TO_CHANNEL").unwrap());
+    let target = target_from_matches(m)?;
+    command::bldr::channel::diff::start(ui,
+                                        &url,
+                                        token.as_deref(),
+                                        &origin,
+                                        &from_channel,
+                                        &to_channel,
+                                        target,
+                                        m.is_present("TO_JSON")).await
+}
+
 async fn sub_bldr_job_start(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     let url = bldr_url_from_matches(&m)?;
@@ -1071,7 +1409,6 @@ async fn sub_pkg_install(ui: &mut UI,
                          -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let channel = channel_from_matches_or_default(m);
-    let install_sources = install_sources_from_matches(m)?;
     let token = maybe_auth_token(&m);
     let install_mode =
         if feature_flags.contains(FeatureFlag::OFFLINE_INSTALL) && m.is_present("OFFLINE") {
@@ -1093,8 +1430,12 @@ async fn sub_pkg_install(ui: &mut UI,
         InstallHookMode::default()
     };
 
+    let license_policy = license_policy_from_matches(m)?;
+
     init()?;
 
+    let install_sources =
+        install_sources_from_matches(ui, m, &url, token.as_deref(), &install_mode).await?;
     for install_source in install_sources.iter() {
         let pkg_install =
             common::command::package::install::start(ui,
@@ -1108,7 +1449,8 @@ async fn sub_pkg_install(ui: &mut UI,
                                                      token.as_deref(),
                                                      &install_mode,
                                                      &local_package_usage,
-                                                     install_hook_mode).await?;
+                                                     install_hook_mode,
+                                                     &license_policy).await?;
 
         if let Some(dest_dir) = binlink_dest_dir_from_matches(m) {
             let force = m.is_present("FORCE");
@@ -1122,6 +1464,17 @@ async fn sub_pkg_install(ui: &mut UI,
     Ok(())
 }
 
+fn license_policy_from_matches(m: &ArgMatches<'_>) -> Result<LicensePolicy> {
+    let denied = m.values_of("DENY_LICENSE")
+                  .map(|v| v.map(str::to_string).collect())
+                  .unwrap_or_default();
+    let mode = m.value_of("LICENSE_POLICY")
+                .map(str::parse::<LicensePolicyMode>)
+                .transpose()?
+                .unwrap_or_default();
+    Ok(LicensePolicy::new(denied, mode))
+}
+
 fn sub_pkg_path(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     command::pkg::path::start(&ident, &*FS_ROOT_PATH)
@@ -1157,12 +1510,55 @@ fn sub_pkg_sign(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let src = Path::new(m.value_of("SOURCE").unwrap()); // Required via clap
     let dst = Path::new(m.value_of("DEST").unwrap()); // Required via clap
     let cache_key_path = cache_key_path_from_matches(&m);
+    let origin = origin_param_or_env(&m)?;
+    let append = m.is_present("APPEND");
     init()?;
-    let pair = SigKeyPair::get_latest_pair_for(&origin_param_or_env(&m)?,
-                                               &cache_key_path,
-                                               Some(PairType::Secret))?;
+    let signer: Box<dyn SigningBackend> = match m.value_of("SIGNING_BACKEND") {
+        Some("pkcs11") => pkcs11_signer(&origin)?,
+        _ => {
+            Box::new(SigKeyPair::get_latest_pair_for(&origin,
+                                                     &cache_key_path,
+                                                     Some(PairType::Secret))?)
+        }
+    };
+
+    command::pkg::sign::start(ui, signer.as_ref(), &src, &dst, append, &cache_key_path)
+}
+
+#[cfg(feature = "pkcs11")]
+fn pkcs11_signer(origin: &str) -> Result<Box<dyn SigningBackend>> {
+    use habitat_core::crypto::signing_backend::Pkcs11Signer;
+    Ok(Box::new(Pkcs11Signer::for_latest_key(origin)?))
+}
 
-    command::pkg::sign::start(ui, &pair, &src, &dst)
+#[cfg(not(feature = "pkcs11"))]
+fn pkcs11_signer(_origin: &str) -> Result<Box<dyn SigningBackend>> {
+    Err(Error::ArgumentError(String::from("This hab was built without PKCS #11 \
+                                           support; rebuild it with the pkcs11 \
+                                           feature")))
+}
+
+fn sub_pkg_re_sign(ui: &mut UI, r: PkgReSign) -> Result<()> {
+    init()?;
+
+    command::pkg::resign::start(ui,
+                                &r.origin,
+                                &r.from_rev,
+                                &r.to_rev,
+                                &r.dir,
+                                &r.cache_key_path.cache_key_path)
+}
+
+fn sub_pkg_shell(ui: &mut UI, s: PkgShell) -> Result<()> {
+    init()?;
+
+    command::pkg::shell::start(ui, &s.pkg_ident.pkg_ident(), s.shell, s.tdeps)
+}
+
+fn sub_pkg_upgrade_hart(ui: &mut UI, u: PkgUpgradeHart) -> Result<()> {
+    init()?;
+
+    command::pkg::upgrade_hart::start(ui, &u.hart_file, &u.cache_key_path.cache_key_path)
 }
 
 async fn sub_pkg_bulkupload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1235,12 +1631,22 @@ async fn sub_pkg_delete(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
-fn sub_pkg_verify(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+async fn sub_pkg_verify(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let src = Path::new(m.value_of("SOURCE").unwrap()); // Required via clap
     let cache_key_path = cache_key_path_from_matches(&m);
+    let to_json = m.is_present("TO_JSON");
+    let deep = m.is_present("DEEP");
+    let policy = match m.values_of("REQUIRE_SIGNER") {
+        Some(origins) => SignaturePolicy::All(origins.map(str::to_string).collect()),
+        None => SignaturePolicy::Any,
+    };
+    let trust = match m.value_of("POLICY") {
+        Some(path) => TrustPolicy::from_file(path)?,
+        None => TrustPolicy::load(&cache_key_path)?,
+    };
     init()?;
 
-    command::pkg::verify::start(ui, &src, &cache_key_path)
+    command::pkg::verify::start(ui, &src, &cache_key_path, policy, &trust, deep, to_json).await
 }
 
 fn sub_pkg_header(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1250,12 +1656,28 @@ fn sub_pkg_header(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::pkg::header::start(ui, &src)
 }
 
-fn sub_pkg_info(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
-    let src = Path::new(m.value_of("SOURCE").unwrap()); // Required via clap
+async fn sub_pkg_info(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let source = m.value_of("SOURCE").unwrap(); // Required via clap
     let to_json = m.is_present("TO_JSON");
     init()?;
 
-    command::pkg::info::start(ui, &src, to_json)
+    let src = Path::new(source);
+    if src.is_file() {
+        return command::pkg::info::start(ui, &src, to_json);
+    }
+    let ident = PackageIdent::from_str(source)?;
+    let url = bldr_url_from_matches(&m)?;
+    let channel = channel_from_matches_or_default(m);
+    let token = maybe_auth_token(&m);
+    let target = target_from_matches(m)?;
+    command::pkg::info::start_for_ident(ui,
+                                        &ident,
+                                        target,
+                                        &url,
+                                        &channel,
+                                        token.as_deref(),
+                                        &*FS_ROOT_PATH,
+                                        to_json).await
 }
 
 async fn sub_pkg_promote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1263,8 +1685,22 @@ async fn sub_pkg_promote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let channel = required_channel_from_matches(&m);
     let token = auth_token_param_or_env(&m)?;
     let target = target_from_matches(m)?;
-    let ident = required_pkg_ident_from_input(m)?;
-    command::pkg::promote::start(ui, &url, (&ident, target), &channel, &token).await
+    match fully_qualified_idents_from_file_input(m)? {
+        Some(idents) => {
+            let concurrency = bulk_concurrency_from_matches(m)?;
+            command::pkg::promote::start_many(ui,
+                                              &url,
+                                              idents,
+                                              target,
+                                              &channel,
+                                              &token,
+                                              concurrency).await
+        }
+        None => {
+            let ident = required_pkg_ident_from_input(m)?;
+            command::pkg::promote::start(ui, &url, (&ident, target), &channel, &token).await
+        }
+    }
 }
 
 async fn sub_pkg_demote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1272,8 +1708,22 @@ async fn sub_pkg_demote(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let channel = required_channel_from_matches(&m);
     let token = auth_token_param_or_env(&m)?;
     let target = target_from_matches(m)?;
-    let ident = required_pkg_ident_from_input(m)?;
-    command::pkg::demote::start(ui, &url, (&ident, target), &channel, &token).await
+    match fully_qualified_idents_from_file_input(m)? {
+        Some(idents) => {
+            let concurrency = bulk_concurrency_from_matches(m)?;
+            command::pkg::demote::start_many(ui,
+                                             &url,
+                                             idents,
+                                             target,
+                                             &channel,
+                                             &token,
+                                             concurrency).await
+        }
+        None => {
+            let ident = required_pkg_ident_from_input(m)?;
+            command::pkg::demote::start(ui, &url, (&ident, target), &channel, &token).await
+        }
+    }
 }
 
 async fn sub_pkg_channels(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
@@ -1307,6 +1757,10 @@ async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
         process::exit(1);
     }
     validate.cfg = Some(buf.clone());
+    if m.is_present("DRY_RUN") {
+        validate.dry_run = Some(true);
+        return sub_svc_set_dry_run(&remote_sup_addr, &secret_key, validate).await;
+    }
     let cache = cache_key_path_from_matches(&m);
     let mut set = sup_proto::ctl::SvcSetCfg::default();
     match (service_group.org(), user_param_or_env(&m)) {
@@ -1324,6 +1778,7 @@ async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     }
     set.service_group = Some(service_group.into());
     set.version = Some(value_t!(m, "VERSION_NUMBER", u64).unwrap());
+    sign_request(&mut set, m.value_of("SIGN_WITH"), &cache, &remote_sup_addr)?;
     ui.begin(format!("Setting new configuration version {} for {}",
                      set.version
                         .as_ref()
@@ -1370,6 +1825,62 @@ async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Prints how each member of the service group would apply the configuration in `validate`,
+/// without applying it.
+async fn sub_svc_set_dry_run(remote_sup_addr: &CtlGateway,
+                             secret_key: &str,
+                             validate: sup_proto::ctl::SvcValidateCfg)
+                             -> Result<()> {
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(remote_sup_addr, secret_key, validate).await?;
+    let mut print_header = true;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "MemberCfgChange" => {
+                let change = reply.parse::<sup_proto::types::MemberCfgChange>()
+                                  .map_err(SrvClientError::Decode)?;
+                if print_header {
+                    writeln!(out, "{}", CFG_DRY_RUN_HEADER.join("\t"))?;
+                    print_header = false;
+                }
+                let changed_keys = if change.changed_keys.is_empty() {
+                    "<none>".to_string()
+                } else {
+                    change.changed_keys.join(", ")
+                };
+                writeln!(out,
+                         "{}\t{}\t{}\t{}",
+                         change.member_id, change.ident, change.outcome, changed_keys)?;
+            }
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Sign `msg` for the Supervisor at `remote_sup` with the latest secret key named `key_name`, if
+/// one was given.
+fn sign_request<T: SignedRequest>(msg: &mut T,
+                                  key_name: Option<&str>,
+                                  cache_key_path: &Path,
+                                  remote_sup: &CtlGateway)
+                                  -> Result<()> {
+    if let Some(name) = key_name {
+        let pair = SigKeyPair::get_latest_pair_for(name, cache_key_path, Some(PairType::Secret))?;
+        ui::ui().status(Status::Signing,
+                        format!("request with {}", pair.name_with_rev()))?;
+        msg.sign(&pair, &remote_sup.to_string())?;
+    }
+    Ok(())
+}
+
 async fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     let cfg = config::load()?;
@@ -1396,10 +1907,114 @@ async fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+async fn sub_svc_env(svc_env: SvcEnv) -> Result<()> {
+    let cfg = config::load()?;
+    let remote_sup_addr = svc_env.remote_sup.to_ctl_gateway()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SvcGetEnv { ident: Some(svc_env.pkg_ident.pkg_ident().into()), };
+
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(&remote_sup_addr, &secret_key, msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "ServiceEnv" => {
+                let env = reply.parse::<sup_proto::types::ServiceEnv>()
+                               .map_err(SrvClientError::Decode)?;
+                print_service_env(&mut out, &env)?;
+            }
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => {
+                warn!("Unexpected service environment message, {:?}", reply);
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
 async fn sub_svc_load(svc_load: SvcLoad) -> Result<()> {
-    let remote_sup_addr = svc_load.remote_sup.to_listen_ctl_addr();
-    let msg = habitat_sup_protocol::ctl::SvcLoad::try_from(svc_load)?;
-    gateway_util::send(&remote_sup_addr, msg).await
+    let remote_sup_addr = svc_load.remote_sup.to_ctl_gateway()?;
+    send_svc_load(svc_load, &remote_sup_addr).await
+}
+
+async fn send_svc_load(svc_load: SvcLoad, remote_sup_addr: &CtlGateway) -> Result<()> {
+    let sign_with = svc_load.sign_with.clone();
+    let cache_key_path = svc_load.cache_key_path.cache_key_path.clone();
+    let mut msg = habitat_sup_protocol::ctl::SvcLoad::try_from(svc_load)?;
+    sign_request(&mut msg, sign_with.as_deref(), &cache_key_path, remote_sup_addr)?;
+    gateway_util::send(remote_sup_addr, msg).await
+}
+
+async fn sub_svc_apply_manifest(svc_apply_manifest: SvcApplyManifest) -> Result<()> {
+    let remote_sup_addr = svc_apply_manifest.remote_sup.to_ctl_gateway()?;
+    let content = std::fs::read_to_string(&svc_apply_manifest.manifest)?;
+    let services = svc::svc_loads_from_str(&content)?;
+    let loaded = loaded_services(&remote_sup_addr).await?;
+    let changes = command::service::manifest::plan(services, &loaded);
+    if changes.is_empty() {
+        println!("No services to load or unload.");
+        return Ok(());
+    }
+    if svc_apply_manifest.dry_run {
+        for change in &changes {
+            println!("Would {}", change);
+        }
+        return Ok(());
+    }
+    let mut errors = HashMap::new();
+    for change in changes {
+        let (ident, result) = match change {
+            Change::Load(svc_load) | Change::Reload(svc_load) => {
+                let ident = svc_load.pkg_ident.clone().pkg_ident();
+                (ident, send_svc_load(svc_load, &remote_sup_addr).await)
+            }
+            Change::Unload(ident) => {
+                let msg = sup_proto::ctl::SvcUnload { ident:              Some(ident.clone().into()),
+                                                      timeout_in_seconds: None, };
+                (ident, gateway_util::send(&remote_sup_addr, msg).await)
+            }
+        };
+        if let Err(e) = result {
+            errors.insert(ident, e);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.into())
+    }
+}
+
+/// The idents of the services loaded by the Supervisor at `remote_sup`.
+async fn loaded_services(remote_sup: &CtlGateway) -> Result<Vec<PackageIdent>> {
+    let cfg = config::load()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SvcStatus::default();
+    let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    let mut idents = Vec::new();
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "ServiceStatus" => {
+                let status = reply.parse::<sup_proto::types::ServiceStatus>()
+                                  .map_err(SrvClientError::Decode)?;
+                idents.push(status.ident.into());
+            }
+            "NetOk" => (),
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => warn!("Unexpected status message, {:?}", reply),
+        }
+    }
+    Ok(idents)
 }
 
 async fn sub_svc_bulk_load(svc_bulk_load: SvcBulkLoad) -> Result<()> {
@@ -1417,19 +2032,36 @@ async fn sub_svc_bulk_load(svc_bulk_load: SvcBulkLoad) -> Result<()> {
     }
 }
 
-async fn sub_svc_unload(m: &ArgMatches<'_>) -> Result<()> {
+async fn sub_svc_unload(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     let timeout_in_seconds =
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
-    let msg = sup_proto::ctl::SvcUnload { ident: Some(ident.into()),
-                                          timeout_in_seconds };
     let remote_sup_addr = remote_sup_from_input(m)?;
+    let purge_data = m.is_present("PURGE_DATA");
+    if purge_data
+       && !m.is_present("NO_PROMPT")
+       && !ui.prompt_yes_no(&format!("Delete the data directory of {} on {}?",
+                                     ident, remote_sup_addr),
+                            Some(false))?
+    {
+        ui.fatal("Aborted")?;
+        return Ok(());
+    }
+    let msg = sup_proto::ctl::SvcUnload { ident: Some(ident.into()),
+                                          timeout_in_seconds,
+                                          purge_data: Some(purge_data),
+                                          archive_data: m.value_of("ARCHIVE_DATA")
+                                                         .map(str::to_string),
+                                          encrypt_archive: Some(m.is_present("ENCRYPT_ARCHIVE")) };
     gateway_util::send(&remote_sup_addr, msg).await
 }
 
 async fn sub_svc_update(u: hab::cli::hab::svc::Update) -> Result<()> {
-    let ctl_addr = u.remote_sup.to_listen_ctl_addr();
-    let msg: sup_proto::ctl::SvcUpdate = TryFrom::try_from(u)?;
+    let ctl_addr = u.remote_sup.to_ctl_gateway()?;
+    let sign_with = u.sign_with.clone();
+    let cache_key_path = u.cache_key_path.cache_key_path.clone();
+    let mut msg: sup_proto::ctl::SvcUpdate = TryFrom::try_from(u)?;
+    sign_request(&mut msg, sign_with.as_deref(), &cache_key_path, &ctl_addr)?;
     gateway_util::send(&ctl_addr, msg).await
 }
 
@@ -1440,24 +2072,55 @@ async fn sub_svc_start(m: &ArgMatches<'_>) -> Result<()> {
     gateway_util::send(&remote_sup_addr, msg).await
 }
 
-async fn sub_svc_status(pkg_ident: Option<PackageIdent>, remote_sup: &ListenCtlAddr) -> Result<()> {
+async fn sub_svc_status(pkg_ident: Option<PackageIdent>,
+                        details: bool,
+                        history: bool,
+                        format: StatusFormat,
+                        watch_interval: Option<Duration>,
+                        remote_sup: &CtlGateway,
+                        http_gateway: Option<HttpGateway>)
+                        -> Result<()> {
+    if let Some(gateway) = http_gateway {
+        if pkg_ident.is_some() || details || history || watch_interval.is_some() {
+            return Err(Error::ArgumentError(String::from("--http-gateway can't be used with a \
+                                                          package identifier, --details, \
+                                                          --history or --watch")));
+        }
+        println!("{}", status::request_http(&gateway).await?);
+        return Ok(());
+    }
+    if let Some(interval) = watch_interval {
+        if format.is_structured() {
+            return Err(Error::ArgumentError(String::from("--watch can only be used with the \
+                                                          table and wide formats")));
+        }
+        let details = details || format == StatusFormat::Wide;
+        return status::watch(&mut ui::ui(), remote_sup, pkg_ident, details, interval).await;
+    }
+    if format.is_structured() {
+        let report = status::request(remote_sup, pkg_ident, history).await?;
+        report.write(&mut io::stdout(), format)?;
+        return Ok(());
+    }
     let cfg = config::load()?;
     let secret_key = config::ctl_secret_key(&cfg)?;
     let mut msg = sup_proto::ctl::SvcStatus::default();
     msg.ident = pkg_ident.map(Into::into);
+    msg.history = Some(history);
 
-    let mut out = TabWriter::new(io::stdout());
     let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    let details = details || format == StatusFormat::Wide;
+    let mut out = TabWriter::new(io::stdout());
     // Ensure there is at least one result from the server otherwise produce an error
     if let Some(message_result) = response.next().await {
         let reply = message_result?;
-        print_svc_status(&mut out, &reply, true)?;
+        print_svc_status(&mut out, &reply, true, details)?;
     } else {
         return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into());
     }
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
-        print_svc_status(&mut out, &reply, false)?;
+        print_svc_status(&mut out, &reply, false, details)?;
     }
     out.flush()?;
     Ok(())
@@ -1516,6 +2179,7 @@ async fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
         }
         _ => msg.content = Some(buf.to_vec()),
     }
+    sign_request(&mut msg, m.value_of("SIGN_WITH"), &cache, &remote_sup_addr)?;
     ui.status(Status::Applying, format!("via peer {}", remote_sup_addr))
       .unwrap();
     let mut response = SrvClient::request(&remote_sup_addr, &secret_key, msg).await?;
@@ -1540,7 +2204,7 @@ async fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
-async fn sub_sup_depart(member_id: String, remote_sup: &ListenCtlAddr) -> Result<()> {
+async fn sub_sup_depart(member_id: String, remote_sup: &CtlGateway) -> Result<()> {
     let cfg = config::load()?;
     let secret_key = config::ctl_secret_key(&cfg)?;
     let mut ui = ui::ui();
@@ -1569,7 +2233,18 @@ async fn sub_sup_depart(member_id: String, remote_sup: &ListenCtlAddr) -> Result
     Ok(())
 }
 
-async fn sub_sup_restart(remote_sup: &ListenCtlAddr) -> Result<()> {
+#[cfg(not(target_os = "macos"))]
+async fn sub_sup_term(remote_sup: CtlGateway, yes: bool, depart: bool) -> Result<()> {
+    if !yes {
+        return Err(Error::ArgumentError(format!("Terminating the Supervisor at {} stops all \
+                                                 of its services; pass --yes to confirm",
+                                                remote_sup)));
+    }
+    let msg = sup_proto::ctl::SupTerm { depart: Some(depart) };
+    gateway_util::send(&remote_sup, msg).await
+}
+
+async fn sub_sup_restart(remote_sup: &CtlGateway) -> Result<()> {
     let cfg = config::load()?;
     let secret_key = config::ctl_secret_key(&cfg)?;
     let mut ui = ui::ui();
@@ -1593,6 +2268,99 @@ async fn sub_sup_restart(remote_sup: &ListenCtlAddr) -> Result<()> {
     Ok(())
 }
 
+async fn sub_sup_drain(timeout: Option<ShutdownTimeout>, remote_sup: &CtlGateway) -> Result<()> {
+    let msg = sup_proto::ctl::SupDrain { timeout_in_seconds: timeout.map(u32::from), };
+    gateway_util::send(remote_sup, msg).await
+}
+
+async fn sub_sup_forget_departed(remote_sup: &CtlGateway) -> Result<()> {
+    gateway_util::send(remote_sup, sup_proto::ctl::SupForgetDeparted::default()).await
+}
+
+async fn sub_sup_members(member_id: Option<String>,
+                         history: bool,
+                         remote_sup: &CtlGateway)
+                         -> Result<()> {
+    let cfg = config::load()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SupMembers { history: Some(history),
+                                           member_id };
+
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    let mut print_header = true;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        print_sup_member(&mut out, &reply, print_header)?;
+        print_header = false;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+async fn sub_sup_ring_reload(retire: bool, remote_sup: &CtlGateway) -> Result<()> {
+    let msg = sup_proto::ctl::SupRingReload { retire: Some(retire) };
+    gateway_util::send(remote_sup, msg).await
+}
+
+async fn sub_sup_ring_health(remote_sup: &CtlGateway) -> Result<()> {
+    let cfg = config::load()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SupRingHealth::default();
+
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "RingHealth" => {
+                let health = reply.parse::<sup_proto::types::RingHealth>()
+                                  .map_err(SrvClientError::Decode)?;
+                print_ring_health(&mut out, &health)?;
+            }
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => {
+                warn!("Unexpected ring health message, {:?}", reply);
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+async fn sub_sup_key_revocations(remote_sup: &CtlGateway) -> Result<()> {
+    let cfg = config::load()?;
+    let secret_key = config::ctl_secret_key(&cfg)?;
+    let msg = sup_proto::ctl::SupKeyRevocations::default();
+
+    let mut out = TabWriter::new(io::stdout());
+    let mut response = SrvClient::request(remote_sup, &secret_key, msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "KeyRevocations" => {
+                let revocations = reply.parse::<sup_proto::types::KeyRevocations>()
+                                       .map_err(SrvClientError::Decode)?;
+                print_key_revocations(&mut out, &revocations)?;
+            }
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => {
+                warn!("Unexpected key revocations message, {:?}", reply);
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
 fn sub_sup_secret_generate() -> Result<()> {
     let mut ui = ui::ui();
     let mut buf = String::new();
@@ -1601,6 +2369,31 @@ fn sub_sup_secret_generate() -> Result<()> {
     Ok(())
 }
 
+async fn sub_sup_secret_rotate(grace_period: Duration, remote_sup: &CtlGateway) -> Result<()> {
+    let mut ui = ui::ui();
+    let mut secret = String::new();
+    sup_proto::generate_secret_key(&mut secret);
+    let grace_period_secs = u32::try_from(grace_period.as_secs()).unwrap_or(u32::MAX);
+    let msg = sup_proto::ctl::SupSecretRotate { secret:       Some(secret.clone()),
+                                                grace_period: Some(grace_period_secs), };
+    ui.begin(format!("Rotating the ctl gateway secret of {}", remote_sup))?;
+    gateway_util::send(remote_sup, msg).await?;
+    ui.info(secret)?;
+    ui.end(format!("Rotated the ctl gateway secret. The previous secret is accepted for {}s; \
+                    give clients the new one above before then.",
+                   grace_period_secs))?;
+    Ok(())
+}
+
+async fn sub_sup_secret_revoke_sessions(remote_sup: &CtlGateway) -> Result<()> {
+    let mut ui = ui::ui();
+    ui.begin(format!("Revoking the ctl gateway sessions of {}", remote_sup))?;
+    gateway_util::send(remote_sup, sup_proto::ctl::SupSessionsRevoke::default()).await?;
+    ui.end("Revoked the ctl gateway sessions. Clients open new ones with the secret as they \
+            need them.")?;
+    Ok(())
+}
+
 fn sub_supportbundle(ui: &mut UI) -> Result<()> {
     init()?;
 
@@ -1617,13 +2410,76 @@ fn sub_ring_key_export(m: &ArgMatches<'_>) -> Result<()> {
 
 fn sub_ring_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let ring = m.value_of("RING").unwrap(); // Required via clap
+    let passphrase = passphrase_from_matches(&m)?;
+    // REVISION is required with FROM_PASSPHRASE via clap
+    let from_passphrase = match m.value_of("FROM_PASSPHRASE") {
+        Some(path) => {
+            Some((Passphrase::from_file(Path::new(path))?, m.value_of("REVISION").unwrap()))
+        }
+        None => None,
+    };
+    let from_passphrase = from_passphrase.as_ref()
+                                         .map(|(passphrase, revision)| (passphrase, *revision));
+    init()?;
+
+    if m.is_present("STDOUT") {
+        return command::ring::key::generate::start_to_stdout(ring,
+                                                             from_passphrase,
+                                                             passphrase.as_ref());
+    }
+    let cache_key_path = cache_key_path_from_matches(&m);
+    command::ring::key::generate::start(ui,
+                                        ring,
+                                        from_passphrase,
+                                        passphrase.as_ref(),
+                                        &cache_key_path)
+}
+
+async fn sub_ring_key_rotate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let ring = m.value_of("RING").unwrap(); // Required via clap
+    let tls = CtlTlsOptions::from_clap(m).to_ctl_tls()?;
+    let remote_sups = m.values_of("REMOTE_SUP")
+                       .unwrap() // Defaulted via clap
+                       .map(|addr| {
+                           Ok(CtlGateway { addr: ListenCtlAddr::resolve_listen_ctl_addr(addr)?,
+                                           tls:  tls.clone(), })
+                       })
+                       .collect::<Result<Vec<_>>>()?;
     let cache_key_path = cache_key_path_from_matches(&m);
     init()?;
 
-    command::ring::key::generate::start(ui, ring, &cache_key_path)
+    command::ring::key::rotate::start(ui, ring, &remote_sups, &cache_key_path).await
+}
+
+async fn sub_ring_exec_update(ui: &mut UI, exec: RingExecUpdate) -> Result<()> {
+    let remote_sup = exec.update.remote_sup.to_ctl_gateway()?;
+    let sign_with = exec.update.sign_with.clone().ok_or_else(|| {
+                        Error::ArgumentError(String::from("Ring commands must be signed; give \
+                                                           a signing key with --sign-with"))
+                    })?;
+    let cache_key_path = exec.update.cache_key_path.cache_key_path.clone();
+    let signer =
+        SigKeyPair::get_latest_pair_for(&sign_with, &cache_key_path, Some(PairType::Secret))?;
+    ui.status(Status::Signing,
+              format!("ring command with {}", signer.name_with_rev()))?;
+    let msg = sup_proto::ctl::SvcUpdate::try_from(exec.update)?;
+    command::ring::exec::start(ui,
+                               &remote_sup,
+                               msg,
+                               &signer,
+                               Duration::from_secs(exec.ttl.into()),
+                               Duration::from_secs(exec.wait)).await
 }
 
 fn sub_ring_key_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    // Waiting on a terminal would look like a hang to anyone who forgot to pipe a key in
+    if atty::is(atty::Stream::Stdin) && !m.is_present("FROM_STDIN") {
+        return Err(Error::ArgumentError(String::from("No ring key was piped \
+                                                      to standard input. \
+                                                      Pipe a key in, or use \
+                                                      --from-stdin to paste \
+                                                      one.")));
+    }
     let mut content = String::new();
     let cache_key_path = cache_key_path_from_matches(&m);
     init()?;
@@ -1634,8 +2490,11 @@ fn sub_ring_key_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
 }
 
 fn sub_service_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
-    let org = org_param_or_env(&m)?;
     let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
+    let org = match (service_group.org(), m.value_of("ORG")) {
+        (Some(org), None) => org.to_string(),
+        _ => org_param_or_env(&m)?,
+    };
     let cache_key_path = cache_key_path_from_matches(&m);
     init()?;
 
@@ -1782,6 +2641,23 @@ fn target_from_matches(matches: &ArgMatches<'_>) -> Result<PackageTarget> {
            .map_err(Error::HabitatCore)
 }
 
+/// All targets given for `PKG_TARGET`, or the active target if none were given.
+fn targets_from_matches(matches: &ArgMatches<'_>) -> Result<Vec<PackageTarget>> {
+    match matches.values_of("PKG_TARGET") {
+        Some(targets) => {
+            let mut unique_targets = Vec::new();
+            for target in targets {
+                let target = PackageTarget::from_str(target).map_err(Error::HabitatCore)?;
+                if !unique_targets.contains(&target) {
+                    unique_targets.push(target);
+                }
+            }
+            Ok(unique_targets)
+        }
+        None => Ok(vec![active_target()]),
+    }
+}
+
 /// Return the path to create our binlinks in, or None if no binlinking should occur
 fn binlink_dest_dir_from_matches(matches: &ArgMatches<'_>) -> Option<PathBuf> {
     // is_present always returns true since BINLINK_DIR has a default value, so we need to use
@@ -1793,6 +2669,14 @@ fn binlink_dest_dir_from_matches(matches: &ArgMatches<'_>) -> Option<PathBuf> {
     }
 }
 
+/// Return the passphrase to encrypt a generated secret key with, if --passphrase-file was given
+fn passphrase_from_matches(matches: &ArgMatches<'_>) -> Result<Option<Passphrase>> {
+    match matches.value_of("PASSPHRASE_FILE") {
+        Some(path) => Ok(Some(Passphrase::from_file(Path::new(path))?)),
+        None => Ok(None),
+    }
+}
+
 /// Helper function to determine active package target.
 /// It overrides x86_64-darwin to be x86_64-linux in order
 /// to provide a better user experience (ie, for the 99% case)
@@ -1804,12 +2688,32 @@ fn active_target() -> PackageTarget {
     }
 }
 
-fn install_sources_from_matches(matches: &ArgMatches<'_>) -> Result<Vec<InstallSource>> {
-    matches
-        .values_of("PKG_IDENT_OR_ARTIFACT")
-        .unwrap() // Required via clap
-        .map(|t| t.parse().map_err(Error::from))
-        .collect()
+/// The install sources given, where any artifact given by URL is first downloaded and verified.
+async fn install_sources_from_matches(ui: &mut UI,
+                                      matches: &ArgMatches<'_>,
+                                      bldr_url: &str,
+                                      token: Option<&str>,
+                                      install_mode: &InstallMode)
+                                      -> Result<Vec<InstallSource>> {
+    let sources = matches.values_of("PKG_IDENT_OR_ARTIFACT").unwrap(); // Required via clap
+    let mut install_sources = Vec::new();
+    for source in sources {
+        let install_source = if install::is_artifact_url(source) {
+            install::fetch_remote_archive(ui,
+                                          source,
+                                          bldr_url,
+                                          PRODUCT,
+                                          VERSION,
+                                          &*FS_ROOT_PATH,
+                                          &cache_artifact_path(Some(&*FS_ROOT_PATH)),
+                                          token,
+                                          install_mode).await?
+        } else {
+            source.parse()?
+        };
+        install_sources.push(install_source);
+    }
+    Ok(install_sources)
 }
 
 fn idents_from_matches(matches: &ArgMatches<'_>) -> Result<Vec<PackageIdent>> {
@@ -1825,7 +2729,7 @@ fn idents_from_matches(matches: &ArgMatches<'_>) -> Result<Vec<PackageIdent>> {
 fn idents_from_file_matches(ui: &mut UI,
                             matches: &ArgMatches<'_>,
                             cli_channel: &ChannelIdent,
-                            cli_target: PackageTarget)
+                            cli_targets: &[PackageTarget])
                             -> Result<Vec<PackageSet>> {
     let mut sources: Vec<PackageSet> = Vec::new();
 
@@ -1837,10 +2741,12 @@ fn idents_from_file_matches(ui: &mut UI,
                 sources.append(&mut package_sets)
             } else {
                 let idents_from_file = habitat_common::cli::file_into_idents(filename)?;
-                let package_set = PackageSet { idents:  idents_from_file,
-                                               channel: cli_channel.clone(),
-                                               target:  cli_target, };
-                sources.push(package_set)
+                for &cli_target in cli_targets {
+                    let package_set = PackageSet { idents:  idents_from_file.clone(),
+                                                   channel: cli_channel.clone(),
+                                                   target:  cli_target, };
+                    sources.push(package_set)
+                }
             }
         }
     }
@@ -1908,7 +2814,8 @@ fn excludes_from_matches(matches: &ArgMatches<'_>) -> Vec<PackageIdent> {
 
 fn print_svc_status<T>(out: &mut T,
                        reply: &SrvMessage,
-                       print_header: bool)
+                       print_header: bool,
+                       details: bool)
                        -> result::Result<(), SrvClientError>
     where T: io::Write
 {
@@ -1917,6 +2824,21 @@ fn print_svc_status<T>(out: &mut T,
             reply.parse::<sup_proto::types::ServiceStatus>()
                  .map_err(SrvClientError::Decode)?
         }
+        "ServiceTransition" => {
+            let transition = reply.parse::<sup_proto::types::ServiceTransition>()
+                                  .map_err(SrvClientError::Decode)?;
+            if print_header {
+                writeln!(out, "{}", SERVICE_HISTORY_HEADER.join("\t"))?;
+            }
+            writeln!(out,
+                     "{}\t{}\t{}\t{}\t{}",
+                     format_timestamp(transition.timestamp),
+                     transition.ident,
+                     transition.service_group,
+                     transition.event,
+                     transition.detail.as_deref().unwrap_or("-"))?;
+            return Ok(());
+        }
         "NetOk" => {
             println!("No services loaded.");
             return Ok(());
@@ -1947,7 +2869,14 @@ fn print_svc_status<T>(out: &mut T,
         }
     };
     if print_header {
-        writeln!(out, "{}", STATUS_HEADER.join("\t")).unwrap();
+        if details {
+            writeln!(out,
+                     "{}\t{}",
+                     STATUS_HEADER.join("\t"),
+                     STATUS_DETAILS_HEADER.join("\t")).unwrap();
+        } else {
+            writeln!(out, "{}", STATUS_HEADER.join("\t")).unwrap();
+        }
     }
     // Composites were removed in 0.75 but people could be
     // depending on the exact format of this output even if they
@@ -1957,27 +2886,179 @@ fn print_svc_status<T>(out: &mut T,
     //
     // TODO: Remove this when we have a stable machine-readable alternative
     // that scripts could depend on
+    write!(out,
+           "{}\tstandalone\t{}\t{}\t{}\t{}\t{}",
+           status.ident,
+           DesiredState::from_str(&svc_desired_state)?,
+           ProcessState::from_str(&svc_state)?,
+           svc_elapsed,
+           svc_pid,
+           status.service_group,)?;
+    if details {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "<none>".to_string());
+        write!(out,
+               "\t{}\t{}\t{}\t{}",
+               or_none(status.cpuset),
+               or_none(status.nice.map(|n| n.to_string())),
+               or_none(status.priority_class),
+               or_none(status.limits))?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn print_sup_member<T>(out: &mut T,
+                       reply: &SrvMessage,
+                       print_header: bool)
+                       -> result::Result<(), SrvClientError>
+    where T: io::Write
+{
+    match reply.message_id() {
+        "MemberStatus" => {
+            let status = reply.parse::<sup_proto::types::MemberStatus>()
+                              .map_err(SrvClientError::Decode)?;
+            if print_header {
+                writeln!(out, "{}", MEMBERS_HEADER.join("\t"))?;
+            }
+            writeln!(out,
+                     "{}\t{}\t{}\t{}\t{}",
+                     status.member_id,
+                     status.address.as_deref().unwrap_or("<none>"),
+                     status.health,
+                     status.flaps.unwrap_or_default(),
+                     status.last_departed
+                           .map_or_else(|| "<none>".to_string(), format_timestamp))?;
+        }
+        "MemberTransition" => {
+            let transition = reply.parse::<sup_proto::types::MemberTransition>()
+                                  .map_err(SrvClientError::Decode)?;
+            if print_header {
+                writeln!(out, "{}", MEMBER_HISTORY_HEADER.join("\t"))?;
+            }
+            writeln!(out,
+                     "{}\t{}\t{}\t{}",
+                     format_timestamp(transition.timestamp),
+                     transition.member_id,
+                     transition.from.as_deref().unwrap_or("<none>"),
+                     transition.to)?;
+        }
+        "NetOk" => {
+            println!("No members found.");
+        }
+        "NetErr" => {
+            let err = reply.parse::<sup_proto::net::NetErr>()
+                           .map_err(SrvClientError::Decode)?;
+            return Err(SrvClientError::from(err));
+        }
+        _ => {
+            warn!("Unexpected members message, {:?}", reply);
+        }
+    }
+    Ok(())
+}
+
+fn print_ring_health<T>(out: &mut T, health: &sup_proto::types::RingHealth) -> io::Result<()>
+    where T: io::Write
+{
+    writeln!(out, "status:\t{}", health.status)?;
     writeln!(out,
-             "{}\tstandalone\t{}\t{}\t{}\t{}\t{}",
-             status.ident,
-             DesiredState::from_str(&svc_desired_state)?,
-             ProcessState::from_str(&svc_state)?,
-             svc_elapsed,
-             svc_pid,
-             status.service_group,)?;
+             "members:\t{} alive, {} suspect, {} confirmed, {} departed",
+             health.alive.unwrap_or_default(),
+             health.suspect.unwrap_or_default(),
+             health.confirmed.unwrap_or_default(),
+             health.departed.unwrap_or_default())?;
+    if let Some(generated_at) = health.generated_at {
+        writeln!(out, "analyzed at:\t{}", format_timestamp(generated_at))?;
+    }
+    if !health.view_conflicts.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", VIEW_CONFLICTS_HEADER.join("\t"))?;
+        for conflict in &health.view_conflicts {
+            writeln!(out,
+                     "{}\t{}\t{}\t{}\t{}",
+                     conflict.member_id,
+                     conflict.reported_by,
+                     conflict.local,
+                     conflict.reported,
+                     format_timestamp(conflict.timestamp))?;
+        }
+    }
+    if !health.election_conflicts.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", ELECTION_CONFLICTS_HEADER.join("\t"))?;
+        for conflict in &health.election_conflicts {
+            writeln!(out,
+                     "{}\t{}\t{}\t{}",
+                     conflict.service_group,
+                     conflict.term,
+                     conflict.leaders.join(","),
+                     format_timestamp(conflict.timestamp))?;
+        }
+    }
     Ok(())
 }
 
+fn print_key_revocations<T>(out: &mut T,
+                            revocations: &sup_proto::types::KeyRevocations)
+                            -> io::Result<()>
+    where T: io::Write
+{
+    if revocations.sources.is_empty() {
+        return writeln!(out, "No key revocation lists are being fetched");
+    }
+    let timestamp = |t: Option<u64>| t.map(format_timestamp).unwrap_or_else(|| "-".to_string());
+    writeln!(out, "{}", KEY_REVOCATIONS_HEADER.join("\t"))?;
+    for source in &revocations.sources {
+        writeln!(out,
+                 "{}\t{}\t{}\t{}\t{}\t{}",
+                 source.url,
+                 source.origin.as_deref().unwrap_or("-"),
+                 source.revoked.unwrap_or_default(),
+                 timestamp(source.last_attempt),
+                 timestamp(source.last_success),
+                 source.error.as_deref().unwrap_or("-"))?;
+    }
+    Ok(())
+}
+
+fn print_service_env<T>(out: &mut T, env: &sup_proto::types::ServiceEnv) -> io::Result<()>
+    where T: io::Write
+{
+    writeln!(out, "{}", SERVICE_ENV_HEADER.join("\t"))?;
+    for var in &env.vars {
+        let value = if var.redacted.unwrap_or_default() {
+            "[REDACTED]"
+        } else {
+            var.value.as_deref().unwrap_or("")
+        };
+        let source = if var.inherited.unwrap_or_default() {
+            "inherited"
+        } else {
+            "service"
+        };
+        writeln!(out, "{}\t{}\t{}", var.name, value, source)?;
+    }
+    Ok(())
+}
+
+/// Format seconds since the UNIX epoch as an RFC 3339 UTC timestamp.
+fn format_timestamp(secs: u64) -> String {
+    Utc.timestamp(secs.try_into().unwrap_or(i64::MAX), 0)
+       .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
 fn bulkupload_dir_from_matches(matches: &ArgMatches<'_>) -> PathBuf {
     matches.value_of("UPLOAD_DIRECTORY")
            .map(PathBuf::from)
            .expect("CLAP-validated upload dir")
 }
 
-fn remote_sup_from_input(m: &ArgMatches<'_>) -> Result<ListenCtlAddr> {
-    Ok(m.value_of("REMOTE_SUP")
-        .map_or(Ok(ListenCtlAddr::default()),
-                ListenCtlAddr::resolve_listen_ctl_addr)?)
+fn remote_sup_from_input(m: &ArgMatches<'_>) -> Result<CtlGateway> {
+    let addr = m.value_of("REMOTE_SUP")
+                .map_or(Ok(ListenCtlAddr::default()),
+                        ListenCtlAddr::resolve_listen_ctl_addr)?;
+    Ok(CtlGateway { addr,
+                    tls: CtlTlsOptions::from_clap(m).to_ctl_tls()? })
 }
 
 fn required_pkg_ident_from_input(m: &ArgMatches<'_>) -> Result<PackageIdent> {
@@ -1986,6 +3067,38 @@ fn required_pkg_ident_from_input(m: &ArgMatches<'_>) -> Result<PackageIdent> {
         .parse()?)
 }
 
+/// If PKG_IDENT names a file (or is `-` for stdin) rather than a single
+/// package identifier, return the newline separated identifiers it
+/// contains. Every identifier must be fully qualified.
+fn fully_qualified_idents_from_file_input(m: &ArgMatches<'_>) -> Result<Option<Vec<PackageIdent>>> {
+    let input = m.value_of("PKG_IDENT")
+                 .expect("PKG_IDENT is a required argument");
+    let idents = if input == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        habitat_common::cli::str_into_idents(&content)?
+    } else if Path::new(input).is_file() {
+        habitat_common::cli::file_into_idents(input)?
+    } else {
+        return Ok(None);
+    };
+    if let Some(ident) = idents.iter().find(|ident| !ident.fully_qualified()) {
+        return Err(Error::ArgumentError(format!("'{}' is not valid. Fully \
+                                                 qualified package \
+                                                 identifiers have the form \
+                                                 origin/name/version/release",
+                                                ident)));
+    }
+    Ok(Some(idents))
+}
+
+fn bulk_concurrency_from_matches(m: &ArgMatches<'_>) -> Result<usize> {
+    match m.value_of("CONCURRENCY") {
+        Some(c) => c.parse().map_err(Error::ParseIntError),
+        None => Ok(command::pkg::DEFAULT_BULK_CONCURRENCY),
+    }
+}
+
 /// Check to see if the user has passed in a USER param.
 /// If not, check the HAB_USER env var. If that's
 /// empty too, then return an error.
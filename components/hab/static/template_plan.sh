@@ -303,6 +303,26 @@ pkg_shutdown_timeout_sec="{{ pkg_shutdown_timeout_sec }}"
 {{~ else ~}}
 # pkg_shutdown_timeout_sec=8
 {{~ /if}}
+
+# Optional.
+# The number of seconds the init, post-stop and reconfigure hooks may run, and
+# that the run hook has to pass its first health check, before the Supervisor
+# kills them. `hab svc load --hook-timeouts` overrides these.
+# pkg_hook_timeouts=(init=600 run-startup-grace=120 post-stop=30 reconfigure=60)
+
+# Optional.
+# Configuration keys which the reconfigure hook can't apply. When any of them,
+# or a key within them, changes, the Supervisor restarts the service instead of
+# running the reconfigure hook.
+# pkg_restart_keys=(port tls)
+
+# Optional.
+# Hooks which the Supervisor runs on a schedule, in the service's environment, as
+# HOOK=SCHEDULE, where the schedule is a cron expression in the host's local
+# time. Add ~SECONDS to delay each run by a random amount of up to that many
+# seconds. A run which is still going when the next one is due makes the
+# Supervisor skip the next one. The hooks live in the hooks directory.
+# pkg_scheduled_tasks=("backup=30 2 * * *~300" "vacuum=@weekly")
 {{~ /unless}}
 
 
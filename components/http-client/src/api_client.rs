@@ -1,5 +1,6 @@
-use crate::error::{Error,
-                   Result};
+use crate::{endpoint,
+            error::{Error,
+                    Result}};
 use habitat_core::{env,
                    fs::cache_ssl_path,
                    package::{PackageIdent,
@@ -15,7 +16,9 @@ use reqwest::{header::{HeaderMap,
               Client as ReqwestClient,
               IntoUrl,
               Proxy,
+              Request,
               RequestBuilder,
+              Response,
               Url};
 use std::{fs,
           iter::FromIterator,
@@ -47,10 +50,12 @@ impl ApiClient {
     /// Creates and returns a new `ApiClient` instance.
     ///
     /// Builds a new Reqwest HTTP client with appropriate SSL configuration and HTTP/HTTPS proxy
-    /// support.
+    /// support. An `srv://` endpoint is resolved to the first reachable endpoint its SRV records
+    /// list.
     ///
     /// # Errors
     ///
+    /// * If the SRV records of an `srv://` endpoint cannot be looked up
     /// * If the underlying Reqwest client cannot be created
     /// * If a suitable SSL context cannot be established
     /// * If an HTTP/S proxy cannot be correctly setup
@@ -62,7 +67,7 @@ impl ApiClient {
                   -> Result<Self>
         where T: IntoUrl
     {
-        let endpoint = endpoint.into_url().map_err(Error::ReqwestError)?;
+        let endpoint = endpoint::resolve(endpoint.into_url().map_err(Error::ReqwestError)?)?;
 
         let timeout_in_secs = match env::var("HAB_CLIENT_SOCKET_TIMEOUT") {
             Ok(t) => {
@@ -115,6 +120,14 @@ impl ApiClient {
                        endpoint })
     }
 
+    /// Sends a request built by one of this client's request builders.
+    ///
+    /// This is useful when a request needs to be inspected after it is built, e.g. to consult a
+    /// cache keyed by its URL, before being sent.
+    pub async fn execute(&self, request: Request) -> reqwest::Result<Response> {
+        self.inner.execute(request).await
+    }
+
     /// Builds an HTTP GET request for a given path.
     pub fn get(&self, path: &str) -> RequestBuilder { self.get_with_custom_url(path, |_| {}) }
 
@@ -275,11 +288,10 @@ fn user_agent(product: &str, version: &str) -> Result<HeaderValue> {
 /// 1. If the `core/cacerts` Habitat package is installed locally, then use the latest release's
 ///    `cacert.pem` file.
 /// 2. If there is no 'core/cacerts packages, then a copy of `cacert.pem` will be written in an SSL
-///    cache directory (by default `/hab/cache/ssl` for a root user and `$HOME/.hab/cache/ssl` for
-///    a non-root user) and this will be used. The contents of this file will be inlined in this
-///    crate at build time as a fallback, which means that if the program using this code is
-///    operating in a minimal environment which may not contain any system certificates, it can
-///    still operate.
+///    cache directory (by default `/hab/cache/ssl` for a root user and `$HOME/.hab/cache/ssl` for a
+///    non-root user) and this will be used. The contents of this file will be inlined in this crate
+///    at build time as a fallback, which means that if the program using this code is operating in
+///    a minimal environment which may not contain any system certificates, it can still operate.
 /// 3. Other certs files (for example self-signed certs) that are found in the SSL cache directory
 ///    will also get loaded into the root certs list. Both PEM and DER formats are supported. All
 ///    files will be assumed to be one of the supported formats, and any errors will be ignored
@@ -0,0 +1,182 @@
+//! Resolution of the endpoint a client is created for.
+//!
+//! An on-prem Builder cluster can be given as an `srv://` URL. `srv://builder.internal/path`
+//! names the SRV records of `_https._tcp.builder.internal`, each of which is an endpoint
+//! `https://<target>:<port>/path`. The endpoints are tried in order of priority, and of weight
+//! within a priority, and the first one accepting connections is used, so that a client fails
+//! over to the next endpoint while one is down.
+//!
+//! Any other URL is used as it was given, including one with an IPv6 literal host such as
+//! `https://[fd00::1]:9636`.
+
+use crate::error::{Error,
+                   Result};
+use reqwest::Url;
+use std::{cmp::Reverse,
+          net::{Ipv6Addr,
+                TcpStream},
+          thread,
+          time::Duration};
+use trust_dns_resolver::Resolver;
+
+/// The scheme of a URL naming the SRV records of a cluster's endpoints.
+const SRV_SCHEME: &str = "srv";
+
+/// The service and protocol of the SRV records an `srv://` URL names.
+const SRV_SERVICE: &str = "_https._tcp";
+
+/// How long an endpoint has to accept a connection before the next one is tried.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const HTTPS_PORT: u16 = 443;
+
+#[derive(Debug)]
+struct SrvRecord {
+    priority: u16,
+    weight:   u16,
+    target:   String,
+    port:     u16,
+}
+
+/// The endpoint to use for `url`, which is looked up first if it is an `srv://` URL.
+///
+/// # Errors
+///
+/// * If the SRV records of an `srv://` URL can't be looked up, or there are none
+pub fn resolve(url: Url) -> Result<Url> {
+    if url.scheme() != SRV_SCHEME {
+        return Ok(url);
+    }
+    let name = srv_name(&url)?;
+    let candidates = candidates(&url, lookup_srv(&name)?)?;
+    if candidates.is_empty() {
+        return Err(Error::SrvLookup(name, "no records found".to_string()));
+    }
+    Ok(first_reachable(candidates))
+}
+
+fn srv_name(url: &Url) -> Result<String> {
+    match url.host_str() {
+        // A full SRV name such as `srv://_builder._tcp.example.com` is used as it is.
+        Some(host) if host.starts_with('_') => Ok(host.to_string()),
+        Some(host) => Ok(format!("{}.{}", SRV_SERVICE, host)),
+        None => Err(Error::SrvLookup(url.to_string(), "no host given".to_string())),
+    }
+}
+
+fn lookup_srv(name: &str) -> Result<Vec<SrvRecord>> {
+    let query = name.to_string();
+    // The resolver blocks on a runtime of its own, which can't be started on a thread already
+    // running one.
+    let lookup = thread::spawn(move || -> std::result::Result<Vec<SrvRecord>, String> {
+        let resolver = Resolver::from_system_conf().map_err(|e| e.to_string())?;
+        let records = resolver.srv_lookup(query.as_str())
+                              .map_err(|e| e.to_string())?;
+        Ok(records.iter()
+                  .map(|srv| {
+                      SrvRecord { priority: srv.priority(),
+                                  weight:   srv.weight(),
+                                  target:   srv.target().to_utf8(),
+                                  port:     srv.port(), }
+                  })
+                  .collect())
+    });
+    lookup.join()
+          .unwrap_or_else(|_| Err("the lookup panicked".to_string()))
+          .map_err(|e| Error::SrvLookup(name.to_string(), e))
+}
+
+/// The endpoints of `records`, in the order they are to be tried.
+fn candidates(url: &Url, mut records: Vec<SrvRecord>) -> Result<Vec<Url>> {
+    records.sort_by_key(|r| (r.priority, Reverse(r.weight)));
+    records.iter().map(|r| endpoint_url(url, r)).collect()
+}
+
+fn endpoint_url(url: &Url, record: &SrvRecord) -> Result<Url> {
+    let target = record.target.trim_end_matches('.');
+    let host = if target.parse::<Ipv6Addr>().is_ok() {
+        format!("[{}]", target)
+    } else {
+        target.to_string()
+    };
+    let mut endpoint = Url::parse(&format!("https://{}:{}", host, record.port))?;
+    endpoint.set_path(url.path());
+    endpoint.set_query(url.query());
+    Ok(endpoint)
+}
+
+/// The first of `candidates` accepting connections. If none are, the first is used anyway so that
+/// requests fail with an error from a real endpoint.
+fn first_reachable(candidates: Vec<Url>) -> Url {
+    if candidates.len() > 1 {
+        for candidate in &candidates {
+            if is_reachable(candidate) {
+                return candidate.clone();
+            }
+            warn!("Builder endpoint {} is unreachable, trying the next one",
+                  candidate);
+        }
+    }
+    candidates.into_iter()
+              .next()
+              .expect("at least one candidate")
+}
+
+fn is_reachable(url: &Url) -> bool {
+    match url.socket_addrs(|| Some(HTTPS_PORT)) {
+        Ok(addrs) => {
+            addrs.iter()
+                 .any(|addr| TcpStream::connect_timeout(addr, PROBE_TIMEOUT).is_ok())
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn record(priority: u16, weight: u16, target: &str) -> SrvRecord {
+        SrvRecord { priority,
+                    weight,
+                    target: target.to_string(),
+                    port: 9636 }
+    }
+
+    #[test]
+    fn urls_other_than_srv_are_used_as_given() {
+        let url = Url::parse("https://[fd00::1]:9636/v1").unwrap();
+        assert_eq!(resolve(url.clone()).unwrap(), url);
+        assert_eq!(url.host_str(), Some("[fd00::1]"));
+    }
+
+    #[test]
+    fn srv_records_are_tried_by_priority_then_weight() {
+        let url = Url::parse("srv://builder.internal/v1").unwrap();
+        assert_eq!(srv_name(&url).unwrap(), "_https._tcp.builder.internal");
+        let records = vec![record(20, 0, "backup.builder.internal."),
+                           record(10, 1, "fd00::2"),
+                           record(10, 5, "primary.builder.internal.")];
+        let candidates = candidates(&url, records).unwrap()
+                                                  .into_iter()
+                                                  .map(String::from)
+                                                  .collect::<Vec<_>>();
+        assert_eq!(candidates,
+                   vec!["https://primary.builder.internal:9636/v1",
+                        "https://[fd00::2]:9636/v1",
+                        "https://backup.builder.internal:9636/v1"]);
+    }
+
+    #[test]
+    fn unreachable_endpoints_are_failed_over() {
+        let listening = TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap()
+                                                     .local_addr()
+                                                     .unwrap();
+        let up = Url::parse(&format!("https://{}/v1", listening.local_addr().unwrap())).unwrap();
+        let down = Url::parse(&format!("https://{}/v1", closed)).unwrap();
+        assert_eq!(first_reachable(vec![down.clone(), up.clone()]), up);
+        assert_eq!(first_reachable(vec![down.clone()]), down);
+    }
+}
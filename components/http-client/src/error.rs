@@ -11,6 +11,8 @@ pub enum Error {
     HabitatCore(hab_core::Error),
     ReqwestError(reqwest::Error),
     IO(io::Error),
+    /// The SRV records with the given name couldn't be looked up.
+    SrvLookup(String, String),
     Json(serde_json::Error),
     UrlParseError(url::ParseError),
     NativeTlsError(native_tls::Error),
@@ -22,6 +24,9 @@ impl fmt::Display for Error {
             Error::HabitatCore(ref e) => format!("{}", e),
             Error::ReqwestError(ref err) => format!("{}", err),
             Error::IO(ref e) => format!("{}", e),
+            Error::SrvLookup(ref name, ref e) => {
+                format!("Can't look up the SRV records of {}: {}", name, e)
+            }
             Error::Json(ref e) => format!("{}", e),
             Error::UrlParseError(ref e) => format!("{}", e),
             Error::NativeTlsError(ref e) => format!("{}", e),
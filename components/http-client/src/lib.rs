@@ -2,6 +2,7 @@
 extern crate log;
 
 mod api_client;
+mod endpoint;
 mod error;
 
 pub use crate::{api_client::{certificates,
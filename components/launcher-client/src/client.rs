@@ -1,12 +1,15 @@
 use crate::error::{Error,
                    Result};
 use habitat_common::types::UserInfo;
-use habitat_core::os::process::Pid;
+use habitat_core::os::process::{CpuLimit,
+                                Pid,
+                                Scheduling};
 use habitat_launcher_protocol::{self as protocol,
                                 Error as ProtocolError};
 use ipc_channel::ipc::{IpcOneShotServer,
                        IpcReceiver,
                        IpcSender};
+use prometheus::IntCounterVec;
 use std::{collections::BTreeMap,
           io,
           path::Path,
@@ -14,6 +17,14 @@ use std::{collections::BTreeMap,
           time::{Duration,
                  Instant}};
 
+lazy_static! {
+    static ref REJECTED_MESSAGES: IntCounterVec =
+        register_int_counter_vec!("hab_sup_launcher_rejected_messages_total",
+                                  "Total number of malformed or oversized messages received from \
+                                   the Launcher",
+                                  &["reason"]).unwrap();
+}
+
 type Env = BTreeMap<String, String>;
 type IpcServer = IpcOneShotServer<Vec<u8>>;
 
@@ -79,12 +90,13 @@ impl LauncherCli {
     fn read<T>(bytes: &[u8]) -> Result<T>
         where T: protocol::LauncherMessage
     {
-        let txn = protocol::NetTxn::from_bytes(bytes)?;
+        let txn = protocol::NetTxn::from_bytes(bytes).map_err(count_rejected_message)?;
         if txn.message_id() == "NetErr" {
-            let err = txn.decode::<protocol::NetErr>()?;
+            let err = txn.decode::<protocol::NetErr>()
+                         .map_err(count_rejected_message)?;
             return Err(Error::Protocol(ProtocolError::NetErr(err)));
         }
-        let msg = txn.decode::<T>()?;
+        let msg = txn.decode::<T>().map_err(count_rejected_message)?;
         Ok(msg)
     }
 
@@ -191,7 +203,8 @@ impl LauncherCli {
                             groupname,
                             gid, }: UserInfo,
                  password: Option<&str>,
-                 env: Env)
+                 env: Env,
+                 scheduling: &Scheduling)
                  -> Result<Pid> {
         // On Windows, we only expect user to be Some.
         //
@@ -206,7 +219,18 @@ impl LauncherCli {
                                     svc_group_id: gid,
                                     svc_password: password.map(str::to_string),
                                     env,
-                                    id: id.to_string() };
+                                    id: id.to_string(),
+                                    // Older Launchers ignore these and spawn the service with
+                                    // the default scheduling.
+                                    cpuset: scheduling.cpuset.as_ref().map(ToString::to_string),
+                                    nice: scheduling.nice.map(i32::from),
+                                    priority_class: scheduling.priority_class
+                                                              .map(|c| c.to_string()),
+                                    limits: Some(scheduling.limits).filter(|l| !l.is_empty())
+                                                                   .map(|l| l.to_string()),
+                                    cpu_limit: scheduling.sandbox.cpu.map(CpuLimit::millis),
+                                    memory_limit: scheduling.sandbox.memory.map(u64::from),
+                                    pids_limit: scheduling.sandbox.pids };
 
         Self::send(&self.tx, &msg)?;
         let reply = Self::recv::<protocol::SpawnOk>(&self.rx)?;
@@ -256,3 +280,13 @@ impl LauncherCli {
         Ok(reply.exit_code)
     }
 }
+
+/// Count a message from the Launcher which could not be decoded.
+fn count_rejected_message(err: ProtocolError) -> ProtocolError {
+    let reason = match err {
+        ProtocolError::MessageTooLarge(_) => "too_large",
+        _ => "malformed",
+    };
+    REJECTED_MESSAGES.with_label_values(&[reason]).inc();
+    err
+}
@@ -1,5 +1,9 @@
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
+#[macro_use]
+extern crate prometheus;
 
 mod client;
 pub mod error;
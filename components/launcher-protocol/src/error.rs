@@ -5,6 +5,7 @@ use std::{fmt,
 #[derive(Debug)]
 pub enum Error {
     Deserialize(prost::DecodeError),
+    MessageTooLarge(usize),
     NetErr(NetErr),
     ProtocolMismatch(&'static str),
     Serialize(prost::EncodeError),
@@ -16,6 +17,11 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let msg = match *self {
             Error::Deserialize(ref e) => format!("Unable to deserialize message: {}", e),
+            Error::MessageTooLarge(len) => {
+                format!("Message of {} bytes exceeds the maximum of {} bytes",
+                        len,
+                        crate::MAX_MESSAGE_SIZE)
+            }
             Error::NetErr(ref e) => format!("Net error: {}", e),
             Error::ProtocolMismatch(ref field) => {
                 format!("Received an unsupported or bad protocol message. Missing field: {}",
@@ -23,6 +23,9 @@ pub const OK_NO_RETRY_EXCODE: i32 = 84;
 /// Same as `OK_NO_RETRY_EXCODE` except the Supervisor ran to completion with an unsuccessful
 /// exit code. The Launcher should exit immediately with a non-zero exit code.
 pub const ERR_NO_RETRY_EXCODE: i32 = 86;
+/// The largest encoded message, in bytes, sent or accepted by either end of the Launcher's pipe.
+/// This comfortably fits a `Spawn` carrying a service's entire environment.
+pub const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
 
 #[derive(Debug)]
 pub struct NetTxn(Envelope);
@@ -37,11 +40,20 @@ impl NetTxn {
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(Error::MessageTooLarge(bytes.len()));
+        }
         let env = Envelope::from_bytes(bytes)?;
         Ok(NetTxn(env))
     }
 
-    pub fn to_bytes(&self) -> Result<Vec<u8>> { self.0.clone().to_bytes() }
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let bytes = self.0.clone().to_bytes()?;
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(Error::MessageTooLarge(bytes.len()));
+        }
+        Ok(bytes)
+    }
 
     pub fn decode<T>(&self) -> Result<T>
         where T: LauncherMessage
@@ -58,3 +70,60 @@ pub fn error<T>(err: T) -> NetErr
     NetErr { msg:  err.to_string(),
              code: err.into(), }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng,
+               Rng,
+               SeedableRng};
+
+    #[test]
+    fn oversized_messages_are_rejected() {
+        let err = NetTxn::from_bytes(&vec![0; MAX_MESSAGE_SIZE + 1]).unwrap_err();
+        assert!(matches!(err, Error::MessageTooLarge(len) if len == MAX_MESSAGE_SIZE + 1));
+
+        let msg = NetErr { code: ErrCode::Unknown,
+                           msg:  "x".repeat(MAX_MESSAGE_SIZE), };
+        let err = NetTxn::build(&msg).and_then(|txn| txn.to_bytes())
+                                     .unwrap_err();
+        assert!(matches!(err, Error::MessageTooLarge(_)));
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let msg = NetErr { code: ErrCode::NoPid,
+                           msg:  String::from("no pid"), };
+        let bytes = NetTxn::build(&msg).unwrap().to_bytes().unwrap();
+        let txn = NetTxn::from_bytes(&bytes).unwrap();
+        assert_eq!(txn.message_id(), NetErr::MESSAGE_ID);
+        assert_eq!(txn.decode::<NetErr>().unwrap(), msg);
+    }
+
+    /// Random bytes, and random corruptions of a valid message, must be rejected or decoded
+    /// without panicking.
+    #[test]
+    fn fuzz_decoding_never_panics() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let valid = NetTxn::build(&Spawn { id: String::from("core/redis"),
+                                           binary: String::from("/bin/redis"),
+                                           ..Default::default() }).unwrap()
+                                                                  .to_bytes()
+                                                                  .unwrap();
+        for _ in 0..10_000 {
+            let bytes: Vec<u8> = if rng.gen() {
+                (0..rng.gen_range(0, 128)).map(|_| rng.gen()).collect()
+            } else {
+                let mut bytes = valid.clone();
+                let i = rng.gen_range(0, bytes.len());
+                bytes[i] = rng.gen();
+                bytes.truncate(rng.gen_range(0, bytes.len() + 1));
+                bytes
+            };
+            if let Ok(txn) = NetTxn::from_bytes(&bytes) {
+                let _ = txn.decode::<Spawn>();
+                let _ = txn.decode::<NetErr>();
+            }
+        }
+    }
+}
@@ -126,14 +126,21 @@ impl From<Restart> for generated::Restart {
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Spawn {
-    pub id:           String,
-    pub binary:       String,
-    pub svc_user:     Option<String>,
-    pub svc_group:    Option<String>,
-    pub svc_password: Option<String>,
-    pub env:          BTreeMap<String, String>,
-    pub svc_user_id:  Option<u32>,
-    pub svc_group_id: Option<u32>,
+    pub id:             String,
+    pub binary:         String,
+    pub svc_user:       Option<String>,
+    pub svc_group:      Option<String>,
+    pub svc_password:   Option<String>,
+    pub env:            BTreeMap<String, String>,
+    pub svc_user_id:    Option<u32>,
+    pub svc_group_id:   Option<u32>,
+    pub cpuset:         Option<String>,
+    pub nice:           Option<i32>,
+    pub priority_class: Option<String>,
+    pub limits:         Option<String>,
+    pub cpu_limit:      Option<u32>,
+    pub memory_limit:   Option<u64>,
+    pub pids_limit:     Option<u64>,
 }
 
 impl LauncherMessage for Spawn {
@@ -142,27 +149,41 @@ impl LauncherMessage for Spawn {
     const MESSAGE_ID: &'static str = "Spawn";
 
     fn from_proto(proto: generated::Spawn) -> Result<Self> {
-        Ok(Spawn { id:           proto.id.ok_or(Error::ProtocolMismatch("id"))?,
-                   binary:       proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
-                   svc_user:     proto.svc_user,
-                   svc_group:    proto.svc_group,
-                   svc_password: proto.svc_password,
-                   env:          BTreeMap::from_iter(proto.env.into_iter()),
-                   svc_user_id:  proto.svc_user_id,
-                   svc_group_id: proto.svc_group_id, })
+        Ok(Spawn { id:             proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+                   binary:         proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
+                   svc_user:       proto.svc_user,
+                   svc_group:      proto.svc_group,
+                   svc_password:   proto.svc_password,
+                   env:            BTreeMap::from_iter(proto.env.into_iter()),
+                   svc_user_id:    proto.svc_user_id,
+                   svc_group_id:   proto.svc_group_id,
+                   cpuset:         proto.cpuset,
+                   nice:           proto.nice,
+                   priority_class: proto.priority_class,
+                   limits:         proto.limits,
+                   cpu_limit:      proto.cpu_limit,
+                   memory_limit:   proto.memory_limit,
+                   pids_limit:     proto.pids_limit, })
     }
 }
 
 impl From<Spawn> for generated::Spawn {
     fn from(value: Spawn) -> Self {
-        generated::Spawn { id:           Some(value.id),
-                           binary:       Some(value.binary),
-                           svc_user:     value.svc_user,
-                           svc_group:    value.svc_group,
-                           svc_password: value.svc_password,
-                           env:          HashMap::from_iter(value.env.into_iter()),
-                           svc_user_id:  value.svc_user_id,
-                           svc_group_id: value.svc_group_id, }
+        generated::Spawn { id:             Some(value.id),
+                           binary:         Some(value.binary),
+                           svc_user:       value.svc_user,
+                           svc_group:      value.svc_group,
+                           svc_password:   value.svc_password,
+                           env:            HashMap::from_iter(value.env.into_iter()),
+                           svc_user_id:    value.svc_user_id,
+                           svc_group_id:   value.svc_group_id,
+                           cpuset:         value.cpuset,
+                           nice:           value.nice,
+                           priority_class: value.priority_class,
+                           limits:         value.limits,
+                           cpu_limit:      value.cpu_limit,
+                           memory_limit:   value.memory_limit,
+                           pids_limit:     value.pids_limit, }
     }
 }
 
@@ -9,6 +9,7 @@ use std::{error,
 #[derive(Debug)]
 pub enum Error {
     AcceptConn,
+    Cgroup(String, io::Error),
     Connect(io::Error),
     ExecWait(io::Error),
     GroupNotFound(String),
@@ -31,6 +32,9 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let msg = match *self {
             Error::AcceptConn => "Unable to accept connection from Supervisor".to_string(),
+            Error::Cgroup(ref id, ref e) => {
+                format!("Unable to confine {} to a cgroup of its own, {}", id, e)
+            }
             Error::Connect(ref e) => {
                 format!("Unable to connect to Supervisor's comm channel, {}", e)
             }
@@ -1,4 +1,5 @@
 use habitat_common::output::{self,
+                             LogSink,
                              OutputFormat,
                              OutputVerbosity};
 use habitat_core::os::signals;
@@ -55,4 +56,41 @@ fn set_global_logging_options(args: &[String]) {
     if args.contains(&String::from("-v")) {
         output::set_verbosity(OutputVerbosity::Verbose);
     }
+    // Unlike the flags above, `--log-sink` takes a value.
+    if let Some(log_sink) = log_sink_arg(args) {
+        if let Err(err) = output::set_log_sink(log_sink) {
+            error!("Unable to write output to the {} log sink, falling back to stdout: {}",
+                   log_sink, err);
+        }
+    }
+}
+
+fn log_sink_arg(args: &[String]) -> Option<LogSink> {
+    let value = args.iter()
+                    .position(|a| a == "--log-sink")
+                    .and_then(|i| args.get(i + 1))
+                    .map(String::as_str)
+                    .or_else(|| {
+                        args.iter()
+                            .find(|a| a.starts_with("--log-sink="))
+                            .map(|a| &a["--log-sink=".len()..])
+                    })?;
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> { args.iter().map(|a| a.to_string()).collect() }
+
+    #[test]
+    fn log_sink_arg_accepts_both_forms() {
+        assert_eq!(log_sink_arg(&args(&["run", "--log-sink", "stdout"])),
+                   Some(LogSink::Stdout));
+        assert_eq!(log_sink_arg(&args(&["run", "--log-sink=stdout"])),
+                   Some(LogSink::Stdout));
+        assert_eq!(log_sink_arg(&args(&["run", "-v"])), None);
+        assert_eq!(log_sink_arg(&args(&["run", "--log-sink", "bogus"])), None);
+    }
 }
@@ -1,16 +1,23 @@
-use crate::protocol;
+use crate::{error::Result,
+            protocol};
 #[cfg(windows)]
 use core::os::process::windows_child::{ChildStderr,
                                        ChildStdout,
                                        ExitStatus};
-use core::util::BufReadLossy;
+use core::{os::process::{CpuLimit,
+                         MemoryLimit,
+                         NiceLevel,
+                         SandboxLimits,
+                         Scheduling},
+           util::BufReadLossy};
 use habitat_common::output::{self,
                              StructuredOutput};
 #[cfg(unix)]
 use std::process::{ChildStderr,
                    ChildStdout,
                    ExitStatus};
-use std::{fmt,
+use std::{convert::TryFrom,
+          fmt,
           io::{self,
                BufReader,
                Read},
@@ -68,6 +75,25 @@ impl fmt::Debug for Service {
     }
 }
 
+/// The scheduling the Supervisor asked for the service in `spawn` to run with.
+pub fn spawn_scheduling(spawn: &protocol::Spawn) -> Result<Scheduling> {
+    Ok(Scheduling { cpuset:         spawn.cpuset.as_ref().map(|c| c.parse()).transpose()?,
+                    nice:           spawn.nice.map(NiceLevel::try_from).transpose()?,
+                    priority_class: spawn.priority_class
+                                         .as_ref()
+                                         .map(|c| c.parse())
+                                         .transpose()?,
+                    limits:         spawn.limits
+                                         .as_ref()
+                                         .map(|l| l.parse())
+                                         .transpose()?
+                                         .unwrap_or_default(),
+                    sandbox:        SandboxLimits { cpu:    spawn.cpu_limit.map(CpuLimit::from),
+                                                    memory: spawn.memory_limit
+                                                                 .map(MemoryLimit::from),
+                                                    pids:   spawn.pids_limit, }, })
+}
+
 /// Consume output from a child process until EOF, then finish
 fn pipe_stdout<T>(out: T, id: &str)
     where T: Read
@@ -1,3 +1,7 @@
+#[cfg(target_os = "linux")]
+#[path = "sys/unix/cgroup.rs"]
+pub mod cgroup;
+
 #[cfg(unix)]
 #[path = "sys/unix/service.rs"]
 pub mod service;
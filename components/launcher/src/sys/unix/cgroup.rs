@@ -0,0 +1,96 @@
+//! Confines services to cgroups (v2) of their own, so that the `SandboxLimits` of a service hold
+//! for its process and every process it starts together.
+//!
+//! Each service gets a cgroup named after it under `/sys/fs/cgroup/hab`. The cgroup is left in
+//! place when the service stops and reused when it is started again.
+
+use core::os::process::SandboxLimits;
+use std::{fs::{self,
+               File,
+               OpenOptions},
+          io,
+          os::unix::io::{AsRawFd,
+                         RawFd},
+          path::Path};
+
+/// Where the unified cgroup hierarchy is mounted
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// The cgroup under which the Launcher creates the cgroups of services
+const HAB_CGROUP: &str = "hab";
+
+/// The period, in microseconds, over which CPU time is limited
+const CPU_PERIOD: u64 = 100_000;
+
+/// The controllers a service's cgroup needs to enforce its limits
+const CONTROLLERS: &str = "+cpu +memory +pids";
+
+/// The cgroup of a service, which its process joins when it is spawned.
+pub struct Cgroup {
+    procs: File,
+}
+
+impl Cgroup {
+    /// Creates, or reuses, the cgroup of the service `id` and sets its limits to `limits`.
+    pub fn create(id: &str, limits: &SandboxLimits) -> io::Result<Self> {
+        let root = Path::new(CGROUP_ROOT);
+        let parent = root.join(HAB_CGROUP);
+        fs::create_dir_all(&parent)?;
+        // Controllers must be enabled at every level above the cgroup they are used in.
+        fs::write(root.join("cgroup.subtree_control"), CONTROLLERS)?;
+        fs::write(parent.join("cgroup.subtree_control"), CONTROLLERS)?;
+
+        let path = parent.join(id);
+        if !path.is_dir() {
+            fs::create_dir(&path)?;
+        }
+        for (file, value) in settings(limits) {
+            fs::write(path.join(file), value)?;
+        }
+        let procs = OpenOptions::new().write(true)
+                                      .open(path.join("cgroup.procs"))?;
+        debug!("Confining {} to cgroup {}", id, path.display());
+        Ok(Cgroup { procs })
+    }
+
+    /// The `cgroup.procs` file of the cgroup, which a process joins the cgroup by writing to.
+    pub fn procs(&self) -> RawFd { self.procs.as_raw_fd() }
+}
+
+/// The interface files of a cgroup, and what to write to them, to enforce `limits`. Limits which
+/// are not set are lifted, so that a service reloaded without them isn't held to old ones.
+fn settings(limits: &SandboxLimits) -> Vec<(&'static str, String)> {
+    let cpu = match limits.cpu {
+        Some(cpu) => {
+            format!("{} {}",
+                    u64::from(cpu.millis()) * CPU_PERIOD / 1000,
+                    CPU_PERIOD)
+        }
+        None => format!("max {}", CPU_PERIOD),
+    };
+    let memory = limits.memory
+                       .map(|m| m.bytes().to_string())
+                       .unwrap_or_else(|| "max".to_string());
+    let pids = limits.pids
+                     .map(|p| p.to_string())
+                     .unwrap_or_else(|| "max".to_string());
+    vec![("cpu.max", cpu), ("memory.max", memory), ("pids.max", pids)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::os::process::{CpuLimit,
+                            MemoryLimit};
+
+    #[test]
+    fn settings_enforce_given_limits_and_lift_others() {
+        let limits = SandboxLimits { cpu:    Some("1.5".parse::<CpuLimit>().unwrap()),
+                                     memory: Some(MemoryLimit::from(1024)),
+                                     pids:   None, };
+        assert_eq!(settings(&limits),
+                   vec![("cpu.max", "150000 100000".to_string()),
+                        ("memory.max", "1024".to_string()),
+                        ("pids.max", "max".to_string())]);
+    }
+}
@@ -2,7 +2,12 @@ use crate::{error::{Error,
                     Result},
             protocol::{self,
                        ShutdownMethod},
-            service::Service};
+            service::{spawn_scheduling,
+                      Service}};
+#[cfg(target_os = "linux")]
+use crate::sys::cgroup::Cgroup;
+#[cfg(target_os = "linux")]
+use habitat_core::os::process::SandboxLimits;
 use habitat_core::os::{self,
                        process::{exec,
                                  signal,
@@ -90,7 +95,27 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
     };
     let gid = Gid::from_raw(group_id);
 
-    let mut cmd = exec::unix::hook_command(&msg.binary, &msg.env, Some((uid, gid)));
+    let scheduling = spawn_scheduling(&msg)?;
+    // The cgroup must stay open until the process has been spawned, as the process joins it
+    // through its `cgroup.procs` file.
+    #[cfg(target_os = "linux")]
+    let cgroup = confine(&msg.id, &scheduling.sandbox)?;
+    #[cfg(target_os = "linux")]
+    let cgroup_procs = cgroup.as_ref().map(Cgroup::procs);
+    #[cfg(not(target_os = "linux"))]
+    let cgroup_procs = {
+        if !scheduling.sandbox.is_empty() {
+            warn!("Ignoring the CPU, memory and process limits of {}; they can only be \
+                   enforced on Linux",
+                  msg.id);
+        }
+        None
+    };
+    let mut cmd = exec::unix::service_command(&msg.binary,
+                                              &msg.env,
+                                              Some((uid, gid)),
+                                              &scheduling,
+                                              cgroup_procs);
 
     let mut child = cmd.spawn().map_err(Error::Spawn)?;
     let stdout = child.stdout.take();
@@ -99,3 +124,14 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
     debug!(target: "pidfile_tracing", "Launcher spawned {} with PID = {}", msg.binary, process.id());
     Ok(Service::new(msg, process, stdout, stderr))
 }
+
+/// The cgroup the service `id` is confined to, if it has any `limits`.
+#[cfg(target_os = "linux")]
+fn confine(id: &str, limits: &SandboxLimits) -> Result<Option<Cgroup>> {
+    if limits.is_empty() {
+        return Ok(None);
+    }
+    Cgroup::create(id, limits).map(Some)
+                              .map_err(|e| Error::Cgroup(id.to_string(), e))
+}
+
@@ -2,36 +2,62 @@ use crate::{error::{Error,
                     Result},
             protocol::{self,
                        ShutdownMethod},
-            service::Service};
+            service::{spawn_scheduling,
+                      Service}};
 use core::{os::{process::{handle_from_pid,
+                          interpreter::Interpreter,
                           windows_child::{Child,
                                           ExitStatus,
-                                          Handle}},
+                                          Handle},
+                          CpuLimit,
+                          PriorityClass,
+                          ResourceLimits,
+                          SandboxLimits},
                 users::get_current_username},
            util};
 use std::{collections::HashMap,
           env,
+          ffi::OsStr,
           io,
           iter::FromIterator,
           mem,
+          path::Path,
+          ptr,
           time::{Duration,
                  Instant}};
-use winapi::{shared::{minwindef::{DWORD,
+use winapi::{shared::{basetsd::SIZE_T,
+                      minwindef::{DWORD,
                                   LPDWORD,
                                   MAX_PATH},
                       winerror::{ERROR_FILE_NOT_FOUND,
                                  WAIT_TIMEOUT}},
              um::{handleapi::{self,
                               INVALID_HANDLE_VALUE},
+                  jobapi2,
                   processthreadsapi,
                   synchapi,
+                  sysinfoapi::{self,
+                               SYSTEM_INFO},
                   tlhelp32::{self,
                              LPPROCESSENTRY32W,
                              PROCESSENTRY32W,
                              TH32CS_SNAPPROCESS},
-                  winbase::{INFINITE,
+                  winbase::{ABOVE_NORMAL_PRIORITY_CLASS,
+                            BELOW_NORMAL_PRIORITY_CLASS,
+                            HIGH_PRIORITY_CLASS,
+                            IDLE_PRIORITY_CLASS,
+                            INFINITE,
+                            NORMAL_PRIORITY_CLASS,
                             WAIT_OBJECT_0},
-                  wincon}};
+                  wincon,
+                  winnt::{JobObjectCpuRateControlInformation,
+                          JobObjectExtendedLimitInformation,
+                          JOBOBJECT_CPU_RATE_CONTROL_INFORMATION,
+                          JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                          JOB_OBJECT_CPU_RATE_CONTROL_ENABLE,
+                          JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+                          JOB_OBJECT_LIMIT_ACTIVE_PROCESS,
+                          JOB_OBJECT_LIMIT_JOB_MEMORY}}};
 
 const PROCESS_ACTIVE: u32 = 259;
 type ProcessTable = HashMap<DWORD, Vec<DWORD>>;
@@ -118,6 +144,22 @@ impl Process {
 }
 
 pub fn run(msg: protocol::Spawn) -> Result<Service> {
+    // A run hook declaring a non-PowerShell interpreter is run by it, found on the service's
+    // PATH. Anything else is evaluated by PowerShell.
+    if let Ok(Some(interpreter)) = Interpreter::from_hook(Path::new(&msg.binary)) {
+        if !interpreter.is_powershell() {
+            let paths = msg.env.get("PATH").map(String::as_str).unwrap_or_default();
+            let program = interpreter.resolve(OsStr::new(paths)).ok_or_else(|| {
+                              let e = format!("Unable to find the interpreter '{}' declared by {}",
+                                              interpreter.program(),
+                                              msg.binary);
+                              Error::Spawn(io::Error::new(io::ErrorKind::NotFound, e))
+                          })?;
+            let mut args: Vec<&str> = interpreter.arg().into_iter().collect();
+            args.push(&msg.binary);
+            return spawn(&program.to_string_lossy(), &args, msg.clone());
+        }
+    }
     // Supervisors prior to version 0.53.0 pulled in beta versions of
     // powershell. The official 6.0.0 version of powershell changed
     // the name of the powershell binary to pwsh.exe. Here we will
@@ -137,8 +179,12 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
 }
 
 fn spawn_pwsh(ps_binary_name: &str, msg: protocol::Spawn) -> Result<Service> {
-    debug!("launcher is spawning {}", msg.binary);
     let ps_cmd = format!("iex $(gc {} | out-string)", &msg.binary);
+    spawn(ps_binary_name, &util::pwsh_args(ps_cmd.as_str()), msg)
+}
+
+fn spawn(program: &str, args: &[&str], msg: protocol::Spawn) -> Result<Service> {
+    debug!("launcher is spawning {}", msg.binary);
     let password = msg.svc_password.clone();
 
     let user = match msg.svc_user.as_ref() {
@@ -169,14 +215,16 @@ fn spawn_pwsh(ps_binary_name: &str, msg: protocol::Spawn) -> Result<Service> {
     };
 
     let new_env = HashMap::from_iter(msg.env.clone().into_iter());
+    let scheduling = spawn_scheduling(&msg)?;
 
-    match Child::spawn(ps_binary_name,
-                       &util::pwsh_args(ps_cmd.as_str()),
-                       &new_env,
-                       &user,
-                       password)
-    {
+    match Child::spawn(program, args, &new_env, &user, password) {
         Ok(child) => {
+            if let Some(priority_class) = scheduling.priority_class {
+                set_priority_class(&child.handle, priority_class);
+            }
+            if !scheduling.limits.is_empty() || !scheduling.sandbox.is_empty() {
+                set_resource_limits(&child.handle, &scheduling.limits, &scheduling.sandbox);
+            }
             let process = Process::new(child.handle);
             Ok(Service::new(msg, process, child.stdout, child.stderr))
         }
@@ -184,6 +232,105 @@ fn spawn_pwsh(ps_binary_name: &str, msg: protocol::Spawn) -> Result<Service> {
     }
 }
 
+/// The priority class is applied once the process has been created, so a failure to apply it is
+/// only logged; the service is left running at the normal priority rather than orphaned.
+fn set_priority_class(handle: &Handle, priority_class: PriorityClass) {
+    let flag = match priority_class {
+        PriorityClass::Idle => IDLE_PRIORITY_CLASS,
+        PriorityClass::BelowNormal => BELOW_NORMAL_PRIORITY_CLASS,
+        PriorityClass::Normal => NORMAL_PRIORITY_CLASS,
+        PriorityClass::AboveNormal => ABOVE_NORMAL_PRIORITY_CLASS,
+        PriorityClass::High => HIGH_PRIORITY_CLASS,
+    };
+    if let Err(e) = cvt(unsafe { processthreadsapi::SetPriorityClass(handle.raw(), flag) }) {
+        error!("Failed to set the priority class of pid {} to {}: {}",
+               unsafe { processthreadsapi::GetProcessId(handle.raw()) },
+               priority_class,
+               e);
+    }
+}
+
+/// Windows has no equivalent of the `nofile` and `core` limits, but the number of processes, the
+/// memory and the CPU time of the service can be limited by putting it in a job object of its
+/// own. Like the priority class, this is applied once the process has been created, so a failure
+/// is only logged.
+fn set_resource_limits(handle: &Handle, limits: &ResourceLimits, sandbox: &SandboxLimits) {
+    let pid = unsafe { processthreadsapi::GetProcessId(handle.raw()) };
+    if limits.nofile.is_some() || limits.core.is_some() {
+        debug!("Ignoring the nofile and core limits of pid {}, which Windows does not support",
+               pid);
+    }
+    let nproc = match limits.nproc {
+        Some(nproc) if nproc != ResourceLimits::UNLIMITED => Some(nproc),
+        _ => None,
+    };
+    // Both the `nproc` limit and the pids limit bound the processes in the job; the lower wins.
+    let processes = match (nproc, sandbox.pids) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    if processes.is_none() && sandbox.cpu.is_none() && sandbox.memory.is_none() {
+        return;
+    }
+    let result = unsafe {
+        let job = jobapi2::CreateJobObjectW(ptr::null_mut(), ptr::null());
+        if job.is_null() {
+            Err(io::Error::last_os_error())
+        } else {
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = mem::zeroed();
+            if let Some(processes) = processes {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+                info.BasicLimitInformation.ActiveProcessLimit =
+                    processes.min(u64::from(DWORD::MAX)) as DWORD;
+            }
+            if let Some(memory) = sandbox.memory {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit = memory.bytes().min(SIZE_T::MAX as u64) as SIZE_T;
+            }
+            let mut result =
+                cvt(jobapi2::SetInformationJobObject(job,
+                                                     JobObjectExtendedLimitInformation,
+                                                     &mut info as *mut _ as *mut _,
+                                                     mem::size_of_val(&info) as DWORD));
+            if let Some(cpu) = sandbox.cpu {
+                let mut rate: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = mem::zeroed();
+                rate.ControlFlags =
+                    JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+                *rate.u.CpuRate_mut() = cpu_rate(cpu, processor_count());
+                result = result.and_then(|_| {
+                    cvt(jobapi2::SetInformationJobObject(job,
+                                                         JobObjectCpuRateControlInformation,
+                                                         &mut rate as *mut _ as *mut _,
+                                                         mem::size_of_val(&rate) as DWORD))
+                });
+            }
+            let result =
+                result.and_then(|_| cvt(jobapi2::AssignProcessToJobObject(job, handle.raw())));
+            // The job lives on for as long as the process is in it.
+            handleapi::CloseHandle(job);
+            result
+        }
+    };
+    if let Err(e) = result {
+        error!("Failed to confine pid {} to a job object: {}", pid, e);
+    }
+}
+
+/// The CPU rate of a job is the share of the whole machine its processes may use, in hundredths
+/// of a percent, whereas `cpu` is a number of CPUs.
+fn cpu_rate(cpu: CpuLimit, processors: u32) -> DWORD {
+    let rate = u64::from(cpu.millis()) * 10 / u64::from(processors.max(1));
+    rate.max(1).min(10_000) as DWORD
+}
+
+fn processor_count() -> u32 {
+    unsafe {
+        let mut info: SYSTEM_INFO = mem::zeroed();
+        sysinfoapi::GetSystemInfo(&mut info);
+        info.dwNumberOfProcessors
+    }
+}
+
 fn build_proc_table() -> ProcessTable {
     let processes_snap_handle =
         unsafe { tlhelp32::CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
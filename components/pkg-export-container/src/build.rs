@@ -14,10 +14,11 @@ use clap::{self,
 #[cfg(unix)]
 use failure::SyncFailure;
 use hab::license;
-use habitat_common::{command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+use habitat_common::{command::package::{install::{InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        license::LicensePolicy},
                      ui::{Status,
                           UIWriter,
                           UI},
@@ -377,7 +378,8 @@ impl BuildSpec {
                                                      // TODO (CM): pass through and enable
                                                      // ignore-local mode
                                                      &LocalPackageUsage::default(),
-                                                     InstallHookMode::Ignore).await?;
+                                                     InstallHookMode::Ignore,
+                                                     &LicensePolicy::default()).await?;
 
         // TODO (CM): Ideally, the typing of PackageInstall would be
         // such that we'd automatically get a
@@ -1,10 +1,11 @@
 use super::{BUSYBOX_IDENT,
             VERSION};
 use crate::{common::{self,
-                     command::package::install::{InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+                     command::package::{install::{InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        license::LicensePolicy},
                      ui::{Status,
                           UIWriter,
                           UI},
@@ -211,7 +212,8 @@ impl<'a> BuildSpec<'a> {
                                                      // TODO (CM): pass through and enable
                                                      // ignore-local mode
                                                      &LocalPackageUsage::default(),
-                                                     InstallHookMode::Ignore).await?;
+                                                     InstallHookMode::Ignore,
+                                                     &LicensePolicy::default()).await?;
         Ok(package_install.into())
     }
 
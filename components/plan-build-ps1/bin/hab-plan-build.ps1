@@ -1382,6 +1382,10 @@ function Write-Metadata {
             Out-File "$pkg_prefix\INCLUDE_DIRS" -Encoding ascii
     }
 
+    if ($pkg_license.Length -gt 0) {
+        $pkg_license | Out-File "$pkg_prefix\LICENSE" -Encoding ascii
+    }
+
     if ($pkg_expose.Length -gt 0) {
         "$($pkg_expose -join ' ')" |
             Out-File "$pkg_prefix\EXPOSES" -Encoding ascii
@@ -1437,6 +1441,18 @@ function Write-Metadata {
         "$pkg_shutdown_timeout_sec" |
             Out-File "$pkg_prefix\SHUTDOWN_TIMEOUT" -Encoding ascii
     }
+    if ($pkg_hook_timeouts) {
+        $pkg_hook_timeouts |
+            Out-File "$pkg_prefix\HOOK_TIMEOUTS" -Encoding ascii
+    }
+    if ($pkg_restart_keys) {
+        $pkg_restart_keys |
+            Out-File "$pkg_prefix\RESTART_KEYS" -Encoding ascii
+    }
+    if ($pkg_scheduled_tasks) {
+        $pkg_scheduled_tasks |
+            Out-File "$pkg_prefix\SCHEDULED_TASKS" -Encoding ascii
+    }
 
     # Generate the blake2b hashes of all the files in the package. This
     # is not in the resulting MANIFEST because MANIFEST is included!
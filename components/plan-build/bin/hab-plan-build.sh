@@ -193,6 +193,14 @@
 # pkg_exposes=(port)
 # ```
 #
+# ### pkg_proxy_port
+# Optional. A `pkg_exports` key containing the default value of the port serving the service's
+# administrative HTTP endpoints. The Supervisor's HTTP gateway can proxy authenticated requests to
+# this port under `/services/<name>/<group>/proxy/`.
+# ```
+# pkg_proxy_port=admin-port
+# ```
+#
 # ### pkg_binds
 # An associative array representing services which you depend on and the configuration keys that
 # you expect the service to export (by their `pkg_exports`). These binds *must* be set for the
@@ -1952,11 +1960,13 @@ do_default_install() {
 # * `$pkg_prefix/DEPS` - Any dependencies we need to use the package at runtime
 # * `$pkg_prefix/EXPORTS` - A list of exported configuration keys and their public name
 # * `$pkg_prefix/EXPOSES` - An array of `pkg_exports` for which ports that this package exposes
+# * `$pkg_prefix/PROXY_PORT` - The port the Supervisor's HTTP gateway proxies requests to
 # * `$pkg_prefix/BINDS` - A list of services you connect to and keys that you expect to be exported
 # * `$pkg_prefix/BINDS_OPTIONAL` - Same as `BINDS` but not required for the service to start
 # * `$pkg_prefix/FILES` - blake2b checksums of all files in the package
 # * `$pkg_prefix/LDFLAGS` - Any LDFLAGS for things that link against us
 # * `$pkg_prefix/LD_RUN_PATH` - The LD_RUN_PATH for things that link against us
+# * `$pkg_prefix/LICENSE` - The licenses the software is released under, one per line
 # * `$pkg_prefix/PATH` - Any PATH entries for things that link against us
 _build_metadata() {
   build_line "Building package metadata"
@@ -1975,6 +1985,7 @@ _build_metadata() {
   _render_metadata_BINDS
   _render_metadata_BINDS_OPTIONAL
   _render_metadata_EXPOSES
+  _render_metadata_PROXY_PORT
   _render_metadata_INTERPRETERS
   _render_metadata_BUILD_DEPS
   _render_metadata_BUILD_TDEPS
@@ -1982,11 +1993,15 @@ _build_metadata() {
   _render_metadata_TDEPS
   _render_metadata_TARGET
   _render_metadata_IDENT
+  _render_metadata_LICENSE
   _render_metadata_RUNTIME_PATH
   _render_metadata_RUNTIME_ENVIRONMENT
   _render_metadata_RUNTIME_ENVIRONMENT_PROVENANCE
   _render_metadata_SHUTDOWN_SIGNAL
   _render_metadata_SHUTDOWN_TIMEOUT
+  _render_metadata_HOOK_TIMEOUTS
+  _render_metadata_RESTART_KEYS
+  _render_metadata_SCHEDULED_TASKS
 
   # Only generate `SVC_USER` & `SVC_GROUP` files if this package is a service.
   # We determine this by checking if there is a `hooks/run` script and/or
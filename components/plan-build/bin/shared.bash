@@ -120,6 +120,16 @@ _render_metadata_INTERPRETERS() {
     fi
 }
 
+_render_metadata_LICENSE() {
+  # shellcheck disable=2154
+  if [[ ${#pkg_license[@]} -gt 0 ]]; then
+    debug "Rendering LICENSE metadata file"
+    printf "%s\n" "${pkg_license[@]}" > "$pkg_prefix"/LICENSE
+  else
+    debug "Would have rendered LICENSE, but there was no data for it"
+  fi
+}
+
 _render_metadata_LDFLAGS(){
     local metadata_file_name="LDFLAGS"
 
@@ -174,6 +184,25 @@ _render_metadata_PATH() {
   fi
 }
 
+# The PROXY_PORT metadata file contains the default value of the
+# `pkg_exports` key named by `pkg_proxy_port`
+#
+_render_metadata_PROXY_PORT() {
+  if [[ -n "${pkg_proxy_port:-}" ]]; then
+    if [[ ! ${pkg_exports[$pkg_proxy_port]+abc} ]]; then
+      exit_with "Bad value in pkg_proxy_port; No pkg_export found matching key: ${pkg_proxy_port}"
+    fi
+    local key port
+    key=${pkg_exports[$pkg_proxy_port]}
+    port=$($_rq_cmd -t < "$PLAN_CONTEXT"/default.toml "at \"${key}\"" | tr -d '"')
+    if ! _port_is_valid "$port"; then
+      exit_with "Bad pkg_export in pkg_proxy_port; Value of key \"${key}\" does not contain a valid TCP port number: ${port}"
+    fi
+    debug "Rendering PROXY_PORT metadata file"
+    echo "$port" > "$pkg_prefix"/PROXY_PORT
+  fi
+}
+
 _render_metadata_PKG_CONFIG_PATH() {
     local pconfig_path_part=()
     local metadata_file_name="PKG_CONFIG_PATH"
@@ -252,6 +281,30 @@ _render_metadata_SHUTDOWN_SIGNAL() {
   fi
 }
 
+_render_metadata_HOOK_TIMEOUTS() {
+  if [[ -n "${pkg_hook_timeouts[*]:-}" ]]; then
+    debug "Rendering HOOK_TIMEOUTS metadata file"
+    # shellcheck disable=2154
+    printf '%s\n' "${pkg_hook_timeouts[@]}" > "$pkg_prefix"/HOOK_TIMEOUTS
+  fi
+}
+
+_render_metadata_RESTART_KEYS() {
+  if [[ -n "${pkg_restart_keys[*]:-}" ]]; then
+    debug "Rendering RESTART_KEYS metadata file"
+    # shellcheck disable=2154
+    printf '%s\n' "${pkg_restart_keys[@]}" > "$pkg_prefix"/RESTART_KEYS
+  fi
+}
+
+_render_metadata_SCHEDULED_TASKS() {
+  if [[ -n "${pkg_scheduled_tasks[*]:-}" ]]; then
+    debug "Rendering SCHEDULED_TASKS metadata file"
+    # shellcheck disable=2154
+    printf '%s\n' "${pkg_scheduled_tasks[@]}" > "$pkg_prefix"/SCHEDULED_TASKS
+  fi
+}
+
 _render_metadata_SHUTDOWN_TIMEOUT() {
   if [[ -n "${pkg_shutdown_timeout_sec:-}" ]]; then
     debug "Rendering SHUTDOWN_TIMEOUT metadata file"
@@ -76,6 +76,20 @@ COMMON OPTIONS:
     -t <STUDIO_TYPE>      Sets a Studio type when creating (default: default)
                           Valid types: [default baseimage busybox stage1]
 
+OPTIONS HANDLED BY \`hab studio':
+    --inject-key <ORIGIN>     Injects only the keys for these origins and scrubs
+                              the secret keys when the Studio exits
+    --inject-secret <NAME>    Passes only these HAB_STUDIO_SECRET_<NAME> variables
+                              into the Studio
+    --cache-artifacts <MODE[:ORIGIN,...]>
+                              Shares the artifact cache read-write (rw), read-only
+                              (ro) or not at all (off), optionally only the
+                              artifacts of these origins (default: rw)
+    --cache-keys <MODE[:ORIGIN,...]>
+                              Shares the key cache read-write (rw), read-only (ro)
+                              or not at all (off), optionally only the keys of
+                              these origins (default: rw)
+
 SUBCOMMANDS:
     build     Build using a Studio
     enter     Interactively enter a Studio
@@ -94,6 +108,13 @@ ENVIRONMENT VARIABLES:
     HAB_ORIGIN             Propagates this variable into any studios
     HAB_ORIGIN_KEYS        Installs secret keys (\`-k' option overrides)
     HAB_STUDIOS_HOME       Sets a home path for all Studios (default: /hab/studios)
+    HAB_STUDIO_ARTIFACT_ORIGINS
+                           Copies in only the artifacts of these origins rather than
+                           mounting the artifact cache path
+    HAB_STUDIO_ARTIFACT_PATH_RO
+                           If set, mounts the artifact cache path read-only
+    HAB_STUDIO_NO_KEYS     If set, installs no origin keys
+    HAB_STUDIO_SCRUB_KEYS  If set, removes the imported secret origin keys on exit
     HAB_STUDIO_NOSTUDIORC  Disables sourcing a \`.studiorc' in \`studio enter'
     HAB_STUDIO_ROOT        Sets a Studio root (\`-r' option overrides)
     HAB_STUDIO_SUP         Sets args for a Supervisor in \`studio enter'
@@ -442,13 +463,25 @@ new_studio() {
     fi
 
     # Mount the `$ARTIFACT_PATH` under `/hab/cache/artifacts` in the Studio,
-    # unless `$NO_ARTIFACT_PATH` are set
+    # unless `$NO_ARTIFACT_PATH` are set. When only some origins' artifacts
+    # are shared, they are copied in rather than mounting the whole cache.
     if [ -z "${NO_ARTIFACT_PATH}" ]; then
       studio_artifact_path="${HAB_STUDIO_ROOT}${HAB_CACHE_ARTIFACT_PATH}"
-      if ! $bb mount | $bb grep -q "on $studio_artifact_path type"; then
+      if [ -n "${HAB_STUDIO_ARTIFACT_ORIGINS:-}" ]; then
+        $bb mkdir -p $v "$studio_artifact_path"
+        for artifact in "$ARTIFACT_PATH"/*.hart; do
+          if [ -f "$artifact" ] \
+          && is_artifact_origin_shared "$(artifact_origin "$artifact")"; then
+            $bb cp $v "$artifact" "$studio_artifact_path"
+          fi
+        done
+      elif ! $bb mount | $bb grep -q "on $studio_artifact_path type"; then
         $bb mkdir -p $v "$ARTIFACT_PATH"
         $bb mkdir -p $v "$studio_artifact_path"
         $bb mount $v --bind "$ARTIFACT_PATH" "$studio_artifact_path"
+        if [ -n "${HAB_STUDIO_ARTIFACT_PATH_RO:-}" ]; then
+          $bb mount $v -o remount,ro,bind "$studio_artifact_path"
+        fi
       fi
     fi
 
@@ -948,6 +981,8 @@ report_env_vars() {
 # **Internal** Run when an interactive studio exits.
 cleanup_studio() {
   kill_launcher
+  scrub_keys
+  copy_back_artifacts
   chown_artifacts
   chown_certs
   unmount_filesystems
@@ -972,6 +1007,59 @@ kill_launcher() {
   fi
 }
 
+# **Internal** Removes the secret origin keys imported into the Studio, if
+# asked to with `$HAB_STUDIO_SCRUB_KEYS`.
+scrub_keys() {
+  if [ -n "${HAB_STUDIO_SCRUB_KEYS:-}" ] && [ -n "${HAB_ORIGIN_KEYS:-}" ]; then
+    # A key revision is a 14 digit timestamp. Matching it exactly leaves alone the
+    # keys of an origin whose name only starts with `$key-`.
+    revision='[0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9]'
+    for key in $(echo "$HAB_ORIGIN_KEYS" | $bb tr ',' ' '); do
+      for key_file in "$HAB_STUDIO_ROOT/hab/cache/keys/$key"-$revision.sig.key; do
+        if [ -f "$key_file" ]; then
+          info "Scrubbing '$key' secret origin key"
+          try "$bb" rm -f "$key_file"
+        fi
+      done
+    done
+  fi
+}
+
+# **Internal** Prints the origin of the artifact at `$1`, as recorded in the
+# artifact itself rather than guessed from its file name, since origins and
+# package names may both contain a `-`.
+artifact_origin() {
+  $hab pkg info "$1" 2> /dev/null | $bb awk '$1 == "Origin" { print $3 }'
+}
+
+# **Internal** Whether `$1` is one of the origins in
+# `$HAB_STUDIO_ARTIFACT_ORIGINS`.
+is_artifact_origin_shared() {
+  for origin in $(echo "$HAB_STUDIO_ARTIFACT_ORIGINS" | $bb tr ',' ' '); do
+    if [ -n "$1" ] && [ "$1" = "$origin" ]; then
+      return 0
+    fi
+  done
+  return 1
+}
+
+# **Internal** Copies the artifacts built in a Studio which was given only
+# some origins' artifacts back into the artifact cache, unless the cache is
+# shared read-only.
+copy_back_artifacts() {
+  if [ -z "${NO_MOUNT}" ] \
+  && [ -z "${NO_ARTIFACT_PATH}" ] \
+  && [ -n "${HAB_STUDIO_ARTIFACT_ORIGINS:-}" ] \
+  && [ -z "${HAB_STUDIO_ARTIFACT_PATH_RO:-}" ]; then
+    for artifact in "${HAB_STUDIO_ROOT}${HAB_CACHE_ARTIFACT_PATH}"/*.hart; do
+      if [ -f "$artifact" ] \
+      && [ ! -f "$ARTIFACT_PATH/$($bb basename "$artifact")" ]; then
+        try "$bb" cp "$artifact" "$ARTIFACT_PATH"
+      fi
+    done
+  fi
+}
+
 # **Internal** Updates file ownership on files under the artifact cache path
 # using the ownership of the artifact cache directory to determine the target
 # uid and gid. This is done in an effort to leave files residing in a user
@@ -1296,6 +1384,10 @@ dir_name="$(echo "$SRC_PATH" | $bb sed -e 's,^/$,root,' -e 's,^/,,' -e 's,/,--,g
 # cache directory. If this environment variable is not set, use the value
 # from `$HAB_ORIGIN` if set, otherwise, it's empty.
 : "${HAB_ORIGIN_KEYS:=${HAB_ORIGIN:-}}"
+# Whether no origin keys at all should be installed into the Studio.
+if [ -n "${HAB_STUDIO_NO_KEYS:-}" ]; then
+  HAB_ORIGIN_KEYS=
+fi
 # The Studio configuration file which is used to determine commands to run,
 # extra environment variables, etc. Note that a valid Studio will have this
 # file at the root of its filesystem.
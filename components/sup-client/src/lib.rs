@@ -5,16 +5,17 @@
 //!
 //! ```rust no_run
 //! use habitat_common::types::ListenCtlAddr;
-//! use habitat_sup_client::SrvClient;
+//! use habitat_sup_client::{CtlGateway,
+//!                          SrvClient};
 //! use habitat_sup_protocol as protocols;
 //! use futures::stream::StreamExt;
 //!
 //! #[tokio::main]
 //! async fn main() {
-//!     let listen_addr = ListenCtlAddr::default();
+//!     let gateway = CtlGateway::from(ListenCtlAddr::default());
 //!     let secret_key = "seekrit";
 //!     let msg = protocols::ctl::SvcGetDefaultCfg::default();
-//!     let mut response = SrvClient::request(&listen_addr, secret_key, msg).await.unwrap();
+//!     let mut response = SrvClient::request(&gateway, secret_key, msg).await.unwrap();
 //!     while let Some(message_result) = response.next().await {
 //!         let reply = message_result.unwrap();
 //!         match reply.message_id() {
@@ -34,26 +35,61 @@
 
 use habitat_sup_protocol as protocol;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
-use crate::{common::types::ListenCtlAddr,
+use crate::{common::{types::ListenCtlAddr,
+                     ui::{UIWriter,
+                          UI}},
             protocol::{codec::*,
-                       net::NetErr}};
+                       message::MessageStatic,
+                       net::{ErrCode,
+                             NetErr}}};
 use futures::{sink::SinkExt,
               stream::{Stream,
                        StreamExt}};
 use habitat_common as common;
-use std::{error,
+use std::{collections::HashMap,
+          error,
           fmt,
-          io,
-          path::PathBuf,
-          time::Duration};
+          fs::File,
+          io::{self,
+               BufReader},
+          path::{Path,
+                 PathBuf},
+          sync::{Arc,
+                 Mutex},
+          time::{Duration,
+                 Instant}};
 use tokio::{net::TcpStream,
             time};
+use tokio_rustls::{rustls::{internal::pemfile,
+                            Certificate,
+                            ClientConfig,
+                            PrivateKey},
+                   webpki::DNSNameRef,
+                   TlsConnector};
 use tokio_util::codec::Framed;
 
 /// Time to wait in milliseconds for a client connection to timeout.
 pub const REQ_TIMEOUT: u64 = 10_000;
 
+/// The name the Control Gateway's certificate must be for when none is given.
+const DEFAULT_CTL_SERVER_NAME: &str = "localhost";
+
+/// How long before a session token expires that the client stops using it
+const SESSION_EXPIRY_MARGIN: Duration = Duration::from_secs(10);
+
+/// A connection to the Control Gateway
+type SrvSocket = Framed<Box<dyn SrvTransport>, SrvCodec>;
+
+lazy_static! {
+    /// The session tokens this process has been given, and when they expire, by server address.
+    /// They are only held in memory, so every `hab` command opens its own session, and only the
+    /// requests it makes after its first one reuse it.
+    static ref SESSIONS: Mutex<HashMap<String, (String, Instant)>> = Mutex::default();
+}
+
 /// Error types returned by a [`SrvClient`].
 #[derive(Debug)]
 pub enum SrvClientError {
@@ -63,8 +99,17 @@ pub enum SrvClientError {
     ConnectionClosed,
     /// Unable to locate a secret key on disk.
     CtlSecretNotFound(PathBuf),
+    /// A certificate file for Control Gateway TLS held no PEM-format certificates.
+    InvalidCertFile(PathBuf),
+    /// A private key file for Control Gateway TLS held no RSA or PKCS8 private key.
+    InvalidKeyFile(PathBuf),
+    /// The name the Control Gateway's certificate must be for is not a valid DNS name.
+    InvalidServerName(String),
     /// Decoding a message from the remote failed.
     Decode(prost::DecodeError),
+    /// The Supervisor at this address predates session tokens, and would have to be sent the
+    /// secret key itself.
+    SessionsUnsupported(String),
     /// An Os level IO error occurred.
     Io(io::Error),
     /// An RPC call to the remote was received but failed.
@@ -97,7 +142,24 @@ impl fmt::Display for SrvClientError {
                          to command the Supervisor.",
                         path.display())
             }
+            SrvClientError::InvalidCertFile(ref path) => {
+                format!("Invalid cert file: {}", path.display())
+            }
+            SrvClientError::InvalidKeyFile(ref path) => {
+                format!("Invalid key file: {}", path.display())
+            }
+            SrvClientError::InvalidServerName(ref name) => {
+                format!("Invalid Control Gateway server name: {}. Set {} to the DNS name its \
+                         certificate is for.",
+                        name, CTL_SERVER_NAME_ENVVAR)
+            }
             SrvClientError::Decode(ref err) => format!("{}", err),
+            SrvClientError::SessionsUnsupported(ref address) => {
+                format!("The Supervisor at {} does not give out session tokens, so it could only \
+                         be commanded by sending it the secret key itself. Upgrade it, or command \
+                         it with a hab of its own version.",
+                        address)
+            }
             SrvClientError::Io(ref err) => format!("{}", err),
             SrvClientError::NetErr(ref err) => format!("{}", err),
             SrvClientError::ParseColor(ref err) => format!("{}", err),
@@ -127,29 +189,160 @@ impl From<termcolor::ParseColorError> for SrvClientError {
     fn from(err: termcolor::ParseColorError) -> Self { SrvClientError::ParseColor(err) }
 }
 
+/// A Supervisor's Control Gateway, and how to connect to it.
+#[derive(Clone, Debug)]
+pub struct CtlGateway {
+    pub addr: ListenCtlAddr,
+    /// Connect with TLS rather than in the clear
+    pub tls:  Option<CtlTls>,
+}
+
+impl From<ListenCtlAddr> for CtlGateway {
+    fn from(addr: ListenCtlAddr) -> Self { CtlGateway { addr, tls: None } }
+}
+
+impl fmt::Display for CtlGateway {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.addr) }
+}
+
+/// The TLS settings for connecting to a Control Gateway.
+#[derive(Clone, Debug)]
+pub struct CtlTls {
+    /// The CA certificate to verify the Control Gateway's certificate with
+    pub server_ca_certificate: PathBuf,
+    /// The name the Control Gateway's certificate must be for, or else `localhost`
+    pub server_name:           Option<String>,
+    /// The certificate, and its private key, to present to a Control Gateway which
+    /// authenticates its clients
+    pub client_certificate:    Option<(PathBuf, PathBuf)>,
+}
+
+impl CtlTls {
+    fn connector(&self) -> Result<TlsConnector, SrvClientError> {
+        let ca_path = &self.server_ca_certificate;
+        let mut config = ClientConfig::new();
+        for cert in read_certs(ca_path)? {
+            config.root_store
+                  .add(&cert)
+                  .map_err(|_| SrvClientError::InvalidCertFile(ca_path.clone()))?;
+        }
+        if let Some((ref cert_path, ref key_path)) = self.client_certificate {
+            config.set_single_client_cert(read_certs(cert_path)?, read_key(key_path)?);
+        }
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    fn server_name(&self) -> Result<DNSNameRef<'_>, SrvClientError> {
+        let name = self.server_name
+                       .as_deref()
+                       .unwrap_or(DEFAULT_CTL_SERVER_NAME);
+        match DNSNameRef::try_from_ascii_str(name) {
+            Ok(name) => Ok(name),
+            Err(_) => Err(SrvClientError::InvalidServerName(name.to_string())),
+        }
+    }
+}
+
 /// Client for connecting and communicating with a server speaking SrvProtocol.
 ///
 /// See module doc for usage.
 pub struct SrvClient;
 
 impl SrvClient {
-    /// Connect to the remote server with the given secret_key and make a request.
+    /// Connect to the remote server with the given secret_key and make a request. The connection
+    /// is made with TLS if the gateway has TLS settings, and fails rather than falling back to
+    /// connecting in the clear if they can't be used.
+    ///
+    /// The secret key itself is never sent. Instead, the client proves it knows it to be given a
+    /// short-lived session token, which later requests this process makes to the same server
+    /// authenticate with until it expires or is revoked. Servers which don't give out session
+    /// tokens are refused with `SrvClientError::SessionsUnsupported`.
     ///
     /// Returns a stream of `SrvMessage`'s representing the server response.
     pub async fn request(
-        address: &ListenCtlAddr,
+        gateway: &CtlGateway,
         secret_key: &str,
         request: impl Into<SrvMessage> + fmt::Debug)
         -> Result<impl Stream<Item = Result<SrvMessage, io::Error>>, SrvClientError> {
-        let socket = TcpStream::connect(address.as_ref()).await?;
-        let mut socket = Framed::new(socket, SrvCodec::new());
+        let address = &gateway.addr;
+        let mut socket = match cached_session(address) {
+            Some(token) => {
+                let mut handshake = protocol::ctl::Handshake::default();
+                handshake.session_token = Some(token);
+                match Self::connect(gateway, handshake).await {
+                    Err(SrvClientError::NetErr(err))
+                        if err.code == ErrCode::Unauthorized as i32 =>
+                    {
+                        debug!("Session token for {} was refused: {}", address, err);
+                        forget_session(address);
+                        Self::open_session(gateway, secret_key).await?
+                    }
+                    result => result?.0,
+                }
+            }
+            None => Self::open_session(gateway, secret_key).await?,
+        };
+
+        // Send the actual request message
         let mut current_transaction = SrvTxn::default();
+        current_transaction.increment();
+        let mut message = request.into();
+        message.set_transaction(current_transaction);
+        trace!("Sending SrvMessage -> {:?}", message);
+        socket.send(message).await?;
+
+        // Return the socket for use as a Stream of responses
+        Ok(socket)
+    }
+
+    /// Connect to the server with a new session, remembering its token for later requests.
+    async fn open_session(gateway: &CtlGateway,
+                          secret_key: &str)
+                          -> Result<SrvSocket, SrvClientError> {
+        let address = &gateway.addr;
+        let (socket, reply) =
+            match Self::connect(gateway, protocol::ctl::CtlSessionOpen::new(secret_key)).await {
+                // Servers which predate sessions close the connection on anything but a
+                // handshake.
+                Err(SrvClientError::ConnectionClosed) => {
+                    return Err(SrvClientError::SessionsUnsupported(address.to_string()));
+                }
+                result => result?,
+            };
+        if reply.message_id() == protocol::ctl::CtlSession::MESSAGE_ID {
+            let session = reply.parse::<protocol::ctl::CtlSession>()?;
+            if let Some(expires_in) = session.secret_expires_in {
+                warn_secret_rotated(address, expires_in);
+            }
+            if let Some(token) = session.token {
+                remember_session(address,
+                                 token,
+                                 Duration::from_secs(session.expires_in.unwrap_or_default()));
+            }
+        }
+        Ok(socket)
+    }
+
+    /// Connect to the server and authenticate with `handshake`, returning the framed socket and
+    /// the server's reply to the handshake.
+    async fn connect(gateway: &CtlGateway,
+                     handshake: impl Into<SrvMessage>)
+                     -> Result<(SrvSocket, SrvMessage), SrvClientError> {
+        let address = &gateway.addr;
+        let transport: Box<dyn SrvTransport> = match gateway.tls {
+            Some(ref tls) => {
+                let connector = tls.connector()?;
+                let server_name = tls.server_name()?;
+                let socket = TcpStream::connect(address.as_ref()).await?;
+                Box::new(connector.connect(server_name, socket).await?)
+            }
+            None => Box::new(TcpStream::connect(address.as_ref()).await?),
+        };
+        let mut socket = Framed::new(transport, SrvCodec::new());
 
         // Send the handshake message to the server
-        let mut handshake = protocol::ctl::Handshake::default();
-        handshake.secret_key = Some(String::from(secret_key));
-        let mut message = SrvMessage::from(handshake);
-        message.set_transaction(current_transaction);
+        let mut message = handshake.into();
+        message.set_transaction(SrvTxn::default());
         socket.send(message).await?;
 
         // Verify the handshake response. There are three kinds of errors we could encounter:
@@ -165,16 +358,11 @@ impl SrvClient {
                                               })?
                                               .ok_or(SrvClientError::ConnectionClosed)??;
         handshake_reply.try_ok()?;
-
-        // Send the actual request message
-        current_transaction.increment();
-        let mut message = request.into();
-        message.set_transaction(current_transaction);
-        trace!("Sending SrvMessage -> {:?}", message);
-        socket.send(message).await?;
-
-        // Return the socket for use as a Stream of responses
-        Ok(socket)
+        if handshake_reply.message_id() == protocol::ctl::CtlSecretRotated::MESSAGE_ID {
+            let notice = handshake_reply.parse::<protocol::ctl::CtlSecretRotated>()?;
+            warn_secret_rotated(address, notice.expires_in.unwrap_or_default());
+        }
+        Ok((socket, handshake_reply))
     }
 
     pub fn read_secret_key() -> Result<String, SrvClientError> {
@@ -184,3 +372,54 @@ impl SrvClient {
         Ok(buf)
     }
 }
+
+fn warn_secret_rotated(address: &ListenCtlAddr, expires_in: u64) {
+    UI::default_with_env().warn(format!("The Supervisor at {} has rotated its ctl gateway \
+                                         secret, and will stop accepting the one given to hab \
+                                         in {}s",
+                                        address, expires_in))
+                          .ok();
+}
+
+/// The session token for the server at `address`, if this process was given one which has yet
+/// to expire.
+fn cached_session(address: &ListenCtlAddr) -> Option<String> {
+    let sessions = SESSIONS.lock().expect("Session cache lock is poisoned");
+    sessions.get(&address.to_string())
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(token, _)| token.clone())
+}
+
+fn remember_session(address: &ListenCtlAddr, token: String, expires_in: Duration) {
+    // Stop using the token a little early, rather than risk it expiring in flight.
+    let expires = Instant::now() + expires_in.checked_sub(SESSION_EXPIRY_MARGIN)
+                                             .unwrap_or_default();
+    SESSIONS.lock()
+            .expect("Session cache lock is poisoned")
+            .insert(address.to_string(), (token, expires));
+}
+
+fn forget_session(address: &ListenCtlAddr) {
+    SESSIONS.lock()
+            .expect("Session cache lock is poisoned")
+            .remove(&address.to_string());
+}
+
+fn read_certs(path: &Path) -> Result<Vec<Certificate>, SrvClientError> {
+    let file = &mut BufReader::new(File::open(path)?);
+    pemfile::certs(file).ok()
+                        .filter(|certs| !certs.is_empty())
+                        .ok_or_else(|| SrvClientError::InvalidCertFile(path.to_path_buf()))
+}
+
+/// Read an RSA private key from `path`, or else a PKCS8-encoded one.
+fn read_key(path: &Path) -> Result<PrivateKey, SrvClientError> {
+    let file = &mut BufReader::new(File::open(path)?);
+    let mut keys = pemfile::rsa_private_keys(file).unwrap_or_default();
+    if keys.is_empty() {
+        let file = &mut BufReader::new(File::open(path)?);
+        keys = pemfile::pkcs8_private_keys(file).unwrap_or_default();
+    }
+    keys.pop()
+        .ok_or_else(|| SrvClientError::InvalidKeyFile(path.to_path_buf()))
+}
@@ -39,9 +39,17 @@
 //!
 //! Contains the actual payload of the message encoded using Google
 //! [Protobuf 2](https://developers.google.com/protocol-buffers/docs/reference/proto2-spec).
+//!
+//! # Limits
+//!
+//! A decoder rejects a frame as soon as its header segment declares a body larger than the
+//! codec's maximum body length or an empty message ID, before any of the body is buffered. The
+//! resulting error carries a [`FrameError`] describing why the frame was rejected.
 
 use crate::{message::MessageStatic,
-            net::{NetErr,
+            net::{self,
+                  ErrCode,
+                  NetErr,
                   NetResult}};
 use bytes::{Buf,
             BufMut,
@@ -49,11 +57,12 @@ use bytes::{Buf,
             BytesMut};
 use prost::{self,
             Message};
-use std::{fmt,
-          io::{self,
-               Cursor},
+use std::{error,
+          fmt,
+          io,
           str};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead,
+                AsyncWrite};
 use tokio_util::codec::{Decoder,
                         Encoder,
                         Framed};
@@ -71,9 +80,18 @@ const RESPONSE_MASK: u32 = 0x1;
 const COMPLETE_OFFSET: u32 = 30;
 const COMPLETE_MASK: u32 = 0x1;
 
-/// A `TcpStream` framed with `SrvCodec`. This is the base socket connection that the CtlGateway
-/// client and server speak.
-pub type SrvStream = Framed<TcpStream, SrvCodec>;
+/// The largest message body a `SrvCodec` accepts unless configured otherwise. This is also the
+/// largest body length a header segment can describe.
+pub const MAX_BODY_LEN: usize = BODY_LEN_MASK as usize;
+
+/// The connection underneath a `SrvStream`: a `TcpStream`, or a TLS session over one.
+pub trait SrvTransport: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T> SrvTransport for T where T: AsyncRead + AsyncWrite + Send + Unpin {}
+
+/// A `SrvTransport` framed with `SrvCodec`. This is the base socket connection that the
+/// CtlGateway client and server speak.
+pub type SrvStream = Framed<Box<dyn SrvTransport>, SrvCodec>;
 
 // The type of the transaction id.
 pub type TxnId = u32;
@@ -265,7 +283,10 @@ impl SrvMessage {
     /// fail out if the received message contains an error.
     pub fn try_ok(&self) -> NetResult<()> {
         if self.message_id() == NetErr::MESSAGE_ID {
-            let err = self.parse().expect("try_ok bad NetErr");
+            let err = self.parse()
+                          .unwrap_or_else(|e| {
+                              net::err(ErrCode::BadPayload, format!("unreadable NetErr: {}", e))
+                          });
             return Err(err);
         }
         Ok(())
@@ -294,15 +315,79 @@ impl<T> From<T> for SrvMessage where T: Message + MessageStatic
     }
 }
 
+/// The reason a `SrvCodec` rejected a frame. Decoding and encoding errors caused by a rejected
+/// frame are `io::ErrorKind::InvalidData` errors wrapping one of these.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameError {
+    /// The message body is longer than the codec's maximum body length.
+    BodyTooLarge { len: usize, max: usize },
+    /// The header segment declared a message ID segment of zero length.
+    EmptyMessageId,
+    /// The message ID segment is not valid UTF-8.
+    InvalidMessageId,
+}
+
+impl FrameError {
+    /// Returns the `FrameError` wrapped by an error returned from a `SrvCodec`, if any.
+    pub fn from_io_error(err: &io::Error) -> Option<Self> {
+        err.get_ref()
+           .and_then(|inner| inner.downcast_ref::<FrameError>())
+           .copied()
+    }
+
+    /// A short, stable name for the reason, suitable as a metric label.
+    pub fn reason(self) -> &'static str {
+        match self {
+            FrameError::BodyTooLarge { .. } => "body_too_large",
+            FrameError::EmptyMessageId => "empty_message_id",
+            FrameError::InvalidMessageId => "invalid_message_id",
+        }
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FrameError::BodyTooLarge { len, max } => {
+                write!(f,
+                       "message body of {} bytes exceeds the maximum of {}",
+                       len, max)
+            }
+            FrameError::EmptyMessageId => write!(f, "message has an empty message ID"),
+            FrameError::InvalidMessageId => write!(f, "message ID is not valid UTF-8"),
+        }
+    }
+}
+
+impl error::Error for FrameError {}
+
+impl From<FrameError> for io::Error {
+    fn from(err: FrameError) -> Self { io::Error::new(io::ErrorKind::InvalidData, err) }
+}
+
 /// Binary encoder decoder for the `SrvProtocol` binary protocol.
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct SrvCodec {
-    recv_buf: Vec<u8>,
+    max_body_len: usize,
 }
 
 impl SrvCodec {
     /// Creates a new `SrvCodec` for shipping around `SrvMessage`s.
-    pub fn new() -> SrvCodec { SrvCodec { recv_buf: vec![0; BODY_LEN_MASK as usize], } }
+    pub fn new() -> SrvCodec { Self::with_max_body_len(MAX_BODY_LEN) }
+
+    /// Creates a new `SrvCodec` which rejects messages with bodies longer than `max_body_len`
+    /// bytes. Limits above `MAX_BODY_LEN` have no effect.
+    pub fn with_max_body_len(max_body_len: usize) -> SrvCodec {
+        SrvCodec { max_body_len: max_body_len.min(MAX_BODY_LEN), }
+    }
+
+    fn check_body_len(&self, len: usize) -> Result<(), FrameError> {
+        if len > self.max_body_len {
+            return Err(FrameError::BodyTooLarge { len,
+                                                  max: self.max_body_len });
+        }
+        Ok(())
+    }
 }
 
 impl Decoder for SrvCodec {
@@ -314,39 +399,40 @@ impl Decoder for SrvCodec {
             trace!("Got fewer than HEADER_LEN bytes: {}", bytes.len());
             return Ok(None);
         }
-        trace!("Decoding SrvMessage\n  -> Bytes: {:?}", bytes);
-        let mut buf = Cursor::new(bytes);
-        let header = SrvHeader(buf.get_u32());
-        trace!("  -> SrvHeader: {:?}", header);
-        let txn = if header.is_transaction() {
-            if buf.remaining() < TXN_LEN {
-                return Ok(None);
-            }
-            let t = SrvTxn(buf.get_u32());
+        let header = SrvHeader((&bytes[..HEADER_LEN]).get_u32());
+        trace!("Decoding SrvMessage\n  -> SrvHeader: {:?}", header);
+        // Validate the header before waiting for the rest of the frame so that a peer cannot make
+        // us buffer a body we are going to reject anyway.
+        self.check_body_len(header.body_len())?;
+        if header.message_id_len() == 0 {
+            return Err(FrameError::EmptyMessageId.into());
+        }
+        let txn_len = if header.is_transaction() { TXN_LEN } else { 0 };
+        let frame_len = HEADER_LEN + txn_len + header.message_id_len() + header.body_len();
+        if bytes.len() < frame_len {
+            trace!("  -> Not enough bytes to read the whole frame");
+            bytes.reserve(frame_len - bytes.len());
+            return Ok(None);
+        }
+        bytes.advance(HEADER_LEN);
+        let transaction = if header.is_transaction() {
+            let t = SrvTxn(bytes.get_u32());
             trace!("  -> SrvTxn: {:?}", t);
             Some(t)
         } else {
             None
         };
-        if buf.remaining() < (header.message_id_len() + header.body_len()) {
-            trace!("  -> Not enough bytes to read message_id and body");
-            return Ok(None);
-        }
-        buf.copy_to_slice(&mut self.recv_buf[0..header.message_id_len()]);
-        let message_id = str::from_utf8(&self.recv_buf[0..header.message_id_len()]).map_err(|e| {
-                             trace!("  -> Invalid message data: {}", e);
-                             io::Error::new(io::ErrorKind::InvalidData, e)
-                         })?
-                         .to_string();
-        buf.copy_to_slice(&mut self.recv_buf[0..header.body_len()]);
-        let position = buf.position() as usize;
-        let bytes = buf.into_inner();
-        bytes.advance(position);
+        let message_id = bytes.split_to(header.message_id_len());
+        let message_id = str::from_utf8(&message_id).map_err(|e| {
+                                                        trace!("  -> Invalid message id: {}", e);
+                                                        FrameError::InvalidMessageId
+                                                    })?
+                                                    .to_string();
+        let body = bytes.split_to(header.body_len()).freeze();
         Ok(Some(SrvMessage { header,
-                             transaction: txn,
+                             transaction,
                              message_id,
-                             body:
-                                 Bytes::copy_from_slice(&self.recv_buf[0..header.body_len()]) }))
+                             body }))
     }
 }
 
@@ -354,6 +440,7 @@ impl Encoder<SrvMessage> for SrvCodec {
     type Error = io::Error;
 
     fn encode(&mut self, msg: SrvMessage, buf: &mut BytesMut) -> io::Result<()> {
+        self.check_body_len(msg.body().len())?;
         buf.reserve(msg.size());
         buf.put_u32(msg.header().0);
         if let Some(txn) = msg.transaction {
@@ -369,6 +456,29 @@ impl Encoder<SrvMessage> for SrvCodec {
 mod test {
     use super::*;
     use crate::net;
+    use rand::{rngs::StdRng,
+               Rng,
+               SeedableRng};
+
+    /// Deterministic source of randomness for the fuzz tests, so that failures are reproducible.
+    fn rng() -> StdRng { StdRng::seed_from_u64(0x5eed) }
+
+    fn random_message(rng: &mut StdRng) -> SrvMessage {
+        let message_id: String =
+            (0..rng.gen_range(1, MESSAGE_ID_MASK + 1)).map(|_| rng.gen_range(b'a', b'z') as char)
+                                                      .collect();
+        let body: Vec<u8> = (0..rng.gen_range(0, 512)).map(|_| rng.gen()).collect();
+        let mut msg = SrvMessage { header: SrvHeader::new(body.len() as u32,
+                                                          message_id.len() as u32,
+                                                          false),
+                                   transaction: None,
+                                   message_id,
+                                   body: Bytes::from(body) };
+        if rng.gen() {
+            msg.set_transaction(SrvTxn(rng.gen()));
+        }
+        msg
+    }
 
     #[test]
     fn test_header_pack_unpack() {
@@ -440,4 +550,105 @@ mod test {
         assert_eq!(decoded.transaction(), msg.transaction());
         assert_eq!(decoded.body(), msg.body());
     }
+
+    #[test]
+    fn test_codec_rejects_oversized_body_before_buffering() {
+        let mut codec = SrvCodec::with_max_body_len(16);
+        let mut buf = BytesMut::new();
+        buf.put_u32(SrvHeader::new(17, 4, false).0);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(FrameError::from_io_error(&err),
+                   Some(FrameError::BodyTooLarge { len: 17, max: 16 }));
+    }
+
+    #[test]
+    fn test_codec_rejects_empty_message_id() {
+        let mut codec = SrvCodec::new();
+        let mut buf = BytesMut::new();
+        buf.put_u32(SrvHeader::new(0, 0, false).0);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(FrameError::from_io_error(&err),
+                   Some(FrameError::EmptyMessageId));
+    }
+
+    #[test]
+    fn test_codec_rejects_invalid_message_id() {
+        let mut codec = SrvCodec::new();
+        let mut buf = BytesMut::new();
+        buf.put_u32(SrvHeader::new(0, 2, false).0);
+        buf.put_slice(&[0xC3, 0x28]);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(FrameError::from_io_error(&err),
+                   Some(FrameError::InvalidMessageId));
+    }
+
+    #[test]
+    fn test_codec_refuses_to_encode_oversized_body() {
+        let mut codec = SrvCodec::with_max_body_len(1);
+        let msg = SrvMessage::from(net::err(net::ErrCode::Internal, "too long"));
+        let err = codec.encode(msg, &mut BytesMut::new()).unwrap_err();
+        assert!(matches!(FrameError::from_io_error(&err),
+                         Some(FrameError::BodyTooLarge { .. })));
+    }
+
+    #[test]
+    fn test_try_ok_with_malformed_net_err() {
+        let mut msg = SrvMessage::from(net::ok());
+        msg.message_id = NetErr::MESSAGE_ID.to_string();
+        msg.body = Bytes::from_static(&[0xFF, 0xFF, 0xFF]);
+        let err = msg.try_ok().unwrap_err();
+        assert_eq!(err.code, net::ErrCode::BadPayload as i32);
+    }
+
+    #[test]
+    fn test_fuzz_random_messages_round_trip() {
+        let mut rng = rng();
+        let mut codec = SrvCodec::new();
+        let messages: Vec<_> = (0..200).map(|_| random_message(&mut rng)).collect();
+        let mut buf = BytesMut::new();
+        for msg in &messages {
+            codec.encode(msg.clone(), &mut buf).unwrap();
+        }
+        for msg in &messages {
+            let decoded = codec.decode(&mut buf).unwrap().unwrap();
+            assert_eq!(decoded.header(), msg.header());
+            assert_eq!(decoded.transaction(), msg.transaction());
+            assert_eq!(decoded.message_id(), msg.message_id());
+            assert_eq!(decoded.body(), msg.body());
+        }
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_fuzz_truncated_frames_wait_for_more_bytes() {
+        let mut rng = rng();
+        let mut codec = SrvCodec::new();
+        for _ in 0..100 {
+            let mut encoded = BytesMut::new();
+            codec.encode(random_message(&mut rng), &mut encoded)
+                 .unwrap();
+            let cut = rng.gen_range(0, encoded.len());
+            let mut buf = BytesMut::from(&encoded[..cut]);
+            assert!(codec.decode(&mut buf).unwrap().is_none());
+            assert_eq!(&buf[..], &encoded[..cut]);
+            buf.extend_from_slice(&encoded[cut..]);
+            assert!(codec.decode(&mut buf).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn test_fuzz_random_bytes_never_panic() {
+        let mut rng = rng();
+        let mut codec = SrvCodec::with_max_body_len(1024);
+        for _ in 0..10_000 {
+            let len = rng.gen_range(0, 256);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let mut buf = BytesMut::from(&bytes[..]);
+            while let Ok(Some(msg)) = codec.decode(&mut buf) {
+                assert!(msg.size() <= len);
+                assert!(msg.body().len() <= 1024);
+            }
+        }
+    }
 }
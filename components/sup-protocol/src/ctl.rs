@@ -4,7 +4,13 @@
 //! Note: See `protocols/ctl.proto` for type level documentation for generated types.
 
 use crate::message;
-use std::fmt;
+use habitat_core::{clock,
+                   crypto::{self,
+                            hash,
+                            SigKeyPair}};
+use prost::Message;
+use std::{fmt,
+          time::UNIX_EPOCH};
 
 include!(concat!(env!("OUT_DIR"), "/sup.ctl.rs"));
 
@@ -16,6 +22,14 @@ impl message::MessageStatic for Handshake {
     const MESSAGE_ID: &'static str = "Handshake";
 }
 
+impl message::MessageStatic for CtlSessionOpen {
+    const MESSAGE_ID: &'static str = "CtlSessionOpen";
+}
+
+impl message::MessageStatic for CtlSession {
+    const MESSAGE_ID: &'static str = "CtlSession";
+}
+
 impl message::MessageStatic for ServiceBindList {
     const MESSAGE_ID: &'static str = "ServiceBindList";
 }
@@ -28,6 +42,58 @@ impl message::MessageStatic for SupRestart {
     const MESSAGE_ID: &'static str = "SupRestart";
 }
 
+impl message::MessageStatic for SupTerm {
+    const MESSAGE_ID: &'static str = "SupTerm";
+}
+
+impl message::MessageStatic for SupForgetDeparted {
+    const MESSAGE_ID: &'static str = "SupForgetDeparted";
+}
+
+impl message::MessageStatic for SupMembers {
+    const MESSAGE_ID: &'static str = "SupMembers";
+}
+
+impl message::MessageStatic for SupRingHealth {
+    const MESSAGE_ID: &'static str = "SupRingHealth";
+}
+
+impl message::MessageStatic for SupKeyRevocations {
+    const MESSAGE_ID: &'static str = "SupKeyRevocations";
+}
+
+impl message::MessageStatic for SupRingKeyRotate {
+    const MESSAGE_ID: &'static str = "SupRingKeyRotate";
+}
+
+impl message::MessageStatic for SupRingReload {
+    const MESSAGE_ID: &'static str = "SupRingReload";
+}
+
+impl message::MessageStatic for SupSecretRotate {
+    const MESSAGE_ID: &'static str = "SupSecretRotate";
+}
+
+impl message::MessageStatic for SupSessionsRevoke {
+    const MESSAGE_ID: &'static str = "SupSessionsRevoke";
+}
+
+impl message::MessageStatic for CtlSecretRotated {
+    const MESSAGE_ID: &'static str = "CtlSecretRotated";
+}
+
+impl message::MessageStatic for SupDrain {
+    const MESSAGE_ID: &'static str = "SupDrain";
+}
+
+impl message::MessageStatic for SupRingExec {
+    const MESSAGE_ID: &'static str = "SupRingExec";
+}
+
+impl message::MessageStatic for SupRingExecStatus {
+    const MESSAGE_ID: &'static str = "SupRingExecStatus";
+}
+
 impl message::MessageStatic for SvcFilePut {
     const MESSAGE_ID: &'static str = "SvcFilePut";
 }
@@ -36,6 +102,10 @@ impl message::MessageStatic for SvcGetDefaultCfg {
     const MESSAGE_ID: &'static str = "SvcGetDefaultCfg";
 }
 
+impl message::MessageStatic for SvcGetEnv {
+    const MESSAGE_ID: &'static str = "SvcGetEnv";
+}
+
 impl message::MessageStatic for SvcValidateCfg {
     const MESSAGE_ID: &'static str = "SvcValidateCfg";
 }
@@ -76,6 +146,133 @@ impl fmt::Display for ConsoleLine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.line) }
 }
 
+impl CtlSessionOpen {
+    /// A request for a session token which proves that the client knows `secret_key`, without
+    /// containing it.
+    pub fn new(secret_key: &str) -> Self {
+        let timestamp = clock::now().duration_since(UNIX_EPOCH)
+                                    .map(|since| since.as_secs())
+                                    .unwrap_or_default();
+        let nonce = crate::generate_session_token();
+        CtlSessionOpen { proof:     Some(session_proof(secret_key, timestamp, &nonce)),
+                         timestamp: Some(timestamp),
+                         nonce:     Some(nonce), }
+    }
+
+    /// Whether the request's proof was made with `secret_key`, compared in constant time.
+    pub fn proves(&self, secret_key: &str) -> bool {
+        let expected = session_proof(secret_key,
+                                     self.timestamp.unwrap_or_default(),
+                                     self.nonce.as_deref().unwrap_or_default());
+        crypto::secure_eq(expected, self.proof.as_deref().unwrap_or_default())
+    }
+}
+
+fn session_proof(secret_key: &str, timestamp: u64, nonce: &str) -> String {
+    hash::hash_string(&format!("{}:{}:{}", secret_key, timestamp, nonce))
+}
+
+/// A request which may carry the `PayloadSignature` of whoever made it, allowing the Supervisor
+/// to authenticate the change independently of the shared ctl secret.
+pub trait SignedRequest: Message + Default + Clone {
+    fn signature(&self) -> Option<&PayloadSignature>;
+
+    fn set_signature(&mut self, signature: Option<PayloadSignature>);
+
+    /// The bytes covered by the signature: this request encoded without the signature itself,
+    /// but with when, for whom and with which nonce it was signed.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut unsigned = self.clone();
+        unsigned.set_signature(self.signature().map(|signature| {
+                                                   PayloadSignature { signature: None,
+                                                                      ..signature.clone() }
+                                               }));
+        let mut buf = Vec::with_capacity(unsigned.encoded_len());
+        unsigned.encode(&mut buf)
+                .expect("buffer is sized to the encoded length");
+        buf
+    }
+
+    /// Sign this request with `pair` for the Supervisor at `target`, replacing any existing
+    /// signature.
+    fn sign(&mut self, pair: &SigKeyPair, target: &str) -> habitat_core::Result<()> {
+        let issued_at = clock::now().duration_since(UNIX_EPOCH)
+                                    .map(|since| since.as_secs())
+                                    .unwrap_or_default();
+        let mut signature = PayloadSignature { signer:    Some(pair.name_with_rev()),
+                                               signature: None,
+                                               issued_at: Some(issued_at),
+                                               target:    Some(target.to_string()),
+                                               nonce:     Some(crate::generate_session_token()), };
+        self.set_signature(Some(signature.clone()));
+        signature.signature = Some(pair.sign_payload(&self.signed_bytes())?);
+        self.set_signature(Some(signature));
+        Ok(())
+    }
+}
+
+macro_rules! signed_request_impl {
+    ($($t:ty)*) => ($(
+        impl SignedRequest for $t {
+            fn signature(&self) -> Option<&PayloadSignature> { self.signature.as_ref() }
+
+            fn set_signature(&mut self, signature: Option<PayloadSignature>) {
+                self.signature = signature;
+            }
+        }
+    )*)
+}
+
+signed_request_impl!(SvcFilePut SvcLoad SvcSetCfg SvcUpdate);
+
+impl SupRingExec {
+    /// An unsigned ring command to apply `request` on every Supervisor until `expires_at`, in
+    /// seconds since the UNIX epoch.
+    pub fn new<T>(id: String, request: &T, expires_at: u64) -> Self
+        where T: Message + message::MessageStatic
+    {
+        let mut body = Vec::with_capacity(request.encoded_len());
+        request.encode(&mut body)
+               .expect("buffer is sized to the encoded length");
+        SupRingExec { id:         Some(id),
+                      expires_at: Some(expires_at),
+                      kind:       Some(T::MESSAGE_ID.to_string()),
+                      body:       Some(body),
+                      signature:  None, }
+    }
+}
+
+/// A ring command is signed over what every Supervisor it is gossiped to receives, rather than
+/// over the request to issue it. Its own ID and expiry stand in for when, for whom and with which
+/// nonce its signature was made.
+impl SignedRequest for SupRingExec {
+    fn signature(&self) -> Option<&PayloadSignature> { self.signature.as_ref() }
+
+    fn set_signature(&mut self, signature: Option<PayloadSignature>) {
+        self.signature = signature;
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        ring_command_signed_bytes(self.id.as_deref().unwrap_or_default(),
+                                  self.expires_at.unwrap_or_default(),
+                                  self.kind.as_deref().unwrap_or_default(),
+                                  self.body.as_deref().unwrap_or_default())
+    }
+}
+
+/// The bytes the signature of a ring command covers: its ID, expiry, kind and body. The strings
+/// are prefixed with their lengths, so that no two commands have the same signed bytes.
+pub fn ring_command_signed_bytes(id: &str, expires_at: u64, kind: &str, body: &[u8]) -> Vec<u8> {
+    let mut signed = Vec::with_capacity(24 + id.len() + kind.len() + body.len());
+    signed.extend_from_slice(&(id.len() as u64).to_be_bytes());
+    signed.extend_from_slice(id.as_bytes());
+    signed.extend_from_slice(&expires_at.to_be_bytes());
+    signed.extend_from_slice(&(kind.len() as u64).to_be_bytes());
+    signed.extend_from_slice(kind.as_bytes());
+    signed.extend_from_slice(body);
+    signed
+}
+
 impl std::iter::FromIterator<habitat_core::service::ServiceBind> for ServiceBindList {
     fn from_iter<T>(iter: T) -> Self
         where T: IntoIterator<Item = habitat_core::service::ServiceBind>
@@ -89,3 +286,85 @@ impl Into<Vec<habitat_core::service::ServiceBind>> for ServiceBindList {
         self.binds.into_iter().map(Into::into).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_bytes_exclude_only_the_signature_itself() {
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        let mut msg = SvcSetCfg { cfg: Some(b"port = 80".to_vec()),
+                                  version: Some(2),
+                                  ..Default::default() };
+        msg.sign(&pair, "127.0.0.1:9632").unwrap();
+
+        let signature = msg.signature().unwrap().clone();
+        assert_eq!(signature.signer, Some(pair.name_with_rev()));
+        assert_eq!(signature.target.as_deref(), Some("127.0.0.1:9632"));
+        assert!(signature.issued_at.is_some());
+        assert!(signature.nonce.is_some());
+        assert!(pair.verify_payload(&msg.signed_bytes(), signature.signature.as_ref().unwrap())
+                    .is_ok());
+        let unsigned = SvcSetCfg { signature: None,
+                                   ..msg.clone() };
+        assert_ne!(msg.signed_bytes(), unsigned.signed_bytes());
+    }
+
+    #[test]
+    fn tampering_invalidates_the_signature() {
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        let mut msg = SvcSetCfg { cfg: Some(b"port = 80".to_vec()),
+                                  version: Some(2),
+                                  ..Default::default() };
+        msg.sign(&pair, "127.0.0.1:9632").unwrap();
+        let signature = msg.signature().unwrap().clone();
+
+        let mut retargeted = msg.clone();
+        retargeted.signature = Some(PayloadSignature { target: Some(String::from("10.0.0.1:9632")),
+                                                       ..signature.clone() });
+        let mut reconfigured = msg.clone();
+        reconfigured.cfg = Some(b"port = 8080".to_vec());
+        for tampered in &[retargeted, reconfigured] {
+            assert!(pair.verify_payload(&tampered.signed_bytes(),
+                                        signature.signature.as_ref().unwrap())
+                        .is_err());
+        }
+    }
+
+    #[test]
+    fn ring_command_signatures_cover_the_id_and_expiry() {
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        let mut msg = SupRingExec { id:         Some(String::from("cmd")),
+                                    expires_at: Some(600),
+                                    kind:       Some(String::from("SvcUpdate")),
+                                    body:       Some(b"update".to_vec()),
+                                    signature:  None, };
+        msg.sign(&pair, "127.0.0.1:9632").unwrap();
+        let signature = msg.signature().unwrap().signature.clone().unwrap();
+        assert!(pair.verify_payload(&ring_command_signed_bytes("cmd", 600, "SvcUpdate", b"update"),
+                                    &signature)
+                    .is_ok());
+
+        for tampered in &[SupRingExec { id: Some(String::from("other")),
+                                        ..msg.clone() },
+                          SupRingExec { expires_at: Some(6000),
+                                        ..msg.clone() }]
+        {
+            assert!(pair.verify_payload(&tampered.signed_bytes(), &signature)
+                        .is_err());
+        }
+    }
+
+    #[test]
+    fn session_open_proves_only_its_own_secret() {
+        let open = CtlSessionOpen::new("seekrit");
+        assert!(open.proves("seekrit"));
+        assert!(!open.proves("bogus"));
+        assert_ne!(open.proof.as_deref(), Some("seekrit"));
+
+        let mut tampered = open.clone();
+        tampered.nonce = Some(crate::generate_session_token());
+        assert!(!tampered.proves("seekrit"));
+    }
+}
@@ -57,6 +57,8 @@ use std::{fs::File,
 const CTL_SECRET_FILENAME: &str = "CTL_SECRET";
 /// Length of characters in CtlGateway secret key.
 const CTL_SECRET_LEN: usize = 64;
+/// Number of random bytes in CtlGateway session tokens and nonces.
+const SESSION_TOKEN_LEN: usize = 32;
 
 lazy_static! {
     /// The root path containing all runtime service directories and files
@@ -77,6 +79,15 @@ pub fn generate_secret_key(out: &mut String) {
     *out = base64::encode(&result);
 }
 
+/// Generate a random value for a `CtlGateway` session: the nonce a client opens one with, or the
+/// token it is given.
+pub fn generate_session_token() -> String {
+    let mut rng = rand::rngs::OsRng;
+    let mut result = vec![0u8; SESSION_TOKEN_LEN];
+    rng.fill_bytes(&mut result);
+    base64::encode(&result)
+}
+
 /// Read the secret key used to authenticate connections to the `CtlGateway` from disk and write
 /// it to the given out buffer. An `Ok` return value of `true` indicates a successful read while
 /// `false` indicates the file was not found.
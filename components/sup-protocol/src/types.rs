@@ -29,15 +29,40 @@ impl message::MessageStatic for ServiceBind {
 impl message::MessageStatic for ServiceCfg {
     const MESSAGE_ID: &'static str = "ServiceCfg";
 }
+
+impl message::MessageStatic for ServiceEnv {
+    const MESSAGE_ID: &'static str = "ServiceEnv";
+}
 impl message::MessageStatic for ServiceGroup {
     const MESSAGE_ID: &'static str = "ServiceGroup";
 }
 impl message::MessageStatic for ServiceStatus {
     const MESSAGE_ID: &'static str = "ServiceStatus";
 }
+impl message::MessageStatic for ServiceTransition {
+    const MESSAGE_ID: &'static str = "ServiceTransition";
+}
 impl message::MessageStatic for HealthCheckInterval {
     const MESSAGE_ID: &'static str = "HealthCheckInterval";
 }
+impl message::MessageStatic for MemberCfgChange {
+    const MESSAGE_ID: &'static str = "MemberCfgChange";
+}
+impl message::MessageStatic for MemberStatus {
+    const MESSAGE_ID: &'static str = "MemberStatus";
+}
+impl message::MessageStatic for MemberTransition {
+    const MESSAGE_ID: &'static str = "MemberTransition";
+}
+impl message::MessageStatic for RingCommandStatus {
+    const MESSAGE_ID: &'static str = "RingCommandStatus";
+}
+impl message::MessageStatic for RingHealth {
+    const MESSAGE_ID: &'static str = "RingHealth";
+}
+impl message::MessageStatic for KeyRevocations {
+    const MESSAGE_ID: &'static str = "KeyRevocations";
+}
 
 impl ServiceGroup {
     pub fn validate(value: &str) -> core::Result<()> {
@@ -301,6 +326,7 @@ impl UpdateStrategy {
             UpdateStrategy::None => "none",
             UpdateStrategy::AtOnce => "at-once",
             UpdateStrategy::Rolling => "rolling",
+            UpdateStrategy::Canary => "canary",
         }
     }
 }
@@ -313,6 +339,7 @@ impl FromStr for UpdateStrategy {
             "none" => Ok(UpdateStrategy::None),
             "at-once" => Ok(UpdateStrategy::AtOnce),
             "rolling" => Ok(UpdateStrategy::Rolling),
+            "canary" => Ok(UpdateStrategy::Canary),
             _ => Err(net::err(ErrCode::InvalidPayload, "Invalid update strategy.")),
         }
     }
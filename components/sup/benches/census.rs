@@ -0,0 +1,90 @@
+//! How long it takes a Supervisor on a large ring to take in a new rumor, and to tell which
+//! service groups it changed.
+//!
+//! Run with `cargo bench -p habitat_sup --bench census`.
+
+use criterion::{criterion_group,
+                criterion_main,
+                Criterion};
+use habitat_butterfly::{member::MemberList,
+                        rumor::{election::{Election as ElectionRumor,
+                                           ElectionUpdate as ElectionUpdateRumor},
+                                service::{Service as ServiceRumor,
+                                          SysInfo},
+                                service_config::ServiceConfig as ServiceConfigRumor,
+                                service_file::ServiceFile as ServiceFileRumor,
+                                RumorStore}};
+use habitat_core::{fs::CACHE_KEY_PATH,
+                   package::PackageIdent,
+                   service::ServiceGroup};
+use habitat_sup::census::CensusRing;
+
+/// The number of members running each service
+const MEMBERS: usize = 300;
+
+/// The number of service groups on the ring
+const GROUPS: usize = 20;
+
+struct Stores {
+    service:         RumorStore<ServiceRumor>,
+    election:        RumorStore<ElectionRumor>,
+    election_update: RumorStore<ElectionUpdateRumor>,
+    members:         MemberList,
+    service_config:  RumorStore<ServiceConfigRumor>,
+    service_file:    RumorStore<ServiceFileRumor>,
+}
+
+impl Stores {
+    fn update(&self, ring: &mut CensusRing) {
+        ring.update_from_rumors_rsr_mlr(&*CACHE_KEY_PATH,
+                                        &[],
+                                        &self.service,
+                                        &self.election,
+                                        &self.election_update,
+                                        &self.members,
+                                        &self.service_config,
+                                        &self.service_file);
+    }
+}
+
+fn rumor(member: usize, sg: &ServiceGroup) -> ServiceRumor {
+    let ident = PackageIdent::new("core", sg.service(), Some("1.0.0"), Some("20200101000000"));
+    ServiceRumor::new(format!("member-{}", member),
+                      &ident,
+                      sg.clone(),
+                      SysInfo::default(),
+                      None)
+}
+
+fn census_update(c: &mut Criterion) {
+    let groups = (0..GROUPS).map(|g| ServiceGroup::new(&format!("svc{}", g), "default", None))
+                            .collect::<Result<Vec<_>, _>>()
+                            .expect("valid service groups");
+    let stores = Stores { service:         RumorStore::default(),
+                          election:        RumorStore::default(),
+                          election_update: RumorStore::default(),
+                          members:         MemberList::new(),
+                          service_config:  RumorStore::default(),
+                          service_file:    RumorStore::default(), };
+    for sg in &groups {
+        for member in 0..MEMBERS {
+            stores.service.insert_rsw(rumor(member, sg));
+        }
+    }
+    let mut ring = CensusRing::new("member-0");
+    stores.update(&mut ring);
+
+    let mut changing = rumor(1, &groups[0]);
+    c.bench_function("census update for a rumor about one of 20 groups of 300 members",
+                     |b| {
+                         b.iter(|| {
+                              changing.incarnation += 1;
+                              stores.service.insert_rsw(changing.clone());
+                              stores.update(&mut ring);
+                              groups.iter().filter(|sg| ring.group_changed(sg)).count()
+                          })
+                     });
+}
+
+criterion_group!(benches, census_update);
+criterion_main!(benches);
@@ -0,0 +1,160 @@
+//! Loads a Supervisor's initial services from a service list published at a URL (`hab sup run
+//! --bootstrap-from`), so that a Supervisor baked into an image can pick up the services of its
+//! role when it starts.
+//!
+//! The list is fetched every time the Supervisor starts, and is only used once it has been
+//! verified. Either its BLAKE2b checksum must match `--bootstrap-checksum`, or it must be signed
+//! with `hab pkg sign` by a key already in the key cache. When both are available both are
+//! checked, and when the Supervisor has `--trusted-signer`s the list must be signed by one of
+//! them.
+
+use crate::{error::{Error,
+                    Result},
+            PRODUCT,
+            VERSION};
+use hab::cli::hab::svc;
+use habitat_common::outputln;
+use habitat_core::crypto::{artifact,
+                           hash,
+                           keys::parse_name_with_rev,
+                           HART_FORMAT_VERSION};
+use habitat_http_client::ApiClient;
+use habitat_sup_protocol::ctl::SvcLoad;
+use std::{convert::TryInto,
+          io::{Read,
+               Write},
+          path::Path,
+          result};
+use tempfile::NamedTempFile;
+
+static LOGKEY: &str = "BS";
+
+/// Fetch and verify the service list at `url`, returning a load request for each of its services.
+pub async fn fetch(url: &str,
+                   checksum: Option<&str>,
+                   trusted_signers: &[String],
+                   cache_key_path: &Path)
+                   -> Result<Vec<SvcLoad>> {
+    let failed = |reason: String| Error::BootstrapFailed(url.to_string(), reason);
+    let content = download(url).await.map_err(failed)?;
+    let list = verify(&content, checksum, trusted_signers, cache_key_path).map_err(failed)?;
+    let svc_loads = svc::svc_loads_from_str(&list).map_err(|e| failed(e.to_string()))?;
+    outputln!("Loading {} service(s) from {}", svc_loads.len(), url);
+    svc_loads.into_iter()
+             .map(|svc_load| -> Result<SvcLoad> {
+                 let mut msg: SvcLoad = svc_load.try_into()?;
+                 // The list describes what this Supervisor should run, whatever it ran before it
+                 // was restarted.
+                 msg.force = Some(true);
+                 Ok(msg)
+             })
+             .collect()
+}
+
+async fn download(url: &str) -> result::Result<Vec<u8>, String> {
+    let client = ApiClient::new(url, PRODUCT, VERSION, None).map_err(|e| e.to_string())?;
+    let resp = client.get("").send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+/// Returns the service list in `content` if it can be trusted.
+fn verify(content: &[u8],
+          checksum: Option<&str>,
+          trusted_signers: &[String],
+          cache_key_path: &Path)
+          -> result::Result<String, String> {
+    if let Some(expected) = checksum {
+        let computed = hash::hash_bytes(content);
+        if !computed.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!("its checksum is {}, not {}",
+                               computed,
+                               expected.trim()));
+        }
+    }
+    if content.starts_with(format!("{}\n", HART_FORMAT_VERSION).as_bytes()) {
+        return verify_signed(content, trusted_signers, cache_key_path);
+    }
+    if !trusted_signers.is_empty() {
+        return Err(format!("it must be signed by one of: {}",
+                           trusted_signers.join(", ")));
+    }
+    if checksum.is_none() {
+        return Err(String::from("it is not signed, and no --bootstrap-checksum was given"));
+    }
+    String::from_utf8(content.to_vec()).map_err(|e| e.to_string())
+}
+
+/// Verify a list signed with `hab pkg sign`, returning the list without its signature header.
+fn verify_signed(content: &[u8],
+                 trusted_signers: &[String],
+                 cache_key_path: &Path)
+                 -> result::Result<String, String> {
+    let mut signed = NamedTempFile::new().map_err(|e| e.to_string())?;
+    signed.write_all(content).map_err(|e| e.to_string())?;
+    let (signer, _) = artifact::verify(signed.path(), cache_key_path).map_err(|e| e.to_string())?;
+    let (origin, _) = parse_name_with_rev(&signer).map_err(|e| e.to_string())?;
+    if !trusted_signers.is_empty() && !trusted_signers.contains(&origin) {
+        return Err(format!("{} is not a trusted signer", origin));
+    }
+    debug!("Verified bootstrap service list signed by {}", signer);
+
+    let mut list = String::new();
+    artifact::get_archive_reader(signed.path()).map_err(|e| e.to_string())?
+                                               .read_to_string(&mut list)
+                                               .map_err(|e| e.to_string())?;
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::crypto::SigKeyPair;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const LIST: &str = "[[services]]\npkg_ident = \"core/redis\"\n";
+
+    fn signed_list(cache: &TempDir, origin: &str) -> Vec<u8> {
+        let pair = SigKeyPair::generate_pair_for_origin(origin);
+        pair.to_pair_files(cache.path()).unwrap();
+        let src = cache.path().join("services.toml");
+        let dst = cache.path().join("services.toml.signed");
+        fs::write(&src, LIST).unwrap();
+        artifact::sign(&src, &dst, &pair).unwrap();
+        fs::read(&dst).unwrap()
+    }
+
+    #[test]
+    fn unsigned_lists_need_a_matching_checksum() {
+        let cache = TempDir::new().expect("Could not create tempdir");
+        let checksum = hash::hash_bytes(LIST.as_bytes());
+        assert_eq!(verify(LIST.as_bytes(), Some(&checksum), &[], cache.path()).unwrap(),
+                   LIST);
+        assert!(verify(LIST.as_bytes(), None, &[], cache.path()).is_err());
+        assert!(verify(b"[[services]]\npkg_ident = \"core/nginx\"\n",
+                       Some(&checksum),
+                       &[],
+                       cache.path()).is_err());
+        assert!(verify(LIST.as_bytes(),
+                       Some(&checksum),
+                       &[String::from("core")],
+                       cache.path()).is_err());
+    }
+
+    #[test]
+    fn signed_lists_are_verified_against_the_key_cache() {
+        let cache = TempDir::new().expect("Could not create tempdir");
+        let content = signed_list(&cache, "core");
+        assert_eq!(verify(&content, None, &[], cache.path()).unwrap(), LIST);
+        assert_eq!(verify(&content, None, &[String::from("core")], cache.path()).unwrap(),
+                   LIST);
+        assert!(verify(&content, None, &[String::from("acme")], cache.path()).is_err());
+
+        let elsewhere = TempDir::new().expect("Could not create tempdir");
+        assert!(verify(&content, None, &[], elsewhere.path()).is_err());
+    }
+}
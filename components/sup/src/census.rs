@@ -1,4 +1,5 @@
-use crate::error::Error;
+use crate::{error::Error,
+            manager::commands};
 use habitat_butterfly::{member::{Health,
                                  Member,
                                  MemberList,
@@ -16,6 +17,10 @@ use habitat_common::outputln;
 use habitat_core::{self,
                    package::PackageIdent,
                    service::ServiceGroup};
+use habitat_sup_protocol::{ctl::{SignedRequest,
+                                 SvcFilePut,
+                                 SvcSetCfg},
+                           types};
 use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
@@ -36,6 +41,10 @@ pub type MemberId = String;
 #[derive(Debug, Serialize)]
 pub struct CensusRing {
     changed: bool,
+    /// The groups which changed with the last update, so that only the services whose template
+    /// data draws on them need to render it again.
+    #[serde(skip)]
+    changed_groups: HashSet<ServiceGroup>,
     census_groups: HashMap<ServiceGroup, CensusGroup>,
     local_member_id: MemberId,
     last_service_counter: usize,
@@ -55,6 +64,7 @@ impl CensusRing {
         where I: Into<MemberId>
     {
         CensusRing { changed: false,
+                     changed_groups: HashSet::new(),
                      census_groups: HashMap::new(),
                      local_member_id: local_member_id.into(),
                      last_service_counter: 0,
@@ -65,12 +75,16 @@ impl CensusRing {
                      last_service_file_counter: 0, }
     }
 
+    /// Configuration and files are only applied from requests signed by one of `trusted_signers`,
+    /// if there are any.
+    ///
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
     /// * `MemberList::entries` (read)
     #[allow(clippy::too_many_arguments)]
     pub fn update_from_rumors_rsr_mlr(&mut self,
                                       cache_key_path: &Path,
+                                      trusted_signers: &[String],
                                       service_rumors: &RumorStore<ServiceRumor>,
                                       election_rumors: &RumorStore<ElectionRumor>,
                                       election_update_rumors: &RumorStore<ElectionUpdateRumor>,
@@ -87,12 +101,18 @@ impl CensusRing {
            || (service_file_rumors.get_update_counter() > self.last_service_file_counter)
         {
             self.changed = true;
+            self.changed_groups.clear();
 
             self.populate_census_rsr_mlr(service_rumors, member_list);
             self.update_from_election_store_rsr(election_rumors);
             self.update_from_election_update_store_rsr(election_update_rumors);
-            self.update_from_service_config_rsr(cache_key_path, service_config_rumors);
-            self.update_from_service_files_rsr(cache_key_path, service_file_rumors);
+            self.update_from_service_config_rsr(cache_key_path,
+                                                trusted_signers,
+                                                service_config_rumors);
+            self.update_from_service_files_rsr(cache_key_path,
+                                               trusted_signers,
+                                               service_file_rumors);
+            self.remove_forgotten_groups_rsr(service_rumors, service_file_rumors);
 
             // Update our counters to reflect current state.
             self.last_membership_counter = member_list.get_update_counter();
@@ -103,9 +123,14 @@ impl CensusRing {
             self.last_service_file_counter = service_file_rumors.get_update_counter();
         } else {
             self.changed = false;
+            self.changed_groups.clear();
         }
     }
 
+    /// Indicates whether the census group for `sg` changed, or left the census, with the last
+    /// update. A rumor about one service group changes the census, but not the other groups in it.
+    pub fn group_changed(&self, sg: &ServiceGroup) -> bool { self.changed_groups.contains(sg) }
+
     pub fn census_group_for(&self, sg: &ServiceGroup) -> Option<&CensusGroup> {
         self.census_groups.get(sg)
     }
@@ -137,18 +162,25 @@ impl CensusRing {
         for (service_group, rumors) in service_rumors.lock_rsr().iter() {
             if let Ok(sg) = service_group_from_str(service_group) {
                 let local_member_id = Cow::from(&self.local_member_id);
-                let census_group = self.census_groups
-                                       .entry(sg.clone())
-                                       .or_insert_with(|| CensusGroup::new(sg, &local_member_id));
-                census_group.update_from_service_rumors(rumors);
+                let census_group =
+                    self.census_groups
+                        .entry(sg.clone())
+                        .or_insert_with(|| CensusGroup::new(sg.clone(), &local_member_id));
+                if census_group.update_from_service_rumors(rumors) {
+                    self.changed_groups.insert(sg);
+                }
             }
         }
 
+        let census_groups = &mut self.census_groups;
+        let changed_groups = &mut self.changed_groups;
         member_list.with_memberships_mlr(|Membership { member, health }| {
-                       for group in self.census_groups.values_mut() {
+                       for (sg, group) in census_groups.iter_mut() {
                            if let Some(census_member) = group.find_member_mut(&member.id) {
-                               census_member.update_from_member(&member);
-                               census_member.update_from_health(health);
+                               let member_changed = census_member.update_from_member(&member);
+                               if census_member.update_from_health(health) || member_changed {
+                                   changed_groups.insert(sg.clone());
+                               }
                            }
                        }
                        Ok(())
@@ -163,7 +195,9 @@ impl CensusRing {
             let election = rumors.get(ElectionRumor::const_id()).unwrap();
             if let Ok(sg) = service_group_from_str(service_group) {
                 if let Some(census_group) = self.census_groups.get_mut(&sg) {
-                    census_group.update_from_election_rumor(election);
+                    if census_group.update_from_election_rumor(election) {
+                        self.changed_groups.insert(sg);
+                    }
                 }
             }
         }
@@ -178,7 +212,9 @@ impl CensusRing {
             if let Ok(sg) = service_group_from_str(service_group) {
                 if let Some(census_group) = self.census_groups.get_mut(&sg) {
                     let election = rumors.get(ElectionUpdateRumor::const_id()).unwrap();
-                    census_group.update_from_election_update_rumor(election);
+                    if census_group.update_from_election_update_rumor(election) {
+                        self.changed_groups.insert(sg);
+                    }
                 }
             }
         }
@@ -188,13 +224,18 @@ impl CensusRing {
     /// * `RumorStore::list` (read)
     fn update_from_service_config_rsr(&mut self,
                                       cache_key_path: &Path,
+                                      trusted_signers: &[String],
                                       service_config_rumors: &RumorStore<ServiceConfigRumor>) {
         for (service_group, rumors) in service_config_rumors.lock_rsr().iter() {
             if let Ok(sg) = service_group_from_str(service_group) {
                 if let Some(service_config) = rumors.get(ServiceConfigRumor::const_id()) {
                     if let Some(census_group) = self.census_groups.get_mut(&sg) {
-                        census_group.update_from_service_config_rumor(cache_key_path,
-                                                                      service_config);
+                        if census_group.update_from_service_config_rumor(cache_key_path,
+                                                                         trusted_signers,
+                                                                         service_config)
+                        {
+                            self.changed_groups.insert(sg);
+                        }
                     }
                 }
             }
@@ -205,17 +246,46 @@ impl CensusRing {
     /// * `RumorStore::list` (read)
     fn update_from_service_files_rsr(&mut self,
                                      cache_key_path: &Path,
+                                     trusted_signers: &[String],
                                      service_file_rumors: &RumorStore<ServiceFileRumor>) {
         for (service_group, rumors) in service_file_rumors.lock_rsr().iter() {
             if let Ok(sg) = service_group_from_str(service_group) {
                 let local_member_id = Cow::from(&self.local_member_id);
-                let census_group = self.census_groups
-                                       .entry(sg.clone())
-                                       .or_insert_with(|| CensusGroup::new(sg, &local_member_id));
-                census_group.update_from_service_file_rumors(cache_key_path, rumors);
+                let census_group =
+                    self.census_groups
+                        .entry(sg.clone())
+                        .or_insert_with(|| CensusGroup::new(sg.clone(), &local_member_id));
+                if census_group.update_from_service_file_rumors(cache_key_path,
+                                                                trusted_signers,
+                                                                rumors)
+                {
+                    self.changed_groups.insert(sg);
+                }
             }
         }
     }
+
+    /// Groups which there are no longer any rumors of, say once their members' rumors have been
+    /// evicted from the rumor store, leave the census. They count as changed, so that services
+    /// bound to them render their templates without them.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    fn remove_forgotten_groups_rsr(&mut self,
+                                   service_rumors: &RumorStore<ServiceRumor>,
+                                   service_file_rumors: &RumorStore<ServiceFileRumor>) {
+        let mut known: HashSet<String> =
+            service_rumors.lock_rsr().iter().map(|(sg, _)| sg.clone()).collect();
+        known.extend(service_file_rumors.lock_rsr().iter().map(|(sg, _)| sg.clone()));
+        let changed_groups = &mut self.changed_groups;
+        self.census_groups.retain(|sg, _| {
+                              let remembered = known.contains(&sg.to_string());
+                              if !remembered {
+                                  changed_groups.insert(sg.clone());
+                              }
+                              remembered
+                          });
+    }
 }
 
 /// This is a proxy struct to represent what information we're writing to the dat file, and
@@ -297,20 +367,20 @@ impl From<ElectionStatusRumor> for ElectionStatus {
     }
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 pub struct ServiceFile {
     pub filename:    String,
     pub incarnation: u64,
     pub body:        Vec<u8>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct ServiceConfig {
     pub incarnation: u64,
     pub value:       toml::value::Table,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct CensusGroup {
     pub service_group:          ServiceGroup,
     pub election_status:        ElectionStatus,
@@ -323,6 +393,8 @@ pub struct CensusGroup {
     update_leader_id:      Option<MemberId>,
     changed_service_files: HashSet<String>,
     service_files:         HashMap<String, ServiceFile>,
+    /// The incarnation of each member's service rumor which the population reflects.
+    service_incarnations:  HashMap<MemberId, u64>,
 }
 
 impl CensusGroup {
@@ -336,7 +408,8 @@ impl CensusGroup {
                       update_leader_id:       None,
                       service_config:         None,
                       service_files:          HashMap::new(),
-                      changed_service_files:  HashSet::new(), }
+                      changed_service_files:  HashSet::new(),
+                      service_incarnations:   HashMap::new(), }
     }
 
     /// Returns the census member in the census ring for the running Supervisor.
@@ -369,6 +442,13 @@ impl CensusGroup {
             .filter(|cm| cm.alive() || cm.suspect())
     }
 
+    /// Same as `active_members`, but without members which are being
+    /// drained for maintenance. These are the members that should be
+    /// sent new work.
+    pub fn serving_members(&self) -> impl Iterator<Item = &CensusMember> {
+        self.active_members().filter(|cm| !cm.draining())
+    }
+
     /// Return references to all a `CensusGroup`'s `ServiceFiles`.
     pub fn service_files(&self) -> impl IntoIterator<Item = &ServiceFile> {
         self.service_files.values()
@@ -410,8 +490,24 @@ impl CensusGroup {
         None
     }
 
-    fn update_from_service_rumors(&mut self, rumors: &HashMap<String, ServiceRumor>) {
+    /// Returns whether the population changed.
+    fn update_from_service_rumors(&mut self, rumors: &HashMap<String, ServiceRumor>) -> bool {
+        // Members whose rumors are gone, such as departed members that have been forgotten, leave
+        // the census.
+        let population = self.population.len();
+        self.population
+            .retain(|member_id, _| rumors.contains_key(member_id));
+        self.service_incarnations
+            .retain(|member_id, _| rumors.contains_key(member_id));
+        let mut changed = self.population.len() != population;
         for (member_id, service_rumor) in rumors.iter() {
+            // A rumor only says something new at a new incarnation.
+            if self.service_incarnations.get(member_id) == Some(&service_rumor.incarnation) {
+                continue;
+            }
+            self.service_incarnations
+                .insert(member_id.to_string(), service_rumor.incarnation);
+            changed = true;
             // Yeah - we are ourself - we're alive.
             let is_self = member_id == &self.local_member_id;
             let member = self.population
@@ -424,10 +520,13 @@ impl CensusGroup {
                              });
             member.update_from_service_rumor(&self.service_group, service_rumor);
         }
+        changed
     }
 
-    fn update_from_election_rumor(&mut self, election: &ElectionRumor) {
-        self.leader_id = None;
+    /// Returns whether the election changed. The members' part in it follows from the leader and
+    /// the status of the election.
+    fn update_from_election_rumor(&mut self, election: &ElectionRumor) -> bool {
+        let previous = (self.leader_id.take(), self.election_status);
         for census_member in self.population.values_mut() {
             if census_member.update_from_election_rumor(election) {
                 self.leader_id = Some(census_member.member_id.clone());
@@ -444,10 +543,12 @@ impl CensusGroup {
                 self.election_status = ElectionStatus::ElectionFinished;
             }
         }
+        (&previous.0, previous.1) != (&self.leader_id, self.election_status)
     }
 
-    fn update_from_election_update_rumor(&mut self, election: &ElectionUpdateRumor) {
-        self.update_leader_id = None;
+    /// Returns whether the update election changed.
+    fn update_from_election_update_rumor(&mut self, election: &ElectionUpdateRumor) -> bool {
+        let previous = (self.update_leader_id.take(), self.update_election_status);
         for census_member in self.population.values_mut() {
             if census_member.update_from_election_update_rumor(election) {
                 self.update_leader_id = Some(census_member.member_id.clone());
@@ -464,29 +565,51 @@ impl CensusGroup {
                 self.update_election_status = ElectionStatus::ElectionFinished;
             }
         }
+        (&previous.0, previous.1) != (&self.update_leader_id, self.update_election_status)
     }
 
+    /// Returns whether the group's configuration changed.
     fn update_from_service_config_rumor(&mut self,
                                         cache_key_path: &Path,
-                                        service_config: &ServiceConfigRumor) {
+                                        trusted_signers: &[String],
+                                        service_config: &ServiceConfigRumor)
+                                        -> bool {
+        if let Some(ref current) = self.service_config {
+            if service_config.incarnation <= current.incarnation {
+                return false;
+            }
+        }
+        let signed = check_signed_request(trusted_signers,
+                                          cache_key_path,
+                                          service_config.signed_request.as_ref(),
+                                          |request: &SvcSetCfg| {
+                                              sets_config(request, service_config)
+                                          });
+        if let Err(reason) = signed {
+            warn!("Not applying configuration {} for {}: {}",
+                  service_config.incarnation, self.service_group, reason);
+            return false;
+        }
         match service_config.config(cache_key_path) {
             Ok(config) => {
-                if self.service_config.is_none()
-                   || service_config.incarnation > self.service_config.as_ref().unwrap().incarnation
-                {
-                    self.service_config = Some(ServiceConfig { incarnation:
-                                                                   service_config.incarnation,
-                                                               value:       config, });
-                }
+                self.service_config = Some(ServiceConfig { incarnation:
+                                                               service_config.incarnation,
+                                                           value:       config, });
+                true
+            }
+            Err(err) => {
+                warn!("{}", err);
+                false
             }
-            Err(err) => warn!("{}", err),
         }
     }
 
+    /// Returns whether any of the group's files changed.
     fn update_from_service_file_rumors(&mut self,
                                        cache_key_path: &Path,
+                                       trusted_signers: &[String],
                                        service_file_rumors: &HashMap<String, ServiceFileRumor>)
-    {
+                                       -> bool {
         self.changed_service_files.clear();
         for (_m_id, service_file_rumor) in service_file_rumors.iter() {
             let filename = service_file_rumor.filename.to_string();
@@ -494,24 +617,37 @@ impl CensusGroup {
                            .entry(filename.clone())
                            .or_insert_with(ServiceFile::default);
 
-            if service_file_rumor.incarnation > file.incarnation {
-                match service_file_rumor.body(cache_key_path) {
-                    Ok(body) => {
-                        self.changed_service_files.insert(filename.clone());
-                        file.filename = filename.clone();
-                        file.incarnation = service_file_rumor.incarnation;
-                        file.body = body;
-                    }
-                    Err(e) => {
-                        warn!("Cannot decrypt service file for {} {} {}: {}",
-                              self.service_group,
-                              service_file_rumor.filename,
-                              service_file_rumor.incarnation,
-                              e)
-                    }
+            if service_file_rumor.incarnation <= file.incarnation {
+                continue;
+            }
+            let signed = check_signed_request(trusted_signers,
+                                              cache_key_path,
+                                              service_file_rumor.signed_request.as_ref(),
+                                              |request: &SvcFilePut| {
+                                                  puts_file(request, service_file_rumor)
+                                              });
+            if let Err(reason) = signed {
+                warn!("Not applying service file for {} {} {}: {}",
+                      self.service_group, filename, service_file_rumor.incarnation, reason);
+                continue;
+            }
+            match service_file_rumor.body(cache_key_path) {
+                Ok(body) => {
+                    self.changed_service_files.insert(filename.clone());
+                    file.filename = filename.clone();
+                    file.incarnation = service_file_rumor.incarnation;
+                    file.body = body;
+                }
+                Err(e) => {
+                    warn!("Cannot decrypt service file for {} {} {}: {}",
+                          self.service_group,
+                          service_file_rumor.filename,
+                          service_file_rumor.incarnation,
+                          e)
                 }
             }
         }
+        !self.changed_service_files.is_empty()
     }
 
     fn find_member_mut(&mut self, member_id: &str) -> Option<&mut CensusMember> {
@@ -565,7 +701,7 @@ impl Serialize for CensusGroup {
 // User-facing documentation is available at
 // https://www.habitat.sh/docs/reference/#template-data; update that
 // as required.
-#[derive(Clone, Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
 pub struct CensusMember {
     pub member_id: MemberId,
     pub pkg: PackageIdent,
@@ -588,6 +724,9 @@ pub struct CensusMember {
     pub suspect: bool,
     pub confirmed: bool,
     pub departed: bool,
+    pub draining: bool,
+    /// Whether the member's health check for the service last came back other than critical
+    pub healthy: bool,
     // Maps must be represented last in a serializable struct for the current version of the toml
     // crate. Additionally, this deserialization method is required to correct any ordering issues
     // with the table being serialized - https://docs.rs/toml/0.4.0/toml/ser/fn.tables_last.html
@@ -608,6 +747,7 @@ impl CensusMember {
             Err(err) => warn!("Received a bad package ident from gossip data, err={}", err),
         };
         self.sys = rumor.sys.clone();
+        self.healthy = rumor.healthy;
         self.cfg = toml::from_slice(&rumor.cfg).unwrap_or_default();
     }
 
@@ -643,13 +783,24 @@ impl CensusMember {
         self.update_leader
     }
 
-    fn update_from_member(&mut self, member: &Member) {
-        self.sys.gossip_ip = member.address.to_string();
-        self.sys.gossip_port = u32::from(member.gossip_port);
+    /// Returns whether anything the member list says about the member changed.
+    fn update_from_member(&mut self, member: &Member) -> bool {
+        let gossip_ip = member.address.to_string();
+        let gossip_port = u32::from(member.gossip_port);
+        let changed = self.sys.gossip_ip != gossip_ip
+                      || self.sys.gossip_port != gossip_port
+                      || !self.persistent
+                      || self.draining != member.draining;
+        self.sys.gossip_ip = gossip_ip;
+        self.sys.gossip_port = gossip_port;
         self.persistent = true;
+        self.draining = member.draining;
+        changed
     }
 
-    fn update_from_health(&mut self, health: Health) {
+    /// Returns whether the member's health changed.
+    fn update_from_health(&mut self, health: Health) -> bool {
+        let previous = (self.alive, self.suspect, self.confirmed, self.departed);
         self.alive = false;
         self.suspect = false;
         self.confirmed = false;
@@ -660,6 +811,7 @@ impl CensusMember {
             Health::Confirmed => self.confirmed = true,
             Health::Departed => self.departed = true,
         }
+        previous != (self.alive, self.suspect, self.confirmed, self.departed)
     }
 
     /// Is this member currently considered to be alive or not?
@@ -670,6 +822,12 @@ impl CensusMember {
     pub fn confirmed(&self) -> bool { self.confirmed }
 
     pub fn departed(&self) -> bool { self.departed }
+
+    /// Is this member's Supervisor being drained for maintenance?
+    pub fn draining(&self) -> bool { self.draining }
+
+    /// Did this member's health check for the service last come back other than critical?
+    pub fn healthy(&self) -> bool { self.healthy }
 }
 
 /// This data structure just wraps the CensusMember and allows us to tweak the serialization logic.
@@ -696,7 +854,7 @@ impl<'a> Serialize for CensusMemberProxy<'a> {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("census_member", 24)?;
+        let mut strukt = serializer.serialize_struct("census_member", 26)?;
         strukt.serialize_field("member_id", &self.member_id)?;
         strukt.serialize_field("pkg", &self.pkg)?;
 
@@ -723,11 +881,71 @@ impl<'a> Serialize for CensusMemberProxy<'a> {
         strukt.serialize_field("suspect", &self.suspect)?;
         strukt.serialize_field("confirmed", &self.confirmed)?;
         strukt.serialize_field("departed", &self.departed)?;
+        strukt.serialize_field("draining", &self.draining)?;
+        strukt.serialize_field("healthy", &self.healthy)?;
         strukt.serialize_field("cfg", &self.cfg)?;
         strukt.end()
     }
 }
 
+/// Checks, when there are `trusted_signers`, that a configuration or file rumor carries the request
+/// which made its change, signed by one of them, and that the request `made` exactly the change
+/// gossiped, so that no member of the ring can forge or alter one. Without trusted signers, every
+/// change is applied.
+fn check_signed_request<T, F>(trusted_signers: &[String],
+                              cache_key_path: &Path,
+                              signed_request: Option<&Vec<u8>>,
+                              made: F)
+                              -> result::Result<(), String>
+    where T: SignedRequest,
+          F: FnOnce(&T) -> bool
+{
+    if trusted_signers.is_empty() {
+        return Ok(());
+    }
+    let request = match signed_request {
+        Some(bytes) => T::decode(bytes.as_slice()).map_err(|e| e.to_string())?,
+        None => return Err("it was not signed".to_string()),
+    };
+    let signature = request.signature()
+                           .ok_or_else(|| "it was not signed".to_string())?;
+    let signer = commands::checked_signer(trusted_signers,
+                                          cache_key_path,
+                                          signature,
+                                          &request.signed_bytes()).map_err(|e| e.to_string())?;
+    if made(&request) {
+        Ok(())
+    } else {
+        Err(format!("it differs from the request {} signed", signer))
+    }
+}
+
+/// Whether `request` set the configuration gossiped in `rumor`.
+fn sets_config(request: &SvcSetCfg, rumor: &ServiceConfigRumor) -> bool {
+    request.cfg.as_ref() == Some(&rumor.config)
+    && request.version == Some(rumor.incarnation)
+    && request.is_encrypted.unwrap_or(false) == rumor.encrypted
+    && is_requested_for(request.service_group.clone(), &rumor.service_group)
+}
+
+/// Whether `request` uploaded the file gossiped in `rumor`.
+fn puts_file(request: &SvcFilePut, rumor: &ServiceFileRumor) -> bool {
+    request.content.as_ref() == Some(&rumor.body)
+    && request.filename.as_ref() == Some(&rumor.filename)
+    && request.version == Some(rumor.incarnation)
+    && request.is_encrypted.unwrap_or(false) == rumor.encrypted
+    && is_requested_for(request.service_group.clone(), &rumor.service_group)
+}
+
+/// Whether a request for `requested` changed `sg`. The Supervisor which took the request scopes
+/// it to its own organization if it names none.
+fn is_requested_for(requested: Option<types::ServiceGroup>, sg: &ServiceGroup) -> bool {
+    requested.map_or(false, |requested| {
+                 let requested: ServiceGroup = requested.into();
+                 requested.in_org(sg.org()) == *sg
+             })
+}
+
 fn service_group_from_str(sg: &str) -> Result<ServiceGroup, habitat_core::Error> {
     ServiceGroup::from_str(sg).map_err(|e| {
                                   outputln!("Malformed service group; cannot populate \
@@ -751,9 +969,12 @@ mod tests {
                                     service_config::ServiceConfig as ServiceConfigRumor,
                                     service_file::ServiceFile as ServiceFileRumor,
                                     RumorStore}};
-    use habitat_core::{fs::CACHE_KEY_PATH,
+    use habitat_core::{crypto::SigKeyPair,
+                       fs::CACHE_KEY_PATH,
                        package::ident::PackageIdent,
                        service::ServiceGroup};
+    use prost::Message;
+    use tempfile::TempDir;
 
     #[test]
     fn update_from_rumors() {
@@ -774,6 +995,235 @@ mod tests {
         assert_eq!(members.next().unwrap().member_id, "member-b");
     }
 
+    #[test]
+    fn members_leave_the_census_with_their_service_rumors() {
+        let sg = ServiceGroup::new("shield", "one", None).unwrap();
+        let ident = PackageIdent::new("starkandwayne", "shield", Some("0.10.4"), None);
+        let mut rumors = HashMap::new();
+        for member_id in &["member-a", "member-b"] {
+            rumors.insert(member_id.to_string(),
+                          ServiceRumor::new(member_id.to_string(),
+                                            &ident,
+                                            sg.clone(),
+                                            SysInfo::default(),
+                                            None));
+        }
+        let mut group = CensusGroup::new(sg, "member-b");
+        group.update_from_service_rumors(&rumors);
+        assert_eq!(group.members().count(), 2);
+
+        rumors.remove("member-a");
+        group.update_from_service_rumors(&rumors);
+        let members = group.members()
+                           .map(|member| member.member_id.as_str())
+                           .collect::<Vec<_>>();
+        assert_eq!(members, vec!["member-b"]);
+    }
+
+    #[test]
+    fn only_groups_with_new_rumors_are_changed() {
+        let ident = PackageIdent::new("starkandwayne", "shield", Some("0.10.4"), None);
+        let sg_one = ServiceGroup::new("shield", "one", None).unwrap();
+        let sg_two = ServiceGroup::new("shield", "two", None).unwrap();
+        let service_store: RumorStore<ServiceRumor> = RumorStore::default();
+        let election_store: RumorStore<ElectionRumor> = RumorStore::default();
+        let election_update_store: RumorStore<ElectionUpdateRumor> = RumorStore::default();
+        let member_list = MemberList::new();
+        let service_config_store: RumorStore<ServiceConfigRumor> = RumorStore::default();
+        let service_file_store: RumorStore<ServiceFileRumor> = RumorStore::default();
+        let mut ring = CensusRing::new("member-a".to_string());
+        let update = |ring: &mut CensusRing| {
+            ring.update_from_rumors_rsr_mlr(&*CACHE_KEY_PATH,
+                                            &[],
+                                            &service_store,
+                                            &election_store,
+                                            &election_update_store,
+                                            &member_list,
+                                            &service_config_store,
+                                            &service_file_store)
+        };
+        let rumor = |member_id: &str, sg: &ServiceGroup| {
+            ServiceRumor::new(member_id.to_string(),
+                              &ident,
+                              sg.clone(),
+                              SysInfo::default(),
+                              None)
+        };
+
+        service_store.insert_rsw(rumor("member-a", &sg_one));
+        service_store.insert_rsw(rumor("member-a", &sg_two));
+        update(&mut ring);
+        assert!(ring.changed());
+        assert!(ring.group_changed(&sg_one));
+        assert!(ring.group_changed(&sg_two));
+
+        service_store.insert_rsw(rumor("member-b", &sg_two));
+        update(&mut ring);
+        assert!(ring.changed());
+        assert!(!ring.group_changed(&sg_one));
+        assert!(ring.group_changed(&sg_two));
+
+        update(&mut ring);
+        assert!(!ring.changed());
+        assert!(!ring.group_changed(&sg_two));
+
+        // Members leaving a group change it too, while members joining the ring outside of any
+        // group change none.
+        service_store.remove_rsw(&sg_two.to_string(), "member-b");
+        member_list.insert_mlw(Member::default(), Health::Alive);
+        update(&mut ring);
+        assert!(ring.changed());
+        assert!(!ring.group_changed(&sg_one));
+        assert!(ring.group_changed(&sg_two));
+        assert_eq!(ring.census_group_for(&sg_two).unwrap().members().count(), 1);
+    }
+
+    #[test]
+    fn identically_named_groups_of_other_organizations_stay_separate() {
+        let ident = PackageIdent::new("starkandwayne", "shield", Some("0.10.4"), None);
+        let acme = ServiceGroup::new("shield", "one", Some("acme")).unwrap();
+        let other = ServiceGroup::new("shield", "one", Some("other")).unwrap();
+        let service_store: RumorStore<ServiceRumor> = RumorStore::default();
+        let service_config_store: RumorStore<ServiceConfigRumor> = RumorStore::default();
+        let mut ring = CensusRing::new("member-a".to_string());
+
+        service_store.insert_rsw(ServiceRumor::new("member-a".to_string(),
+                                                   &ident,
+                                                   acme.clone(),
+                                                   SysInfo::default(),
+                                                   None));
+        service_store.insert_rsw(ServiceRumor::new("member-b".to_string(),
+                                                   &ident,
+                                                   other.clone(),
+                                                   SysInfo::default(),
+                                                   None));
+        service_config_store.insert_rsw(ServiceConfigRumor::new("member-a",
+                                                                acme.clone(),
+                                                                b"port = 1".to_vec()));
+        ring.update_from_rumors_rsr_mlr(&*CACHE_KEY_PATH,
+                                        &[],
+                                        &service_store,
+                                        &RumorStore::default(),
+                                        &RumorStore::default(),
+                                        &MemberList::new(),
+                                        &service_config_store,
+                                        &RumorStore::default());
+
+        let acme_group = ring.census_group_for(&acme).unwrap();
+        let members = acme_group.members()
+                                .map(|member| member.member_id.as_str())
+                                .collect::<Vec<_>>();
+        assert_eq!(members, vec!["member-a"]);
+        assert!(acme_group.service_config.is_some());
+        let other_group = ring.census_group_for(&other).unwrap();
+        let members = other_group.members()
+                                 .map(|member| member.member_id.as_str())
+                                 .collect::<Vec<_>>();
+        assert_eq!(members, vec!["member-b"]);
+        assert!(other_group.service_config.is_none());
+        assert!(ring.census_group_for(&ServiceGroup::new("shield", "one", None).unwrap())
+                    .is_none());
+    }
+
+    /// A config rumor for `sg`, gossiping `config` and carrying a request to set `signed_config`
+    /// signed by `pair`.
+    fn signed_config_rumor(pair: &SigKeyPair,
+                           sg: &ServiceGroup,
+                           config: &str,
+                           signed_config: &str)
+                           -> ServiceConfigRumor {
+        let mut request = SvcSetCfg { service_group: Some(sg.clone().into()),
+                                      cfg: Some(signed_config.as_bytes().to_vec()),
+                                      version: Some(1),
+                                      ..Default::default() };
+        request.sign(pair, "127.0.0.1:9632").unwrap();
+        let mut signed_request = Vec::new();
+        request.encode(&mut signed_request).unwrap();
+        let mut rumor = ServiceConfigRumor::new("member-a", sg.clone(), config.as_bytes().to_vec());
+        rumor.incarnation = 1;
+        rumor.signed_request = Some(signed_request);
+        rumor
+    }
+
+    #[test]
+    fn only_configuration_signed_by_a_trusted_signer_is_applied() {
+        let cache = TempDir::new().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        pair.to_pair_files(cache.path()).unwrap();
+        let trusted = vec!["acme".to_string()];
+        let sg = ServiceGroup::new("shield", "one", None).unwrap();
+        let mut group = CensusGroup::new(sg.clone(), "member-b");
+
+        let mut unsigned = signed_config_rumor(&pair, &sg, "port = 1", "port = 1");
+        unsigned.signed_request = None;
+        assert!(!group.update_from_service_config_rumor(cache.path(), &trusted, &unsigned));
+        let altered = signed_config_rumor(&pair, &sg, "port = 2", "port = 1");
+        assert!(!group.update_from_service_config_rumor(cache.path(), &trusted, &altered));
+        let signed = signed_config_rumor(&pair, &sg, "port = 1", "port = 1");
+        assert!(!group.update_from_service_config_rumor(cache.path(),
+                                                        &["other".to_string()],
+                                                        &signed));
+        assert!(group.service_config.is_none());
+
+        assert!(group.update_from_service_config_rumor(cache.path(), &trusted, &signed));
+        assert_eq!(group.service_config.as_ref().unwrap().incarnation, 1);
+
+        // Without trusted signers, configuration is applied whether it is signed or not.
+        unsigned.incarnation = 2;
+        assert!(group.update_from_service_config_rumor(cache.path(), &[], &unsigned));
+    }
+
+    #[test]
+    fn signed_configuration_applies_to_the_organization_it_was_gossiped_in() {
+        let cache = TempDir::new().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        pair.to_pair_files(cache.path()).unwrap();
+        let trusted = vec!["acme".to_string()];
+        let requested = ServiceGroup::new("shield", "one", None).unwrap();
+        let gossiped = ServiceGroup::new("shield", "one", Some("acme-corp")).unwrap();
+        let other_org = ServiceGroup::new("shield", "one", Some("other-corp")).unwrap();
+
+        let mut rumor = signed_config_rumor(&pair, &requested, "port = 1", "port = 1");
+        rumor.service_group = gossiped.clone();
+        let mut group = CensusGroup::new(gossiped, "member-b");
+        assert!(group.update_from_service_config_rumor(cache.path(), &trusted, &rumor));
+
+        let mut rumor = signed_config_rumor(&pair, &other_org, "port = 1", "port = 1");
+        rumor.service_group = group.service_group.clone();
+        let mut group = CensusGroup::new(rumor.service_group.clone(), "member-b");
+        assert!(!group.update_from_service_config_rumor(cache.path(), &trusted, &rumor));
+    }
+
+    #[test]
+    fn only_files_uploaded_by_a_trusted_signer_are_applied() {
+        let cache = TempDir::new().unwrap();
+        let pair = SigKeyPair::generate_pair_for_origin("acme");
+        pair.to_pair_files(cache.path()).unwrap();
+        let trusted = vec!["acme".to_string()];
+        let sg = ServiceGroup::new("shield", "one", None).unwrap();
+        let mut request = SvcFilePut { service_group: Some(sg.clone().into()),
+                                       content: Some(b"secret".to_vec()),
+                                       filename: Some("shield.conf".to_string()),
+                                       version: Some(1),
+                                       ..Default::default() };
+        request.sign(&pair, "127.0.0.1:9632").unwrap();
+        let mut signed_request = Vec::new();
+        request.encode(&mut signed_request).unwrap();
+        let mut rumor =
+            ServiceFileRumor::new("member-a", sg.clone(), "other.conf", b"secret".to_vec());
+        rumor.incarnation = 1;
+        rumor.signed_request = Some(signed_request);
+        let mut rumors = HashMap::new();
+        rumors.insert("member-a".to_string(), rumor);
+        let mut group = CensusGroup::new(sg, "member-b");
+
+        assert!(!group.update_from_service_file_rumors(cache.path(), &trusted, &rumors));
+
+        rumors.get_mut("member-a").unwrap().filename = "shield.conf".to_string();
+        assert!(group.update_from_service_file_rumors(cache.path(), &trusted, &rumors));
+        assert_eq!(group.changed_service_files()[0].body, b"secret".to_vec());
+    }
+
     #[test]
     fn census_ring_proxy_conforms_to_the_schema() {
         let (ring, ..) = test_census_ring();
@@ -842,6 +1292,7 @@ mod tests {
         let service_file_store: RumorStore<ServiceFileRumor> = RumorStore::default();
         let mut ring = CensusRing::new("member-b".to_string());
         ring.update_from_rumors_rsr_mlr(&*CACHE_KEY_PATH,
+                                        &[],
                                         &service_store,
                                         &election_store,
                                         &election_update_store,
@@ -877,6 +1328,8 @@ mod tests {
                        suspect: health == Health::Suspect,
                        confirmed: health == Health::Confirmed,
                        departed: health == Health::Departed,
+                       draining: false,
+                       healthy: true,
                        cfg: toml::value::Table::new() }
     }
 
@@ -903,6 +1356,30 @@ mod tests {
         assert!(active_members.next().is_none());
     }
 
+    #[test]
+    fn serving_members_leaves_out_draining_members() {
+        let mut draining = test_census_member("draining-one", Health::Alive);
+        draining.draining = true;
+        let population = vec![test_census_member("live-one", Health::Alive),
+                              draining,
+                              test_census_member("departed-one", Health::Departed),];
+
+        let sg: ServiceGroup =
+            "test-service.default".parse()
+                                  .expect("This should be a valid service group");
+
+        let mut census_group = CensusGroup::new(sg, &"live-one".to_string());
+        for member in population {
+            census_group.population
+                        .insert(member.member_id.clone(), member);
+        }
+
+        assert_eq!(census_group.active_members().count(), 2);
+        let mut serving_members = census_group.serving_members();
+        assert_eq!(serving_members.next().unwrap().member_id, "live-one");
+        assert!(serving_members.next().is_none());
+    }
+
     fn assert_eq_member_ids(cm: Option<&CensusMember>, id: Option<&str>) {
         assert_eq!(cm.map(|cm| cm.member_id.as_str()), id);
     }
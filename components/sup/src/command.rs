@@ -1,3 +1,4 @@
 //! The CLI commands.
 
 pub mod shell;
+pub mod simulate;
@@ -0,0 +1,92 @@
+//! Simulates a gossip network of many Supervisors in this process, on the loopback network, and
+//! reports how long it takes membership to converge, a rumor to reach every member and an
+//! election to finish. See `habitat_butterfly::simulation`.
+
+use crate::error::Result;
+use hab::cli::hab::sup::SupSimulate;
+use habitat_butterfly::{server::{timing::Timing,
+                                 NetworkConditions},
+                        simulation::SimulatedNetwork};
+use habitat_common::outputln;
+use habitat_core::service::ServiceGroup;
+use std::{thread,
+          time::{Duration,
+                 Instant}};
+
+/// Our output key
+static LOGKEY: &str = "SM";
+
+/// How often to check whether the network has reached the state being waited for
+const POLL: Duration = Duration::from_millis(250);
+
+/// Runs the simulation `simulate` describes. The members keep running until the process exits.
+pub fn run(simulate: SupSimulate) -> Result<()> {
+    let SupSimulate { members,
+                      loss,
+                      latency,
+                      partitions,
+                      partition_for,
+                      timeout,
+                      port, } = simulate;
+    let timeout = Duration::from(timeout);
+
+    outputln!("Starting {} members on ports {} and up", members, port);
+    let mut net = SimulatedNetwork::start_rsw_imlw_mlw_smw_rhw(members, port, &Timing::default())?;
+    net.set_conditions_sncw(NetworkConditions { loss:    loss / 100.0,
+                                                latency: Duration::from_millis(latency), });
+    if !report("Membership converged", timeout, || net.converged_mlr()) {
+        return Ok(());
+    }
+
+    let service_group = ServiceGroup::new("simulation", "default", None)?;
+    if let Some(rumor) = net.spread_rumor_rsw_rhw(&service_group) {
+        if !report("A rumor reached every member", timeout, || {
+            net.count_with_rumor_rsr(&rumor) == net.len()
+        }) {
+            outputln!("The rumor reached {} of {} members",
+                      net.count_with_rumor_rsr(&rumor),
+                      net.len());
+        }
+    }
+
+    net.start_election_rsw_mlw_rhw_msr(&service_group);
+    if report("Every member agreed on an elected leader", timeout, || {
+        net.elected_rsr(&service_group).is_some()
+    }) {
+        if let Some(leader) = net.elected_rsr(&service_group) {
+            outputln!("{} was elected the leader of {}", leader, service_group);
+        }
+    }
+
+    if let Some(count) = partitions {
+        let partitioned = Instant::now();
+        net.partition_sblw(count);
+        report(&format!("Every member noticed the split into {} partitions", count),
+               timeout,
+               || net.converged_mlr());
+        let partition_for = Duration::from(partition_for);
+        if let Some(remaining) = partition_for.checked_sub(partitioned.elapsed()) {
+            thread::sleep(remaining);
+        }
+        net.heal_sblw();
+        report("Membership converged after the partitions healed",
+               timeout,
+               || net.converged_mlr());
+    }
+    Ok(())
+}
+
+/// Waits up to `timeout` for `condition`, printing how long it took for `what` to happen, or that
+/// it didn't. Returns whether it happened.
+fn report(what: &str, timeout: Duration, condition: impl Fn() -> bool) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        if condition() {
+            outputln!("{} in {:.1}s", what, started.elapsed().as_secs_f64());
+            return true;
+        }
+        thread::sleep(POLL);
+    }
+    outputln!("{}: not within {}s", what, timeout.as_secs());
+    false
+}
@@ -19,12 +19,23 @@ use habitat_common::{output::{self,
                               StructuredOutput},
                      ui::UIWriter,
                      PROGRAM_NAME};
-use std::{fmt,
+use habitat_core::{clock,
+                   crypto};
+use habitat_sup_protocol::ctl::CtlSessionOpen;
+use parking_lot::Mutex;
+use std::{collections::HashMap,
+          fmt,
           fs::{self,
                File},
           io::{self,
                Write},
-          path::Path};
+          mem,
+          net::SocketAddr,
+          path::Path,
+          sync::Arc,
+          time::{Duration,
+                 Instant,
+                 UNIX_EPOCH}};
 use termcolor::{Color,
                 ColorSpec,
                 StandardStream,
@@ -34,11 +45,42 @@ use termcolor::{Color,
 pub const REQ_TIMEOUT: u64 = 10_000;
 static LOGKEY: &str = "AG";
 
+habitat_core::env_config_duration!(/// How long a ctl gateway session token is accepted for
+                                   /// after it is given out
+                                   CtlSessionTtl,
+                                   HAB_CTL_SESSION_TTL_SECS => from_secs,
+                                   Duration::from_secs(15 * 60));
+
+/// How far the timestamp of a request for a session token may be from the Supervisor's clock
+const SESSION_OPEN_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// How far the time a request was signed at may be from the Supervisor's clock
+const SIGNED_REQUEST_SKEW: Duration = Duration::from_secs(5 * 60);
+
 /// The control gateway secret should only be readable by the
 /// Supervisor process
 #[cfg(not(windows))]
 pub const CTL_SECRET_PERMISSIONS: u32 = 0o600;
 
+/// How a client authenticated with the `CtlGateway`. Every client shares the secret key, so this
+/// says how the client was let in, not who it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtlAuthentication {
+    /// By sending the secret key in its handshake.
+    SecretKey,
+    /// With a session token, given out to a client which proved it knows the secret key.
+    Session,
+}
+
+impl fmt::Display for CtlAuthentication {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CtlAuthentication::SecretKey => write!(f, "secret-key"),
+            CtlAuthentication::Session => write!(f, "session"),
+        }
+    }
+}
+
 /// Used by modules outside of the CtlGateway for seamlessly replying to transactional messages.
 /// This type is used in functions which can be called by the CtlGateway such as
 /// [`Manager::service_load`] and [`Manager::service_unload`].
@@ -65,6 +107,11 @@ pub struct CtlRequest {
     transaction:        Option<habitat_sup_protocol::codec::SrvTxn>,
     current_color_spec: ColorSpec,
     is_new_line:        bool,
+    /// The address of the client which sent the request. It is only
+    /// where the request came from, not who sent it.
+    peer_addr:          Option<SocketAddr>,
+    /// How the client which sent the request authenticated.
+    authentication:     Option<CtlAuthentication>,
 }
 
 impl CtlRequest {
@@ -76,9 +123,19 @@ impl CtlRequest {
         CtlRequest { tx: Some(tx),
                      transaction,
                      current_color_spec: ColorSpec::new(),
-                     is_new_line: true }
+                     is_new_line: true,
+                     peer_addr: None,
+                     authentication: None }
     }
 
+    /// The address of the client which sent the request, or `None` for
+    /// a bare request.
+    pub fn peer_addr(&self) -> Option<SocketAddr> { self.peer_addr }
+
+    /// How the client which sent the request authenticated, or `None`
+    /// for a bare request.
+    pub fn authentication(&self) -> Option<CtlAuthentication> { self.authentication }
+
     /// Reply to the transaction with the given message but indicate to the receiver that this is
     /// not the final message for the transaction.
     pub fn reply_partial<T>(&mut self, msg: T)
@@ -268,18 +325,190 @@ pub fn readgen_secret_key<T>(sup_root: T) -> Result<String>
     {
         Ok(out)
     } else {
-        let secret_key_path = habitat_sup_protocol::secret_key_path(sup_root);
-        {
-            let mut f = File::create(&secret_key_path)?;
-            habitat_sup_protocol::generate_secret_key(&mut out);
-            f.write_all(out.as_bytes())?;
-            f.sync_all()?;
-        }
-        set_permissions(&secret_key_path)?;
+        habitat_sup_protocol::generate_secret_key(&mut out);
+        write_secret_key(sup_root, &out)?;
         Ok(out)
     }
 }
 
+/// Writes `secret_key` to disk as the secret used to authenticate with the `CtlGateway`,
+/// replacing any that was there.
+pub fn write_secret_key<T>(sup_root: T, secret_key: &str) -> Result<()>
+    where T: AsRef<Path>
+{
+    let secret_key_path = habitat_sup_protocol::secret_key_path(sup_root);
+    {
+        let mut f = File::create(&secret_key_path)?;
+        f.write_all(secret_key.as_bytes())?;
+        f.sync_all()?;
+    }
+    set_permissions(&secret_key_path)?;
+    Ok(())
+}
+
+/// How a client's secret key compares with the one the `CtlGateway` authenticates with.
+#[derive(Debug, PartialEq)]
+pub enum SecretKeyMatch {
+    Current,
+    /// The client has the secret key which was rotated out, which is only accepted for the
+    /// remaining grace period.
+    Rotated(Duration),
+    Mismatch,
+}
+
+/// The secret key clients of the `CtlGateway` authenticate with. Clones share the same key.
+///
+/// Once the key is rotated, the previous one is still accepted for a grace period, so that
+/// clients can be moved over to the new key without being locked out.
+#[derive(Clone)]
+pub struct CtlSecret(Arc<Mutex<CtlSecretInner>>);
+
+struct CtlSecretInner {
+    current:  String,
+    /// The rotated out key, and when it stops being accepted
+    previous: Option<(String, Instant)>,
+}
+
+impl CtlSecret {
+    pub fn new(secret_key: String) -> Self {
+        CtlSecret(Arc::new(Mutex::new(CtlSecretInner { current:  secret_key,
+                                                       previous: None, })))
+    }
+
+    /// Compares `candidate` with the secret key, in constant time.
+    pub fn check(&self, candidate: &str) -> SecretKeyMatch {
+        self.check_by(|secret_key| crypto::secure_eq(candidate, secret_key))
+    }
+
+    /// Finds which secret key, if any, `matches` accepts. It must compare them in constant time.
+    pub fn check_by(&self, matches: impl Fn(&str) -> bool) -> SecretKeyMatch {
+        let inner = self.0.lock();
+        if matches(&inner.current) {
+            return SecretKeyMatch::Current;
+        }
+        match &inner.previous {
+            Some((previous, expires)) if matches(previous) => {
+                match expires.checked_duration_since(clock::instant()) {
+                    Some(remaining) if remaining > Duration::from_secs(0) => {
+                        SecretKeyMatch::Rotated(remaining)
+                    }
+                    _ => SecretKeyMatch::Mismatch,
+                }
+            }
+            _ => SecretKeyMatch::Mismatch,
+        }
+    }
+
+    /// Replaces the secret key with `secret_key`, accepting the one it replaces for
+    /// `grace_period` more. A key which was already rotated out stops being accepted.
+    pub fn rotate(&self, secret_key: String, grace_period: Duration) {
+        let mut inner = self.0.lock();
+        let previous = mem::replace(&mut inner.current, secret_key);
+        inner.previous = Some((previous, clock::instant() + grace_period));
+    }
+}
+
+/// The session tokens given out to clients of the `CtlGateway`, each of which authenticates them
+/// in place of the secret key until it expires or is revoked. Clones share the same sessions.
+#[derive(Clone, Default)]
+pub struct CtlSessions(Arc<Mutex<CtlSessionsInner>>);
+
+#[derive(Default)]
+struct CtlSessionsInner {
+    /// When each token stops being accepted
+    tokens: HashMap<String, Instant>,
+    /// The nonces of the requests which opened sessions, and when they can be forgotten
+    nonces: HashMap<String, Instant>,
+}
+
+impl CtlSessions {
+    /// Opens a session for a client whose `request` proved it knows the secret key, returning
+    /// its token and how long it lasts. A session lasts no longer than `secret_expires_in`, if
+    /// the key it was opened with has been rotated out.
+    ///
+    /// Returns `None` if the request was made too long ago, or its nonce was used before, so
+    /// that a recorded request can't be replayed.
+    pub fn open(&self,
+                request: &CtlSessionOpen,
+                secret_expires_in: Option<Duration>)
+                -> Option<(String, Duration)> {
+        let timestamp = UNIX_EPOCH + Duration::from_secs(request.timestamp.unwrap_or_default());
+        let skew = match clock::now().duration_since(timestamp) {
+            Ok(skew) => skew,
+            Err(e) => e.duration(),
+        };
+        let nonce = request.nonce.clone().unwrap_or_default();
+        let now = clock::instant();
+        let mut inner = self.0.lock();
+        inner.tokens.retain(|_, expires| *expires > now);
+        inner.nonces.retain(|_, forget_at| *forget_at > now);
+        if skew > SESSION_OPEN_SKEW || nonce.is_empty() || inner.nonces.contains_key(&nonce) {
+            return None;
+        }
+        // Past this, the request's timestamp is too far off for it to be accepted anyway.
+        inner.nonces.insert(nonce, now + 2 * SESSION_OPEN_SKEW);
+
+        let ttl = Duration::from(CtlSessionTtl::configured_value());
+        let ttl = secret_expires_in.map_or(ttl, |remaining| remaining.min(ttl));
+        let token = habitat_sup_protocol::generate_session_token();
+        inner.tokens.insert(token.clone(), now + ttl);
+        Some((token, ttl))
+    }
+
+    /// Whether `token` is that of a session which hasn't expired or been revoked. Every token
+    /// is compared, in constant time, so that how long this takes gives nothing away.
+    pub fn check(&self, token: &str) -> bool {
+        let now = clock::instant();
+        self.0
+            .lock()
+            .tokens
+            .iter()
+            .filter(|(_, expires)| **expires > now)
+            .fold(false, |found, (candidate, _)| {
+                crypto::secure_eq(candidate, token) || found
+            })
+    }
+
+    /// Revokes every session, returning how many had yet to expire.
+    pub fn revoke_all(&self) -> usize {
+        let now = clock::instant();
+        self.0
+            .lock()
+            .tokens
+            .drain()
+            .filter(|(_, expires)| *expires > now)
+            .count()
+    }
+}
+
+/// The nonces of the signed requests the `CtlGateway` has taken, so that none is taken twice.
+/// Clones share the same nonces.
+#[derive(Clone, Default)]
+pub struct SignedRequestNonces(Arc<Mutex<HashMap<String, Instant>>>);
+
+impl SignedRequestNonces {
+    /// Takes the nonce of a request signed at `issued_at`, in seconds since the UNIX epoch.
+    ///
+    /// Returns `false` if the request was signed too long ago, or its nonce was used before, so
+    /// that a recorded request can't be replayed.
+    pub fn take(&self, issued_at: u64, nonce: &str) -> bool {
+        let issued_at = UNIX_EPOCH + Duration::from_secs(issued_at);
+        let skew = match clock::now().duration_since(issued_at) {
+            Ok(skew) => skew,
+            Err(e) => e.duration(),
+        };
+        let now = clock::instant();
+        let mut nonces = self.0.lock();
+        nonces.retain(|_, forget_at| *forget_at > now);
+        if skew > SIGNED_REQUEST_SKEW || nonce.is_empty() || nonces.contains_key(nonce) {
+            return false;
+        }
+        // Past this, the request's signing time is too far off for it to be taken anyway.
+        nonces.insert(nonce.to_string(), now + 2 * SIGNED_REQUEST_SKEW);
+        true
+    }
+}
+
 #[cfg(not(windows))]
 fn set_permissions<T: AsRef<Path>>(path: T) -> habitat_core::error::Result<()> {
     use habitat_core::util::posix_perm;
@@ -293,3 +522,87 @@ fn set_permissions<T: AsRef<Path>>(path: T) -> habitat_core::error::Result<()> {
 
     win_perm::harden_path(path.as_ref())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::clock::ManualClock;
+
+    #[test]
+    fn rotated_secret_key_is_accepted_until_its_grace_period_ends() {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        let secret = CtlSecret::new("first".to_string());
+        assert_eq!(secret.check("first"), SecretKeyMatch::Current);
+
+        secret.rotate("second".to_string(), Duration::from_secs(300));
+        assert_eq!(secret.check("second"), SecretKeyMatch::Current);
+        assert_eq!(secret.check("first"),
+                   SecretKeyMatch::Rotated(Duration::from_secs(300)));
+        clock.advance(Duration::from_secs(299));
+        assert_eq!(secret.check("first"),
+                   SecretKeyMatch::Rotated(Duration::from_secs(1)));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(secret.check("first"), SecretKeyMatch::Mismatch);
+
+        secret.rotate("third".to_string(), Duration::from_secs(300));
+        assert_eq!(secret.check("first"), SecretKeyMatch::Mismatch);
+        assert_eq!(secret.check("second"),
+                   SecretKeyMatch::Rotated(Duration::from_secs(300)));
+        assert_eq!(secret.check("bogus"), SecretKeyMatch::Mismatch);
+    }
+
+    #[test]
+    fn session_tokens_last_until_they_expire_or_are_revoked() {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        let sessions = CtlSessions::default();
+        let request = CtlSessionOpen::new("seekrit");
+
+        let (token, ttl) = sessions.open(&request, None).unwrap();
+        assert_eq!(ttl, Duration::from(CtlSessionTtl::default()));
+        assert!(sessions.check(&token));
+        assert!(!sessions.check("bogus"));
+        assert!(sessions.open(&request, None).is_none());
+
+        clock.advance(ttl);
+        assert!(!sessions.check(&token));
+
+        let (first, _) = sessions.open(&CtlSessionOpen::new("seekrit"), None).unwrap();
+        let (second, ttl) = sessions.open(&CtlSessionOpen::new("seekrit"),
+                                          Some(Duration::from_secs(30)))
+                                    .unwrap();
+        assert_eq!(ttl, Duration::from_secs(30));
+        assert_eq!(sessions.revoke_all(), 2);
+        assert!(!sessions.check(&first));
+        assert!(!sessions.check(&second));
+    }
+
+    #[test]
+    fn stale_session_requests_are_refused() {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        let sessions = CtlSessions::default();
+        let request = CtlSessionOpen::new("seekrit");
+
+        clock.advance(SESSION_OPEN_SKEW + Duration::from_secs(1));
+        assert!(sessions.open(&request, None).is_none());
+    }
+
+    #[test]
+    fn signed_requests_are_taken_once_while_they_are_fresh() {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        let nonces = SignedRequestNonces::default();
+        let issued_at = clock::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        assert!(nonces.take(issued_at, "first"));
+        assert!(!nonces.take(issued_at, "first"));
+        assert!(!nonces.take(issued_at, ""));
+
+        clock.advance(SIGNED_REQUEST_SKEW + Duration::from_secs(1));
+        assert!(!nonces.take(issued_at, "second"));
+        let too_early = issued_at + 2 * SIGNED_REQUEST_SKEW.as_secs() + 2;
+        assert!(!nonces.take(too_early, "third"));
+    }
+}
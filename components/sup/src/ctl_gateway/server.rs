@@ -8,7 +8,11 @@
 //! mpsc channel, [`CtlSender`], to [`CtlReceiver`]. A new mpsc pair is created for each
 //! transactional request where the sending half is given to a [`ctl_gateway.CtlRequest`].
 
-use super::{CtlRequest,
+use super::{CtlAuthentication,
+            CtlRequest,
+            CtlSecret,
+            CtlSessions,
+            SecretKeyMatch,
             REQ_TIMEOUT};
 use crate::manager::{action::ActionSender,
                      commands,
@@ -19,11 +23,12 @@ use futures::{channel::mpsc,
               ready,
               task::{Context,
                      Poll}};
-use habitat_core::crypto;
 use habitat_sup_protocol::{self as protocol,
-                           codec::{SrvCodec,
+                           codec::{FrameError,
+                                   SrvCodec,
                                    SrvMessage,
                                    SrvStream,
+                                   SrvTransport,
                                    SrvTxn},
                            net::{self,
                                  ErrCode,
@@ -41,9 +46,12 @@ use std::{error,
           sync::{Arc,
                  Mutex},
           time::Duration};
-use tokio::{net::TcpListener,
+use tokio::{net::{TcpListener,
+                  TcpStream},
             task,
             time};
+use tokio_rustls::{rustls::ServerConfig,
+                   TlsAcceptor};
 use tokio_util::codec::Decoder;
 
 lazy_static! {
@@ -54,6 +62,19 @@ lazy_static! {
         register_histogram_vec!("hab_sup_rpc_call_request_duration_seconds",
                                 "The latency for RPC calls",
                                 &["name"]).unwrap();
+    static ref REJECTED_FRAMES: IntCounterVec =
+        register_int_counter_vec!("hab_sup_ctl_gateway_rejected_frames_total",
+                                  "Total number of malformed or oversized frames received",
+                                  &["reason"]).unwrap();
+}
+
+/// Count a frame the codec refused to decode. Any other error means the connection failed rather
+/// than the client misbehaving, so it is not counted.
+fn count_rejected_frame(err: &io::Error) {
+    if let Some(frame_err) = FrameError::from_io_error(err) {
+        REJECTED_FRAMES.with_label_values(&[frame_err.reason()])
+                       .inc();
+    }
 }
 
 /// Sending half of an mpsc unbounded channel used for sending replies for a transactional message
@@ -133,6 +154,13 @@ impl CtlCommand {
                      req: CtlRequest::new(tx, txn), }
     }
 
+    /// Record the address of the client which sent the request, and how it authenticated.
+    fn with_client(mut self, peer_addr: SocketAddr, authentication: CtlAuthentication) -> Self {
+        self.req.peer_addr = Some(peer_addr);
+        self.req.authentication = Some(authentication);
+        self
+    }
+
     /// Run the contained closure with the given [`manager.ManagerState`].
     pub fn run(&mut self, state: &ManagerState, action_sender: ActionSender) -> NetResult<()> {
         (self.fun)(state, &mut self.req, action_sender)
@@ -141,7 +169,8 @@ impl CtlCommand {
 
 /// Server's client representation. Each new connection will allocate a new Client.
 struct Client {
-    state: Arc<Mutex<SrvState>>,
+    state:     Arc<Mutex<SrvState>>,
+    peer_addr: SocketAddr,
 }
 
 impl Client {
@@ -154,53 +183,136 @@ impl Client {
                              .clone();
         let handshake_with_timeout = time::timeout(Duration::from_millis(REQ_TIMEOUT),
                                                    self.handshake(&mut socket));
-        handshake_with_timeout.await
-                              .map_err(|_| {
-                                  io::Error::new(io::ErrorKind::TimedOut, "client timed out")
-                              })??;
-        SrvHandler::new(socket, mgr_sender).await
+        let authentication =
+            handshake_with_timeout.await
+                                  .map_err(|_| {
+                                      io::Error::new(io::ErrorKind::TimedOut, "client timed out")
+                                  })??;
+        SrvHandler::new(socket, mgr_sender, self.peer_addr, authentication).await
     }
 
-    /// Initiate a handshake with the connected client before allowing future requests. A failed
-    /// handshake will close the connection.
-    async fn handshake(&self, socket: &mut SrvStream) -> Result<(), HandlerError> {
+    /// Initiate a handshake with the connected client before allowing future requests. The
+    /// client authenticates with either the secret key or a session token, or else proves it
+    /// knows the secret key to be given a session token. A failed handshake will close the
+    /// connection; a successful one returns how the client authenticated.
+    async fn handshake(&self, socket: &mut SrvStream) -> Result<CtlAuthentication, HandlerError> {
         let message = socket.next()
                             .await
-                            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
-        let success = if message.message_id() != "Handshake" {
-            debug!("No handshake");
-            return Err(HandlerError::from(io::Error::from(io::ErrorKind::ConnectionAborted)));
-        } else if !message.is_transaction() {
-            return Err(HandlerError::from(io::Error::from(io::ErrorKind::ConnectionAborted)));
-        } else {
-            match message.parse::<protocol::ctl::Handshake>() {
-                Ok(decoded) => {
-                    trace!("Received handshake, {:?}", decoded);
-                    let secret_key = self.state
-                                         .lock()
-                                         .expect("SrvState mutex poisoned")
-                                         .secret_key
-                                         .to_string();
-                    let decoded_key = decoded.secret_key.unwrap_or_default();
-                    crypto::secure_eq(decoded_key, secret_key)
+                            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?
+                            .map_err(|err| {
+                                count_rejected_frame(&err);
+                                err
+                            })?;
+        let aborted = || HandlerError::from(io::Error::from(io::ErrorKind::ConnectionAborted));
+        if !message.is_transaction() {
+            return Err(aborted());
+        }
+        let authenticated = match message.message_id() {
+            "Handshake" => {
+                match message.parse::<protocol::ctl::Handshake>() {
+                    Ok(decoded) => {
+                        trace!("Received handshake from {}", self.peer_addr);
+                        let authentication = if decoded.session_token.is_some() {
+                            CtlAuthentication::Session
+                        } else {
+                            CtlAuthentication::SecretKey
+                        };
+                        self.authenticate(decoded)
+                            .map(|reply| (reply, authentication))
+                    }
+                    Err(err) => {
+                        warn!("Handshake error, {:?}", err);
+                        return Err(aborted());
+                    }
                 }
-                Err(err) => {
-                    warn!("Handshake error, {:?}", err);
-                    return Err(HandlerError::from(io::Error::from(io::ErrorKind::ConnectionAborted)));
+            }
+            "CtlSessionOpen" => {
+                match message.parse::<protocol::ctl::CtlSessionOpen>() {
+                    Ok(decoded) => {
+                        trace!("Received session request from {}", self.peer_addr);
+                        self.open_session(&decoded)
+                            .map(|reply| (reply, CtlAuthentication::Session))
+                    }
+                    Err(err) => {
+                        warn!("Session request error, {:?}", err);
+                        return Err(aborted());
+                    }
                 }
             }
+            _ => {
+                debug!("No handshake");
+                return Err(aborted());
+            }
         };
-        let (mut reply, result) = if success {
-            (SrvMessage::from(net::ok()), Ok(()))
-        } else {
-            (SrvMessage::from(net::err(ErrCode::Unauthorized, "secret key mismatch")),
-             Err(HandlerError::from(io::Error::new(io::ErrorKind::ConnectionAborted,
-                                                   "handshake failed"))))
+        let (mut reply, result) = match authenticated {
+            Ok((reply, authentication)) => (reply, Ok(authentication)),
+            Err(reason) => {
+                (SrvMessage::from(net::err(ErrCode::Unauthorized, reason)),
+                 Err(HandlerError::from(io::Error::new(io::ErrorKind::ConnectionAborted,
+                                                       "handshake failed"))))
+            }
         };
         reply.reply_for(message.transaction().unwrap(), true);
         socket.send(reply).await?;
         result
     }
+
+    /// The reply to a handshake, or why it failed.
+    fn authenticate(&self,
+                    handshake: protocol::ctl::Handshake)
+                    -> Result<SrvMessage, &'static str> {
+        let state = self.state.lock().expect("SrvState mutex poisoned");
+        if let Some(token) = handshake.session_token {
+            return if state.sessions.check(&token) {
+                Ok(SrvMessage::from(net::ok()))
+            } else {
+                Err("session token expired or revoked")
+            };
+        }
+        if state.require_session {
+            return Err("the secret key must be proven by opening a session, not sent");
+        }
+        match state.secret_key.check(&handshake.secret_key.unwrap_or_default()) {
+            SecretKeyMatch::Current => Ok(SrvMessage::from(net::ok())),
+            SecretKeyMatch::Rotated(remaining) => {
+                // Let the client know, so that its user can move it over to the new secret key
+                // before this one stops working.
+                debug!("{} authenticated with the rotated out secret key", self.peer_addr);
+                let notice =
+                    protocol::ctl::CtlSecretRotated { expires_in: Some(remaining.as_secs()), };
+                Ok(SrvMessage::from(notice))
+            }
+            SecretKeyMatch::Mismatch => Err("secret key mismatch"),
+        }
+    }
+
+    /// The reply to a request for a session token, or why it failed.
+    fn open_session(&self,
+                    request: &protocol::ctl::CtlSessionOpen)
+                    -> Result<SrvMessage, &'static str> {
+        let state = self.state.lock().expect("SrvState mutex poisoned");
+        let secret_expires_in = match state.secret_key.check_by(|secret| request.proves(secret)) {
+            SecretKeyMatch::Current => None,
+            SecretKeyMatch::Rotated(remaining) => {
+                debug!("{} opened a session with the rotated out secret key",
+                       self.peer_addr);
+                Some(remaining)
+            }
+            SecretKeyMatch::Mismatch => return Err("secret key mismatch"),
+        };
+        let (token, expires_in) =
+            state.sessions
+                 .open(request, secret_expires_in)
+                 .ok_or("session request expired or already used")?;
+        debug!("Opened a ctl gateway session for {} lasting {}s",
+               self.peer_addr,
+               expires_in.as_secs());
+        let session = protocol::ctl::CtlSession { token:             Some(token),
+                                                  expires_in:        Some(expires_in.as_secs()),
+                                                  secret_expires_in:
+                                                      secret_expires_in.map(|r| r.as_secs()), };
+        Ok(SrvMessage::from(session))
+    }
 }
 
 /// Helpers for creating `CtlCommand`s in a `SrvHandler` for a given
@@ -266,16 +378,22 @@ mod util {
 #[pin_project]
 struct SrvHandler {
     #[pin]
-    io:           SrvStream,
-    state:        SrvHandlerState,
-    mgr_sender:   MgrSender,
-    ctl_receiver: CtlReceiver,
-    ctl_sender:   CtlSender,
-    timer:        Option<HistogramTimer>,
+    io:             SrvStream,
+    state:          SrvHandlerState,
+    mgr_sender:     MgrSender,
+    ctl_receiver:   CtlReceiver,
+    ctl_sender:     CtlSender,
+    timer:          Option<HistogramTimer>,
+    peer_addr:      SocketAddr,
+    authentication: CtlAuthentication,
 }
 
 impl SrvHandler {
-    fn new(io: SrvStream, mgr_sender: MgrSender) -> Self {
+    fn new(io: SrvStream,
+           mgr_sender: MgrSender,
+           peer_addr: SocketAddr,
+           authentication: CtlAuthentication)
+           -> Self {
         let (ctl_sender, ctl_receiver) = mpsc::unbounded();
 
         SrvHandler { io,
@@ -283,7 +401,9 @@ impl SrvHandler {
                      mgr_sender,
                      ctl_receiver,
                      ctl_sender,
-                     timer: None }
+                     timer: None,
+                     peer_addr,
+                     authentication }
     }
 
     /// # Locking (see locking.md)
@@ -294,6 +414,7 @@ impl SrvHandler {
                                           -> std::result::Result<CtlCommand, HandlerError> {
         match msg.message_id() {
             "SvcGetDefaultCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_msr),
+            "SvcGetEnv" => util::to_command(msg, ctl_sender, commands::service_env_msr),
             "SvcFilePut" => util::to_command(msg, ctl_sender, commands::service_file_put),
             "SvcSetCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_set),
             "SvcValidateCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_validate),
@@ -311,6 +432,11 @@ impl SrvHandler {
                                        // be awaited in a closure. It is safe to use
                                        // `block_in_place` here because it is called within a
                                        // spawned future.
+                                       //
+                                       // The signature is checked here rather than in
+                                       // `service_load`, which the Supervisor also uses to load
+                                       // services given on its own command line.
+                                       commands::verify_signature(state, req, &m)?;
                                        task::block_in_place(|| {
                                            executor::block_on(commands::service_load(state,
                                                                                      req,
@@ -325,6 +451,40 @@ impl SrvHandler {
             "SvcStatus" => util::to_command(msg, ctl_sender, commands::service_status_gsr),
             "SupDepart" => util::to_command(msg, ctl_sender, commands::supervisor_depart),
             "SupRestart" => util::to_command(msg, ctl_sender, commands::supervisor_restart),
+            "SupTerm" => util::to_command(msg, ctl_sender, commands::supervisor_term),
+            "SupForgetDeparted" => {
+                util::to_command(msg,
+                                 ctl_sender,
+                                 commands::supervisor_forget_departed_mlw_rsw_rhw)
+            }
+            "SupDrain" => {
+                util::to_supervisor_command(msg, ctl_sender, commands::supervisor_drain_mlw_smw_rhw)
+            }
+            "SupMembers" => util::to_command(msg, ctl_sender, commands::supervisor_members_gsr),
+            "SupRingExec" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_ring_exec_rsw_rhw_mlr)
+            }
+            "SupRingExecStatus" => {
+                util::to_command(msg,
+                                 ctl_sender,
+                                 commands::supervisor_ring_exec_status_rsr_mlr)
+            }
+            "SupRingHealth" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_ring_health_gsr)
+            }
+            "SupKeyRevocations" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_key_revocations_gsr)
+            }
+            "SupRingKeyRotate" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_ring_key_rotate)
+            }
+            "SupRingReload" => util::to_command(msg, ctl_sender, commands::supervisor_ring_reload),
+            "SupSecretRotate" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_secret_rotate)
+            }
+            "SupSessionsRevoke" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_sessions_revoke)
+            }
             _ => {
                 warn!("Unhandled message, {}", msg.message_id());
                 Err(HandlerError::from(io::Error::from(io::ErrorKind::InvalidData)))
@@ -362,7 +522,7 @@ impl Future for SrvHandler {
                                 Self::command_from_message_gsr_msr(&msg, self.ctl_sender.clone());
                             tokio::pin!(fut);
                             let cmd = match futures::ready!(fut.poll_unpin(cx)) {
-                                Ok(cmd) => cmd,
+                                Ok(cmd) => cmd.with_client(self.peer_addr, self.authentication),
                                 Err(_) => {
                                     break;
                                 }
@@ -390,6 +550,7 @@ impl Future for SrvHandler {
                             }
                         }
                         Some(Err(err)) => {
+                            count_rejected_frame(&err);
                             error!("SrvHandler failed to receive message, err: {}", err);
                             return Poll::Ready(Err(HandlerError::from(err)));
                         }
@@ -449,18 +610,32 @@ enum SrvHandlerState {
 }
 
 struct SrvState {
-    secret_key: String,
-    mgr_sender: MgrSender,
+    secret_key:      CtlSecret,
+    sessions:        CtlSessions,
+    /// Whether clients which send the secret key, rather than open a session, are refused
+    require_session: bool,
+    mgr_sender:      MgrSender,
 }
 
 /// Start a new thread which will run the CtlGateway server.
 ///
-/// New connections will be authenticated using `secret_key`. Messages from the main thread
-/// will be sent over the channel `mgr_sender`.
-pub async fn run(listen_addr: SocketAddr, secret_key: String, mgr_sender: MgrSender) {
+/// New connections will be authenticated using `secret_key`, which may be rotated while the server
+/// runs, or a token from `sessions`. With `require_session`, clients must prove they know the
+/// secret key by opening a session, rather than send it. If `tls_config` is given, clients must
+/// also connect with TLS, and present a certificate if it requires one. Messages from the main
+/// thread will be sent over the channel `mgr_sender`.
+pub async fn run(listen_addr: SocketAddr,
+                 secret_key: CtlSecret,
+                 sessions: CtlSessions,
+                 require_session: bool,
+                 tls_config: Option<Arc<ServerConfig>>,
+                 mgr_sender: MgrSender) {
     let state = SrvState { secret_key,
+                           sessions,
+                           require_session,
                            mgr_sender };
     let state = Arc::new(Mutex::new(state));
+    let acceptor = tls_config.map(TlsAcceptor::from);
     let mut listner =
         TcpListener::bind(&listen_addr).await
                                        .expect("Could not bind ctl gateway listen address!");
@@ -475,10 +650,14 @@ pub async fn run(listen_addr: SocketAddr, secret_key: String, mgr_sender: MgrSen
                         continue;
                     }
                 };
-                let io = SrvCodec::new().framed(tcp_stream);
-                let client = Client { state: Arc::clone(&state), };
+                let client = Client { state:     Arc::clone(&state),
+                                      peer_addr: addr, };
+                let acceptor = acceptor.clone();
                 tokio::spawn(async move {
-                    let res = client.serve(io).await;
+                    let res = match accept(tcp_stream, acceptor).await {
+                        Ok(io) => client.serve(io).await,
+                        Err(e) => Err(HandlerError::from(e)),
+                    };
                     debug!("DISCONNECTED from {:?} with result {:?}", addr, res);
                 });
             }
@@ -486,3 +665,21 @@ pub async fn run(listen_addr: SocketAddr, secret_key: String, mgr_sender: MgrSen
         }
     }
 }
+
+/// Frame a newly accepted connection, first completing a TLS handshake with the client if the
+/// gateway serves TLS.
+async fn accept(tcp_stream: TcpStream, acceptor: Option<TlsAcceptor>) -> io::Result<SrvStream> {
+    let transport: Box<dyn SrvTransport> = match acceptor {
+        Some(acceptor) => {
+            let handshake = time::timeout(Duration::from_millis(REQ_TIMEOUT),
+                                          acceptor.accept(tcp_stream));
+            let tls_stream = handshake.await.map_err(|_| {
+                                                io::Error::new(io::ErrorKind::TimedOut,
+                                                               "TLS handshake timed out")
+                                            })??;
+            Box::new(tls_stream)
+        }
+        None => Box::new(tcp_stream),
+    };
+    Ok(SrvCodec::new().framed(transport))
+}
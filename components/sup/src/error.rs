@@ -26,15 +26,21 @@ pub type Result<T> = result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
     Departed,
+    DownloadsPaused,
+    Acme(String),
     BadAddress(String),
     BadDataFile(PathBuf, io::Error),
     BadDataPath(PathBuf, io::Error),
     BadDesiredState(String),
     BadElectionStatus(String),
+    BadHttpAuthTokenFile(PathBuf, io::Error),
+    BadMemberIdentity(PathBuf, io::Error),
     BadSpecsPath(PathBuf, io::Error),
     BadStartStyle(String),
     BindTimeout(String),
+    BootstrapFailed(String, String),
     LockPoisoned,
+    MembershipDeserializationError(serde_json::Error),
     TestBootFail,
     ButterflyError(habitat_butterfly::error::Error),
     CtlSecretIo(PathBuf, io::Error),
@@ -51,6 +57,7 @@ pub enum Error {
     HabitatCore(habitat_core::Error),
     InvalidBinds(Vec<String>),
     InvalidCertFile(PathBuf),
+    InvalidEnvRedactPattern(String, glob::PatternError),
     InvalidHealthCheckResult(i32),
     InvalidKeyFile(PathBuf),
     InvalidKeyParameter(String),
@@ -61,6 +68,7 @@ pub enum Error {
     TaskJoin(JoinError),
     Launcher(habitat_launcher_client::Error),
     MissingRequiredBind(Vec<String>),
+    MissingAcmeDnsHook,
     MissingRequiredIdent,
     NameLookup(io::Error),
     NetErr(habitat_sup_protocol::net::NetErr),
@@ -74,6 +82,7 @@ pub enum Error {
     OneshotCanceled(oneshot::Canceled),
     PackageNotFound(package::PackageIdent),
     PackageNotRunnable(package::PackageIdent),
+    PackageTampered(package::PackageIdent, usize),
     Permissions(String),
     ProcessLockCorrupt,
     ProcessLocked(Pid),
@@ -95,6 +104,7 @@ pub enum Error {
     TLSError(rustls::TLSError),
     TomlEncode(toml::ser::Error),
     TryRecvError(mpsc::TryRecvError),
+    UnencryptableDataArchive(habitat_core::service::ServiceGroup),
     UnpackFailed,
     UserNotFound(String),
     WithDuration(Box<Self>, Duration),
@@ -107,6 +117,15 @@ impl Error {
     pub fn with_duration(self, duration: Duration) -> Self {
         Self::WithDuration(Box::new(self), duration)
     }
+
+    /// The timeout of the hook whose running past it this error reports, if it does.
+    pub fn hook_timeout(&self) -> Option<Duration> {
+        match self {
+            Error::WithDuration(e, _) => e.hook_timeout(),
+            Error::HabitatCommon(habitat_common::Error::HookTimedOut(_, timeout)) => Some(*timeout),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -114,6 +133,7 @@ impl fmt::Display for Error {
     // verbose on, and print it.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let content = match self {
+            Error::Acme(ref err) => format!("ACME certificate request failed: {}", err),
             Error::APIClient(ref err) => err.to_string(),
             Error::BadAddress(ref err) => format!("Unable to bind to address {}.", err),
             Error::Departed => "This Supervisor has been manually departed.\n\nFor the safety of \
@@ -126,6 +146,11 @@ impl fmt::Display for Error {
                                 If you are in doubt, it is better to consider the services \
                                 managed by this Supervisor as unsafe to run."
                                                                              .to_string(),
+            Error::DownloadsPaused => {
+                "Package downloads are paused because the filesystem holding /hab is low on \
+                 space; they resume once space is freed"
+                                                        .to_string()
+            }
             Error::BadDataFile(ref path, ref err) => {
                 format!("Unable to read or write to data file, {}, {}",
                         path.display(),
@@ -140,6 +165,19 @@ impl fmt::Display for Error {
                 format!("Unknown service desired state style '{}'", state)
             }
             Error::BadElectionStatus(ref status) => format!("Unknown election status '{}'", status),
+            Error::BootstrapFailed(ref url, ref reason) => {
+                format!("Unable to load services from {}: {}", url, reason)
+            }
+            Error::BadHttpAuthTokenFile(ref path, ref err) => {
+                format!("Unable to read the HTTP Gateway authentication token from {}, {}",
+                        path.display(),
+                        err)
+            }
+            Error::BadMemberIdentity(ref path, ref err) => {
+                format!("Unable to read the machine identity to derive the member-id from, {}, {}",
+                        path.display(),
+                        err)
+            }
             Error::BadSpecsPath(ref path, ref err) => {
                 format!("Unable to create the specs directory '{}' ({})",
                         path.display(),
@@ -170,6 +208,9 @@ impl fmt::Display for Error {
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
             Error::InvalidBinds(ref e) => format!("Invalid bind(s), {}", e.join(", ")),
             Error::InvalidCertFile(ref path) => format!("Invalid cert file: {}", path.display()),
+            Error::InvalidEnvRedactPattern(ref pattern, ref e) => {
+                format!("Invalid environment redaction pattern '{}': {}", pattern, e)
+            }
             Error::InvalidHealthCheckResult(code) => {
                 format!("Invalid health check result: {}", code)
             }
@@ -186,6 +227,9 @@ impl fmt::Display for Error {
             Error::MissingRequiredBind(ref e) => {
                 format!("Missing required bind(s), {}", e.join(", "))
             }
+            Error::MissingAcmeDnsHook => {
+                "The dns-01 ACME challenge requires --acme-dns-hook".to_string()
+            }
             Error::MissingRequiredIdent => {
                 "Missing required ident field: (example: ident = \"core/redis\")".to_string()
             }
@@ -209,6 +253,10 @@ impl fmt::Display for Error {
                 }
             }
             Error::PackageNotRunnable(ref pkg) => format!("Package is not runnable: {}", pkg),
+            Error::PackageTampered(ref pkg, count) => {
+                format!("{} file(s) of package {} do not match its FILES manifest",
+                        count, pkg)
+            }
             Error::ProcessLockCorrupt => "Unable to decode contents of process lock".to_string(),
             Error::ProcessLocked(ref pid) => {
                 format!("Unable to start Habitat Supervisor because another instance is already \
@@ -223,6 +271,9 @@ impl fmt::Display for Error {
             }
             Error::RecvError(ref err) => err.to_string(),
             Error::RecvTimeoutError(ref err) => err.to_string(),
+            Error::MembershipDeserializationError(ref e) => {
+                format!("Can't deserialize ring membership: {}", e)
+            }
             Error::ServiceDeserializationError(ref e) => {
                 format!("Can't deserialize service status: {}", e)
             }
@@ -253,6 +304,11 @@ impl fmt::Display for Error {
             Error::TLSError(ref e) => e.to_string(),
             Error::TomlEncode(ref e) => format!("Failed to encode TOML: {}", e),
             Error::TryRecvError(ref err) => err.to_string(),
+            Error::UnencryptableDataArchive(ref group) => {
+                format!("Cannot encrypt the data archive of {}, which has no organization and so \
+                         no service group key",
+                        group)
+            }
             Error::UnpackFailed => "Failed to unpack a package".to_string(),
             Error::UserNotFound(ref e) => format!("No UID for user '{}' could be found", e),
             Error::WithDuration(ref e, ref duration) => {
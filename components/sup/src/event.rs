@@ -2,48 +2,82 @@
 //! in the course of its operations.
 //!
 //! Currently, the Supervisor is able to send events to a [NATS][1]
-//! server. The `init_stream` function must be called
+//! server, to a [Kafka][2] topic (when built with the `kafka` feature), or to webhooks. The
+//! `init_stream` function must be called
 //! before sending events to initialize the publishing thread in the
 //! background. Thereafter, you can pass "event" structs to the
 //! `event` function, which will publish the event to the stream.
 //!
-//! All events are published under the "habitat" subject.
+//! All events are published under the "habitat" subject. On Kafka,
+//! the subject is each event's key. Webhooks are POSTed only service
+//! lifecycle and health check events, as JSON.
 //!
 //! [1]:https://github.com/nats-io/nats-server
+//! [2]:https://kafka.apache.org
 
+mod buffer;
 mod error;
+#[cfg(feature = "kafka")]
+mod kafka_message_stream;
 mod nats_message_stream;
 mod types;
+mod webhook_message_stream;
 
 pub(crate) use self::types::ServiceMetadata;
-use self::types::{EventMessage,
+use self::types::{DiskConsumer,
+                  DiskSpaceEvent,
+                  EventMessage,
                   EventMetadata,
                   HealthCheckEvent,
+                  PackageTamperedEvent,
+                  RingHealthEvent,
+                  ScheduledTaskEvent,
+                  ServiceConfigAppliedEvent,
+                  ServiceConfigChangedEvent,
+                  ServiceDataDisposedEvent,
+                  ServiceFileAppliedEvent,
                   ServiceStartedEvent,
                   ServiceStoppedEvent,
-                  ServiceUpdateStartedEvent};
-use crate::manager::{service::{HealthCheckHookStatus,
+                  ServiceUpdateStartedEvent,
+                  TamperedFile,
+                  TlsConfigReloadedEvent};
+use crate::manager::{disk_watchdog,
+                     service::{HealthCheckHookStatus,
                                HealthCheckResult,
                                ProcessOutput,
                                Service,
                                StandardStreams},
+                     service_data,
                      sys::Sys};
 pub use error::{Error,
                 Result};
-use habitat_common::types::{EventStreamConnectMethod,
+use habitat_butterfly::server::ring_health::RingHealthReport;
+use habitat_common::types::{EventStreamBufferPolicy,
+                            EventStreamConnectMethod,
                             EventStreamMetadata,
                             EventStreamServerCertificate,
-                            EventStreamToken};
-use habitat_core::{package::ident::PackageIdent,
-                   service::HealthCheckInterval};
+                            EventStreamToken,
+                            EventStreamWebhookEvent,
+                            KafkaSaslMechanism};
+use habitat_core::{crypto::hash,
+                   package::{self,
+                             ident::PackageIdent},
+                   service::{HealthCheckInterval,
+                             ServiceGroup}};
+#[cfg(feature = "kafka")]
+use kafka_message_stream::KafkaMessageStream;
 use nats_message_stream::{NatsMessage,
                           NatsMessageStream};
 use prost_types::Duration as ProstDuration;
 use rants::{Address,
             Subject};
 use state::Storage;
-use std::{net::SocketAddr,
+use std::{fmt,
+          net::SocketAddr,
+          path::{Path,
+                 PathBuf},
           time::Duration};
+use webhook_message_stream::WebhookMessageStream;
 
 lazy_static! {
     // TODO (CM): When const fn support lands in stable, we can ditch
@@ -58,25 +92,66 @@ lazy_static! {
         "habitat.event.service_update_started".parse().expect("valid NATS subject");
     static ref HEALTHCHECK_SUBJECT: Subject =
         "habitat.event.healthcheck".parse().expect("valid NATS subject");
+    static ref RING_HEALTH_SUBJECT: Subject =
+        "habitat.event.ring_health".parse().expect("valid NATS subject");
+    static ref SERVICE_CONFIG_APPLIED_SUBJECT: Subject =
+        "habitat.event.service_config_applied".parse().expect("valid NATS subject");
+    static ref SERVICE_FILE_APPLIED_SUBJECT: Subject =
+        "habitat.event.service_file_applied".parse().expect("valid NATS subject");
+    static ref SERVICE_CONFIG_CHANGED_SUBJECT: Subject =
+        "habitat.event.service_config_changed".parse().expect("valid NATS subject");
+    static ref TLS_CONFIG_RELOADED_SUBJECT: Subject =
+        "habitat.event.tls_config_reloaded".parse().expect("valid NATS subject");
+    static ref DISK_SPACE_SUBJECT: Subject =
+        "habitat.event.disk_space".parse().expect("valid NATS subject");
+    static ref PACKAGE_TAMPERED_SUBJECT: Subject =
+        "habitat.event.package_tampered".parse().expect("valid NATS subject");
+    static ref SCHEDULED_TASK_SUBJECT: Subject =
+        "habitat.event.scheduled_task".parse().expect("valid NATS subject");
+    static ref SERVICE_DATA_DISPOSED_SUBJECT: Subject =
+        "habitat.event.service_data_disposed".parse().expect("valid NATS subject");
 
     /// Reference to the event stream.
-    static ref NATS_MESSAGE_STREAM: Storage<NatsMessageStream> = Storage::new();
+    static ref EVENT_STREAM: Storage<EventStream> = Storage::new();
     /// Core information that is shared between all events.
     static ref EVENT_CORE: Storage<EventCore> = Storage::new();
 }
 
 /// Starts a new task for sending events to a NATS Streaming
-/// server. Stashes the handle to the stream, as well as the core
+/// server, Kafka topic or webhooks. Stashes the handle to the stream, as well as the core
 /// event information that will be a part of all events, in a global
 /// static reference for access later.
-pub async fn init(sys: &Sys, fqdn: String, config: EventStreamConfig) -> Result<()> {
+///
+/// Events that cannot be published while the server, brokers or webhooks are
+/// unreachable are buffered in `data_path`.
+pub async fn init(sys: &Sys,
+                  fqdn: String,
+                  config: EventStreamConfig,
+                  data_path: &Path)
+                  -> Result<()> {
     // Only initialize once
     if !initialized() {
         let supervisor_id = sys.member_id.clone();
         let ip_address = sys.gossip_listen();
         let event_core = EventCore::new(&supervisor_id, ip_address, &fqdn, &config);
-        let stream = NatsMessageStream::new(&supervisor_id, config).await?;
-        NATS_MESSAGE_STREAM.set(stream);
+        let buffer_path = buffer::buffer_path(data_path);
+        let stream = match config.sink.clone() {
+            EventSink::Nats { url, token } => {
+                let stream =
+                    NatsMessageStream::new(&supervisor_id, url, token, config, buffer_path).await?;
+                EventStream::Nats(stream)
+            }
+            #[cfg(feature = "kafka")]
+            EventSink::Kafka(kafka) => {
+                EventStream::Kafka(KafkaMessageStream::new(kafka, config, buffer_path).await?)
+            }
+            #[cfg(not(feature = "kafka"))]
+            EventSink::Kafka(_) => return Err(Error::KafkaUnsupported),
+            EventSink::Webhook(webhook) => {
+                EventStream::Webhook(WebhookMessageStream::new(webhook, config, &buffer_path)?)
+            }
+        };
+        EVENT_STREAM.set(stream);
         EVENT_CORE.set(event_core);
     }
     Ok(())
@@ -92,10 +167,79 @@ pub struct EventStreamConfig {
     pub application:        String,
     pub site:               Option<String>,
     pub meta:               EventStreamMetadata,
-    pub token:              EventStreamToken,
-    pub url:                Address,
+    pub sink:               EventSink,
     pub connect_method:     EventStreamConnectMethod,
     pub server_certificate: Option<EventStreamServerCertificate>,
+    /// How many events to hold, and which to drop, while the server is unreachable.
+    pub buffer_size:        usize,
+    pub buffer_policy:      EventStreamBufferPolicy,
+}
+
+/// Where events are published
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventSink {
+    /// A NATS server, such as Chef Automate's
+    Nats {
+        url:   Address,
+        token: EventStreamToken,
+    },
+    Kafka(KafkaConfig),
+    Webhook(WebhookConfig),
+}
+
+/// The Kafka brokers and topic events are published to. `server_certificate` is trusted when
+/// connecting to the brokers with TLS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KafkaConfig {
+    pub brokers: Vec<String>,
+    pub topic:   String,
+    pub tls:     bool,
+    pub sasl:    Option<KafkaSasl>,
+}
+
+/// The credentials the Supervisor authenticates to Kafka brokers with.
+#[derive(Clone, PartialEq)]
+pub struct KafkaSasl {
+    pub mechanism: KafkaSaslMechanism,
+    pub username:  String,
+    pub password:  String,
+}
+
+impl fmt::Debug for KafkaSasl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaSasl")
+         .field("mechanism", &self.mechanism)
+         .field("username", &self.username)
+         .finish()
+    }
+}
+
+/// The URLs events are POSTed to, which kinds of event are sent, and the Handlebars template
+/// their bodies are rendered with, if not the event itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookConfig {
+    pub urls:     Vec<String>,
+    pub template: Option<PathBuf>,
+    pub events:   Vec<EventStreamWebhookEvent>,
+}
+
+/// A handle to the stream events are sent to be published.
+enum EventStream {
+    Nats(NatsMessageStream),
+    #[cfg(feature = "kafka")]
+    Kafka(KafkaMessageStream),
+    Webhook(WebhookMessageStream),
+}
+
+impl EventStream {
+    fn send(&self, packet: NatsMessage) {
+        match self {
+            EventStream::Nats(stream) => stream.send(packet),
+            #[cfg(feature = "kafka")]
+            EventStream::Kafka(stream) => stream.send(packet),
+            EventStream::Webhook(stream) => stream.send(packet),
+        }
+    }
 }
 
 /// Send an event for the start of a Service.
@@ -158,6 +302,167 @@ pub fn health_check(metadata: ServiceMetadata,
     }
 }
 
+/// Send an event when the health of the gossip ring, as seen by this
+/// Supervisor, changes.
+pub fn ring_health_changed(report: &RingHealthReport) {
+    if initialized() {
+        publish(&RING_HEALTH_SUBJECT, RingHealthEvent::from(report));
+    }
+}
+
+/// Send an event when new configuration is applied to a service group
+/// at the request of `submitted_by`, sent from `peer_addr`.
+pub fn service_config_applied(service_group: &ServiceGroup,
+                              incarnation: u64,
+                              payload: &[u8],
+                              encrypted: bool,
+                              submitted_by: &str,
+                              peer_addr: Option<SocketAddr>) {
+    if initialized() {
+        publish(&SERVICE_CONFIG_APPLIED_SUBJECT,
+                ServiceConfigAppliedEvent { event_metadata: None,
+                                            service_group: service_group.to_string(),
+                                            incarnation,
+                                            payload_hash: hash::hash_bytes(payload),
+                                            encrypted,
+                                            submitted_by: submitted_by.to_string(),
+                                            peer_addr: peer_addr.map(|a| a.to_string())
+                                                                .unwrap_or_default() });
+    }
+}
+
+/// Send an event when a file is uploaded to a service group at the
+/// request of `submitted_by`, sent from `peer_addr`.
+pub fn service_file_applied(service_group: &ServiceGroup,
+                            filename: &str,
+                            incarnation: u64,
+                            payload: &[u8],
+                            encrypted: bool,
+                            submitted_by: &str,
+                            peer_addr: Option<SocketAddr>) {
+    if initialized() {
+        publish(&SERVICE_FILE_APPLIED_SUBJECT,
+                ServiceFileAppliedEvent { event_metadata: None,
+                                          service_group: service_group.to_string(),
+                                          filename: filename.to_string(),
+                                          incarnation,
+                                          payload_hash: hash::hash_bytes(payload),
+                                          encrypted,
+                                          submitted_by: submitted_by.to_string(),
+                                          peer_addr: peer_addr.map(|a| a.to_string())
+                                                              .unwrap_or_default() });
+    }
+}
+
+/// Send an event when a service's configuration changed, saying whether
+/// it was restarted or reconfigured to apply it.
+pub fn service_config_changed(service: &Service, changed_keys: &[String], restart: bool) {
+    if initialized() {
+        publish(&SERVICE_CONFIG_CHANGED_SUBJECT,
+                ServiceConfigChangedEvent { event_metadata: None,
+                                            service_metadata:
+                                                Some(service.to_service_metadata()),
+                                            changed_keys: changed_keys.to_vec(),
+                                            restart });
+    }
+}
+
+/// Send an event when the TLS configuration of the HTTP gateway has
+/// been reloaded, or, with the reason, when it could not be.
+pub fn tls_config_reloaded(error: Option<&str>) {
+    if initialized() {
+        publish(&TLS_CONFIG_RELOADED_SUBJECT,
+                TlsConfigReloadedEvent { event_metadata: None,
+                                         success:        error.is_none(),
+                                         error:          error.map(str::to_string)
+                                                              .unwrap_or_default(), });
+    }
+}
+
+/// Send an event when a service is not started because the files of
+/// its package don't match the package's `FILES` manifest.
+pub fn package_tampered(service: &Service, tampered: &[package::archive::TamperedFile]) {
+    if initialized() {
+        let tampered_files = tampered.iter()
+                                     .map(|file| {
+                                         TamperedFile { path:    file.path.display().to_string(),
+                                                        problem: file.problem.to_string(), }
+                                     })
+                                     .collect();
+        publish(&PACKAGE_TAMPERED_SUBJECT,
+                PackageTamperedEvent { event_metadata: None,
+                                       service_metadata: Some(service.to_service_metadata()),
+                                       tampered_files });
+    }
+}
+
+/// Send an event when the data directory of an unloaded service has
+/// been archived or purged. Takes metadata directly, as the service
+/// has already been stopped and dropped.
+pub fn service_data_disposed(metadata: ServiceMetadata,
+                             data: &service_data::DataDisposition,
+                             error: Option<&str>) {
+    if initialized() {
+        publish(&SERVICE_DATA_DISPOSED_SUBJECT,
+                ServiceDataDisposedEvent { event_metadata:    None,
+                                           service_metadata:  Some(metadata),
+                                           archive_path:      data.archive
+                                                                  .as_ref()
+                                                                  .map(|p| p.display().to_string())
+                                                                  .unwrap_or_default(),
+                                           archive_encrypted: data.encrypt,
+                                           purged:            data.purge && error.is_none(),
+                                           success:           error.is_none(),
+                                           error:             error.map(str::to_string)
+                                                                   .unwrap_or_default(), });
+    }
+}
+
+/// Send an event when a scheduled task of a service has been run.
+/// Takes metadata directly, as tasks run apart from their service,
+/// like health checks.
+pub fn scheduled_task_ran(metadata: ServiceMetadata,
+                          task: &package::schedule::ScheduledTask,
+                          success: bool,
+                          exit_status: Option<i32>,
+                          execution: Duration,
+                          skipped_runs: u64) {
+    if initialized() {
+        publish(&SCHEDULED_TASK_SUBJECT,
+                ScheduledTaskEvent { event_metadata: None,
+                                     service_metadata: Some(metadata),
+                                     hook: task.hook.clone(),
+                                     schedule: task.schedule.to_string(),
+                                     success,
+                                     execution: Some(execution.into()),
+                                     exit_status,
+                                     skipped_runs });
+    }
+}
+
+/// Send an event when package downloads are paused because the
+/// filesystem holding `/hab` is low on space, naming the directories
+/// using the most of it, or when they resume.
+pub fn disk_space_changed(low: bool,
+                          space: disk_watchdog::DiskSpace,
+                          consumers: &[disk_watchdog::DiskConsumer]) {
+    if initialized() {
+        publish(&DISK_SPACE_SUBJECT,
+                DiskSpaceEvent { event_metadata: None,
+                                 low,
+                                 available_bytes: space.available,
+                                 total_bytes: space.total,
+                                 top_consumers: consumers.iter()
+                                                         .map(|c| {
+                                                             DiskConsumer { path:  c.path
+                                                                                    .display()
+                                                                                    .to_string(),
+                                                                            bytes: c.bytes, }
+                                                         })
+                                                         .collect() });
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 /// A collection of data that will be present in all events. Rather
@@ -200,7 +505,7 @@ impl EventCore {
 /// Internal helper function to know whether or not to go to the trouble of
 /// creating event structures. If the event stream hasn't been
 /// initialized, then we shouldn't need to do anything.
-fn initialized() -> bool { NATS_MESSAGE_STREAM.try_get().is_some() }
+fn initialized() -> bool { EVENT_STREAM.try_get().is_some() }
 
 /// Publish an event. This is the main interface that client code will
 /// use.
@@ -208,7 +513,7 @@ fn initialized() -> bool { NATS_MESSAGE_STREAM.try_get().is_some() }
 /// If `init_stream` has not been called already, this function will
 /// be a no-op.
 fn publish(subject: &'static Subject, mut event: impl EventMessage) {
-    if let Some(stream) = NATS_MESSAGE_STREAM.try_get() {
+    if let Some(stream) = EVENT_STREAM.try_get() {
         // TODO (CM): Yeah... this is looking pretty gross. The
         // intention is to be able to timestamp the events right as
         // they go out.
@@ -225,7 +530,7 @@ fn publish(subject: &'static Subject, mut event: impl EventMessage) {
                                                  Some(std::time::SystemTime::now().into()),
                                              ..EVENT_CORE.get().to_event_metadata() });
 
-        let packet = NatsMessage::new(subject, event.to_bytes());
+        let packet = NatsMessage::new(subject.clone(), event.to_bytes());
         stream.send(packet);
     }
 }
@@ -248,7 +553,7 @@ mod tests {
     #[cfg(any(unix, windows))]
     async fn health_check_event() {
         let (tx, rx) = futures_mpsc::unbounded();
-        NATS_MESSAGE_STREAM.set(NatsMessageStream(tx));
+        EVENT_STREAM.set(EventStream::Nats(NatsMessageStream(tx)));
         EVENT_CORE.set(EventCore { supervisor_id: String::from("supervisor_id"),
                                    ip_address:    "127.0.0.1:8080".parse().unwrap(),
                                    fqdn:          String::from("fqdn"),
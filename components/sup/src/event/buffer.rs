@@ -0,0 +1,241 @@
+//! A bounded buffer of events that could not be published because the
+//! NATS server was unreachable.
+//!
+//! The buffer is periodically saved to the Supervisor's data directory,
+//! so events raised while disconnected are not lost if the Supervisor
+//! restarts before the connection is restored.
+
+use super::nats_message_stream::NatsMessage;
+use byteorder::{BigEndian,
+                ReadBytesExt,
+                WriteBytesExt};
+use habitat_common::types::EventStreamBufferPolicy;
+use habitat_core::fs::atomic_write;
+use prometheus::{IntCounterVec,
+                 IntGauge};
+use std::{collections::VecDeque,
+          fs,
+          io::{self,
+               Cursor,
+               Read},
+          path::{Path,
+                 PathBuf}};
+
+lazy_static! {
+    static ref BUFFERED_EVENTS: IntGauge =
+        register_int_gauge!("hab_sup_event_stream_buffered_events",
+                            "The number of events waiting for the event stream to be reachable").unwrap();
+    static ref DROPPED_EVENTS: IntCounterVec =
+        register_int_counter_vec!("hab_sup_event_stream_dropped_events_total",
+                                  "The number of events that were never published",
+                                  &["reason"]).unwrap();
+}
+
+/// Record that an event was dropped because publishing it failed for a
+/// reason other than the server being unreachable.
+pub fn count_publish_error() { DROPPED_EVENTS.with_label_values(&["publish_error"]).inc(); }
+
+/// Returns the location of the event buffer file within the given
+/// Supervisor data path.
+pub fn buffer_path<P>(data_path: P) -> PathBuf
+    where P: AsRef<Path>
+{
+    data_path.as_ref().join("EVENT_BUFFER")
+}
+
+/// Events waiting to be published, oldest first.
+pub struct EventBuffer {
+    messages: VecDeque<NatsMessage>,
+    capacity: usize,
+    policy:   EventStreamBufferPolicy,
+    path:     PathBuf,
+    /// Whether `messages` changed since they were last saved.
+    dirty:    bool,
+}
+
+impl EventBuffer {
+    /// Create a buffer saved to `path`, starting with any events saved
+    /// there previously. A buffer which cannot be read is discarded.
+    pub fn load(capacity: usize, policy: EventStreamBufferPolicy, path: PathBuf) -> Self {
+        let mut buffer = EventBuffer { messages: VecDeque::new(),
+                                       capacity,
+                                       policy,
+                                       path,
+                                       dirty: false };
+        match fs::read(&buffer.path).and_then(|bytes| decode(&bytes)) {
+            Ok(messages) => {
+                let saved = messages.len();
+                for message in messages {
+                    buffer.push(message);
+                }
+                // Anything dropped to fit the current capacity should not be loaded again.
+                buffer.dirty = buffer.len() != saved;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!("Discarding unreadable event buffer {}: {}",
+                      buffer.path.display(),
+                      e);
+                buffer.dirty = true;
+            }
+        }
+        buffer
+    }
+
+    pub fn len(&self) -> usize { self.messages.len() }
+
+    pub fn is_empty(&self) -> bool { self.messages.is_empty() }
+
+    /// Add an event to the back of the buffer, dropping an event
+    /// according to the buffer's policy if it is full.
+    pub fn push(&mut self, message: NatsMessage) {
+        if self.messages.len() >= self.capacity {
+            DROPPED_EVENTS.with_label_values(&["buffer_full"]).inc();
+            match self.policy {
+                EventStreamBufferPolicy::DropNewest => {
+                    trace!("Event buffer full, dropping message: {:?}", message);
+                    return;
+                }
+                EventStreamBufferPolicy::DropOldest => {
+                    if self.messages.pop_front().is_none() {
+                        trace!("Event buffer disabled, dropping message: {:?}", message);
+                        return;
+                    }
+                }
+            }
+        }
+        self.messages.push_back(message);
+        self.changed();
+    }
+
+    /// Take the oldest event from the buffer.
+    pub fn pop_front(&mut self) -> Option<NatsMessage> {
+        let message = self.messages.pop_front();
+        if message.is_some() {
+            self.changed();
+        }
+        message
+    }
+
+    /// Return an event taken with `pop_front` which could not be
+    /// published, so that it is the next to be retried.
+    pub fn push_front(&mut self, message: NatsMessage) {
+        self.messages.push_front(message);
+        self.changed();
+    }
+
+    /// Save the buffer if it changed since it was last saved.
+    pub fn persist(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        match atomic_write(&self.path, encode(&self.messages)) {
+            Ok(()) => self.dirty = false,
+            Err(e) => {
+                error!("Failed to save event buffer {}: {}", self.path.display(), e)
+            }
+        }
+    }
+
+    fn changed(&mut self) {
+        self.dirty = true;
+        BUFFERED_EVENTS.set(self.messages.len() as i64);
+    }
+}
+
+/// Each message is written as its length-prefixed subject followed by
+/// its length-prefixed payload.
+fn encode<'a>(messages: impl IntoIterator<Item = &'a NatsMessage>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for message in messages {
+        let subject = message.subject().to_string();
+        for field in &[subject.as_bytes(), message.payload()] {
+            bytes.write_u32::<BigEndian>(field.len() as u32)
+                 .expect("writing to a Vec cannot fail");
+            bytes.extend_from_slice(field);
+        }
+    }
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> io::Result<Vec<NatsMessage>> {
+    let mut reader = Cursor::new(bytes);
+    let mut messages = Vec::new();
+    while reader.position() < bytes.len() as u64 {
+        let subject = String::from_utf8(read_field(&mut reader)?).ok()
+                                                                 .and_then(|s| s.parse().ok());
+        let subject = subject.ok_or_else(|| {
+                                 io::Error::new(io::ErrorKind::InvalidData, "invalid NATS subject")
+                             })?;
+        messages.push(NatsMessage::new(subject, read_field(&mut reader)?));
+    }
+    Ok(messages)
+}
+
+fn read_field(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = reader.read_u32::<BigEndian>()?;
+    let mut field = vec![0; len as usize];
+    reader.read_exact(&mut field)?;
+    Ok(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn message(payload: &str) -> NatsMessage {
+        NatsMessage::new("habitat.event.test".parse().unwrap(),
+                         payload.as_bytes().to_vec())
+    }
+
+    fn payloads(buffer: &mut EventBuffer) -> Vec<String> {
+        let mut payloads = Vec::new();
+        while let Some(message) = buffer.pop_front() {
+            payloads.push(String::from_utf8(message.payload().to_vec()).unwrap());
+        }
+        payloads
+    }
+
+    #[test]
+    fn full_buffer_drops_according_to_policy() {
+        let dir = TempDir::new().expect("Could not create tempdir");
+        for (policy, expected) in &[(EventStreamBufferPolicy::DropOldest, vec!["b", "c"]),
+                                    (EventStreamBufferPolicy::DropNewest, vec!["a", "b"])]
+        {
+            let mut buffer = EventBuffer::load(2, *policy, buffer_path(dir.path()));
+            for payload in &["a", "b", "c"] {
+                buffer.push(message(payload));
+            }
+            assert_eq!(&payloads(&mut buffer), expected);
+        }
+    }
+
+    #[test]
+    fn zero_capacity_buffers_nothing() {
+        let dir = TempDir::new().expect("Could not create tempdir");
+        let mut buffer = EventBuffer::load(0,
+                                           EventStreamBufferPolicy::DropOldest,
+                                           buffer_path(dir.path()));
+        buffer.push(message("a"));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn persisted_events_are_loaded_in_order() {
+        let dir = TempDir::new().expect("Could not create tempdir");
+        let path = buffer_path(dir.path());
+        let mut buffer = EventBuffer::load(10, EventStreamBufferPolicy::DropOldest, path.clone());
+        for payload in &["a", "b", "c"] {
+            buffer.push(message(payload));
+        }
+        buffer.persist();
+
+        let mut loaded = EventBuffer::load(2, EventStreamBufferPolicy::DropOldest, path);
+        assert_eq!(loaded.len(), 2);
+        let first = loaded.pop_front().unwrap();
+        assert_eq!(first.subject().to_string(), "habitat.event.test");
+        assert_eq!(first.payload(), b"b");
+        assert_eq!(payloads(&mut loaded), vec!["c"]);
+    }
+}
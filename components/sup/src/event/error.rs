@@ -2,6 +2,8 @@
 
 use rants::{error::Error as RantsError,
             native_tls};
+#[cfg(feature = "kafka")]
+use rdkafka::error::KafkaError;
 use std::{error,
           fmt,
           result};
@@ -10,10 +12,17 @@ pub type Result<T> = result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
+    #[cfg(feature = "kafka")]
+    ConnectKafkaBrokers,
     ConnectNatsServer,
     HabitatHttpClient(habitat_http_client::Error),
+    #[cfg(feature = "kafka")]
+    Kafka(KafkaError),
+    #[cfg(not(feature = "kafka"))]
+    KafkaUnsupported,
     NativeTls(native_tls::Error),
     Rants(RantsError),
+    WebhookTemplate(String),
 }
 
 // TODO (CM): I would have like to have derived Fail on our Error
@@ -27,10 +36,25 @@ pub enum Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            #[cfg(feature = "kafka")]
+            Error::ConnectKafkaBrokers => {
+                "Could not establish connection to Kafka brokers".fmt(f)
+            }
             Error::ConnectNatsServer => "Could not establish connection to NATS server".fmt(f),
             Error::HabitatHttpClient(_) => "{}".fmt(f),
+            #[cfg(feature = "kafka")]
+            Error::Kafka(e) => format!("{}", e).fmt(f),
+            #[cfg(not(feature = "kafka"))]
+            Error::KafkaUnsupported => {
+                write!(f,
+                       "This Supervisor was built without Kafka support; rebuild it with the \
+                        kafka feature to publish events to Kafka")
+            }
             Error::NativeTls(e) => format!("{}", e).fmt(f),
             Error::Rants(e) => format!("{}", e).fmt(f),
+            Error::WebhookTemplate(e) => {
+                format!("Could not load the webhook template: {}", e).fmt(f)
+            }
         }
     }
 }
@@ -38,10 +62,17 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
+            #[cfg(feature = "kafka")]
+            Error::ConnectKafkaBrokers => None,
             Error::ConnectNatsServer => None,
             Error::HabitatHttpClient(ref e) => Some(e),
+            #[cfg(feature = "kafka")]
+            Error::Kafka(ref e) => Some(e),
+            #[cfg(not(feature = "kafka"))]
+            Error::KafkaUnsupported => None,
             Error::Rants(ref e) => Some(e),
             Error::NativeTls(ref e) => Some(e),
+            Error::WebhookTemplate(_) => None,
         }
     }
 }
@@ -50,6 +81,11 @@ impl From<habitat_http_client::Error> for Error {
     fn from(error: habitat_http_client::Error) -> Self { Error::HabitatHttpClient(error) }
 }
 
+#[cfg(feature = "kafka")]
+impl From<KafkaError> for Error {
+    fn from(error: KafkaError) -> Self { Error::Kafka(error) }
+}
+
 impl From<RantsError> for Error {
     fn from(error: RantsError) -> Self { Error::Rants(error) }
 }
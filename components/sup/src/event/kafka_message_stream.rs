@@ -0,0 +1,251 @@
+//! Publishes events to a Kafka topic instead of a NATS server.
+//!
+//! Events are keyed by their NATS subject, so consumers can tell them apart as they would on
+//! NATS, and those that could not be delivered are buffered just as they are for NATS.
+
+use crate::event::{buffer::{self,
+                            EventBuffer},
+                   nats_message_stream::NatsMessage,
+                   Error,
+                   EventStreamConfig,
+                   KafkaConfig,
+                   Result};
+use futures::{channel::{mpsc as futures_mpsc,
+                        mpsc::UnboundedSender},
+              future,
+              stream::{self,
+                       StreamExt},
+              FutureExt};
+use habitat_common::types::EventStreamServerCertificate;
+use rdkafka::{config::ClientConfig,
+              error::{KafkaError,
+                      RDKafkaError},
+              producer::{FutureProducer,
+                         FutureRecord}};
+use std::{iter,
+          path::PathBuf,
+          time::Duration};
+use tokio::{task,
+            time};
+
+/// How often to retry sending buffered events, and to save them, while the brokers are
+/// unreachable.
+const BUFFER_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The most events sent to the brokers at once.
+const MAX_BATCH: usize = 1000;
+
+/// How long the producer waits for more events to send along with those it has.
+const LINGER: Duration = Duration::from_millis(50);
+
+/// How many times the producer retries sending an event before it gives up on it.
+const SEND_RETRIES: u32 = 3;
+
+/// How long the producer waits between retries.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// How long the producer tries to deliver an event before it is handed back to be buffered.
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What the publishing task should do next.
+enum Input {
+    Message(NatsMessage),
+    Flush,
+}
+
+/// A lightweight handle for the Kafka message stream. Like `NatsMessageStream`, events are sent
+/// into this stream to be published, and those which cannot be are moved to a bounded buffer.
+pub struct KafkaMessageStream(UnboundedSender<NatsMessage>);
+
+impl KafkaMessageStream {
+    pub async fn new(kafka: KafkaConfig,
+                     config: EventStreamConfig,
+                     buffer_path: PathBuf)
+                     -> Result<KafkaMessageStream> {
+        let EventStreamConfig { connect_method,
+                                server_certificate,
+                                buffer_size,
+                                buffer_policy,
+                                .. } = config;
+
+        let producer: FutureProducer = client_config(&kafka, server_certificate).create()?;
+        let topic = kafka.topic;
+
+        // As with NATS, if a timeout was set the brokers must be reachable before we return. The
+        // producer connects to them by itself, so otherwise there is nothing to wait for.
+        if let Some(timeout) = connect_method.into() {
+            let client = producer.clone();
+            let metadata_topic = topic.clone();
+            let metadata = task::spawn_blocking(move || {
+                               client.client().fetch_metadata(Some(&metadata_topic), timeout)
+                           });
+            if !matches!(metadata.await, Ok(Ok(_))) {
+                return Err(Error::ConnectKafkaBrokers);
+            }
+        }
+
+        let (tx, rx) = futures_mpsc::unbounded::<NatsMessage>();
+        let mut buffer = EventBuffer::load(buffer_size, buffer_policy, buffer_path);
+
+        // Spawn a task to handle publishing received messages. Messages which are queued together
+        // are sent together, and while any are buffered new messages join the back of the buffer
+        // so that they are still sent in the order they were received.
+        tokio::spawn(async move {
+            let flushes = time::interval(BUFFER_FLUSH_INTERVAL).map(|_| Input::Flush);
+            let mut inputs = stream::select(rx.map(Input::Message), flushes);
+            while let Some(input) = inputs.next().await {
+                let mut batch = Vec::new();
+                let mut flush_due = false;
+                let mut next = Some(input);
+                while let Some(input) = next {
+                    match input {
+                        Input::Message(message) => batch.push(message),
+                        Input::Flush => flush_due = true,
+                    }
+                    next = if batch.len() < MAX_BATCH {
+                        inputs.next().now_or_never().flatten()
+                    } else {
+                        None
+                    };
+                }
+
+                let buffered = !buffer.is_empty();
+                if buffered {
+                    batch.into_iter().for_each(|message| buffer.push(message));
+                } else {
+                    for message in send(&producer, &topic, batch).await {
+                        buffer.push(message);
+                    }
+                }
+                if buffered || flush_due {
+                    flush(&producer, &topic, &mut buffer).await;
+                }
+                if flush_due {
+                    buffer.persist();
+                }
+            }
+        });
+
+        Ok(KafkaMessageStream(tx))
+    }
+
+    /// Queues a message to be published
+    pub fn send(&self, event_packet: NatsMessage) {
+        trace!("Queueing message: {:?}", event_packet);
+        if let Err(e) = self.0.unbounded_send(event_packet) {
+            error!("Failed to queue message, err: {}", e);
+        }
+    }
+}
+
+/// The producer's configuration, in librdkafka's terms.
+fn client_config(kafka: &KafkaConfig,
+                 server_certificate: Option<EventStreamServerCertificate>)
+                 -> ClientConfig {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", &kafka.brokers.join(","))
+          .set("linger.ms", &LINGER.as_millis().to_string())
+          .set("batch.num.messages", &MAX_BATCH.to_string())
+          .set("message.send.max.retries", &SEND_RETRIES.to_string())
+          .set("retry.backoff.ms", &RETRY_BACKOFF.as_millis().to_string())
+          .set("message.timeout.ms", &MESSAGE_TIMEOUT.as_millis().to_string())
+          .set("security.protocol", security_protocol(kafka));
+    if let (true, Some(certificate)) = (kafka.tls, server_certificate) {
+        let path: PathBuf = certificate.into();
+        config.set("ssl.ca.location", &path.to_string_lossy());
+    }
+    if let Some(sasl) = &kafka.sasl {
+        config.set("sasl.mechanism", sasl.mechanism.as_str())
+              .set("sasl.username", &sasl.username)
+              .set("sasl.password", &sasl.password);
+    }
+    config
+}
+
+fn security_protocol(kafka: &KafkaConfig) -> &'static str {
+    match (kafka.tls, kafka.sasl.is_some()) {
+        (false, false) => "plaintext",
+        (true, false) => "ssl",
+        (false, true) => "sasl_plaintext",
+        (true, true) => "sasl_ssl",
+    }
+}
+
+/// Send messages to the topic all at once, handing back those which could not be delivered
+/// because the brokers were unreachable, in order, so that they can be retried. Messages that
+/// fail to send for any other reason are dropped.
+async fn send(producer: &FutureProducer,
+              topic: &str,
+              messages: Vec<NatsMessage>)
+              -> Vec<NatsMessage> {
+    let deliveries = messages.into_iter()
+                             .map(|message| send_one(producer, topic, message));
+    future::join_all(deliveries).await
+                                .into_iter()
+                                .flatten()
+                                .collect()
+}
+
+/// Send a message to the topic, handing it back if it should be retried.
+async fn send_one(producer: &FutureProducer,
+                  topic: &str,
+                  message: NatsMessage)
+                  -> Option<NatsMessage> {
+    let key = message.subject().to_string();
+    let record = FutureRecord::to(topic).key(&key).payload(message.payload());
+    let result = producer.send(record, Duration::from_secs(0)).await;
+    match result {
+        Ok(_) => None,
+        Err((KafkaError::MessageProduction(RDKafkaError::MessageTimedOut), _))
+        | Err((KafkaError::MessageProduction(RDKafkaError::QueueFull), _)) => {
+            trace!("Failed to send message with key '{}' because the brokers are unreachable",
+                   key);
+            Some(message)
+        }
+        Err((e, _)) => {
+            error!("Failed to send message with key '{}', err: {}", key, e);
+            buffer::count_publish_error();
+            None
+        }
+    }
+}
+
+/// Send buffered messages, oldest first, until the buffer is empty or the brokers are
+/// unreachable.
+async fn flush(producer: &FutureProducer, topic: &str, buffer: &mut EventBuffer) {
+    while !buffer.is_empty() {
+        let batch = iter::from_fn(|| buffer.pop_front()).take(MAX_BATCH)
+                                                        .collect();
+        let unsent = send(producer, topic, batch).await;
+        if !unsent.is_empty() {
+            for message in unsent.into_iter().rev() {
+                buffer.push_front(message);
+            }
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::KafkaSasl;
+    use habitat_common::types::KafkaSaslMechanism;
+
+    #[test]
+    fn security_protocol_follows_tls_and_sasl() {
+        let mut kafka = KafkaConfig { brokers: vec!["kafka1:9092".to_string()],
+                                      topic:   "habitat".to_string(),
+                                      tls:     false,
+                                      sasl:    None, };
+        assert_eq!(security_protocol(&kafka), "plaintext");
+        kafka.tls = true;
+        assert_eq!(security_protocol(&kafka), "ssl");
+        kafka.sasl = Some(KafkaSasl { mechanism: KafkaSaslMechanism::ScramSha256,
+                                      username:  "hab".to_string(),
+                                      password:  "secret".to_string(), });
+        assert_eq!(security_protocol(&kafka), "sasl_ssl");
+        kafka.tls = false;
+        assert_eq!(security_protocol(&kafka), "sasl_plaintext");
+    }
+}
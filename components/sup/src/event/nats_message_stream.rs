@@ -1,43 +1,65 @@
-use crate::event::{Error,
+use crate::event::{buffer::{self,
+                            EventBuffer},
+                   Error,
                    EventStreamConfig,
                    Result};
 use futures::{channel::{mpsc as futures_mpsc,
                         mpsc::UnboundedSender},
-              stream::StreamExt};
+              stream::{self,
+                       StreamExt}};
+use habitat_common::types::EventStreamToken;
 use rants::{error::Error as RantsError,
             native_tls::TlsConnector,
+            Address,
             Client,
             Subject};
+use std::{path::PathBuf,
+          time::Duration};
 use tokio::time;
 
+/// How often to retry publishing buffered messages, and to save them, while the client is not
+/// connected.
+const BUFFER_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
 /// The subject and payload of a NATS message.
 #[derive(Debug)]
 pub struct NatsMessage {
-    subject: &'static Subject,
+    subject: Subject,
     payload: Vec<u8>,
 }
 
 impl NatsMessage {
-    pub fn new(subject: &'static Subject, payload: Vec<u8>) -> Self {
-        NatsMessage { subject, payload }
-    }
+    pub fn new(subject: Subject, payload: Vec<u8>) -> Self { NatsMessage { subject, payload } }
+
+    pub fn subject(&self) -> &Subject { &self.subject }
 
     pub fn payload(&self) -> &[u8] { self.payload.as_slice() }
 }
 
+/// What the publishing task should do next.
+enum Input {
+    Message(NatsMessage),
+    Flush,
+}
+
 /// A lightweight handle for the NATS message stream. All events are converted into a NatsMessage
 /// and sent into this stream to be published.
 ///
 /// An UnboundedSender should be ok here. Messages are continously processed even if the client is
-/// not currently connected.
+/// not currently connected; those which cannot be published are moved to a bounded buffer.
 pub struct NatsMessageStream(pub(super) UnboundedSender<NatsMessage>);
 
 impl NatsMessageStream {
-    pub async fn new(supervisor_id: &str, config: EventStreamConfig) -> Result<NatsMessageStream> {
-        let EventStreamConfig { url,
-                                token,
-                                connect_method,
+    pub async fn new(supervisor_id: &str,
+                     url: Address,
+                     token: EventStreamToken,
+                     config: EventStreamConfig,
+                     buffer_path: PathBuf)
+                     -> Result<NatsMessageStream> {
+        let EventStreamConfig { connect_method,
                                 server_certificate,
+                                buffer_size,
+                                buffer_policy,
                                 .. } = config;
 
         let mut client = Client::new(vec![url]);
@@ -73,22 +95,28 @@ impl NatsMessageStream {
             tokio::spawn(async move { client.connect().await });
         }
 
-        let (tx, mut rx) = futures_mpsc::unbounded::<NatsMessage>();
+        let (tx, rx) = futures_mpsc::unbounded::<NatsMessage>();
+        let mut buffer = EventBuffer::load(buffer_size, buffer_policy, buffer_path);
 
-        // Spawn a task to handle publishing received messages
+        // Spawn a task to handle publishing received messages. Messages are published in the order
+        // they were received, so while any are buffered new messages join the back of the buffer.
         tokio::spawn(async move {
-            while let Some(packet) = rx.next().await {
-                if let Err(e) = client.publish(packet.subject, packet.payload()).await {
-                    // We do not retry any messages. If we are not connected when the message is
-                    // processed or there is an error in publishing the message, the message will
-                    // never be sent.
-                    if let RantsError::NotConnected = e {
-                        trace!("Failed to publish message to subject '{}' because the client is \
-                                not connected",
-                               packet.subject);
-                    } else {
-                        error!("Failed to publish message to subject '{}', err: {}",
-                               packet.subject, e);
+            let flushes = time::interval(BUFFER_FLUSH_INTERVAL).map(|_| Input::Flush);
+            let mut inputs = stream::select(rx.map(Input::Message), flushes);
+            while let Some(input) = inputs.next().await {
+                match input {
+                    Input::Message(packet) if buffer.is_empty() => {
+                        if let Some(packet) = publish(&client, packet).await {
+                            buffer.push(packet);
+                        }
+                    }
+                    Input::Message(packet) => {
+                        buffer.push(packet);
+                        flush(&client, &mut buffer).await;
+                    }
+                    Input::Flush => {
+                        flush(&client, &mut buffer).await;
+                        buffer.persist();
                     }
                 }
             }
@@ -105,3 +133,34 @@ impl NatsMessageStream {
         }
     }
 }
+
+/// Publish a message, handing it back if the client is not connected so that it can be retried.
+/// Messages that fail to publish for any other reason are dropped.
+async fn publish(client: &Client, packet: NatsMessage) -> Option<NatsMessage> {
+    let result = client.publish(&packet.subject, packet.payload()).await;
+    match result {
+        Ok(()) => None,
+        Err(RantsError::NotConnected) => {
+            trace!("Failed to publish message to subject '{}' because the client is not connected",
+                   packet.subject);
+            Some(packet)
+        }
+        Err(e) => {
+            error!("Failed to publish message to subject '{}', err: {}",
+                   packet.subject, e);
+            buffer::count_publish_error();
+            None
+        }
+    }
+}
+
+/// Publish buffered messages, oldest first, until the buffer is empty or the client is not
+/// connected.
+async fn flush(client: &Client, buffer: &mut EventBuffer) {
+    while let Some(packet) = buffer.pop_front() {
+        if let Some(packet) = publish(client, packet).await {
+            buffer.push_front(packet);
+            break;
+        }
+    }
+}
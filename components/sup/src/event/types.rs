@@ -5,6 +5,8 @@ use super::EventCore;
 use crate::manager::service::{HealthCheckResult as DomainHealthCheckResult,
                               Service,
                               UpdateStrategy as DomainUpdateStrategy};
+use habitat_butterfly::server::ring_health::{RingHealthReport,
+                                             RingHealthStatus as DomainRingHealthStatus};
 use prost::Message;
 
 include!(concat!(env!("OUT_DIR"), "/chef.habitat.supervisor.event.rs"));
@@ -23,6 +25,38 @@ impl Into<HealthCheckResult> for DomainHealthCheckResult {
     }
 }
 
+impl Into<RingHealthStatus> for DomainRingHealthStatus {
+    fn into(self) -> RingHealthStatus {
+        match self {
+            DomainRingHealthStatus::Healthy => RingHealthStatus::Healthy,
+            DomainRingHealthStatus::Degraded => RingHealthStatus::Degraded,
+            DomainRingHealthStatus::PartitionSuspected => RingHealthStatus::PartitionSuspected,
+        }
+    }
+}
+
+impl From<&RingHealthReport> for RingHealthEvent {
+    fn from(report: &RingHealthReport) -> Self {
+        let status: RingHealthStatus = report.status.into();
+        let unreachable_members = report.unreachable_members()
+                                        .into_iter()
+                                        .map(str::to_string)
+                                        .collect();
+        let conflicting_service_groups = report.election_conflicts
+                                               .iter()
+                                               .map(|c| c.service_group.clone())
+                                               .collect();
+        RingHealthEvent { event_metadata: None,
+                          status: status.into(),
+                          alive: report.alive as u32,
+                          suspect: report.suspect as u32,
+                          confirmed: report.confirmed as u32,
+                          departed: report.departed as u32,
+                          unreachable_members,
+                          conflicting_service_groups }
+    }
+}
+
 impl Service {
     /// Create a protobuf metadata struct for Service-related event
     /// messages.
@@ -50,6 +84,7 @@ impl Service {
             }
             DomainUpdateStrategy::AtOnce => UpdateStrategy::AtOnce,
             DomainUpdateStrategy::Rolling => UpdateStrategy::Rolling,
+            DomainUpdateStrategy::Canary => UpdateStrategy::Canary,
         };
 
         Some(UpdateConfig { strategy: strategy.into(),
@@ -104,3 +139,12 @@ event_msg_impl!(ServiceStartedEvent);
 event_msg_impl!(ServiceStoppedEvent);
 event_msg_impl!(ServiceUpdateStartedEvent);
 event_msg_impl!(HealthCheckEvent);
+event_msg_impl!(RingHealthEvent);
+event_msg_impl!(ServiceConfigAppliedEvent);
+event_msg_impl!(ServiceFileAppliedEvent);
+event_msg_impl!(ServiceConfigChangedEvent);
+event_msg_impl!(TlsConfigReloadedEvent);
+event_msg_impl!(DiskSpaceEvent);
+event_msg_impl!(PackageTamperedEvent);
+event_msg_impl!(ServiceDataDisposedEvent);
+event_msg_impl!(ScheduledTaskEvent);
@@ -0,0 +1,421 @@
+//! POSTs service lifecycle and health check events to webhooks instead of publishing them to a
+//! NATS server.
+//!
+//! Each event is sent as JSON, or as the JSON rendered from it by a Handlebars template, and
+//! only the kinds of event a webhook was configured for are sent. Every webhook has a buffer of
+//! its own, so events are only held back from those which could not be reached.
+
+use crate::{event::{buffer::{self,
+                             EventBuffer},
+                    nats_message_stream::NatsMessage,
+                    types::{EventMetadata,
+                            HealthCheckEvent,
+                            HealthCheckResult,
+                            ServiceMetadata,
+                            ServiceStartedEvent,
+                            ServiceStoppedEvent,
+                            ServiceUpdateStartedEvent,
+                            UpdateStrategy},
+                    Error,
+                    EventStreamConfig,
+                    Result,
+                    WebhookConfig},
+            PRODUCT,
+            VERSION};
+use chrono::{TimeZone,
+             Utc};
+use futures::{channel::{mpsc as futures_mpsc,
+                        mpsc::UnboundedSender},
+              stream::{self,
+                       StreamExt}};
+use habitat_common::{templating::TemplateRenderer,
+                     types::EventStreamWebhookEvent};
+use habitat_core::crypto::hash;
+use habitat_http_client::ApiClient;
+use prost::{DecodeError,
+            Message};
+use prost_types::Duration as ProstDuration;
+use serde::Serialize;
+use std::{collections::HashMap,
+          path::{Path,
+                 PathBuf},
+          result,
+          time::Duration};
+use tokio::time;
+
+/// How often to retry sending buffered events, and to save them, while a webhook is
+/// unreachable.
+const BUFFER_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The name the body template is registered under.
+const TEMPLATE: &str = "webhook";
+
+/// What a webhook's sending task should do next.
+enum Input {
+    Message(NatsMessage),
+    Flush,
+}
+
+/// An event as it is sent to webhooks, and as their body templates see it.
+#[derive(Debug, Serialize)]
+struct WebhookEvent {
+    event:                &'static str,
+    occurred_at:          Option<String>,
+    supervisor_id:        String,
+    ip_address:           String,
+    fqdn:                 String,
+    application:          String,
+    environment:          String,
+    site:                 Option<String>,
+    meta:                 HashMap<String, String>,
+    service:              Option<WebhookService>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update_package_ident: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health_check:         Option<WebhookHealthCheck>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookService {
+    package_ident:   String,
+    spec_ident:      String,
+    service_group:   String,
+    update_strategy: Option<&'static str>,
+    update_channel:  Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookHealthCheck {
+    result:        &'static str,
+    execution_ms:  Option<u64>,
+    exit_status:   Option<i32>,
+    stdout:        Option<String>,
+    stderr:        Option<String>,
+    interval_secs: Option<u64>,
+}
+
+/// A lightweight handle for sending events to webhooks. Events are rendered as they are sent
+/// into this stream, then queued for each webhook to be POSTed, and those which cannot be are
+/// moved to that webhook's bounded buffer.
+pub struct WebhookMessageStream {
+    events:   Vec<EventStreamWebhookEvent>,
+    renderer: Option<TemplateRenderer>,
+    webhooks: Vec<UnboundedSender<NatsMessage>>,
+}
+
+impl WebhookMessageStream {
+    pub fn new(webhook: WebhookConfig,
+               config: EventStreamConfig,
+               buffer_path: &Path)
+               -> Result<WebhookMessageStream> {
+        let EventStreamConfig { buffer_size,
+                                buffer_policy,
+                                .. } = config;
+
+        let renderer = match &webhook.template {
+            Some(template) => {
+                let mut renderer = TemplateRenderer::new();
+                renderer.register_template_file(TEMPLATE, template)
+                        .map_err(|e| Error::WebhookTemplate(e.to_string()))?;
+                Some(renderer)
+            }
+            None => None,
+        };
+
+        let mut webhooks = Vec::with_capacity(webhook.urls.len());
+        for url in webhook.urls {
+            let client = ApiClient::new(url.as_str(), PRODUCT, VERSION, None)?;
+            let buffer = EventBuffer::load(buffer_size,
+                                           buffer_policy,
+                                           webhook_buffer_path(buffer_path, &url));
+            webhooks.push(spawn_webhook(url, client, buffer));
+        }
+
+        Ok(WebhookMessageStream { events: webhook.events,
+                                  renderer,
+                                  webhooks })
+    }
+
+    /// Queues a message to be POSTed to every webhook, if they take its kind of event
+    pub fn send(&self, event_packet: NatsMessage) {
+        if let Some(body) = self.body(&event_packet) {
+            for webhook in &self.webhooks {
+                let message = NatsMessage::new(event_packet.subject().clone(), body.clone());
+                trace!("Queueing message: {:?}", message);
+                if let Err(e) = webhook.unbounded_send(message) {
+                    error!("Failed to queue message, err: {}", e);
+                }
+            }
+        }
+    }
+
+    /// The body to POST for a message, or `None` if webhooks don't take its kind of event or it
+    /// could not be rendered.
+    fn body(&self, message: &NatsMessage) -> Option<Vec<u8>> {
+        let subject = message.subject().to_string();
+        let kind = subject.rsplit('.')
+                          .next()
+                          .and_then(|kind| kind.parse::<EventStreamWebhookEvent>().ok())
+                          .filter(|kind| self.events.contains(kind))?;
+        let rendered = match webhook_event(kind, message.payload()) {
+            Ok(event) => {
+                match &self.renderer {
+                    Some(renderer) => renderer.render(TEMPLATE, &event).map_err(|e| e.to_string()),
+                    None => serde_json::to_string(&event).map_err(|e| e.to_string()),
+                }
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match rendered {
+            Ok(body) => Some(body.into_bytes()),
+            Err(e) => {
+                error!("Failed to render webhook body for '{}', err: {}", subject, e);
+                buffer::count_publish_error();
+                None
+            }
+        }
+    }
+}
+
+/// Where the buffer of the webhook at `url` is saved, alongside the event stream's own buffer.
+fn webhook_buffer_path(buffer_path: &Path, url: &str) -> PathBuf {
+    let mut file_name = buffer_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!("-{}", &hash::hash_string(url)[..16]));
+    buffer_path.with_file_name(file_name)
+}
+
+/// Spawn a task to POST the messages sent to the returned handle to a webhook, one at a time.
+/// While any are buffered new messages join the back of the buffer so that they are still sent
+/// in the order they were received.
+fn spawn_webhook(url: String,
+                 client: ApiClient,
+                 mut buffer: EventBuffer)
+                 -> UnboundedSender<NatsMessage> {
+    let (tx, rx) = futures_mpsc::unbounded::<NatsMessage>();
+    tokio::spawn(async move {
+        let flushes = time::interval(BUFFER_FLUSH_INTERVAL).map(|_| Input::Flush);
+        let mut inputs = stream::select(rx.map(Input::Message), flushes);
+        while let Some(input) = inputs.next().await {
+            match input {
+                Input::Message(message) if buffer.is_empty() => {
+                    if let Some(message) = post(&client, &url, message).await {
+                        buffer.push(message);
+                    }
+                }
+                Input::Message(message) => buffer.push(message),
+                Input::Flush => {
+                    flush(&client, &url, &mut buffer).await;
+                    buffer.persist();
+                }
+            }
+        }
+    });
+    tx
+}
+
+/// POST a message to a webhook, handing it back if it should be retried because the webhook
+/// was unreachable or unavailable. Messages the webhook refuses are dropped.
+async fn post(client: &ApiClient, url: &str, message: NatsMessage) -> Option<NatsMessage> {
+    let result = client.post("")
+                       .header("Content-Type", "application/json")
+                       .body(message.payload().to_vec())
+                       .send()
+                       .await;
+    match result {
+        Ok(response) if response.status().is_success() => None,
+        Ok(response)
+            if response.status().is_server_error() || response.status().as_u16() == 429 =>
+        {
+            trace!("Failed to POST message '{}' to {} because it is unavailable: HTTP {}",
+                   message.subject(),
+                   url,
+                   response.status());
+            Some(message)
+        }
+        Ok(response) => {
+            error!("Failed to POST message '{}' to {}: HTTP {}",
+                   message.subject(),
+                   url,
+                   response.status());
+            buffer::count_publish_error();
+            None
+        }
+        Err(e) => {
+            trace!("Failed to POST message '{}' to {} because it is unreachable, err: {}",
+                   message.subject(),
+                   url,
+                   e);
+            Some(message)
+        }
+    }
+}
+
+/// POST buffered messages, oldest first, until the buffer is empty or the webhook is
+/// unreachable.
+async fn flush(client: &ApiClient, url: &str, buffer: &mut EventBuffer) {
+    while let Some(message) = buffer.pop_front() {
+        if let Some(message) = post(client, url, message).await {
+            buffer.push_front(message);
+            break;
+        }
+    }
+}
+
+/// Decode an event of the given kind for webhooks.
+fn webhook_event(kind: EventStreamWebhookEvent,
+                 payload: &[u8])
+                 -> result::Result<WebhookEvent, DecodeError> {
+    let (event_metadata, service_metadata, update_package_ident, health_check) = match kind {
+        EventStreamWebhookEvent::ServiceStarted => {
+            let event = ServiceStartedEvent::decode(payload)?;
+            (event.event_metadata, event.service_metadata, None, None)
+        }
+        EventStreamWebhookEvent::ServiceStopped => {
+            let event = ServiceStoppedEvent::decode(payload)?;
+            (event.event_metadata, event.service_metadata, None, None)
+        }
+        EventStreamWebhookEvent::ServiceUpdateStarted => {
+            let event = ServiceUpdateStartedEvent::decode(payload)?;
+            (event.event_metadata,
+             event.service_metadata,
+             Some(event.update_package_ident),
+             None)
+        }
+        EventStreamWebhookEvent::HealthCheck => {
+            let event = HealthCheckEvent::decode(payload)?;
+            let health_check =
+                WebhookHealthCheck { result:        health_check_result(event.result),
+                                     execution_ms:  event.execution.map(millis),
+                                     exit_status:   event.exit_status,
+                                     stdout:        event.stdout,
+                                     stderr:        event.stderr,
+                                     interval_secs: event.interval.map(|d| d.seconds as u64), };
+            (event.event_metadata, event.service_metadata, None, Some(health_check))
+        }
+    };
+    let EventMetadata { supervisor_id,
+                        ip_address,
+                        occurred_at,
+                        application,
+                        environment,
+                        meta,
+                        fqdn,
+                        site, } = event_metadata.unwrap_or_default();
+    let occurred_at = occurred_at.and_then(|t| {
+                                     Utc.timestamp_opt(t.seconds, t.nanos as u32).single()
+                                 })
+                                 .map(|t| t.to_rfc3339());
+    Ok(WebhookEvent { event: kind.as_str(),
+                      occurred_at,
+                      supervisor_id,
+                      ip_address,
+                      fqdn,
+                      application,
+                      environment,
+                      site: Some(site).filter(|site| !site.is_empty()),
+                      meta,
+                      service: service_metadata.map(webhook_service),
+                      update_package_ident,
+                      health_check })
+}
+
+fn webhook_service(metadata: ServiceMetadata) -> WebhookService {
+    let update_strategy = metadata.update_config
+                                  .as_ref()
+                                  .map(|config| update_strategy(config.strategy));
+    WebhookService { package_ident: metadata.package_ident,
+                     spec_ident: metadata.spec_ident,
+                     service_group: metadata.service_group,
+                     update_strategy,
+                     update_channel: metadata.update_config.map(|config| config.channel) }
+}
+
+fn health_check_result(result: i32) -> &'static str {
+    match HealthCheckResult::from_i32(result) {
+        Some(HealthCheckResult::Ok) => "ok",
+        Some(HealthCheckResult::Warning) => "warning",
+        Some(HealthCheckResult::Critical) => "critical",
+        Some(HealthCheckResult::Unknown) | None => "unknown",
+    }
+}
+
+/// The update strategy as it is named on the command line
+fn update_strategy(strategy: i32) -> &'static str {
+    match UpdateStrategy::from_i32(strategy) {
+        Some(UpdateStrategy::AtOnce) | None => "at-once",
+        Some(UpdateStrategy::Rolling) => "rolling",
+        Some(UpdateStrategy::Canary) => "canary",
+    }
+}
+
+fn millis(duration: ProstDuration) -> u64 {
+    duration.seconds as u64 * 1000 + duration.nanos as u64 / 1_000_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::types::UpdateConfig;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn health_check_message() -> NatsMessage {
+        let event_metadata = EventMetadata { supervisor_id: String::from("sup1"),
+                                             ..Default::default() };
+        let update_config = UpdateConfig { strategy: UpdateStrategy::Rolling as i32,
+                                           channel:  String::from("stable"), };
+        let service_metadata = ServiceMetadata { service_group: String::from("redis.default"),
+                                                 update_config: Some(update_config),
+                                                 ..Default::default() };
+        let event = HealthCheckEvent { event_metadata: Some(event_metadata),
+                                       service_metadata: Some(service_metadata),
+                                       result: HealthCheckResult::Critical as i32,
+                                       execution: Some(Duration::from_millis(1500).into()),
+                                       exit_status: Some(2),
+                                       ..Default::default() };
+        let mut payload = Vec::new();
+        event.encode(&mut payload).unwrap();
+        NatsMessage::new("habitat.event.healthcheck".parse().unwrap(), payload)
+    }
+
+    #[test]
+    fn bodies_are_only_rendered_for_the_configured_events() {
+        let message = health_check_message();
+        let mut stream =
+            WebhookMessageStream { events:   vec![EventStreamWebhookEvent::ServiceStopped],
+                                   renderer: None,
+                                   webhooks: Vec::new(), };
+        assert_eq!(stream.body(&message), None);
+
+        stream.events.push(EventStreamWebhookEvent::HealthCheck);
+        let body = stream.body(&message).expect("health checks should be sent");
+        let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(body["event"], "healthcheck");
+        assert_eq!(body["supervisor_id"], "sup1");
+        assert_eq!(body["site"], serde_json::Value::Null);
+        assert_eq!(body["service"]["service_group"], "redis.default");
+        assert_eq!(body["service"]["update_strategy"], "rolling");
+        assert_eq!(body["health_check"]["result"], "critical");
+        assert_eq!(body["health_check"]["execution_ms"], 1500);
+        assert_eq!(body["health_check"]["exit_status"], 2);
+        assert!(body.get("update_package_ident").is_none());
+    }
+
+    #[test]
+    fn bodies_are_rendered_with_the_template() {
+        let dir = TempDir::new().unwrap();
+        let template = dir.path().join("webhook.json");
+        fs::write(&template,
+                  r#"{"text": {{toJson service.service_group}}, "ok": "{{health_check.result}}"}"#)
+            .unwrap();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_file(TEMPLATE, &template).unwrap();
+        let stream = WebhookMessageStream { events:   EventStreamWebhookEvent::ALL.to_vec(),
+                                            renderer: Some(renderer),
+                                            webhooks: Vec::new(), };
+
+        let body = stream.body(&health_check_message()).unwrap();
+        assert_eq!(String::from_utf8(body).unwrap(),
+                   r#"{"text": "redis.default", "ok": "critical"}"#);
+    }
+}
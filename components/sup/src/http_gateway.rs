@@ -1,24 +1,36 @@
-use crate::manager::{self,
-                     service::{HealthCheckHook,
-                               HealthCheckResult}};
-use actix_rt::System;
-use actix_web::{dev::{Body,
+use crate::{event,
+            manager::{self,
+                      service::{HealthCheckHook,
+                                HealthCheckResult}}};
+use actix_rt::{time,
+               System};
+use actix_web::{client::Client,
+                dev::{self,
+                      Body,
                       Service,
                       ServiceRequest,
                       ServiceResponse},
                 http::{self,
+                       header,
                        StatusCode},
+                middleware::Compress,
                 web::{self,
+                      Bytes,
                       Data,
-                      Path},
+                      Path,
+                      Query},
                 App,
                 Error,
+                HttpRequest,
                 HttpResponse,
                 HttpServer,
                 Scope};
-use futures::future::{ok,
-                      Either,
-                      Future};
+use futures::{channel::mpsc::UnboundedReceiver,
+              future::{self,
+                       ok,
+                       Either,
+                       Future},
+              stream::StreamExt};
 use habitat_common::{self,
                      templating::hooks,
                      types::HttpListenAddr,
@@ -44,7 +56,8 @@ use std::{self,
           sync::{Arc,
                  Condvar,
                  Mutex},
-          thread};
+          thread,
+          time::Duration};
 
 const APIDOCS: &str = include_str!(concat!(env!("OUT_DIR"), "/api.html"));
 pub const HTTP_THREADS_ENVVAR: &str = "HAB_SUP_HTTP_THREADS";
@@ -53,6 +66,29 @@ pub const HTTP_THREAD_COUNT: usize = 2;
 /// Default listening port for the HTTPGateway listener.
 pub const DEFAULT_PORT: u16 = 9631;
 
+/// How many times to retry binding the listener after reloading the TLS configuration, and how
+/// long to wait between attempts.
+const REBIND_ATTEMPTS: u32 = 10;
+const REBIND_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The protocols offered to TLS clients, most preferred first. Clients which can't speak HTTP/2
+/// fall back to HTTP/1.1.
+const ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// The largest response body the gateway will relay from a proxied service.
+const PROXY_MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Headers which only apply to a single connection, or which carry the gateway's own credentials,
+/// and so are never passed between the client and a proxied service.
+const PROXY_HOP_BY_HOP_HEADERS: &[header::HeaderName] = &[header::AUTHORIZATION,
+                                                          header::CONNECTION,
+                                                          header::CONTENT_LENGTH,
+                                                          header::HOST,
+                                                          header::TE,
+                                                          header::TRAILER,
+                                                          header::TRANSFER_ENCODING,
+                                                          header::UPGRADE];
+
 lazy_static! {
     static ref HTTP_GATEWAY_REQUESTS: CounterVec =
         register_counter_vec!("hab_sup_http_gateway_requests_total",
@@ -78,6 +114,29 @@ habitat_core::env_config!(
     s,
     Ok(GatewayAuthenticationToken(Some(String::from(s)))));
 
+impl GatewayAuthenticationToken {
+    /// The token given to the Supervisor with `--http-auth-token-file`, if any, and otherwise
+    /// the one in the environment.
+    pub fn configured_value_or(token: Option<String>) -> Self {
+        match token {
+            Some(token) => GatewayAuthenticationToken(Some(token)),
+            None => Self::configured_value(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MemberHistoryQuery {
+    member_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    service_group: Option<String>,
+    since:         Option<u64>,
+    until:         Option<u64>,
+}
+
 #[derive(Default, Serialize)]
 struct HealthCheckBody {
     status: String,
@@ -219,8 +278,12 @@ pub enum ServerStartup {
 pub struct Server;
 
 impl Server {
+    /// Runs the gateway on a new thread. Each TLS configuration received on `tls_reloads`
+    /// replaces the current one: the listener is closed once the requests in flight finish, and
+    /// bound again with the new configuration.
     pub fn run(listen_addr: HttpListenAddr,
                tls_config: Option<ServerConfig>,
+               tls_reloads: UnboundedReceiver<ServerConfig>,
                gateway_state: Arc<GatewayState>,
                authentication_token: GatewayAuthenticationToken,
                feature_flags: FeatureFlag,
@@ -238,27 +301,36 @@ impl Server {
                 Err(_) => HTTP_THREAD_COUNT,
             };
 
-            let mut server = HttpServer::new(move || {
-                                 let app_state =
-                                     Data::new(AppState::new(gateway_state.clone(),
-                                                             authentication_token.clone(),
-                                                             feature_flags));
-                                 App::new().app_data(app_state)
-                                           .wrap_fn(authentication_middleware)
-                                           .wrap_fn(metrics_middleware)
-                                           .service(routes())
-                             }).workers(thread_count);
-
-            server = server.disable_signals();
-            debug!("http_gateway server configured");
-
-            let bind = match tls_config {
-                Some(c) => server.bind_rustls(listen_addr.to_string(), c),
-                None => server.bind(listen_addr.to_string()),
+            let app = move || {
+                let app_state = Data::new(AppState::new(gateway_state.clone(),
+                                                        authentication_token.clone(),
+                                                        feature_flags));
+                App::new().app_data(app_state)
+                          .wrap_fn(authentication_middleware)
+                          .wrap_fn(metrics_middleware)
+                          .wrap(Compress::default())
+                          .service(routes())
+            };
+            let bind = move |tls_config: Option<ServerConfig>| {
+                let server = HttpServer::new(app.clone()).workers(thread_count)
+                                                         .disable_signals();
+                debug!("http_gateway server configured");
+                match tls_config {
+                    Some(mut c) => {
+                        let protocols = ALPN_PROTOCOLS.iter()
+                                                      .map(|p| p.to_vec())
+                                                      .collect::<Vec<_>>();
+                        c.set_protocols(&protocols);
+                        server.bind_rustls(listen_addr.to_string(), c)
+                    }
+                    None => server.bind(listen_addr.to_string()),
+                }
             };
+
+            let bound = bind(tls_config);
             debug!("http_gateway server port bound");
 
-            *lock.lock().expect("Control mutex is poisoned") = match bind {
+            *lock.lock().expect("Control mutex is poisoned") = match bound {
                 Ok(_) => ServerStartup::Started,
                 Err(ref e) => {
                     error!("HTTP gateway failed to bind: {}", e);
@@ -268,17 +340,84 @@ impl Server {
 
             cvar.notify_one();
 
-            if let Ok(b) = bind {
+            if let Ok(b) = bound {
                 // Starting the server could be simplified
                 // See https://github.com/habitat-sh/habitat/issues/7352
-                System::new("actix-rt").block_on(async move {
-                                           b.run().await.expect("to start http server");
-                                       })
+                System::new("actix-rt").block_on(serve(move || b.run(),
+                                                       move |tls_config| {
+                                                           bind(tls_config).map(|b| b.run())
+                                                       },
+                                                       tls_reloads));
             }
         });
     }
 }
 
+/// Runs the gateway started by `first` until it stops, starting it again with `start` for each TLS
+/// configuration received on `tls_reloads`.
+async fn serve<F1, F2>(first: F1, start: F2, mut tls_reloads: UnboundedReceiver<ServerConfig>)
+    where F1: FnOnce() -> dev::Server,
+          F2: Fn(Option<ServerConfig>) -> std::io::Result<dev::Server>
+{
+    let mut server = Some(first());
+    loop {
+        let reload = match server.as_mut() {
+            Some(running) => {
+                match future::select(running, tls_reloads.next()).await {
+                    Either::Left((result, _)) => {
+                        result.expect("to start http server");
+                        return;
+                    }
+                    Either::Right((reload, _)) => reload,
+                }
+            }
+            None => tls_reloads.next().await,
+        };
+        let tls_config = match reload {
+            Some(tls_config) => tls_config,
+            None => {
+                // Nothing will reload the configuration any more.
+                if let Some(running) = server {
+                    running.await.expect("to start http server");
+                }
+                return;
+            }
+        };
+        if let Some(running) = server.take() {
+            running.stop(true).await;
+        }
+        server = restart(&start, tls_config).await;
+    }
+}
+
+/// Starts the gateway again with `tls_config`, retrying briefly while the previous listener
+/// releases the port.
+async fn restart<F>(start: F, tls_config: ServerConfig) -> Option<dev::Server>
+    where F: Fn(Option<ServerConfig>) -> std::io::Result<dev::Server>
+{
+    let mut attempts = 0;
+    loop {
+        match start(Some(tls_config.clone())) {
+            Ok(server) => {
+                info!("Reloaded the http-gateway TLS configuration");
+                event::tls_config_reloaded(None);
+                return Some(server);
+            }
+            Err(e) if attempts < REBIND_ATTEMPTS => {
+                debug!("Rebinding the http-gateway failed, retrying: {}", e);
+                attempts += 1;
+                time::delay_for(REBIND_RETRY_DELAY).await;
+            }
+            Err(e) => {
+                error!("HTTP gateway failed to bind with its reloaded TLS configuration: {}",
+                       e);
+                event::tls_config_reloaded(Some(&e.to_string()));
+                return None;
+            }
+        }
+    }
+}
+
 fn services_routes() -> Scope {
     web::scope("/services").route("", web::get().to(services_gsr))
                            .route("/{svc}/{group}", web::get().to(service_without_org_gsr))
@@ -286,11 +425,19 @@ fn services_routes() -> Scope {
                                   web::get().to(config_without_org_gsr))
                            .route("/{svc}/{group}/health",
                                   web::get().to(health_without_org_gsr))
+                           .route("/{svc}/{group}/history",
+                                  web::get().to(history_without_org_gsr))
+                           .route("/{svc}/{group}/proxy/{tail:.*}",
+                                  web::route().to(proxy_without_org_gsr))
                            .route("/{svc}/{group}/{org}", web::get().to(service_with_org_gsr))
                            .route("/{svc}/{group}/{org}/config",
                                   web::get().to(config_with_org_gsr))
                            .route("/{svc}/{group}/{org}/health",
                                   web::get().to(health_with_org_gsr))
+                           .route("/{svc}/{group}/{org}/history",
+                                  web::get().to(history_with_org_gsr))
+                           .route("/{svc}/{group}/{org}/proxy/{tail:.*}",
+                                  web::route().to(proxy_with_org_gsr))
 }
 
 fn routes() -> Scope {
@@ -298,8 +445,15 @@ fn routes() -> Scope {
                    .service(services_routes())
                    .service(web::resource("/butterfly").route(web::get().to(butterfly_gsr))
                                                        .wrap_fn(redact_http_middleware))
+                   .service(web::resource("/butterfly/ring-health")
+                                .route(web::get().to(ring_health_gsr))
+                                .wrap_fn(redact_http_middleware))
                    .service(web::resource("/census").route(web::get().to(census_gsr))
                                                     .wrap_fn(redact_http_middleware))
+                   .service(web::resource("/census/history")
+                                .route(web::get().to(member_history_gsr))
+                                .wrap_fn(redact_http_middleware))
+                   .route("/events", web::get().to(events_gsr))
                    .route("/metrics", web::get().to(metrics))
 }
 
@@ -318,6 +472,13 @@ fn butterfly_gsr(state: Data<AppState>) -> HttpResponse {
     json_response(data)
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn ring_health_gsr(state: Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(state.gateway_state.lock_gsr().ring_health())
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
@@ -326,6 +487,23 @@ fn census_gsr(state: Data<AppState>) -> HttpResponse {
     json_response(data)
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn member_history_gsr(query: Query<MemberHistoryQuery>, state: Data<AppState>) -> HttpResponse {
+    let gateway_state = state.gateway_state.lock_gsr();
+    let history = gateway_state.member_history();
+    match query.member_id {
+        Some(ref member_id) => {
+            let filtered: Vec<_> = history.iter()
+                                          .filter(|t| &t.member_id == member_id)
+                                          .collect();
+            HttpResponse::Ok().json(filtered)
+        }
+        None => HttpResponse::Ok().json(history),
+    }
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
@@ -418,6 +596,63 @@ fn health_gsr(svc: String, group: String, org: Option<&str>, state: &AppState) -
     }
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn events_gsr(query: Query<EventsQuery>, state: Data<AppState>) -> HttpResponse {
+    let service_group = match query.service_group
+                                   .as_deref()
+                                   .map(str::parse::<ServiceGroup>)
+                                   .transpose()
+    {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    let gateway_state = state.gateway_state.lock_gsr();
+    let events: Vec<_> = gateway_state.event_replay()
+                                      .query(service_group.as_ref(), query.since, query.until)
+                                      .collect();
+    HttpResponse::Ok().json(events)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn history_with_org_gsr(path: Path<(String, String, String)>,
+                        state: Data<AppState>)
+                        -> HttpResponse {
+    let (svc, group, org) = path.into_inner();
+    history_gsr(svc, group, Some(&org), &state)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn history_without_org_gsr(path: Path<(String, String)>, state: Data<AppState>) -> HttpResponse {
+    let (svc, group) = path.into_inner();
+    history_gsr(svc, group, None, &state)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+fn history_gsr(svc: String, group: String, org: Option<&str>, state: &AppState) -> HttpResponse {
+    let service_group = match ServiceGroup::new(svc, group, org) {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    let gateway_state = state.gateway_state.lock_gsr();
+    let transitions: Vec<_> = gateway_state.service_history()
+                                           .for_service_group(&service_group)
+                                           .collect();
+    if transitions.is_empty() {
+        HttpResponse::NotFound().finish()
+    } else {
+        HttpResponse::Ok().json(transitions)
+    }
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
@@ -452,6 +687,107 @@ fn service_gsr(svc: String, group: String, org: Option<&str>, state: &AppState)
     }
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+async fn proxy_with_org_gsr(path: Path<(String, String, String, String)>,
+                            req: HttpRequest,
+                            body: Bytes,
+                            state: Data<AppState>)
+                            -> HttpResponse {
+    let (svc, group, org, tail) = path.into_inner();
+    proxy_gsr(svc, group, Some(&org), &tail, &req, body, &state).await
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+async fn proxy_without_org_gsr(path: Path<(String, String, String)>,
+                               req: HttpRequest,
+                               body: Bytes,
+                               state: Data<AppState>)
+                               -> HttpResponse {
+    let (svc, group, tail) = path.into_inner();
+    proxy_gsr(svc, group, None, &tail, &req, body, &state).await
+}
+
+/// Forward a request to the port a service declares in its package's `PROXY_PORT` metadata.
+///
+/// The proxy is only available when the gateway requires an authentication token, so that a
+/// service's administrative endpoints are never reachable through it without credentials.
+///
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+async fn proxy_gsr(svc: String,
+                   group: String,
+                   org: Option<&str>,
+                   tail: &str,
+                   req: &HttpRequest,
+                   body: Bytes,
+                   state: &AppState)
+                   -> HttpResponse {
+    if state.authentication_token.is_none() {
+        debug!("Not proxying to services without an HTTP gateway authentication token");
+        return HttpResponse::NotFound().finish();
+    }
+    let service_group = match ServiceGroup::new(svc, group, org) {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+    let port = match service_from_services(&service_group,
+                                           state.gateway_state.lock_gsr().services_data())
+    {
+        Some(s) => s["pkg"]["proxy_port"].as_u64(),
+        None => return HttpResponse::NotFound().finish(),
+    };
+    let port = match port {
+        Some(port) => port,
+        None => {
+            debug!("Service group {} doesn't declare a proxy port",
+                   service_group);
+            return HttpResponse::NotFound().finish();
+        }
+    };
+
+    let mut url = format!("http://127.0.0.1:{}/{}", port, tail);
+    if !req.query_string().is_empty() {
+        url.push('?');
+        url.push_str(req.query_string());
+    }
+    let mut proxied = Client::new().request(req.method().clone(), url.as_str());
+    for (name, value) in req.headers()
+                            .iter()
+                            .filter(|(name, _)| !PROXY_HOP_BY_HOP_HEADERS.contains(*name))
+    {
+        proxied = proxied.header(name.clone(), value.clone());
+    }
+
+    let mut res = match proxied.send_body(body).await {
+        Ok(res) => res,
+        Err(e) => {
+            debug!("Failed to proxy request to {} at {}: {}",
+                   service_group, url, e);
+            return HttpResponse::BadGateway().finish();
+        }
+    };
+    let body = match res.body().limit(PROXY_MAX_RESPONSE_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            debug!("Failed to read proxied response from {} at {}: {}",
+                   service_group, url, e);
+            return HttpResponse::BadGateway().finish();
+        }
+    };
+    let mut response = HttpResponse::build(res.status());
+    for (name, value) in res.headers()
+                            .iter()
+                            .filter(|(name, _)| !PROXY_HOP_BY_HOP_HEADERS.contains(*name))
+    {
+        response.header(name.clone(), value.clone());
+    }
+    response.body(body)
+}
+
 fn metrics() -> HttpResponse {
     let encoder = TextEncoder::new();
     let metric_families = prometheus::gather();
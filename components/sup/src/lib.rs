@@ -54,6 +54,7 @@ extern crate json;
 #[cfg(test)]
 #[macro_use]
 pub mod cli_test_helpers;
+pub mod bootstrap;
 pub mod census;
 pub mod cli;
 pub mod command;
@@ -63,9 +64,12 @@ pub mod event;
 pub mod http_gateway;
 pub mod logger; // must be pub if used in the `hab-sup` binary
 pub mod manager;
+pub mod profile;
 mod sys;
 #[cfg(test)]
 pub mod test_helpers;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod util;
 
 use std::env;
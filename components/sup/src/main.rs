@@ -12,27 +12,41 @@ extern crate rustls;
 extern crate tempfile;
 extern crate url;
 
-use crate::sup::{cli::cli,
+use crate::sup::{bootstrap,
+                 cli::cli,
                  command,
                  error::{Error,
                          Result},
-                 event::EventStreamConfig,
+                 event::{EventSink,
+                         EventStreamConfig,
+                         KafkaConfig,
+                         KafkaSasl,
+                         WebhookConfig},
                  logger,
-                 manager::{Manager,
+                 manager::{acme::{AcmeConfig,
+                                  AcmeValidation},
+                           key_revocation::RevocationFeed,
+                           Manager,
                            ManagerConfig,
                            TLSConfig,
                            PROC_LOCK_FILE},
+                 profile,
                  util};
 use configopt::ConfigOpt;
-use hab::cli::hab::{sup::SupRun,
+use hab::cli::hab::{sup::{SupRun,
+                          SupSimulate},
                     svc};
+use habitat_butterfly::rumor::{RumorLimit,
+                               RumorLimits};
 use habitat_common::{command::package::install::InstallSource,
                      liveliness_checker,
                      output::{self,
                               OutputFormat,
                               OutputVerbosity},
                      outputln,
-                     types::GossipListenAddr,
+                     types::{AcmeChallenge,
+                             EventStreamWebhookEvent,
+                             GossipListenAddr},
                      ui::{self,
                           UI},
                      FeatureFlag};
@@ -46,12 +60,15 @@ use habitat_launcher_client::{LauncherCli,
 use habitat_sup_protocol::{self as sup_proto};
 use std::{convert::TryInto,
           env,
+          fs,
           io,
           io::Write,
           net::{IpAddr,
                 Ipv4Addr},
+          path::Path,
           process,
           str::{self}};
+use structopt::StructOpt;
 use tokio::{self,
             runtime::Builder as RuntimeBuilder};
 
@@ -175,10 +192,22 @@ async fn start_rsr_imlw_mlw_gsw_smw_rhw_msw(feature_flags: FeatureFlag) -> Resul
                     }
                 }
             };
+            if sup_run.profile_help {
+                print!("{}", profile::HELP);
+                let exit_code = if launcher.is_some() {
+                    OK_NO_RETRY_EXCODE
+                } else {
+                    0
+                };
+                process::exit(exit_code);
+            }
             let launcher = launcher.ok_or(Error::NoLauncher)?;
             sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run, launcher, feature_flags).await
         }
         ("sh", Some(_)) => sub_sh().await,
+        ("simulate", Some(_)) => {
+            command::simulate::run(SupSimulate::from_iter(env::args().skip(1)))
+        }
         ("term", Some(_)) => sub_term(),
         _ => unreachable!(),
     }
@@ -210,6 +239,13 @@ async fn sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run: SupRun,
         vec![]
     };
 
+    if let Some(url) = &sup_run.bootstrap_from {
+        svc_load_msgs.extend(bootstrap::fetch(url,
+                                              sup_run.bootstrap_checksum.as_deref(),
+                                              &sup_run.trusted_signer,
+                                              &sup_run.cache_key_path.cache_key_path).await?);
+    }
+
     let (manager_cfg, maybe_svc_load_msg) = split_apart_sup_run(sup_run, feature_flags).await?;
     if let Some(svc_load_msg) = maybe_svc_load_msg {
         svc_load_msgs.push(svc_load_msg);
@@ -222,7 +258,9 @@ async fn sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run: SupRun,
 async fn sub_sh() -> Result<()> { command::shell::sh().await }
 
 fn sub_term() -> Result<()> {
-    // We were generating a ManagerConfig from matches here, but 'hab sup term' takes no options.
+    // We were generating a ManagerConfig from matches here, but 'hab sup term' takes no options
+    // for the local Supervisor. (`hab` sends `--remote-sup` terminations over the ctl gateway
+    // itself.)
     // This means that we were implicitly getting the default ManagerConfig here. Instead of calling
     // a function to generate said config, we can just explicitly pass the default.
     let proc_lock_file = habitat_sup_protocol::sup_root(None).join(PROC_LOCK_FILE);
@@ -244,41 +282,137 @@ async fn split_apart_sup_run(sup_run: SupRun,
     let ring_key = get_ring_key(&sup_run)?;
 
     let shared_load = sup_run.shared_load;
-
-    let event_stream_config = if sup_run.event_stream_url.is_some() {
-        Some(EventStreamConfig { environment:
-                                     sup_run.event_stream_environment
-                                            .expect("Required option for EventStream feature"),
-                                 application:
-                                     sup_run.event_stream_application
-                                            .expect("Required option for EventStream feature"),
-                                 site:               sup_run.event_stream_site,
-                                 meta:               sup_run.event_meta.into(),
-                                 token:
-                                     sup_run.event_stream_token
-                                            .expect("Required option for EventStream feature"),
-                                 url:
-                                     sup_run.event_stream_url
-                                            .expect("Required option for EventStream feature")
-                                            .into(),
-                                 connect_method:     sup_run.event_stream_connect_timeout,
-                                 server_certificate: sup_run.event_stream_server_certificate, })
+    let headless = profile::is_headless(sup_run.profile);
+
+    let event_sink = if let Some(url) = sup_run.event_stream_url {
+        Some(EventSink::Nats { url:   url.into(),
+                               token: sup_run.event_stream_token
+                                             .expect("Required option for EventStream feature"), })
+    } else if !sup_run.event_stream_kafka_brokers.is_empty() {
+        let sasl = if let Some(mechanism) = sup_run.event_stream_kafka_sasl_mechanism {
+            Some(KafkaSasl { mechanism,
+                             username: sup_run.event_stream_kafka_sasl_username
+                                              .expect("Required option for Kafka SASL"),
+                             password: sup_run.event_stream_kafka_sasl_password
+                                              .expect("Required option for Kafka SASL") })
+        } else {
+            None
+        };
+        Some(EventSink::Kafka(KafkaConfig { brokers: sup_run.event_stream_kafka_brokers,
+                                            topic: sup_run.event_stream_kafka_topic
+                                                          .expect("Required option for Kafka"),
+                                            tls: sup_run.event_stream_kafka_tls,
+                                            sasl }))
+    } else if !sup_run.event_stream_webhook_url.is_empty() {
+        let events = if sup_run.event_stream_webhook_events.is_empty() {
+            EventStreamWebhookEvent::ALL.to_vec()
+        } else {
+            sup_run.event_stream_webhook_events
+        };
+        Some(EventSink::Webhook(WebhookConfig { urls: sup_run.event_stream_webhook_url,
+                                                template: sup_run.event_stream_webhook_template,
+                                                events }))
     } else {
         None
     };
 
+    let event_stream_config = match event_sink {
+        Some(_) if headless => {
+            warn!("Ignoring the event stream options, the event stream is disabled by the '{}' \
+                   profile",
+                  sup_run.profile);
+            None
+        }
+        Some(sink) => {
+            Some(EventStreamConfig { environment:
+                                         sup_run.event_stream_environment
+                                                .expect("Required option for EventStream feature"),
+                                     application:
+                                         sup_run.event_stream_application
+                                                .expect("Required option for EventStream feature"),
+                                     site: sup_run.event_stream_site,
+                                     meta: sup_run.event_meta.into(),
+                                     sink,
+                                     connect_method: sup_run.event_stream_connect_timeout,
+                                     server_certificate: sup_run.event_stream_server_certificate,
+                                     buffer_size: sup_run.event_stream_buffer_size,
+                                     buffer_policy: sup_run.event_stream_buffer_policy })
+        }
+        None => None,
+    };
+
+    let acme_config = if sup_run.acme_domain.is_empty() {
+        None
+    } else {
+        let validation = match sup_run.acme_challenge {
+            AcmeChallenge::Http01 => AcmeValidation::Http01(sup_run.acme_http_listen),
+            AcmeChallenge::Dns01 => {
+                AcmeValidation::Dns01(sup_run.acme_dns_hook.ok_or(Error::MissingAcmeDnsHook)?)
+            }
+        };
+        Some(AcmeConfig { domains: sup_run.acme_domain,
+                          email: sup_run.acme_email
+                                        .expect("`acme_email` should always have a value if \
+                                                 `acme_domain` has a value."),
+                          directory_url: sup_run.acme_directory,
+                          validation })
+    };
+
     let tls_config = if let Some(key_file) = sup_run.key_file {
         let cert_path =
             sup_run.cert_file
                    .expect("`cert_file` should always have a value if `key_file` has a value.");
         Some(TLSConfig { key_path: key_file,
                          cert_path,
-                         ca_cert_path: sup_run.ca_cert_file })
+                         ca_cert_path: sup_run.ca_cert_file,
+                         acme: acme_config })
     } else {
         None
     };
 
+    let ctl_tls_config = if let Some(cert_path) = sup_run.ctl_server_certificate {
+        let key_path = sup_run.ctl_server_key.expect("`ctl_server_key` should always have a \
+                                                      value if `ctl_server_certificate` has a \
+                                                      value.");
+        Some(TLSConfig { cert_path,
+                         key_path,
+                         ca_cert_path: sup_run.ctl_client_ca,
+                         acme: None })
+    } else {
+        None
+    };
+
+    let http_auth_token = match &sup_run.http_auth_token_file {
+        Some(path) => Some(read_http_auth_token(path)?),
+        None => None,
+    };
+
+    let env_redact_patterns =
+        sup_run.env_redact_pattern
+               .iter()
+               .map(|p| {
+                   glob::Pattern::new(p).map_err(|e| Error::InvalidEnvRedactPattern(p.clone(), e))
+               })
+               .collect::<Result<Vec<_>>>()?;
+
+    let mut rumor_limits = profile::rumor_limits(sup_run.profile);
+    for cap in &sup_run.max_rumors {
+        rumor_limit_mut(&mut rumor_limits, &cap.rumor_type).max_rumors = Some(cap.value);
+    }
+    for cap in &sup_run.max_rumor_bytes {
+        rumor_limit_mut(&mut rumor_limits, &cap.rumor_type).max_bytes = Some(cap.value);
+    }
+
     let bldr_url = habitat_core::url::bldr_url(shared_load.bldr_url.as_ref());
+    let key_revocation_feeds =
+        sup_run.key_revocation_url
+               .into_iter()
+               .map(RevocationFeed::Url)
+               .chain(sup_run.key_revocation_origin.into_iter().map(|origin| {
+                          RevocationFeed::Builder { bldr_url: bldr_url.clone(),
+                                                    origin }
+                      }))
+               .collect();
 
     let cfg = ManagerConfig { auto_update: sup_run.auto_update,
                               auto_update_period: sup_run.auto_update_period.into(),
@@ -287,9 +421,13 @@ async fn split_apart_sup_run(sup_run: SupRun,
                               cache_key_path: sup_run.cache_key_path.cache_key_path,
                               update_url: bldr_url.clone(),
                               update_channel: shared_load.channel.clone(),
-                              http_disable: sup_run.http_disable,
+                              http_disable: sup_run.http_disable || headless,
+                              http_auth_token,
                               organization: sup_run.organization,
                               gossip_permanent: sup_run.permanent_peer,
+                              member_id_source: sup_run.member_id_source,
+                              rumor_limits,
+                              gossip_timing: profile::gossip_timing(sup_run.profile),
                               ring_key,
                               gossip_peers: sup_run.peer,
                               watch_peer_file: sup_run.peer_watch_file
@@ -302,9 +440,15 @@ async fn split_apart_sup_run(sup_run: SupRun,
                               ctl_listen: sup_run.listen_ctl,
                               http_listen: sup_run.listen_http,
                               tls_config,
+                              ctl_tls_config,
+                              ctl_require_session: sup_run.ctl_require_session,
                               feature_flags,
                               event_stream_config,
                               keep_latest_packages: sup_run.keep_latest_packages,
+                              trusted_signers: sup_run.trusted_signer,
+                              key_revocation_feeds,
+                              verify_on_start: sup_run.verify_on_start,
+                              env_redact_patterns,
                               sys_ip: sup_run.sys_ip_address
                                              .or_else(|| {
                                                  let result_ip = habitat_core::util::sys::ip();
@@ -345,6 +489,15 @@ async fn split_apart_sup_run(sup_run: SupRun,
 // Various CLI Parsing Functions
 ////////////////////////////////////////////////////////////////////////
 
+/// The limit for the rumor type named by a `RumorCap`.
+fn rumor_limit_mut<'a>(limits: &'a mut RumorLimits, rumor_type: &str) -> &'a mut RumorLimit {
+    match rumor_type {
+        "service-config" => &mut limits.service_config,
+        "service-file" => &mut limits.service_file,
+        _ => &mut limits.service,
+    }
+}
+
 fn get_ring_key(sup_run: &SupRun) -> Result<Option<SymKey>> {
     let cache_key_path = &sup_run.cache_key_path.cache_key_path;
     match &sup_run.ring {
@@ -364,6 +517,19 @@ fn get_ring_key(sup_run: &SupRun) -> Result<Option<SymKey>> {
     }
 }
 
+/// The HTTP Gateway authentication token in `path`, less any surrounding whitespace such as a
+/// trailing newline.
+fn read_http_auth_token(path: &Path) -> Result<String> {
+    let token = fs::read_to_string(path).map_err(|e| Error::BadHttpAuthTokenFile(path.into(), e))?
+                                        .trim()
+                                        .to_string();
+    if token.is_empty() {
+        let err = io::Error::new(io::ErrorKind::InvalidData, "the file is empty");
+        return Err(Error::BadHttpAuthTokenFile(path.into(), err));
+    }
+    Ok(token)
+}
+
 // ServiceSpec Modification Functions
 ////////////////////////////////////////////////////////////////////////
 
@@ -377,6 +543,10 @@ fn set_supervisor_logging_options(sup_run: &SupRun) {
     if sup_run.json_logging {
         output::set_format(OutputFormat::JSON)
     }
+    if let Err(e) = output::set_log_sink(sup_run.log_sink) {
+        error!("Unable to write output to the {} log sink, falling back to stdout: {}",
+               sup_run.log_sink, e);
+    }
 }
 
 #[cfg(test)]
@@ -385,7 +555,8 @@ mod test {
     use hab::cli::hab::sup::Sup;
     use habitat_common::types::{GossipListenAddr,
                                 HttpListenAddr,
-                                ListenCtlAddr};
+                                ListenCtlAddr,
+                                MemberIdSource};
     use habitat_core::locked_env_var;
     use habitat_sup_protocol::{ctl::ServiceBindList,
                                types::{BindingMode,
@@ -426,7 +597,11 @@ mod test {
         use super::*;
         use configopt::ConfigOpt;
         use futures::executor;
-        use habitat_common::types::EventStreamConnectMethod;
+        use habitat_butterfly::server::timing::Timing;
+        use habitat_common::{output::LogSink,
+                             types::{EventStreamBufferPolicy,
+                                     EventStreamConnectMethod,
+                                     KafkaSaslMechanism}};
         #[cfg(windows)]
         use habitat_core::crypto::dpapi::decrypt;
         use habitat_core::{fs::CACHE_KEY_PATH,
@@ -510,6 +685,80 @@ mod test {
             assert_eq!(config.update_channel, ChannelIdent::unstable());
         }
 
+        #[test]
+        fn acme_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --key /hab/sup/key.pem --certs \
+                                              /hab/sup/cert.pem --acme-domain sup.example.com \
+                                              --acme-domain example.com --acme-email \
+                                              ops@example.com");
+            assert_eq!(config.tls_config.and_then(|c| c.acme),
+                       Some(AcmeConfig { domains:       vec![String::from("sup.example.com"),
+                                                             String::from("example.com")],
+                                         email:         String::from("ops@example.com"),
+                                         directory_url:
+                                             String::from("https://acme-v02.api.letsencrypt.\
+                                                           org/directory"),
+                                         validation:
+                                             AcmeValidation::Http01("0.0.0.0:80".parse()
+                                                                                .unwrap()), }));
+
+            let config =
+                config_from_cmd_str("hab-sup run --key /hab/sup/key.pem --certs /hab/sup/cert.pem");
+            assert_eq!(config.tls_config.and_then(|c| c.acme), None);
+        }
+
+        #[test]
+        fn acme_dns_challenge_requires_a_hook() {
+            let cmd = "hab-sup run --key /hab/sup/key.pem --certs /hab/sup/cert.pem --acme-domain \
+                       sup.example.com --acme-email ops@example.com --acme-challenge dns-01";
+            let sup_run = sup_run_from_cmd_str(cmd);
+            assert!(executor::block_on(split_apart_sup_run(sup_run, no_feature_flags())).is_err());
+
+            let config = config_from_cmd_str(&format!("{} --acme-dns-hook /bin/dns-hook", cmd));
+            assert_eq!(config.tls_config.and_then(|c| c.acme).map(|a| a.validation),
+                       Some(AcmeValidation::Dns01(PathBuf::from("/bin/dns-hook"))));
+        }
+
+        #[test]
+        fn ctl_tls_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --ctl-server-certificate \
+                                              /hab/sup/ctl-cert.pem --ctl-server-key \
+                                              /hab/sup/ctl-key.pem --ctl-client-ca \
+                                              /hab/sup/ctl-ca.pem");
+            assert_eq!(config.ctl_tls_config,
+                       Some(TLSConfig { cert_path:    PathBuf::from("/hab/sup/ctl-cert.pem"),
+                                        key_path:     PathBuf::from("/hab/sup/ctl-key.pem"),
+                                        ca_cert_path: Some(PathBuf::from("/hab/sup/ctl-ca.pem")),
+                                        acme:         None, }));
+            assert_eq!(config.tls_config, None);
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.ctl_tls_config, None);
+        }
+
+        #[test]
+        fn http_auth_token_should_be_read_from_file() {
+            let tmp_dir = TempDir::new().expect("Could not create temp dir");
+            let token_file = tmp_dir.path().join("http-token");
+            fs::write(&token_file, "s3cr3t\n").expect("Could not write token file");
+            let config = config_from_cmd_str(&format!("hab-sup run --http-auth-token-file {}",
+                                                      token_file.display()));
+            assert_eq!(config.http_auth_token, Some(String::from("s3cr3t")));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.http_auth_token, None);
+        }
+
+        #[test]
+        fn http_auth_token_file_must_not_be_empty() {
+            let tmp_dir = TempDir::new().expect("Could not create temp dir");
+            let token_file = tmp_dir.path().join("http-token");
+            fs::write(&token_file, "\n").expect("Could not write token file");
+            let sup_run = sup_run_from_cmd_str(&format!("hab-sup run --http-auth-token-file {}",
+                                                        token_file.display()));
+            assert!(executor::block_on(split_apart_sup_run(sup_run, no_feature_flags())).is_err());
+        }
+
         #[test]
         fn update_channel_is_set_to_default_when_not_specified() {
             let config = config_from_cmd_str("hab-sup run");
@@ -712,15 +961,25 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ctl_listen:            ListenCtlAddr::default(),
                                        http_listen:           HttpListenAddr::default(),
                                        http_disable:          false,
+                                       http_auth_token:       None,
                                        gossip_peers:          vec![],
                                        gossip_permanent:      false,
+                                       member_id_source:      MemberIdSource::default(),
+                                       rumor_limits:          RumorLimits::default(),
+                                       gossip_timing:         Timing::default(),
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       None,
                                        tls_config:            None,
+                                       ctl_tls_config:        None,
+                                       ctl_require_session:   false,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
                                        keep_latest_packages:  None,
+                                       trusted_signers:       vec![],
+                                       key_revocation_feeds:  vec![],
+                                       verify_on_start:       false,
+                                       env_redact_patterns:   vec![],
                                        sys_ip:
                                            habitat_core::util::sys::ip().unwrap(), },
                        config);
@@ -783,18 +1042,29 @@ gpoVMSncu2jMIDZX63IkQII=
                                        http_listen:
                                            HttpListenAddr::from_str("5.5.5.5:11111").unwrap(),
                                        http_disable: true,
+                                       http_auth_token: None,
                                        gossip_peers,
                                        gossip_permanent: true,
+                                       member_id_source: MemberIdSource::default(),
+                                       rumor_limits: RumorLimits::default(),
+                                       gossip_timing: Timing::default(),
                                        ring_key: Some(sym_key),
                                        organization: Some(String::from("MY_ORG")),
                                        watch_peer_file: None,
                                        tls_config: Some(TLSConfig { cert_path,
                                                                     key_path,
                                                                     ca_cert_path:
-                                                                        Some(ca_cert_path) }),
+                                                                        Some(ca_cert_path),
+                                                                    acme: None }),
+                                       ctl_tls_config: None,
+                                       ctl_require_session: false,
                                        feature_flags: FeatureFlag::empty(),
                                        event_stream_config: None,
                                        keep_latest_packages: Some(5),
+                                       trusted_signers: vec![],
+                                       key_revocation_feeds: vec![],
+                                       verify_on_start: false,
+                                       env_redact_patterns: vec![],
                                        sys_ip: "7.8.9.0".parse().unwrap() },
                        config);
         }
@@ -820,15 +1090,25 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ctl_listen:            ListenCtlAddr::default(),
                                        http_listen:           HttpListenAddr::default(),
                                        http_disable:          false,
+                                       http_auth_token:       None,
                                        gossip_peers:          vec![],
                                        gossip_permanent:      false,
+                                       member_id_source:      MemberIdSource::default(),
+                                       rumor_limits:          RumorLimits::default(),
+                                       gossip_timing:         Timing::default(),
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       None,
                                        tls_config:            None,
+                                       ctl_tls_config:        None,
+                                       ctl_require_session:   false,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
                                        keep_latest_packages:  None,
+                                       trusted_signers:       vec![],
+                                       key_revocation_feeds:  vec![],
+                                       verify_on_start:       false,
+                                       env_redact_patterns:   vec![],
                                        sys_ip:
                                            habitat_core::util::sys::ip().unwrap(), },
                        config);
@@ -854,15 +1134,25 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ctl_listen:            ListenCtlAddr::default(),
                                        http_listen:           HttpListenAddr::default(),
                                        http_disable:          false,
+                                       http_auth_token:       None,
                                        gossip_peers:          vec![],
                                        gossip_permanent:      false,
+                                       member_id_source:      MemberIdSource::default(),
+                                       rumor_limits:          RumorLimits::default(),
+                                       gossip_timing:         Timing::default(),
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       Some(String::from("/some/path")),
                                        tls_config:            None,
+                                       ctl_tls_config:        None,
+                                       ctl_require_session:   false,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
                                        keep_latest_packages:  None,
+                                       trusted_signers:       vec![],
+                                       key_revocation_feeds:  vec![],
+                                       verify_on_start:       false,
+                                       env_redact_patterns:   vec![],
                                        sys_ip:
                                            habitat_core::util::sys::ip().unwrap(), },
                        config);
@@ -875,6 +1165,7 @@ gpoVMSncu2jMIDZX63IkQII=
             assert!(!m.verbose);
             assert!(!m.no_color);
             assert!(!m.json_logging);
+            assert_eq!(m.log_sink, LogSink::Stdout);
 
             let args = "hab-sup run -v --no-color --json-logging";
             let m = sup_run_from_cmd_str(args);
@@ -883,6 +1174,14 @@ gpoVMSncu2jMIDZX63IkQII=
             assert!(m.json_logging);
         }
 
+        #[test]
+        #[cfg(target_os = "linux")]
+        fn test_hab_sup_run_cli_log_sink() {
+            let args = "hab-sup run --log-sink journald";
+            let m = sup_run_from_cmd_str(args);
+            assert_eq!(m.log_sink, LogSink::Journald);
+        }
+
         #[test]
         fn test_hab_sup_run_cli_event_stream() {
             let lock = lock_var();
@@ -921,28 +1220,119 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ctl_listen:           ListenCtlAddr::default(),
                                        http_listen:          HttpListenAddr::default(),
                                        http_disable:         false,
+                                       http_auth_token:      None,
                                        gossip_peers:         vec![],
                                        gossip_permanent:     false,
+                                       member_id_source:     MemberIdSource::default(),
+                                       rumor_limits:     RumorLimits::default(),
+                                       gossip_timing: Timing::default(),
                                        ring_key:             None,
                                        organization:         None,
                                        watch_peer_file:      None,
                                        tls_config:           None,
+                                       ctl_tls_config:       None,
+                                       ctl_require_session:  false,
                                        feature_flags:        FeatureFlag::empty(),
                                        event_stream_config:  Some(EventStreamConfig {
                                         environment: String::from("MY_ENV"),
                                         application: String::from("MY_APP"),
                                         site: Some(String::from("my_site")),
                                         meta: meta.into(),
-                                        token: "some_token".parse().unwrap(),
-                                        url: "127.0.0.1:3456".parse().unwrap(),
+                                        sink: EventSink::Nats {
+                                            url: "127.0.0.1:3456".parse().unwrap(),
+                                            token: "some_token".parse().unwrap(),
+                                        },
                                         connect_method: EventStreamConnectMethod::Timeout {secs: 5},
                                         server_certificate: Some(certificate_path_str.parse().unwrap()),
+                                        buffer_size: 10_000,
+                                        buffer_policy: EventStreamBufferPolicy::DropOldest,
                                        }),
                                        keep_latest_packages: None,
+                                       trusted_signers: vec![],
+                                       key_revocation_feeds: vec![],
+                                       verify_on_start: false,
+                                       env_redact_patterns: vec![],
                                        sys_ip:               habitat_core::util::sys::ip().unwrap(), },
                        config,);
         }
 
+        #[test]
+        fn test_hab_sup_run_cli_minimal_profile() {
+            let config = config_from_cmd_str("hab-sup run --profile minimal --max-rumors \
+                                              service=50 --event-stream-application=MY_APP \
+                                              --event-stream-environment=MY_ENV \
+                                              --event-stream-url 127.0.0.1:3456 \
+                                              --event-stream-token some_token");
+
+            assert!(config.http_disable);
+            assert!(config.event_stream_config.is_none());
+            assert_ne!(config.gossip_timing, Timing::default());
+            assert_eq!(config.rumor_limits.service.max_rumors, Some(50));
+            assert_eq!(config.rumor_limits.service_file.max_rumors, Some(100));
+        }
+
+        #[test]
+        fn test_hab_sup_run_cli_event_stream_kafka() {
+            let config = config_from_cmd_str("hab-sup run --event-stream-application=MY_APP \
+                                              --event-stream-environment=MY_ENV \
+                                              --event-stream-kafka-brokers \
+                                              kafka1:9092,kafka2:9092 \
+                                              --event-stream-kafka-topic habitat \
+                                              --event-stream-kafka-tls \
+                                              --event-stream-kafka-sasl-mechanism PLAIN \
+                                              --event-stream-kafka-sasl-username hab \
+                                              --event-stream-kafka-sasl-password secret");
+            let sink = config.event_stream_config
+                             .expect("event stream should be enabled")
+                             .sink;
+            let sasl = KafkaSasl { mechanism: KafkaSaslMechanism::Plain,
+                                   username:  String::from("hab"),
+                                   password:  String::from("secret"), };
+            assert_eq!(sink,
+                       EventSink::Kafka(KafkaConfig { brokers: vec![String::from("kafka1:9092"),
+                                                                    String::from("kafka2:9092")],
+                                                      topic:   String::from("habitat"),
+                                                      tls:     true,
+                                                      sasl:    Some(sasl), }));
+        }
+
+        #[test]
+        fn test_hab_sup_run_cli_event_stream_webhook() {
+            let config = config_from_cmd_str("hab-sup run --event-stream-application=MY_APP \
+                                              --event-stream-environment=MY_ENV \
+                                              --event-stream-webhook-url \
+                                              https://hooks.example.com/one \
+                                              --event-stream-webhook-url \
+                                              https://hooks.example.com/two \
+                                              --event-stream-webhook-template /tmp/hook.json \
+                                              --event-stream-webhook-events \
+                                              service_stopped,healthcheck");
+            let sink = config.event_stream_config
+                             .expect("event stream should be enabled")
+                             .sink;
+            let urls = vec![String::from("https://hooks.example.com/one"),
+                            String::from("https://hooks.example.com/two")];
+            let events = vec![EventStreamWebhookEvent::ServiceStopped,
+                              EventStreamWebhookEvent::HealthCheck];
+            let template = Some(PathBuf::from("/tmp/hook.json"));
+            assert_eq!(sink,
+                       EventSink::Webhook(WebhookConfig { urls,
+                                                          template,
+                                                          events }));
+
+            let config = config_from_cmd_str("hab-sup run --event-stream-application=MY_APP \
+                                              --event-stream-environment=MY_ENV \
+                                              --event-stream-webhook-url \
+                                              https://hooks.example.com/one");
+            match config.event_stream_config.map(|config| config.sink) {
+                Some(EventSink::Webhook(webhook)) => {
+                    assert_eq!(webhook.template, None);
+                    assert_eq!(webhook.events, EventStreamWebhookEvent::ALL);
+                }
+                sink => panic!("Expected a webhook event sink, got {:?}", sink),
+            }
+        }
+
         #[test]
         fn test_hab_sup_run_cli_svc() {
             let temp_dir = TempDir::new().expect("Could not create tempdir");
@@ -952,7 +1342,7 @@ gpoVMSncu2jMIDZX63IkQII=
                                 two:service2.default --binding-mode relaxed --url http://my_url.com \
                                 --config-from={} --group MyGroup --topology leader \
                                 --strategy rolling --update-condition track-channel --health-check-interval 17 \
-                                --shutdown-timeout=12 core/redis",
+                                --shutdown-timeout=12 --cpuset 2,0-1 --nice -5 core/redis",
                                temp_dir_str);
 
             let mut binds = ServiceBindList::default();
@@ -976,6 +1366,7 @@ gpoVMSncu2jMIDZX63IkQII=
                                                      Some(String::from("my_channel")),
                                                  config_from:
                                                      Some(String::from(temp_dir_str)),
+                                                 watch_config_from:       Some(false),
                                                  force:                   Some(true),
                                                  group:
                                                      Some(String::from("MyGroup")),
@@ -984,11 +1375,24 @@ gpoVMSncu2jMIDZX63IkQII=
                                                      Some(Topology::Leader.into()),
                                                  update_strategy:
                                                      Some(UpdateStrategy::Rolling.into()),
+                                                 canary_percent:          None,
+                                                 canary_soak:             None,
+                                                 update_window:           None,
                                                  health_check_interval:
                                                      Some(health_check_interval),
                                                  shutdown_timeout:        Some(12),
+                                                 shutdown_order:          None,
+                                                 hook_timeouts:           None,
                                                  update_condition:
-                                                     Some(UpdateCondition::TrackChannel.into()), },
+                                                     Some(UpdateCondition::TrackChannel.into()),
+                                                 signature:               None,
+                                                 cpuset:                  Some(String::from("0-2")),
+                                                 nice:                    Some(-5),
+                                                 priority_class:          None,
+                                                 limits:                  None,
+                                                 cpu_limit:               None,
+                                                 memory_limit:            None,
+                                                 pids_limit:              None, },
                        service_load);
         }
 
@@ -1100,18 +1504,29 @@ sys_ip_address = "7.8.9.0"
                                        http_listen:
                                            HttpListenAddr::from_str("5.5.5.5:11111").unwrap(),
                                        http_disable: true,
+                                       http_auth_token: None,
                                        gossip_peers,
                                        gossip_permanent: true,
+                                       member_id_source: MemberIdSource::default(),
+                                       rumor_limits: RumorLimits::default(),
+                                       gossip_timing: Timing::default(),
                                        ring_key: Some(sym_key),
                                        organization: Some(String::from("MY_ORG")),
                                        watch_peer_file: None,
                                        tls_config: Some(TLSConfig { cert_path,
                                                                     key_path,
                                                                     ca_cert_path:
-                                                                        Some(ca_cert_path) }),
+                                                                        Some(ca_cert_path),
+                                                                    acme: None }),
+                                       ctl_tls_config: None,
+                                       ctl_require_session: false,
                                        feature_flags: FeatureFlag::empty(),
                                        event_stream_config: None,
                                        keep_latest_packages: Some(5),
+                                       trusted_signers: vec![],
+                                       key_revocation_feeds: vec![],
+                                       verify_on_start: false,
+                                       env_redact_patterns: vec![],
                                        sys_ip: "7.8.9.0".parse().unwrap() },
                        config);
         }
@@ -1146,15 +1561,25 @@ sys_ip_address = "7.8.9.0"
                                        ctl_listen:            ListenCtlAddr::default(),
                                        http_listen:           HttpListenAddr::default(),
                                        http_disable:          false,
+                                       http_auth_token:       None,
                                        gossip_peers:          vec![],
                                        gossip_permanent:      false,
+                                       member_id_source:      MemberIdSource::default(),
+                                       rumor_limits:          RumorLimits::default(),
+                                       gossip_timing:         Timing::default(),
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       None,
                                        tls_config:            None,
+                                       ctl_tls_config:        None,
+                                       ctl_require_session:   false,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
                                        keep_latest_packages:  None,
+                                       trusted_signers:       vec![],
+                                       key_revocation_feeds:  vec![],
+                                       verify_on_start:       false,
+                                       env_redact_patterns:   vec![],
                                        sys_ip:
                                            habitat_core::util::sys::ip().unwrap(), },
                        config);
@@ -1189,15 +1614,25 @@ sys_ip_address = "7.8.9.0"
                                        ctl_listen:            ListenCtlAddr::default(),
                                        http_listen:           HttpListenAddr::default(),
                                        http_disable:          false,
+                                       http_auth_token:       None,
                                        gossip_peers:          vec![],
                                        gossip_permanent:      false,
+                                       member_id_source:      MemberIdSource::default(),
+                                       rumor_limits:          RumorLimits::default(),
+                                       gossip_timing:         Timing::default(),
                                        ring_key:              None,
                                        organization:          None,
                                        watch_peer_file:       Some(String::from("/some/path")),
                                        tls_config:            None,
+                                       ctl_tls_config:        None,
+                                       ctl_require_session:   false,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
                                        keep_latest_packages:  None,
+                                       trusted_signers:       vec![],
+                                       key_revocation_feeds:  vec![],
+                                       verify_on_start:       false,
+                                       env_redact_patterns:   vec![],
                                        sys_ip:
                                            habitat_core::util::sys::ip().unwrap(), },
                        config);
@@ -1266,6 +1701,8 @@ event_stream_site = "my_site"
 event_stream_token = "some_token"
 event_meta = ["key1=val1", "key2=val2", "keyA=valA"]
 event_stream_server_certificate = "{}"
+event_stream_buffer_size = 500
+event_stream_buffer_policy = "drop-newest"
 "#,
                                           certificate_path_str.replace("\\", "/")
             );
@@ -1293,24 +1730,38 @@ event_stream_server_certificate = "{}"
                                        ctl_listen:           ListenCtlAddr::default(),
                                        http_listen:          HttpListenAddr::default(),
                                        http_disable:         false,
+                                       http_auth_token:      None,
                                        gossip_peers:         vec![],
                                        gossip_permanent:     false,
+                                       member_id_source:     MemberIdSource::default(),
+                                       rumor_limits:     RumorLimits::default(),
+                                       gossip_timing: Timing::default(),
                                        ring_key:             None,
                                        organization:         None,
                                        watch_peer_file:      None,
                                        tls_config:           None,
+                                       ctl_tls_config:       None,
+                                       ctl_require_session:  false,
                                        feature_flags:        FeatureFlag::empty(),
                                        event_stream_config:  Some(EventStreamConfig {
                                         environment: String::from("MY_ENV"),
                                         application: String::from("MY_APP"),
                                         site: Some(String::from("my_site")),
                                         meta: meta.into(),
-                                        token: "some_token".parse().unwrap(),
-                                        url: "127.0.0.1:3456".parse().unwrap(),
+                                        sink: EventSink::Nats {
+                                            url: "127.0.0.1:3456".parse().unwrap(),
+                                            token: "some_token".parse().unwrap(),
+                                        },
                                         connect_method: EventStreamConnectMethod::Timeout {secs: 5},
                                         server_certificate: Some(certificate_path_str.parse().unwrap()),
+                                        buffer_size: 500,
+                                        buffer_policy: EventStreamBufferPolicy::DropNewest,
                                        }),
                                        keep_latest_packages: None,
+                                       trusted_signers: vec![],
+                                       key_revocation_feeds: vec![],
+                                       verify_on_start: false,
+                                       env_redact_patterns: vec![],
                                        sys_ip:               habitat_core::util::sys::ip().unwrap(), },
                        config,);
         }
@@ -1366,6 +1817,7 @@ pkg_ident_or_artifact = "core/redis"
                                                      Some(String::from("my_channel")),
                                                  config_from:
                                                      Some(temp_dir_str.replace("\\", "/")),
+                                                 watch_config_from:       Some(false),
                                                  force:                   Some(true),
                                                  group:
                                                      Some(String::from("MyGroup")),
@@ -1374,11 +1826,24 @@ pkg_ident_or_artifact = "core/redis"
                                                      Some(Topology::Standalone.into()),
                                                  update_strategy:
                                                      Some(UpdateStrategy::AtOnce.into()),
+                                                 canary_percent:          None,
+                                                 canary_soak:             None,
+                                                 update_window:           None,
                                                  health_check_interval:
                                                      Some(health_check_interval),
                                                  shutdown_timeout:        Some(12),
+                                                 shutdown_order:          None,
+                                                 hook_timeouts:           None,
                                                  update_condition:
-                                                     Some(UpdateCondition::TrackChannel.into()), },
+                                                     Some(UpdateCondition::TrackChannel.into()),
+                                                 signature:               None,
+                                                 cpuset:                  None,
+                                                 nice:                    None,
+                                                 priority_class:          None,
+                                                 limits:                  None,
+                                                 cpu_limit:               None,
+                                                 memory_limit:            None,
+                                                 pids_limit:              None, },
                        service_load);
         }
 
@@ -1480,16 +1945,26 @@ organization = "MY_ORG_FROM_SECOND_CONFG"
                                        http_listen:
                                            HttpListenAddr::from_str("3.3.3.3:3333").unwrap(),
                                        http_disable:          false,
+                                       http_auth_token:       None,
                                        gossip_peers:          vec![],
                                        gossip_permanent:      false,
+                                       member_id_source:      MemberIdSource::default(),
+                                       rumor_limits:          RumorLimits::default(),
+                                       gossip_timing:         Timing::default(),
                                        ring_key:              None,
                                        organization:
                                            Some(String::from("MY_ORG_FROM_SECOND_CONFG")),
                                        watch_peer_file:       None,
                                        tls_config:            None,
+                                       ctl_tls_config:        None,
+                                       ctl_require_session:   false,
                                        feature_flags:         FeatureFlag::empty(),
                                        event_stream_config:   None,
                                        keep_latest_packages:  None,
+                                       trusted_signers:       vec![],
+                                       key_revocation_feeds:  vec![],
+                                       verify_on_start:       false,
+                                       env_redact_patterns:   vec![],
                                        sys_ip:
                                            habitat_core::util::sys::ip().unwrap(), },
                        config);
@@ -1,19 +1,39 @@
+pub mod acme;
 pub(crate) mod action;
 pub mod service;
 #[macro_use]
 mod debug;
+pub(crate) mod disk_watchdog;
 pub mod commands;
+mod config_dry_run;
+mod config_from_watcher;
 mod file_watcher;
+pub mod key_revocation;
+mod package_verifier;
 mod peer_watcher;
 mod self_updater;
+pub(crate) mod service_data;
+mod service_env;
+pub(crate) mod service_history;
 mod service_updater;
 mod spec_dir;
 mod spec_watcher;
 pub(crate) mod sys;
+mod tls_watcher;
 mod user_config_watcher;
 
-use self::{action::{ShutdownInput,
+use self::{acme::{AcmeClient,
+                  AcmeConfig,
+                  AcmeRenewalCheckPeriod},
+           action::{ShutdownInput,
                     SupervisorAction},
+           config_from_watcher::ConfigFromWatcher,
+           disk_watchdog::{DiskSpaceCheckPeriod,
+                           DiskWatchdog},
+           key_revocation::{KeyRevocationFetchPeriod,
+                            RevocationFeed,
+                            RevocationSource},
+           package_verifier::PackageVerifier,
            peer_watcher::PeerWatcher,
            self_updater::{SelfUpdater,
                           SUP_PKG_IDENT},
@@ -26,16 +46,26 @@ use self::{action::{ShutdownInput,
                      ServiceProxy,
                      ServiceSpec,
                      Topology},
+           service_data::DataDisposition,
+           service_history::{EventReplay,
+                             Lifecycle,
+                             ServiceHistory,
+                             Transition as ServiceTransition},
            service_updater::ServiceUpdater,
            spec_dir::SpecDir,
            spec_watcher::SpecWatcher,
            sys::Sys,
+           tls_watcher::{TlsReloadCheckPeriod,
+                         TlsWatcher},
            user_config_watcher::UserConfigWatcher};
 use crate::{census::{CensusRing,
                      CensusRingProxy},
             ctl_gateway::{self,
                           acceptor::CtlAcceptor,
-                          CtlRequest},
+                          CtlRequest,
+                          CtlSecret,
+                          CtlSessions,
+                          SignedRequestNonces},
             error::{Error,
                     Result},
             event::{self,
@@ -49,21 +79,27 @@ use futures::{channel::{mpsc as fut_mpsc,
               future,
               prelude::*,
               stream::FuturesUnordered};
-use habitat_butterfly::{member::Member,
-                        server::{timing::Timing,
+use habitat_butterfly::{member::{history::Transition,
+                                 Member},
+                        rumor::RumorLimits,
+                        server::{ring_health::{RingHealthReport,
+                                               RingHealthStatus},
+                                 timing::Timing,
                                  ServerProxy,
                                  Suitability}};
 use habitat_common::{liveliness_checker,
                      outputln,
                      types::{GossipListenAddr,
                              HttpListenAddr,
-                             ListenCtlAddr},
+                             ListenCtlAddr,
+                             MemberIdSource},
                      FeatureFlag};
 #[cfg(unix)]
 use habitat_core::os::{process::{ShutdownSignal,
                                  Signal},
                        signals};
-use habitat_core::{crypto::SymKey,
+use habitat_core::{crypto::{hash,
+                            SymKey},
                    env,
                    env::Config,
                    fs::FS_ROOT_PATH,
@@ -90,13 +126,15 @@ use rustls::{internal::pemfile,
              NoClientAuth,
              RootCertStore,
              ServerConfig};
-use std::{collections::{HashMap,
+use std::{collections::{BTreeMap,
+                        HashMap,
                         HashSet},
           ffi::OsStr,
           fs::{self,
                File,
                OpenOptions},
-          io::{BufRead,
+          io::{self,
+               BufRead,
                BufReader,
                Read,
                Write},
@@ -158,6 +196,19 @@ habitat_core::env_config_duration!( HttpStartupTimeout,
                                     HAB_HTTP_STARTUP_TIMEOUT_SECS => from_secs,
                                     Duration::from_secs(10));
 
+habitat_core::env_config_duration!(
+    /// How often the health of the gossip ring is analyzed.
+    RingHealthCheckPeriod,
+    HAB_RING_HEALTH_CHECK_PERIOD_SECS => from_secs,
+    Duration::from_secs(30));
+
+habitat_core::env_config_duration!(
+    /// How long to wait after stopping each group of services with the same shutdown order
+    /// before stopping the next group.
+    ShutdownGroupDelay,
+    HAB_SUP_SHUTDOWN_GROUP_DELAY_SECS => from_secs,
+    Duration::from_secs(0));
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 /// Determines whether the new pidfile-less behavior is enabled, or
 /// the old behavior is used.
@@ -267,18 +318,43 @@ pub struct ManagerConfig {
     pub ctl_listen:            ListenCtlAddr,
     pub http_listen:           HttpListenAddr,
     pub http_disable:          bool,
+    /// The bearer token requests to the HTTP Gateway must carry, read from
+    /// `--http-auth-token-file`.
+    pub http_auth_token:       Option<String>,
     pub gossip_peers:          Vec<SocketAddr>,
     pub gossip_permanent:      bool,
+    pub member_id_source:      MemberIdSource,
+    pub rumor_limits:          RumorLimits,
+    /// How often this Supervisor probes other members and gossips rumors.
+    pub gossip_timing:         Timing,
     pub ring_key:              Option<SymKey>,
     pub organization:          Option<String>,
     pub watch_peer_file:       Option<String>,
     pub tls_config:            Option<TLSConfig>,
+    /// The certificate and key the Control Gateway serves TLS with, and the CA its clients'
+    /// certificates must be signed by, if any.
+    pub ctl_tls_config:        Option<TLSConfig>,
+    /// Whether Control Gateway clients must prove they know the secret key with a session
+    /// request, rather than send it.
+    pub ctl_require_session:   bool,
     pub feature_flags:         FeatureFlag,
     pub event_stream_config:   Option<EventStreamConfig>,
     /// If this field is `Some`, keep the indicated number of latest packages and uninstall all
     /// others during service start. If this field is `None`, automatic package cleanup is
     /// disabled.
     pub keep_latest_packages:  Option<usize>,
+    /// Names of the signing keys whose signatures are accepted on service load, update and
+    /// configuration requests. When empty, unsigned requests are accepted.
+    pub trusted_signers:       Vec<String>,
+    /// Where to fetch the signed origin key revocation lists to keep installed in the key cache
+    /// from.
+    pub key_revocation_feeds:  Vec<RevocationFeed>,
+    /// Verify the files of a service's package against its `FILES` manifest before starting
+    /// the service.
+    pub verify_on_start:       bool,
+    /// Names of environment variables whose values are withheld from `hab svc env`, in addition
+    /// to the default patterns.
+    pub env_redact_patterns:   Vec<glob::Pattern>,
     pub sys_ip:                IpAddr,
 }
 
@@ -287,6 +363,9 @@ pub struct TLSConfig {
     pub cert_path:    PathBuf,
     pub key_path:     PathBuf,
     pub ca_cert_path: Option<PathBuf>,
+    /// Obtain and renew the certificate and key with ACME, rather than relying on the operator to
+    /// provide them.
+    pub acme:         Option<AcmeConfig>,
 }
 
 impl ManagerConfig {
@@ -304,6 +383,12 @@ impl ManagerConfig {
         spec.to_file(self.spec_path_for(&spec.ident))
     }
 
+    /// Every service spec on disk.
+    pub fn specs(&self) -> Vec<ServiceSpec> {
+        SpecDir::new(self.sup_root().join("specs")).map(|dir| dir.specs())
+                                                   .unwrap_or_default()
+    }
+
     /// Given a `PackageIdent`, return current spec if it exists.
     pub fn spec_for_ident(&self, ident: &PackageIdent) -> Option<ServiceSpec> {
         let spec_file = self.spec_path_for(ident);
@@ -383,7 +468,16 @@ pub struct ManagerState {
     cfg:            ManagerConfig,
     services:       Arc<sync::ManagerServices>,
     gateway_state:  Arc<sync::GatewayState>,
+    butterfly:      habitat_butterfly::Server,
     should_restart: AtomicBool,
+    /// Set by `hab sup drain`; no services are loaded or started until the Supervisor restarts.
+    draining:       AtomicBool,
+    /// The secret key ctl gateway clients authenticate with
+    ctl_secret:     CtlSecret,
+    /// The session tokens ctl gateway clients may authenticate with instead
+    ctl_sessions:   CtlSessions,
+    /// The nonces of the signed requests which have been taken
+    signed_nonces:  SignedRequestNonces,
 }
 
 pub(crate) mod sync {
@@ -403,6 +497,16 @@ pub(crate) mod sync {
 
         pub fn services_data(&self) -> &str { &self.0.services_data }
 
+        pub fn member_history(&self) -> &[Transition] { &self.0.member_history }
+
+        pub fn ring_health(&self) -> &RingHealthReport { &self.0.ring_health }
+
+        pub fn key_revocations(&self) -> &[RevocationSource] { &self.0.key_revocations }
+
+        pub fn service_history(&self) -> &ServiceHistory { &self.0.service_history }
+
+        pub fn event_replay(&self) -> &EventReplay { &self.0.event_replay }
+
         pub fn health_of(&self, service_group: &ServiceGroup) -> Option<HealthCheckResult> {
             self.0.health_check_data.get(service_group).copied()
         }
@@ -419,6 +523,26 @@ pub(crate) mod sync {
 
         pub fn set_services_data(&mut self, new_data: String) { self.0.services_data = new_data }
 
+        pub fn set_member_history(&mut self, new_data: Vec<Transition>) {
+            self.0.member_history = new_data
+        }
+
+        pub fn set_ring_health(&mut self, new_data: RingHealthReport) {
+            self.0.ring_health = new_data
+        }
+
+        /// The status of the revocation list fetched from `url`, added if it isn't tracked yet.
+        pub fn key_revocation_source_mut(&mut self, url: &str) -> &mut RevocationSource {
+            let sources = &mut self.0.key_revocations;
+            match sources.iter().position(|s| s.url == url) {
+                Some(i) => &mut sources[i],
+                None => {
+                    sources.push(RevocationSource::new(url));
+                    sources.last_mut().expect("just pushed a source")
+                }
+            }
+        }
+
         pub fn remove(&mut self, service_group: &ServiceGroup) {
             self.0.health_check_data.remove(service_group);
         }
@@ -426,6 +550,15 @@ pub(crate) mod sync {
         pub fn set_health_of(&mut self, service_group: ServiceGroup, value: HealthCheckResult) {
             self.0.health_check_data.insert(service_group, value);
         }
+
+        pub fn record_lifecycle(&mut self,
+                                service_group: &ServiceGroup,
+                                ident: &PackageIdent,
+                                lifecycle: Lifecycle) {
+            let transition = ServiceTransition::new(service_group, ident, lifecycle);
+            self.0.event_replay.push(transition.clone());
+            self.0.service_history.push(transition);
+        }
     }
 
     /// All the data that is ultimately served from the Supervisor's HTTP
@@ -453,6 +586,17 @@ pub(crate) mod sync {
         butterfly_data:    String,
         /// JSON returned by the /services endpoint
         services_data:     String,
+        /// Data returned by the /census/history endpoint
+        member_history:    Vec<Transition>,
+        /// Data returned by the /butterfly/ring-health endpoint
+        ring_health:       RingHealthReport,
+        /// Data returned by the `SupKeyRevocations` ctl gateway request
+        key_revocations:   Vec<RevocationSource>,
+        /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/history
+        /// endpoint
+        service_history:   ServiceHistory,
+        /// Data returned by the /events endpoint
+        event_replay:      EventReplay,
         /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/health
         /// endpoint
         health_check_data: HashMap<ServiceGroup, HealthCheckResult>,
@@ -549,11 +693,22 @@ pub struct Manager {
     // other threads (e.g., maybe we subscribe to messages to change
     // the watcher)
     user_config_watcher: UserConfigWatcher,
+    /// Watches the `config_from` directories of services loaded with `--watch`.
+    config_from_watcher: ConfigFromWatcher,
+    /// Verifies the files of services' packages before they start, with `--verify-on-start`.
+    package_verifier:    Option<PackageVerifier>,
+    /// Reloads the http-gateway's TLS configuration, when it has one.
+    tls_watcher:         Option<TlsWatcher>,
+    /// Renews the http-gateway's certificate, when it is obtained with ACME.
+    acme_client:         Option<Arc<AcmeClient>>,
     spec_dir:            SpecDir,
     organization:        Option<String>,
     self_updater:        Option<SelfUpdater>,
     sys:                 Arc<Sys>,
     http_disable:        bool,
+    gossip_timing:       Timing,
+    /// The update counter of the ring command store when ring commands were last applied.
+    ring_commands_seen:  usize,
     /// Though it is a `HashMap`, `service_states` not really used as
     /// a `HashMap`. The values are there to act as a kind of
     /// "snapshot marker"... if any of those time markers change
@@ -642,17 +797,18 @@ impl Manager {
                                cfg.ctl_listen,
                                cfg.http_listen,
                                cfg.sys_ip);
-        let member = Self::load_member(&mut sys, &fs_cfg)?;
+        let member = Self::load_member(&mut sys, &fs_cfg, &cfg.member_id_source)?;
         let services = Arc::default();
         let suitability_lookup = Arc::clone(&services) as Arc<dyn Suitability>;
 
-        let server = habitat_butterfly::Server::new(sys.gossip_listen(),
-                                                    sys.gossip_listen(),
-                                                    member,
-                                                    cfg.ring_key,
-                                                    None,
-                                                    Some(&fs_cfg.data_path),
-                                                    suitability_lookup)?;
+        let mut server = habitat_butterfly::Server::new(sys.gossip_listen(),
+                                                        sys.gossip_listen(),
+                                                        member,
+                                                        cfg.ring_key,
+                                                        None,
+                                                        Some(&fs_cfg.data_path),
+                                                        suitability_lookup)?;
+        server.set_rumor_limits(cfg.rumor_limits);
         outputln!("Supervisor Member-ID {}", sys.member_id);
         for peer_addr in &cfg.gossip_peers {
             let mut peer = Member::default();
@@ -678,16 +834,23 @@ impl Manager {
             let fqdn = habitat_core::os::net::fqdn().unwrap_or_else(|| sys.hostname.clone());
             outputln!("Event FQDN {}", fqdn);
 
-            event::init(&sys, fqdn, config).await?;
+            event::init(&sys, fqdn, config, &fs_cfg.data_path).await?;
         }
 
         let pid_source = ServicePidSource::determine_source(&launcher);
 
+        let ctl_secret = CtlSecret::new(ctl_gateway::readgen_secret_key(&fs_cfg.sup_root)?);
         let census_ring = Arc::new(RwLock::new(CensusRing::new(sys.member_id.clone())));
         Ok(Manager { state: Arc::new(ManagerState { cfg: cfg_static,
                                                     services,
                                                     gateway_state: Arc::default(),
-                                                    should_restart: AtomicBool::default() }),
+                                                    butterfly: server.clone(),
+                                                    should_restart: AtomicBool::default(),
+                                                    draining: AtomicBool::default(),
+                                                    ctl_secret,
+                                                    ctl_sessions: CtlSessions::default(),
+                                                    signed_nonces:
+                                                        SignedRequestNonces::default() }),
                      self_updater,
                      service_updater:
                          Arc::new(Mutex::new(ServiceUpdater::new(server.clone(),
@@ -699,12 +862,22 @@ impl Manager {
                      peer_watcher,
                      spec_watcher,
                      user_config_watcher: UserConfigWatcher::new(),
+                     config_from_watcher: ConfigFromWatcher::new(),
+                     package_verifier: if cfg.verify_on_start {
+                         Some(PackageVerifier::new())
+                     } else {
+                         None
+                     },
+                     tls_watcher: None,
+                     acme_client: None,
                      spec_dir,
                      fs_cfg: Arc::new(fs_cfg),
                      organization: cfg.organization,
                      service_states: HashMap::new(),
                      sys: Arc::new(sys),
                      http_disable: cfg.http_disable,
+                     gossip_timing: cfg.gossip_timing,
+                     ring_commands_seen: 0,
                      busy_services: Arc::default(),
                      services_need_reconciliation: ReconciliationFlag::new(false),
                      feature_flags: cfg.feature_flags,
@@ -713,7 +886,8 @@ impl Manager {
 
     /// Load the initial Butterly Member which is used in initializing the Butterfly server. This
     /// will load the member-id for the initial Member from disk if a previous manager has been
-    /// run.
+    /// run, unless the member-id is derived from a machine identity, in which case the derived
+    /// member-id is saved in its place.
     ///
     /// The mutable ref to `Sys` will be configured with Butterfly Member details and will also
     /// populate the initial Member.
@@ -722,26 +896,36 @@ impl Manager {
     // in there, so splitting the initialization is needlessly
     // confusing. It's also blurs the lines between the manager and
     // Butterfly.
-    fn load_member(sys: &mut Sys, fs_cfg: &FsCfg) -> Result<Member> {
+    fn load_member(sys: &mut Sys,
+                   fs_cfg: &FsCfg,
+                   member_id_source: &MemberIdSource)
+                   -> Result<Member> {
         let mut member = Member::default();
-        match File::open(&fs_cfg.member_id_file) {
-            Ok(mut file) => {
-                let mut member_id = String::new();
-                file.read_to_string(&mut member_id).map_err(|e| {
-                                                        Error::BadDataFile(fs_cfg.member_id_file
-                                                                                 .clone(),
-                                                                           e)
-                                                    })?;
-                member.id = member_id;
-            }
-            Err(_) => {
-                match File::create(&fs_cfg.member_id_file) {
-                    Ok(mut file) => {
-                        file.write(member.id.as_bytes())
-                            .map_err(|e| Error::BadDataFile(fs_cfg.member_id_file.clone(), e))?;
-                    }
-                    Err(err) => {
-                        return Err(Error::BadDataFile(fs_cfg.member_id_file.clone(), err));
+        if let Some(identity_path) = member_id_source.identity_path() {
+            member.id = derived_member_id(&identity_path)?;
+            fs::write(&fs_cfg.member_id_file, member.id.as_bytes())
+                .map_err(|e| Error::BadDataFile(fs_cfg.member_id_file.clone(), e))?;
+        } else {
+            match File::open(&fs_cfg.member_id_file) {
+                Ok(mut file) => {
+                    let mut member_id = String::new();
+                    file.read_to_string(&mut member_id).map_err(|e| {
+                                                            Error::BadDataFile(fs_cfg.member_id_file
+                                                                                     .clone(),
+                                                                               e)
+                                                        })?;
+                    member.id = member_id;
+                }
+                Err(_) => {
+                    match File::create(&fs_cfg.member_id_file) {
+                        Ok(mut file) => {
+                            file.write(member.id.as_bytes()).map_err(|e| {
+                                Error::BadDataFile(fs_cfg.member_id_file.clone(), e)
+                            })?;
+                        }
+                        Err(err) => {
+                            return Err(Error::BadDataFile(fs_cfg.member_id_file.clone(), err));
+                        }
                     }
                 }
             }
@@ -807,9 +991,10 @@ impl Manager {
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
     /// * `MemberList::entries` (write)
+    /// * `GatewayState::inner` (write)
     /// * `RumorHeat::inner` (write)
     /// * `ManagerServices::inner` (read)
-    async fn add_service_rsw_mlw_rhw_msr(&mut self, spec: ServiceSpec) {
+    async fn add_service_rsw_mlw_gsw_rhw_msr(&mut self, spec: ServiceSpec) {
         let ident = spec.ident.clone();
         let mut service = match Service::new(self.sys.clone(),
                                              spec,
@@ -831,6 +1016,14 @@ impl Manager {
             }
         };
 
+        if let Some(package_verifier) = self.package_verifier.as_mut() {
+            if let Err(err) = package_verifier.verify(&service) {
+                outputln!("Refusing to start {}, {}", ident, err);
+                self.remove_spec_file(&ident).ok();
+                return;
+            }
+        }
+
         if let Ok(package) =
             PackageInstall::load(service.pkg.ident.as_ref(), Some(Path::new(&*FS_ROOT_PATH)))
         {
@@ -873,11 +1066,19 @@ impl Manager {
             return;
         }
 
+        self.watch_config_from(&service);
+
         self.maybe_uninstall_old_packages(&ident).await;
 
         self.service_updater.lock().register(&service);
 
         event::service_started(&service);
+        self.state
+            .gateway_state
+            .lock_gsw()
+            .record_lifecycle(&service.service_group,
+                              service.pkg.ident.as_ref(),
+                              Lifecycle::Loaded);
 
         self.state
             .services
@@ -904,6 +1105,14 @@ impl Manager {
         let main_hist = RUN_LOOP_DURATION.with_label_values(&["sup"]);
         let service_hist = RUN_LOOP_DURATION.with_label_values(&["service"]);
         let mut next_cpu_measurement = Instant::now();
+        let mut next_ring_health_check = Instant::now();
+        let mut next_key_revocation_fetch = Instant::now();
+        let mut next_tls_check = Instant::now();
+        let mut next_disk_space_check = Instant::now();
+        let disk_watchdog = DiskWatchdog::new();
+        let acme_renewal_period: Duration = AcmeRenewalCheckPeriod::configured_value().into();
+        // A certificate obtained with ACME is renewed as needed on startup.
+        let mut next_acme_renewal_check = Instant::now() + acme_renewal_period;
         let mut cpu_start = ProcessTime::now();
 
         // TODO (CM): consider bundling up these disparate channel
@@ -936,7 +1145,7 @@ impl Manager {
         outputln!("Starting gossip-listener on {}",
                   self.butterfly.gossip_addr());
         self.butterfly
-            .start_rsw_mlw_smw_rhw_msr(&Timing::default())?;
+            .start_rsw_mlw_smw_rhw_msr(&self.gossip_timing)?;
         debug!("gossip-listener started");
 
         // This serves to start up any services that need starting
@@ -946,9 +1155,17 @@ impl Manager {
         self.persist_state_rsr_mlr_gsw_msr().await;
         let http_listen_addr = self.sys.http_listen();
         let ctl_listen_addr = self.sys.ctl_listen();
-        let ctl_secret_key = ctl_gateway::readgen_secret_key(&self.fs_cfg.sup_root)?;
+        let ctl_tls_config = match &self.state.cfg.ctl_tls_config {
+            Some(c) => Some(Arc::new(tls_config(c)?)),
+            None => None,
+        };
         outputln!("Starting ctl-gateway on {}", &ctl_listen_addr);
-        tokio::spawn(ctl_gateway::server::run(ctl_listen_addr, ctl_secret_key, mgr_sender));
+        tokio::spawn(ctl_gateway::server::run(ctl_listen_addr,
+                                              self.state.ctl_secret.clone(),
+                                              self.state.ctl_sessions.clone(),
+                                              self.state.cfg.ctl_require_session,
+                                              ctl_tls_config,
+                                              mgr_sender));
         debug!("ctl-gateway started");
 
         if self.http_disable {
@@ -958,6 +1175,15 @@ impl Manager {
             // appropriate config here, where it's easy to propagate errors, vs in a separate
             // thread, where that process is more cumbersome.
 
+            if let Some(c) = &self.state.cfg.tls_config {
+                if let Some(acme_config) = &c.acme {
+                    let client = Arc::new(AcmeClient::new(acme_config.clone(),
+                                                          c,
+                                                          self.fs_cfg.sup_root.join("acme")));
+                    acme::renew_at_startup(Arc::clone(&client)).await?;
+                    self.acme_client = Some(client);
+                }
+            }
             let tls_server_config = match &self.state.cfg.tls_config {
                 Some(c) => {
                     match tls_config(c) {
@@ -967,6 +1193,10 @@ impl Manager {
                 }
                 None => None,
             };
+            let (tls_reload_tx, tls_reload_rx) = fut_mpsc::unbounded();
+            if let Some(c) = &self.state.cfg.tls_config {
+                self.tls_watcher = Some(TlsWatcher::new(c.clone(), tls_reload_tx));
+            }
 
             // Here we use a Condvar to wait on the HTTP gateway server to start up and inspect its
             // return value. Specifically, we're looking for errors when it tries to bind to the
@@ -974,11 +1204,15 @@ impl Manager {
             let pair =
                 Arc::new((StdMutex::new(http_gateway::ServerStartup::NotStarted), Condvar::new()));
 
+            let auth_token = http_gateway::GatewayAuthenticationToken::configured_value_or(
+                self.state.cfg.http_auth_token.clone(),
+            );
             outputln!("Starting http-gateway on {}", &http_listen_addr);
             http_gateway::Server::run(http_listen_addr,
                                       tls_server_config,
+                                      tls_reload_rx,
                                       self.state.gateway_state.clone(),
-                                      http_gateway::GatewayAuthenticationToken::configured_value(),
+                                      auth_token,
                                       self.feature_flags,
                                       pair.clone());
 
@@ -1103,9 +1337,16 @@ impl Manager {
                         self.stop_service_gsw_msw(&service_spec.ident, &shutdown_input);
                     }
                     UnloadService { service_spec,
-                                    shutdown_input, } => {
+                                    shutdown_input,
+                                    data, } => {
                         self.remove_spec_file(&service_spec.ident).ok();
-                        self.stop_service_gsw_msw(&service_spec.ident, &shutdown_input);
+                        if data.retains_only() {
+                            self.stop_service_gsw_msw(&service_spec.ident, &shutdown_input);
+                        } else {
+                            self.unload_service_gsw_msw(&service_spec.ident,
+                                                        &shutdown_input,
+                                                        data);
+                        }
                     }
                     UpdateService { service_spec } => {
                         trace!("Received UpdateService action for {}", service_spec.ident);
@@ -1114,9 +1355,22 @@ impl Manager {
                                   service_spec.ident, err);
                         }
                     }
+                    Drain { shutdown_input,
+                            progress, } => {
+                        self.drain_services_gsw_msw(&shutdown_input, progress);
+                    }
                 }
             }
 
+            // Ring commands update spec files, which are then picked up below.
+            self.apply_ring_commands_rsw_rhw();
+
+            // Services holding changes for a restart apply them once
+            // their update window opens.
+            if self.state.services.lock_msr().services().any(Service::restart_due) {
+                self.services_need_reconciliation.set();
+            }
+
             // Indicates if we need to examine our on-disk specfiles
             // in order to reconcile them with whatever we're
             // currently running.
@@ -1144,14 +1398,16 @@ impl Manager {
 
             self.update_peers_from_watch_file_mlr_imlw()?;
             self.update_running_services_from_user_config_watcher_msw();
+            self.update_running_services_from_config_from_watcher_msw();
 
             // Restart all services that need it
-            self.restart_services_rsw_mlr_rhw_msw();
+            self.restart_services_rsw_mlr_gsw_rhw_msw();
 
             self.restart_elections_rsw_mlr_rhw_msr(self.feature_flags);
             self.census_ring
                 .write()
                 .update_from_rumors_rsr_mlr(&self.state.cfg.cache_key_path,
+                                            &self.state.cfg.trusted_signers,
                                             &self.butterfly.service_store,
                                             &self.butterfly.election_store,
                                             &self.butterfly.update_store,
@@ -1193,6 +1449,44 @@ impl Manager {
                 next_cpu_measurement = Instant::now() + Duration::from_secs(1);
                 cpu_start = ProcessTime::now();
             }
+
+            if Instant::now() >= next_ring_health_check {
+                self.check_ring_health_mlr_gsw();
+                let period: Duration = RingHealthCheckPeriod::configured_value().into();
+                next_ring_health_check = Instant::now() + period;
+            }
+
+            if !self.state.cfg.key_revocation_feeds.is_empty()
+               && Instant::now() >= next_key_revocation_fetch
+            {
+                key_revocation::fetch_all(&self.state.cfg.key_revocation_feeds,
+                                          &self.state.cfg.cache_key_path,
+                                          &self.state.gateway_state);
+                let period: Duration = KeyRevocationFetchPeriod::configured_value().into();
+                next_key_revocation_fetch = Instant::now() + period;
+            }
+
+            if Instant::now() >= next_disk_space_check {
+                disk_watchdog.check();
+                let period: Duration = DiskSpaceCheckPeriod::configured_value().into();
+                next_disk_space_check = Instant::now() + period;
+            }
+
+            if let Some(tls_watcher) = self.tls_watcher.as_mut() {
+                if Instant::now() >= next_tls_check {
+                    tls_watcher.check();
+                    let period: Duration = TlsReloadCheckPeriod::configured_value().into();
+                    next_tls_check = Instant::now() + period;
+                }
+            }
+
+            if let Some(acme_client) = &self.acme_client {
+                if Instant::now() >= next_acme_renewal_check {
+                    acme::renew_in_background(acme_client);
+                    let period: Duration = AcmeRenewalCheckPeriod::configured_value().into();
+                    next_acme_renewal_check = Instant::now() + period;
+                }
+            }
         }; // end main loop
 
         // When we make it down here, we've broken out of the main
@@ -1217,17 +1511,27 @@ impl Manager {
                 outputln!("Gracefully departing from butterfly network.");
                 self.butterfly.set_departed_mlw_smw_rhw();
 
-                let service_stop_futures =
-                    FuturesUnordered::from_iter(self.state
-                                                    .services
-                                                    .lock_msw()
-                                                    .drain_services()
-                                                    .map(|svc| {
-                                                        self.stop_service_future_gsw(svc, None,
-                                                                                     None)
-                                                    }));
-                // Wait while all services are stopped
-                service_stop_futures.collect::<Vec<_>>().await;
+                // Services are stopped in groups, highest shutdown order
+                // first, so that a service can outlive the services that
+                // depend on it.
+                let mut groups: BTreeMap<i32, Vec<Service>> = BTreeMap::new();
+                for svc in self.state.services.lock_msw().drain_services() {
+                    groups.entry(svc.shutdown_order()).or_default().push(svc);
+                }
+                let delay: Duration = ShutdownGroupDelay::configured_value().into();
+                let mut groups = groups.into_iter().rev().peekable();
+                while let Some((order, services)) = groups.next() {
+                    debug!("Stopping services with shutdown order {}", order);
+                    let service_stop_futures =
+                        FuturesUnordered::from_iter(services.into_iter().map(|svc| {
+                            self.stop_service_future_gsw(svc, None, None)
+                        }));
+                    // Wait while all services in this group are stopped
+                    service_stop_futures.collect::<Vec<_>>().await;
+                    if groups.peek().is_some() && delay > Duration::from_secs(0) {
+                        tokio::time::delay_for(delay).await;
+                    }
+                }
             }
         }
 
@@ -1252,9 +1556,10 @@ impl Manager {
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
     /// * `MemberList::entries` (read)
+    /// * `GatewayState::inner` (write)
     /// * `RumorHeat::inner` (write)
     /// * `ManagerServices::inner` (write)
-    fn restart_services_rsw_mlr_rhw_msw(&mut self) {
+    fn restart_services_rsw_mlr_gsw_rhw_msw(&mut self) {
         let service_updater = self.service_updater.lock();
 
         let mut state_services = self.state.services.lock_msw();
@@ -1263,6 +1568,14 @@ impl Manager {
             if let Some(new_ident) = service_updater.has_update(&service.service_group) {
                 outputln!("Restarting {} with package {}", ident, new_ident);
                 event::service_update_started(&service, &new_ident);
+                let lifecycle = Lifecycle::Updated { from: service.pkg.ident.clone().into(),
+                                                     to:   new_ident.clone(), };
+                self.state
+                    .gateway_state
+                    .lock_gsw()
+                    .record_lifecycle(&service.service_group,
+                                      service.pkg.ident.as_ref(),
+                                      lifecycle);
                 // The supervisor always runs the latest package on disk. When we have an update
                 // ensure that the lastest package on disk is the package we updated to.
                 idents_to_restart_and_latest_desired_on_restart.push((ident.clone(),
@@ -1310,6 +1623,55 @@ impl Manager {
 
     fn check_for_departure(&self) -> bool { self.butterfly.is_departed() }
 
+    /// Apply the ring commands this Supervisor has not acknowledged yet, gossiping the outcome of
+    /// each. Any member can gossip a copy of a ring command, so only a copy signed by a trusted
+    /// signer is applied, and the others are ignored. A Supervisor which trusts no signer can not
+    /// apply any copy, and acknowledges the command with why.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    fn apply_ring_commands_rsw_rhw(&mut self) {
+        let counter = self.butterfly.ring_command_store.get_update_counter();
+        if counter == self.ring_commands_seen {
+            return;
+        }
+        self.ring_commands_seen = counter;
+        for copies in self.butterfly.pending_ring_commands_rsr() {
+            let verified = copies.iter().find_map(|command| {
+                                            commands::ring_command_signer(&self.state, command)
+                                                .ok()
+                                                .map(|signer| (command, signer))
+                                        });
+            let (command, result) = match verified {
+                Some((command, signer)) => {
+                    (command, commands::apply_ring_command(&self.state, command, &signer))
+                }
+                None if self.state.cfg.trusted_signers.is_empty() => {
+                    let command = &copies[0];
+                    let result =
+                        commands::ring_command_signer(&self.state, command).and_then(|signer| {
+                            commands::apply_ring_command(&self.state, command, &signer)
+                        });
+                    (command, result)
+                }
+                None => {
+                    debug!("Ignoring ring command {}, none of its copies is signed by a trusted \
+                            signer",
+                           copies[0].id);
+                    continue;
+                }
+            };
+            match &result {
+                Ok(message) => outputln!("Applied ring command {}: {}", command.id, message),
+                Err(message) => {
+                    outputln!("Could not apply ring command {}: {}", command.id, message)
+                }
+            }
+            self.butterfly.acknowledge_ring_command_rsw_rhw(command, result);
+        }
+    }
+
     fn check_for_restart(&self) -> bool {
         let should_restart = self.state.should_restart.load(Ordering::Relaxed);
         #[cfg(unix)]
@@ -1384,7 +1746,40 @@ impl Manager {
     fn persist_butterfly_state_rsr_mlr_gsw(&self) {
         let bs = ServerProxy::new(&self.butterfly);
         let json = serde_json::to_string(&bs).expect("ServerProxy::serialize failure");
-        self.state.gateway_state.lock_gsw().set_butterfly_data(json);
+        let history = self.butterfly.member_list.history_transitions();
+        let mut gateway_state = self.state.gateway_state.lock_gsw();
+        gateway_state.set_butterfly_data(json);
+        gateway_state.set_member_history(history);
+    }
+
+    /// Analyze the health of the gossip ring, making the result available
+    /// to the HTTP gateway and `hab sup ring-health`. Changes in status
+    /// are logged and sent to the event stream.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    /// * `GatewayState::inner` (write)
+    fn check_ring_health_mlr_gsw(&self) {
+        let report = self.butterfly.ring_health_report_mlr();
+        let previous = self.state.gateway_state.lock_gsr().ring_health().status;
+        if report.status != previous {
+            match report.status {
+                RingHealthStatus::Healthy => outputln!("Gossip ring is healthy"),
+                RingHealthStatus::Degraded => {
+                    outputln!("Gossip ring is degraded; {} disagreement(s) with peers about \
+                               member reachability",
+                              report.view_conflicts.len())
+                }
+                RingHealthStatus::PartitionSuspected => {
+                    outputln!("Gossip ring partition suspected; unreachable members: [{}], \
+                               conflicting elections: {}",
+                              report.unreachable_members().join(", "),
+                              report.election_conflicts.len())
+                }
+            }
+            event::ring_health_changed(&report);
+        }
+        self.state.gateway_state.lock_gsw().set_ring_health(report);
     }
 
     /// # Locking (see locking.md)
@@ -1463,9 +1858,118 @@ impl Manager {
         }
     }
 
+    /// Stop a service being unloaded, then archive or purge its data directory as requested.
+    ///
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (write)
+    /// * `ManagerServices::inner` (write)
+    fn unload_service_gsw_msw(&mut self,
+                              ident: &PackageIdent,
+                              shutdown_input: &ShutdownInput,
+                              data: DataDisposition) {
+        let service = match self.remove_service_from_state_msw(&ident) {
+            Some(service) => service,
+            None => {
+                warn!("Tried to unload '{}', but couldn't find it in our list of running \
+                       services!",
+                      ident);
+                return;
+            }
+        };
+        let metadata = service.to_service_metadata();
+        let service_group = service.service_group.clone();
+        let data_path = service.pkg.svc_data_path.clone();
+        let cache_key_path = self.state.cfg.cache_key_path.clone();
+        let stop_it = self.stop_service_future_gsw(service, None, Some(shutdown_input));
+        tokio::spawn(async move {
+            stop_it.await;
+            let to_apply = data.clone();
+            let applied = tokio::task::spawn_blocking(move || {
+                              to_apply.apply(&service_group, &data_path, &cache_key_path)
+                          }).await;
+            let error = match applied {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(ref e) = error {
+                outputln!("Unable to archive or purge the data of {}: {}",
+                          metadata.service_group,
+                          e);
+            }
+            event::service_data_disposed(metadata, &data, error.as_deref());
+        });
+    }
+
+    /// Stop every running service for `hab sup drain`, leaving their specs untouched so they are
+    /// started again when the Supervisor restarts. Services are stopped in groups: highest
+    /// shutdown order first, and within a shutdown order, services which bind to another of our
+    /// services before that service. Each service is reported to `progress` once it has stopped.
+    ///
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (write)
+    /// * `ManagerServices::inner` (write)
+    fn drain_services_gsw_msw(&mut self,
+                              shutdown_input: &ShutdownInput,
+                              progress: std_mpsc::Sender<String>) {
+        let services = self.state
+                           .services
+                           .lock_msw()
+                           .drain_services()
+                           .collect::<Vec<_>>();
+        let waves = stop_waves(&services.iter()
+                                        .map(|svc| {
+                                            (svc.shutdown_order(),
+                                             svc.service_group.clone(),
+                                             svc.spec()
+                                                .binds
+                                                .iter()
+                                                .map(|b| {
+                                                    b.service_group()
+                                                     .in_org(svc.service_group.org())
+                                                })
+                                                .collect())
+                                        })
+                                        .collect::<Vec<_>>());
+        outputln!("Draining {} service(s)", services.len());
+        let mut services = services.into_iter().map(Some).collect::<Vec<_>>();
+        let waves =
+            waves.into_iter()
+                 .map(|(order, wave)| {
+                     let stops =
+                         wave.into_iter()
+                             .filter_map(|i| services[i].take())
+                             .map(|svc| {
+                                 let ident = svc.spec_ident();
+                                 (ident,
+                                  self.stop_service_future_gsw(svc, None, Some(shutdown_input)))
+                             })
+                             .collect::<Vec<_>>();
+                     (order, stops)
+                 })
+                 .collect::<Vec<_>>();
+        let delay: Duration = ShutdownGroupDelay::configured_value().into();
+        tokio::spawn(async move {
+            let mut previous_order = None;
+            for (order, stops) in waves {
+                if previous_order.map_or(false, |o| o != order) && delay > Duration::from_secs(0) {
+                    tokio::time::delay_for(delay).await;
+                }
+                previous_order = Some(order);
+                let (idents, futures): (Vec<_>, Vec<_>) = stops.into_iter().unzip();
+                FuturesUnordered::from_iter(futures).collect::<Vec<_>>()
+                                                    .await;
+                for ident in idents {
+                    progress.send(format!("Stopped {}", ident)).ok();
+                }
+            }
+            outputln!("Supervisor drained; all services are stopped");
+        });
+    }
+
     /// Create a future for stopping a Service removing it from the manager. The Service is assumed
     /// to have been removed from the internal list of active services already (see, e.g.,
-    /// restart_services_rsw_mlr_rhw_msw and remove_service_from_state).
+    /// restart_services_rsw_mlr_gsw_rhw_msw and remove_service_from_state).
     /// # Locking for the returned Future (see locking.md)
     /// * `GatewayState::inner` (write)
     fn stop_service_future_gsw(&self,
@@ -1474,6 +1978,7 @@ impl Manager {
                                shutdown_input: Option<&ShutdownInput>)
                                -> impl Future<Output = ()> {
         let mut user_config_watcher = self.user_config_watcher.clone();
+        let config_from_watcher = self.config_from_watcher.clone();
         let service_updater = Arc::clone(&self.service_updater);
         let busy_services = Arc::clone(&self.busy_services);
         let services_need_reconciliation = self.services_need_reconciliation.clone();
@@ -1487,6 +1992,7 @@ impl Manager {
             service.stop_gsw(shutdown_config).await;
             event::service_stopped(&service);
             user_config_watcher.remove(&service);
+            config_from_watcher.remove(&service.service_group);
             service_updater.lock().remove(&service.service_group);
             // At this point the service process is stopped but the package is still loaded by the
             // Supervisor.
@@ -1633,8 +2139,14 @@ impl Manager {
                     }
                 }
                 ServiceOperation::Start(spec) => {
+                    if self.state.draining.load(Ordering::Relaxed) {
+                        debug!("Not starting {} while the Supervisor is draining",
+                               spec.ident);
+                        continue;
+                    }
+                    let spec = self.apply_held_restart(spec);
                     // Execute the future synchronously
-                    self.add_service_rsw_mlw_rhw_msr(spec).await;
+                    self.add_service_rsw_mlw_gsw_rhw_msr(spec).await;
                 }
                 ServiceOperation::Update(spec, ops) => {
                     trace!("ServiceOperation::Update! {:?}", spec);
@@ -1648,6 +2160,10 @@ impl Manager {
                                 RefreshOperation::RestartUpdater => {
                                     self.service_updater.lock().register(&s);
                                 }
+                                RefreshOperation::RestartConfigFromWatcher => {
+                                    self.config_from_watcher.remove(&s.service_group);
+                                    self.watch_config_from(s);
+                                }
                             }
                         }
                     } else {
@@ -1660,8 +2176,43 @@ impl Manager {
                                   &spec.ident);
                     }
                 }
+                ServiceOperation::HoldRestart(spec) => {
+                    let ident = spec.ident.clone();
+                    let newly_held = self.state
+                                         .services
+                                         .lock_msw()
+                                         .get_mut(&ident)
+                                         .map(|s| s.hold_restart_gsw(spec));
+                    match newly_held {
+                        Some(true) => {
+                            outputln!("Changes to {} which need a restart are held until it is \
+                                       next started or its update window opens",
+                                      ident);
+                        }
+                        Some(false) => {}
+                        None => {
+                            outputln!("Tried to hold changes for service {} but could not find \
+                                       it running, skipping",
+                                      ident);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// A service starting from a spec whose changes were held for a
+    /// restart applies them now, so the spec on disk no longer needs
+    /// to mark them as pending.
+    fn apply_held_restart(&self, mut spec: ServiceSpec) -> ServiceSpec {
+        if spec.restart_pending {
+            spec.restart_pending = false;
+            if let Err(err) = self.state.cfg.save_spec_for(&spec) {
+                warn!("Applied held changes to '{}', but couldn't update the spec: {:?}",
+                      spec.ident, err);
             }
         }
+        spec
     }
 
     /// Determine what services we need to start, stop, or restart in
@@ -1755,10 +2306,89 @@ impl Manager {
             }
         }
     }
+
+    /// Starts watching the `config_from` directory of a service loaded with `--watch`.
+    fn watch_config_from(&self, service: &Service) {
+        if let Some(path) = service.watched_config_from() {
+            match self.config_from_watcher.add(&service.service_group, path) {
+                Ok(()) => {
+                    outputln!(preamble service.service_group, "Watching {}", path.display())
+                }
+                Err(e) => {
+                    outputln!("Unable to watch {} for {}: {}",
+                              path.display(),
+                              service.spec_ident(),
+                              e)
+                }
+            }
+        }
+    }
+
+    /// # Locking (see locking.md)
+    /// * `ManagerServices::inner` (write)
+    fn update_running_services_from_config_from_watcher_msw(&mut self) {
+        for service in self.state.services.lock_msw().services() {
+            if self.config_from_watcher.have_events_for(&service.service_group) {
+                outputln!("config-from changes detected for {}", &service.spec_ident());
+                service.config_from_updated = true;
+            }
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////
 
+/// Orders services, given as their shutdown order, service group and the service groups they
+/// bind to, into the groups they are stopped in when draining. Each group is returned with its
+/// shutdown order and the indices of its services. Services bound to by another service with
+/// the same shutdown order wait for it to stop; services which bind to each other are stopped
+/// together.
+fn stop_waves(services: &[(i32, ServiceGroup, Vec<ServiceGroup>)]) -> Vec<(i32, Vec<usize>)> {
+    let mut orders: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    for (i, (order, ..)) in services.iter().enumerate() {
+        orders.entry(*order).or_default().push(i);
+    }
+    let mut waves = Vec::new();
+    for (order, mut remaining) in orders.into_iter().rev() {
+        while !remaining.is_empty() {
+            let (wave, rest): (Vec<usize>, Vec<usize>) =
+                remaining.iter().partition(|&&i| {
+                                    !remaining.iter()
+                                              .any(|&j| {
+                                                  j != i && services[j].2.contains(&services[i].1)
+                                              })
+                                });
+            if wave.is_empty() {
+                waves.push((order, rest));
+                break;
+            }
+            waves.push((order, wave));
+            remaining = rest;
+        }
+    }
+    waves
+}
+
+/// Derive a member-id from the machine identity in `identity_path`. The identity is hashed
+/// rather than used directly so the member-id, which is gossiped to the whole ring, does not
+/// disclose it. Member-ids are the same length as the random ones they stand in for.
+fn derived_member_id(identity_path: &Path) -> Result<String> {
+    let identity =
+        fs::read_to_string(identity_path).map_err(|e| {
+                                             Error::BadMemberIdentity(identity_path.to_path_buf(),
+                                                                      e)
+                                         })?;
+    let identity = identity.trim();
+    if identity.is_empty() {
+        return Err(Error::BadMemberIdentity(identity_path.to_path_buf(),
+                                            io::Error::new(io::ErrorKind::InvalidData,
+                                                           "identity is empty")));
+    }
+    let mut member_id = hash::hash_string(&format!("habitat-member-id:{}", identity));
+    member_id.truncate(32);
+    Ok(member_id)
+}
+
 fn tls_config(config: &TLSConfig) -> Result<rustls::ServerConfig> {
     let client_auth = match &config.ca_cert_path {
         Some(path) => {
@@ -1788,10 +2418,14 @@ fn tls_config(config: &TLSConfig) -> Result<rustls::ServerConfig> {
         pemfile::certs(cert_file).and_then(|c| if c.is_empty() { Err(()) } else { Ok(c) })
                                  .map_err(|_| Error::InvalidCertFile(config.cert_path.clone()))?;
 
-    let key = pemfile::rsa_private_keys(key_file).and_then(|mut k| k.pop().ok_or(()))
-                                                 .map_err(|_| {
-                                                     Error::InvalidKeyFile(config.key_path.clone())
-                                                 })?;
+    // Keys obtained with ACME are PKCS8-encoded rather than plain RSA keys.
+    let mut keys = pemfile::rsa_private_keys(key_file).unwrap_or_default();
+    if keys.is_empty() {
+        let key_file = &mut BufReader::new(File::open(&config.key_path)?);
+        keys = pemfile::pkcs8_private_keys(key_file).unwrap_or_default();
+    }
+    let key = keys.pop()
+                  .ok_or_else(|| Error::InvalidKeyFile(config.key_path.clone()))?;
 
     server_config.set_single_cert(cert_chain, key)?;
     server_config.ignore_client_order = true;
@@ -1989,15 +2623,25 @@ mod test {
                             ctl_listen:            ListenCtlAddr::default(),
                             http_listen:           HttpListenAddr::default(),
                             http_disable:          false,
+                            http_auth_token:       None,
                             gossip_peers:          vec![],
                             gossip_permanent:      false,
+                            member_id_source:      MemberIdSource::default(),
+                            rumor_limits:          RumorLimits::default(),
+                            gossip_timing:         Timing::default(),
                             ring_key:              None,
                             organization:          None,
                             watch_peer_file:       None,
                             tls_config:            None,
+                            ctl_tls_config:        None,
+                            ctl_require_session:   false,
                             feature_flags:         FeatureFlag::empty(),
                             event_stream_config:   None,
                             keep_latest_packages:  None,
+                            trusted_signers:       vec![],
+                            key_revocation_feeds:  vec![],
+                            verify_on_start:       false,
+                            env_redact_patterns:   vec![],
                             sys_ip:                IpAddr::V4(Ipv4Addr::LOCALHOST), }
         }
     }
@@ -2028,4 +2672,37 @@ mod test {
 
         assert_eq!(PathBuf::from("/tmp/partay"), path);
     }
+
+    #[test]
+    fn stop_waves_stop_dependents_first() {
+        let sg = |s: &str| s.parse::<ServiceGroup>().unwrap();
+        let services = vec![(0, sg("redis.default"), vec![]),
+                            (0, sg("app.default"), vec![sg("redis.default")]),
+                            (0, sg("proxy.default"), vec![sg("app.default")]),
+                            (0, sg("cron.default"), vec![]),
+                            (10, sg("agent.default"), vec![]),
+                            (-5, sg("a.default"), vec![sg("b.default")]),
+                            (-5, sg("b.default"), vec![sg("a.default")]),];
+        assert_eq!(stop_waves(&services),
+                   vec![(10, vec![4]),
+                        (0, vec![2, 3]),
+                        (0, vec![1]),
+                        (0, vec![0]),
+                        (-5, vec![5, 6]),]);
+    }
+
+    #[test]
+    fn derived_member_id_is_stable_and_hides_the_identity() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let identity_path = tmpdir.path().join("machine-id");
+        fs::write(&identity_path, "4f1c0ad6a1c44ae5bd8f7e0ce7df6f9b\n").unwrap();
+
+        let member_id = derived_member_id(&identity_path).unwrap();
+        assert_eq!(member_id.len(), 32);
+        assert_ne!(member_id, "4f1c0ad6a1c44ae5bd8f7e0ce7df6f9b");
+        assert_eq!(member_id, derived_member_id(&identity_path).unwrap());
+
+        fs::write(&identity_path, "  \n").unwrap();
+        assert!(derived_member_id(&identity_path).is_err());
+    }
 }
@@ -0,0 +1,462 @@
+//! Obtains and renews the HTTP gateway certificate from an ACME server, such as Let's Encrypt.
+//!
+//! The ACME account key and the last certificate issued are kept in the `acme` directory of the
+//! Supervisor's state. The certificate and its private key are then installed in the gateway's
+//! `--certs` and `--key` files, where the `TlsWatcher` picks up each renewal.
+
+use super::TLSConfig;
+use crate::error::{Error,
+                   Result};
+use acme_lib::{create_rsa_key,
+               order::Auth,
+               persist::{Persist,
+                         PersistKey,
+                         PersistKind},
+               Account,
+               Certificate,
+               Directory,
+               DirectoryUrl};
+use habitat_common::outputln;
+use habitat_core::fs::{AtomicWriter,
+                       Permissions,
+                       DEFAULT_SECRET_KEY_PERMISSIONS};
+use std::{fs,
+          io::{self,
+               BufRead,
+               BufReader,
+               Write},
+          net::{SocketAddr,
+                TcpListener,
+                TcpStream},
+          path::{Path,
+                 PathBuf},
+          process::Command,
+          sync::{atomic::{AtomicBool,
+                          Ordering},
+                 Arc},
+          thread::{self,
+                   JoinHandle},
+          time::Duration};
+
+static LOGKEY: &str = "AC";
+
+habitat_core::env_config_duration!(
+    /// How often the HTTP gateway's ACME certificate is checked for renewal.
+    AcmeRenewalCheckPeriod,
+    HAB_ACME_RENEWAL_CHECK_PERIOD_SECS => from_secs,
+    Duration::from_secs(12 * 60 * 60));
+
+/// A certificate is renewed once it has fewer days left than this.
+const RENEW_BEFORE_DAYS: i64 = 30;
+/// How long to wait between polls of the ACME server while it validates a domain or issues the
+/// certificate.
+const POLL_DELAY_MILLIS: u64 = 5000;
+/// How many times each domain's validation is attempted before giving up on an order.
+const VALIDATION_ATTEMPTS: usize = 3;
+/// The gateway only reads RSA private keys.
+const RSA_KEY_BITS: u32 = 2048;
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const HTTP_CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcmeConfig {
+    /// The domains of the certificate. The first is its subject, the others its alternative
+    /// names.
+    pub domains:       Vec<String>,
+    pub email:         String,
+    pub directory_url: String,
+    pub validation:    AcmeValidation,
+}
+
+/// How the Supervisor answers the ACME server's challenges.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AcmeValidation {
+    /// Serve `http-01` challenge tokens on this address.
+    Http01(SocketAddr),
+    /// Publish `dns-01` challenge records with this program.
+    Dns01(PathBuf),
+}
+
+pub struct AcmeClient {
+    config:    AcmeConfig,
+    key_path:  PathBuf,
+    cert_path: PathBuf,
+    state:     StatePersist,
+    /// Whether a renewal is running, so that a slow one isn't started over.
+    renewing:  AtomicBool,
+}
+
+impl AcmeClient {
+    /// A client installing certificates for `config` in the files of `tls`, keeping its own
+    /// state in `state_path`.
+    pub fn new(config: AcmeConfig, tls: &TLSConfig, state_path: PathBuf) -> Self {
+        AcmeClient { config,
+                     key_path: tls.key_path.clone(),
+                     cert_path: tls.cert_path.clone(),
+                     state: StatePersist { path: state_path },
+                     renewing: AtomicBool::new(false) }
+    }
+
+    /// Whether the gateway has a certificate and key to start with, renewed or not.
+    pub fn is_installed(&self) -> bool { self.key_path.is_file() && self.cert_path.is_file() }
+
+    /// Obtains a new certificate when the last one issued is due for renewal or was issued for
+    /// other domains, and makes sure the gateway's files hold the current one. This blocks until
+    /// the ACME server has validated every domain and issued the certificate.
+    pub fn renew_if_due(&self) -> Result<()> {
+        if self.renewing.swap(true, Ordering::SeqCst) {
+            debug!("An ACME renewal is already running");
+            return Ok(());
+        }
+        let result = self.renew_if_due_impl();
+        self.renewing.store(false, Ordering::SeqCst);
+        result
+    }
+
+    fn renew_if_due_impl(&self) -> Result<()> {
+        let directory = Directory::from_url(self.state.clone(),
+                                            DirectoryUrl::Other(&self.config.directory_url))
+            .map_err(acme_error)?;
+        let account = directory.account(&self.config.email).map_err(acme_error)?;
+        let current = account.certificate(self.primary_domain())
+                             .map_err(acme_error)?
+                             .filter(|cert| cert.valid_days_left() >= RENEW_BEFORE_DAYS)
+                             .filter(|_| self.state.domains() == self.config.domains);
+        let cert = match current {
+            Some(cert) => cert,
+            None => {
+                outputln!("Requesting a certificate for {} from {}",
+                          self.config.domains.join(", "),
+                          self.config.directory_url);
+                let cert = self.order(&account)?;
+                self.state.set_domains(&self.config.domains)?;
+                outputln!("Obtained a certificate for {}, valid for {} days",
+                          self.config.domains.join(", "),
+                          cert.valid_days_left());
+                cert
+            }
+        };
+        self.install(&cert)
+    }
+
+    fn primary_domain(&self) -> &str { &self.config.domains[0] }
+
+    fn order(&self, account: &Account<StatePersist>) -> Result<Certificate> {
+        let alt_names = self.config.domains[1..].iter()
+                                                .map(String::as_str)
+                                                .collect::<Vec<_>>();
+        let mut order = account.new_order(self.primary_domain(), &alt_names)
+                               .map_err(acme_error)?;
+        let mut attempts = 0;
+        let csr = loop {
+            if let Some(csr) = order.confirm_validations() {
+                break csr;
+            }
+            if attempts == VALIDATION_ATTEMPTS {
+                return Err(Error::Acme(format!("The domains were not validated \
+                                                after {} attempts",
+                                               attempts)));
+            }
+            attempts += 1;
+            for auth in order.authorizations().map_err(acme_error)? {
+                if auth.need_challenge() {
+                    self.validate(&auth)?;
+                }
+            }
+            order.refresh().map_err(acme_error)?;
+        };
+        csr.finalize_pkey(create_rsa_key(RSA_KEY_BITS), POLL_DELAY_MILLIS)
+           .and_then(|cert_order| cert_order.download_and_save_cert())
+           .map_err(acme_error)
+    }
+
+    fn validate(&self, auth: &Auth<StatePersist>) -> Result<()> {
+        let domain = auth.domain_name();
+        debug!("Answering the ACME challenge for {}", domain);
+        match &self.config.validation {
+            AcmeValidation::Http01(listen) => {
+                let challenge = auth.http_challenge();
+                let _responder = HttpChallengeResponder::start(*listen,
+                                                               challenge.http_token(),
+                                                               &challenge.http_proof())?;
+                challenge.validate(POLL_DELAY_MILLIS).map_err(acme_error)
+            }
+            AcmeValidation::Dns01(hook) => {
+                let challenge = auth.dns_challenge();
+                let proof = challenge.dns_proof();
+                run_dns_hook(hook, "deploy", domain, &proof)?;
+                let result = challenge.validate(POLL_DELAY_MILLIS).map_err(acme_error);
+                if let Err(e) = run_dns_hook(hook, "clean", domain, &proof) {
+                    outputln!("Unable to remove the ACME challenge record of {}: {}",
+                              domain,
+                              e);
+                }
+                result
+            }
+        }
+    }
+
+    /// Writes `cert` and its private key to the gateway's files, unless they already hold them.
+    fn install(&self, cert: &Certificate) -> Result<()> {
+        if fs::read_to_string(&self.cert_path).ok().as_deref() == Some(cert.certificate())
+           && fs::read_to_string(&self.key_path).ok().as_deref() == Some(cert.private_key())
+        {
+            return Ok(());
+        }
+        for path in &[&self.key_path, &self.cert_path] {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        AtomicWriter::new_with_permissions(&self.key_path, DEFAULT_SECRET_KEY_PERMISSIONS)?
+            .with_writer(|f| f.write_all(cert.private_key().as_bytes()))?;
+        AtomicWriter::new(&self.cert_path)?.with_writer(|f| {
+                                               f.write_all(cert.certificate().as_bytes())
+                                           })?;
+        outputln!("Installed the ACME certificate in {}",
+                  self.cert_path.display());
+        Ok(())
+    }
+}
+
+/// Makes sure the gateway has a certificate to start with. A failed renewal only stops the
+/// Supervisor when there is no certificate from an earlier run to fall back on.
+pub async fn renew_at_startup(client: Arc<AcmeClient>) -> Result<()> {
+    let renewing = Arc::clone(&client);
+    match tokio::task::spawn_blocking(move || renewing.renew_if_due()).await? {
+        Err(e) if client.is_installed() => {
+            outputln!("Unable to renew the http-gateway certificate: {}", e);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Renews the certificate on a blocking thread, logging any failure; it is retried at the next
+/// check.
+pub fn renew_in_background(client: &Arc<AcmeClient>) {
+    let client = Arc::clone(client);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = client.renew_if_due() {
+            outputln!("Unable to renew the http-gateway certificate: {}", e);
+        }
+    });
+}
+
+fn acme_error(e: acme_lib::Error) -> Error { Error::Acme(e.to_string()) }
+
+fn run_dns_hook(hook: &Path, action: &str, domain: &str, value: &str) -> Result<()> {
+    let status = Command::new(hook).arg(action)
+                                   .arg(domain)
+                                   .arg(value)
+                                   .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Acme(format!("'{} {} {}' failed with {}",
+                                hook.display(),
+                                action,
+                                domain,
+                                status)))
+    }
+}
+
+/// Stores what the ACME client persists in a directory, with the account and certificate keys
+/// readable only by the Supervisor.
+#[derive(Clone)]
+struct StatePersist {
+    path: PathBuf,
+}
+
+impl StatePersist {
+    fn file(&self, key: &PersistKey) -> PathBuf {
+        let extension = match key.kind {
+            PersistKind::Certificate => "crt",
+            PersistKind::PrivateKey => "key",
+            PersistKind::AccountPrivateKey => "account.key",
+        };
+        let name = key.key
+                      .chars()
+                      .map(|c| {
+                          if c.is_ascii_alphanumeric() || c == '.' {
+                              c
+                          } else {
+                              '_'
+                          }
+                      })
+                      .collect::<String>();
+        self.path
+            .join(format!("{}-{}.{}", key.realm, name, extension))
+    }
+
+    fn domains_file(&self) -> PathBuf { self.path.join("domains") }
+
+    /// The domains the last certificate was issued for.
+    fn domains(&self) -> Vec<String> {
+        fs::read_to_string(self.domains_file()).map(|d| d.lines().map(str::to_string).collect())
+                                               .unwrap_or_default()
+    }
+
+    fn set_domains(&self, domains: &[String]) -> io::Result<()> {
+        AtomicWriter::new(&self.domains_file())?.with_writer(|f| {
+                                                    f.write_all(domains.join("\n").as_bytes())
+                                                })
+    }
+}
+
+impl Persist for StatePersist {
+    fn put(&self, key: &PersistKey, value: &[u8]) -> acme_lib::Result<()> {
+        fs::create_dir_all(&self.path)?;
+        let permissions = match key.kind {
+            PersistKind::Certificate => Permissions::Standard,
+            PersistKind::PrivateKey | PersistKind::AccountPrivateKey => {
+                DEFAULT_SECRET_KEY_PERMISSIONS
+            }
+        };
+        AtomicWriter::new_with_permissions(&self.file(key), permissions)?.with_writer(|f| {
+                                                                             f.write_all(value)
+                                                                         })?;
+        Ok(())
+    }
+
+    fn get(&self, key: &PersistKey) -> acme_lib::Result<Option<Vec<u8>>> {
+        match fs::read(self.file(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Serves the proof of one `http-01` challenge until it is dropped.
+struct HttpChallengeResponder {
+    local_addr: SocketAddr,
+    stop:       Arc<AtomicBool>,
+    handle:     Option<JoinHandle<()>>,
+}
+
+impl HttpChallengeResponder {
+    fn start(listen: SocketAddr, token: &str, proof: &str) -> Result<Self> {
+        let listener = TcpListener::bind(listen).map_err(|e| {
+                                                    Error::Acme(format!("Unable to answer the \
+                                                                         http-01 challenge on \
+                                                                         {}: {}",
+                                                                        listen, e))
+                                                })?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let path = format!("{}{}", HTTP_CHALLENGE_PATH, token);
+        let proof = proof.to_string();
+        let handle =
+            thread::Builder::new().name(String::from("acme-http-01"))
+                                  .spawn(move || {
+                                      while !stopped.load(Ordering::Relaxed) {
+                                          match listener.accept() {
+                                              Ok((stream, _)) => {
+                                                  if let Err(e) = respond(stream, &path, &proof) {
+                                                      debug!("ACME http-01 request failed: {}", e);
+                                                  }
+                                              }
+                                              Err(_) => thread::sleep(ACCEPT_POLL_INTERVAL),
+                                          }
+                                      }
+                                  })?;
+        Ok(HttpChallengeResponder { local_addr,
+                                    stop,
+                                    handle: Some(handle) })
+    }
+}
+
+impl Drop for HttpChallengeResponder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+        debug!("Stopped answering http-01 challenges on {}",
+               self.local_addr);
+    }
+}
+
+/// Answers one HTTP request, with `proof` if it is for `path`.
+fn respond(mut stream: TcpStream, path: &str, proof: &str) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    match request_line.split_whitespace().nth(1) {
+        Some(requested) if requested == path => {
+            write!(stream,
+                   "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: \
+                    {}\r\nConnection: close\r\n\r\n{}",
+                   proof.len(),
+                   proof)
+        }
+        _ => {
+            write!(stream,
+                   "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Read,
+              net::Ipv4Addr};
+    use tempfile::TempDir;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream,
+               "GET {} HTTP/1.1\r\nHost: sup.example.com\r\n\r\n",
+               path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn the_http_responder_serves_only_its_token() {
+        let listen = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let responder = HttpChallengeResponder::start(listen, "token", "token.proof").unwrap();
+
+        let response = get(responder.local_addr, "/.well-known/acme-challenge/token");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\r\n\r\ntoken.proof"));
+        let response = get(responder.local_addr, "/.well-known/acme-challenge/other");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+
+        let addr = responder.local_addr;
+        drop(responder);
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn the_domains_of_the_last_certificate_are_kept() {
+        let dir = TempDir::new().unwrap();
+        let state = StatePersist { path: dir.path().to_path_buf(), };
+        assert!(state.domains().is_empty());
+        let domains = vec![String::from("sup.example.com"), String::from("example.com")];
+        state.set_domains(&domains).unwrap();
+        assert_eq!(state.domains(), domains);
+    }
+
+    #[test]
+    fn persisted_values_are_read_back() {
+        let dir = TempDir::new().unwrap();
+        let state = StatePersist { path: dir.path().join("acme"), };
+        let key = PersistKey { realm: 42,
+                               kind:  PersistKind::AccountPrivateKey,
+                               key:   "ops@example.com", };
+        assert_eq!(state.get(&key).unwrap(), None);
+        state.put(&key, b"secret").unwrap();
+        assert_eq!(state.get(&key).unwrap(), Some(b"secret".to_vec()));
+        assert_eq!(state.file(&key),
+                   dir.path()
+                      .join("acme")
+                      .join("42-ops_example.com.account.key"));
+    }
+}
@@ -1,7 +1,8 @@
 //! Defines types for sending information about "actions" from one
 //! part of the Supervisor to another.
 
-use super::service::ServiceSpec;
+use super::{service::ServiceSpec,
+            service_data::DataDisposition};
 use habitat_core::os::process::ShutdownTimeout;
 use std::sync::mpsc;
 
@@ -27,10 +28,18 @@ pub enum SupervisorAction {
     UnloadService {
         service_spec:   ServiceSpec,
         shutdown_input: ShutdownInput,
+        /// What to do with the service's data directory once it has stopped.
+        data:           DataDisposition,
     },
     UpdateService {
         service_spec: ServiceSpec,
     },
+    /// Stop every service for maintenance, without changing their specs. A line describing each
+    /// service is sent to `progress` as it stops; `progress` is dropped once all have stopped.
+    Drain {
+        shutdown_input: ShutdownInput,
+        progress:       mpsc::Sender<String>,
+    },
 }
 
 pub type ActionSender = mpsc::Sender<SupervisorAction>;
@@ -46,3 +55,9 @@ impl Into<ShutdownInput> for habitat_sup_protocol::ctl::SvcStop {
         ShutdownInput { timeout: self.timeout_in_seconds.map(ShutdownTimeout::from), }
     }
 }
+
+impl Into<ShutdownInput> for habitat_sup_protocol::ctl::SupDrain {
+    fn into(self) -> ShutdownInput {
+        ShutdownInput { timeout: self.timeout_in_seconds.map(ShutdownTimeout::from), }
+    }
+}
@@ -1,33 +1,65 @@
 //! All the code for responding to Supervisor commands
 
-use crate::{ctl_gateway::CtlRequest,
+use crate::{ctl_gateway::{self,
+                          CtlRequest},
             error::Error,
+            event,
             manager::{action::{ActionSender,
                                SupervisorAction},
+                      config_dry_run,
+                      key_revocation::RevocationSource,
                       service::{spec::ServiceSpec,
                                 DesiredState,
                                 ProcessState},
-                      ManagerState},
+                      service_data::DataDisposition,
+                      service_env,
+                      service_history,
+                      Manager,
+                      ManagerState,
+                      PROC_LOCK_FILE},
             util};
-use habitat_butterfly as butterfly;
+use habitat_butterfly::{self as butterfly,
+                        member::{history::Transition,
+                                 Health},
+                        rumor::{RingCommand,
+                                RingCommandOutcome},
+                        server::ring_health::{ElectionConflict,
+                                              RingHealthReport,
+                                              ViewConflict}};
 use habitat_common::{command::package::install::InstallSource,
                      outputln,
                      templating::package::Pkg,
                      ui::UIWriter};
-use habitat_core::{package::{Identifiable,
+use habitat_core::{crypto::{keys::{self,
+                                   SecretKeyFile},
+                            SigKeyPair,
+                            SymKey},
+                   os::process::{CpuSet,
+                                 NiceLevel,
+                                 PriorityClass,
+                                 ResourceLimits},
+                   package::{Identifiable,
                              PackageIdent,
                              PackageTarget},
                    service::ServiceGroup};
 use habitat_sup_protocol::{self as protocol,
+                           ctl::SignedRequest,
+                           message::MessageStatic,
                            net::{self,
                                  ErrCode,
                                  NetResult}};
-use std::{convert::TryFrom,
+use prost::Message;
+use std::{collections::HashMap,
+          convert::TryFrom,
           fmt,
+          net::SocketAddr,
+          path::Path,
           result,
-          sync::atomic::Ordering,
+          sync::{atomic::Ordering,
+                 mpsc},
           time::{Duration,
                  SystemTime}};
+use tokio::task;
 
 static LOGKEY: &str = "CMD";
 
@@ -55,7 +87,26 @@ pub fn service_cfg_msr(mgr: &ManagerState,
     Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))
 }
 
-pub fn service_cfg_validate(_mgr: &ManagerState,
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+pub fn service_env_msr(mgr: &ManagerState,
+                       req: &mut CtlRequest,
+                       opts: protocol::ctl::SvcGetEnv)
+                       -> NetResult<()> {
+    let ident: PackageIdent = opts.ident.ok_or_else(err_update_client)?.into();
+    for service in mgr.services.lock_msr().services() {
+        if service.pkg.ident.satisfies(&ident) {
+            let msg = service_env::service_env(&service.pkg.env,
+                                               std::env::vars(),
+                                               &mgr.cfg.env_redact_patterns);
+            req.reply_complete(msg);
+            return Ok(());
+        }
+    }
+    Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))
+}
+
+pub fn service_cfg_validate(mgr: &ManagerState,
                             req: &mut CtlRequest,
                             opts: protocol::ctl::SvcValidateCfg)
                             -> NetResult<()> {
@@ -71,12 +122,34 @@ pub fn service_cfg_validate(_mgr: &ManagerState,
                             format!("Configuration format {} not available.",
                                     format)));
     }
-    let _new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
-                                                                 net::err(
+    let new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
+                                                                net::err(
             ErrCode::BadPayload,
             format!("Unable to decode configuration as {}, {}", format, e),
         )
-                                                             })?;
+                                                            })?;
+    if opts.dry_run.unwrap_or(false) {
+        let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+        let service_group = service_group.in_org(mgr.cfg.organization.as_deref());
+        let changes = config_dry_run::dry_run_rsr(&mgr.butterfly,
+                                                  &mgr.cfg.cache_key_path,
+                                                  &service_group,
+                                                  &new_cfg);
+        if changes.is_empty() {
+            return Err(net::err(ErrCode::NotFound,
+                                format!("No members of {} are known",
+                                        service_group)));
+        }
+        let mut changes = changes.into_iter().peekable();
+        while let Some(msg) = changes.next() {
+            if changes.peek().is_some() {
+                req.reply_partial(msg);
+            } else {
+                req.reply_complete(msg);
+            }
+        }
+        return Ok(());
+    }
     req.reply_complete(net::ok());
     Ok(())
     // JW TODO: Hold off on validation until we can validate services which aren't currently
@@ -115,10 +188,13 @@ pub fn service_cfg_set(mgr: &ManagerState,
                        req: &mut CtlRequest,
                        opts: protocol::ctl::SvcSetCfg)
                        -> NetResult<()> {
+    let signer = verify_signature(mgr, req, &opts)?;
+    let signed_request = signed_request(&opts);
     let cfg = opts.cfg.ok_or_else(err_update_client)?;
     let is_encrypted = opts.is_encrypted.unwrap_or(false);
     let version = opts.version.ok_or_else(err_update_client)?;
     let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let service_group = service_group.in_org(mgr.cfg.organization.as_deref());
     if cfg.len() > protocol::butterfly::MAX_SVC_CFG_SIZE {
         return Err(net::err(ErrCode::EntityTooLarge, "Configuration too large."));
     }
@@ -126,8 +202,8 @@ pub fn service_cfg_set(mgr: &ManagerState,
               version,
               service_group,);
     let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
+        match mgr.butterfly
+                 .client(&mgr.cfg.gossip_listen.local_addr().to_string())
         {
             Ok(client) => client,
             Err(err) => {
@@ -135,22 +211,34 @@ pub fn service_cfg_set(mgr: &ManagerState,
                 return Err(net::err(ErrCode::Internal, err.to_string()));
             }
         };
-    client.send_service_config(service_group, version, &cfg, is_encrypted)
-          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
-          .map(|_| {
-              req.reply_complete(net::ok());
-          })
+    client.send_service_config(service_group.clone(),
+                               version,
+                               &cfg,
+                               is_encrypted,
+                               signed_request)
+          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    event::service_config_applied(&service_group,
+                                  version,
+                                  &cfg,
+                                  is_encrypted,
+                                  &submitted_by(req, signer),
+                                  req.peer_addr());
+    req.reply_complete(net::ok());
+    Ok(())
 }
 
 pub fn service_file_put(mgr: &ManagerState,
                         req: &mut CtlRequest,
                         opts: protocol::ctl::SvcFilePut)
                         -> NetResult<()> {
+    let signer = verify_signature(mgr, req, &opts)?;
+    let signed_request = signed_request(&opts);
     let content = opts.content.ok_or_else(err_update_client)?;
     let filename = opts.filename.ok_or_else(err_update_client)?;
     let is_encrypted = opts.is_encrypted.unwrap_or(false);
     let version = opts.version.ok_or_else(err_update_client)?;
     let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let service_group = service_group.in_org(mgr.cfg.organization.as_deref());
     if content.len() > protocol::butterfly::MAX_FILE_PUT_SIZE_BYTES {
         return Err(net::err(ErrCode::EntityTooLarge, "File content too large."));
     }
@@ -159,8 +247,8 @@ pub fn service_file_put(mgr: &ManagerState,
               filename,
               service_group,);
     let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
+        match mgr.butterfly
+                 .client(&mgr.cfg.gossip_listen.local_addr().to_string())
         {
             Ok(client) => client,
             Err(err) => {
@@ -168,11 +256,22 @@ pub fn service_file_put(mgr: &ManagerState,
                 return Err(net::err(ErrCode::Internal, err.to_string()));
             }
         };
-    client.send_service_file(service_group, filename, version, &content, is_encrypted)
-          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
-          .map(|_| {
-              req.reply_complete(net::ok());
-          })
+    client.send_service_file(service_group.clone(),
+                             filename.as_str(),
+                             version,
+                             &content,
+                             is_encrypted,
+                             signed_request)
+          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    event::service_file_applied(&service_group,
+                                &filename,
+                                version,
+                                &content,
+                                is_encrypted,
+                                &submitted_by(req, signer),
+                                req.peer_addr());
+    req.reply_complete(net::ok());
+    Ok(())
 }
 
 pub async fn service_load(mgr: &ManagerState,
@@ -180,11 +279,31 @@ pub async fn service_load(mgr: &ManagerState,
                           opts: protocol::ctl::SvcLoad)
                           -> NetResult<()> {
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
+    if mgr.draining.load(Ordering::Relaxed) {
+        return Err(net::err(ErrCode::Conflict,
+                            format!("Cannot load {}, this Supervisor is \
+                                     draining for maintenance. Restart it \
+                                     to load services again.",
+                                    ident)));
+    }
     let source = InstallSource::Ident(ident.clone(), PackageTarget::active_target());
+    let force = opts.force.unwrap_or(false);
     let spec = if let Some(spec) = mgr.cfg.spec_for_ident(source.as_ref()) {
         // We've seen this service before. Thus `load` acts as a way to edit spec files from the
         // command line. As a result, we check that you *really* meant to change an existing spec.
-        if !opts.force.unwrap_or(false) {
+        if !force {
+            if spec.ident.origin != ident.origin {
+                // Both packages would be members of the same service group.
+                return Err(net::err(ErrCode::Conflict,
+                                    format!("{} is already loaded in the {}.{} \
+                                             service group, which {} would join. \
+                                             Unload it and try again, or load with \
+                                             the --force flag to replace it.",
+                                            spec.ident,
+                                            spec.ident.name,
+                                            spec.group,
+                                            ident)));
+            }
             return Err(net::err(ErrCode::Conflict,
                                 format!("Service already loaded. Unload '{}' \
                                          and try again, or load with the \
@@ -199,6 +318,26 @@ pub async fn service_load(mgr: &ManagerState,
 
     let package = util::pkg::satisfy_or_install(req, &source, &spec.bldr_url, &spec.channel).await?;
     spec.validate(&package)?;
+    if !force {
+        let loaded = mgr.cfg
+                        .specs()
+                        .into_iter()
+                        .filter_map(|other| {
+                            let exposes = util::pkg::installed(&other.ident)?.exposes().ok()?;
+                            Some((other, exposes))
+                        })
+                        .collect::<Vec<_>>();
+        let conflicts = spec.port_conflicts(&package.exposes().map_err(Error::from)?, &loaded);
+        if !conflicts.is_empty() {
+            return Err(net::err(ErrCode::Conflict,
+                                format!("Cannot load {}: {}. Unload the \
+                                         conflicting service(s) and try again, \
+                                         or load with the --force flag to load \
+                                         it anyway.",
+                                        spec.ident,
+                                        conflicts.join("; "))));
+        }
+    }
     mgr.cfg.save_spec_for(&spec)?;
 
     req.info(format!("The {} service was successfully loaded", spec.ident))?;
@@ -211,13 +350,21 @@ pub fn service_update(mgr: &ManagerState,
                       opts: protocol::ctl::SvcUpdate,
                       action_sender: &ActionSender)
                       -> NetResult<()> {
+    verify_signature(mgr, req, &opts)?;
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
     if let Some(mut service_spec) = mgr.cfg.spec_for_ident(&ident) {
-        service_spec.merge_svc_update(opts);
+        let no_restart = opts.no_restart.unwrap_or(false);
+        service_spec.merge_svc_update(opts)?;
         let action = SupervisorAction::UpdateService { service_spec };
         send_action(action, action_sender)?;
 
-        req.info(format!("Updating {}", ident))?;
+        if no_restart {
+            req.info(format!("Updating {}; changes which need a restart are held until it is \
+                              next started or its update window opens",
+                             ident))?;
+        } else {
+            req.info(format!("Updating {}", ident))?;
+        }
         req.reply_complete(net::ok());
         Ok(())
     } else {
@@ -232,9 +379,11 @@ pub fn service_unload(mgr: &ManagerState,
                       -> NetResult<()> {
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
     if let Some(service_spec) = mgr.cfg.spec_for_ident(&ident) {
+        let data = DataDisposition::from(&opts);
         let shutdown_input = opts.into();
         let action = SupervisorAction::UnloadService { service_spec,
-                                                       shutdown_input };
+                                                       shutdown_input,
+                                                       data };
         send_action(action, action_sender)?;
 
         // JW TODO: Change this to unloaded from unloading when the Supervisor waits for
@@ -309,8 +458,8 @@ pub fn supervisor_depart(mgr: &ManagerState,
                          -> NetResult<()> {
     let member_id = opts.member_id.ok_or_else(err_update_client)?;
     let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
+        match mgr.butterfly
+                 .client(&mgr.cfg.gossip_listen.local_addr().to_string())
         {
             Ok(client) => client,
             Err(err) => {
@@ -337,12 +486,420 @@ pub fn supervisor_restart(mgr: &ManagerState,
     Ok(())
 }
 
+/// Gracefully terminate this Supervisor and its services, as `hab sup term` does locally, after
+/// replying. If asked to, permanently depart it from the ring first; the departure alone shuts
+/// the Supervisor down.
+pub fn supervisor_term(mgr: &ManagerState,
+                       req: &mut CtlRequest,
+                       opts: protocol::ctl::SupTerm)
+                       -> NetResult<()> {
+    let requester = req.peer_addr()
+                       .map(|addr| addr.to_string())
+                       .unwrap_or_else(|| "UNKNOWN".to_string());
+    if opts.depart.unwrap_or(false) {
+        let member_id = mgr.butterfly.member_id().to_string();
+        let gossip_addr = mgr.cfg.gossip_listen.local_addr().to_string();
+        let mut client = mgr.butterfly
+                            .client(&gossip_addr)
+                            .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+        outputln!("Departing and terminating at the request of {}", requester);
+        client.send_departure(&member_id)
+              .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+        req.info(format!("Departed member {}", member_id))?;
+        req.reply_complete(net::ok());
+        return Ok(());
+    }
+    outputln!("Terminating at the request of {}", requester);
+    req.reply_complete(net::ok());
+    // Signalling the Launcher stops the Supervisor and its services just as a local `hab sup
+    // term` does, and keeps the Launcher from starting the Supervisor again.
+    Manager::term(&mgr.cfg.sup_root().join(PROC_LOCK_FILE)).map_err(|e| {
+                                                                 net::err(ErrCode::Internal,
+                                                                          e.to_string())
+                                                             })
+}
+
+/// Forget every departed member, and its services, without waiting for the departed member
+/// retention to pass.
+///
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (write)
+/// * `RumorStore::list` (write)
+/// * `RumorHeat::inner` (write)
+pub fn supervisor_forget_departed_mlw_rsw_rhw(mgr: &ManagerState,
+                                              req: &mut CtlRequest,
+                                              _opts: protocol::ctl::SupForgetDeparted)
+                                              -> NetResult<()> {
+    let forgotten = mgr.butterfly
+                       .forget_departed_members_mlw_rsw_rhw(Duration::from_secs(0));
+    for member_id in &forgotten {
+        req.info(format!("Forgot departed member {}", member_id))?;
+    }
+    outputln!("Forgot {} departed member(s)", forgotten.len());
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// Drain this Supervisor for maintenance, replying once all of its services have stopped.
+///
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (write)
+/// * `Server::member` (write)
+/// * `RumorHeat::inner` (write)
+pub fn supervisor_drain_mlw_smw_rhw(mgr: &ManagerState,
+                                    req: &mut CtlRequest,
+                                    opts: protocol::ctl::SupDrain,
+                                    action_sender: &ActionSender)
+                                    -> NetResult<()> {
+    if !mgr.draining.swap(true, Ordering::Relaxed) {
+        outputln!("Draining Supervisor for maintenance");
+        mgr.butterfly.set_draining_mlw_smw_rhw();
+    }
+    let (progress, stopped) = mpsc::channel();
+    let shutdown_input = opts.into();
+    send_action(SupervisorAction::Drain { shutdown_input,
+                                          progress },
+                action_sender)?;
+    req.info("Marked this Supervisor as draining; stopping its services")?;
+
+    // The stopped services are reported from the main loop until the last one has stopped,
+    // which may take a while. As with `SvcLoad`, it is safe to block here because this is
+    // called within a spawned future.
+    let mut count = 0;
+    task::block_in_place(|| -> NetResult<()> {
+        for line in stopped {
+            req.info(line)?;
+            count += 1;
+        }
+        Ok(())
+    })?;
+    req.info(format!("Supervisor drained; {} service(s) stopped", count))?;
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// Gossip a signed request to every Supervisor in the ring. Each Supervisor, this one included,
+/// applies it from its main loop and gossips back an acknowledgment; the acknowledgments heard so
+/// far are reported by `supervisor_ring_exec_status_rsr_mlr`.
+///
+/// The requestor signs the command's ID and expiry along with the request, so a command can
+/// neither be issued again under another ID nor outlive the expiry it was signed with.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (write)
+/// * `RumorHeat::inner` (write)
+/// * `MemberList::entries` (read)
+pub fn supervisor_ring_exec_rsw_rhw_mlr(mgr: &ManagerState,
+                                        req: &mut CtlRequest,
+                                        opts: protocol::ctl::SupRingExec)
+                                        -> NetResult<()> {
+    let signer = verified_ring_command_signer(mgr, opts.signature(), &opts.signed_bytes())?;
+    req.info(format!("Verified signature from {}", signer))?;
+    let signature = opts.signature
+                        .and_then(|s| s.signature)
+                        .ok_or_else(err_update_client)?;
+    let id = opts.id.filter(|id| !id.is_empty()).ok_or_else(err_update_client)?;
+    let issued = mgr.butterfly
+                    .issue_ring_command_rsw_rhw(&id,
+                                                &opts.kind.ok_or_else(err_update_client)?,
+                                                opts.body.unwrap_or_default(),
+                                                opts.expires_at.unwrap_or_default(),
+                                                signer,
+                                                signature);
+    if !issued {
+        return Err(net::err(ErrCode::InvalidPayload,
+                            format!("Ring command {} has already expired", id)));
+    }
+    outputln!("Issued ring command {}", id);
+    req.reply_complete(ring_command_status_rsr_mlr(mgr, &id)?);
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (read)
+pub fn supervisor_ring_exec_status_rsr_mlr(mgr: &ManagerState,
+                                           req: &mut CtlRequest,
+                                           opts: protocol::ctl::SupRingExecStatus)
+                                           -> NetResult<()> {
+    let id = opts.id.ok_or_else(err_update_client)?;
+    req.reply_complete(ring_command_status_rsr_mlr(mgr, &id)?);
+    Ok(())
+}
+
+/// Returns the signer of a ring command gossiped by `hab ring exec` once its signature has been
+/// verified. See `verified_ring_command_signer` for which commands are trusted.
+pub fn ring_command_signer(mgr: &ManagerState,
+                           command: &RingCommand)
+                           -> result::Result<String, String> {
+    let signature =
+        protocol::ctl::PayloadSignature { signer: Some(command.signer.clone()),
+                                          signature: Some(command.signature.clone()),
+                                          ..Default::default() };
+    let signed_bytes = protocol::ctl::ring_command_signed_bytes(&command.id,
+                                                                command.expires_at,
+                                                                &command.kind,
+                                                                &command.body);
+    verified_ring_command_signer(mgr, Some(&signature), &signed_bytes).map_err(|e| e.msg)
+}
+
+/// Apply a ring command which `signer` signed, returning what was done for the acknowledgment.
+pub fn apply_ring_command(mgr: &ManagerState,
+                          command: &RingCommand,
+                          signer: &str)
+                          -> result::Result<String, String> {
+    if command.kind != protocol::ctl::SvcUpdate::MESSAGE_ID {
+        return Err(format!("{} is not supported as a ring command", command.kind));
+    }
+    let svc_update =
+        protocol::ctl::SvcUpdate::decode(command.body.as_slice()).map_err(|e| e.to_string())?;
+    let ident: PackageIdent = svc_update.ident
+                                        .clone()
+                                        .ok_or_else(|| String::from("No service to update"))?
+                                        .into();
+    let mut service_spec = match mgr.cfg.spec_for_ident(&ident) {
+        Some(service_spec) => service_spec,
+        None => return Ok(format!("{} is not loaded; nothing to update", ident)),
+    };
+    service_spec.merge_svc_update(svc_update)
+                .map_err(|e| e.to_string())?;
+    mgr.cfg
+       .save_spec_for(&service_spec)
+       .map_err(|e| e.to_string())?;
+    Ok(format!("Updated {} as signed by {}", ident, signer))
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+pub fn supervisor_members_gsr(mgr: &ManagerState,
+                              req: &mut CtlRequest,
+                              opts: protocol::ctl::SupMembers)
+                              -> NetResult<()> {
+    let gateway_state = mgr.gateway_state.lock_gsr();
+    let history = gateway_state.member_history();
+    let requested = |member_id: &str| {
+        opts.member_id
+            .as_ref()
+            .map_or(true, |requested| requested == member_id)
+    };
+
+    if opts.history.unwrap_or(false) {
+        let transitions = history.iter()
+                                 .filter(|t| requested(&t.member_id))
+                                 .cloned()
+                                 .map(protocol::types::MemberTransition::from);
+        reply_all(req, transitions);
+        return Ok(());
+    }
+
+    // Before the first butterfly state has been persisted there is
+    // nothing to report.
+    let membership = if gateway_state.butterfly_data().is_empty() {
+        HashMap::new()
+    } else {
+        serde_json::from_str::<ButterflyMembership>(gateway_state.butterfly_data())
+            .map_err(Error::MembershipDeserializationError)?
+            .membership
+    };
+    let mut members: Vec<_> = membership.into_iter()
+                                        .filter(|(member_id, _)| requested(member_id))
+                                        .collect();
+    if let Some(ref member_id) = opts.member_id {
+        if members.is_empty() {
+            return Err(net::err(ErrCode::NotFound, format!("Unknown member, {}", member_id)));
+        }
+    }
+    members.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let statuses = members.into_iter()
+                          .map(|(member_id, status)| member_status(member_id, status, history));
+    reply_all(req, statuses);
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+pub fn supervisor_ring_health_gsr(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  _opts: protocol::ctl::SupRingHealth)
+                                  -> NetResult<()> {
+    let report = mgr.gateway_state.lock_gsr().ring_health().clone();
+    req.reply_complete(protocol::types::RingHealth::from(report));
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+pub fn supervisor_key_revocations_gsr(mgr: &ManagerState,
+                                      req: &mut CtlRequest,
+                                      _opts: protocol::ctl::SupKeyRevocations)
+                                      -> NetResult<()> {
+    let sources = mgr.gateway_state
+                     .lock_gsr()
+                     .key_revocations()
+                     .iter()
+                     .cloned()
+                     .map(Into::into)
+                     .collect();
+    req.reply_complete(protocol::types::KeyRevocations { sources });
+    Ok(())
+}
+
+/// Move gossip encryption through a phase of a ring key rotation. Once a revision is activated it
+/// is also written to the key cache, so that the Supervisor keeps using it after a restart.
+pub fn supervisor_ring_key_rotate(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  opts: protocol::ctl::SupRingKeyRotate)
+                                  -> NetResult<()> {
+    use protocol::ctl::RingKeyRotationPhase;
+
+    let phase = opts.phase
+                    .and_then(RingKeyRotationPhase::from_i32)
+                    .ok_or_else(err_update_client)?;
+    let rotation_err =
+        |e: butterfly::error::Error| net::err(ErrCode::InvalidPayload, e.to_string());
+    match phase {
+        RingKeyRotationPhase::Stage => {
+            let content = opts.key.ok_or_else(err_update_client)?;
+            let key = SymKey::from_secret_str(&content).map_err(|e| {
+                                                           net::err(ErrCode::BadPayload,
+                                                                    e.to_string())
+                                                       })?;
+            let name_with_rev = key.name_with_rev();
+            mgr.butterfly
+               .rotate_ring_key_smw(|keys| keys.stage(key))
+               .map_err(rotation_err)?;
+            outputln!("Staged ring key {}", name_with_rev);
+        }
+        RingKeyRotationPhase::Activate => {
+            let name_with_rev = opts.name_with_rev.ok_or_else(err_update_client)?;
+            let key = mgr.butterfly
+                         .rotate_ring_key_smw(|keys| {
+                             keys.activate(&name_with_rev)?;
+                             keys.active().to_secret_string()
+                         })
+                         .map_err(rotation_err)?;
+            SymKey::write_file_from_str(&key, &mgr.cfg.cache_key_path).map_err(|e| {
+                                                                          net::err(ErrCode::Io,
+                                 format!("Activated ring key {}, but could not save it: {}",
+                                         name_with_rev, e))
+                                                                      })?;
+            outputln!("Activated ring key {}", name_with_rev);
+        }
+        RingKeyRotationPhase::Retire => {
+            let retired = mgr.butterfly
+                             .rotate_ring_key_smw(|keys| Ok(keys.retire()))
+                             .map_err(rotation_err)?;
+            for name_with_rev in retired {
+                outputln!("Retired ring key {}", name_with_rev);
+            }
+        }
+    }
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+pub fn supervisor_ring_reload(mgr: &ManagerState,
+                              req: &mut CtlRequest,
+                              opts: protocol::ctl::SupRingReload)
+                              -> NetResult<()> {
+    let active = match mgr.butterfly.ring_keys() {
+        Some(keys) => keys.active().clone(),
+        None => {
+            return Err(net::err(ErrCode::InvalidPayload,
+                                "Gossip is not encrypted, so there is no ring \
+                                 key to reload"));
+        }
+    };
+    let latest = SymKey::get_latest_pair_for(&active.name, &mgr.cfg.cache_key_path).map_err(|e| {
+                     net::err(ErrCode::NotFound, e.to_string())
+                 })?;
+    let name_with_rev = latest.name_with_rev();
+    let switched = mgr.butterfly
+                      .rotate_ring_key_smw(|keys| keys.switch_to(latest))
+                      .map_err(|e| net::err(ErrCode::InvalidPayload, e.to_string()))?;
+    if switched {
+        outputln!("Switched to ring key {} from {}",
+                  name_with_rev,
+                  active.name_with_rev());
+        req.info(format!("Switched to ring key {}", name_with_rev))?;
+    } else {
+        req.info(format!("Ring key {} is already active", name_with_rev))?;
+    }
+    if opts.retire.unwrap_or(false) {
+        let retired = mgr.butterfly
+                         .rotate_ring_key_smw(|keys| Ok(keys.retire()))
+                         .map_err(|e| net::err(ErrCode::InvalidPayload, e.to_string()))?;
+        for name_with_rev in retired {
+            outputln!("Retired ring key {}", name_with_rev);
+            req.info(format!("Retired ring key {}", name_with_rev))?;
+        }
+    }
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// Replace the ctl gateway secret, both on disk and for the running gateway. The previous secret
+/// is still accepted for the requested grace period, and clients which use it in that time are
+/// told it has been rotated.
+pub fn supervisor_secret_rotate(mgr: &ManagerState,
+                                req: &mut CtlRequest,
+                                opts: protocol::ctl::SupSecretRotate)
+                                -> NetResult<()> {
+    let grace_period = Duration::from_secs(opts.grace_period().into());
+    let secret_key = opts.secret
+                         .filter(|secret| !secret.is_empty())
+                         .ok_or_else(err_update_client)?;
+    ctl_gateway::write_secret_key(mgr.cfg.sup_root(), &secret_key).map_err(|e| {
+        net::err(ErrCode::Io, format!("Could not save the new ctl gateway secret: {}", e))
+    })?;
+    mgr.ctl_secret.rotate(secret_key, grace_period);
+    let requester = req.peer_addr()
+                       .map(|addr| addr.to_string())
+                       .unwrap_or_else(|| "UNKNOWN".to_string());
+    outputln!("Rotated the ctl gateway secret at the request of {}; the previous secret is \
+               accepted for {}s",
+              requester,
+              grace_period.as_secs());
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// Revoke every ctl gateway session token, so that clients holding one have to prove they know
+/// the secret again. Clients authenticating with the secret itself are unaffected.
+pub fn supervisor_sessions_revoke(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  _opts: protocol::ctl::SupSessionsRevoke)
+                                  -> NetResult<()> {
+    let revoked = mgr.ctl_sessions.revoke_all();
+    let requester = req.peer_addr()
+                       .map(|addr| addr.to_string())
+                       .unwrap_or_else(|| "UNKNOWN".to_string());
+    outputln!("Revoked {} ctl gateway session(s) at the request of {}",
+              revoked,
+              requester);
+    req.info(format!("Revoked {} session(s)", revoked))?;
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 pub fn service_status_gsr(mgr: &ManagerState,
                           req: &mut CtlRequest,
                           opts: protocol::ctl::SvcStatus)
                           -> NetResult<()> {
+    if opts.history.unwrap_or(false) {
+        let gateway_state = mgr.gateway_state.lock_gsr();
+        let transitions =
+            gateway_state.service_history()
+                         .transitions()
+                         .into_iter()
+                         .filter(|t| opts.ident.as_ref().map_or(true, |i| t.ident.satisfies(i)))
+                         .map(protocol::types::ServiceTransition::from);
+        reply_all(req, transitions);
+        return Ok(());
+    }
+
     let statuses: Vec<ServiceStatus> =
         serde_json::from_str(mgr.gateway_state.lock_gsr().services_data()).map_err(Error::ServiceDeserializationError)?;
 
@@ -374,16 +931,315 @@ pub fn service_status_gsr(mgr: &ManagerState,
     Ok(())
 }
 
+/// Check the signature on a request which changes what a service runs or how it is configured.
+///
+/// When the Supervisor was started with no `--trusted-signer`, unsigned requests are accepted,
+/// and signed ones are refused rather than checked against whichever keys are in the key cache.
+/// Otherwise the request must be signed by one of the trusted signers, for this Supervisor,
+/// within a few minutes of its clock, and must not have been taken before.
+///
+/// Returns the signer of a signed request.
+pub fn verify_signature<T>(mgr: &ManagerState,
+                           req: &mut CtlRequest,
+                           msg: &T)
+                           -> NetResult<Option<String>>
+    where T: SignedRequest + MessageStatic
+{
+    let signer = verified_signer(mgr, msg)?;
+    if let Some(ref signer) = signer {
+        req.info(format!("Verified signature from {}", signer))?;
+    }
+    Ok(signer)
+}
+
+/// Who submitted `req`, to record with the changes it makes: `signer`, the signer of the
+/// request, if it was signed, and otherwise how its client authenticated.
+fn submitted_by(req: &CtlRequest, signer: Option<String>) -> String {
+    signer.or_else(|| req.authentication().map(|a| a.to_string()))
+          .unwrap_or_default()
+}
+
+/// Returns the signer of `msg` once its signature has been verified, or `None` if it is unsigned
+/// and may be accepted anyway. See `verify_signature`.
+fn verified_signer<T>(mgr: &ManagerState, msg: &T) -> NetResult<Option<String>>
+    where T: SignedRequest + MessageStatic
+{
+    let trusted = &mgr.cfg.trusted_signers;
+    let signature = match msg.signature() {
+        None if trusted.is_empty() => return Ok(None),
+        None => {
+            return Err(net::err(ErrCode::Unauthorized,
+                                format!("{} must be signed by one of: {}",
+                                        T::MESSAGE_ID,
+                                        trusted.join(", "))));
+        }
+        Some(_) if trusted.is_empty() => {
+            return Err(net::err(ErrCode::Unauthorized,
+                                format!("{} is signed, but this Supervisor has no \
+                                         --trusted-signer to check its signature against",
+                                        T::MESSAGE_ID)));
+        }
+        Some(signature) => signature,
+    };
+    let signer = checked_signer(&mgr.cfg.trusted_signers,
+                                &mgr.cfg.cache_key_path,
+                                signature,
+                                &msg.signed_bytes())?;
+    let target = signature.target.as_deref().unwrap_or_default();
+    if !is_ctl_gateway_addr(mgr, target) {
+        return Err(net::err(ErrCode::Unauthorized,
+                            format!("{} was signed for the Supervisor at {}, not this one",
+                                    T::MESSAGE_ID, target)));
+    }
+    if !mgr.signed_nonces
+           .take(signature.issued_at.unwrap_or_default(),
+                 signature.nonce.as_deref().unwrap_or_default())
+    {
+        return Err(net::err(ErrCode::Unauthorized,
+                            format!("{} was signed too long ago, or has already been taken",
+                                    T::MESSAGE_ID)));
+    }
+    Ok(Some(signer))
+}
+
+/// Whether `target`, the address a request was signed for, reaches this Supervisor's ctl
+/// gateway: its listen address or, when it listens on every address, the loopback address or
+/// the address it gossips on.
+fn is_ctl_gateway_addr(mgr: &ManagerState, target: &str) -> bool {
+    let listen = mgr.cfg.ctl_listen;
+    match target.parse::<SocketAddr>() {
+        Ok(target) if target.port() == listen.port() => {
+            target.ip() == listen.ip()
+            || (listen.ip().is_unspecified()
+                && (target.ip().is_loopback() || target.ip() == mgr.cfg.sys_ip))
+        }
+        _ => false,
+    }
+}
+
+/// Returns the signer of a ring command once its signature over `signed_bytes` has been verified.
+/// Unlike requests to the control gateway, ring commands must always be signed by one of the
+/// trusted signers, so a Supervisor with no `--trusted-signer` refuses them all.
+fn verified_ring_command_signer(mgr: &ManagerState,
+                                signature: Option<&protocol::ctl::PayloadSignature>,
+                                signed_bytes: &[u8])
+                                -> NetResult<String> {
+    if mgr.cfg.trusted_signers.is_empty() {
+        return Err(net::err(ErrCode::Unauthorized,
+                            "Ring commands are refused by Supervisors without a --trusted-signer"));
+    }
+    let signature =
+        signature.ok_or_else(|| net::err(ErrCode::Unauthorized, "Ring commands must be signed"))?;
+    checked_signer(&mgr.cfg.trusted_signers,
+                   &mgr.cfg.cache_key_path,
+                   signature,
+                   signed_bytes)
+}
+
+/// Returns the signer of `signature` once it has been verified over `signed_bytes` with the
+/// signer's public key from `cache_key_path`, provided that the signer is one of `trusted`.
+pub fn checked_signer(trusted: &[String],
+                      cache_key_path: &Path,
+                      signature: &protocol::ctl::PayloadSignature,
+                      signed_bytes: &[u8])
+                      -> NetResult<String> {
+    let signer = signature.signer.as_ref().ok_or_else(err_update_client)?;
+    let bytes = signature.signature.as_ref().ok_or_else(err_update_client)?;
+    let (name, _) = keys::parse_name_with_rev(signer).map_err(|e| {
+                                                         net::err(ErrCode::BadPayload,
+                                                                  e.to_string())
+                                                     })?;
+    if !trusted.contains(&name) {
+        return Err(net::err(ErrCode::Unauthorized,
+                            format!("{} is not a trusted signer", name)));
+    }
+    SigKeyPair::get_pair_for(signer, cache_key_path)
+        .and_then(|pair| pair.verify_payload(signed_bytes, bytes))
+        .map_err(|e| net::err(ErrCode::Unauthorized, e.to_string()))?;
+    Ok(signer.clone())
+}
+
+/// `msg` encoded, to gossip along with the change it makes so that every member can check its
+/// signature, if it was signed.
+fn signed_request<T: SignedRequest>(msg: &T) -> Option<Vec<u8>> {
+    msg.signature().map(|_| {
+                       let mut buf = Vec::with_capacity(msg.encoded_len());
+                       msg.encode(&mut buf)
+                          .expect("buffer is sized to the encoded length");
+                       buf
+                   })
+}
+
 ////////////////////////////////////////////////////////////////////////
 // Private helper functions
 fn err_update_client() -> net::NetErr { net::err(ErrCode::UpdateClient, "client out of date") }
 
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (read)
+fn ring_command_status_rsr_mlr(mgr: &ManagerState,
+                               id: &str)
+                               -> NetResult<protocol::types::RingCommandStatus> {
+    let (expires_at, mut acks) = {
+        let store = mgr.butterfly.ring_command_store.lock_rsr();
+        let copies =
+            store.get(id)
+                 .filter(|copies| !copies.is_empty())
+                 .ok_or_else(|| net::err(ErrCode::NotFound, format!("No ring command {}", id)))?;
+        let expires_at = copies.values().next().map_or(0, |c| c.expires_at);
+        let acks = copies.values()
+                         .filter(|c| c.is_acknowledgment())
+                         .map(|c| {
+                             let applied = c.outcome == RingCommandOutcome::Applied;
+                             protocol::types::RingCommandAck { member_id: c.member_id.clone(),
+                                                               applied,
+                                                               message: Some(c.message.clone()) }
+                         })
+                         .collect::<Vec<_>>();
+        (expires_at, acks)
+    };
+    acks.sort_by(|a, b| a.member_id.cmp(&b.member_id));
+    let alive = mgr.butterfly
+                   .member_list
+                   .health_counts_mlr()
+                   .get(&Health::Alive)
+                   .map_or(0, |&alive| u32::try_from(alive).unwrap_or(u32::MAX));
+    Ok(protocol::types::RingCommandStatus { id: id.to_string(),
+                                            expires_at: Some(expires_at),
+                                            alive: Some(alive),
+                                            acks })
+}
+
+/// Send each of `msgs` as a partial reply, completing the request with
+/// the last one. If there are no messages the request is completed
+/// with a `NetOk`.
+fn reply_all<T>(req: &mut CtlRequest, msgs: impl IntoIterator<Item = T>)
+    where T: Into<protocol::codec::SrvMessage> + fmt::Debug
+{
+    let mut list = msgs.into_iter().peekable();
+    if list.peek().is_none() {
+        req.reply_complete(net::ok());
+        return;
+    }
+    while let Some(msg) = list.next() {
+        if list.peek().is_some() {
+            req.reply_partial(msg);
+        } else {
+            req.reply_complete(msg);
+        }
+    }
+}
+
+// The subset of the JSON served by the /butterfly endpoint that
+// describes the current membership of the ring. See
+// `habitat_butterfly::member::MemberProxy`.
+#[derive(Deserialize)]
+struct ButterflyMembership {
+    membership: HashMap<String, MembershipStatus>,
+}
+
+#[derive(Deserialize)]
+struct MembershipStatus {
+    address: String,
+    health:  Health,
+}
+
+fn member_status(member_id: String,
+                 status: MembershipStatus,
+                 history: &[Transition])
+                 -> protocol::types::MemberStatus {
+    let member_history = || history.iter().filter(|t| t.member_id == member_id);
+    let flaps = member_history().filter(|t| t.is_flap()).count();
+    let last_departed = member_history().filter(|t| t.to == Health::Departed)
+                                        .last()
+                                        .map(|t| t.timestamp);
+    protocol::types::MemberStatus { address: Some(status.address),
+                                    health: status.health.to_string(),
+                                    flaps: Some(flaps as u32),
+                                    last_departed,
+                                    member_id }
+}
+
+impl From<Transition> for protocol::types::MemberTransition {
+    fn from(other: Transition) -> Self {
+        protocol::types::MemberTransition { member_id: other.member_id,
+                                            from:      other.from.map(|h| h.to_string()),
+                                            to:        other.to.to_string(),
+                                            timestamp: other.timestamp, }
+    }
+}
+
+impl From<&service_history::Transition> for protocol::types::ServiceTransition {
+    fn from(other: &service_history::Transition) -> Self {
+        protocol::types::ServiceTransition { ident:         other.ident.clone().into(),
+                                             service_group: other.service_group.clone().into(),
+                                             event:         other.lifecycle.name().to_string(),
+                                             detail:        other.lifecycle.detail(),
+                                             timestamp:     other.timestamp, }
+    }
+}
+
+impl From<RingHealthReport> for protocol::types::RingHealth {
+    fn from(other: RingHealthReport) -> Self {
+        protocol::types::RingHealth { status:             other.status.to_string(),
+                                      alive:              Some(other.alive as u32),
+                                      suspect:            Some(other.suspect as u32),
+                                      confirmed:          Some(other.confirmed as u32),
+                                      departed:           Some(other.departed as u32),
+                                      view_conflicts:     other.view_conflicts
+                                                               .into_iter()
+                                                               .map(Into::into)
+                                                               .collect(),
+                                      election_conflicts: other.election_conflicts
+                                                               .into_iter()
+                                                               .map(Into::into)
+                                                               .collect(),
+                                      generated_at:       Some(other.generated_at), }
+    }
+}
+
+impl From<RevocationSource> for protocol::types::KeyRevocationSource {
+    fn from(other: RevocationSource) -> Self {
+        protocol::types::KeyRevocationSource { url:          other.url,
+                                               origin:       other.origin,
+                                               revoked:      Some(other.revoked),
+                                               last_attempt: other.last_attempt,
+                                               last_success: other.last_success,
+                                               error:        other.error, }
+    }
+}
+
+impl From<ViewConflict> for protocol::types::MemberViewConflict {
+    fn from(other: ViewConflict) -> Self {
+        protocol::types::MemberViewConflict { member_id:   other.member_id,
+                                              reported_by: other.reported_by,
+                                              local:       other.local.to_string(),
+                                              reported:    other.reported.to_string(),
+                                              timestamp:   other.timestamp, }
+    }
+}
+
+impl From<ElectionConflict> for protocol::types::ElectionConflict {
+    fn from(other: ElectionConflict) -> Self {
+        protocol::types::ElectionConflict { service_group: other.service_group,
+                                            term:          other.term,
+                                            leaders:       other.leaders,
+                                            timestamp:     other.timestamp, }
+    }
+}
+
 #[derive(Deserialize)]
 struct ServiceStatus {
-    pkg:           Pkg,
-    process:       ProcessStatus,
-    service_group: ServiceGroup,
-    desired_state: DesiredState,
+    pkg:             Pkg,
+    process:         ProcessStatus,
+    service_group:   ServiceGroup,
+    desired_state:   DesiredState,
+    cpuset:          Option<CpuSet>,
+    nice:            Option<NiceLevel>,
+    priority_class:  Option<PriorityClass>,
+    limits:          ResourceLimits,
+    #[serde(default)]
+    restart_pending: bool,
 }
 
 impl From<ServiceStatus> for protocol::types::ServiceStatus {
@@ -393,6 +1249,12 @@ impl From<ServiceStatus> for protocol::types::ServiceStatus {
         proto.process = Some(other.process.into());
         proto.service_group = other.service_group.into();
         proto.desired_state = Some(other.desired_state.into());
+        proto.cpuset = other.cpuset.map(|c| c.to_string());
+        proto.nice = other.nice.map(i32::from);
+        proto.priority_class = other.priority_class.map(|c| c.to_string());
+        proto.limits = Some(other.limits).filter(|l| !l.is_empty())
+                                         .map(|l| l.to_string());
+        proto.restart_pending = Some(other.restart_pending);
         proto
     }
 }
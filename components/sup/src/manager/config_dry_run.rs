@@ -0,0 +1,129 @@
+//! Reports how each member of a service group would apply a new configuration, for
+//! `hab config apply --dry-run`, without gossiping it.
+//!
+//! The keys which differ from the group's current gossiped configuration are the ones which
+//! change. A member restarts to apply them if its package has no `reconfigure` hook, or lists one
+//! of them in its `RESTART_KEYS` metafile; otherwise its `reconfigure` hook applies them live. A
+//! member's package is read from this Supervisor's package cache, so members running packages
+//! which aren't installed here can't be assessed.
+
+use super::service;
+use habitat_butterfly::{rumor::{service_config::ServiceConfig as ServiceConfigRumor,
+                                ConstIdRumor as _},
+                        Server};
+use habitat_common::templating::config;
+use habitat_core::{fs::FS_ROOT_PATH,
+                   package::{PackageIdent,
+                             PackageInstall},
+                   service::ServiceGroup};
+use habitat_sup_protocol::types::MemberCfgChange;
+use std::{fmt,
+          path::Path};
+
+/// How a member would apply the new configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Unchanged,
+    Reconfigure,
+    Restart,
+    Unknown,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match *self {
+            Outcome::Unchanged => "unchanged",
+            Outcome::Reconfigure => "reconfigure",
+            Outcome::Restart => "restart",
+            Outcome::Unknown => "unknown",
+        };
+        write!(f, "{}", outcome)
+    }
+}
+
+/// The parts of a package which decide how it applies configuration changes.
+struct PackageCfgTraits {
+    has_reconfigure_hook: bool,
+    restart_keys:         Vec<String>,
+}
+
+impl PackageCfgTraits {
+    fn load(ident: &PackageIdent) -> Option<Self> {
+        let package = PackageInstall::load(ident, Some(Path::new(&*FS_ROOT_PATH))).ok()?;
+        Some(PackageCfgTraits { has_reconfigure_hook:
+                                    service::has_reconfigure_hook(&package.installed_path),
+                                restart_keys:         package.restart_keys().ok()?, })
+    }
+}
+
+fn outcome(changed_keys: &[String], package: Option<&PackageCfgTraits>) -> Outcome {
+    match package {
+        _ if changed_keys.is_empty() => Outcome::Unchanged,
+        None => Outcome::Unknown,
+        Some(package)
+            if !package.has_reconfigure_hook
+               || service::restart_keys_changed(changed_keys, &package.restart_keys) =>
+        {
+            Outcome::Restart
+        }
+        Some(_) => Outcome::Reconfigure,
+    }
+}
+
+/// Reports how each member of `service_group` known to this Supervisor would apply `new_cfg`.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+pub fn dry_run_rsr(butterfly: &Server,
+                   cache_key_path: &Path,
+                   service_group: &ServiceGroup,
+                   new_cfg: &toml::value::Table)
+                   -> Vec<MemberCfgChange> {
+    let current_cfg = butterfly.service_config_store
+                               .lock_rsr()
+                               .service_group(service_group)
+                               .map_rumor(ServiceConfigRumor::const_id(), |rumor| {
+                                   rumor.config(cache_key_path)
+                               })
+                               .and_then(Result::ok)
+                               .unwrap_or_default();
+    let changed_keys = config::changed_keys(&current_cfg, new_cfg);
+    butterfly.service_store
+             .lock_rsr()
+             .service_group(service_group)
+             .rumors()
+             .map(move |rumor| {
+                 let package = rumor.pkg
+                                    .parse::<PackageIdent>()
+                                    .ok()
+                                    .and_then(|ident| PackageCfgTraits::load(&ident));
+                 MemberCfgChange { member_id:    rumor.member_id.clone(),
+                                   ident:        rumor.pkg.clone(),
+                                   outcome:
+                                       outcome(&changed_keys, package.as_ref()).to_string(),
+                                   changed_keys: changed_keys.clone(), }
+             })
+             .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_restart_without_a_reconfigure_hook_or_for_restart_keys() {
+        let changed = vec!["log.level".to_string()];
+        let reconfigurable = PackageCfgTraits { has_reconfigure_hook: true,
+                                                restart_keys:         vec!["port".to_string()], };
+        let without_hook = PackageCfgTraits { has_reconfigure_hook: false,
+                                              restart_keys:         Vec::new(), };
+
+        assert_eq!(outcome(&changed, Some(&reconfigurable)),
+                   Outcome::Reconfigure);
+        assert_eq!(outcome(&changed, Some(&without_hook)), Outcome::Restart);
+        assert_eq!(outcome(&["port".to_string()], Some(&reconfigurable)),
+                   Outcome::Restart);
+        assert_eq!(outcome(&changed, None), Outcome::Unknown);
+        assert_eq!(outcome(&[], None), Outcome::Unchanged);
+    }
+}
@@ -0,0 +1,161 @@
+//! Watches the `--config-from` directories of services loaded with
+//! `--watch`, so that edits to their templates and hooks are picked up
+//! without reloading the service by hand.
+
+use crate::error::Result;
+use habitat_core::service::ServiceGroup;
+use notify::{DebouncedEvent,
+             RecommendedWatcher,
+             RecursiveMode,
+             Watcher};
+use std::{collections::HashMap,
+          path::Path,
+          sync::{mpsc,
+                 Arc,
+                 Mutex},
+          time::Duration};
+
+habitat_core::env_config_duration!(
+    /// How long should we wait to consolidate filesystem events in a
+    /// `--config-from` directory?
+    ///
+    /// Editors commonly write a file several times when saving it, so
+    /// this keeps a single save from rendering a service's templates
+    /// more than once.
+    ConfigFromWatcherDelay,
+    HAB_CONFIG_FROM_WATCHER_DELAY_MS => from_millis,
+    Duration::from_secs(1));
+
+struct Worker {
+    // Not actually used; only holding onto it for lifetime / Drop
+    // purposes (`Drop` kills the threads that the watcher spawns to do
+    // its work).
+    _watcher: RecommendedWatcher,
+    channel:  mpsc::Receiver<DebouncedEvent>,
+}
+
+#[derive(Clone)]
+pub struct ConfigFromWatcher {
+    // We use Arc/Mutex here, because this needs to be shareable
+    // across threads so we can remove watchers from futures.
+    workers: Arc<Mutex<HashMap<ServiceGroup, Worker>>>,
+}
+
+impl ConfigFromWatcher {
+    pub fn new() -> Self { Self { workers: Arc::new(Mutex::new(HashMap::new())), } }
+
+    /// Starts watching `path`, and everything below it, on behalf of
+    /// the service group. Nothing happens if the service group is
+    /// already being watched.
+    pub fn add(&self, service_group: &ServiceGroup, path: &Path) -> Result<()> {
+        let mut workers = self.workers.lock().expect("workers lock was poisoned");
+        if !workers.contains_key(service_group) {
+            let (tx, rx) = mpsc::channel();
+            let delay = ConfigFromWatcherDelay::configured_value();
+            let mut watcher = RecommendedWatcher::new(tx, delay.0)?;
+            watcher.watch(path, RecursiveMode::Recursive)?;
+            workers.insert(service_group.clone(),
+                           Worker { _watcher: watcher,
+                                    channel:  rx, });
+        }
+        Ok(())
+    }
+
+    /// Stops watching the `--config-from` directory of the service
+    /// group, if it was being watched.
+    pub fn remove(&self, service_group: &ServiceGroup) {
+        self.workers
+            .lock()
+            .expect("workers lock was poisoned")
+            .remove(service_group);
+    }
+
+    /// Returns `true` if any filesystem events were detected in the
+    /// service group's `--config-from` directory since the last time
+    /// this was called.
+    ///
+    /// As with the `SpecWatcher`, this is only a coarse "something
+    /// changed" signal; what changed is worked out by rendering the
+    /// templates again and comparing them with what is on disk.
+    pub fn have_events_for(&self, service_group: &ServiceGroup) -> bool {
+        if let Some(worker) = self.workers
+                                  .lock()
+                                  .expect("workers lock was poisoned")
+                                  .get(service_group)
+        {
+            let events = worker.channel.try_iter().collect::<Vec<_>>();
+            if !events.is_empty() {
+                trace!("ConfigFromWatcher events for {}: {:?}",
+                       service_group,
+                       events);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::locked_env_var;
+    use std::{fs::{self,
+                   File},
+              io::Write,
+              str::FromStr,
+              thread,
+              time::Instant};
+    use tempfile::TempDir;
+
+    locked_env_var!(HAB_CONFIG_FROM_WATCHER_DELAY_MS, lock_delay_var);
+
+    fn wait_for_events(watcher: &ConfigFromWatcher, service_group: &ServiceGroup) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(10) {
+            if watcher.have_events_for(service_group) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        false
+    }
+
+    #[test]
+    fn events_present_after_changing_a_template() {
+        let delay = lock_delay_var();
+        delay.set("100");
+
+        let dir = TempDir::new().expect("creating temp dir");
+        fs::create_dir(dir.path().join("config")).expect("creating config dir");
+        let service_group = ServiceGroup::from_str("foo.default").unwrap();
+        let watcher = ConfigFromWatcher::new();
+        watcher.add(&service_group, dir.path())
+               .expect("adding service group");
+
+        assert!(!watcher.have_events_for(&service_group));
+
+        let mut file =
+            File::create(dir.path().join("config").join("app.conf")).expect("creating file");
+        file.write_all(b"port = 80").expect("writing file");
+
+        assert!(wait_for_events(&watcher, &service_group));
+    }
+
+    #[test]
+    fn no_events_after_removing_service_group() {
+        let delay = lock_delay_var();
+        delay.set("100");
+
+        let dir = TempDir::new().expect("creating temp dir");
+        let service_group = ServiceGroup::from_str("foo.default").unwrap();
+        let watcher = ConfigFromWatcher::new();
+        watcher.add(&service_group, dir.path())
+               .expect("adding service group");
+        watcher.remove(&service_group);
+
+        File::create(dir.path().join("default.toml")).expect("creating file");
+        thread::sleep(Duration::from_millis(500));
+
+        assert!(!watcher.have_events_for(&service_group));
+    }
+}
@@ -0,0 +1,243 @@
+//! Watches the free space of the filesystem holding `/hab`, pausing package downloads while it
+//! is nearly full instead of letting installs fail partway through writing an artifact.
+//!
+//! The free space is checked periodically. When it drops below `HAB_DISK_SPACE_MIN_FREE_MB`,
+//! package installs (from the service updaters, the self updater and `hab svc load`) fail early
+//! and a `disk_space` event names the directories using the most space. Downloads resume, with
+//! another event, once the free space is back above the threshold by a margin, so that a
+//! filesystem hovering right at the threshold doesn't flap between the two.
+
+use crate::event;
+use habitat_common::outputln;
+use habitat_core::fs::{self as hab_fs,
+                       FS_ROOT_PATH,
+                       SVC_ROOT};
+use std::{fs,
+          io,
+          path::{Path,
+                 PathBuf},
+          sync::atomic::{AtomicBool,
+                         Ordering},
+          time::Duration};
+
+static LOGKEY: &str = "DW";
+
+/// The number of directories reported as the top consumers of space.
+const TOP_CONSUMERS: usize = 5;
+
+/// Downloads resume once the free space is this many times the minimum.
+const RESUME_FACTOR: u64 = 2;
+
+static DOWNLOADS_PAUSED: AtomicBool = AtomicBool::new(false);
+
+habitat_core::env_config_duration!(
+    /// How often the free space of the filesystem holding `/hab` is checked.
+    DiskSpaceCheckPeriod,
+    HAB_DISK_SPACE_CHECK_PERIOD_SECS => from_secs,
+    Duration::from_secs(30));
+
+habitat_core::env_config_int!(/// The free space, in megabytes, below which package downloads
+                              /// are paused.
+                              #[derive(Debug, Clone, Copy)]
+                              DiskSpaceMinFreeMb,
+                              u64,
+                              HAB_DISK_SPACE_MIN_FREE_MB,
+                              512);
+
+/// Whether package downloads are paused because the filesystem holding `/hab` is nearly full.
+pub fn downloads_paused() -> bool { DOWNLOADS_PAUSED.load(Ordering::Relaxed) }
+
+/// The space used by a directory under `/hab`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiskConsumer {
+    pub path:  PathBuf,
+    pub bytes: u64,
+}
+
+/// The free and total space of a filesystem, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub available: u64,
+    pub total:     u64,
+}
+
+pub struct DiskWatchdog {
+    path: PathBuf,
+}
+
+impl DiskWatchdog {
+    pub fn new() -> Self { DiskWatchdog { path: FS_ROOT_PATH.join(hab_fs::ROOT_PATH), } }
+
+    /// Pauses or resumes package downloads if the free space has crossed the threshold since the
+    /// last check.
+    pub fn check(&self) {
+        let space = match disk_space(&self.path) {
+            Ok(space) => space,
+            Err(e) => {
+                debug!("Unable to determine the free space of {}: {}",
+                       self.path.display(),
+                       e);
+                return;
+            }
+        };
+        let min_free = u64::from(DiskSpaceMinFreeMb::configured_value()) * 1024 * 1024;
+        let was_paused = downloads_paused();
+        let paused = should_pause(was_paused, space.available, min_free);
+        if paused == was_paused {
+            return;
+        }
+        DOWNLOADS_PAUSED.store(paused, Ordering::Relaxed);
+        if paused {
+            let consumers = top_consumers();
+            outputln!("Only {} MB free on {}; pausing package downloads until space is freed. \
+                       Largest directories: {}",
+                      space.available / 1024 / 1024,
+                      self.path.display(),
+                      consumers.iter()
+                               .map(|c| {
+                                   format!("{} ({} MB)", c.path.display(), c.bytes / 1024 / 1024)
+                               })
+                               .collect::<Vec<_>>()
+                               .join(", "));
+            event::disk_space_changed(true, space, &consumers);
+        } else {
+            outputln!("{} MB free on {}; resuming package downloads",
+                      space.available / 1024 / 1024,
+                      self.path.display());
+            event::disk_space_changed(false, space, &[]);
+        }
+    }
+}
+
+/// Whether downloads should be paused, given whether they currently are.
+fn should_pause(paused: bool, available: u64, min_free: u64) -> bool {
+    if paused {
+        available < min_free.saturating_mul(RESUME_FACTOR)
+    } else {
+        available < min_free
+    }
+}
+
+/// The directories under `/hab` using the most space: each origin's installed packages, the
+/// artifact cache and each service's directory.
+fn top_consumers() -> Vec<DiskConsumer> {
+    let pkg_root = hab_fs::pkg_root_path(Some(&*FS_ROOT_PATH));
+    let mut paths = subdirectories(&pkg_root);
+    paths.extend(subdirectories(&*SVC_ROOT));
+    paths.push(hab_fs::cache_artifact_path(Some(&*FS_ROOT_PATH)));
+    let mut consumers = paths.into_iter()
+                             .map(|path| {
+                                 let bytes = dir_size(&path);
+                                 DiskConsumer { path, bytes }
+                             })
+                             .collect::<Vec<_>>();
+    consumers.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    consumers.truncate(TOP_CONSUMERS);
+    consumers
+}
+
+fn subdirectories(path: &Path) -> Vec<PathBuf> {
+    fs::read_dir(path).map(|entries| {
+                          entries.filter_map(|entry| entry.ok())
+                                 .filter(|entry| {
+                                     entry.file_type().map(|t| t.is_dir()).unwrap_or(false)
+                                 })
+                                 .map(|entry| entry.path())
+                                 .collect()
+                      })
+                      .unwrap_or_default()
+}
+
+/// The total size of the files below `path`. Symlinks are not followed, and anything which can't
+/// be read is skipped.
+fn dir_size(path: &Path) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries.filter_map(|entry| entry.ok())
+           .map(|entry| {
+               match entry.file_type() {
+                   Ok(t) if t.is_dir() => dir_size(&entry.path()),
+                   Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+                   _ => 0,
+               }
+           })
+           .sum()
+}
+
+#[cfg(unix)]
+fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::{ffi::CString,
+              mem,
+              os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // The field types vary between platforms, hence the conversions.
+    #[allow(clippy::useless_conversion)]
+    let (block_size, available, total) = (u64::from(stat.f_frsize),
+                                          u64::from(stat.f_bavail),
+                                          u64::from(stat.f_blocks));
+    Ok(DiskSpace { available: available * block_size,
+                   total:     total * block_size, })
+}
+
+#[cfg(windows)]
+fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::{iter::once,
+              os::windows::ffi::OsStrExt,
+              ptr};
+    use winapi::um::{fileapi,
+                     winnt::ULARGE_INTEGER};
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(once(0)).collect();
+    let mut available: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+    let mut total: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+    if unsafe {
+        fileapi::GetDiskFreeSpaceExW(wide_path.as_ptr(),
+                                     &mut available,
+                                     &mut total,
+                                     ptr::null_mut())
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(DiskSpace { available: unsafe { *available.QuadPart() },
+                   total:     unsafe { *total.QuadPart() }, })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn downloads_resume_only_once_space_is_well_above_the_threshold() {
+        assert!(!should_pause(false, 600, 512));
+        assert!(should_pause(false, 500, 512));
+        assert!(should_pause(true, 600, 512));
+        assert!(!should_pause(true, 1024, 512));
+    }
+
+    #[test]
+    fn dir_size_includes_nested_files() {
+        let dir = TempDir::new().expect("creating temp dir");
+        fs::create_dir(dir.path().join("nested")).expect("creating nested dir");
+        fs::write(dir.path().join("a"), vec![0; 100]).expect("writing file");
+        fs::write(dir.path().join("nested").join("b"), vec![0; 50]).expect("writing file");
+
+        assert_eq!(dir_size(dir.path()), 150);
+    }
+
+    #[test]
+    fn disk_space_of_an_existing_directory() {
+        let dir = TempDir::new().expect("creating temp dir");
+        let space = disk_space(dir.path()).expect("reading disk space");
+        assert!(space.total > 0);
+        assert!(space.available <= space.total);
+    }
+}
@@ -0,0 +1,174 @@
+//! Keeps the origin key revocation lists in the key cache up to date.
+//!
+//! The list of each `--key-revocation-origin` is fetched periodically in the background from
+//! Builder, as is each `--key-revocation-url`. A list is only installed once its signature has
+//! been verified against a key already in the cache, and only if it is newer than the installed
+//! one. The outcome of the latest attempt for every feed is recorded in the gateway state so that
+//! it can be inspected with `hab sup key-revocations`.
+
+use super::sync::GatewayState;
+use crate::{PRODUCT,
+            VERSION};
+use habitat_api_client::BuilderAPIClient;
+use habitat_core::crypto::keys::revocation::RevocationList;
+use habitat_http_client::ApiClient;
+use serde::Serialize;
+use std::{fmt,
+          path::Path,
+          sync::Arc,
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
+
+habitat_core::env_config_duration!(
+    /// How often key revocation lists are fetched.
+    KeyRevocationFetchPeriod,
+    HAB_KEY_REVOCATION_FETCH_PERIOD_SECS => from_secs,
+    Duration::from_secs(60 * 60));
+
+/// Where a signed key revocation list is fetched from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RevocationFeed {
+    /// The list at an arbitrary URL
+    Url(String),
+    /// The list Builder publishes for an origin
+    Builder { bldr_url: String, origin: String },
+}
+
+impl fmt::Display for RevocationFeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevocationFeed::Url(url) => write!(f, "{}", url),
+            RevocationFeed::Builder { bldr_url, origin } => {
+                write!(f,
+                       "{}/v1/depot/origins/{}/revocation_list",
+                       bldr_url.trim_end_matches('/'),
+                       origin)
+            }
+        }
+    }
+}
+
+/// The outcome of fetching the revocation list at a single URL.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RevocationSource {
+    pub url:          String,
+    /// The origin of the most recently installed list.
+    pub origin:       Option<String>,
+    pub revoked:      u32,
+    /// Seconds since the UNIX epoch at which the list was last fetched, successfully or not.
+    pub last_attempt: Option<u64>,
+    /// Seconds since the UNIX epoch at which a list was last installed.
+    pub last_success: Option<u64>,
+    pub error:        Option<String>,
+}
+
+impl RevocationSource {
+    pub fn new(url: &str) -> Self {
+        RevocationSource { url: url.to_string(),
+                           ..Default::default() }
+    }
+}
+
+/// Fetch the list of every feed in `feeds` in the background, recording the results in
+/// `gateway_state`.
+pub fn fetch_all(feeds: &[RevocationFeed],
+                 cache_key_path: &Path,
+                 gateway_state: &Arc<GatewayState>) {
+    for feed in feeds {
+        let feed = feed.clone();
+        let cache_key_path = cache_key_path.to_path_buf();
+        let gateway_state = Arc::clone(gateway_state);
+        tokio::spawn(async move {
+            let result = fetch(&feed, &cache_key_path).await;
+            record_gsw(&gateway_state, &feed.to_string(), result);
+        });
+    }
+}
+
+async fn fetch(feed: &RevocationFeed, cache_key_path: &Path) -> Result<RevocationList, String> {
+    let content = match feed {
+        RevocationFeed::Url(url) => {
+            let client = ApiClient::new(url, PRODUCT, VERSION, None).map_err(|e| e.to_string())?;
+            let resp = client.get("").send().await.map_err(|e| e.to_string())?;
+            if !resp.status().is_success() {
+                return Err(format!("HTTP {}", resp.status()));
+            }
+            resp.text().await.map_err(|e| e.to_string())?
+        }
+        RevocationFeed::Builder { bldr_url, origin } => {
+            BuilderAPIClient::new(bldr_url.as_str(), PRODUCT, VERSION, None)
+                .map_err(|e| e.to_string())?
+                .fetch_origin_revocation_list(origin)
+                .await
+                .map_err(|e| e.to_string())?
+        }
+    };
+    if let RevocationFeed::Builder { origin, .. } = feed {
+        let list =
+            RevocationList::from_signed_str(&content, cache_key_path).map_err(|e| e.to_string())?;
+        if list.origin() != origin {
+            return Err(format!("Builder returned the revocation list of {} rather than {}",
+                               list.origin(),
+                               origin));
+        }
+    }
+    RevocationList::install(&content, cache_key_path).map_err(|e| e.to_string())
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (write)
+fn record_gsw(gateway_state: &GatewayState, url: &str, result: Result<RevocationList, String>) {
+    let mut state = gateway_state.lock_gsw();
+    let source = state.key_revocation_source_mut(url);
+    let now = now();
+    source.last_attempt = Some(now);
+    match result {
+        Ok(list) => {
+            debug!("Installed key revocation list for {} from {}",
+                   list.origin(),
+                   url);
+            source.origin = Some(list.origin().to_string());
+            source.revoked = list.revoked_count() as u32;
+            source.last_success = Some(now);
+            source.error = None;
+        }
+        Err(e) => {
+            warn!("Unable to update key revocation list from {}: {}", url, e);
+            source.error = Some(e);
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+                     .map(|d| d.as_secs())
+                     .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_keep_the_last_successful_fetch() {
+        let gateway_state = GatewayState::default();
+        let url = "https://example.com/core.crl";
+        record_gsw(&gateway_state, url, Ok(RevocationList::new("core")));
+        record_gsw(&gateway_state, url, Err("HTTP 500".to_string()));
+
+        let state = gateway_state.lock_gsr();
+        let source = &state.key_revocations()[0];
+        assert_eq!(source.origin.as_deref(), Some("core"));
+        assert!(source.last_success.is_some());
+        assert_eq!(source.error.as_deref(), Some("HTTP 500"));
+    }
+
+    #[test]
+    fn builder_feeds_are_shown_by_the_url_of_the_origins_list() {
+        let feed = RevocationFeed::Builder { bldr_url: "https://bldr.habitat.sh/".to_string(),
+                                             origin:   "core".to_string(), };
+        assert_eq!(feed.to_string(),
+                   "https://bldr.habitat.sh/v1/depot/origins/core/revocation_list");
+    }
+}
@@ -0,0 +1,91 @@
+//! Verifies the installed files of a service's package against the checksums of its `FILES`
+//! manifest before the service starts (`hab sup run --verify-on-start`), so that a tampered
+//! package is never run.
+//!
+//! Hashing every file of a large package is slow, so packages which have been verified are
+//! remembered and aren't verified again when their services restart.
+
+use crate::{error::{Error,
+                    Result},
+            event,
+            manager::service::Service};
+use habitat_common::outputln;
+use habitat_core::{error::Error as CoreError,
+                   fs::FS_ROOT_PATH,
+                   package::{metadata::MetaFile,
+                             PackageIdent,
+                             PackageInstall}};
+use std::{collections::HashSet,
+          path::Path};
+
+static LOGKEY: &str = "PV";
+
+/// The most packages remembered as verified. Past this, they are all forgotten, and verified
+/// again the next time their services start.
+const MAX_VERIFIED: usize = 256;
+
+#[derive(Default)]
+pub struct PackageVerifier {
+    verified: HashSet<PackageIdent>,
+}
+
+impl PackageVerifier {
+    pub fn new() -> Self { Self::default() }
+
+    /// Checks the files of the service's package, unless they were checked before. A package
+    /// built without a `FILES` manifest can't be checked, and is allowed to start.
+    ///
+    /// # Failures
+    ///
+    /// * If the package's files don't match its `FILES` manifest, in which case a
+    ///   `package_tampered` event is sent
+    pub fn verify(&mut self, service: &Service) -> Result<()> {
+        let ident = service.pkg.ident.as_ref();
+        if self.verified.contains(ident) {
+            return Ok(());
+        }
+        let package = PackageInstall::load(ident, Some(Path::new(&*FS_ROOT_PATH)))?;
+        match package.tampered_files() {
+            Ok(tampered) if tampered.is_empty() => {
+                debug!("Verified the files of {}", ident);
+            }
+            Ok(tampered) => {
+                for file in &tampered {
+                    outputln!("{}: {}", file.path.display(), file.problem);
+                }
+                event::package_tampered(service, &tampered);
+                return Err(Error::PackageTampered(ident.clone(), tampered.len()));
+            }
+            Err(CoreError::MetaFileNotFound(MetaFile::Files)) => {
+                outputln!("Not verifying {}, which has no FILES manifest", ident);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        self.remember(ident.clone());
+        Ok(())
+    }
+
+    fn remember(&mut self, ident: PackageIdent) {
+        if self.verified.len() >= MAX_VERIFIED {
+            self.verified.clear();
+        }
+        self.verified.insert(ident);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verified_packages_are_forgotten_past_the_limit() {
+        let mut verifier = PackageVerifier::new();
+        for release in 0..MAX_VERIFIED {
+            verifier.remember(format!("core/redis/4.0.14/{}", release).parse().unwrap());
+        }
+        assert_eq!(verifier.verified.len(), MAX_VERIFIED);
+
+        verifier.remember("core/redis/4.0.14/20200421191514".parse().unwrap());
+        assert_eq!(verifier.verified.len(), 1);
+    }
+}
@@ -14,6 +14,7 @@ mod hook_runner;
 mod hooks;
 #[cfg(windows)]
 mod pipe_hook_client;
+mod scheduled_tasks;
 pub mod spec;
 mod supervisor;
 mod terminator;
@@ -38,6 +39,8 @@ use crate::{census::{CensusGroup,
             error::{Error,
                     Result},
             manager::{event,
+                      service_history::{Lifecycle,
+                                        RestartReason},
                       sync::GatewayState,
                       FsCfg,
                       ServicePidSource,
@@ -54,22 +57,26 @@ pub use habitat_common::templating::{config::{Cfg,
                                                Pkg,
                                                PkgProxy}};
 use habitat_common::{outputln,
-                     templating::{config::CfgRenderer,
+                     templating::{config::{self,
+                                           CfgRenderer},
                                   hooks::Hook},
                      FeatureFlag};
 #[cfg(windows)]
 use habitat_core::os::users;
-use habitat_core::{crypto::hash,
+use habitat_core::{clock,
+                   crypto::hash,
                    fs::{atomic_write,
                         svc_hooks_path,
                         SvcDir,
                         FS_ROOT_PATH},
-                   os::process::ShutdownTimeout,
+                   os::process::{HookTimeouts,
+                                 ShutdownTimeout},
                    package::{metadata::Bind,
                              PackageIdent,
                              PackageInstall},
                    service::{ServiceBind,
-                             ServiceGroup},
+                             ServiceGroup,
+                             UpdateWindow},
                    ChannelIdent};
 use habitat_launcher_client::LauncherCli;
 use habitat_sup_protocol::types::BindingMode;
@@ -79,7 +86,8 @@ pub use habitat_sup_protocol::types::{ProcessState,
                                       UpdateStrategy};
 use parking_lot::RwLock;
 use prometheus::{HistogramTimer,
-                 HistogramVec};
+                 HistogramVec,
+                 IntCounterVec};
 use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
@@ -87,13 +95,16 @@ use std::{self,
           collections::HashSet,
           fmt,
           fs,
+          mem,
           ops::Deref,
           path::{Path,
                  PathBuf},
           result,
           sync::{Arc,
                  Mutex},
-          time::SystemTime};
+          time::{Duration,
+                 Instant,
+                 SystemTime}};
 
 static LOGKEY: &str = "SR";
 
@@ -105,6 +116,11 @@ lazy_static! {
         register_histogram_vec!("hab_sup_hook_duration_seconds",
                                 "The time it takes for a hook to run",
                                 &["hook"]).unwrap();
+    static ref CONFIG_FILE_RENDERS: IntCounterVec =
+        register_int_counter_vec!("hab_sup_config_file_renders_total",
+                                  "Configuration files rendered from templates, by whether they \
+                                   were written, restored or left unchanged",
+                                  &["result"]).unwrap();
 }
 
 /// When evaluating whether a particular service group can satisfy a
@@ -135,25 +151,45 @@ struct TemplateUpdate {
     hooks:                 HookCompileTable,
     config_changed:        bool,
     have_reconfigure_hook: bool,
+    /// The configuration keys which changed since the templates were last rendered.
+    changed_keys:          Vec<String>,
+    /// Whether any of `changed_keys` is one the package declared the `reconfigure` hook can't
+    /// apply.
+    restart_keys_changed:  bool,
 }
 
 impl TemplateUpdate {
-    fn new(hooks: HookCompileTable, config_changed: bool, have_reconfigure_hook: bool) -> Self {
+    fn new(hooks: HookCompileTable,
+           config_changed: bool,
+           have_reconfigure_hook: bool,
+           changed_keys: Vec<String>,
+           restart_keys: &[String])
+           -> Self {
+        let restart_keys_changed = restart_keys_changed(&changed_keys, restart_keys);
         Self { hooks,
                config_changed,
-               have_reconfigure_hook }
+               have_reconfigure_hook,
+               changed_keys,
+               restart_keys_changed }
     }
 
-    /// Returns `true` if the service needs to be restarted.
+    /// Returns why the service needs to be restarted, if it does.
     ///
     /// A restart is needed under the following conditions:
     /// 1. the `run` or `post-run` hooks have changed. A restart is limited to these hooks
     /// because they are the only hooks that can impact the execution of the service.
     /// 2. `/config` changed and there is no `reconfigure` hook
-    fn needs_restart(&self) -> bool {
-        self.hooks.run_changed()
-        || self.hooks.post_run_changed()
-        || (!self.have_reconfigure_hook && self.config_changed)
+    /// 3. `/config` changed along with a key the package lists in `pkg_restart_keys`
+    fn restart_reason(&self) -> Option<RestartReason> {
+        if self.hooks.run_changed() || self.hooks.post_run_changed() {
+            Some(RestartReason::HooksChanged)
+        } else if !self.have_reconfigure_hook && self.config_changed {
+            Some(RestartReason::ConfigChanged)
+        } else if self.restart_keys_changed && self.config_changed {
+            Some(RestartReason::RestartKeysChanged)
+        } else {
+            None
+        }
     }
 
     /// Returns `true` if the service needs to be reconfigured.
@@ -184,6 +220,8 @@ pub struct Service {
     pub pkg:                 Pkg,
     pub sys:                 Arc<Sys>,
     pub user_config_updated: bool,
+    /// Set when the `config_from` directory of a service loaded with `--watch` has changed.
+    pub config_from_updated: bool,
     // TODO (DM): This flag is a temporary hack to signal to the `Manager` that this service needs
     // to be restarted. As we continue refactoring lifecycle hooks this flag should be removed.
     pub needs_restart:       bool,
@@ -193,6 +231,9 @@ pub struct Service {
     initialization_state:    Arc<RwLock<InitializationState>>,
 
     config_renderer:      CfgRenderer,
+    /// The merged configuration as it was when templates were last rendered, to tell which keys
+    /// changed when they are rendered again.
+    rendered_cfg:         toml::value::Table,
     // Note: This field is really only needed for serializing a
     // Service in the gateway (see ServiceProxy's Serialize
     // implementation). Ideally, we could get rid of this, since we're
@@ -204,6 +245,9 @@ pub struct Service {
     // hook, we need to wrap some Arc<Mutex<_>> protection around it
     // :(
     health_check_result:  Arc<Mutex<HealthCheckResult>>,
+    /// Whether the service was healthy, as far as the rest of its service group was last told.
+    /// Other members go by this when deciding whether an update may carry on.
+    gossiped_healthy:     bool,
     last_election_status: ElectionStatus,
     /// The binds that the current service package declares, both
     /// required and optional. We don't differentiate because this is
@@ -224,6 +268,7 @@ pub struct Service {
     /// census.
     unsatisfied_binds:    HashSet<ServiceBind>,
     hooks:                HookTable,
+    feature_flags:        FeatureFlag,
     manager_fs_cfg:       Arc<FsCfg>,
     supervisor:           Arc<Mutex<Supervisor>>,
 
@@ -232,9 +277,17 @@ pub struct Service {
     /// A "handle" to the never-ending future that periodically runs
     /// health checks on this service. This is the means by which we
     /// can stop that future.
-    health_check_handle: Option<AbortHandle>,
-    post_run_handle:     Option<AbortHandle>,
-    initialize_handle:   Option<AbortHandle>,
+    health_check_handle:    Option<AbortHandle>,
+    /// The tasks the package asks to be run on a schedule, and how their runs have gone.
+    scheduled_tasks:        Vec<scheduled_tasks::Task>,
+    /// "Handles" to the never-ending futures that run the scheduled tasks.
+    scheduled_task_handles: Vec<AbortHandle>,
+    post_run_handle:        Option<AbortHandle>,
+    initialize_handle:      Option<AbortHandle>,
+    /// When the `run` hook's startup grace period ends, until it first passes a health check.
+    startup_deadline:       Option<Instant>,
+    /// A spec loaded with `--no-restart` whose changes are waiting for the service to restart.
+    pending_spec:           Option<ServiceSpec>,
 }
 
 impl Service {
@@ -250,15 +303,57 @@ impl Service {
 
     pub(crate) fn update_condition(&self) -> UpdateCondition { self.spec.update_condition }
 
+    pub(crate) fn canary_percent(&self) -> u32 { self.spec.canary_percent }
+
+    pub(crate) fn canary_soak(&self) -> Duration {
+        Duration::from_secs(self.spec.canary_soak.into())
+    }
+
+    pub(crate) fn update_window(&self) -> Option<UpdateWindow> { self.spec.update_window.clone() }
+
     pub(crate) fn shutdown_timeout(&self) -> Option<ShutdownTimeout> { self.spec.shutdown_timeout }
 
+    pub(crate) fn shutdown_order(&self) -> i32 { self.spec.shutdown_order }
+
     pub(crate) fn spec(&self) -> ServiceSpec { self.spec.clone() }
 
+    /// The `config_from` directory of the service, if it was loaded with `--watch`.
+    pub(crate) fn watched_config_from(&self) -> Option<&Path> {
+        self.spec
+            .config_from
+            .as_deref()
+            .filter(|_| self.spec.watch_config_from)
+    }
+
+    /// The timeouts of the service's hooks, from its spec or else from its package.
+    fn hook_timeouts(&self) -> HookTimeouts { self.spec.hook_timeouts.or(self.pkg.hook_timeouts) }
+
     pub(crate) fn set_spec(&mut self, spec: ServiceSpec) {
         trace!("Setting spec for {}: {:?}", self.spec.ident, spec);
         self.spec = spec
     }
 
+    /// Holds back `spec`, whose changes need a restart, until the service is next restarted or
+    /// its update window opens. Returns whether the service was not already waiting on a spec.
+    ///
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (write)
+    pub(crate) fn hold_restart_gsw(&mut self, spec: ServiceSpec) -> bool {
+        let newly_held = self.pending_spec.is_none();
+        if newly_held {
+            self.record_lifecycle_gsw(Lifecycle::RestartPending);
+        }
+        self.pending_spec = Some(spec);
+        newly_held
+    }
+
+    /// Whether a held spec may now be applied, because the service's update window is open.
+    pub(crate) fn restart_due(&self) -> bool {
+        self.pending_spec
+            .as_ref()
+            .map_or(false, ServiceSpec::held_restart_due)
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn with_package(sys: Arc<Sys>,
                           package: &PackageInstall,
@@ -271,6 +366,7 @@ impl Service {
                           -> Result<Service> {
         spec.validate(&package)?;
         let all_pkg_binds = package.all_binds()?;
+        let tasks = package.scheduled_tasks()?;
         let pkg = Self::resolve_pkg(&package, &spec).await?;
         let spec_file = manager_fs_cfg.specs_path.join(spec.file());
         let service_group = ServiceGroup::new(&pkg.name, &spec.group, organization)?;
@@ -279,15 +375,20 @@ impl Service {
         let cfg = Cfg::new(&pkg, spec.config_from.as_ref())?;
         Ok(Service { spec,
                      sys,
+                     rendered_cfg: cfg.merged(),
                      cfg,
                      config_renderer: CfgRenderer::new(&config_root)?,
                      health_check_result: Arc::new(Mutex::new(HealthCheckResult::Unknown)),
+                     gossiped_healthy: true,
                      hooks: HookTable::load(&pkg.name,
                                             &hooks_root,
                                             svc_hooks_path(&service_group.service()),
+                                            &tasks,
                                             feature_flags),
+                     feature_flags,
                      last_election_status: ElectionStatus::None,
                      user_config_updated: false,
+                     config_from_updated: false,
                      needs_restart: false,
                      initialization_state:
                          Arc::new(RwLock::new(InitializationState::Uninitialized)),
@@ -301,8 +402,14 @@ impl Service {
                      spec_file,
                      gateway_state,
                      health_check_handle: None,
+                     scheduled_tasks: tasks.into_iter()
+                                           .map(scheduled_tasks::Task::new)
+                                           .collect(),
+                     scheduled_task_handles: Vec::new(),
                      post_run_handle: None,
-                     initialize_handle: None })
+                     initialize_handle: None,
+                     startup_deadline: None,
+                     pending_spec: None })
     }
 
     // And now prepare yourself for a little horribleness...Ready?
@@ -383,11 +490,18 @@ impl Service {
                          .start(&self.pkg,
                                 &self.service_group,
                                 launcher,
-                                self.spec.svc_encrypted_password.as_deref());
+                                self.spec.svc_encrypted_password.as_deref(),
+                                &self.spec.scheduling());
         match result {
             Ok(_) => {
                 self.needs_restart = false;
+                self.startup_deadline =
+                    seconds(self.hook_timeouts().run_startup_grace).map(|grace| {
+                                                                       clock::instant() + grace
+                                                                   });
+                self.record_lifecycle_gsw(Lifecycle::Started);
                 self.start_health_checks();
+                self.start_scheduled_tasks();
             }
             Err(e) => {
                 outputln!(preamble self.service_group, "Service start failed: {}", e);
@@ -399,6 +513,32 @@ impl Service {
         *self.initialization_state.read() == InitializationState::Initialized
     }
 
+    /// Whether the `run` hook has been running for its whole startup grace period without
+    /// passing a health check. The grace period no longer applies once it has passed one.
+    fn startup_grace_expired(&mut self) -> bool {
+        let healthy = *self.health_check_result
+                           .lock()
+                           .expect("Could not unlock health_check_result")
+                      == HealthCheckResult::Ok;
+        match self.startup_deadline {
+            Some(deadline) if healthy || clock::instant() >= deadline => {
+                self.startup_deadline = None;
+                !healthy
+            }
+            _ => false,
+        }
+    }
+
+    /// Add a transition to this service's lifecycle history.
+    ///
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (write)
+    fn record_lifecycle_gsw(&self, lifecycle: Lifecycle) {
+        self.gateway_state
+            .lock_gsw()
+            .record_lifecycle(&self.service_group, self.pkg.ident.as_ref(), lifecycle);
+    }
+
     /// Initiate an endless task that performs periodic health checks for the service and takes
     /// appropriate actions upon receiving the results of a health check. The actions taken are:
     ///
@@ -415,6 +555,7 @@ impl Service {
                                               self.spec.svc_encrypted_password.clone());
 
         let service_group = self.service_group.clone();
+        let ident = PackageIdent::from(self.pkg.ident.clone());
         let service_event_metadata = self.to_service_metadata();
         let service_health_result = Arc::clone(&self.health_check_result);
         let gateway_state = Arc::clone(&self.gateway_state);
@@ -428,11 +569,21 @@ impl Service {
             {
                 debug!("Caching HealthCheckResult = '{}' for '{}'",
                        result, service_group);
-                *service_health_result.lock()
-                                      .expect("Could not unlock service_health_result") = result;
-
-                gateway_state.lock_gsw()
-                             .set_health_of(service_group.clone(), result);
+                let previous = {
+                    let mut cached =
+                        service_health_result.lock()
+                                             .expect("Could not unlock service_health_result");
+                    mem::replace(&mut *cached, result)
+                };
+
+                let mut state = gateway_state.lock_gsw();
+                state.set_health_of(service_group.clone(), result);
+                if previous != result {
+                    let lifecycle = Lifecycle::HealthChanged { from: previous,
+                                                               to:   result, };
+                    state.record_lifecycle(&service_group, &ident, lifecycle);
+                }
+                drop(state);
 
                 event::health_check(service_event_metadata.clone(), result, status, interval);
             }
@@ -462,6 +613,57 @@ impl Service {
         self.start_health_checks();
     }
 
+    /// Initiate an endless task for each of the service's scheduled tasks, which runs its hook
+    /// whenever it is due, records how the run went for the gateway and sends a
+    /// `ScheduledTaskEvent`.
+    fn start_scheduled_tasks(&mut self) {
+        let mut handles = Vec::with_capacity(self.scheduled_tasks.len());
+        for task in &self.scheduled_tasks {
+            let hook = match self.hooks
+                                 .tasks
+                                 .iter()
+                                 .find(|hook| hook.name() == task.task.hook)
+            {
+                Some(hook) => Arc::clone(hook),
+                None => {
+                    outputln!(preamble self.service_group,
+                              "No {} hook for scheduled task {}; it will not be run",
+                              task.task.hook,
+                              task.task);
+                    continue;
+                }
+            };
+            debug!("Starting scheduled task {} for {}",
+                   task.task, self.pkg.ident);
+            let f = scheduled_tasks::run_on_schedule(task.task.clone(),
+                                                     hook,
+                                                     self.service_group.clone(),
+                                                     self.pkg.clone(),
+                                                     self.spec.svc_encrypted_password.clone(),
+                                                     Arc::clone(&task.status),
+                                                     self.to_service_metadata());
+            let (f, handle) = future::abortable(f);
+            handles.push(handle);
+            tokio::spawn(f);
+        }
+        self.scheduled_task_handles = handles;
+    }
+
+    /// Stop the endless futures that run the service's scheduled tasks, along with any run in
+    /// progress.
+    fn stop_scheduled_tasks(&mut self) {
+        for h in self.scheduled_task_handles.drain(..) {
+            h.abort();
+        }
+        for task in &self.scheduled_tasks {
+            let mut status = task.status
+                                 .lock()
+                                 .expect("Couldn't lock scheduled task status");
+            status.running = false;
+            status.next_run = None;
+        }
+    }
+
     /// Called when the Supervisor reattaches itself to an already
     /// running service. Use this to re-initiate any associated
     /// processes, futures, etc.
@@ -471,6 +673,8 @@ impl Service {
         outputln!("Reattaching to {}", self.service_group);
         *self.initialization_state.write() = InitializationState::Initialized;
         self.restart_health_checks();
+        self.stop_scheduled_tasks();
+        self.start_scheduled_tasks();
         // We intentionally do not restart the `post_run` retry future. Currently, there is not
         // a way to track if `post_run` ran successfully following a Supervisor restart.
         // See https://github.com/habitat-sh/habitat/issues/6739
@@ -492,6 +696,7 @@ impl Service {
         self.stop_initialize();
         self.stop_post_run();
         self.stop_health_checks();
+        self.stop_scheduled_tasks();
     }
 
     /// Return a future that will shut down a service, performing any
@@ -510,10 +715,17 @@ impl Service {
             .expect("Couldn't lock supervisor")
             .stop(shutdown_config);
         gs.lock_gsw().remove(&service_group);
+        self.record_lifecycle_gsw(Lifecycle::Stopped);
 
         if let Some(hook) = self.post_stop() {
             if let Err(e) = hook.into_future().await {
                 outputln!(preamble service_group, "Service stop failed: {}", e);
+                if let Some(timeout) = e.hook_timeout() {
+                    let hook = hooks::PostStopHook::FILE_NAME;
+                    self.record_lifecycle_gsw(Lifecycle::HookTimedOut { hook,
+                                                                        timeout_secs:
+                                                                            timeout.as_secs() });
+                }
             }
         }
     }
@@ -529,7 +741,8 @@ impl Service {
 
     /// Performs updates and executes hooks.
     ///
-    /// Returns `true` if the service was marked to be restarted or reconfigured.
+    /// Returns `true` if the service was marked to be restarted or reconfigured, or its health
+    /// changed such that its service rumor needs to be gossiped again.
     pub fn tick(&mut self, census_ring: &CensusRing, launcher: &LauncherCli) -> bool {
         // We may need to block the service from starting until all
         // its binds are satisfied
@@ -608,7 +821,19 @@ impl Service {
                 }
             }
         }
-        template_data_changed
+        template_data_changed || self.health_changed()
+    }
+
+    /// Whether the service has become healthy or critical since its service rumor was last
+    /// gossiped, noting the new health if so.
+    fn health_changed(&mut self) -> bool {
+        let healthy = *self.health_check_result
+                           .lock()
+                           .expect("Could not unlock health_check_result")
+                      != HealthCheckResult::Critical;
+        let changed = healthy != self.gossiped_healthy;
+        self.gossiped_healthy = healthy;
+        changed
     }
 
     /// Iterate through all the service binds, marking any that are
@@ -688,7 +913,9 @@ impl Service {
                                census_ring: &'a CensusRing,
                                service_bind: &'a ServiceBind)
                                -> BindStatus<'a> {
-        match census_ring.census_group_for(service_bind.service_group()) {
+        let service_group = service_bind.service_group()
+                                        .in_org(self.service_group.org());
+        match census_ring.census_group_for(&service_group) {
             None => BindStatus::NotPresent,
             Some(group) => {
                 if group.active_members().count() == 0 {
@@ -781,7 +1008,8 @@ impl Service {
             census_ring.census_group_for(&self.service_group)
                        .expect("Service update failed; unable to find own service group");
         let cfg_updated_from_rumors = self.update_gossip(census_group);
-        let template_data_changed = cfg_updated_from_rumors || self.user_config_updated;
+        let template_data_changed =
+            cfg_updated_from_rumors || self.user_config_updated || self.config_from_updated;
 
         if self.user_config_updated {
             if let Err(e) = self.cfg.reload_user() {
@@ -791,17 +1019,54 @@ impl Service {
             self.user_config_updated = false;
         }
 
-        let template_update = if template_data_changed || census_ring.changed() {
+        if self.config_from_updated {
+            self.reload_config_from();
+            self.config_from_updated = false;
+        }
+
+        let template_update = if template_data_changed || self.census_data_changed(census_ring) {
             let ctx = self.render_context(census_ring);
+            let cfg = self.cfg.merged();
+            let changed_keys = config::changed_keys(&self.rendered_cfg, &cfg);
+            self.rendered_cfg = cfg;
             TemplateUpdate::new(self.compile_hooks(&ctx),
                                 self.compile_configuration(&ctx),
-                                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some())
+                                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some(),
+                                changed_keys,
+                                &self.pkg.restart_keys)
         } else {
             TemplateUpdate::default()
         };
         (template_data_changed, template_update)
     }
 
+    /// Reads the default configuration, configuration templates, and hook templates from the
+    /// `config_from` directory again, so the next render picks up any changes to them.
+    fn reload_config_from(&mut self) {
+        if let Err(e) = self.cfg.update_defaults_from_package(&self.pkg) {
+            outputln!(preamble self.service_group, "Reloading default.toml failed: {}", e);
+        }
+        let config_root = Self::config_root(&self.pkg, self.spec.config_from.as_ref());
+        match CfgRenderer::new(&config_root) {
+            Ok(renderer) => self.config_renderer = renderer,
+            Err(e) => {
+                outputln!(preamble self.service_group,
+                          "Reloading configuration templates failed: {}",
+                          e)
+            }
+        }
+        let hooks_root = Self::hooks_root(&self.pkg, self.spec.config_from.as_ref());
+        let tasks = self.scheduled_tasks
+                        .iter()
+                        .map(|t| t.task.clone())
+                        .collect::<Vec<_>>();
+        self.hooks = HookTable::load(&self.pkg.name,
+                                     &hooks_root,
+                                     svc_hooks_path(&self.service_group.service()),
+                                     &tasks,
+                                     self.feature_flags);
+    }
+
     pub fn to_rumor(&self, incarnation: u64) -> ServiceRumor {
         let exported = match self.cfg.to_exported(&self.pkg) {
             Ok(exported) => Some(exported),
@@ -818,6 +1083,7 @@ impl Service {
                                           self.sys.as_sys_info(),
                                           exported);
         rumor.incarnation = incarnation;
+        rumor.healthy = self.gossiped_healthy;
         rumor
     }
 
@@ -826,12 +1092,16 @@ impl Service {
         outputln!(preamble self.service_group, "Initializing");
         *self.initialization_state.write() = InitializationState::Initializing;
         if let Some(ref hook) = self.hooks.init {
-            let hook_runner = HookRunner::new(Arc::clone(&hook),
-                                              self.service_group.clone(),
-                                              self.pkg.clone(),
-                                              self.spec.svc_encrypted_password.clone());
+            let timeout = seconds(self.hook_timeouts().init);
+            let hook_runner =
+                HookRunner::new(Arc::clone(&hook),
+                                self.service_group.clone(),
+                                self.pkg.clone(),
+                                self.spec.svc_encrypted_password.clone()).with_timeout(timeout);
             // These clones are unfortunate. async/await will make this much better.
             let service_group = self.service_group.clone();
+            let ident = PackageIdent::from(self.pkg.ident.clone());
+            let gateway_state = Arc::clone(&self.gateway_state);
             let initialization_state = Arc::clone(&self.initialization_state);
             let initialization_state_for_err = Arc::clone(&self.initialization_state);
             let f = async move {
@@ -845,6 +1115,13 @@ impl Service {
                     }
                     Err(e) => {
                         outputln!(preamble service_group, "Service initialization failed: {}", e);
+                        if let Some(timeout) = e.hook_timeout() {
+                            let lifecycle =
+                                Lifecycle::HookTimedOut { hook:         hooks::InitHook::FILE_NAME,
+                                                          timeout_secs: timeout.as_secs(), };
+                            gateway_state.lock_gsw()
+                                         .record_lifecycle(&service_group, &ident, lifecycle);
+                        }
                         *initialization_state_for_err.write() = InitializationState::Uninitialized;
                     }
                 }
@@ -875,10 +1152,15 @@ impl Service {
         }
 
         if let Some(ref hook) = self.hooks.reconfigure {
-            hook.run(&self.service_group,
-                     &self.pkg,
-                     self.spec.svc_encrypted_password.as_ref())
-                .ok();
+            let result = hook.run_with_timeout(&self.service_group,
+                                               &self.pkg,
+                                               self.spec.svc_encrypted_password.as_ref(),
+                                               seconds(self.hook_timeouts().reconfigure));
+            if let Err(habitat_common::Error::HookTimedOut(hook, timeout)) = result {
+                self.record_lifecycle_gsw(Lifecycle::HookTimedOut { hook,
+                                                                    timeout_secs:
+                                                                        timeout.as_secs() });
+            }
             // The intention here is to do a health check soon after a service's configuration
             // changes, as a way to (among other things) detect potential impacts when bound
             // services change exported configuration.
@@ -908,11 +1190,13 @@ impl Service {
     }
 
     fn post_stop(&self) -> Option<HookRunner<hooks::PostStopHook>> {
+        let timeout = seconds(self.hook_timeouts().post_stop);
         self.hooks.post_stop.as_ref().map(|hook| {
                                          HookRunner::new(Arc::clone(&hook),
                                                          self.service_group.clone(),
                                                          self.pkg.clone(),
                                                          self.spec.svc_encrypted_password.clone())
+                                         .with_timeout(timeout)
                                      })
     }
 
@@ -944,8 +1228,15 @@ impl Service {
                                            &self.pkg.svc_config_path,
                                            ctx)
         {
-            Ok(true) => true,
-            Ok(false) => false,
+            Ok(summary) => {
+                CONFIG_FILE_RENDERS.with_label_values(&["written"])
+                                   .inc_by(summary.written as i64);
+                CONFIG_FILE_RENDERS.with_label_values(&["restored"])
+                                   .inc_by(summary.restored as i64);
+                CONFIG_FILE_RENDERS.with_label_values(&["unchanged"])
+                                   .inc_by(summary.unchanged as i64);
+                summary.changed()
+            }
             Err(e) => {
                 outputln!(preamble self.service_group,
                           "Failed to compile configuration: {}",
@@ -1036,12 +1327,30 @@ impl Service {
             InitializationState::Initialized => {
                 // If the service is initialized and the process is not running, the process
                 // unexpectedly died and needs to be restarted.
-                if !up || template_update.needs_restart() {
+                let restart_reason = if !up {
+                    Some(RestartReason::ProcessExited)
+                } else if self.startup_grace_expired() {
+                    outputln!(preamble self.service_group,
+                              "Service did not pass a health check within its startup grace \
+                               period; restarting it");
+                    Some(RestartReason::StartupGraceExpired)
+                } else {
+                    template_update.restart_reason()
+                };
+                if up && template_update.config_changed {
+                    let restart = restart_reason.is_some();
+                    let changed_keys = template_update.changed_keys.clone();
+                    event::service_config_changed(self, &changed_keys, restart);
+                    self.record_lifecycle_gsw(Lifecycle::ConfigApplied { changed_keys,
+                                                                         restart });
+                }
+                if let Some(reason) = restart_reason {
                     // TODO (DM): This flag is a hack. We have the `TaskExecutor` here. We could
                     // just schedule the `stop` future, but the `Manager` wraps
                     // the `stop` future with additional functionality. Can we
                     // refactor to make this flag unnecessary?
                     self.needs_restart = true;
+                    self.record_lifecycle_gsw(Lifecycle::Restarted { reason });
                     return true;
                 } else if template_update.needs_reconfigure() {
                     // Only reconfigure if we did NOT restart the service
@@ -1124,6 +1433,17 @@ impl Service {
         updated
     }
 
+    /// Whether the census data in the service's template data changed with the last census
+    /// update: that of its own service group, or of a group it binds to.
+    fn census_data_changed(&self, census_ring: &CensusRing) -> bool {
+        census_ring.group_changed(&self.service_group)
+        || self.spec.binds.iter().any(|bind| {
+                                     let sg = bind.service_group()
+                                                  .in_org(self.service_group.org());
+                                     census_ring.group_changed(&sg)
+                                 })
+    }
+
     /// Helper for constructing a new render context for the service.
     fn render_context<'a>(&'a self, census: &'a CensusRing) -> RenderContext<'a> {
         // Unsatisfied binds are filtered out; you only get bind
@@ -1260,9 +1580,9 @@ impl<'a> Serialize for ServiceProxy<'a> {
         where S: Serializer
     {
         let num_fields: usize = if self.config_rendering == ConfigRendering::Full {
-            27
+            33
         } else {
-            26
+            32
         };
 
         let s = &self.service;
@@ -1278,21 +1598,27 @@ impl<'a> Serialize for ServiceProxy<'a> {
 
         strukt.serialize_field("channel", &s.spec.channel)?;
         strukt.serialize_field("config_from", &s.spec.config_from)?;
+        strukt.serialize_field("cpuset", &s.spec.cpuset)?;
         strukt.serialize_field("desired_state", &s.spec.desired_state)?;
         strukt.serialize_field("health_check", &s.health_check_result)?;
         strukt.serialize_field("hooks", &s.hooks)?;
         strukt.serialize_field("initialized", &s.initialized())?;
         strukt.serialize_field("last_election_status", &s.last_election_status)?;
+        strukt.serialize_field("limits", &s.spec.limits)?;
         strukt.serialize_field("manager_fs_cfg", &s.manager_fs_cfg)?;
+        strukt.serialize_field("nice", &s.spec.nice)?;
 
         let pkg_proxy = PkgProxy::new(&s.pkg);
         strukt.serialize_field("pkg", &pkg_proxy)?;
+        strukt.serialize_field("priority_class", &s.spec.priority_class)?;
 
         strukt.serialize_field("process",
                                s.supervisor
                                 .lock()
                                 .expect("Couldn't lock supervisor")
                                 .deref())?;
+        strukt.serialize_field("restart_pending", &s.pending_spec.is_some())?;
+        strukt.serialize_field("scheduled_tasks", &s.scheduled_tasks)?;
         strukt.serialize_field("service_group", &s.service_group)?;
         strukt.serialize_field("spec_file", &s.spec_file)?;
         // Deprecated field; use spec_identifier instead
@@ -1309,6 +1635,28 @@ impl<'a> Serialize for ServiceProxy<'a> {
     }
 }
 
+/// Whether a change to `changed_keys` must restart a service whose package lists `restart_keys`,
+/// even though it has a `reconfigure` hook.
+pub(crate) fn restart_keys_changed(changed_keys: &[String], restart_keys: &[String]) -> bool {
+    changed_keys.iter().any(|changed| {
+                           restart_keys.iter()
+                                       .any(|key| config::keys_overlap(changed, key))
+                       })
+}
+
+/// Whether the package installed at `package_path` has a `reconfigure` hook, or the deprecated
+/// `reload` hook.
+pub(crate) fn has_reconfigure_hook(package_path: &Path) -> bool {
+    let hooks_path = package_path.join("hooks");
+    [hooks::ReconfigureHook::FILE_NAME,
+     hooks::ReloadHook::FILE_NAME].iter()
+                                  .any(|name| hooks_path.join(name).exists())
+}
+
+fn seconds(timeout: Option<u32>) -> Option<Duration> {
+    timeout.map(|seconds| Duration::from_secs(seconds.into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1385,4 +1733,31 @@ mod tests {
                                                                    JSON but failed");
         assert_valid(&json_without_config, "http_gateway_services_schema.json");
     }
+
+    #[test]
+    fn only_restart_keys_restart_a_service_with_a_reconfigure_hook() {
+        let restart_keys = vec!["tls".to_string()];
+        let update = TemplateUpdate::new(HookCompileTable::default(),
+                                         true,
+                                         true,
+                                         vec!["log.level".to_string()],
+                                         &restart_keys);
+        assert_eq!(update.restart_reason(), None);
+        assert!(update.needs_reconfigure());
+
+        let update = TemplateUpdate::new(HookCompileTable::default(),
+                                         true,
+                                         true,
+                                         vec!["log.level".to_string(), "tls.cert".to_string()],
+                                         &restart_keys);
+        assert_eq!(update.restart_reason(),
+                   Some(RestartReason::RestartKeysChanged));
+
+        let update = TemplateUpdate::new(HookCompileTable::default(),
+                                         true,
+                                         false,
+                                         vec!["log.level".to_string()],
+                                         &restart_keys);
+        assert_eq!(update.restart_reason(), Some(RestartReason::ConfigChanged));
+    }
 }
@@ -103,7 +103,7 @@ impl<'a> RenderContext<'a> {
                         pkg:  Package::from_pkg(pkg),
                         cfg:  Cow::Borrowed(cfg),
                         svc:  Svc::new(census_group),
-                        bind: Binds::new(bindings, census), }
+                        bind: Binds::new(bindings, service_group.org(), census), }
     }
 
     // Exposed only for logging... can probably do this another way.
@@ -329,12 +329,15 @@ impl<'a> Serialize for Svc<'a> {
 struct Binds<'a>(BTreeMap<String, BindGroup<'a>>);
 
 impl<'a> Binds<'a> {
-    fn new<T>(bindings: T, census: &'a CensusRing) -> Self
+    /// Binds which name no organization are to service groups in `organization`, the service's
+    /// own.
+    fn new<T>(bindings: T, organization: Option<&str>, census: &'a CensusRing) -> Self
         where T: Iterator<Item = &'a ServiceBind>
     {
         let mut map = BTreeMap::default();
         for bind in bindings {
-            if let Some(group) = census.census_group_for(&bind.service_group()) {
+            if let Some(group) = census.census_group_for(&bind.service_group().in_org(organization))
+            {
                 map.insert(bind.name().to_string(), BindGroup::new(group));
             }
         }
@@ -353,7 +356,7 @@ impl<'a> BindGroup<'a> {
     fn new(group: &'a CensusGroup) -> Self {
         BindGroup { first:   select_first(group),
                     leader:  group.leader().map(|m| SvcMember::new(m)),
-                    members: group.active_members().map(|m| SvcMember::new(m)).collect(), }
+                    members: group.serving_members().map(|m| SvcMember::new(m)).collect(), }
     }
 }
 
@@ -477,6 +480,8 @@ two = 2
                                            suspect: false,
                                            confirmed: false,
                                            departed: false,
+                                           draining: false,
+                                           healthy: true,
                                            cfg: toml::value::Table::new(), };
         SvcMember::new_owned(census_member)
     }
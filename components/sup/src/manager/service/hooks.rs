@@ -12,6 +12,8 @@ use habitat_common::{error::Result,
                      FeatureFlag};
 #[cfg(windows)]
 use habitat_core::os::process::windows_child::ExitStatus;
+use habitat_core::{fs::svc_logs_path,
+                   package::schedule::ScheduledTask};
 use serde::Serialize;
 #[cfg(not(windows))]
 use std::process::ExitStatus;
@@ -152,7 +154,7 @@ impl Hook for HealthCheckHook {
                 }
             }
         } else {
-            self.run_impl(service_group, pkg, svc_encrypted_password)
+            self.run_impl(service_group, pkg, svc_encrypted_password, None)
         }
     }
 
@@ -513,6 +515,100 @@ impl Hook for PostStopHook {
     fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
 }
 
+/// A hook which a package asks the Supervisor to run on a schedule. Unlike the lifecycle hooks,
+/// it is named by the package, so it is loaded and compiled by that name rather than by
+/// `FILE_NAME`.
+#[derive(Debug, Serialize)]
+pub struct TaskHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl TaskHook {
+    /// Loads the hook `name` of the package, if it has one.
+    fn load_named<C, T>(package_name: &str,
+                        name: &str,
+                        concrete_path: C,
+                        template_path: T)
+                        -> Option<Self>
+        where C: AsRef<Path>,
+              T: AsRef<Path>
+    {
+        let template = template_path.as_ref().join(name);
+        if !template.exists() {
+            outputln!(preamble package_name,
+                      "The scheduled task hook {} was not found at {}",
+                      name,
+                      template.display());
+            return None;
+        }
+        match RenderPair::new(concrete_path.as_ref().join(name), &template, Self::FILE_NAME) {
+            Ok(pair) => {
+                let logs_path = svc_logs_path(package_name);
+                Some(TaskHook { render_pair:     pair,
+                                stdout_log_path: logs_path.join(format!("{}.stdout.log", name)),
+                                stderr_log_path: logs_path.join(format!("{}.stderr.log", name)), })
+            }
+            Err(err) => {
+                outputln!(preamble package_name, "Failed to load hook: {}", err);
+                None
+            }
+        }
+    }
+
+    /// The name of the hook, as given by the package.
+    pub fn name(&self) -> String {
+        self.render_pair
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl Hook for TaskHook {
+    type ExitValue = ExitStatus;
+
+    const FILE_NAME: &'static str = "task";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        TaskHook { render_pair:     pair,
+                   stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                   stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn compile<T>(&self, service_group: &str, ctx: &T) -> Result<bool>
+        where T: Serialize
+    {
+        let content = self.renderer().render(Self::FILE_NAME, ctx)?;
+        if hooks::write_hook(&content, self.path())? {
+            outputln!(preamble service_group,
+                      "Modified hook content in {}",
+                      self.path().display());
+            Self::set_permissions(self.path())?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        if status.code().is_none() {
+            Self::output_termination_message(&pkg.name, status);
+        }
+        status
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
 /// A lookup of hooks that have changed after compilation.
 #[derive(Default)]
 pub struct HookCompileTable {
@@ -573,13 +669,17 @@ pub struct HookTable {
     pub run:          Option<RunHook>,
     pub post_run:     Option<Arc<PostRunHook>>,
     pub post_stop:    Option<Arc<PostStopHook>>,
+    /// The hooks of the package's scheduled tasks
+    pub tasks:        Vec<Arc<TaskHook>>,
 }
 
 impl HookTable {
-    /// Read all available hook templates from the table's package directory into the table.
+    /// Read all available hook templates from the table's package directory into the table,
+    /// along with those of the package's scheduled `tasks`.
     pub fn load<P, T>(package_name: &str,
                       templates: T,
                       hooks_path: P,
+                      tasks: &[ScheduledTask],
                       feature_flags: FeatureFlag)
                       -> Self
         where P: AsRef<Path>,
@@ -610,6 +710,15 @@ impl HookTable {
                                                      &hooks_path,
                                                      &templates,
                                                      feature_flags).map(Arc::new);
+                table.tasks = tasks.iter()
+                                   .filter_map(|task| {
+                                       TaskHook::load_named(package_name,
+                                                            &task.hook,
+                                                            &hooks_path,
+                                                            &templates)
+                                   })
+                                   .map(Arc::new)
+                                   .collect();
             }
         }
         debug!("{}, Hooks loaded, destination={}, templates={}",
@@ -652,6 +761,10 @@ impl HookTable {
         if let Some(ref hook) = self.post_stop {
             changed.post_stop = self.compile_one(hook.as_ref(), service_group, ctx);
         }
+        // Scheduled tasks are run afresh each time, so a change to them needs no action.
+        for hook in &self.tasks {
+            self.compile_one(hook.as_ref(), service_group, ctx);
+        }
         changed
     }
 
@@ -792,6 +905,7 @@ mod tests {
         let service_file_store: RumorStore<ServiceFileRumor> = RumorStore::default();
 
         ring.update_from_rumors_rsr_mlr(&*CACHE_KEY_PATH,
+                                        &[],
                                         &service_store,
                                         &election_store,
                                         &election_update_store,
@@ -833,6 +947,7 @@ mod tests {
         let hook_table = HookTable::load(&service_group,
                                          &template_path,
                                          &hooks_path,
+                                         &[],
                                          FeatureFlag::empty());
         assert!(hook_table.compile(&service_group, &ctx).changed());
 
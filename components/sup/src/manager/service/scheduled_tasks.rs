@@ -0,0 +1,207 @@
+//! Runs the hooks a package asks the Supervisor to run on a schedule, for as long as its service
+//! is running.
+//!
+//! Each task is run one run at a time: a run which is still going when the task is next due
+//! makes the Supervisor skip that run, rather than start another one alongside it.
+
+use super::{hook_runner::HookRunner,
+            hooks::TaskHook,
+            Pkg};
+use crate::{error::Error,
+            manager::event};
+use habitat_common::outputln;
+use habitat_core::{clock,
+                   package::schedule::{Schedule,
+                                       ScheduledTask},
+                   service::ServiceGroup};
+use rand::Rng;
+use serde::{Serialize,
+            Serializer};
+use std::{result,
+          sync::{Arc,
+                 Mutex},
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
+use tokio::time;
+
+static LOGKEY: &str = "ST";
+
+/// How the runs of a scheduled task have gone, as shown in the HTTP gateway.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TaskStatus {
+    pub hook:             String,
+    pub schedule:         String,
+    /// When the task is next due to run, in seconds since the epoch
+    pub next_run:         Option<u64>,
+    pub running:          bool,
+    /// When the latest run started, in seconds since the epoch
+    pub last_run:         Option<u64>,
+    pub last_duration_ms: Option<u64>,
+    /// The exit code of the latest run, unless it couldn't be run or was killed by a signal
+    pub last_exit_code:   Option<i32>,
+    pub last_succeeded:   Option<bool>,
+    /// How many runs were skipped because the one before was still going
+    pub skipped_runs:     u64,
+}
+
+/// A scheduled task of a service, along with how its runs have gone.
+#[derive(Debug)]
+pub struct Task {
+    pub task:   ScheduledTask,
+    pub status: Arc<Mutex<TaskStatus>>,
+}
+
+impl Task {
+    pub fn new(task: ScheduledTask) -> Self {
+        let status = TaskStatus { hook: task.hook.clone(),
+                                  schedule: task.schedule.to_string(),
+                                  ..TaskStatus::default() };
+        Task { task,
+               status: Arc::new(Mutex::new(status)) }
+    }
+}
+
+impl Serialize for Task {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        self.status
+            .lock()
+            .expect("Couldn't lock scheduled task status")
+            .serialize(serializer)
+    }
+}
+
+/// Runs `hook` each time `task` is due, recording each run in `status` and sending an event for
+/// it. This never finishes; it is stopped by aborting it.
+pub async fn run_on_schedule(task: ScheduledTask,
+                             hook: Arc<TaskHook>,
+                             service_group: ServiceGroup,
+                             pkg: Pkg,
+                             password: Option<String>,
+                             status: Arc<Mutex<TaskStatus>>,
+                             metadata: event::ServiceMetadata) {
+    loop {
+        let now = clock::now();
+        let due = match task.schedule.next_after(now) {
+            Some(due) => due,
+            None => {
+                outputln!(preamble service_group,
+                          "The schedule of {} never comes due, so it will not be run",
+                          task.hook);
+                return;
+            }
+        };
+        let start_at = due + jitter(task.jitter);
+        status.lock()
+              .expect("Couldn't lock scheduled task status")
+              .next_run = Some(seconds_since_epoch(start_at));
+        time::delay_for(start_at.duration_since(now).unwrap_or_default()).await;
+
+        let started = clock::now();
+        status.lock()
+              .expect("Couldn't lock scheduled task status")
+              .running = true;
+        let result = HookRunner::new(Arc::clone(&hook),
+                                     service_group.clone(),
+                                     pkg.clone(),
+                                     password.clone()).into_future()
+                                                      .await;
+        let (exit_code, succeeded, duration) = match result {
+            Ok((exit_status, duration)) => (exit_status.code(), exit_status.success(), duration),
+            Err(Error::WithDuration(e, duration)) => {
+                outputln!(preamble service_group,
+                          "Scheduled task {} failed to run: {}",
+                          task.hook,
+                          e);
+                (None, false, duration)
+            }
+            Err(e) => {
+                outputln!(preamble service_group,
+                          "Scheduled task {} failed to start: {}",
+                          task.hook,
+                          e);
+                (None, false, Duration::default())
+            }
+        };
+        let skipped = missed_runs(&task.schedule, due, clock::now());
+        if skipped > 0 {
+            outputln!(preamble service_group,
+                      "Scheduled task {} was still running when it was next due; skipped {} \
+                       run(s)",
+                      task.hook,
+                      skipped);
+        }
+        {
+            let mut status = status.lock()
+                                   .expect("Couldn't lock scheduled task status");
+            status.running = false;
+            status.last_run = Some(seconds_since_epoch(started));
+            status.last_duration_ms = Some(duration.as_millis() as u64);
+            status.last_exit_code = exit_code;
+            status.last_succeeded = Some(succeeded);
+            status.skipped_runs += skipped;
+        }
+        event::scheduled_task_ran(metadata.clone(),
+                                  &task,
+                                  succeeded,
+                                  exit_code,
+                                  duration,
+                                  skipped);
+    }
+}
+
+/// How many times `schedule` came due after `from`, up to `to`.
+fn missed_runs(schedule: &Schedule, from: SystemTime, to: SystemTime) -> u64 {
+    let mut missed = 0;
+    let mut after = from;
+    while let Some(next) = schedule.next_after(after) {
+        if next > to {
+            break;
+        }
+        missed += 1;
+        after = next;
+    }
+    missed
+}
+
+/// A random delay of up to `max`.
+fn jitter(max: Duration) -> Duration {
+    if max.as_secs() == 0 {
+        Duration::default()
+    } else {
+        Duration::from_secs(rand::thread_rng().gen_range(0, max.as_secs() + 1))
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_due_while_a_run_was_going_are_missed() {
+        let schedule: Schedule = "* * * * *".parse().unwrap();
+        // Time zones are offset from UTC by whole minutes, so this is on the minute locally, too.
+        let from = UNIX_EPOCH + Duration::from_secs(60 * 26_666_666);
+        assert_eq!(missed_runs(&schedule, from, from + Duration::from_secs(30)), 0);
+        assert_eq!(missed_runs(&schedule, from, from + Duration::from_secs(210)), 3);
+
+        let hourly: Schedule = "@hourly".parse().unwrap();
+        assert_eq!(missed_runs(&hourly, from, from + Duration::from_secs(210)), 0);
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        assert_eq!(jitter(Duration::default()), Duration::default());
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_secs(5)) <= Duration::from_secs(5));
+        }
+    }
+}
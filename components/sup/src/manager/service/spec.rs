@@ -4,12 +4,23 @@ use super::{BindingMode,
             UpdateStrategy};
 use crate::error::{Error,
                    Result};
-use habitat_core::{fs::atomic_write,
-                   os::process::ShutdownTimeout,
+use habitat_core::{clock,
+                   fs::atomic_write,
+                   os::process::{CpuLimit,
+                                 CpuSet,
+                                 HookTimeouts,
+                                 MemoryLimit,
+                                 NiceLevel,
+                                 PriorityClass,
+                                 ResourceLimits,
+                                 SandboxLimits,
+                                 Scheduling,
+                                 ShutdownTimeout},
                    package::{PackageIdent,
                              PackageInstall},
                    service::{HealthCheckInterval,
-                             ServiceBind},
+                             ServiceBind,
+                             UpdateWindow},
                    url::DEFAULT_BLDR_URL,
                    util,
                    ChannelIdent};
@@ -32,6 +43,11 @@ use std::{collections::HashSet,
 
 static DEFAULT_GROUP: &str = "default";
 const SPEC_FILE_EXT: &str = "spec";
+/// The percentage of a service group which updates first with the canary update strategy, unless
+/// the service says otherwise
+pub const DEFAULT_CANARY_PERCENT: u32 = 10;
+/// How many seconds the canaries must stay healthy for, unless the service says otherwise
+pub const DEFAULT_CANARY_SOAK: u32 = 600;
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum DesiredState {
@@ -85,13 +101,42 @@ pub struct ServiceSpec {
     pub topology:               Topology,
     pub update_strategy:        UpdateStrategy,
     pub update_condition:       UpdateCondition,
+    /// The percentage of the service group which updates first with the canary update strategy.
+    pub canary_percent:         u32,
+    /// How many seconds the canaries must stay healthy before the rest of the group updates.
+    pub canary_soak:            u32,
+    /// When updates may be applied to the service, if not at any time.
+    pub update_window:          Option<UpdateWindow>,
     pub binds:                  Vec<ServiceBind>,
     pub binding_mode:           BindingMode,
     pub config_from:            Option<PathBuf>,
+    /// Whether changes in the `config_from` directory re-render the service's templates.
+    pub watch_config_from:      bool,
     #[serde(with = "util::serde::string")]
     pub desired_state:          DesiredState,
     pub shutdown_timeout:       Option<ShutdownTimeout>,
+    /// Services with a higher shutdown order are stopped first when the Supervisor shuts down.
+    pub shutdown_order:         i32,
     pub svc_encrypted_password: Option<String>,
+    pub cpuset:                 Option<CpuSet>,
+    pub nice:                   Option<NiceLevel>,
+    pub priority_class:         Option<PriorityClass>,
+    /// The resource limits the Launcher sets on the service's process.
+    #[serde(skip_serializing_if = "ResourceLimits::is_empty")]
+    pub limits:                 ResourceLimits,
+    /// The CPU time the service and its descendants may use together.
+    pub cpu_limit:              Option<CpuLimit>,
+    /// The memory the service and its descendants may use together.
+    pub memory_limit:           Option<MemoryLimit>,
+    /// The number of processes the service may have.
+    pub pids_limit:             Option<u64>,
+    /// Whether changes to this spec which need the service to restart are held until it is next
+    /// started, or its update window opens, rather than applied right away.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub restart_pending:        bool,
+    /// How long the service's hooks may run, over those set by its package. This is serialized
+    /// as a table, so it must follow every field which isn't.
+    pub hook_timeouts:          HookTimeouts,
     // it is important that the health check interval
     // is the last field to be serialized because it
     // is serialized as a table. Individual values
@@ -112,13 +157,27 @@ impl ServiceSpec {
                topology: Topology::default(),
                update_strategy: UpdateStrategy::default(),
                update_condition: UpdateCondition::default(),
+               canary_percent: DEFAULT_CANARY_PERCENT,
+               canary_soak: DEFAULT_CANARY_SOAK,
+               update_window: None,
                binds: Vec::default(),
                binding_mode: BindingMode::Strict,
                config_from: None,
+               watch_config_from: false,
                desired_state: DesiredState::default(),
                health_check_interval: HealthCheckInterval::default(),
                svc_encrypted_password: None,
-               shutdown_timeout: None }
+               shutdown_timeout: None,
+               shutdown_order: 0,
+               cpuset: None,
+               nice: None,
+               priority_class: None,
+               hook_timeouts: HookTimeouts::default(),
+               limits: ResourceLimits::default(),
+               cpu_limit: None,
+               memory_limit: None,
+               pids_limit: None,
+               restart_pending: false }
     }
 
     // This should only be used to provide a default value when deserializing. We intentially do not
@@ -168,6 +227,42 @@ impl ServiceSpec {
 
     pub fn file(&self) -> PathBuf { Self::ident_file(&self.ident) }
 
+    /// How the Launcher should schedule the service's process.
+    pub fn scheduling(&self) -> Scheduling {
+        Scheduling { cpuset:         self.cpuset.clone(),
+                     nice:           self.nice,
+                     priority_class: self.priority_class,
+                     limits:         self.limits,
+                     sandbox:        SandboxLimits { cpu:    self.cpu_limit,
+                                                     memory: self.memory_limit,
+                                                     pids:   self.pids_limit, }, }
+    }
+
+    /// Whether changes held for a restart may be applied now, because the service's update
+    /// window is open.
+    pub fn held_restart_due(&self) -> bool {
+        self.update_window
+            .as_ref()
+            .map_or(false, |window| window.contains(clock::now()))
+    }
+
+    fn merge_scheduling(&mut self,
+                        cpuset: Option<String>,
+                        nice: Option<i32>,
+                        priority_class: Option<String>)
+                        -> Result<()> {
+        if let Some(cpuset) = cpuset {
+            self.cpuset = Some(cpuset.parse()?);
+        }
+        if let Some(nice) = nice {
+            self.nice = Some(NiceLevel::try_from(nice)?);
+        }
+        if let Some(priority_class) = priority_class {
+            self.priority_class = Some(priority_class.parse()?);
+        }
+        Ok(())
+    }
+
     /// Validates that all required package binds are present in service binds and all remaining
     /// service binds are optional package binds.
     ///
@@ -212,6 +307,25 @@ impl ServiceSpec {
         Ok(())
     }
 
+    /// Describes each port in `exposes`, those exposed by this spec's package, which the package
+    /// of another of the `loaded` services, given with the ports it exposes, exposes too.
+    pub fn port_conflicts(&self,
+                          exposes: &[String],
+                          loaded: &[(ServiceSpec, Vec<String>)])
+                          -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (other, other_exposes) in loaded {
+            if other.ident.name == self.ident.name {
+                continue;
+            }
+            for port in exposes.iter().filter(|port| other_exposes.contains(port)) {
+                conflicts.push(format!("port {} is already exposed by {} in {}.{}",
+                                       port, other.ident, other.ident.name, other.group));
+            }
+        }
+        conflicts
+    }
+
     pub fn merge_svc_load(mut self, svc_load: habitat_sup_protocol::ctl::SvcLoad) -> Result<Self> {
         self.ident =
             svc_load.ident
@@ -252,6 +366,15 @@ impl ServiceSpec {
                       update_condition);
             }
         }
+        if let Some(canary_percent) = svc_load.canary_percent {
+            self.canary_percent = canary_percent.max(1).min(100);
+        }
+        if let Some(canary_soak) = svc_load.canary_soak {
+            self.canary_soak = canary_soak;
+        }
+        if let Some(update_window) = svc_load.update_window {
+            self.update_window = Some(update_window.parse()?);
+        }
         if let Some(list) = svc_load.binds {
             self.binds = list.into();
         }
@@ -267,6 +390,9 @@ impl ServiceSpec {
         if let Some(config_from) = svc_load.config_from {
             self.config_from = Some(PathBuf::from(config_from));
         }
+        if let Some(watch_config_from) = svc_load.watch_config_from {
+            self.watch_config_from = watch_config_from;
+        }
         if let Some(svc_encrypted_password) = svc_load.svc_encrypted_password {
             self.svc_encrypted_password = Some(svc_encrypted_password);
         }
@@ -276,10 +402,31 @@ impl ServiceSpec {
         if let Some(shutdown_timeout) = svc_load.shutdown_timeout {
             self.shutdown_timeout = Some(ShutdownTimeout::from(shutdown_timeout));
         }
+        if let Some(shutdown_order) = svc_load.shutdown_order {
+            self.shutdown_order = shutdown_order;
+        }
+        if let Some(hook_timeouts) = svc_load.hook_timeouts {
+            self.hook_timeouts = hook_timeouts.parse()?;
+        }
+        if let Some(limits) = svc_load.limits {
+            self.limits = limits.parse()?;
+        }
+        if let Some(cpu_limit) = svc_load.cpu_limit {
+            self.cpu_limit = Some(cpu_limit.parse()?);
+        }
+        if let Some(memory_limit) = svc_load.memory_limit {
+            self.memory_limit = Some(MemoryLimit::from(memory_limit));
+        }
+        if let Some(pids_limit) = svc_load.pids_limit {
+            self.pids_limit = Some(pids_limit);
+        }
+        self.merge_scheduling(svc_load.cpuset, svc_load.nice, svc_load.priority_class)?;
         Ok(self)
     }
 
-    pub fn merge_svc_update(&mut self, svc_update: habitat_sup_protocol::ctl::SvcUpdate) {
+    pub fn merge_svc_update(&mut self,
+                            svc_update: habitat_sup_protocol::ctl::SvcUpdate)
+                            -> Result<()> {
         if let Some(group) = svc_update.group {
             self.group = group;
         }
@@ -316,9 +463,37 @@ impl ServiceSpec {
                       update_condition);
             }
         }
+        if let Some(canary_percent) = svc_update.canary_percent {
+            self.canary_percent = canary_percent.max(1).min(100);
+        }
+        if let Some(canary_soak) = svc_update.canary_soak {
+            self.canary_soak = canary_soak;
+        }
+        if let Some(update_window) = svc_update.update_window {
+            // An empty window lets the service be updated at any time again.
+            self.update_window = if update_window.is_empty() {
+                None
+            } else {
+                Some(update_window.parse()?)
+            };
+        }
         if let Some(list) = svc_update.binds {
             self.binds = list.into();
         }
+        for name in svc_update.remove_binds {
+            if !self.remove_bind(&name) {
+                warn!("Service {} has no bind named '{}' to remove; ignoring",
+                      self.ident,
+                      name);
+            }
+        }
+        if let Some(list) = svc_update.add_binds {
+            let binds: Vec<ServiceBind> = list.into();
+            for bind in binds {
+                self.remove_bind(bind.name());
+                self.binds.push(bind);
+            }
+        }
         if let Some(binding_mode) = svc_update.binding_mode {
             if let Some(binding_mode) = BindingMode::from_i32(binding_mode) {
                 self.binding_mode = binding_mode;
@@ -337,6 +512,37 @@ impl ServiceSpec {
         if let Some(shutdown_timeout) = svc_update.shutdown_timeout {
             self.shutdown_timeout = Some(ShutdownTimeout::from(shutdown_timeout));
         }
+        if let Some(shutdown_order) = svc_update.shutdown_order {
+            self.shutdown_order = shutdown_order;
+        }
+        if let Some(hook_timeouts) = svc_update.hook_timeouts {
+            self.hook_timeouts = hook_timeouts.parse::<HookTimeouts>()?
+                                              .or(self.hook_timeouts);
+        }
+        if let Some(limits) = svc_update.limits {
+            self.limits = limits.parse::<ResourceLimits>()?.or(self.limits);
+        }
+        if let Some(cpu_limit) = svc_update.cpu_limit {
+            self.cpu_limit = Some(cpu_limit.parse()?);
+        }
+        if let Some(memory_limit) = svc_update.memory_limit {
+            self.memory_limit = Some(MemoryLimit::from(memory_limit));
+        }
+        if let Some(pids_limit) = svc_update.pids_limit {
+            self.pids_limit = Some(pids_limit);
+        }
+        // An update which may restart the service also applies any changes held for a restart.
+        self.restart_pending = svc_update.no_restart.unwrap_or(false);
+        self.merge_scheduling(svc_update.cpuset,
+                              svc_update.nice,
+                              svc_update.priority_class)
+    }
+
+    /// Removes the bind with the given name, returning whether there was one.
+    fn remove_bind(&mut self, name: &str) -> bool {
+        let count = self.binds.len();
+        self.binds.retain(|bind| bind.name() != name);
+        self.binds.len() != count
     }
 
     /// Given an `old` and a `new` spec, figure out what operations
@@ -419,15 +625,29 @@ impl ServiceSpec {
                         topology,
                         update_strategy,
                         update_condition,
+                        canary_percent,
+                        canary_soak,
+                        update_window,
                         binds,
                         binding_mode,
                         config_from,
+                        watch_config_from,
                         // This has to be `Up` if we're in this
                         // code. As a result, we don't care about
                         // matching or destructuring it.
                         desired_state: _,
                         shutdown_timeout,
+                        shutdown_order,
                         svc_encrypted_password,
+                        cpuset,
+                        nice,
+                        priority_class,
+                        hook_timeouts,
+                        limits,
+                        cpu_limit,
+                        memory_limit,
+                        pids_limit,
+                        restart_pending,
                         health_check_interval,
                     } = &running_spec;
 
@@ -456,27 +676,58 @@ impl ServiceSpec {
                         // TODO (CM): This probably doesn't need to be here
                         || shutdown_timeout != &disk_spec.shutdown_timeout
                         || svc_encrypted_password != &disk_spec.svc_encrypted_password
+                        // Scheduling is only applied when the process is spawned
+                        || cpuset != &disk_spec.cpuset
+                        || nice != &disk_spec.nice
+                        || priority_class != &disk_spec.priority_class
+                        || limits != &disk_spec.limits
+                        || cpu_limit != &disk_spec.cpu_limit
+                        || memory_limit != &disk_spec.memory_limit
+                        || pids_limit != &disk_spec.pids_limit
                         // TODO (CM): This probably doesn't need to be here, either
                         || health_check_interval != &disk_spec.health_check_interval
                     {
-                        debug!("Reconciliation: '{}' queued for restart",
-                               running_spec.ident);
-                        Some(ServiceOperation::Restart { to_stop:  running_spec,
-                                                         to_start: disk_spec, })
+                        if disk_spec.restart_pending && !disk_spec.held_restart_due() {
+                            debug!("Reconciliation: '{}' holding changes for a restart",
+                                   running_spec.ident);
+                            Some(ServiceOperation::HoldRestart(disk_spec))
+                        } else {
+                            debug!("Reconciliation: '{}' queued for restart",
+                                   running_spec.ident);
+                            Some(ServiceOperation::Restart { to_stop:  running_spec,
+                                                             to_start: disk_spec, })
+                        }
                     } else {
                         let mut ops = HashSet::new();
                         if bldr_url != &disk_spec.bldr_url
                             || channel != &disk_spec.channel
                             || update_strategy != &disk_spec.update_strategy
                             || update_condition != &disk_spec.update_condition
+                            || canary_percent != &disk_spec.canary_percent
+                            || canary_soak != &disk_spec.canary_soak
+                            || update_window != &disk_spec.update_window
                         {
                             ops.insert(RefreshOperation::RestartUpdater);
                         }
 
-                        // We should have *something* to do down
-                        // here, but if we don't, let's be explicit
-                        // about it.
-                        if ops.is_empty() {
+                        if watch_config_from != &disk_spec.watch_config_from {
+                            ops.insert(RefreshOperation::RestartConfigFromWatcher);
+                        }
+
+                        // The shutdown order is only consulted when
+                        // the Supervisor shuts down, and hook
+                        // timeouts each time a hook is run, so
+                        // taking the new spec is all that's needed
+                        // for them.
+                        //
+                        // Otherwise we should have *something* to do
+                        // down here, but if we don't, let's be
+                        // explicit about it.
+                        if ops.is_empty()
+                           && shutdown_order == &disk_spec.shutdown_order
+                           && hook_timeouts == &disk_spec.hook_timeouts
+                           && restart_pending == &disk_spec.restart_pending
+                        {
                             warn!("No refresh operations computed for {}!", ident);
                             None
                         } else {
@@ -498,6 +749,8 @@ pub(crate) enum RefreshOperation {
     /// This can happen if a user wants to change the channel a
     /// service is updating from, for instance.
     RestartUpdater,
+    /// Start or stop watching the service's `config_from` directory.
+    RestartConfigFromWatcher,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -520,6 +773,11 @@ pub(crate) enum ServiceOperation {
         to_stop:  ServiceSpec,
         to_start: ServiceSpec,
     },
+    /// The specified spec has changes which need the running service
+    /// to restart, but they were saved with `hab svc update
+    /// --no-restart`. The service keeps running as it is until it is
+    /// next started, or its update window opens.
+    HoldRestart(ServiceSpec),
 }
 
 impl FromStr for ServiceSpec {
@@ -680,6 +938,9 @@ mod test {
                           topology:               Topology::Leader,
                           update_strategy:        UpdateStrategy::AtOnce,
                           update_condition:       UpdateCondition::Latest,
+                          canary_percent:         25,
+                          canary_soak:            300,
+                          update_window:          Some("Sat 02:00-04:00 UTC".parse().unwrap()),
                           binds:                  vec![ServiceBind::from_str("cache:redis.cache@\
                                                                               acmecorp").unwrap(),
                                                        ServiceBind::from_str("db:postgres.app@\
@@ -687,9 +948,19 @@ mod test {
                           binding_mode:           BindingMode::Relaxed,
                           health_check_interval:  HealthCheckInterval::from_str("123").unwrap(),
                           config_from:            Some(PathBuf::from("/only/for/development")),
+                          watch_config_from:      false,
                           desired_state:          DesiredState::Down,
                           svc_encrypted_password: None,
-                          shutdown_timeout:       Some(ShutdownTimeout::from_str("10").unwrap()), };
+                          shutdown_timeout:       Some(ShutdownTimeout::from_str("10").unwrap()),
+                          shutdown_order:         -10,
+                          cpuset:                 Some(CpuSet::from_str("0-3,8").unwrap()),
+                          nice:                   Some(NiceLevel::from_str("-5").unwrap()),
+                          priority_class:         Some(PriorityClass::AboveNormal),
+                          hook_timeouts:          "init=600,post-stop=30".parse().unwrap(),
+                          limits:                 "nofile=65536".parse().unwrap(),
+                          cpu_limit:              Some("0.5".parse().unwrap()),
+                          memory_limit:           Some(MemoryLimit::from(536_870_912)),
+                          pids_limit:             Some(256), };
         let toml = spec.to_toml_string().unwrap();
 
         assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
@@ -698,15 +969,31 @@ mod test {
         assert!(toml.contains(r#"channel = "unstable""#));
         assert!(toml.contains(r#"topology = "leader""#));
         assert!(toml.contains(r#"update_strategy = "at-once""#));
+        assert!(toml.contains(r#"canary_percent = 25"#));
+        assert!(toml.contains(r#"canary_soak = 300"#));
+        assert!(toml.contains(r#"update_window = "Sat 02:00-04:00 UTC""#));
         assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
         assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
         assert!(toml.contains(r#"desired_state = "down""#));
         assert!(toml.contains(r#"config_from = "/only/for/development""#));
         assert!(toml.contains(r#"binding_mode = "relaxed""#));
+        assert!(toml.contains(r#"shutdown_order = -10"#));
         assert!(toml.contains(r#"[health_check_interval]"#));
         assert!(toml.contains(r#"secs = 123"#));
         assert!(toml.contains(r#"nanos = 0"#));
         assert!(toml.contains(r#"shutdown_timeout = 10"#));
+        assert!(toml.contains(r#"cpuset = "0-3,8""#));
+        assert!(toml.contains(r#"nice = -5"#));
+        assert!(toml.contains(r#"priority_class = "above-normal""#));
+        assert!(toml.contains(r#"[hook_timeouts]"#));
+        assert!(toml.contains(r#"init = 600"#));
+        assert!(toml.contains(r#"post_stop = 30"#));
+        assert!(toml.contains(r#"limits = "nofile=65536""#));
+        assert!(toml.contains(r#"cpu_limit = "0.5""#));
+        assert!(toml.contains(r#"memory_limit = 536870912"#));
+        assert!(toml.contains(r#"pids_limit = 256"#));
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap().hook_timeouts,
+                   spec.hook_timeouts);
     }
 
     #[test]
@@ -849,6 +1136,9 @@ mod test {
                           topology:               Topology::Leader,
                           update_strategy:        UpdateStrategy::AtOnce,
                           update_condition:       UpdateCondition::Latest,
+                          canary_percent:         DEFAULT_CANARY_PERCENT,
+                          canary_soak:            DEFAULT_CANARY_SOAK,
+                          update_window:          None,
                           binds:                  vec![ServiceBind::from_str("cache:redis.cache@\
                                                                               acmecorp").unwrap(),
                                                        ServiceBind::from_str("db:postgres.app@\
@@ -856,9 +1146,20 @@ mod test {
                           binding_mode:           BindingMode::Relaxed,
                           health_check_interval:  HealthCheckInterval::from_str("23").unwrap(),
                           config_from:            Some(PathBuf::from("/only/for/development")),
+                          watch_config_from:      false,
                           desired_state:          DesiredState::Down,
                           svc_encrypted_password: None,
-                          shutdown_timeout:       Some(ShutdownTimeout::default()), };
+                          shutdown_timeout:       Some(ShutdownTimeout::default()),
+                          shutdown_order:         0,
+                          cpuset:                 None,
+                          nice:                   None,
+                          priority_class:         None,
+                          hook_timeouts:          HookTimeouts::default(),
+                          limits:                 ResourceLimits::default(),
+                          cpu_limit:              None,
+                          memory_limit:           None,
+                          pids_limit:             None,
+                          restart_pending:        false, };
         spec.to_file(&path).unwrap();
         let toml = string_from_file(path);
 
@@ -989,6 +1290,40 @@ mod test {
         }
     }
 
+    #[test]
+    fn merge_svc_update_adds_and_removes_binds() {
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("core/app").unwrap());
+        spec.binds = vec![ServiceBind::from_str("database:postgres.app@acmecorp").unwrap(),
+                          ServiceBind::from_str("cache:redis.app@acmecorp").unwrap(),];
+
+        let add_binds = vec![ServiceBind::from_str("database:postgres.blue@acmecorp").unwrap(),
+                             ServiceBind::from_str("storage:minio.app@acmecorp").unwrap(),];
+        let svc_update =
+            habitat_sup_protocol::ctl::SvcUpdate { add_binds: Some(add_binds.into_iter().collect()),
+                                                   remove_binds: vec!["cache".to_string()],
+                                                   ..Default::default() };
+        spec.merge_svc_update(svc_update).unwrap();
+
+        assert_eq!(spec.binds,
+                   vec![ServiceBind::from_str("database:postgres.blue@acmecorp").unwrap(),
+                        ServiceBind::from_str("storage:minio.app@acmecorp").unwrap(),]);
+    }
+
+    #[test]
+    fn merge_svc_update_sets_and_clears_update_window() {
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("core/app").unwrap());
+        let update = |window: &str| {
+            habitat_sup_protocol::ctl::SvcUpdate { update_window: Some(window.to_string()),
+                                                   ..Default::default() }
+        };
+        spec.merge_svc_update(update("Sat 02:00-04:00 UTC")).unwrap();
+        assert_eq!(spec.update_window,
+                   Some("Sat 02:00-04:00 UTC".parse().unwrap()));
+        assert!(spec.merge_svc_update(update("whenever")).is_err());
+        spec.merge_svc_update(update("")).unwrap();
+        assert_eq!(spec.update_window, None);
+    }
+
     /// This is to support backward compatibility with the old
     /// application/environment functionality that is being removed.
     #[test]
@@ -1030,6 +1365,23 @@ mod test {
                    HealthCheckInterval::from_str("5").unwrap());
     }
 
+    #[test]
+    fn port_conflicts_are_found_among_other_loaded_services() {
+        let spec = ServiceSpec::new(PackageIdent::from_str("acme/cache").unwrap());
+        let exposes = vec![String::from("6379"), String::from("8080")];
+        let mut redis = ServiceSpec::new(PackageIdent::from_str("core/redis").unwrap());
+        redis.group = String::from("prod");
+        let nginx = ServiceSpec::new(PackageIdent::from_str("core/nginx").unwrap());
+        let older = ServiceSpec::new(PackageIdent::from_str("acme/cache/1.0.0").unwrap());
+        let loaded = vec![(redis, vec![String::from("6379")]),
+                          (nginx, vec![String::from("80"), String::from("443")]),
+                          (older, exposes.clone())];
+
+        assert_eq!(spec.port_conflicts(&exposes, &loaded),
+                   vec![String::from("port 6379 is already exposed by core/redis in redis.prod")]);
+        assert!(spec.port_conflicts(&[], &loaded).is_empty());
+    }
+
     mod reconcile {
         use super::*;
 
@@ -1153,11 +1505,46 @@ mod test {
                    restart,
                    svc_encrypted_password,
                    Some("monkeys".to_string()));
+        reconcile!(cpuset_causes_restart,
+                   restart,
+                   cpuset,
+                   Some("2-3".parse().unwrap()));
+        reconcile!(nice_causes_restart,
+                   restart,
+                   nice,
+                   Some("10".parse().unwrap()));
+        reconcile!(priority_class_causes_restart,
+                   restart,
+                   priority_class,
+                   Some(PriorityClass::BelowNormal));
+        reconcile!(limits_causes_restart,
+                   restart,
+                   limits,
+                   "nofile=1024".parse().unwrap());
+        reconcile!(cpu_limit_causes_restart,
+                   restart,
+                   cpu_limit,
+                   Some("2".parse().unwrap()));
+        reconcile!(memory_limit_causes_restart,
+                   restart,
+                   memory_limit,
+                   Some(MemoryLimit::from(1024)));
+        reconcile!(pids_limit_causes_restart, restart, pids_limit, Some(64));
         reconcile!(health_check_interval_causes_restart,
                    restart,
                    health_check_interval,
                    10000.into());
 
+        reconcile!(shutdown_order_causes_update,
+                   update,
+                   shutdown_order,
+                   10,
+                   vec![]);
+        reconcile!(hook_timeouts_causes_update,
+                   update,
+                   hook_timeouts,
+                   "init=600".parse().unwrap(),
+                   vec![]);
         reconcile!(bldr_url_causes_update,
                    update,
                    bldr_url,
@@ -1178,5 +1565,45 @@ mod test {
                    update_condition,
                    UpdateCondition::TrackChannel,
                    vec![RefreshOperation::RestartUpdater]);
+        reconcile!(canary_percent_causes_update,
+                   update,
+                   canary_percent,
+                   50,
+                   vec![RefreshOperation::RestartUpdater]);
+        reconcile!(canary_soak_causes_update,
+                   update,
+                   canary_soak,
+                   60,
+                   vec![RefreshOperation::RestartUpdater]);
+        reconcile!(update_window_causes_update,
+                   update,
+                   update_window,
+                   Some("Sun 01:00-02:00".parse().unwrap()),
+                   vec![RefreshOperation::RestartUpdater]);
+        reconcile!(watch_config_from_causes_update,
+                   update,
+                   watch_config_from,
+                   true,
+                   vec![RefreshOperation::RestartConfigFromWatcher]);
+        reconcile!(restart_pending_causes_update,
+                   update,
+                   restart_pending,
+                   true,
+                   vec![]);
+
+        #[test]
+        fn held_restart_waits_for_the_update_window() {
+            let running = spec("core/blah", DesiredState::Up);
+            let mut disk = running.clone();
+            disk.group = "prod".to_string();
+            disk.restart_pending = true;
+            assert_eq!(ServiceSpec::reconcile(Some(running.clone()), Some(disk.clone())),
+                       Some(ServiceOperation::HoldRestart(disk.clone())));
+
+            disk.update_window = Some("00:00-24:00 UTC".parse().unwrap());
+            assert_eq!(ServiceSpec::reconcile(Some(running.clone()), Some(disk.clone())),
+                       Some(ServiceOperation::Restart { to_stop:  running,
+                                                        to_start: disk, }));
+        }
     }
 }
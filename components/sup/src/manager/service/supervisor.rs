@@ -18,7 +18,8 @@ use habitat_core::{fs,
                    fs::{AtomicWriter,
                         Permissions},
                    os::process::{self,
-                                 Pid},
+                                 Pid,
+                                 Scheduling},
                    service::ServiceGroup};
 #[cfg(windows)]
 use habitat_launcher_client::Error as launcher_error;
@@ -237,7 +238,8 @@ impl Supervisor {
                  pkg: &Pkg,
                  group: &ServiceGroup,
                  launcher: &LauncherCli,
-                 svc_password: Option<&str>)
+                 svc_password: Option<&str>,
+                 scheduling: &Scheduling)
                  -> Result<()> {
         let user_info = self.user_info(&pkg, launcher)?;
         outputln!(preamble self.service_group,
@@ -268,7 +270,8 @@ impl Supervisor {
                                  &pkg.svc_run,
                                  user_info,
                                  svc_password, // Windows optional
-                                 (*pkg.env).clone())?;
+                                 (*pkg.env).clone(),
+                                 scheduling)?;
         if pid == 0 {
             warn!(target: "pidfile_tracing", "Spawned service for {} has a PID of 0!", group);
         }
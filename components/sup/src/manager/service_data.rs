@@ -0,0 +1,133 @@
+//! What happens to a service's data directory when the service is unloaded.
+//!
+//! The data directory is kept unless `hab svc unload --purge-data` is given. With
+//! `--archive-data`, a tarball of the directory is written first, optionally sealed with the
+//! service group's key so that only holders of its secret key can read it back.
+
+use crate::error::{Error,
+                   Result};
+use habitat_core::{crypto::BoxKeyPair,
+                   service::ServiceGroup};
+use habitat_sup_protocol::ctl::SvcUnload;
+use std::{fs,
+          path::{Path,
+                 PathBuf}};
+
+/// What to do with the data directory of an unloaded service.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DataDisposition {
+    /// Write a tarball of the data directory to this path.
+    pub archive: Option<PathBuf>,
+    /// Seal the tarball with the service group's key.
+    pub encrypt: bool,
+    /// Delete the data directory, after it has been archived.
+    pub purge:   bool,
+}
+
+impl From<&SvcUnload> for DataDisposition {
+    fn from(unload: &SvcUnload) -> Self {
+        DataDisposition { archive: unload.archive_data.as_ref().map(PathBuf::from),
+                          encrypt: unload.encrypt_archive.unwrap_or(false),
+                          purge:   unload.purge_data.unwrap_or(false), }
+    }
+}
+
+impl DataDisposition {
+    /// Whether the data directory is left as it is, as it always was before it could be purged or
+    /// archived.
+    pub fn retains_only(&self) -> bool { self.archive.is_none() && !self.purge }
+
+    /// Archives and purges the data directory at `data_path` of the service in `service_group`,
+    /// as requested. Nothing is purged if archiving fails.
+    pub fn apply(&self,
+                 service_group: &ServiceGroup,
+                 data_path: &Path,
+                 cache_key_path: &Path)
+                 -> Result<()> {
+        if !data_path.is_dir() {
+            debug!("{} has no data directory at {}",
+                   service_group,
+                   data_path.display());
+            return Ok(());
+        }
+        if let Some(ref archive) = self.archive {
+            let tarball = tarball(data_path)?;
+            let contents = if self.encrypt {
+                seal(service_group, &tarball, cache_key_path)?
+            } else {
+                tarball
+            };
+            fs::write(archive, contents).map_err(|e| Error::BadDataFile(archive.clone(), e))?;
+        }
+        if self.purge {
+            fs::remove_dir_all(data_path).map_err(|e| {
+                                             Error::BadDataPath(data_path.to_path_buf(), e)
+                                         })?;
+        }
+        Ok(())
+    }
+}
+
+/// A tarball of the contents of `data_path`, with paths relative to it.
+fn tarball(data_path: &Path) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", data_path)
+           .map_err(|e| Error::BadDataPath(data_path.to_path_buf(), e))?;
+    Ok(builder.into_inner()?)
+}
+
+/// Seals `data` with the latest key of `service_group`, which only services with an organization
+/// have.
+fn seal(service_group: &ServiceGroup, data: &[u8], cache_key_path: &Path) -> Result<Vec<u8>> {
+    if service_group.org().is_none() {
+        return Err(Error::UnencryptableDataArchive(service_group.clone()));
+    }
+    let key = BoxKeyPair::get_latest_pair_for(service_group.to_string(), cache_key_path)?;
+    Ok(key.encrypt(data, None)?.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn data_is_archived_before_it_is_purged() {
+        let root = TempDir::new().expect("creating temp dir");
+        let data_path = root.path().join("data");
+        fs::create_dir(&data_path).expect("creating data dir");
+        fs::write(data_path.join("dump.rdb"), "REDIS").expect("writing data file");
+        let archive = root.path().join("redis-data.tar");
+        let service_group = "redis.default".parse().expect("valid service group");
+        let disposition = DataDisposition { archive: Some(archive.clone()),
+                                            encrypt: false,
+                                            purge:   true, };
+
+        disposition.apply(&service_group, &data_path, root.path())
+                   .expect("applying disposition");
+
+        assert!(!data_path.exists());
+        let mut tarball = tar::Archive::new(fs::File::open(&archive).expect("opening archive"));
+        let paths = tarball.entries()
+                           .expect("reading archive")
+                           .map(|e| e.unwrap().path().unwrap().into_owned())
+                           .collect::<Vec<_>>();
+        assert!(paths.iter().any(|p| p.ends_with("dump.rdb")));
+    }
+
+    #[test]
+    fn encrypting_requires_an_organization() {
+        let root = TempDir::new().expect("creating temp dir");
+        fs::create_dir(root.path().join("data")).expect("creating data dir");
+        let service_group = "redis.default".parse().expect("valid service group");
+        let disposition = DataDisposition { archive: Some(root.path().join("redis-data.tar")),
+                                            encrypt: true,
+                                            purge:   true, };
+
+        assert!(disposition.apply(&service_group, &root.path().join("data"), root.path())
+                           .is_err());
+        // Nothing is purged when the archive couldn't be written.
+        assert!(root.path().join("data").exists());
+    }
+}
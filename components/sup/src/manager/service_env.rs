@@ -0,0 +1,113 @@
+//! Reports the environment a service's process was spawned with, for `hab svc env`.
+//!
+//! A service's process inherits the Launcher's environment, on top of which the Supervisor sets
+//! the variables computed from the service's package. The Launcher's environment is the one it
+//! gave the Supervisor, less the variables it uses to talk to the Supervisor.
+
+use glob::{MatchOptions,
+           Pattern};
+use habitat_launcher_protocol::{LAUNCHER_LOCK_CLEAN_ENV,
+                                LAUNCHER_PID_ENV,
+                                LAUNCHER_PIPE_ENV};
+use habitat_sup_protocol::types::{ServiceEnv,
+                                  ServiceEnvVar};
+use std::collections::BTreeMap;
+
+/// Variables whose names match these are always redacted.
+const DEFAULT_REDACT_PATTERNS: &[&str] =
+    &["*PASSWORD*", "*SECRET*", "*TOKEN*", "*CREDENTIAL*", "*_KEY"];
+
+lazy_static! {
+    static ref DEFAULT_PATTERNS: Vec<Pattern> =
+        DEFAULT_REDACT_PATTERNS.iter()
+                               .map(|p| Pattern::new(p).expect("valid redaction pattern"))
+                               .collect();
+}
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions { case_sensitive:              false,
+                                                   require_literal_separator:   false,
+                                                   require_literal_leading_dot: false, };
+
+/// The environment of a service spawned with `service_env`, given the Supervisor's own
+/// environment. The values of variables matching a default pattern or one of `redact_patterns`
+/// are withheld.
+pub fn service_env<I>(service_env: &BTreeMap<String, String>,
+                      supervisor_env: I,
+                      redact_patterns: &[Pattern])
+                      -> ServiceEnv
+    where I: IntoIterator<Item = (String, String)>
+{
+    let mut vars: BTreeMap<String, (String, bool)> =
+        supervisor_env.into_iter()
+                      .filter(|(name, _)| {
+                          ![LAUNCHER_LOCK_CLEAN_ENV, LAUNCHER_PID_ENV, LAUNCHER_PIPE_ENV].contains(&name.as_str())
+                      })
+                      .map(|(name, value)| (name, (value, true)))
+                      .collect();
+    for (name, value) in service_env {
+        vars.insert(name.clone(), (value.clone(), false));
+    }
+
+    let vars = vars.into_iter()
+                   .map(|(name, (value, inherited))| {
+                       let redacted = is_redacted(&name, redact_patterns);
+                       ServiceEnvVar { value: if redacted { None } else { Some(value) },
+                                       name,
+                                       redacted: Some(redacted),
+                                       inherited: Some(inherited) }
+                   })
+                   .collect();
+    ServiceEnv { vars }
+}
+
+fn is_redacted(name: &str, redact_patterns: &[Pattern]) -> bool {
+    DEFAULT_PATTERNS.iter()
+                    .chain(redact_patterns)
+                    .any(|p| p.matches_with(name, MATCH_OPTIONS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var<'a>(env: &'a ServiceEnv, name: &str) -> &'a ServiceEnvVar {
+        env.vars
+           .iter()
+           .find(|v| v.name == name)
+           .unwrap_or_else(|| panic!("{} should be in the environment", name))
+    }
+
+    #[test]
+    fn service_variables_override_inherited_ones() {
+        let mut pkg_env = BTreeMap::new();
+        pkg_env.insert("PATH".to_string(), "/hab/pkgs/core/redis/bin".to_string());
+        let sup_env = vec![("PATH".to_string(), "/usr/bin".to_string()),
+                           ("LANG".to_string(), "C".to_string()),
+                           (LAUNCHER_PIPE_ENV.to_string(), "pipe".to_string()),];
+
+        let env = service_env(&pkg_env, sup_env, &[]);
+
+        assert_eq!(env.vars.len(), 2);
+        let path = var(&env, "PATH");
+        assert_eq!(path.value.as_deref(), Some("/hab/pkgs/core/redis/bin"));
+        assert_eq!(path.inherited, Some(false));
+        assert_eq!(var(&env, "LANG").inherited, Some(true));
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let mut pkg_env = BTreeMap::new();
+        pkg_env.insert("DB_Password".to_string(), "hunter2".to_string());
+        pkg_env.insert("AWS_REGION".to_string(), "us-east-1".to_string());
+        pkg_env.insert("HOME".to_string(), "/hab/svc/redis".to_string());
+        let patterns = vec![Pattern::new("aws_*").unwrap()];
+
+        let env = service_env(&pkg_env, Vec::new(), &patterns);
+
+        let password = var(&env, "DB_Password");
+        assert_eq!(password.value, None);
+        assert_eq!(password.redacted, Some(true));
+        assert_eq!(var(&env, "AWS_REGION").value, None);
+        assert_eq!(var(&env, "HOME").value.as_deref(), Some("/hab/svc/redis"));
+    }
+}
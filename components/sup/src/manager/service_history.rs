@@ -0,0 +1,368 @@
+//! A bounded record of the lifecycle transitions each service has gone through.
+//!
+//! This lets operators reconstruct what happened to a service (when it was loaded, restarted,
+//! updated or became unhealthy) without trawling the Supervisor's log output.
+
+use super::service::HealthCheckResult;
+use habitat_core::{package::PackageIdent,
+                   service::ServiceGroup};
+use std::{collections::{HashMap,
+                        VecDeque},
+          fmt,
+          time::{SystemTime,
+                 UNIX_EPOCH}};
+
+habitat_core::env_config_int!(/// The maximum number of lifecycle transitions to retain for
+                              /// each service. Once this limit is reached the oldest
+                              /// transitions of that service are discarded first.
+                              #[derive(Debug, Clone, Copy)]
+                              ServiceHistoryCapacity,
+                              usize,
+                              HAB_SERVICE_HISTORY_CAPACITY,
+                              100);
+
+habitat_core::env_config_int!(/// The number of recent lifecycle transitions, across all
+                              /// services, served from the HTTP gateway's `/events`
+                              /// endpoint. Once this limit is reached the oldest are
+                              /// discarded first.
+                              #[derive(Debug, Clone, Copy)]
+                              EventReplayCapacity,
+                              usize,
+                              HAB_EVENT_REPLAY_CAPACITY,
+                              1000);
+
+/// Why the Supervisor restarted a service's process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartReason {
+    /// The process exited without being asked to.
+    ProcessExited,
+    /// The `run` or `post-run` hook changed.
+    HooksChanged,
+    /// The configuration changed and the service has no `reconfigure` hook.
+    ConfigChanged,
+    /// The process did not pass a health check within its startup grace period.
+    StartupGraceExpired,
+    /// The configuration changed in keys which the `reconfigure` hook can't apply.
+    RestartKeysChanged,
+}
+
+impl fmt::Display for RestartReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match *self {
+            RestartReason::ProcessExited => "process exited",
+            RestartReason::HooksChanged => "run hooks changed",
+            RestartReason::ConfigChanged => "configuration changed",
+            RestartReason::StartupGraceExpired => "not healthy within its startup grace period",
+            RestartReason::RestartKeysChanged => "configuration requiring a restart changed",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Lifecycle {
+    Loaded,
+    Started,
+    HealthChanged {
+        from: HealthCheckResult,
+        to:   HealthCheckResult,
+    },
+    Updated {
+        from: PackageIdent,
+        to:   PackageIdent,
+    },
+    Restarted {
+        reason: RestartReason,
+    },
+    /// The configuration changed, and the Supervisor either restarted the service or ran its
+    /// `reconfigure` hook to apply it.
+    ConfigApplied {
+        changed_keys: Vec<String>,
+        restart:      bool,
+    },
+    /// A hook ran past its timeout and was killed, which is recorded apart from the hook failing.
+    HookTimedOut {
+        hook:         &'static str,
+        timeout_secs: u64,
+    },
+    /// A spec change that needs a restart was held back with `--no-restart`. It is applied when
+    /// the service is next restarted, or once its update window opens.
+    RestartPending,
+    Stopped,
+}
+
+impl Lifecycle {
+    /// A short name for the kind of transition.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lifecycle::Loaded => "loaded",
+            Lifecycle::Started => "started",
+            Lifecycle::HealthChanged { .. } => "health_changed",
+            Lifecycle::Updated { .. } => "updated",
+            Lifecycle::Restarted { .. } => "restarted",
+            Lifecycle::ConfigApplied { .. } => "config_applied",
+            Lifecycle::HookTimedOut { .. } => "hook_timed_out",
+            Lifecycle::RestartPending => "restart_pending",
+            Lifecycle::Stopped => "stopped",
+        }
+    }
+
+    /// A human readable description of the transition, if there is more to say than its name.
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            Lifecycle::Loaded
+            | Lifecycle::Started
+            | Lifecycle::RestartPending
+            | Lifecycle::Stopped => None,
+            Lifecycle::HealthChanged { from, to } => Some(format!("from {} to {}", from, to)),
+            Lifecycle::Updated { from, to } => Some(format!("from {} to {}", from, to)),
+            Lifecycle::Restarted { reason } => Some(reason.to_string()),
+            Lifecycle::ConfigApplied { changed_keys,
+                                       restart, } => {
+                let changed = if changed_keys.is_empty() {
+                    "templated data".to_string()
+                } else {
+                    changed_keys.join(", ")
+                };
+                let action = if *restart {
+                    "restarted"
+                } else {
+                    "reconfigured"
+                };
+                Some(format!("{} after {} changed", action, changed))
+            }
+            Lifecycle::HookTimedOut { hook, timeout_secs } => {
+                Some(format!("{} hook killed after {}s", hook, timeout_secs))
+            }
+        }
+    }
+}
+
+/// A single lifecycle transition of a service, as observed by this Supervisor.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Transition {
+    pub service_group: ServiceGroup,
+    /// The package the service was running when the transition happened.
+    pub ident:         PackageIdent,
+    #[serde(flatten)]
+    pub lifecycle:     Lifecycle,
+    /// Seconds since the UNIX epoch at which the transition was observed.
+    pub timestamp:     u64,
+}
+
+impl Transition {
+    /// A transition of the service running `ident` in `service_group`, observed now.
+    pub fn new(service_group: &ServiceGroup, ident: &PackageIdent, lifecycle: Lifecycle) -> Self {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+                                         .map(|d| d.as_secs())
+                                         .unwrap_or_default();
+        Transition { service_group: service_group.clone(),
+                     ident: ident.clone(),
+                     lifecycle,
+                     timestamp }
+    }
+}
+
+/// The retained transitions of every service loaded since the Supervisor started, bounded per
+/// service. The history of a service is kept after it is stopped so that it survives restarts
+/// and updates, which stop the service before starting it again.
+#[derive(Debug)]
+pub struct ServiceHistory {
+    services: HashMap<ServiceGroup, VecDeque<Transition>>,
+    capacity: usize,
+}
+
+impl Default for ServiceHistory {
+    fn default() -> Self { Self::new(ServiceHistoryCapacity::configured_value().into()) }
+}
+
+impl ServiceHistory {
+    pub fn new(capacity: usize) -> Self {
+        ServiceHistory { services: HashMap::new(),
+                         capacity }
+    }
+
+    /// Record that the service running `ident` in `service_group` went through `lifecycle`.
+    pub fn record(&mut self,
+                  service_group: &ServiceGroup,
+                  ident: &PackageIdent,
+                  lifecycle: Lifecycle) {
+        self.push(Transition::new(service_group, ident, lifecycle));
+    }
+
+    /// Retain a transition that has already been observed.
+    pub fn push(&mut self, transition: Transition) {
+        if self.capacity == 0 {
+            return;
+        }
+        let transitions = self.services
+                              .entry(transition.service_group.clone())
+                              .or_default();
+        while transitions.len() >= self.capacity {
+            transitions.pop_front();
+        }
+        transitions.push_back(transition);
+    }
+
+    /// The retained transitions of a single service group, oldest first.
+    pub fn for_service_group<'a>(&'a self,
+                                 service_group: &ServiceGroup)
+                                 -> impl Iterator<Item = &'a Transition> {
+        self.services.get(service_group).into_iter().flatten()
+    }
+
+    /// The retained transitions of every service, ordered by the time they were observed.
+    pub fn transitions(&self) -> Vec<&Transition> {
+        let mut transitions: Vec<_> = self.services.values().flatten().collect();
+        transitions.sort_by_key(|t| t.timestamp);
+        transitions
+    }
+}
+
+/// The most recent transitions of all services, in the order they were observed, so that
+/// operators can see what happened on the Supervisor lately without an event stream. Unlike
+/// `ServiceHistory`, the bound is on the transitions of every service together.
+#[derive(Debug)]
+pub struct EventReplay {
+    transitions: VecDeque<Transition>,
+    capacity:    usize,
+}
+
+impl Default for EventReplay {
+    fn default() -> Self { Self::new(EventReplayCapacity::configured_value().into()) }
+}
+
+impl EventReplay {
+    pub fn new(capacity: usize) -> Self {
+        EventReplay { transitions: VecDeque::new(),
+                      capacity }
+    }
+
+    pub fn push(&mut self, transition: Transition) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.transitions.len() >= self.capacity {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(transition);
+    }
+
+    /// The retained transitions, oldest first, of `service_group` if it is given, observed
+    /// between `since` and `until` seconds since the UNIX epoch, inclusive, if they are given.
+    pub fn query<'a>(&'a self,
+                     service_group: Option<&'a ServiceGroup>,
+                     since: Option<u64>,
+                     until: Option<u64>)
+                     -> impl Iterator<Item = &'a Transition> {
+        self.transitions.iter().filter(move |t| {
+                                    service_group.map_or(true, |sg| &t.service_group == sg)
+                                    && since.map_or(true, |since| t.timestamp >= since)
+                                    && until.map_or(true, |until| t.timestamp <= until)
+                                })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service_group(name: &str) -> ServiceGroup {
+        ServiceGroup::new(name, "default", None).unwrap()
+    }
+
+    #[test]
+    fn oldest_transitions_of_a_service_are_evicted_first() {
+        let redis = service_group("redis");
+        let nginx = service_group("nginx");
+        let ident: PackageIdent = "core/redis/4.0.14/20190319155852".parse().unwrap();
+        let mut history = ServiceHistory::new(2);
+        history.record(&redis, &ident, Lifecycle::Loaded);
+        history.record(&nginx, &ident, Lifecycle::Loaded);
+        history.record(&redis, &ident, Lifecycle::Started);
+        history.record(&redis,
+                       &ident,
+                       Lifecycle::Restarted { reason: RestartReason::ProcessExited, });
+
+        let redis_history: Vec<_> = history.for_service_group(&redis)
+                                           .map(|t| t.lifecycle.name())
+                                           .collect();
+        assert_eq!(redis_history, vec!["started", "restarted"]);
+        assert_eq!(history.for_service_group(&nginx).count(), 1);
+        assert_eq!(history.transitions().len(), 3);
+    }
+
+    #[test]
+    fn event_replay_keeps_the_latest_transitions_of_all_services() {
+        let redis = service_group("redis");
+        let nginx = service_group("nginx");
+        let ident: PackageIdent = "core/redis/4.0.14/20190319155852".parse().unwrap();
+        let transition = |service_group, lifecycle, timestamp| {
+            Transition { timestamp,
+                         ..Transition::new(service_group, &ident, lifecycle) }
+        };
+        let mut replay = EventReplay::new(3);
+        replay.push(transition(&redis, Lifecycle::Loaded, 100));
+        replay.push(transition(&nginx, Lifecycle::Loaded, 110));
+        replay.push(transition(&redis, Lifecycle::Started, 120));
+        replay.push(transition(&nginx, Lifecycle::Started, 130));
+
+        let names = |events: Vec<&Transition>| {
+            events.into_iter()
+                  .map(|t| format!("{} {}", t.service_group.service(), t.lifecycle.name()))
+                  .collect::<Vec<_>>()
+        };
+        assert_eq!(names(replay.query(None, None, None).collect()),
+                   vec!["nginx loaded", "redis started", "nginx started"]);
+        assert_eq!(names(replay.query(Some(&nginx), None, None).collect()),
+                   vec!["nginx loaded", "nginx started"]);
+        assert_eq!(names(replay.query(None, Some(120), Some(130)).collect()),
+                   vec!["redis started", "nginx started"]);
+        assert_eq!(names(replay.query(Some(&redis), None, Some(119)).collect()),
+                   Vec::<String>::new());
+    }
+
+    #[test]
+    fn zero_capacity_retains_nothing() {
+        let redis = service_group("redis");
+        let ident: PackageIdent = "core/redis".parse().unwrap();
+        let mut history = ServiceHistory::new(0);
+        history.record(&redis, &ident, Lifecycle::Loaded);
+        assert!(history.transitions().is_empty());
+    }
+
+    #[test]
+    fn transitions_serialize_with_their_event_name() {
+        let redis = service_group("redis");
+        let from: PackageIdent = "core/redis/4.0.14/20190319155852".parse().unwrap();
+        let to: PackageIdent = "core/redis/5.0.7/20200101000000".parse().unwrap();
+        let mut history = ServiceHistory::new(10);
+        history.record(&redis,
+                       &to,
+                       Lifecycle::Updated { from: from.clone(),
+                                            to:   to.clone(), });
+
+        let transition = history.for_service_group(&redis).next().unwrap();
+        let json = serde_json::to_value(transition).unwrap();
+        assert_eq!(json["event"], "updated");
+        assert_eq!(json["from"]["version"], "4.0.14");
+        assert_eq!(json["to"]["version"], "5.0.7");
+        assert_eq!(transition.lifecycle.detail().unwrap(),
+                   format!("from {} to {}", from, to));
+    }
+
+    #[test]
+    fn config_applied_describes_the_decision() {
+        let applied = Lifecycle::ConfigApplied { changed_keys: vec!["port".to_string(),
+                                                                    "tls.cert".to_string()],
+                                                 restart:      true, };
+        assert_eq!(applied.detail().unwrap(),
+                   "restarted after port, tls.cert changed");
+        let applied = Lifecycle::ConfigApplied { changed_keys: Vec::new(),
+                                                 restart:      false, };
+        assert_eq!(applied.detail().unwrap(),
+                   "reconfigured after templated data changed");
+    }
+}
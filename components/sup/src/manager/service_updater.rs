@@ -1,7 +1,9 @@
+mod canary_update_worker;
 mod package_update_worker;
 mod rolling_update_worker;
 
-use self::{package_update_worker::PackageUpdateWorker,
+use self::{canary_update_worker::CanaryUpdateWorker,
+           package_update_worker::PackageUpdateWorker,
            rolling_update_worker::RollingUpdateWorker};
 use crate::{census::CensusRing,
             manager::service::{Service,
@@ -9,8 +11,10 @@ use crate::{census::CensusRing,
 use futures::future::{self,
                       AbortHandle};
 use habitat_common::outputln;
-use habitat_core::{package::PackageIdent,
-                   service::ServiceGroup};
+use habitat_core::{clock,
+                   package::PackageIdent,
+                   service::{ServiceGroup,
+                             UpdateWindow}};
 use parking_lot::{Mutex,
                   RwLock};
 use std::{self,
@@ -18,9 +22,14 @@ use std::{self,
           future::Future,
           sync::Arc,
           time::Duration};
+use tokio::time;
 
 static LOGKEY: &str = "SU";
 
+/// The longest to wait before checking again whether a service's update window is open, so that
+/// changes to the system clock are noticed
+const UPDATE_WINDOW_POLL: Duration = Duration::from_secs(60);
+
 /// A handle to an update worker that automatically aborts the worker when dropped.
 struct Worker(AbortHandle);
 
@@ -35,6 +44,10 @@ pub struct ServiceUpdater {
     butterfly:   habitat_butterfly::Server,
     census_ring: Arc<RwLock<CensusRing>>,
     updates:     Arc<Mutex<HashMap<ServiceGroup, PackageIdent>>>,
+    /// The package each service group last rolled back from after its canaries failed, which
+    /// its canary updates pass over. This outlives the service's registration, since rolling
+    /// back restarts the service.
+    rejected:    Arc<Mutex<HashMap<ServiceGroup, PackageIdent>>>,
     workers:     HashMap<ServiceGroup, Worker>,
     period:      Duration,
 }
@@ -47,6 +60,7 @@ impl ServiceUpdater {
         ServiceUpdater { butterfly,
                          census_ring,
                          updates: Arc::default(),
+                         rejected: Arc::default(),
                          workers: HashMap::new(),
                          period }
     }
@@ -75,6 +89,11 @@ impl ServiceUpdater {
                 let worker = self.rolling_worker(service, Arc::clone(&self.census_ring));
                 self.spawn_worker(service_group, worker);
             }
+            UpdateStrategy::Canary => {
+                debug!("Registering canary updater for {}", service);
+                let worker = self.canary_worker(service, Arc::clone(&self.census_ring));
+                self.spawn_worker(service_group, worker);
+            }
         };
     }
 
@@ -103,11 +122,13 @@ impl ServiceUpdater {
         let service_group = service.service_group.clone();
         let full_ident = service.pkg.ident.clone();
         let updates = Arc::clone(&self.updates);
+        let window = service.update_window();
         let package_update_worker = PackageUpdateWorker::new(service, self.period);
         async move {
             let new_ident = package_update_worker.update().await;
             debug!("'{}' at-once updater found update from '{}' to '{}'",
                    service_group, full_ident, new_ident);
+            Self::wait_for_window(&service_group, window, &new_ident, full_ident.as_ref()).await;
             Self::update_message(&new_ident, full_ident.as_ref());
             updates.lock().insert(service_group, new_ident);
         }
@@ -125,17 +146,74 @@ impl ServiceUpdater {
         let service_group = service.service_group.clone();
         let full_ident = service.pkg.ident.clone();
         let updates = Arc::clone(&self.updates);
+        let window = service.update_window();
         let worker =
             RollingUpdateWorker::new(service, census_ring, self.butterfly.clone(), self.period);
         async move {
             let new_ident = worker.run().await;
             debug!("'{}' rolling updater found update from '{}' to '{}'",
                    service_group, full_ident, new_ident);
+            Self::wait_for_window(&service_group, window, &new_ident, full_ident.as_ref()).await;
+            Self::update_message(&new_ident, full_ident.as_ref());
+            updates.lock().insert(service_group, new_ident);
+        }
+    }
+
+    fn canary_worker(&mut self,
+                     service: &Service,
+                     census_ring: Arc<RwLock<CensusRing>>)
+                     -> impl Future<Output = ()> + Send + 'static {
+        debug!("'{}' service updater spawning canary worker watching for changes to '{}' from \
+                channel '{}'",
+               service.service_group,
+               service.spec_ident(),
+               service.channel());
+        let service_group = service.service_group.clone();
+        let full_ident = service.pkg.ident.clone();
+        let updates = Arc::clone(&self.updates);
+        let window = service.update_window();
+        let worker = CanaryUpdateWorker::new(service,
+                                             census_ring,
+                                             self.butterfly.clone(),
+                                             self.period,
+                                             Arc::clone(&self.rejected));
+        async move {
+            let new_ident = worker.run().await;
+            debug!("'{}' canary updater found update from '{}' to '{}'",
+                   service_group, full_ident, new_ident);
+            Self::wait_for_window(&service_group, window, &new_ident, full_ident.as_ref()).await;
             Self::update_message(&new_ident, full_ident.as_ref());
             updates.lock().insert(service_group, new_ident);
         }
     }
 
+    /// Wait until `window`, if there is one, is open before updating to `new_ident`. Rolling back
+    /// isn't held up by it.
+    async fn wait_for_window(service_group: &ServiceGroup,
+                             window: Option<UpdateWindow>,
+                             new_ident: &PackageIdent,
+                             current_ident: &PackageIdent) {
+        let window = match window {
+            Some(window) if new_ident > current_ident => window,
+            _ => return,
+        };
+        if !window.contains(clock::now()) {
+            outputln!(preamble service_group,
+                      "Waiting for the update window {} to update",
+                      window);
+        }
+        loop {
+            let now = clock::now();
+            if window.contains(now) {
+                break;
+            }
+            let wait = window.next_open(now)
+                             .and_then(|opens| opens.duration_since(now).ok())
+                             .unwrap_or(UPDATE_WINDOW_POLL);
+            time::delay_for(wait.min(UPDATE_WINDOW_POLL)).await;
+        }
+    }
+
     fn update_message(new_ident: &PackageIdent, current_ident: &PackageIdent) {
         if new_ident > current_ident {
             outputln!("Updating from {} to {}", current_ident, new_ident);
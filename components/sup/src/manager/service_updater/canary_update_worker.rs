@@ -0,0 +1,327 @@
+use super::{package_update_worker::PackageUpdateWorker,
+            rolling_update_worker::{Role,
+                                    RollingUpdateWorker,
+                                    DELAY}};
+use crate::{census::{CensusGroup,
+                     CensusRing},
+            manager::service::Service};
+use habitat_common::outputln;
+use habitat_core::{clock,
+                   package::PackageIdent,
+                   service::ServiceGroup};
+use parking_lot::{Mutex,
+                  RwLock};
+use std::{self,
+          collections::HashMap,
+          sync::Arc,
+          time::{Duration,
+                 Instant}};
+use tokio::{self,
+            time};
+
+static LOGKEY: &str = "CU";
+
+/// How an update the leader has taken is going across the rest of the group.
+enum Rollout {
+    /// Every active member is running the leader's package.
+    Done,
+    /// Some members have yet to update to the leader's package.
+    InProgress,
+    /// A canary failed its health check before any other member updated. The group goes back to
+    /// the given package.
+    Failed(PackageIdent),
+}
+
+/// What an update follower should do next.
+enum FollowerStep {
+    /// The leader died and this follower was chosen as the leader.
+    PromotedToLeader,
+    /// The leader is running the same package as this follower.
+    Idle,
+    /// This follower is a canary and should update to the leader's package right away.
+    UpdateTo(PackageIdent),
+    /// This follower is not a canary. It may update to the leader's package once the canaries
+    /// have all been running it, healthy, for the soak period.
+    AwaitCanaries {
+        target:  PackageIdent,
+        healthy: bool,
+    },
+}
+
+/// How long the canaries have been running a package with passing health checks, as told by the
+/// thread's `clock`.
+#[derive(Default)]
+struct Soak(Option<(PackageIdent, Instant)>);
+
+impl Soak {
+    /// Records that the canaries are healthy running `target`, returning how long they have
+    /// been, or `None` if they have only just started to be.
+    fn healthy(&mut self, target: &PackageIdent) -> Option<Duration> {
+        match &self.0 {
+            Some((ident, since)) if ident == target => {
+                Some(clock::instant().duration_since(*since))
+            }
+            _ => {
+                self.0 = Some((target.clone(), clock::instant()));
+                None
+            }
+        }
+    }
+
+    fn reset(&mut self) { self.0 = None; }
+}
+
+/// The worker for handling canary updates.
+///
+/// An update leader is elected as it is for a rolling update. When the leader finds an update, it
+/// and the rest of the group's canaries, `canary_percent` percent of its active members, update
+/// first. The other members wait until every canary has been running the new package with a
+/// passing health check for `canary_soak`, then all update together. If a canary fails its
+/// health check before then, the leader goes back to the package the rest of the group is
+/// running, the other canaries follow it, and the package is not updated to again until the
+/// Supervisor is restarted.
+///
+/// If the leader goes away partway through, the group converges on the new leader's package, and
+/// any update starts over from its canaries.
+pub struct CanaryUpdateWorker {
+    service_group:         ServiceGroup,
+    current:               PackageIdent,
+    percent:               u32,
+    soak:                  Duration,
+    package_update_worker: PackageUpdateWorker,
+    rolling:               RollingUpdateWorker,
+    rejected:              Arc<Mutex<HashMap<ServiceGroup, PackageIdent>>>,
+}
+
+impl CanaryUpdateWorker {
+    pub fn new(service: &Service,
+               census_ring: Arc<RwLock<CensusRing>>,
+               butterfly: habitat_butterfly::Server,
+               period: Duration,
+               rejected: Arc<Mutex<HashMap<ServiceGroup, PackageIdent>>>)
+               -> Self {
+        let current: &PackageIdent = service.pkg.ident.as_ref();
+        Self { service_group: service.service_group.clone(),
+               current: current.clone(),
+               percent: service.canary_percent(),
+               soak: service.canary_soak(),
+               package_update_worker: PackageUpdateWorker::new(service, period),
+               rolling: RollingUpdateWorker::new(service, census_ring, butterfly, period),
+               rejected }
+    }
+
+    pub async fn run(self) -> PackageIdent {
+        self.rolling.start_update_election().await;
+        match self.rolling.update_role().await {
+            Role::Leader => self.lead().await,
+            Role::Follower => {
+                match self.follow().await {
+                    Some(new_ident) => self.package_update_worker.update_to(new_ident).await,
+                    None => self.lead().await,
+                }
+            }
+        }
+    }
+
+    /// Watch over an update the leader has already taken until the group is done with it, rolling
+    /// back if its canaries fail, then wait for the next update.
+    async fn lead(&self) -> PackageIdent {
+        loop {
+            let rollout = {
+                let census_group = self.rolling.census_group().await;
+                self.rollout(&census_group)
+            };
+            match rollout {
+                Rollout::Done => break,
+                Rollout::InProgress => {}
+                Rollout::Failed(previous) => {
+                    outputln!(preamble self.service_group,
+                              "A canary failed its health check running {}; rolling back to {}",
+                              self.current,
+                              previous);
+                    self.rejected
+                        .lock()
+                        .insert(self.service_group.clone(), self.current.clone());
+                    return self.package_update_worker.update_to(previous).await;
+                }
+            }
+            time::delay_for(DELAY).await;
+        }
+        loop {
+            let new_ident = self.package_update_worker.update().await;
+            if self.rejected.lock().get(&self.service_group) != Some(&new_ident) {
+                break new_ident;
+            }
+            debug!("'{}' canary update leader is passing over '{}', which failed its canaries",
+                   self.service_group, new_ident);
+        }
+    }
+
+    fn rollout(&self, census_group: &CensusGroup) -> Rollout {
+        let me = match census_group.me() {
+            Some(me) if me.pkg == self.current => me,
+            _ => {
+                // The census doesn't yet know this member is running its current package.
+                trace!("'{}' canary update leader is waiting to hear its own package",
+                       self.service_group);
+                return Rollout::InProgress;
+            }
+        };
+        let members = census_group.active_members().collect::<Vec<_>>();
+        let previous = match members.iter().find(|m| m.pkg != me.pkg) {
+            Some(member) => member.pkg.clone(),
+            None => return Rollout::Done,
+        };
+        let canary_ids = canaries(members.iter().map(|m| m.member_id.as_str()),
+                                  &me.member_id,
+                                  self.percent);
+        let (canary_members, others): (Vec<_>, Vec<_>) =
+            members.iter()
+                   .partition(|m| canary_ids.contains(&m.member_id.as_str()));
+        let committed = others.iter().any(|m| m.pkg == me.pkg);
+        let failed = canary_members.iter().any(|m| m.pkg == me.pkg && !m.healthy());
+        if failed && !committed {
+            Rollout::Failed(previous)
+        } else {
+            Rollout::InProgress
+        }
+    }
+
+    /// Wait for this follower's turn to update, returning the package to update to, or `None` if
+    /// it was promoted to the leader.
+    async fn follow(&self) -> Option<PackageIdent> {
+        let mut prepared = None;
+        let mut soak = Soak::default();
+        loop {
+            let step = {
+                let census_group = self.rolling.census_group().await;
+                self.follower_step(&census_group)
+            };
+            match step {
+                FollowerStep::PromotedToLeader => {
+                    debug!("'{}' canary update follower was promoted to the leader",
+                           self.service_group);
+                    return None;
+                }
+                FollowerStep::Idle => soak.reset(),
+                FollowerStep::UpdateTo(target) => {
+                    debug!("'{}' canary is updating to '{}'", self.service_group, target);
+                    return Some(target);
+                }
+                FollowerStep::AwaitCanaries { target, healthy } => {
+                    if prepared.as_ref() != Some(&target) {
+                        // Fetch everything the update needs while the canaries soak.
+                        self.package_update_worker.prepare(&target).await;
+                        prepared = Some(target.clone());
+                    }
+                    if !healthy {
+                        soak.reset();
+                    } else {
+                        match soak.healthy(&target) {
+                            Some(soaked) if soaked >= self.soak => {
+                                debug!("'{}' canaries stayed healthy running '{}'; updating",
+                                       self.service_group, target);
+                                return Some(target);
+                            }
+                            Some(_) => {}
+                            None => {
+                                outputln!(preamble self.service_group,
+                                          "Canaries are running {}; updating if they stay \
+                                           healthy for {}s",
+                                          target,
+                                          self.soak.as_secs());
+                            }
+                        }
+                    }
+                }
+            }
+            time::delay_for(DELAY).await;
+        }
+    }
+
+    fn follower_step(&self, census_group: &CensusGroup) -> FollowerStep {
+        match (census_group.update_leader(), census_group.me()) {
+            (Some(leader), Some(me)) => {
+                if leader.member_id == me.member_id {
+                    FollowerStep::PromotedToLeader
+                } else if leader.pkg == me.pkg {
+                    trace!("'{}' is not in a canary update", self.service_group);
+                    FollowerStep::Idle
+                } else {
+                    let canaries = canaries(census_group.active_members()
+                                                        .map(|m| m.member_id.as_str()),
+                                            &leader.member_id,
+                                            self.percent);
+                    if canaries.contains(&me.member_id.as_str()) {
+                        FollowerStep::UpdateTo(leader.pkg.clone())
+                    } else {
+                        let healthy =
+                            census_group.active_members()
+                                        .filter(|m| canaries.contains(&m.member_id.as_str()))
+                                        .all(|m| m.pkg == leader.pkg && m.healthy());
+                        FollowerStep::AwaitCanaries { target: leader.pkg.clone(),
+                                                      healthy }
+                    }
+                }
+            }
+            _ => {
+                error!("The census group for '{}' is in a bad state. It could not determine the \
+                        update leader or its own identity.",
+                       self.service_group);
+                debug_assert!(false);
+                FollowerStep::Idle
+            }
+        }
+    }
+}
+
+/// The members which update first: the update leader, then the others in order of member ID, up
+/// to `percent` percent of the members, rounded up.
+fn canaries<'a>(member_ids: impl Iterator<Item = &'a str>,
+                leader_id: &str,
+                percent: u32)
+                -> Vec<&'a str> {
+    let mut member_ids = member_ids.collect::<Vec<_>>();
+    member_ids.sort_unstable_by_key(|id| (*id != leader_id, *id));
+    let count = (member_ids.len() * percent as usize + 99) / 100;
+    member_ids.truncate(count.max(1));
+    member_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::clock::ManualClock;
+
+    #[test]
+    fn canaries_start_with_the_leader_and_round_up() {
+        let members = ["d", "b", "a", "c", "e"];
+        assert_eq!(canaries(members.iter().copied(), "c", 10), vec!["c"]);
+        assert_eq!(canaries(members.iter().copied(), "c", 50), vec!["c", "a", "b"]);
+        assert_eq!(canaries(members.iter().copied(), "c", 100),
+                   vec!["c", "a", "b", "d", "e"]);
+        assert_eq!(canaries(["a"].iter().copied(), "a", 1), vec!["a"]);
+    }
+
+    #[test]
+    fn soak_starts_over_for_a_new_package_or_after_a_reset() {
+        let clock = ManualClock::new();
+        let _clock = clock::set_for_thread(clock.clone());
+        let v1 = "core/redis/1.0.0/20200101000000".parse::<PackageIdent>().unwrap();
+        let v2 = "core/redis/2.0.0/20200101000000".parse::<PackageIdent>().unwrap();
+        let mut soak = Soak::default();
+
+        assert_eq!(soak.healthy(&v1), None);
+        clock.advance(Duration::from_secs(300));
+        assert_eq!(soak.healthy(&v1), Some(Duration::from_secs(300)));
+
+        assert_eq!(soak.healthy(&v2), None);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(soak.healthy(&v2), Some(Duration::from_secs(10)));
+
+        soak.reset();
+        assert_eq!(soak.healthy(&v2), None);
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(soak.healthy(&v2), Some(Duration::from_secs(600)));
+    }
+}
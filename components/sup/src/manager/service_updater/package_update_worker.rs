@@ -49,6 +49,11 @@ impl PackageUpdateWorkerPeriod {
 /// When `run`, a `PackageUpdateWorker` returns a future that continuously checks for a change in
 /// version of the package being run by a service. If a change is detected, the package is installed
 /// and its identifier returned.
+///
+/// A new release is only returned once all of its transitive dependencies are installed and their
+/// signatures verified. Returning it is what restarts the service, and in a rolling update is what
+/// signals the rest of the group to follow, so a release with a dependency which can't be fetched
+/// is passed over until it can be rather than leaving the group half updated.
 pub struct PackageUpdateWorker {
     service_group:    ServiceGroup,
     ident:            PackageIdent,
@@ -95,18 +100,7 @@ impl PackageUpdateWorker {
                 }
             };
             match package_result {
-                Ok(package) => {
-                    if &package.ident != self.full_ident.as_ref() {
-                        debug!("'{}' package update worker found change from '{}' to '{}' for \
-                                '{}' in channel '{}' using '{}' update condition",
-                               self.service_group,
-                               self.full_ident,
-                               package.ident,
-                               ident,
-                               self.channel,
-                               self.update_condition);
-                        break package.ident;
-                    }
+                Ok(package) if &package.ident == self.full_ident.as_ref() => {
                     trace!("'{}' package update worker did not find change from '{}' for '{}' in \
                             channel '{}' using '{}' update condition",
                            self.service_group,
@@ -115,6 +109,26 @@ impl PackageUpdateWorker {
                            self.channel,
                            self.update_condition)
                 }
+                Ok(package) => {
+                    match util::pkg::verify_dependency_closure(&package) {
+                        Ok(()) => {
+                            debug!("'{}' package update worker found change from '{}' to '{}' \
+                                    for '{}' in channel '{}' using '{}' update condition",
+                                   self.service_group,
+                                   self.full_ident,
+                                   package.ident,
+                                   ident,
+                                   self.channel,
+                                   self.update_condition);
+                            break package.ident;
+                        }
+                        Err(err) => {
+                            warn!("'{}' package update worker is not updating to '{}' until its \
+                                   dependencies are all installed and verified, err: {}",
+                                  self.service_group, package.ident, err)
+                        }
+                    }
+                }
                 Err(err) => {
                     warn!("'{}' package update worker failed to install '{}' from channel '{}', \
                            err: {}",
@@ -128,6 +142,27 @@ impl PackageUpdateWorker {
         }
     }
 
+    /// Installs `ident` and its dependencies ahead of this member's turn in a rolling update, so
+    /// that the turn isn't held up fetching them. If they can't be installed now, they are tried
+    /// again when the turn comes.
+    pub async fn prepare(&self, ident: &PackageIdent) {
+        let install_source = ident.clone().into();
+        let result = util::pkg::install_no_ui(&self.builder_url, &install_source, &self.channel)
+            .await
+            .and_then(|package| util::pkg::verify_dependency_closure(&package));
+        match result {
+            Ok(()) => {
+                debug!("'{}' package update worker prepared '{}' for a rolling update",
+                       self.service_group, ident)
+            }
+            Err(err) => {
+                warn!("'{}' package update worker failed to prepare '{}' for a rolling update, \
+                       err: {}",
+                      self.service_group, ident, err)
+            }
+        }
+    }
+
     /// Use the service spec's package ident to search for packages.
     pub async fn update(&self) -> PackageIdent { self.update_to(self.ident.clone()).await }
 }
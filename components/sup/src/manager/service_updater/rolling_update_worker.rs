@@ -15,10 +15,10 @@ use tokio::{self,
 
 // The census ring does not have an async API. We make it look async by making API calls in a
 // loop with this delay after each call.
-const DELAY: Duration = Duration::from_secs(1);
+pub(super) const DELAY: Duration = Duration::from_secs(1);
 
 /// The role of the supervisor in the rolling update.
-enum Role {
+pub(super) enum Role {
     Leader,
     Follower,
 }
@@ -72,10 +72,7 @@ impl RollingUpdateWorker {
     }
 
     pub async fn run(self) -> PackageIdent {
-        // Determine this services suitablity and start the update leader election
-        let suitability = self.update_election_suitability(self.topology).await;
-        self.butterfly
-            .start_update_election_rsw_mlr_rhw(&self.service_group, suitability, 0);
+        self.start_update_election().await;
         // Determine this services role in the rolling update
         match self.update_role().await {
             Role::Leader => {
@@ -106,6 +103,13 @@ impl RollingUpdateWorker {
         }
     }
 
+    /// Determine this services suitablity and start the update leader election.
+    pub(super) async fn start_update_election(&self) {
+        let suitability = self.update_election_suitability(self.topology).await;
+        self.butterfly
+            .start_update_election_rsw_mlr_rhw(&self.service_group, suitability, 0);
+    }
+
     async fn update_election_suitability(&self, topology: Topology) -> u64 {
         match topology {
             Topology::Standalone => {
@@ -158,7 +162,7 @@ impl RollingUpdateWorker {
         }
     }
 
-    async fn update_role(&self) -> Role {
+    pub(super) async fn update_role(&self) -> Role {
         loop {
             {
                 let census_group = self.census_group().await;
@@ -233,6 +237,8 @@ impl RollingUpdateWorker {
             }
             FollowerUpdateStartEvent::UpdateTo(ident) => ident,
         };
+        // Fetch everything the update needs while the members ahead of this one take their turns.
+        self.package_update_worker.prepare(&update_to).await;
         loop {
             {
                 let census_group = self.census_group().await;
@@ -289,7 +295,7 @@ impl RollingUpdateWorker {
 
     /// Returns a reference to the services census group. The reference is behind a read write lock
     /// so the lifetime of the reference should be minimized to avoid uneccesarily holding the lock.
-    async fn census_group(&self) -> RwLockReadGuardRef<'_, CensusRing, CensusGroup> {
+    pub(super) async fn census_group(&self) -> RwLockReadGuardRef<'_, CensusRing, CensusGroup> {
         loop {
             {
                 let census_ring = RwLockReadGuardRef::new(self.census_ring.read().into());
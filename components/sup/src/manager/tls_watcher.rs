@@ -0,0 +1,125 @@
+//! Reloads the TLS configuration of the HTTP gateway when its `--key`, `--certs` or `--ca-certs`
+//! files change, so that short-lived certificates (from ACME or Vault, for example) can be renewed
+//! without restarting the Supervisor.
+//!
+//! The files are checked periodically. When their contents have changed and still make a valid
+//! configuration, the gateway rebinds its listener with it, letting requests in flight finish.
+//! Either way, a `tls_config_reloaded` event records the outcome.
+
+use super::{tls_config,
+            TLSConfig};
+use crate::event;
+use futures::channel::mpsc::UnboundedSender;
+use habitat_common::outputln;
+use habitat_core::crypto::hash;
+use rustls::ServerConfig;
+use std::time::Duration;
+
+static LOGKEY: &str = "TW";
+
+habitat_core::env_config_duration!(
+    /// How often the HTTP gateway's TLS files are checked for changes.
+    TlsReloadCheckPeriod,
+    HAB_TLS_RELOAD_CHECK_PERIOD_SECS => from_secs,
+    Duration::from_secs(10));
+
+pub struct TlsWatcher {
+    config:   TLSConfig,
+    /// The hash of the files' contents when they were last checked.
+    contents: String,
+    reloads:  UnboundedSender<ServerConfig>,
+}
+
+impl TlsWatcher {
+    /// Watches the files of `config`, which the gateway was started with, sending `reloads` the
+    /// new configuration whenever they change.
+    pub fn new(config: TLSConfig, reloads: UnboundedSender<ServerConfig>) -> Self {
+        let contents = contents_hash(&config);
+        TlsWatcher { config,
+                     contents,
+                     reloads }
+    }
+
+    /// Reloads the gateway's TLS configuration if its files have changed since the last check.
+    pub fn check(&mut self) {
+        let contents = contents_hash(&self.config);
+        if contents == self.contents {
+            return;
+        }
+        self.contents = contents;
+        match tls_config(&self.config) {
+            Ok(server_config) => {
+                outputln!("Reloading the http-gateway TLS configuration");
+                if self.reloads.unbounded_send(server_config).is_err() {
+                    warn!("The http-gateway is no longer running; not reloading its TLS \
+                           configuration");
+                }
+            }
+            Err(e) => {
+                outputln!("Not reloading the http-gateway TLS configuration: {}", e);
+                event::tls_config_reloaded(Some(&e.to_string()));
+            }
+        }
+    }
+}
+
+/// Hashes the contents of every file of `config`. A file which can't be read hashes as empty, so
+/// that it is picked up again once it reappears.
+fn contents_hash(config: &TLSConfig) -> String {
+    let hashes =
+        [Some(&config.key_path),
+         Some(&config.cert_path),
+         config.ca_cert_path.as_ref()].iter()
+                                      .flatten()
+                                      .map(|path| hash::hash_file(path).unwrap_or_default())
+                                      .collect::<Vec<_>>();
+    hash::hash_string(&hashes.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn config(dir: &TempDir) -> TLSConfig {
+        fs::write(dir.path().join("key.pem"), "not a key").unwrap();
+        fs::write(dir.path().join("cert.pem"), "not a certificate").unwrap();
+        TLSConfig { cert_path:    dir.path().join("cert.pem"),
+                    key_path:     dir.path().join("key.pem"),
+                    ca_cert_path: None,
+                    acme:         None, }
+    }
+
+    #[test]
+    fn changes_to_any_file_are_noticed() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config(&dir);
+        let original = contents_hash(&config);
+        assert_eq!(contents_hash(&config), original);
+
+        fs::write(&config.cert_path, "a renewed certificate").unwrap();
+        let renewed = contents_hash(&config);
+        assert_ne!(renewed, original);
+
+        config.ca_cert_path = Some(dir.path().join("ca.pem"));
+        fs::write(dir.path().join("ca.pem"), "a CA certificate").unwrap();
+        assert_ne!(contents_hash(&config), renewed);
+    }
+
+    #[test]
+    fn invalid_files_are_not_sent_to_the_gateway() {
+        let dir = TempDir::new().unwrap();
+        let (tx, mut rx) = mpsc::unbounded();
+        let mut watcher = TlsWatcher::new(config(&dir), tx);
+        watcher.check();
+
+        fs::write(&watcher.config.cert_path, "still not a certificate").unwrap();
+        let before = watcher.contents.clone();
+        watcher.check();
+        assert_ne!(watcher.contents, before);
+        // Nothing was sent, but the gateway is still listening.
+        assert!(rx.try_next().is_err());
+    }
+}
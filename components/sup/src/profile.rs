@@ -0,0 +1,60 @@
+//! The defaults applied by each Supervisor profile (`hab sup run --profile`).
+//!
+//! Options given explicitly on the command line or in `sup.toml` are applied on top of these
+//! defaults, except where noted in `HELP`.
+
+use habitat_butterfly::{rumor::{RumorLimit,
+                                RumorLimits},
+                        server::timing::Timing};
+use habitat_common::types::SupervisorProfile;
+
+/// How many times less often a `minimal` Supervisor probes and gossips.
+const MINIMAL_GOSSIP_SLOWDOWN: u32 = 3;
+
+/// What `hab sup run --profile-help` prints.
+pub const HELP: &str = "\
+Supervisor profiles
+
+default
+    The standard Supervisor configuration.
+
+minimal
+    Targets resource-constrained edge devices, aiming to keep the Supervisor under 64MB
+    of resident memory. Compared to the default profile:
+
+    * The HTTP gateway is disabled, so service status and configuration are only available
+      through the control gateway (`hab svc status`, `hab config show`, ...).
+    * The event stream is disabled; any --event-stream-* options are ignored.
+    * Members are probed and rumors gossiped three times less often. Failed members take
+      about three times as long to be suspected and confirmed, and changes take longer to
+      spread through the ring.
+    * At most 1000 service and 1000 service configuration rumors, and 100 service file
+      rumors totalling 16MB, are kept. The least recently updated rumors are evicted past
+      these limits. --max-rumors and --max-rumor-bytes override them.
+";
+
+/// The gossip protocol timings of a profile.
+pub fn gossip_timing(profile: SupervisorProfile) -> Timing {
+    match profile {
+        SupervisorProfile::Default => Timing::default(),
+        SupervisorProfile::Minimal => Timing::slowed_by(MINIMAL_GOSSIP_SLOWDOWN),
+    }
+}
+
+/// The rumor limits of a profile, before any `--max-rumors` or `--max-rumor-bytes` are applied.
+pub fn rumor_limits(profile: SupervisorProfile) -> RumorLimits {
+    match profile {
+        SupervisorProfile::Default => RumorLimits::default(),
+        SupervisorProfile::Minimal => {
+            RumorLimits { service:        RumorLimit { max_rumors: Some(1000),
+                                                       max_bytes:  None, },
+                          service_config: RumorLimit { max_rumors: Some(1000),
+                                                       max_bytes:  None, },
+                          service_file:   RumorLimit { max_rumors: Some(100),
+                                                       max_bytes:  Some(16 * 1024 * 1024), }, }
+        }
+    }
+}
+
+/// Whether a profile runs without the HTTP gateway and event stream.
+pub fn is_headless(profile: SupervisorProfile) -> bool { profile == SupervisorProfile::Minimal }
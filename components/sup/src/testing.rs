@@ -0,0 +1,231 @@
+//! Runs throwaway Supervisors for integration tests of automation built on top of Habitat.
+//!
+//! A `TestSupervisor` runs the `hab-launch` and `hab-sup` binaries as a subprocess, with its own
+//! temporary filesystem root, key cache and control gateway secret, listening on free local
+//! ports. Everything is cleaned up when it is dropped. Mock services can be installed into its
+//! filesystem root and loaded, before or after it is started, without a Builder.
+//!
+//! The binaries are taken from `HAB_LAUNCH_BINARY` and `HAB_SUP_BINARY` if they are set, and
+//! otherwise from the cargo target directory the running test was built in.
+//!
+//! Only built with the `testing` feature.
+
+use crate::{error::{Error,
+                    Result},
+            manager::service::ServiceSpec};
+use habitat_core::{crypto::{keys::sym_key::SymKey,
+                            CACHE_KEY_PATH_ENV_VAR},
+                   package::{PackageIdent,
+                             PackageInstall},
+                   testing::{self,
+                             TestKeyCache},
+                   url::BLDR_URL_ENVVAR};
+use habitat_sup_protocol as protocol;
+use std::{env,
+          fs,
+          io,
+          net::{Ipv4Addr,
+                SocketAddr,
+                TcpStream},
+          path::{Path,
+                 PathBuf},
+          process::{Child,
+                    Command,
+                    Stdio},
+          thread,
+          time::{Duration,
+                 Instant}};
+use tempfile::TempDir;
+
+/// Where test Supervisors look for packages they can't find installed. Nothing answers there,
+/// so they never update or install anything.
+const TEST_BLDR_URL: &str = "http://hab.sup.test";
+
+/// How often to check whether a starting Supervisor is listening yet
+const READY_POLL: Duration = Duration::from_millis(250);
+
+/// A Supervisor run for the length of a test.
+pub struct TestSupervisor {
+    fs_root:     TempDir,
+    keys:        TestKeyCache,
+    ring:        Option<String>,
+    peers:       Vec<SocketAddr>,
+    gossip_port: u16,
+    http_port:   u16,
+    ctl_port:    u16,
+    ctl_secret:  String,
+    process:     Option<Child>,
+}
+
+impl TestSupervisor {
+    /// Sets up a Supervisor, without starting it.
+    pub fn new() -> Result<Self> {
+        let fs_root = tempfile::Builder::new().prefix("hab-sup").tempdir()?;
+        let mut ctl_secret = String::new();
+        protocol::generate_secret_key(&mut ctl_secret);
+        let sup = TestSupervisor { fs_root,
+                                   keys: TestKeyCache::new()?,
+                                   ring: None,
+                                   peers: Vec::new(),
+                                   gossip_port: testing::unclaimed_port()?,
+                                   http_port: testing::unclaimed_port()?,
+                                   ctl_port: testing::unclaimed_port()?,
+                                   ctl_secret,
+                                   process: None };
+        fs::create_dir_all(sup.sup_root())?;
+        fs::write(protocol::secret_key_path(sup.sup_root()), &sup.ctl_secret)?;
+        Ok(sup)
+    }
+
+    /// Encrypts the Supervisor's gossip with a newly generated key for `ring`. Other test
+    /// Supervisors join the same ring with `join_ring`.
+    pub fn with_ring(mut self, ring: &str) -> Result<Self> {
+        self.keys.generate_ring_key(ring)?;
+        self.ring = Some(ring.to_string());
+        Ok(self)
+    }
+
+    /// Encrypts the Supervisor's gossip with the ring key of `other`.
+    pub fn join_ring(mut self, other: &TestSupervisor) -> Result<Self> {
+        if let Some(ring) = &other.ring {
+            let key = SymKey::get_latest_pair_for(ring, other.keys.path())?;
+            key.to_pair_files(self.keys.path())?;
+            self.ring = Some(ring.clone());
+        }
+        Ok(self)
+    }
+
+    /// Has the Supervisor join the gossip network of `other` when it starts.
+    pub fn with_peer(mut self, other: &TestSupervisor) -> Self {
+        self.peers.push(other.gossip_addr());
+        self
+    }
+
+    /// The directory the Supervisor sees as `/`.
+    pub fn fs_root(&self) -> &Path { self.fs_root.path() }
+
+    /// The Supervisor's state directory, where it keeps its specs and control gateway secret.
+    pub fn sup_root(&self) -> PathBuf { self.fs_root().join("hab").join("sup").join("default") }
+
+    /// The key cache the Supervisor reads its keys from.
+    pub fn keys(&self) -> &TestKeyCache { &self.keys }
+
+    pub fn gossip_addr(&self) -> SocketAddr { local_addr(self.gossip_port) }
+
+    pub fn http_addr(&self) -> SocketAddr { local_addr(self.http_port) }
+
+    pub fn ctl_addr(&self) -> SocketAddr { local_addr(self.ctl_port) }
+
+    /// The secret clients of the control gateway authenticate with.
+    pub fn ctl_secret(&self) -> &str { &self.ctl_secret }
+
+    /// Installs a package under the Supervisor's filesystem root whose `run` hook is `run_hook`,
+    /// so that it can be loaded as a service.
+    pub fn install_mock_service(&self, ident: &str, run_hook: &str) -> Result<PackageInstall> {
+        let install = testing::install_package(ident, self.fs_root())?;
+        let hooks = install.installed_path().join("hooks");
+        fs::create_dir_all(&hooks)?;
+        let run = hooks.join("run");
+        fs::write(&run, run_hook)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&run, fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(install)
+    }
+
+    /// Loads `ident` as a service with the default spec.
+    pub fn load(&self, ident: &PackageIdent) -> Result<()> {
+        self.load_spec(&ServiceSpec::new(ident.clone()))
+    }
+
+    /// Loads a service as `spec` describes it, as `hab svc load` would. A running Supervisor
+    /// picks it up within a few seconds.
+    pub fn load_spec(&self, spec: &ServiceSpec) -> Result<()> {
+        spec.to_file(self.sup_root().join("specs").join(spec.file()))
+    }
+
+    /// Starts the Supervisor and waits up to `timeout` for its HTTP gateway to listen.
+    pub fn start(&mut self, timeout: Duration) -> Result<()> {
+        let mut cmd = Command::new(binary("HAB_LAUNCH_BINARY", "hab-launch")?);
+        cmd.env("FS_ROOT", self.fs_root())
+           .env("HAB_SUP_BINARY", binary("HAB_SUP_BINARY", "hab-sup")?)
+           .env(CACHE_KEY_PATH_ENV_VAR, self.keys.path())
+           .env(BLDR_URL_ENVVAR, TEST_BLDR_URL)
+           .arg("run")
+           .arg("--listen-gossip")
+           .arg(self.gossip_addr().to_string())
+           .arg("--listen-http")
+           .arg(self.http_addr().to_string())
+           .arg("--listen-ctl")
+           .arg(self.ctl_addr().to_string())
+           .stdin(Stdio::null());
+        if let Some(ring) = &self.ring {
+            cmd.arg("--ring").arg(ring);
+        }
+        for peer in &self.peers {
+            cmd.arg("--peer").arg(peer.to_string());
+        }
+        self.process = Some(cmd.spawn()?);
+        self.wait_until_ready(timeout)
+    }
+
+    fn wait_until_ready(&mut self, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+        while started.elapsed() < timeout {
+            if TcpStream::connect(self.http_addr()).is_ok() {
+                return Ok(());
+            }
+            let exited = self.process
+                             .as_mut()
+                             .and_then(|process| process.try_wait().ok())
+                             .flatten();
+            if let Some(status) = exited {
+                self.process = None;
+                return Err(Error::Io(io::Error::new(io::ErrorKind::Other,
+                                                    format!("The Supervisor exited with {} \
+                                                             while starting",
+                                                            status))));
+            }
+            thread::sleep(READY_POLL);
+        }
+        Err(Error::Io(io::Error::new(io::ErrorKind::TimedOut,
+                                     format!("The Supervisor wasn't listening on {} after {}s",
+                                             self.http_addr(),
+                                             timeout.as_secs()))))
+    }
+
+    /// Kills the Supervisor, if it is running.
+    pub fn stop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            process.kill().ok();
+            process.wait().ok();
+        }
+    }
+}
+
+// We kill the Supervisor so you don't have to, and free up its ports for other tests.
+impl Drop for TestSupervisor {
+    fn drop(&mut self) {
+        self.stop();
+        for port in &[self.gossip_port, self.http_port, self.ctl_port] {
+            testing::release_port(*port);
+        }
+    }
+}
+
+fn local_addr(port: u16) -> SocketAddr { (Ipv4Addr::LOCALHOST, port).into() }
+
+/// The binary named by the environment variable `var`, or else `name` in the cargo target
+/// directory of the running test.
+fn binary(var: &str, name: &str) -> Result<PathBuf> {
+    if let Some(path) = env::var_os(var) {
+        return Ok(PathBuf::from(path));
+    }
+    testing::target_dir().map(|dir| dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX)))
+                         .filter(|path| path.is_file())
+                         .ok_or_else(|| {
+                             Error::FileNotFound(format!("{} (set {} to its path)", name, var))
+                         })
+}
@@ -1,5 +1,6 @@
 use crate::{error::{Error,
                     Result},
+            manager::disk_watchdog,
             PRODUCT,
             VERSION};
 use hab::{command::pkg::{self,
@@ -9,15 +10,17 @@ use hab::{command::pkg::{self,
           config,
           error::Result as HabResult};
 use habitat_api_client::BuilderAPIClient;
-use habitat_common::{command::package::install::{self as install_cmd,
-                                                 InstallHookMode,
-                                                 InstallMode,
-                                                 InstallSource,
-                                                 LocalPackageUsage},
+use habitat_common::{command::package::{install::{self as install_cmd,
+                                                  InstallHookMode,
+                                                  InstallMode,
+                                                  InstallSource,
+                                                  LocalPackageUsage},
+                                        license::LicensePolicy},
                      outputln,
                      ui::{NullUi,
                           UIWriter}};
-use habitat_core::{env as henv,
+use habitat_core::{crypto::artifact,
+                   env as henv,
                    fs::{self,
                         FS_ROOT_PATH},
                    package::{PackageIdent,
@@ -43,6 +46,11 @@ pub async fn install<T>(ui: &mut T,
                         -> Result<PackageInstall>
     where T: UIWriter
 {
+    // Downloading while the disk is nearly full would risk leaving a
+    // partially written artifact or package behind.
+    if disk_watchdog::downloads_paused() {
+        return Err(Error::DownloadsPaused);
+    }
     let fs_root_path = Path::new(&*FS_ROOT_PATH);
     let auth_token = get_auth_token();
     install_cmd::start(ui,
@@ -59,8 +67,9 @@ pub async fn install<T>(ui: &mut T,
                        // Install hooks are run when the supervisor
                        // loads the package in add_service so it is
                        // repetitive to run them here
-                       InstallHookMode::Ignore).await
-                                               .map_err(Error::from)
+                       InstallHookMode::Ignore,
+                       &LicensePolicy::default()).await
+                                                 .map_err(Error::from)
 }
 
 // `install` but with no ui output and the benefit of thread safety
@@ -126,6 +135,26 @@ pub async fn install_channel_head(url: &str,
     install_no_ui(url, &channel_latest_ident.into(), channel).await
 }
 
+/// Checks that every transitive dependency of `package` is installed, and that the cached
+/// artifacts of the package and its dependencies, where there are any, have valid signatures.
+pub fn verify_dependency_closure(package: &PackageInstall) -> Result<()> {
+    let fs_root_path = Path::new(&*FS_ROOT_PATH);
+    let artifact_path = fs::cache_artifact_path(None::<String>);
+    let key_cache_path = fs::cache_key_path(fs_root_path);
+    let mut idents = vec![package.ident().clone()];
+    idents.extend(package.tdeps()?);
+    for ident in idents {
+        if PackageInstall::load(&ident, Some(fs_root_path)).is_err() {
+            return Err(Error::PackageNotFound(ident));
+        }
+        let archive = artifact_path.join(ident.archive_name()?);
+        if archive.is_file() {
+            artifact::verify(&archive, &key_cache_path)?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn uninstall_all_but_latest(ident: impl AsRef<PackageIdent>,
                                       number_latest_to_keep: usize)
                                       -> HabResult<usize> {
@@ -51,7 +51,8 @@ impl Client {
                                                    None).unwrap(),
                                  incarnation,
                                  config,
-                                 false)
+                                 false,
+                                 None)
             .expect("Cannot send the service configuration");
     }
 